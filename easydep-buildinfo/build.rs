@@ -0,0 +1,123 @@
+/*
+ * This file is part of easydep, licensed under the MIT License (MIT).
+ *
+ * Copyright (c) 2024 easybill GmbH
+ *
+ * Permission is hereby granted, free of charge, to any person obtaining a copy
+ * of this software and associated documentation files (the "Software"), to deal
+ * in the Software without restriction, including without limitation the rights
+ * to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+ * copies of the Software, and to permit persons to whom the Software is
+ * furnished to do so, subject to the following conditions:
+ *
+ * The above copyright notice and this permission notice shall be included in all
+ * copies or substantial portions of the Software.
+ *
+ * THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+ * IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+ * FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+ * AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+ * LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+ * OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+ * SOFTWARE.
+ */
+use std::env;
+use std::path::Path;
+use std::process::Command;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+fn main() -> Result<(), Box<dyn std::error::Error>> {
+    // used to embed the git hash into the binaries, rebuilt when the checked-out commit changes
+    let git_hash = run_command("git", &["rev-parse", "--short", "HEAD"]);
+    println!(
+        "cargo:rustc-env=EASYDEP_GIT_HASH={}",
+        git_hash.as_deref().unwrap_or("unknown")
+    );
+    if let Some(git_directory) = run_command("git", &["rev-parse", "--git-dir"]) {
+        for git_path in ["HEAD", "refs", "packed-refs"] {
+            let git_path = Path::new(&git_directory).join(git_path);
+            if git_path.exists() {
+                println!("cargo:rerun-if-changed={}", git_path.display());
+            }
+        }
+    }
+
+    // the build time can be fixed using SOURCE_DATE_EPOCH for reproducible builds
+    println!("cargo:rerun-if-env-changed=SOURCE_DATE_EPOCH");
+    let build_timestamp = match env::var("SOURCE_DATE_EPOCH") {
+        Ok(source_date_epoch) => source_date_epoch.parse()?,
+        Err(_) => SystemTime::now().duration_since(UNIX_EPOCH)?.as_secs(),
+    };
+    println!(
+        "cargo:rustc-env=EASYDEP_BUILD_TIMESTAMP={}",
+        format_utc_timestamp(build_timestamp)
+    );
+
+    let rustc = env::var("RUSTC").unwrap_or_else(|_| "rustc".to_string());
+    let rustc_version = run_command(&rustc, &["--version"]);
+    println!(
+        "cargo:rustc-env=EASYDEP_RUSTC_VERSION={}",
+        rustc_version.as_deref().unwrap_or("unknown")
+    );
+
+    Ok(())
+}
+
+/// Runs the given command and returns its trimmed output, `None` if the command failed.
+fn run_command(program: &str, args: &[&str]) -> Option<String> {
+    let output = Command::new(program).args(args).output().ok()?;
+    if !output.status.success() {
+        return None;
+    }
+    let output = String::from_utf8(output.stdout).ok()?;
+    Some(output.trim().to_string())
+}
+
+/// Formats the given unix timestamp (in seconds) as ISO 8601 date and time in UTC.
+fn format_utc_timestamp(timestamp: u64) -> String {
+    let mut remaining_days = timestamp / 86400;
+    let seconds_of_day = timestamp % 86400;
+    let mut year = 1970;
+    while remaining_days >= days_in_year(year) {
+        remaining_days -= days_in_year(year);
+        year += 1;
+    }
+    let mut month = 1;
+    while remaining_days >= days_in_month(year, month) {
+        remaining_days -= days_in_month(year, month);
+        month += 1;
+    }
+    format!(
+        "{:04}-{:02}-{:02}T{:02}:{:02}:{:02}Z",
+        year,
+        month,
+        remaining_days + 1,
+        seconds_of_day / 3600,
+        seconds_of_day % 3600 / 60,
+        seconds_of_day % 60
+    )
+}
+
+/// Get the amount of days in the given year.
+fn days_in_year(year: u64) -> u64 {
+    if is_leap_year(year) {
+        366
+    } else {
+        365
+    }
+}
+
+/// Get the amount of days in the given month (1-12) of the given year.
+fn days_in_month(year: u64, month: u64) -> u64 {
+    match month {
+        2 if is_leap_year(year) => 29,
+        2 => 28,
+        4 | 6 | 9 | 11 => 30,
+        _ => 31,
+    }
+}
+
+/// Checks if the given year is a leap year.
+fn is_leap_year(year: u64) -> bool {
+    (year % 4 == 0 && year % 100 != 0) || year % 400 == 0
+}
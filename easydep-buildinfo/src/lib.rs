@@ -0,0 +1,130 @@
+/*
+ * This file is part of easydep, licensed under the MIT License (MIT).
+ *
+ * Copyright (c) 2024 easybill GmbH
+ *
+ * Permission is hereby granted, free of charge, to any person obtaining a copy
+ * of this software and associated documentation files (the "Software"), to deal
+ * in the Software without restriction, including without limitation the rights
+ * to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+ * copies of the Software, and to permit persons to whom the Software is
+ * furnished to do so, subject to the following conditions:
+ *
+ * The above copyright notice and this permission notice shall be included in all
+ * copies or substantial portions of the Software.
+ *
+ * THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+ * IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+ * FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+ * AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+ * LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+ * OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+ * SOFTWARE.
+ */
+
+//! The build information shared by the easydep binaries, embedded while building.
+
+use clap::ValueEnum;
+use serde::Serialize;
+
+/// The semantic version of easydep.
+pub const VERSION: &str = env!("CARGO_PKG_VERSION");
+/// The short hash of the git commit from which easydep was built, `unknown` if built outside a git checkout.
+pub const GIT_HASH: &str = env!("EASYDEP_GIT_HASH");
+/// The time (ISO 8601, UTC) at which the build information was generated.
+pub const BUILD_TIMESTAMP: &str = env!("EASYDEP_BUILD_TIMESTAMP");
+/// The version of the rust compiler that built easydep.
+pub const RUSTC_VERSION: &str = env!("EASYDEP_RUSTC_VERSION");
+/// The version of the grpc protocol spoken between the client and the server, incremented on incompatible changes of the
+/// protocol definitions.
+pub const PROTOCOL_VERSION: u32 = 1;
+/// The version including all build information in a single line, for example logged when a binary starts.
+pub const LONG_VERSION: &str = concat!(
+    env!("CARGO_PKG_VERSION"),
+    " (git commit ",
+    env!("EASYDEP_GIT_HASH"),
+    ", built at ",
+    env!("EASYDEP_BUILD_TIMESTAMP"),
+    " with ",
+    env!("EASYDEP_RUSTC_VERSION"),
+    ")"
+);
+
+/// Get the version of easydep including the git commit as build metadata, for example `1.2.0+abc1234`.
+pub fn version_string() -> String {
+    format!("{}+{}", VERSION, GIT_HASH)
+}
+
+/// The formats in which the build information can be printed.
+#[derive(ValueEnum, Clone, Copy, Debug, Eq, PartialEq)]
+pub enum VersionFormat {
+    /// One line per information, meant to be read by humans.
+    Human,
+    /// A json object, meant to be processed by tools.
+    Json,
+}
+
+/// The build information of one of the easydep binaries, printed by their `--version` flags.
+#[derive(Serialize, Debug)]
+pub struct BuildInfo<'a> {
+    /// The name of the binary.
+    pub binary: &'a str,
+    /// The semantic version of easydep.
+    pub version: &'a str,
+    /// The short hash of the git commit from which the binary was built.
+    pub git_hash: &'a str,
+    /// The time (ISO 8601, UTC) at which the binary was built.
+    pub build_timestamp: &'a str,
+    /// The version of the rust compiler that built the binary.
+    pub rustc_version: &'a str,
+    /// The version of the grpc protocol spoken by the binary.
+    pub protocol_version: u32,
+    /// The features supported by the binary.
+    pub features: &'a [&'a str],
+}
+
+impl<'a> BuildInfo<'a> {
+    /// Constructs the build information of the given binary.
+    ///
+    /// # Arguments
+    /// * `binary` - The name of the binary.
+    /// * `features` - The features supported by the binary.
+    pub fn new(binary: &'a str, features: &'a [&'a str]) -> Self {
+        Self {
+            binary,
+            version: VERSION,
+            git_hash: GIT_HASH,
+            build_timestamp: BUILD_TIMESTAMP,
+            rustc_version: RUSTC_VERSION,
+            protocol_version: PROTOCOL_VERSION,
+            features,
+        }
+    }
+
+    /// Formats this build information in the given format.
+    ///
+    /// # Arguments
+    /// * `format` - The format to use.
+    pub fn format(&self, format: VersionFormat) -> String {
+        match format {
+            VersionFormat::Human => {
+                let features = if self.features.is_empty() {
+                    "none".to_string()
+                } else {
+                    self.features.join(", ")
+                };
+                format!(
+                    "{} {}\ngit commit:       {}\nbuilt at:         {}\nrustc version:    {}\nprotocol version: {}\nfeatures:         {}",
+                    self.binary,
+                    self.version,
+                    self.git_hash,
+                    self.build_timestamp,
+                    self.rustc_version,
+                    self.protocol_version,
+                    features
+                )
+            }
+            VersionFormat::Json => serde_json::to_string_pretty(self).unwrap_or_default(),
+        }
+    }
+}
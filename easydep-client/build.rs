@@ -21,8 +21,6 @@
  * OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
  * SOFTWARE.
  */
-use std::process::Command;
-
 fn main() -> Result<(), Box<dyn std::error::Error>> {
     tonic_build::configure()
         .build_client(true)
@@ -32,12 +30,5 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
             &["../proto"],
         )?;
 
-    // used to embed the git hash into the crate
-    let output = Command::new("git")
-        .args(["rev-parse", "--short", "HEAD"])
-        .output()?;
-    let git_hash = String::from_utf8(output.stdout)?;
-    println!("cargo:rustc-env=GIT_HASH={}", git_hash);
-
     Ok(())
 }
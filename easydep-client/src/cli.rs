@@ -22,9 +22,14 @@
  * SOFTWARE.
  */
 
-use clap::{Parser, Subcommand};
+use clap::{ArgMatches, Args, Parser, Subcommand, ValueEnum};
+use easydep_buildinfo::VersionFormat;
 use std::path::PathBuf;
 
+use crate::easydep::{DeployAnnotation, ScriptPhase};
+use crate::util::ansi_output::AnsiMode;
+use crate::util::calendar_date::parse_calendar_date;
+
 /// The CLI interface of easyde
 #[derive(Parser, Debug, Clone)]
 #[command(disable_version_flag = true)]
@@ -33,8 +38,47 @@ pub(crate) struct Cli {
     #[command(subcommand)]
     pub command: RootCommands,
     /// The path where the client configuration file is located.
-    #[arg(short = 'c', long = "config-path", env = "EASYDEP_CONFIG_PATH")]
+    #[arg(
+        short = 'c',
+        long = "config-path",
+        env = "EASYDEP_CONFIG_PATH",
+        required = false,
+        required_unless_present = "version_format"
+    )]
     pub configuration_path: PathBuf,
+    /// Prints the version, build and protocol information of the client in the given format (`human` if omitted) and
+    /// exits, no configuration or command is required.
+    #[arg(
+        short = 'V',
+        long = "version",
+        value_enum,
+        num_args = 0..=1,
+        require_equals = true,
+        default_missing_value = "human"
+    )]
+    pub version_format: Option<VersionFormat>,
+    /// How ANSI escape sequences (for example colors) in the output are handled. In auto mode they are only kept if
+    /// the output is attached to a terminal, so that files and syslog receive readable output.
+    #[arg(
+        long = "ansi",
+        env = "EASYDEP_ANSI",
+        value_enum,
+        default_value_t = AnsiMode::Auto,
+        global = true
+    )]
+    pub ansi_mode: AnsiMode,
+    /// The file into which the streams received from the servers are recorded, for example to share the exact output
+    /// of a failed deployment. The recording can be replayed using `deploy replay`.
+    #[arg(long = "record", global = true)]
+    pub record_path: Option<PathBuf>,
+    /// Collapses the log lines that multiple servers print identically at the same position of their output into a
+    /// single line naming the amount of servers that printed it, keeping diverging lines visible.
+    #[arg(
+        long = "aggregate-output",
+        env = "EASYDEP_AGGREGATE_OUTPUT",
+        global = true
+    )]
+    pub aggregate_output: bool,
 }
 
 /// Holds the collection of top-level commands.
@@ -55,6 +99,13 @@ pub(crate) enum RootCommands {
         #[command(subcommand)]
         action: DeployCommands,
     },
+    /// Obtains a new access token for the servers from the configured OpenID Connect provider.
+    Login,
+    /// Manages the remote servers.
+    Server {
+        #[command(subcommand)]
+        action: ServerCommands,
+    },
 }
 
 /// The subcommand to manage the client configuration file.
@@ -70,12 +121,89 @@ pub(crate) enum ConfigCommands {
         server_host: String,
         /// The tags to add for the server, these can be used to easily deploy to a group of servers later.
         server_tags: Vec<String>,
+        /// The static api token to authenticate at the server with, if the server is configured with api tokens.
+        #[arg(long = "token")]
+        token: Option<String>,
     },
     /// Removes a server from the configuration.
     Remove {
         /// The id of the server to remove from the configuration.
         server_id: String,
     },
+    /// Encrypts the configuration file with a passphrase, which is read from the `EASYDEP_CONFIG_PASSPHRASE`
+    /// environment variable or prompted for. Also changes the passphrase of an encrypted configuration file.
+    Encrypt,
+    /// Decrypts the configuration file, storing it in plain text again.
+    Decrypt,
+}
+
+/// The subcommand to manage the remote servers.
+#[derive(Subcommand, Debug, Clone)]
+pub(crate) enum ServerCommands {
+    /// Inspects and installs the configuration of the remote servers. Requires the admin role.
+    Config {
+        #[command(subcommand)]
+        action: ServerConfigCommands,
+    },
+    /// Reports which easydep version is running on the given server(s), flagging outdated servers.
+    Versions {
+        /// The server(s) to get the running version of. If empty all servers will be reported.
+        server_ids: Vec<String>,
+    },
+    /// Upgrades the given server(s) to another easydep release, after which they restart. Requires the admin role.
+    Upgrade {
+        /// The tag of the easydep release to install. If not given the latest release is installed.
+        #[arg(long = "release")]
+        release_tag: Option<String>,
+        /// The server(s) to upgrade. If empty all servers will be upgraded.
+        server_ids: Vec<String>,
+    },
+    /// Checks that the given server(s) can authenticate as GitHub app and access the repositories of their profiles,
+    /// reporting the remaining rate limit of the app installations. Requires the admin role.
+    GithubCheck {
+        /// The server(s) to check. If empty all servers will be checked.
+        server_ids: Vec<String>,
+    },
+    /// Executes a command that is configured for the given profile in the directory of the published release on the
+    /// given server(s), for example to clear caches. Only commands configured on the servers can be executed.
+    Exec {
+        /// The profile whose published release the command should be executed in.
+        profile: String,
+        /// The name of the configured command to execute.
+        command_name: String,
+        /// The server(s) to execute the command on. If empty it will be executed on all servers.
+        server_ids: Vec<String>,
+    },
+}
+
+/// The subcommand to inspect and install the configuration of the remote servers.
+#[derive(Subcommand, Debug, Clone)]
+pub(crate) enum ServerConfigCommands {
+    /// Displays the effective configuration of the given server, with credentials being redacted.
+    Show {
+        /// The id of the server to display the configuration of.
+        server_id: String,
+    },
+    /// Compares the effective configuration of the given servers, displaying the values that differ.
+    Diff {
+        /// The id of the first server to compare the configuration of.
+        server_id: String,
+        /// The id of the second server to compare the configuration of.
+        other_server_id: String,
+    },
+    /// Validates and installs the given configuration file on the given server(s), which restart to apply it.
+    Push {
+        /// The path to the configuration file to install.
+        configuration_file: PathBuf,
+        /// The server(s) to install the configuration on. If empty it will be installed on all servers.
+        server_ids: Vec<String>,
+    },
+    /// Reloads the configuration file of the given server(s) without restarting them. The reload is rejected if the
+    /// configuration is invalid or changes settings that can only be applied by a restart.
+    Reload {
+        /// The server(s) to reload the configuration of. If empty it will be reloaded on all servers.
+        server_ids: Vec<String>,
+    },
 }
 
 /// The subcommand to manage deployments on one or multiple servers.
@@ -96,6 +224,48 @@ pub(crate) enum DeployCommands {
         release_id: u64,
         /// The server(s) to execute the deployment on. If empty it will be deployed on all servers.
         server_ids: Vec<String>,
+        /// Only plans the deployment on the servers: the steps the servers would execute, the differences between
+        /// their plans and the servers that are missing prerequisites are reported, without executing the deployment.
+        #[arg(long = "dry-run")]
+        dry_run: bool,
+        #[command(flatten)]
+        start_options: StartArgs,
+        #[command(flatten)]
+        annotation: AnnotationArgs,
+    },
+    /// Deploys the given release using the given profile: the deployment is prepared on all server(s) in parallel and
+    /// published on one server after another afterward, for example for stateful services.
+    Run {
+        /// The profile to use to execute the deployment.
+        profile: String,
+        /// The id of the release that should be deployed.
+        release_id: u64,
+        /// The server(s) to execute the deployment on. If empty it will be deployed on all servers.
+        server_ids: Vec<String>,
+        /// The time (in seconds) to wait after publishing on a server before checking that it still reports the
+        /// release as published. The next server is only published if the check succeeded.
+        #[arg(long = "health-wait")]
+        health_wait_seconds: Option<u64>,
+        #[command(flatten)]
+        start_options: StartArgs,
+        #[command(flatten)]
+        annotation: AnnotationArgs,
+    },
+    /// Starts the deployment process for the given git ref (tag, branch or commit SHA) instead of a release.
+    StartRef {
+        /// The profile to use to execute the deployment, must allow deployments from git refs.
+        profile: String,
+        /// The git ref that should be deployed.
+        git_ref: String,
+        /// The server(s) to execute the deployment on. If empty it will be deployed on all servers.
+        server_ids: Vec<String>,
+        /// Only reports the steps the servers would execute for the deployment, without executing it.
+        #[arg(long = "dry-run")]
+        dry_run: bool,
+        #[command(flatten)]
+        start_options: StartArgs,
+        #[command(flatten)]
+        annotation: AnnotationArgs,
     },
     /// Publishes a previously started deployment.
     Publish {
@@ -103,6 +273,22 @@ pub(crate) enum DeployCommands {
         release_id: u64,
         /// The server(s) to publish the deployment on. If empty it will be published on all servers.
         server_ids: Vec<String>,
+        /// The token issued by a server when approving the deployment, required if the profile requires an approval.
+        /// Can be given multiple times to pass the tokens of all servers.
+        #[arg(long = "approval-token")]
+        approval_tokens: Vec<String>,
+        #[command(flatten)]
+        annotation: AnnotationArgs,
+    },
+    /// Approves a prepared deployment whose profile requires an approval, displaying the token per server that must be
+    /// passed when publishing it. Requires the admin role.
+    Approve {
+        /// The id of the release whose deployment should be approved.
+        release_id: u64,
+        /// The server(s) to approve the deployment on. If empty it will be approved on all servers.
+        server_ids: Vec<String>,
+        #[command(flatten)]
+        annotation: AnnotationArgs,
     },
     /// Deletes a started but not yet published deployment from the given server(s).
     Delete {
@@ -111,11 +297,309 @@ pub(crate) enum DeployCommands {
         /// The server(s) to delete the deployment on. If empty it will be deleted on all servers.
         server_ids: Vec<String>,
     },
+    /// Cancels a deployment that is still being prepared on the given server(s), terminating its running scripts.
+    Cancel {
+        /// The id of the release to cancel the deployment of.
+        release_id: u64,
+        /// The server(s) to cancel the deployment on. If empty it will be cancelled on all servers.
+        server_ids: Vec<String>,
+    },
+    /// Lists the deployments of the given profile that wait in the action queue of the given server(s) until the
+    /// running action completed.
+    #[command(args_conflicts_with_subcommands = true, subcommand_negates_reqs = true)]
+    Queue {
+        #[command(subcommand)]
+        action: Option<QueueCommands>,
+        /// The profile to list the queued deployments of.
+        #[arg(required = true)]
+        profile: Option<String>,
+        /// The server(s) to list the queued deployments of. If empty the queues of all servers will be listed.
+        server_ids: Vec<String>,
+    },
     /// Rolls back to the previous deployment of the given profile on the given target server(s).
     Rollback {
         /// The profile to roll the deployment back of.
         profile: String,
         /// The server(s) to roll back the deployment on. If empty it will be rolled back on all servers.
         server_ids: Vec<String>,
+        #[command(flatten)]
+        annotation: AnnotationArgs,
+    },
+    /// Executes the scripts of a phase again in the directory of the release that is published with the given profile,
+    /// for example when a publish script failed for a transient reason.
+    RerunScripts {
+        /// The profile whose published release the scripts should be executed for.
+        profile: String,
+        /// The phase of which the scripts should be executed.
+        #[arg(long = "phase", value_enum, default_value_t = ScriptPhaseArg::Publish)]
+        phase: ScriptPhaseArg,
+        /// The server(s) to execute the scripts on. If empty they will be executed on all servers.
+        server_ids: Vec<String>,
+        #[command(flatten)]
+        annotation: AnnotationArgs,
+    },
+    /// Replays the streams of the servers stored in a recording made using `--record` into the console.
+    Replay {
+        /// The path of the file containing the recording.
+        recording_path: PathBuf,
+        /// Replay the entries with the delays in which they were received.
+        #[arg(long = "realtime")]
+        realtime: bool,
+    },
+    /// Compares the release deployed with the given profile across the given server(s), reporting divergences.
+    Audit {
+        /// The profile to compare the deployed releases of.
+        profile: String,
+        /// The server(s) to compare the deployed releases on. If empty all servers will be compared.
+        server_ids: Vec<String>,
+    },
+    /// Marks a release as bad for the given profile, preventing it from being deployed again.
+    Blacklist {
+        /// The profile in which the release should be marked as bad.
+        profile: String,
+        /// The id of the release that should be marked as bad.
+        release_id: u64,
+        /// The reason why the release is marked as bad, shown to everyone trying to deploy it.
+        #[arg(short = 'r', long = "reason")]
+        reason: String,
+        /// The server(s) to mark the release as bad on. If empty it will be marked on all servers.
+        server_ids: Vec<String>,
+    },
+    /// Lists the releases of the given profile that are stored on the given server(s), newest first.
+    Releases {
+        /// The profile to list the stored releases of.
+        profile: String,
+        #[command(flatten)]
+        list_options: ReleaseListArgs,
+        /// The server(s) to list the stored releases of. If empty the releases of all servers will be listed.
+        server_ids: Vec<String>,
+    },
+    /// Removes the oldest releases of the given profile that are stored on the given server(s). The published release
+    /// and the releases deployed in a slot are never removed.
+    Prune {
+        /// The profile to remove the stored releases of.
+        profile: String,
+        /// The maximum amount of releases that should remain stored.
+        #[arg(long = "keep", required_unless_present = "max_disk_usage")]
+        keep_releases: Option<u32>,
+        /// The maximum disk usage of all stored releases, in bytes or with a `K`, `M`, `G` or `T` suffix.
+        #[arg(long = "max-disk-usage", value_parser = parse_byte_size)]
+        max_disk_usage: Option<u64>,
+        /// The server(s) to remove the stored releases on. If empty they will be removed on all servers.
+        server_ids: Vec<String>,
+    },
+    /// Prints the history of the actions executed for the given profile on the given server(s), newest first.
+    #[command(args_conflicts_with_subcommands = true, subcommand_negates_reqs = true)]
+    History {
+        #[command(subcommand)]
+        action: Option<HistoryCommands>,
+        /// The profile to print the history of.
+        #[arg(required = true)]
+        profile: Option<String>,
+        /// The maximum amount of entries to print per server. Defaults to 50, at most 500 entries are printed.
+        #[arg(long = "page-size")]
+        page_size: Option<u32>,
+        /// The token of the page to print, as displayed after the previous page of a server.
+        #[arg(long = "page-token")]
+        page_token: Option<String>,
+        /// The server(s) to print the history of. If empty the history of all servers will be printed.
+        server_ids: Vec<String>,
+    },
+    /// Adopts an existing directory on the given server(s) as a release of the given profile, for example a directory
+    /// that was deployed manually before migrating to easydep. Requires the admin role.
+    Adopt {
+        /// The profile to adopt the release into.
+        profile: String,
+        /// The id of the release that is stored in the directory.
+        release_id: u64,
+        /// The absolute path of the directory to adopt on the servers, it is moved into the releases directory.
+        source_directory: String,
+        #[command(flatten)]
+        adopt_options: AdoptArgs,
+        /// The server(s) to adopt the release on. If empty it will be adopted on all servers.
+        server_ids: Vec<String>,
+    },
+}
+
+/// The arguments to annotate a deployment action with the reason why it is executed.
+#[derive(Args, Debug, Clone)]
+pub(crate) struct AnnotationArgs {
+    /// A free-text reason why the action is executed.
+    #[arg(short = 'm', long = "message")]
+    pub message: Option<String>,
+    /// A reference to the ticket that is associated with the action.
+    #[arg(long = "ticket", requires = "message")]
+    pub ticket: Option<String>,
+}
+
+impl AnnotationArgs {
+    /// Converts the given annotation arguments into the annotation that is sent to the server. `None` is returned if
+    /// no message was provided.
+    pub fn into_annotation(self) -> Option<DeployAnnotation> {
+        self.message.map(|message| DeployAnnotation {
+            message,
+            ticket_reference: self.ticket,
+        })
+    }
+}
+
+/// The subcommand to manage the deployments that wait in the action queue of a profile.
+#[derive(Subcommand, Debug, Clone)]
+pub(crate) enum QueueCommands {
+    /// Cancels the deployments of the given release that wait in the action queue of the given profile on the given
+    /// server(s), before they started executing. The clients waiting for the deployments are notified.
+    Cancel {
+        /// The profile in whose action queue the deployments wait.
+        profile: String,
+        /// The id of the release whose queued deployments should be cancelled.
+        release_id: u64,
+        /// The server(s) to cancel the queued deployments on. If empty they will be cancelled on all servers.
+        server_ids: Vec<String>,
+    },
+}
+
+/// The subcommand to process the history of the actions executed for a profile.
+#[derive(Subcommand, Debug, Clone)]
+pub(crate) enum HistoryCommands {
+    /// Exports the history of the actions executed for the given profile on the given server(s) as report to stdout,
+    /// for example for compliance reporting of production changes. All recorded actions are exported, newest first.
+    Export {
+        /// The profile to export the history of.
+        profile: String,
+        /// The format of the exported report.
+        #[arg(long = "format", value_enum, default_value_t = HistoryExportFormat::Csv)]
+        format: HistoryExportFormat,
+        /// Only export actions started on or after the given date (`YYYY-MM-DD`, UTC).
+        #[arg(long = "since", value_parser = parse_calendar_date)]
+        started_after: Option<u64>,
+        /// Only export actions started before the given date (`YYYY-MM-DD`, UTC).
+        #[arg(long = "until", value_parser = parse_calendar_date)]
+        started_before: Option<u64>,
+        /// The server(s) to export the history of. If empty the history of all servers will be exported.
+        server_ids: Vec<String>,
     },
 }
+
+/// The formats in which the history of a profile can be exported.
+#[derive(ValueEnum, Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum HistoryExportFormat {
+    /// Comma-separated values with a header line, one action per line.
+    Csv,
+    /// A json array containing one object per action.
+    Json,
+}
+
+/// The script phases that can be executed again using the `rerun-scripts` command.
+#[derive(ValueEnum, Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum ScriptPhaseArg {
+    /// The scripts executed when publishing a deployment.
+    Publish,
+    /// The scripts executed when initializing a deployment.
+    Init,
+}
+
+impl ScriptPhaseArg {
+    /// Converts this phase into the script phase that is sent to the server.
+    pub fn into_script_phase(self) -> ScriptPhase {
+        match self {
+            Self::Publish => ScriptPhase::Publish,
+            Self::Init => ScriptPhase::Init,
+        }
+    }
+}
+
+/// The arguments that control how a deployment is started.
+#[derive(Args, Debug, Clone)]
+pub(crate) struct StartArgs {
+    /// Start the deployment even if the profile reached its limit of deployments per hour. Requires the admin role.
+    #[arg(long = "override-rate-limit")]
+    pub override_rate_limit: bool,
+    /// Metadata to store with the deployment as `<key>=<value>`, for example the url of the CI run. Can be given
+    /// multiple times.
+    #[arg(long = "metadata", value_parser = parse_metadata_entry)]
+    pub metadata: Vec<(String, String)>,
+    /// The SHA (or a prefix of at least 7 characters) of the commit that must be checked out for the deployment.
+    #[arg(long = "expected-commit")]
+    pub expected_commit_sha: Option<String>,
+}
+
+/// The arguments that control which stored releases are listed.
+#[derive(Args, Debug, Clone)]
+pub(crate) struct ReleaseListArgs {
+    /// The maximum amount of releases to list per server. Defaults to 50, at most 500 releases are listed.
+    #[arg(long = "page-size")]
+    pub page_size: Option<u32>,
+    /// The token of the page to list, as displayed after the previous page.
+    #[arg(long = "page-token")]
+    pub page_token: Option<String>,
+    /// Only list releases stored on or after the given date (`YYYY-MM-DD`, UTC).
+    #[arg(long = "since", value_parser = parse_calendar_date)]
+    pub stored_after: Option<u64>,
+    /// Only list releases stored before the given date (`YYYY-MM-DD`, UTC).
+    #[arg(long = "until", value_parser = parse_calendar_date)]
+    pub stored_before: Option<u64>,
+    /// Only list releases that are (`true`) or are not (`false`) marked as bad.
+    #[arg(long = "marked-bad")]
+    pub marked_bad: Option<bool>,
+}
+
+/// The arguments that control how an existing directory is adopted as a release.
+#[derive(Args, Debug, Clone)]
+pub(crate) struct AdoptArgs {
+    /// The tag name of the release. If not given it is resolved from GitHub by the servers.
+    #[arg(long = "tag")]
+    pub tag_name: Option<String>,
+    /// Metadata to store with the adopted release as `<key>=<value>`. Can be given multiple times.
+    #[arg(long = "metadata", value_parser = parse_metadata_entry)]
+    pub metadata: Vec<(String, String)>,
+    /// Publish the adopted release as the current release of the profile.
+    #[arg(long = "publish")]
+    pub publish: bool,
+}
+
+/// Parses a metadata entry given as `<key>=<value>` into the key and the value.
+///
+/// # Arguments
+/// * `entry` - The metadata entry to parse.
+fn parse_metadata_entry(entry: &str) -> Result<(String, String), String> {
+    match entry.split_once('=') {
+        Some((key, value)) if !key.is_empty() => Ok((key.to_string(), value.to_string())),
+        _ => Err(format!(
+            "invalid metadata entry {entry:?}, expected <key>=<value>"
+        )),
+    }
+}
+
+/// Parses a byte size given as plain amount of bytes or with a binary unit suffix (`K`, `M`, `G` or `T`), for
+/// example `512M`.
+///
+/// # Arguments
+/// * `size` - The byte size to parse.
+fn parse_byte_size(size: &str) -> Result<u64, String> {
+    let (amount, multiplier) = match size.char_indices().last() {
+        Some((index, 'K' | 'k')) => (&size[..index], 1u64 << 10),
+        Some((index, 'M' | 'm')) => (&size[..index], 1u64 << 20),
+        Some((index, 'G' | 'g')) => (&size[..index], 1u64 << 30),
+        Some((index, 'T' | 't')) => (&size[..index], 1u64 << 40),
+        _ => (size, 1),
+    };
+    amount
+        .parse::<u64>()
+        .ok()
+        .and_then(|amount| amount.checked_mul(multiplier))
+        .ok_or_else(|| format!("invalid byte size {size:?}, expected for example 1073741824 or 1G"))
+}
+
+/// Resolves the name of the executed command from the given matches, for example `deploy start`.
+///
+/// # Arguments
+/// * `matches` - The matches of the parsed command line arguments.
+pub(crate) fn resolve_command_name(matches: &ArgMatches) -> String {
+    let mut command_names = Vec::<&str>::new();
+    let mut current_matches = matches;
+    while let Some((subcommand_name, subcommand_matches)) = current_matches.subcommand() {
+        command_names.push(subcommand_name);
+        current_matches = subcommand_matches;
+    }
+    command_names.join(" ")
+}
@@ -25,18 +25,38 @@
 use std::collections::HashSet;
 use std::hash::{Hash, Hasher};
 use std::path::Path;
+use std::str;
 
 use anyhow::{bail, Context};
 use serde::{Deserialize, Serialize};
 use tokio::fs;
+use tokio::fs::OpenOptions;
+use tokio::io::AsyncWriteExt;
 
+use crate::util::config_encryption::{
+    decrypt_configuration, encrypt_configuration, is_encrypted_configuration,
+    ConfigurationPassphrase,
+};
 use crate::util::input_validator::validate_grpc_endpoint_uri;
 
 /// The root configuration file model.
 #[derive(Serialize, Deserialize, Default, Clone, Debug)]
 pub(crate) struct Configuration {
+    /// The name of the operator using this client, sent to the servers to identify who triggered an action.
+    pub operator: Option<String>,
+    /// The OpenID Connect settings used to obtain a token for the servers, if the servers require authentication.
+    pub oidc: Option<OidcSettings>,
+    /// The path of the file to which the timings of deployment actions are appended, as csv if the file has a `.csv`
+    /// extension and as json lines otherwise. If not given, no report is written.
+    pub telemetry_report: Option<String>,
+    /// The plugins that are invoked before and after the executed commands.
+    #[serde(default)]
+    pub plugins: Vec<PluginConfiguration>,
     /// The servers that can be used for deployments.
     pub servers: Vec<TargetServer>,
+    /// The passphrase with which the configuration file is encrypted, `None` if it is stored in plain text.
+    #[serde(skip)]
+    pub passphrase: Option<ConfigurationPassphrase>,
 }
 
 /// A target server that can execute deployments.
@@ -48,27 +68,101 @@ pub(crate) struct TargetServer {
     pub address: String,
     /// The additional tags of the server, can be used to group them.
     pub tags: Vec<String>,
+    /// The static api token to authenticate at the server with. If given it is sent instead of the access token
+    /// obtained from the OpenID Connect provider.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub token: Option<String>,
+}
+
+/// An external executable that is invoked before and after commands, for example to verify that a change ticket exists
+/// before a deployment is started. The executable receives the details of the command as json on stdin and can reject
+/// the command by exiting with a non-zero status or by printing `{"allow": false, "message": "<reason>"}` to stdout.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub(crate) struct PluginConfiguration {
+    /// The name of the plugin, used in the log output.
+    pub name: String,
+    /// The path of the executable to invoke.
+    pub executable: String,
+    /// The arguments to pass to the executable.
+    #[serde(default)]
+    pub args: Vec<String>,
+    /// The commands for which the plugin is invoked, for example `deploy start`. A command also matches all of its
+    /// subcommands (`deploy` matches `deploy start`). If empty, the plugin is invoked for all commands.
+    #[serde(default)]
+    pub commands: Vec<String>,
+    /// The time (in seconds) after which the invocation of the plugin is aborted and considered as failed.
+    #[serde(default = "default_plugin_timeout_seconds")]
+    pub timeout_seconds: u64,
+}
+
+/// The settings to obtain access tokens from an OpenID Connect provider using the device authorization flow.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub(crate) struct OidcSettings {
+    /// The url of the token issuer.
+    pub issuer: String,
+    /// The id of this client registered at the token issuer.
+    pub client_id: String,
+    /// The scopes to request when obtaining a token.
+    #[serde(default = "default_oidc_scopes")]
+    pub scopes: Vec<String>,
+    /// The audience to request the token for, required by some providers.
+    pub audience: Option<String>,
+    /// The access token that was obtained during the last login.
+    pub access_token: Option<String>,
+    /// The unix timestamp (in seconds) when the access token expires.
+    pub access_token_expires_at: Option<u64>,
 }
 
 impl Configuration {
     /// Loads the configuration from the given file path, returning an error if the file reading or toml parsing fails.
+    /// If the file is encrypted, the passphrase to decrypt it is read from the environment or prompted for.
     ///
     /// # Arguments
     /// * `file_path` - The path to load the configuration from.
     pub async fn load_from_file(file_path: impl AsRef<Path>) -> anyhow::Result<Self> {
-        let toml_file_content = fs::read_to_string(file_path).await?;
-        let parsed_configuration: Configuration = toml::from_str(&toml_file_content)?;
-        Ok(parsed_configuration)
+        let file_content = fs::read(file_path).await?;
+        if is_encrypted_configuration(&file_content) {
+            let passphrase = ConfigurationPassphrase::read(false)?;
+            let toml_file_content = decrypt_configuration(&file_content, &passphrase)?;
+            let mut parsed_configuration: Configuration =
+                toml::from_str(str::from_utf8(&toml_file_content)?)?;
+            parsed_configuration.passphrase = Some(passphrase);
+            Ok(parsed_configuration)
+        } else {
+            let parsed_configuration: Configuration =
+                toml::from_str(str::from_utf8(&file_content)?)?;
+            Ok(parsed_configuration)
+        }
     }
 
-    /// Saves the current configuration state into the file at the given path.
+    /// Saves the current configuration state into the file at the given path, encrypted with the passphrase of the
+    /// configuration if it has one. On unix systems the file is only readable by the owner, as it contains the access
+    /// token of the user and the api tokens of the servers. The permissions of an existing file are restricted before
+    /// the configuration is written into it.
     ///
     /// # Arguments
     /// * `file_path` - The path where the configuration should be stored.
     pub async fn save_to_file(&self, file_path: impl AsRef<Path>) -> anyhow::Result<()> {
         let serialized =
             toml::to_string_pretty(&self).context("unable to serialize config to toml")?;
-        fs::write(file_path, serialized).await?;
+        let file_content = match &self.passphrase {
+            Some(passphrase) => encrypt_configuration(serialized.as_bytes(), passphrase)?,
+            None => serialized.into_bytes(),
+        };
+        let mut open_options = OpenOptions::new();
+        open_options.write(true).create(true).truncate(true);
+        #[cfg(unix)]
+        open_options.mode(0o600);
+        let mut config_file = open_options.open(file_path).await?;
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            config_file
+                .set_permissions(std::fs::Permissions::from_mode(0o600))
+                .await?;
+        }
+        config_file.write_all(&file_content).await?;
+        config_file.flush().await?;
         Ok(())
     }
 
@@ -89,6 +183,17 @@ impl Configuration {
             }
         }
 
+        // validate that all plugin names are unique, they are used to identify the plugins in the log output
+        let mut known_plugin_names = HashSet::<&String>::new();
+        for plugin in &self.plugins {
+            if !known_plugin_names.insert(&plugin.name) {
+                bail!("detected duplicate plugin name: {}", plugin.name)
+            }
+            if plugin.executable.is_empty() {
+                bail!("plugin {} has no executable configured", plugin.name)
+            }
+        }
+
         Ok(())
     }
 
@@ -112,6 +217,30 @@ impl Configuration {
     }
 }
 
+impl TargetServer {
+    /// Get the position of this server in the publish order, configured using an `order:<n>` tag. Servers with a lower
+    /// order are published before servers with a higher order.
+    ///
+    /// # Returns
+    /// * `Option<u32>` - The publish order of the server, `None` if the server has no order tag.
+    pub fn get_publish_order(&self) -> anyhow::Result<Option<u32>> {
+        let mut order_tags = self
+            .tags
+            .iter()
+            .filter_map(|tag| tag.strip_prefix("order:"));
+        let Some(order_tag) = order_tags.next() else {
+            return Ok(None);
+        };
+        if order_tags.next().is_some() {
+            bail!("server {} has multiple order tags", self.id)
+        }
+        let publish_order = order_tag
+            .parse::<u32>()
+            .with_context(|| format!("invalid order tag of server {}: {}", self.id, order_tag))?;
+        Ok(Some(publish_order))
+    }
+}
+
 /// An implementation for partial eq for the `TargetServer` type which only checks if the id of the server is the same.
 impl PartialEq<Self> for TargetServer {
     fn eq(&self, other: &Self) -> bool {
@@ -125,3 +254,13 @@ impl Hash for TargetServer {
         self.id.hash(hasher)
     }
 }
+
+/// The default scopes that are requested when obtaining a token.
+fn default_oidc_scopes() -> Vec<String> {
+    vec!["openid".to_string()]
+}
+
+/// The default time (in seconds) after which the invocation of a plugin is aborted.
+fn default_plugin_timeout_seconds() -> u64 {
+    30
+}
@@ -29,6 +29,7 @@ use anyhow::bail;
 use log::info;
 
 use crate::config::{Configuration, TargetServer};
+use crate::util::config_encryption::ConfigurationPassphrase;
 use crate::util::input_validator::validate_grpc_endpoint_uri;
 
 /// Prints the servers that are registered in the client configuration into the console.
@@ -59,12 +60,14 @@ pub(crate) fn display_configured_servers(configuration: Configuration) {
 /// * `server_id` - The given id of the server to register.
 /// * `server_address` - The gRPC endpoint address of the server to register.
 /// * `tags` - The tags of the server to register.
+/// * `token` - The static api token to authenticate at the server with.
 pub(crate) async fn add_server_to_config(
     mut configuration: Configuration,
     config_path: PathBuf,
     server_id: String,
     server_address: String,
     tags: Vec<String>,
+    token: Option<String>,
 ) -> anyhow::Result<()> {
     // check if the id is already taken
     let server_id = server_id.trim().to_string();
@@ -91,6 +94,7 @@ pub(crate) async fn add_server_to_config(
         id: server_id,
         address: server_address,
         tags: Vec::from_iter(tags),
+        token: token.filter(|token| !token.trim().is_empty()),
     };
     configuration.servers.push(new_server);
     configuration.save_to_file(config_path).await?;
@@ -127,3 +131,38 @@ pub(crate) async fn remove_server_from_config(
 
     Ok(())
 }
+
+/// Encrypts the configuration file with a new passphrase, which also replaces the passphrase of an already encrypted
+/// configuration file.
+///
+/// # Arguments
+/// * `configuration` - The current client configuration.
+/// * `config_path` - The path from where the configuration is loaded.
+pub(crate) async fn encrypt_config(
+    mut configuration: Configuration,
+    config_path: PathBuf,
+) -> anyhow::Result<()> {
+    configuration.passphrase = Some(ConfigurationPassphrase::read(true)?);
+    configuration.save_to_file(config_path).await?;
+    info!("Successfully encrypted configuration");
+
+    Ok(())
+}
+
+/// Decrypts the configuration file, returning an error if the configuration file is not encrypted.
+///
+/// # Arguments
+/// * `configuration` - The current client configuration.
+/// * `config_path` - The path from where the configuration is loaded.
+pub(crate) async fn decrypt_config(
+    mut configuration: Configuration,
+    config_path: PathBuf,
+) -> anyhow::Result<()> {
+    if configuration.passphrase.take().is_none() {
+        bail!("the configuration is not encrypted")
+    }
+    configuration.save_to_file(config_path).await?;
+    info!("Successfully decrypted configuration");
+
+    Ok(())
+}
@@ -22,21 +22,47 @@
  * SOFTWARE.
  */
 
-use anyhow::{anyhow, bail};
-use futures::StreamExt;
-use log::{error, info, warn};
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::path::Path;
+use std::pin::pin;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use anyhow::{anyhow, bail, Context};
+use futures::future::join_all;
+use futures::future::BoxFuture;
+use futures::{stream, FutureExt, Stream, StreamExt};
+use log::{error, info, warn, Level};
 use prost::UnknownEnumValue;
-use tonic::transport::Channel;
-use tonic::Streaming;
+use serde::Serialize;
+use tonic::service::interceptor::InterceptedService;
+use tonic::transport::{Channel, Endpoint};
+use tonic::{Code, Status, Streaming};
 
+use crate::cli::{AdoptArgs, HistoryExportFormat, ReleaseListArgs, StartArgs};
 use crate::config::{Configuration, TargetServer};
 use crate::easydep::deployment_service_client::DeploymentServiceClient;
 use crate::easydep::{
-    Action, ActionStatus, DeployDeleteRequest, DeployPublishRequest, DeployRollbackRequest,
-    DeployStartRequest, DeployStatusRequest, ExecutedActionEntry, LogType,
+    Action, ActionStatus, AdoptReleaseRequest, DeployAnnotation, DeployApproveRequest,
+    DeployCancelQueuedRequest, DeployCancelRequest, DeployDeleteRequest, DeployPlanRequest,
+    DeployPlanResponse, DeployPublishRequest, DeployRerunScriptsRequest, DeployRollbackRequest,
+    DeployStartRequest, DeployStatusRequest, DeploymentHistoryEntry, DeploymentHistoryRequest,
+    DeploymentHistoryResponse, ExecCommandRequest, ExecutedActionEntry, ListReleasesRequest,
+    ListReleasesResponse, LogType, MarkReleaseBadRequest, PruneReleasesRequest, RollbackCandidate,
+    ScriptPhase,
 };
+use crate::executor::status_commands::ensure_servers_support;
+use crate::util::ansi_output::prepare_remote_output_line;
+use crate::util::calendar_date::format_unix_timestamp;
+use crate::util::deployment_telemetry::{FleetTelemetry, ServerTiming};
+use crate::util::metadata_interceptor::MetadataInterceptor;
+use crate::util::output_aggregator::OutputAggregator;
 use crate::util::server_connector::execute_for_servers;
-use crate::util::server_selector::select_target_servers;
+use crate::util::server_selector::{group_servers_by_publish_order, select_target_servers};
+use crate::util::stream_recorder::{read_recorded_stream_entries, record_stream_entry};
+
+/// The client type for the deployment gRPC service, attaching the identity of the invoking user to all requests.
+type DeploymentClient = DeploymentServiceClient<InterceptedService<Channel, MetadataInterceptor>>;
 
 /// Displays the deployment status of the given release profile on the requested servers.
 ///
@@ -52,7 +78,7 @@ pub(crate) async fn display_servers_deployment_status(
     let target_servers = select_target_servers(&configuration, &server_ids)?;
     execute_for_servers(
         target_servers,
-        open_deployment_client_connection,
+        open_deployment_client_connection(&configuration),
         move |server, mut client| {
             let profile = profile.clone();
             async move {
@@ -71,6 +97,17 @@ pub(crate) async fn display_servers_deployment_status(
                     "[{}] --| Release Created From : {}",
                     server.id, response_message.target_commit
                 );
+                info!(
+                    "[{}] --| Rollback             : {}",
+                    server.id,
+                    format_rollback_availability(&response_message.rollback_candidates)
+                );
+                for (key, value) in response_message.metadata.iter().collect::<BTreeMap<_, _>>() {
+                    info!(
+                        "[{}] --| Metadata             : {} = {}",
+                        server.id, key, value
+                    );
+                }
                 Ok(())
             }
         },
@@ -79,6 +116,229 @@ pub(crate) async fn display_servers_deployment_status(
     Ok(())
 }
 
+/// Compares the release that is deployed with the given profile across the given target servers. Servers on which the
+/// deployed release differs from the release deployed on the majority of servers are reported, as well as servers that
+/// could not report their deployed release. An error is returned if the servers are inconsistent.
+///
+/// # Arguments
+/// * `configuration` - The client configuration.
+/// * `profile` - The profile to compare the deployed releases of.
+/// * `server_ids` - The ids of the servers to compare the deployed releases on.
+pub(crate) async fn audit_deployment_on_servers(
+    configuration: Configuration,
+    profile: String,
+    server_ids: Vec<String>,
+) -> anyhow::Result<()> {
+    let target_servers = select_target_servers(&configuration, &server_ids)?;
+    let deployed_releases = Arc::new(Mutex::new(BTreeMap::<String, (u64, String)>::new()));
+    let execution_result = execute_for_servers(
+        target_servers,
+        open_deployment_client_connection(&configuration),
+        {
+            let deployed_releases = deployed_releases.clone();
+            move |server, mut client| {
+                let profile = profile.clone();
+                let deployed_releases = deployed_releases.clone();
+                async move {
+                    let request = DeployStatusRequest { profile };
+                    let response = client.get_deployment_status(request).await?.into_inner();
+                    if let Ok(mut deployed_releases) = deployed_releases.lock() {
+                        deployed_releases
+                            .insert(server.id, (response.release_id, response.tag_name));
+                    }
+                    Ok(())
+                }
+            }
+        },
+    )
+    .await;
+
+    // group the servers by their deployed release, the release deployed on most servers is expected everywhere
+    let deployed_releases = deployed_releases
+        .lock()
+        .map_err(|_| anyhow!("unable to read the deployed releases"))?
+        .clone();
+    let mut release_servers = BTreeMap::<u64, Vec<&String>>::new();
+    for (server_id, (release_id, _)) in &deployed_releases {
+        release_servers
+            .entry(*release_id)
+            .or_default()
+            .push(server_id);
+    }
+    let expected_release_id = release_servers
+        .iter()
+        .max_by_key(|(_, server_ids)| server_ids.len())
+        .map(|(release_id, _)| *release_id);
+    for (server_id, (release_id, tag_name)) in &deployed_releases {
+        let audit_result = if Some(*release_id) == expected_release_id {
+            "consistent"
+        } else {
+            "DIVERGED"
+        };
+        info!(
+            "[{}] --| Deployed Release     : {} (id: {}) - {}",
+            server_id, tag_name, release_id, audit_result
+        );
+    }
+
+    // servers that could not report their release are not necessarily consistent either
+    let execution_result = execution_result.context("unable to audit all servers");
+    if release_servers.len() > 1 {
+        if let Err(err) = execution_result {
+            error!("{err:?}");
+        }
+        bail!(
+            "the servers have {} different releases deployed",
+            release_servers.len()
+        )
+    }
+    execution_result?;
+    info!("All servers have the same release deployed");
+    Ok(())
+}
+
+/// Plans the deployment of the given release with the given profile on the given target servers without executing it.
+/// The steps that each server would execute are streamed first, followed by the steps of the plan that differ between
+/// the servers and the servers that are missing prerequisites of the deployment. An error is returned if a server
+/// cannot execute the deployment.
+///
+/// # Arguments
+/// * `configuration` - The client configuration.
+/// * `profile` - The name of the profile to plan the deployment with.
+/// * `release_id` - The id of the release to plan the deployment of.
+/// * `server_ids` - The ids of the servers to plan the deployment on.
+pub(crate) async fn plan_deployment_on_servers(
+    configuration: Configuration,
+    profile: String,
+    release_id: u64,
+    server_ids: Vec<String>,
+) -> anyhow::Result<()> {
+    // servers that do not know dry runs would ignore the flag and execute the deployment
+    ensure_servers_support(&configuration, &server_ids, "dry-run").await?;
+    ensure_servers_support(&configuration, &server_ids, "plan").await?;
+
+    // stream the steps that the servers would execute, a server that cannot plan them is reported at the end
+    let dry_run_request = DeployStartRequest {
+        profile: profile.clone(),
+        release_id,
+        annotation: None,
+        r#ref: None,
+        override_rate_limit: false,
+        metadata: HashMap::new(),
+        expected_commit_sha: None,
+        dry_run: true,
+    };
+    let dry_run_result =
+        start_deployment_with_request(configuration.clone(), server_ids.clone(), dry_run_request)
+            .await;
+
+    let target_servers = select_target_servers(&configuration, &server_ids)?;
+    let deployment_plans = Arc::new(Mutex::new(BTreeMap::<String, DeployPlanResponse>::new()));
+    let execution_result = execute_for_servers(
+        target_servers,
+        open_deployment_client_connection(&configuration),
+        {
+            let deployment_plans = deployment_plans.clone();
+            move |server, mut client| {
+                let profile = profile.clone();
+                let deployment_plans = deployment_plans.clone();
+                async move {
+                    let request = DeployPlanRequest {
+                        profile,
+                        release_id,
+                    };
+                    let response = client.plan_deployment(request).await?.into_inner();
+                    if let Ok(mut deployment_plans) = deployment_plans.lock() {
+                        deployment_plans.insert(server.id, response);
+                    }
+                    Ok(())
+                }
+            }
+        },
+    )
+    .await;
+
+    // collect the values of each planned step per server, steps that are missing on a server have no value
+    let deployment_plans = deployment_plans
+        .lock()
+        .map_err(|_| anyhow!("unable to read the deployment plans"))?
+        .clone();
+    let mut planned_steps = BTreeMap::<&String, BTreeMap<&String, &String>>::new();
+    for (server_id, deployment_plan) in &deployment_plans {
+        for entry in &deployment_plan.entries {
+            planned_steps
+                .entry(&entry.name)
+                .or_default()
+                .insert(server_id, &entry.value);
+        }
+    }
+    for (step_name, server_values) in &planned_steps {
+        let distinct_values = server_values.values().collect::<HashSet<_>>();
+        if distinct_values.len() == 1 && server_values.len() == deployment_plans.len() {
+            info!(
+                "--| Planned {:<20} : {}",
+                step_name,
+                server_values.values().next().unwrap()
+            );
+            continue;
+        }
+        warn!(
+            "--| Planned {:<20} : differs between the servers",
+            step_name
+        );
+        for server_id in deployment_plans.keys() {
+            let server_value = server_values
+                .get(server_id)
+                .map_or("<none>", |value| value.as_str());
+            warn!("[{}] --| {:<28} : {}", server_id, step_name, server_value);
+        }
+    }
+
+    // report the servers that are missing prerequisites of the deployment
+    let mut servers_missing_prerequisites = 0;
+    for (server_id, deployment_plan) in &deployment_plans {
+        let failed_checks = deployment_plan
+            .checks
+            .iter()
+            .filter(|check| !check.passed)
+            .collect::<Vec<_>>();
+        if failed_checks.is_empty() {
+            info!("[{}] --| Prerequisites        : satisfied", server_id);
+            continue;
+        }
+        servers_missing_prerequisites += 1;
+        for failed_check in failed_checks {
+            if failed_check.detail.is_empty() {
+                error!(
+                    "[{}] --| Failed Check         : {}",
+                    server_id, failed_check.name
+                );
+            } else {
+                error!(
+                    "[{}] --| Failed Check         : {} ({})",
+                    server_id, failed_check.name, failed_check.detail
+                );
+            }
+        }
+    }
+
+    // servers that could not plan the deployment cannot execute it either
+    let execution_result = execution_result.context("unable to plan the deployment on all servers");
+    if servers_missing_prerequisites > 0 {
+        if let Err(err) = execution_result {
+            error!("{err:?}");
+        }
+        bail!(
+            "{} server(s) are missing prerequisites of the deployment",
+            servers_missing_prerequisites
+        )
+    }
+    execution_result?;
+    dry_run_result.context("unable to plan the deployment steps on all servers")?;
+    info!("All servers satisfy the prerequisites of the deployment");
+    Ok(())
+}
+
 /// Starts the deployment process for the given release with the given profile on the given target servers. This method
 /// returns an error result if one of the execution fails, and consolidates multiple errors into a single one.
 ///
@@ -87,25 +347,800 @@ pub(crate) async fn display_servers_deployment_status(
 /// * `profile` - The name of the profile to use for the deployment.
 /// * `release_id` - The id of the release to deploy.
 /// * `server_ids` - The ids of the servers to start the deployment process on.
+/// * `start_options` - The options that control how the deployment is started.
+/// * `annotation` - The reason why the deployment is started, if any.
 pub(crate) async fn start_deployment_on_servers(
     configuration: Configuration,
     profile: String,
     release_id: u64,
     server_ids: Vec<String>,
+    start_options: StartArgs,
+    annotation: Option<DeployAnnotation>,
+) -> anyhow::Result<()> {
+    let request = DeployStartRequest {
+        profile,
+        release_id,
+        annotation,
+        r#ref: None,
+        override_rate_limit: start_options.override_rate_limit,
+        metadata: start_options.metadata.into_iter().collect(),
+        expected_commit_sha: start_options.expected_commit_sha,
+        dry_run: false,
+    };
+    start_deployment_with_request(configuration, server_ids, request).await
+}
+
+/// Starts the deployment process for the given git ref (tag, branch or commit SHA) instead of a release with the given
+/// profile on the given target servers. The servers report the id assigned to the deployment in their output, which
+/// must be used to publish or delete the deployment afterward.
+///
+/// # Arguments
+/// * `configuration` - The client configuration.
+/// * `profile` - The name of the profile to use for the deployment.
+/// * `git_ref` - The git ref to deploy.
+/// * `server_ids` - The ids of the servers to start the deployment process on.
+/// * `dry_run` - If the servers should only report the steps they would execute, without executing the deployment.
+/// * `start_options` - The options that control how the deployment is started.
+/// * `annotation` - The reason why the deployment is started, if any.
+pub(crate) async fn start_ref_deployment_on_servers(
+    configuration: Configuration,
+    profile: String,
+    git_ref: String,
+    server_ids: Vec<String>,
+    dry_run: bool,
+    start_options: StartArgs,
+    annotation: Option<DeployAnnotation>,
+) -> anyhow::Result<()> {
+    // servers that do not know ref deployments would ignore the ref and deploy the release with id 0
+    ensure_servers_support(&configuration, &server_ids, "ref-deploy").await?;
+    if dry_run {
+        // servers that do not know dry runs would ignore the flag and execute the deployment
+        ensure_servers_support(&configuration, &server_ids, "dry-run").await?;
+    }
+    let request = DeployStartRequest {
+        profile,
+        release_id: 0,
+        annotation,
+        r#ref: Some(git_ref),
+        override_rate_limit: start_options.override_rate_limit,
+        metadata: start_options.metadata.into_iter().collect(),
+        expected_commit_sha: start_options.expected_commit_sha,
+        dry_run,
+    };
+    start_deployment_with_request(configuration, server_ids, request).await
+}
+
+/// Deploys the given release with the given profile on the given target servers: the deployment is prepared on all
+/// servers in parallel and published on one server after another afterward, in the publish order of the servers
+/// (configured using `order:<n>` tags) and the order of the server ids. Nothing is
+/// published if the preparation failed on one server, the remaining servers are not published if publishing failed on
+/// one server.
+///
+/// # Arguments
+/// * `configuration` - The client configuration.
+/// * `profile` - The name of the profile to use for the deployment.
+/// * `release_id` - The id of the release to deploy.
+/// * `server_ids` - The ids of the servers to deploy the release on.
+/// * `health_wait` - The time to wait after publishing on a server before checking that it still reports the release
+///   as published, if the servers should be checked.
+/// * `start_options` - The options that control how the deployment is started.
+/// * `annotation` - The reason why the deployment is executed, if any.
+pub(crate) async fn run_deployment_on_servers(
+    configuration: Configuration,
+    profile: String,
+    release_id: u64,
+    server_ids: Vec<String>,
+    health_wait: Option<Duration>,
+    start_options: StartArgs,
+    annotation: Option<DeployAnnotation>,
+) -> anyhow::Result<()> {
+    let mut target_servers = select_target_servers(&configuration, &server_ids)?
+        .into_iter()
+        .map(|server| Ok((server.get_publish_order()?.unwrap_or(u32::MAX), &server.id)))
+        .collect::<anyhow::Result<Vec<(u32, &String)>>>()?;
+    target_servers.sort();
+    let target_server_ids = target_servers
+        .into_iter()
+        .map(|(_, server_id)| server_id.clone())
+        .collect::<Vec<String>>();
+
+    // prepare the deployment on all servers in parallel
+    info!(
+        "--| Preparing release {} on {} server(s)",
+        release_id,
+        target_server_ids.len()
+    );
+    start_deployment_on_servers(
+        configuration.clone(),
+        profile.clone(),
+        release_id,
+        target_server_ids.clone(),
+        start_options,
+        annotation.clone(),
+    )
+    .await
+    .context(
+        "deployment could not be prepared on all servers, it was not published on any server",
+    )?;
+
+    // publish the deployment on one server after another, waiting for each server to stay healthy if requested
+    for (server_index, server_id) in target_server_ids.iter().enumerate() {
+        info!(
+            "[{}] --| Publishing release {} ({}/{})",
+            server_id,
+            release_id,
+            server_index + 1,
+            target_server_ids.len()
+        );
+        let unpublished_server_ids = target_server_ids[server_index + 1..].join(", ");
+        publish_deployment_on_servers(
+            configuration.clone(),
+            release_id,
+            vec![server_id.clone()],
+            Vec::new(),
+            annotation.clone(),
+        )
+        .await
+        .with_context(|| {
+            format!(
+                "publishing failed on {}, the deployment is still prepared on: {}",
+                server_id, unpublished_server_ids
+            )
+        })?;
+        if let Some(health_wait) = health_wait {
+            await_published_release(&configuration, &profile, release_id, server_id, health_wait)
+                .await
+                .with_context(|| {
+                    format!(
+                        "{} did not stay healthy, the deployment is still prepared on: {}",
+                        server_id, unpublished_server_ids
+                    )
+                })?;
+        }
+    }
+    Ok(())
+}
+
+/// Waits for the given time and checks that the given server still reports the given release as published with the
+/// given profile afterward, for example to detect a failing service before the next server is published.
+///
+/// # Arguments
+/// * `configuration` - The client configuration.
+/// * `profile` - The profile with which the release was published.
+/// * `release_id` - The id of the release that was published.
+/// * `server_id` - The id of the server on which the release was published.
+/// * `health_wait` - The time to wait before checking the server.
+async fn await_published_release(
+    configuration: &Configuration,
+    profile: &str,
+    release_id: u64,
+    server_id: &str,
+    health_wait: Duration,
+) -> anyhow::Result<()> {
+    info!(
+        "[{}] --| Waiting {}s before checking the published release",
+        server_id,
+        health_wait.as_secs()
+    );
+    tokio::time::sleep(health_wait).await;
+    let target_servers = select_target_servers(configuration, &vec![server_id.to_string()])?;
+    let profile = profile.to_string();
+    execute_for_servers(
+        target_servers,
+        open_deployment_client_connection(configuration),
+        move |server, mut client| {
+            let profile = profile.clone();
+            async move {
+                let request = DeployStatusRequest { profile };
+                let response = client.get_deployment_status(request).await?;
+                let published_release_id = response.get_ref().release_id;
+                if published_release_id != release_id {
+                    bail!(
+                        "server reports release {} as published instead of {}",
+                        published_release_id,
+                        release_id
+                    )
+                }
+                info!(
+                    "[{}] --| Release {} is still published",
+                    server.id, release_id
+                );
+                Ok(())
+            }
+        },
+    )
+    .await
+}
+
+/// Sends the given deployment start request to the given target servers, streaming the output of the servers.
+///
+/// # Arguments
+/// * `configuration` - The client configuration.
+/// * `server_ids` - The ids of the servers to start the deployment process on.
+/// * `request` - The request to send to the servers.
+async fn start_deployment_with_request(
+    configuration: Configuration,
+    server_ids: Vec<String>,
+    request: DeployStartRequest,
+) -> anyhow::Result<()> {
+    let dry_run = request.dry_run;
+    let target_servers = select_target_servers(&configuration, &server_ids)?;
+    let fleet_telemetry = FleetTelemetry::default();
+    let output_aggregator = OutputAggregator::for_servers(target_servers.len());
+    let execution_result = execute_for_servers(
+        target_servers,
+        open_deployment_client_connection(&configuration),
+        {
+            let fleet_telemetry = fleet_telemetry.clone();
+            let output_aggregator = output_aggregator.clone();
+            move |server, mut client| {
+                let request = request.clone();
+                let fleet_telemetry = fleet_telemetry.clone();
+                let output_aggregator = output_aggregator.clone();
+                async move {
+                    let response_stream = client.start_deployment(request).await?.into_inner();
+                    stream_executed_actions(
+                        server,
+                        response_stream,
+                        &fleet_telemetry,
+                        &output_aggregator,
+                    )
+                    .await
+                }
+            }
+        },
+    )
+    .await;
+    output_aggregator.finish();
+    if !dry_run {
+        // dry runs execute nothing, their timings are meaningless
+        fleet_telemetry.finish(&configuration, "start").await;
+    }
+    execution_result
+}
+
+/// Publishes a previously started deployment on the requested servers. If the servers have a publish order (configured
+/// using `order:<n>` tags) the servers with the same order are published in parallel, one group after another. The
+/// next group is only published if publishing succeeded on all servers of the previous group.
+///
+/// # Arguments
+/// * `configuration` - The client configuration.
+/// * `release_id` - The id of the release that should get published.
+/// * `server_ids` - The ids of the servers to publish the deployment on.
+/// * `approval_tokens` - The tokens issued by the servers when approving the deployment, if it was approved.
+/// * `annotation` - The reason why the deployment is published, if any.
+pub(crate) async fn publish_deployment_on_servers(
+    configuration: Configuration,
+    release_id: u64,
+    server_ids: Vec<String>,
+    approval_tokens: Vec<String>,
+    annotation: Option<DeployAnnotation>,
+) -> anyhow::Result<()> {
+    let target_servers = select_target_servers(&configuration, &server_ids)?;
+    let publish_groups = group_servers_by_publish_order(target_servers)?;
+    let fleet_telemetry = FleetTelemetry::default();
+    let mut execution_result = Ok(());
+    for (group_index, publish_group) in publish_groups.iter().enumerate() {
+        if publish_groups.len() > 1 {
+            let mut group_server_ids = publish_group
+                .iter()
+                .map(|server| server.id.as_str())
+                .collect::<Vec<&str>>();
+            group_server_ids.sort();
+            info!(
+                "--| Publishing group {}/{}: {}",
+                group_index + 1,
+                publish_groups.len(),
+                group_server_ids.join(", ")
+            );
+        }
+
+        // the next group is only published if publishing succeeded on all servers of the group
+        execution_result = publish_deployment_on_target_servers(
+            &configuration,
+            release_id,
+            publish_group.clone(),
+            &approval_tokens,
+            &annotation,
+            &fleet_telemetry,
+        )
+        .await;
+        if execution_result.is_err() {
+            let mut unpublished_server_ids = publish_groups[group_index + 1..]
+                .iter()
+                .flatten()
+                .map(|server| server.id.as_str())
+                .collect::<Vec<&str>>();
+            if !unpublished_server_ids.is_empty() {
+                unpublished_server_ids.sort();
+                warn!(
+                    "--| Not publishing on the servers of the next groups: {}",
+                    unpublished_server_ids.join(", ")
+                );
+            }
+            break;
+        }
+    }
+    fleet_telemetry.finish(&configuration, "publish").await;
+    execution_result
+}
+
+/// Sends a publish request for the given release to the given target servers in parallel, streaming the output of the
+/// servers.
+///
+/// # Arguments
+/// * `configuration` - The client configuration.
+/// * `release_id` - The id of the release to publish.
+/// * `target_servers` - The servers to publish the release on.
+/// * `approval_tokens` - The tokens issued by the servers when approving the deployment, if it was approved.
+/// * `annotation` - The reason why the deployment is published, if any.
+/// * `fleet_telemetry` - The telemetry to record the timings of the servers into.
+async fn publish_deployment_on_target_servers(
+    configuration: &Configuration,
+    release_id: u64,
+    target_servers: HashSet<&TargetServer>,
+    approval_tokens: &[String],
+    annotation: &Option<DeployAnnotation>,
+    fleet_telemetry: &FleetTelemetry,
+) -> anyhow::Result<()> {
+    let output_aggregator = OutputAggregator::for_servers(target_servers.len());
+    let execution_result = execute_for_servers(
+        target_servers,
+        open_deployment_client_connection(configuration),
+        {
+            let approval_tokens = approval_tokens.to_vec();
+            let annotation = annotation.clone();
+            let fleet_telemetry = fleet_telemetry.clone();
+            let output_aggregator = output_aggregator.clone();
+            move |server, mut client| {
+                let approval_tokens = approval_tokens.clone();
+                let annotation = annotation.clone();
+                let fleet_telemetry = fleet_telemetry.clone();
+                let output_aggregator = output_aggregator.clone();
+                async move {
+                    let request = DeployPublishRequest {
+                        release_id,
+                        annotation,
+                        approval_tokens,
+                    };
+                    let response_stream = client.publish_deployment(request).await?.into_inner();
+                    stream_executed_actions(
+                        server,
+                        response_stream,
+                        &fleet_telemetry,
+                        &output_aggregator,
+                    )
+                    .await
+                }
+            }
+        },
+    )
+    .await;
+    output_aggregator.finish();
+    execution_result
+}
+
+/// Requests to roll back to the previous deployment of the given profile on the given target servers.
+///
+/// # Arguments
+/// * `configuration` - The client configuration.
+/// * `profile` - The release profile of which the rollback to the previous release should happen.
+/// * `server_ids` - The ids of the servers to roll back to the previous deployment on.
+/// * `annotation` - The reason why the deployment is rolled back, if any.
+pub(crate) async fn rollback_deployment_on_servers(
+    configuration: Configuration,
+    profile: String,
+    server_ids: Vec<String>,
+    annotation: Option<DeployAnnotation>,
+) -> anyhow::Result<()> {
+    let target_servers = select_target_servers(&configuration, &server_ids)?;
+    let fleet_telemetry = FleetTelemetry::default();
+    let output_aggregator = OutputAggregator::for_servers(target_servers.len());
+    let execution_result = execute_for_servers(
+        target_servers,
+        open_deployment_client_connection(&configuration),
+        {
+            let fleet_telemetry = fleet_telemetry.clone();
+            let output_aggregator = output_aggregator.clone();
+            move |server, mut client| {
+                let profile = profile.clone();
+                let annotation = annotation.clone();
+                let fleet_telemetry = fleet_telemetry.clone();
+                let output_aggregator = output_aggregator.clone();
+                async move {
+                    // fail fast if the server has no release to roll back to
+                    let status_request = DeployStatusRequest {
+                        profile: profile.clone(),
+                    };
+                    let status_response = client.get_deployment_status(status_request).await?;
+                    match status_response.get_ref().rollback_candidates.first() {
+                        Some(candidate) if candidate.marked_bad => bail!(
+                            "previous release {} was marked as bad, cannot roll back",
+                            format_rollback_candidate(candidate)
+                        ),
+                        Some(_) => {}
+                        None => bail!("no previous release is available to roll back to"),
+                    }
+
+                    let request = DeployRollbackRequest {
+                        profile,
+                        annotation,
+                    };
+                    let response_stream = client.rollback_deployment(request).await?.into_inner();
+                    stream_executed_actions(
+                        server,
+                        response_stream,
+                        &fleet_telemetry,
+                        &output_aggregator,
+                    )
+                    .await
+                }
+            }
+        },
+    )
+    .await;
+    output_aggregator.finish();
+    fleet_telemetry.finish(&configuration, "rollback").await;
+    execution_result
+}
+
+/// Requests to execute the scripts of the given phase again for the release that is published with the given profile
+/// on the given target servers.
+///
+/// # Arguments
+/// * `configuration` - The client configuration.
+/// * `profile` - The release profile whose published release the scripts should be executed for.
+/// * `phase` - The phase of which the scripts should be executed.
+/// * `server_ids` - The ids of the servers to execute the scripts on.
+/// * `annotation` - The reason why the scripts are executed again, if any.
+pub(crate) async fn rerun_scripts_on_servers(
+    configuration: Configuration,
+    profile: String,
+    phase: ScriptPhase,
+    server_ids: Vec<String>,
+    annotation: Option<DeployAnnotation>,
+) -> anyhow::Result<()> {
+    let target_servers = select_target_servers(&configuration, &server_ids)?;
+    let fleet_telemetry = FleetTelemetry::default();
+    let output_aggregator = OutputAggregator::for_servers(target_servers.len());
+    let execution_result = execute_for_servers(
+        target_servers,
+        open_deployment_client_connection(&configuration),
+        {
+            let fleet_telemetry = fleet_telemetry.clone();
+            let output_aggregator = output_aggregator.clone();
+            move |server, mut client| {
+                let profile = profile.clone();
+                let annotation = annotation.clone();
+                let fleet_telemetry = fleet_telemetry.clone();
+                let output_aggregator = output_aggregator.clone();
+                async move {
+                    let request = DeployRerunScriptsRequest {
+                        profile,
+                        phase: i32::from(phase),
+                        annotation,
+                    };
+                    let response_stream = client.rerun_scripts(request).await?.into_inner();
+                    stream_executed_actions(
+                        server,
+                        response_stream,
+                        &fleet_telemetry,
+                        &output_aggregator,
+                    )
+                    .await
+                }
+            }
+        },
+    )
+    .await;
+    output_aggregator.finish();
+    fleet_telemetry
+        .finish(&configuration, "rerun-scripts")
+        .await;
+    execution_result
+}
+
+/// Requests to execute the configured command with the given name in the directory of the release published with the
+/// given profile on the given target servers.
+///
+/// # Arguments
+/// * `configuration` - The client configuration.
+/// * `profile` - The release profile whose published release the command should be executed in.
+/// * `command_name` - The name of the configured command to execute.
+/// * `server_ids` - The ids of the servers to execute the command on.
+pub(crate) async fn exec_command_on_servers(
+    configuration: Configuration,
+    profile: String,
+    command_name: String,
+    server_ids: Vec<String>,
+) -> anyhow::Result<()> {
+    ensure_servers_support(&configuration, &server_ids, "exec").await?;
+    let target_servers = select_target_servers(&configuration, &server_ids)?;
+    let fleet_telemetry = FleetTelemetry::default();
+    let output_aggregator = OutputAggregator::for_servers(target_servers.len());
+    let execution_result = execute_for_servers(
+        target_servers,
+        open_deployment_client_connection(&configuration),
+        {
+            let fleet_telemetry = fleet_telemetry.clone();
+            let output_aggregator = output_aggregator.clone();
+            move |server, mut client| {
+                let profile = profile.clone();
+                let command_name = command_name.clone();
+                let fleet_telemetry = fleet_telemetry.clone();
+                let output_aggregator = output_aggregator.clone();
+                async move {
+                    let request = ExecCommandRequest {
+                        profile,
+                        command_name,
+                    };
+                    let response_stream = client.exec_command(request).await?.into_inner();
+                    stream_executed_actions(
+                        server,
+                        response_stream,
+                        &fleet_telemetry,
+                        &output_aggregator,
+                    )
+                    .await
+                }
+            }
+        },
+    )
+    .await;
+    output_aggregator.finish();
+    fleet_telemetry.finish(&configuration, "exec").await;
+    execution_result
+}
+
+/// Requests to remove the oldest releases of the given profile that are stored on the given target servers, down to
+/// the given amount of releases or disk usage. The removed releases are streamed into the console.
+///
+/// # Arguments
+/// * `configuration` - The client configuration.
+/// * `profile` - The release profile whose stored releases should be removed.
+/// * `keep_releases` - The maximum amount of releases that should remain stored, if given.
+/// * `max_disk_usage_bytes` - The maximum disk usage (in bytes) of all stored releases, if given.
+/// * `server_ids` - The ids of the servers to remove the stored releases on.
+pub(crate) async fn prune_releases_on_servers(
+    configuration: Configuration,
+    profile: String,
+    keep_releases: Option<u32>,
+    max_disk_usage_bytes: Option<u64>,
+    server_ids: Vec<String>,
+) -> anyhow::Result<()> {
+    ensure_servers_support(&configuration, &server_ids, "prune").await?;
+    let target_servers = select_target_servers(&configuration, &server_ids)?;
+    let request = PruneReleasesRequest {
+        profile,
+        keep_releases,
+        max_disk_usage_bytes,
+    };
+    let fleet_telemetry = FleetTelemetry::default();
+    let output_aggregator = OutputAggregator::for_servers(target_servers.len());
+    let execution_result = execute_for_servers(
+        target_servers,
+        open_deployment_client_connection(&configuration),
+        {
+            let fleet_telemetry = fleet_telemetry.clone();
+            let output_aggregator = output_aggregator.clone();
+            move |server, mut client| {
+                let request = request.clone();
+                let fleet_telemetry = fleet_telemetry.clone();
+                let output_aggregator = output_aggregator.clone();
+                async move {
+                    let response_stream = client.prune_releases(request).await?.into_inner();
+                    stream_executed_actions(
+                        server,
+                        response_stream,
+                        &fleet_telemetry,
+                        &output_aggregator,
+                    )
+                    .await
+                }
+            }
+        },
+    )
+    .await;
+    output_aggregator.finish();
+    fleet_telemetry.finish(&configuration, "prune").await;
+    execution_result
+}
+
+/// Replays the streams of the servers stored in the given recording into the console, as if the recorded command was
+/// executed again.
+///
+/// # Arguments
+/// * `recording_path` - The path of the file containing the recording.
+/// * `realtime` - If the entries should be replayed with the delays in which they were received.
+pub(crate) async fn replay_recorded_streams(
+    recording_path: &Path,
+    realtime: bool,
+) -> anyhow::Result<()> {
+    let mut server_entries = BTreeMap::<String, Vec<_>>::new();
+    for recorded_entry in read_recorded_stream_entries(recording_path).await? {
+        server_entries
+            .entry(recorded_entry.server_id.clone())
+            .or_default()
+            .push(recorded_entry);
+    }
+    info!(
+        "Replaying recorded streams of {} server(s)...",
+        server_entries.len()
+    );
+
+    let replay_started_at = Instant::now();
+    let output_aggregator = OutputAggregator::for_servers(server_entries.len());
+    let output_aggregator = &output_aggregator;
+    let replays = server_entries
+        .into_iter()
+        .map(|(server_id, recorded_entries)| async move {
+            let recorded_stream =
+                stream::iter(recorded_entries).then(|recorded_entry| async move {
+                    if realtime {
+                        let replay_elapsed = replay_started_at.elapsed();
+                        if let Some(delay) =
+                            recorded_entry.get_elapsed().checked_sub(replay_elapsed)
+                        {
+                            tokio::time::sleep(delay).await;
+                        }
+                    }
+                    recorded_entry.into_stream_entry()
+                });
+            let mut phase_durations = BTreeMap::<String, Duration>::new();
+            let replay_result = stream_executed_action_entries(
+                &server_id,
+                recorded_stream,
+                &mut phase_durations,
+                output_aggregator,
+            )
+            .await;
+            output_aggregator.finish_server(&server_id);
+            (server_id, replay_result)
+        });
+
+    let replay_results = join_all(replays).await;
+    output_aggregator.finish();
+    let mut failed_server_ids = Vec::<String>::new();
+    for (server_id, replay_result) in replay_results {
+        if let Err(err) = replay_result {
+            error!("[{}] Recorded execution failed: {:#}", server_id, err);
+            failed_server_ids.push(server_id);
+        }
+    }
+    if failed_server_ids.is_empty() {
+        Ok(())
+    } else {
+        Err(anyhow!(
+            "Recorded execution failed on: {}",
+            failed_server_ids.join(", ")
+        ))
+    }
+}
+
+/// Deletes a deployment that wasn't published before on the given target servers.
+///
+/// # Arguments
+/// * `configuration` - The client configuration.
+/// * `release_id` - The id of the release that should be deleted.
+/// * `server_ids` - The ids of the servers on which the deployment should be deleted.
+pub(crate) async fn delete_unpublished_deployment_on_servers(
+    configuration: Configuration,
+    release_id: u64,
+    server_ids: Vec<String>,
+) -> anyhow::Result<()> {
+    let target_servers = select_target_servers(&configuration, &server_ids)?;
+    let fleet_telemetry = FleetTelemetry::default();
+    let output_aggregator = OutputAggregator::for_servers(target_servers.len());
+    let execution_result = execute_for_servers(
+        target_servers,
+        open_deployment_client_connection(&configuration),
+        {
+            let fleet_telemetry = fleet_telemetry.clone();
+            let output_aggregator = output_aggregator.clone();
+            move |server, mut client| {
+                let fleet_telemetry = fleet_telemetry.clone();
+                let output_aggregator = output_aggregator.clone();
+                async move {
+                    let request = DeployDeleteRequest { release_id };
+                    let response_stream = client
+                        .delete_unpublished_deployment(request)
+                        .await?
+                        .into_inner();
+                    stream_executed_actions(
+                        server,
+                        response_stream,
+                        &fleet_telemetry,
+                        &output_aggregator,
+                    )
+                    .await
+                }
+            }
+        },
+    )
+    .await;
+    output_aggregator.finish();
+    fleet_telemetry.finish(&configuration, "delete").await;
+    execution_result
+}
+
+/// Cancels the deployment of the given release that is being prepared on the given target servers. The running
+/// processes of the deployment are terminated, the cancel scripts are executed and the deployment directory is removed.
+///
+/// # Arguments
+/// * `configuration` - The client configuration.
+/// * `release_id` - The id of the release whose deployment should be cancelled.
+/// * `server_ids` - The ids of the servers on which the deployment should be cancelled.
+pub(crate) async fn cancel_deployment_on_servers(
+    configuration: Configuration,
+    release_id: u64,
+    server_ids: Vec<String>,
+) -> anyhow::Result<()> {
+    ensure_servers_support(&configuration, &server_ids, "cancel").await?;
+    let target_servers = select_target_servers(&configuration, &server_ids)?;
+    execute_for_servers(
+        target_servers,
+        open_deployment_client_connection(&configuration),
+        move |server, mut client| async move {
+            let request = DeployCancelRequest { release_id };
+            let response = client.cancel_deployment(request).await?;
+            if response.get_ref().completed {
+                info!(
+                    "[{}] --| Cancelled deployment of release {}",
+                    server.id, release_id
+                );
+            } else {
+                warn!(
+                    "[{}] --| Cancelled deployment of release {}, the cleanup is still running",
+                    server.id, release_id
+                );
+            }
+            Ok(())
+        },
+    )
+    .await?;
+    Ok(())
+}
+
+/// Cancels the deployments of the given release that wait in the action queue of the given profile on the given target
+/// servers. Servers on which no deployment of the release is queued are skipped.
+///
+/// # Arguments
+/// * `configuration` - The client configuration.
+/// * `profile` - The profile in whose action queue the deployments wait.
+/// * `release_id` - The id of the release whose queued deployments should be cancelled.
+/// * `server_ids` - The ids of the servers on which the queued deployments should be cancelled.
+pub(crate) async fn cancel_queued_deployment_on_servers(
+    configuration: Configuration,
+    profile: String,
+    release_id: u64,
+    server_ids: Vec<String>,
 ) -> anyhow::Result<()> {
+    ensure_servers_support(&configuration, &server_ids, "queue").await?;
     let target_servers = select_target_servers(&configuration, &server_ids)?;
     execute_for_servers(
         target_servers,
-        open_deployment_client_connection,
+        open_deployment_client_connection(&configuration),
         move |server, mut client| {
             let profile = profile.clone();
             async move {
-                let request = DeployStartRequest {
-                    profile,
+                let request = DeployCancelQueuedRequest {
+                    profile: profile.clone(),
                     release_id,
                 };
-                let response_stream = client.start_deployment(request).await?.into_inner();
-                stream_executed_actions(server, response_stream).await
+                match client.cancel_queued_deployment(request).await {
+                    Ok(response) => info!(
+                        "[{}] --| Cancelled {} queued deployment(s) of release {}",
+                        server.id,
+                        response.get_ref().cancelled_deployments,
+                        release_id
+                    ),
+                    Err(status) if status.code() == Code::NotFound => info!(
+                        "[{}] --| No deployment of release {} is queued for profile {}",
+                        server.id, release_id, profile
+                    ),
+                    Err(status) => return Err(status.into()),
+                }
+                Ok(())
             }
         },
     )
@@ -113,52 +1148,82 @@ pub(crate) async fn start_deployment_on_servers(
     Ok(())
 }
 
-/// Publishes a previously started deployment on the requested servers.
+/// Approves the prepared deployment of the given release on the given target servers, displaying the token issued by
+/// each server. The tokens must be passed when publishing the deployment if its profile requires an approval.
 ///
 /// # Arguments
 /// * `configuration` - The client configuration.
-/// * `release_id` - The id of the release that should get published.
-/// * `server_ids` - The ids of the servers to publish the deployment on.
-pub(crate) async fn publish_deployment_on_servers(
+/// * `release_id` - The id of the release whose deployment should be approved.
+/// * `server_ids` - The ids of the servers on which the deployment should be approved.
+/// * `annotation` - The reason why the deployment is approved, if any.
+pub(crate) async fn approve_deployment_on_servers(
     configuration: Configuration,
     release_id: u64,
     server_ids: Vec<String>,
+    annotation: Option<DeployAnnotation>,
 ) -> anyhow::Result<()> {
+    ensure_servers_support(&configuration, &server_ids, "approve").await?;
     let target_servers = select_target_servers(&configuration, &server_ids)?;
     execute_for_servers(
         target_servers,
-        open_deployment_client_connection,
-        move |server, mut client| async move {
-            let request = DeployPublishRequest { release_id };
-            let response_stream = client.publish_deployment(request).await?.into_inner();
-            stream_executed_actions(server, response_stream).await
+        open_deployment_client_connection(&configuration),
+        move |server, mut client| {
+            let annotation = annotation.clone();
+            async move {
+                let request = DeployApproveRequest {
+                    release_id,
+                    annotation,
+                };
+                let response = client.approve_deployment(request).await?.into_inner();
+                info!(
+                    "[{}] --| Approved deployment of release {} (profile {}), approval token: {}",
+                    server.id, response.release_id, response.profile, response.approval_token
+                );
+                Ok(())
+            }
         },
     )
     .await?;
     Ok(())
 }
 
-/// Requests to roll back to the previous deployment of the given profile on the given target servers.
+/// Marks the given release as bad for the given profile on the given target servers. Releases that are marked as bad
+/// are rejected by the servers when trying to start, publish or roll back to them.
 ///
 /// # Arguments
 /// * `configuration` - The client configuration.
-/// * `profile` - The release profile of which the rollback to the previous release should happen.
-/// * `server_ids` - The ids of the servers to roll back to the previous deployment on.
-pub(crate) async fn rollback_deployment_on_servers(
+/// * `profile` - The profile in which the release should be marked as bad.
+/// * `release_id` - The id of the release that should be marked as bad.
+/// * `reason` - The reason why the release is marked as bad.
+/// * `server_ids` - The ids of the servers on which the release should be marked as bad.
+pub(crate) async fn mark_release_bad_on_servers(
     configuration: Configuration,
     profile: String,
+    release_id: u64,
+    reason: String,
     server_ids: Vec<String>,
 ) -> anyhow::Result<()> {
+    ensure_servers_support(&configuration, &server_ids, "mark-bad").await?;
     let target_servers = select_target_servers(&configuration, &server_ids)?;
     execute_for_servers(
         target_servers,
-        open_deployment_client_connection,
+        open_deployment_client_connection(&configuration),
         move |server, mut client| {
             let profile = profile.clone();
+            let reason = reason.clone();
             async move {
-                let request = DeployRollbackRequest { profile };
-                let response_stream = client.rollback_deployment(request).await?.into_inner();
-                stream_executed_actions(server, response_stream).await
+                let request = MarkReleaseBadRequest {
+                    profile,
+                    release_id,
+                    reason,
+                };
+                let response = client.mark_release_bad(request).await?;
+                let response_message = response.get_ref();
+                info!(
+                    "[{}] --| Marked release {} as bad for profile {}",
+                    server.id, response_message.release_id, response_message.profile
+                );
+                Ok(())
             }
         },
     )
@@ -166,44 +1231,491 @@ pub(crate) async fn rollback_deployment_on_servers(
     Ok(())
 }
 
-/// Deletes a deployment that wasn't published before on the given target servers.
+/// Lists the releases of the given profile that are stored on the given target servers, newest first. The releases are
+/// displayed grouped by server, followed by the token to list the next page with if a server has more releases.
 ///
 /// # Arguments
 /// * `configuration` - The client configuration.
-/// * `release_id` - The id of the release that should be deleted.
-/// * `server_ids` - The ids of the servers on which the deployment should be deleted.
-pub(crate) async fn delete_unpublished_deployment_on_servers(
+/// * `profile` - The profile to list the stored releases of.
+/// * `list_options` - The options that control which releases are listed.
+/// * `server_ids` - The ids of the servers to list the stored releases of.
+pub(crate) async fn list_releases_on_servers(
     configuration: Configuration,
+    profile: String,
+    list_options: ReleaseListArgs,
+    server_ids: Vec<String>,
+) -> anyhow::Result<()> {
+    ensure_servers_support(&configuration, &server_ids, "list-releases").await?;
+    let target_servers = select_target_servers(&configuration, &server_ids)?;
+    let request = ListReleasesRequest {
+        profile,
+        page_size: list_options.page_size.unwrap_or_default(),
+        page_token: list_options.page_token.unwrap_or_default(),
+        stored_after: list_options.stored_after,
+        stored_before: list_options.stored_before,
+        marked_bad: list_options.marked_bad,
+    };
+    let listed_releases = Arc::new(Mutex::new(BTreeMap::<String, ListReleasesResponse>::new()));
+    let execution_result = execute_for_servers(
+        target_servers,
+        open_deployment_client_connection(&configuration),
+        {
+            let listed_releases = listed_releases.clone();
+            move |server, mut client| {
+                let request = request.clone();
+                let listed_releases = listed_releases.clone();
+                async move {
+                    let response = client.list_releases(request).await?.into_inner();
+                    if let Ok(mut listed_releases) = listed_releases.lock() {
+                        listed_releases.insert(server.id, response);
+                    }
+                    Ok(())
+                }
+            }
+        },
+    )
+    .await;
+
+    let listed_releases = listed_releases
+        .lock()
+        .map_err(|_| anyhow!("unable to read the listed releases"))?
+        .clone();
+    for (server_id, response) in &listed_releases {
+        if response.releases.is_empty() {
+            info!("[{}] --| No stored releases found", server_id);
+        }
+        for release in &response.releases {
+            let mut release_flags = Vec::<&str>::new();
+            if release.current {
+                release_flags.push("current");
+            }
+            if release.marked_bad {
+                release_flags.push("marked bad");
+            }
+            info!(
+                "[{}] --| Release {:<12} : {} ({}, stored at {} UTC, {} on disk){}",
+                server_id,
+                release.release_id,
+                release.directory_name,
+                release.release_tag.as_deref().unwrap_or("unknown tag"),
+                format_unix_timestamp(release.stored_at),
+                format_byte_size(release.disk_usage_bytes),
+                if release_flags.is_empty() {
+                    String::new()
+                } else {
+                    format!(" [{}]", release_flags.join(", "))
+                }
+            );
+            for (key, value) in release.metadata.iter().collect::<BTreeMap<_, _>>() {
+                info!(
+                    "[{}] --|   Metadata           : {} = {}",
+                    server_id, key, value
+                );
+            }
+        }
+    }
+
+    // the page tokens are release ids, continuing with the highest one skips no release on any server
+    let next_page_token = listed_releases
+        .values()
+        .filter_map(|response| response.next_page_token.parse::<u64>().ok())
+        .max();
+    if let Some(next_page_token) = next_page_token {
+        info!(
+            "More releases are stored, use --page-token {} to list the next page",
+            next_page_token
+        );
+    }
+    execution_result
+}
+
+/// Prints the history of the actions executed for the given profile on the given target servers, newest first. The
+/// entries are displayed grouped by server. The page tokens are only valid for the server that returned them, the token
+/// to print the next page with is therefore displayed per server.
+///
+/// # Arguments
+/// * `configuration` - The client configuration.
+/// * `profile` - The profile to print the history of.
+/// * `page_size` - The maximum amount of entries to print per server, if given.
+/// * `page_token` - The token of the page to print, if given.
+/// * `server_ids` - The ids of the servers to print the history of.
+pub(crate) async fn print_deployment_history_of_servers(
+    configuration: Configuration,
+    profile: String,
+    page_size: Option<u32>,
+    page_token: Option<String>,
+    server_ids: Vec<String>,
+) -> anyhow::Result<()> {
+    ensure_servers_support(&configuration, &server_ids, "history").await?;
+    let target_servers = select_target_servers(&configuration, &server_ids)?;
+    let request = DeploymentHistoryRequest {
+        profile,
+        page_size: page_size.unwrap_or_default(),
+        page_token: page_token.unwrap_or_default(),
+        started_after: None,
+        started_before: None,
+    };
+    let histories = Arc::new(Mutex::new(
+        BTreeMap::<String, DeploymentHistoryResponse>::new(),
+    ));
+    let execution_result = execute_for_servers(
+        target_servers,
+        open_deployment_client_connection(&configuration),
+        {
+            let histories = histories.clone();
+            move |server, mut client| {
+                let request = request.clone();
+                let histories = histories.clone();
+                async move {
+                    let response = client.get_deployment_history(request).await?.into_inner();
+                    if let Ok(mut histories) = histories.lock() {
+                        histories.insert(server.id, response);
+                    }
+                    Ok(())
+                }
+            }
+        },
+    )
+    .await;
+
+    let histories = histories
+        .lock()
+        .map_err(|_| anyhow!("unable to read the deployment histories"))?
+        .clone();
+    for (server_id, response) in &histories {
+        if response.entries.is_empty() {
+            info!("[{}] --| No recorded actions found", server_id);
+        }
+        for entry in &response.entries {
+            info!(
+                "[{}] --| {} UTC : {:<8} release {} ({}) {} after {}s by {}",
+                server_id,
+                format_unix_timestamp(entry.started_at),
+                entry.action,
+                entry.release_id,
+                entry.release_tag,
+                if entry.succeeded {
+                    "succeeded"
+                } else {
+                    "failed"
+                },
+                entry.finished_at.saturating_sub(entry.started_at),
+                entry.triggered_by
+            );
+            if let Some(reason) = &entry.reason {
+                match &entry.ticket_reference {
+                    Some(ticket_reference) => info!(
+                        "[{}] --|   Reason: {} [{}]",
+                        server_id, reason, ticket_reference
+                    ),
+                    None => info!("[{}] --|   Reason: {}", server_id, reason),
+                }
+            }
+            if let Some(git_ref) = &entry.git_ref {
+                info!("[{}] --|   Deployed from git ref {}", server_id, git_ref);
+            }
+            for (key, value) in entry.metadata.iter().collect::<BTreeMap<_, _>>() {
+                info!("[{}] --|   Metadata: {} = {}", server_id, key, value);
+            }
+            if let Some(failure_archive) = &entry.failure_archive {
+                info!(
+                    "[{}] --|   Output archived in state/failed/{}",
+                    server_id, failure_archive
+                );
+            }
+        }
+        if !response.next_page_token.is_empty() {
+            info!(
+                "[{}] --| More actions are recorded, use --page-token {} to print the next page",
+                server_id, response.next_page_token
+            );
+        }
+    }
+    execution_result
+}
+
+/// The maximum amount of history entries requested per page when exporting the history, the most the servers return.
+const HISTORY_EXPORT_PAGE_SIZE: u32 = 500;
+
+/// The columns of the history report exported as csv.
+const HISTORY_EXPORT_CSV_HEADER: &str = "server,profile,action,release_id,release_tag,git_ref,started_at,finished_at,succeeded,triggered_by,reason,ticket_reference,metadata,failure_archive";
+
+/// An action of the history of a server, as exported in the history report.
+#[derive(Serialize, Debug)]
+struct HistoryReportEntry {
+    /// The id of the server on which the action was executed.
+    server: String,
+    /// The profile for which the action was executed.
+    profile: String,
+    /// The action that was executed, one of prepare, publish, rollback or delete.
+    action: String,
+    /// The id of the release on which the action was executed.
     release_id: u64,
+    /// The tag name of the release on which the action was executed.
+    release_tag: String,
+    /// The git ref that was deployed if the release was deployed from a git ref.
+    git_ref: Option<String>,
+    /// The time when the action was started, as `YYYY-MM-DD HH:MM:SS` in UTC.
+    started_at: String,
+    /// The time when the action finished, as `YYYY-MM-DD HH:MM:SS` in UTC.
+    finished_at: String,
+    /// Indicates if the action completed successfully.
+    succeeded: bool,
+    /// The identity that triggered the action.
+    triggered_by: String,
+    /// The reason that was given for the action, if any.
+    reason: Option<String>,
+    /// The ticket that is associated with the action, if any.
+    ticket_reference: Option<String>,
+    /// The metadata that was attached to the deployment of the release.
+    metadata: BTreeMap<String, String>,
+    /// The directory in the failed action archive of the server containing the output of the failed action, if any.
+    failure_archive: Option<String>,
+}
+
+impl HistoryReportEntry {
+    /// Constructs the report entry for the given history entry of the given server.
+    ///
+    /// # Arguments
+    /// * `server` - The id of the server on which the action was executed.
+    /// * `profile` - The profile for which the action was executed.
+    /// * `entry` - The history entry of the action.
+    fn new(server: String, profile: String, entry: DeploymentHistoryEntry) -> Self {
+        Self {
+            server,
+            profile,
+            action: entry.action,
+            release_id: entry.release_id,
+            release_tag: entry.release_tag,
+            git_ref: entry.git_ref,
+            started_at: format_unix_timestamp(entry.started_at),
+            finished_at: format_unix_timestamp(entry.finished_at),
+            succeeded: entry.succeeded,
+            triggered_by: entry.triggered_by,
+            reason: entry.reason,
+            ticket_reference: entry.ticket_reference,
+            metadata: entry.metadata.into_iter().collect(),
+            failure_archive: entry.failure_archive,
+        }
+    }
+
+    /// Formats this entry as csv line, with the columns of the csv header. The metadata is joined into a single
+    /// column as `<key>=<value>` pairs separated by `;`.
+    fn to_csv_line(&self) -> String {
+        let metadata = self
+            .metadata
+            .iter()
+            .map(|(key, value)| format!("{key}={value}"))
+            .collect::<Vec<_>>()
+            .join(";");
+        [
+            self.server.as_str(),
+            self.profile.as_str(),
+            self.action.as_str(),
+            &self.release_id.to_string(),
+            self.release_tag.as_str(),
+            self.git_ref.as_deref().unwrap_or_default(),
+            self.started_at.as_str(),
+            self.finished_at.as_str(),
+            &self.succeeded.to_string(),
+            self.triggered_by.as_str(),
+            self.reason.as_deref().unwrap_or_default(),
+            self.ticket_reference.as_deref().unwrap_or_default(),
+            &metadata,
+            self.failure_archive.as_deref().unwrap_or_default(),
+        ]
+        .iter()
+        .map(|value| escape_csv_value(value))
+        .collect::<Vec<_>>()
+        .join(",")
+    }
+}
+
+/// Exports the history of the actions executed for the given profile on the given target servers as report to
+/// stdout. All pages of the history are requested from the servers, the actions of all servers are exported newest
+/// first.
+///
+/// # Arguments
+/// * `configuration` - The client configuration.
+/// * `profile` - The profile to export the history of.
+/// * `format` - The format of the exported report.
+/// * `started_after` - Only actions started at or after this unix timestamp (in seconds) are exported, if given.
+/// * `started_before` - Only actions started before this unix timestamp (in seconds) are exported, if given.
+/// * `server_ids` - The ids of the servers to export the history of.
+pub(crate) async fn export_deployment_history_of_servers(
+    configuration: Configuration,
+    profile: String,
+    format: HistoryExportFormat,
+    started_after: Option<u64>,
+    started_before: Option<u64>,
     server_ids: Vec<String>,
 ) -> anyhow::Result<()> {
+    ensure_servers_support(&configuration, &server_ids, "history").await?;
     let target_servers = select_target_servers(&configuration, &server_ids)?;
+    let request = DeploymentHistoryRequest {
+        profile: profile.clone(),
+        page_size: HISTORY_EXPORT_PAGE_SIZE,
+        page_token: String::new(),
+        started_after,
+        started_before,
+    };
+    let history_entries = Arc::new(Mutex::new(Vec::<(String, DeploymentHistoryEntry)>::new()));
     execute_for_servers(
         target_servers,
-        open_deployment_client_connection,
-        move |server, mut client| async move {
-            let request = DeployDeleteRequest { release_id };
-            let response_stream = client
-                .delete_unpublished_deployment(request)
-                .await?
-                .into_inner();
-            stream_executed_actions(server, response_stream).await
+        open_deployment_client_connection(&configuration),
+        {
+            let history_entries = history_entries.clone();
+            move |server, mut client| {
+                let mut request = request.clone();
+                let history_entries = history_entries.clone();
+                async move {
+                    // request the pages one after another, each page references the next one
+                    let mut server_entries = Vec::new();
+                    loop {
+                        let response = client
+                            .get_deployment_history(request.clone())
+                            .await?
+                            .into_inner();
+                        server_entries.extend(response.entries);
+                        if response.next_page_token.is_empty() {
+                            break;
+                        }
+                        request.page_token = response.next_page_token;
+                    }
+                    if let Ok(mut history_entries) = history_entries.lock() {
+                        history_entries.extend(
+                            server_entries
+                                .into_iter()
+                                .map(|entry| (server.id.clone(), entry)),
+                        );
+                    }
+                    Ok(())
+                }
+            }
         },
     )
     .await?;
+
+    let mut history_entries = history_entries
+        .lock()
+        .map_err(|_| anyhow!("unable to read the deployment histories"))?
+        .drain(..)
+        .collect::<Vec<_>>();
+    history_entries.sort_by(|(server_id, entry), (other_server_id, other_entry)| {
+        other_entry
+            .started_at
+            .cmp(&entry.started_at)
+            .then_with(|| server_id.cmp(other_server_id))
+    });
+    let report_entries = history_entries
+        .into_iter()
+        .map(|(server, entry)| HistoryReportEntry::new(server, profile.clone(), entry))
+        .collect::<Vec<_>>();
+    match format {
+        HistoryExportFormat::Csv => {
+            println!("{HISTORY_EXPORT_CSV_HEADER}");
+            for report_entry in &report_entries {
+                println!("{}", report_entry.to_csv_line());
+            }
+        }
+        HistoryExportFormat::Json => {
+            println!("{}", serde_json::to_string_pretty(&report_entries)?);
+        }
+    }
     Ok(())
 }
 
-/// Opens a client connection for the deployment gRPC service to the endpoint of the given target server.
+/// Escapes the given value for a csv column: values containing a separator, quote or line break are quoted, quotes
+/// in them are doubled.
 ///
 /// # Arguments
-/// * `server` - The target server to connect to.
-async fn open_deployment_client_connection(
-    server: TargetServer,
-) -> anyhow::Result<DeploymentServiceClient<Channel>> {
-    DeploymentServiceClient::connect(server.address.clone())
-        .await
-        .map_err(Into::into)
+/// * `value` - The value to escape.
+fn escape_csv_value(value: &str) -> String {
+    if value.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value.to_string()
+    }
+}
+
+/// Adopts the given existing directory as a release of the given profile on the given target servers, moving it into
+/// the releases directory of the profile without executing a deployment.
+///
+/// # Arguments
+/// * `configuration` - The client configuration.
+/// * `profile` - The profile to adopt the release into.
+/// * `release_id` - The id of the release that is stored in the directory.
+/// * `source_directory` - The absolute path of the directory to adopt on the servers.
+/// * `adopt_options` - The options that control how the directory is adopted.
+/// * `server_ids` - The ids of the servers on which the release should be adopted.
+pub(crate) async fn adopt_release_on_servers(
+    configuration: Configuration,
+    profile: String,
+    release_id: u64,
+    source_directory: String,
+    adopt_options: AdoptArgs,
+    server_ids: Vec<String>,
+) -> anyhow::Result<()> {
+    ensure_servers_support(&configuration, &server_ids, "adopt").await?;
+    let target_servers = select_target_servers(&configuration, &server_ids)?;
+    let request = AdoptReleaseRequest {
+        profile,
+        release_id,
+        tag_name: adopt_options.tag_name,
+        source_directory,
+        metadata: adopt_options.metadata.into_iter().collect(),
+        publish: adopt_options.publish,
+    };
+    execute_for_servers(
+        target_servers,
+        open_deployment_client_connection(&configuration),
+        move |server, mut client| {
+            let request = request.clone();
+            async move {
+                let response = client.adopt_release(request).await?;
+                let response_message = response.get_ref();
+                info!(
+                    "[{}] --| Adopted release {} for profile {} in {}",
+                    server.id,
+                    response_message.release_id,
+                    response_message.profile,
+                    response_message.release_directory
+                );
+                if response_message.published {
+                    info!(
+                        "[{}] --| Published release {} as current release",
+                        server.id, response_message.release_id
+                    );
+                }
+                Ok(())
+            }
+        },
+    )
+    .await?;
+    Ok(())
+}
+
+/// Get a function that opens a client connection for the deployment gRPC service to the endpoint of a target server.
+/// The identity of the invoking user is attached to all requests sent through the opened connections.
+///
+/// # Arguments
+/// * `configuration` - The client configuration.
+fn open_deployment_client_connection(
+    configuration: &Configuration,
+) -> impl Fn(TargetServer) -> BoxFuture<'static, anyhow::Result<DeploymentClient>> + Clone + Send + 'static
+{
+    let interceptor = MetadataInterceptor::new(configuration);
+    move |server| {
+        let interceptor = interceptor.for_server(&server);
+        async move {
+            let channel = Endpoint::from_shared(server.address)?.connect().await?;
+            Ok(DeploymentServiceClient::with_interceptor(
+                channel,
+                interceptor,
+            ))
+        }
+        .boxed()
+    }
 }
 
 /// Streams the executed action entries returned by the provided stream into the console until the stream finished
@@ -213,14 +1725,53 @@ async fn open_deployment_client_connection(
 /// # Arguments
 /// * `server` - The server of which the output is streamed into the console.
 /// * `stream` - The data stream containing the executed action entries coming from the server.
+/// * `fleet_telemetry` - The telemetry in which the timings of the server are recorded.
+/// * `output_aggregator` - The aggregator through which the output lines of the server are printed.
 ///
 /// # Returns
 /// * `anyhow::Result<()>` - `Ok` if the execution completed successfully on the remote, `Err` if some error occurred.
 async fn stream_executed_actions(
     server: TargetServer,
-    mut stream: Streaming<ExecutedActionEntry>,
+    stream: Streaming<ExecutedActionEntry>,
+    fleet_telemetry: &FleetTelemetry,
+    output_aggregator: &OutputAggregator,
+) -> anyhow::Result<()> {
+    let started_at = Instant::now();
+    let mut phase_durations = BTreeMap::<String, Duration>::new();
+    let recorded_server_id = server.id.clone();
+    let stream = stream.inspect(move |stream_entry| {
+        record_stream_entry(&recorded_server_id, stream_entry);
+    });
+    let execution_result =
+        stream_executed_action_entries(&server.id, stream, &mut phase_durations, output_aggregator)
+            .await;
+    output_aggregator.finish_server(&server.id);
+    fleet_telemetry.record(ServerTiming {
+        server_id: server.id,
+        succeeded: execution_result.is_ok(),
+        total_duration: started_at.elapsed(),
+        phase_durations,
+    });
+    execution_result
+}
+
+/// Streams the executed action entries returned by the provided stream into the console, recording the time it took
+/// to execute each action.
+///
+/// # Arguments
+/// * `server_id` - The id of the server of which the output is streamed into the console.
+/// * `stream` - The data stream containing the executed action entries coming from the server.
+/// * `phase_durations` - The map in which the time spent in each action is recorded.
+/// * `output_aggregator` - The aggregator through which the output lines of the server are printed.
+async fn stream_executed_action_entries(
+    server_id: &str,
+    stream: impl Stream<Item = Result<ExecutedActionEntry, Status>>,
+    phase_durations: &mut BTreeMap<String, Duration>,
+    output_aggregator: &OutputAggregator,
 ) -> anyhow::Result<()> {
+    let mut stream = pin!(stream);
     let mut encountered_failed_script = false;
+    let mut action_started_at = HashMap::<i32, Instant>::new();
     while let Some(data) = stream.next().await {
         match data {
             Ok(action_entry) => {
@@ -230,32 +1781,89 @@ async fn stream_executed_actions(
                         format_action_name(Action::try_from(action_entry.current_action));
                     let log_stream =
                         LogType::try_from(log_entry.stream_type).unwrap_or(LogType::Stdout);
-                    match log_stream {
-                        LogType::Stdout => info!(
-                            "[{} @ {}] --| {}",
-                            server.id, current_action, log_entry.content
-                        ),
-                        LogType::Stderr => warn!(
-                            "[{} @ {}] --| {}",
-                            server.id, current_action, log_entry.content
+                    let log_line = prepare_remote_output_line(&log_entry.content);
+                    let log_level = match log_stream {
+                        LogType::Stdout => Level::Info,
+                        LogType::Stderr => Level::Warn,
+                    };
+                    output_aggregator.print_line(
+                        server_id,
+                        Some(current_action),
+                        log_level,
+                        log_line.to_string(),
+                    );
+                }
+
+                // display the progress information reported by the script, if present
+                if let Some(phase) = &action_entry.phase {
+                    let current_action =
+                        format_action_name(Action::try_from(action_entry.current_action));
+                    output_aggregator.print_line(
+                        server_id,
+                        Some(current_action),
+                        Level::Info,
+                        format!("Phase    : {}", prepare_remote_output_line(phase)),
+                    );
+                }
+                if let Some(progress_percent) = action_entry.progress_percent {
+                    let current_action =
+                        format_action_name(Action::try_from(action_entry.current_action));
+                    output_aggregator.print_line(
+                        server_id,
+                        Some(current_action),
+                        Level::Info,
+                        format!(
+                            "Progress : {:>3}% [{:<20}]",
+                            progress_percent,
+                            "#".repeat(progress_percent as usize / 5)
                         ),
+                    );
+                }
+
+                // record the time spent in the action once it completed
+                let action_status = ActionStatus::try_from(action_entry.action_status);
+                match action_status {
+                    Ok(ActionStatus::Started) => {
+                        action_started_at.insert(action_entry.current_action, Instant::now());
+                    }
+                    Ok(ActionStatus::CompletedSuccess | ActionStatus::CompletedFailure) => {
+                        if let Some(started_at) =
+                            action_started_at.remove(&action_entry.current_action)
+                        {
+                            let phase =
+                                format_action_name(Action::try_from(action_entry.current_action));
+                            *phase_durations.entry(phase).or_default() += started_at.elapsed();
+                        }
                     }
+                    _ => {}
                 }
 
                 // display information about the current action status
-                if let Ok(action_status) = ActionStatus::try_from(action_entry.action_status) {
+                if let Ok(action_status) = action_status {
                     match action_status {
                         ActionStatus::Started => {
-                            info!("[{}] --| Script Execution Started", server.id);
+                            output_aggregator.print_line(
+                                server_id,
+                                None,
+                                Level::Info,
+                                "Script Execution Started".to_string(),
+                            );
                         }
                         ActionStatus::CompletedSuccess => {
-                            info!(
-                                "[{}] --| Script Execution Completed Successfully",
-                                server.id
+                            output_aggregator.print_line(
+                                server_id,
+                                None,
+                                Level::Info,
+                                "Script Execution Completed Successfully".to_string(),
                             );
                         }
                         ActionStatus::CompletedFailure => {
-                            error!("[{}] --| Script Execution Failed", server.id);
+                            output_aggregator.print_line(
+                                server_id,
+                                None,
+                                Level::Error,
+                                "Script Execution Failed".to_string(),
+                            );
                             encountered_failed_script = true;
                         }
                         ActionStatus::Running => {}
@@ -264,7 +1872,7 @@ async fn stream_executed_actions(
             }
             Err(status) => bail!(
                 "[{}] Server returned status {}: {}",
-                server.id,
+                server_id,
                 status.code(),
                 status.message()
             ),
@@ -275,7 +1883,7 @@ async fn stream_executed_actions(
     if encountered_failed_script {
         Err(anyhow!(
             "Encountered at least one script on {} that did not complete successfully",
-            server.id
+            server_id
         ))
     } else {
         Ok(())
@@ -297,7 +1905,71 @@ fn format_action_name(maybe_action: Result<Action, UnknownEnumValue>) -> String
             Action::InitScript => "Init Script".to_string(),
             Action::FinishScript => "Finish Script".to_string(),
             Action::DeleteScript => "Delete Script".to_string(),
+            Action::PublishHook => "Publish Hook".to_string(),
+            Action::BuildScript => "Build Script".to_string(),
+            Action::Warmup => "Warmup".to_string(),
+            Action::HealthCheck => "Health Check".to_string(),
+            Action::CancelScript => "Cancel Script".to_string(),
+            Action::FilePermissions => "File Permissions".to_string(),
+            Action::Queue => "Queue".to_string(),
+            Action::ExecCommand => "Command".to_string(),
+            Action::ArtifactDownload => "Artifact Download".to_string(),
+            Action::ReleasePrune => "Release Prune".to_string(),
+            Action::Migration => "Migration".to_string(),
+            Action::Backup => "Backup".to_string(),
         },
-        Err(action) => format!("{}", action),
+        // actions introduced in a newer server version, display them by their number
+        Err(UnknownEnumValue(action_number)) => format!("Unknown Action ({action_number})"),
+    }
+}
+
+/// Formats the rollback availability reported by a server for display.
+///
+/// # Arguments
+/// * `rollback_candidates` - The previous releases stored on the server, newest first.
+fn format_rollback_availability(rollback_candidates: &[RollbackCandidate]) -> String {
+    match rollback_candidates.first() {
+        Some(candidate) if candidate.marked_bad => format!(
+            "not possible, {} was marked as bad ({} previous releases stored)",
+            format_rollback_candidate(candidate),
+            rollback_candidates.len()
+        ),
+        Some(candidate) => format!(
+            "possible to {} ({} previous releases stored)",
+            format_rollback_candidate(candidate),
+            rollback_candidates.len()
+        ),
+        None => "not possible, no previous release stored".to_string(),
+    }
+}
+
+/// Formats the given rollback candidate for display, using the tag name if it is known.
+///
+/// # Arguments
+/// * `candidate` - The rollback candidate to format.
+fn format_rollback_candidate(candidate: &RollbackCandidate) -> String {
+    if candidate.tag_name.is_empty() {
+        format!("release {}", candidate.release_id)
+    } else {
+        format!("{} (id: {})", candidate.tag_name, candidate.release_id)
+    }
+}
+
+/// Formats the given amount of bytes as a human-readable size using binary units.
+///
+/// # Arguments
+/// * `bytes` - The amount of bytes to format.
+fn format_byte_size(bytes: u64) -> String {
+    const UNITS: [&str; 5] = ["B", "KiB", "MiB", "GiB", "TiB"];
+    let mut size = bytes as f64;
+    let mut unit_index = 0;
+    while size >= 1024.0 && unit_index < UNITS.len() - 1 {
+        size /= 1024.0;
+        unit_index += 1;
+    }
+    if unit_index == 0 {
+        format!("{} {}", bytes, UNITS[0])
+    } else {
+        format!("{:.1} {}", size, UNITS[unit_index])
     }
 }
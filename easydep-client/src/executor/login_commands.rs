@@ -0,0 +1,177 @@
+/*
+ * This file is part of easydep, licensed under the MIT License (MIT).
+ *
+ * Copyright (c) 2024 easybill GmbH
+ *
+ * Permission is hereby granted, free of charge, to any person obtaining a copy
+ * of this software and associated documentation files (the "Software"), to deal
+ * in the Software without restriction, including without limitation the rights
+ * to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+ * copies of the Software, and to permit persons to whom the Software is
+ * furnished to do so, subject to the following conditions:
+ *
+ * The above copyright notice and this permission notice shall be included in all
+ * copies or substantial portions of the Software.
+ *
+ * THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+ * IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+ * FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+ * AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+ * LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+ * OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+ * SOFTWARE.
+ */
+
+use std::path::PathBuf;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use anyhow::{bail, Context};
+use log::info;
+use serde::Deserialize;
+
+use crate::config::Configuration;
+
+/// The grant type used to poll the token endpoint during the device authorization flow.
+const DEVICE_CODE_GRANT_TYPE: &str = "urn:ietf:params:oauth:grant-type:device_code";
+
+/// The subset of the OpenID Connect discovery document that is needed for the device authorization flow.
+#[derive(Deserialize, Debug)]
+struct DiscoveryDocument {
+    /// The endpoint to request a device code from.
+    device_authorization_endpoint: String,
+    /// The endpoint to exchange the device code for a token.
+    token_endpoint: String,
+}
+
+/// The response of the device authorization endpoint.
+#[derive(Deserialize, Debug)]
+struct DeviceAuthorizationResponse {
+    /// The code that identifies this client during polling.
+    device_code: String,
+    /// The code that the user must enter on the verification page.
+    user_code: String,
+    /// The page where the user must enter the user code.
+    verification_uri: String,
+    /// The page where the user can confirm the login without entering the user code.
+    verification_uri_complete: Option<String>,
+    /// The time (in seconds) after which the device code expires.
+    expires_in: u64,
+    /// The minimum time (in seconds) to wait between polling requests.
+    interval: Option<u64>,
+}
+
+/// The response of the token endpoint, either containing the token or the reason why no token was issued.
+#[derive(Deserialize, Debug)]
+struct TokenResponse {
+    /// The issued access token.
+    access_token: Option<String>,
+    /// The time (in seconds) after which the access token expires.
+    expires_in: Option<u64>,
+    /// The error code if no token was issued.
+    error: Option<String>,
+    /// A human-readable description of the error, if no token was issued.
+    error_description: Option<String>,
+}
+
+/// Obtains a new access token from the configured OpenID Connect provider using the device authorization flow and
+/// stores it in the client configuration. The token is sent to the servers with each following request.
+///
+/// # Arguments
+/// * `configuration` - The current client configuration.
+/// * `config_path` - The path from where the configuration is loaded.
+pub(crate) async fn login_with_device_flow(
+    mut configuration: Configuration,
+    config_path: PathBuf,
+) -> anyhow::Result<()> {
+    let oidc_settings = configuration
+        .oidc
+        .as_mut()
+        .context("no oidc settings are present in the client configuration")?;
+    let http_client = reqwest::Client::new();
+
+    // discover the endpoints needed for the device flow
+    let discovery_url = format!(
+        "{}/.well-known/openid-configuration",
+        oidc_settings.issuer.trim_end_matches('/')
+    );
+    let discovery_document: DiscoveryDocument = http_client
+        .get(&discovery_url)
+        .send()
+        .await?
+        .error_for_status()?
+        .json()
+        .await
+        .context("unable to parse discovery document of token issuer")?;
+
+    // request a device code and tell the user where to confirm the login
+    let scope = oidc_settings.scopes.join(" ");
+    let mut device_authorization_form = vec![
+        ("client_id", oidc_settings.client_id.as_str()),
+        ("scope", scope.as_str()),
+    ];
+    if let Some(audience) = &oidc_settings.audience {
+        device_authorization_form.push(("audience", audience.as_str()));
+    }
+    let device_authorization: DeviceAuthorizationResponse = http_client
+        .post(&discovery_document.device_authorization_endpoint)
+        .form(&device_authorization_form)
+        .send()
+        .await?
+        .error_for_status()?
+        .json()
+        .await
+        .context("unable to parse device authorization response")?;
+    match &device_authorization.verification_uri_complete {
+        Some(verification_uri_complete) => {
+            info!("Open {} to confirm the login", verification_uri_complete)
+        }
+        None => info!(
+            "Open {} and enter the code {} to confirm the login",
+            device_authorization.verification_uri, device_authorization.user_code
+        ),
+    }
+
+    // poll the token endpoint until the user confirmed the login or the device code expired
+    let mut poll_interval = Duration::from_secs(device_authorization.interval.unwrap_or(5));
+    let device_code_expiry =
+        SystemTime::now() + Duration::from_secs(device_authorization.expires_in);
+    let token_form = [
+        ("grant_type", DEVICE_CODE_GRANT_TYPE),
+        ("device_code", device_authorization.device_code.as_str()),
+        ("client_id", oidc_settings.client_id.as_str()),
+    ];
+    let (access_token, expires_in) = loop {
+        if SystemTime::now() > device_code_expiry {
+            bail!("the login was not confirmed before the device code expired")
+        }
+
+        tokio::time::sleep(poll_interval).await;
+        let token_response: TokenResponse = http_client
+            .post(&discovery_document.token_endpoint)
+            .form(&token_form)
+            .send()
+            .await?
+            .json()
+            .await
+            .context("unable to parse token response")?;
+        match (token_response.access_token, token_response.error.as_deref()) {
+            (Some(access_token), _) => break (access_token, token_response.expires_in),
+            (None, Some("authorization_pending")) => {}
+            (None, Some("slow_down")) => poll_interval += Duration::from_secs(5),
+            (None, error) => bail!(
+                "token issuer rejected the login: {} {}",
+                error.unwrap_or("unknown error"),
+                token_response.error_description.unwrap_or_default()
+            ),
+        }
+    };
+
+    // store the obtained token in the configuration
+    let issued_at = SystemTime::now().duration_since(UNIX_EPOCH)?.as_secs();
+    oidc_settings.access_token = Some(access_token);
+    oidc_settings.access_token_expires_at = expires_in.map(|expires_in| issued_at + expires_in);
+    configuration.save_to_file(config_path).await?;
+    info!("Successfully logged in");
+
+    Ok(())
+}
@@ -24,4 +24,6 @@
 
 pub(crate) mod config_commands;
 pub(crate) mod deployment_commands;
+pub(crate) mod login_commands;
+pub(crate) mod server_commands;
 pub(crate) mod status_commands;
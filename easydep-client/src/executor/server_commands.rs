@@ -0,0 +1,354 @@
+/*
+ * This file is part of easydep, licensed under the MIT License (MIT).
+ *
+ * Copyright (c) 2024 easybill GmbH
+ *
+ * Permission is hereby granted, free of charge, to any person obtaining a copy
+ * of this software and associated documentation files (the "Software"), to deal
+ * in the Software without restriction, including without limitation the rights
+ * to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+ * copies of the Software, and to permit persons to whom the Software is
+ * furnished to do so, subject to the following conditions:
+ *
+ * The above copyright notice and this permission notice shall be included in all
+ * copies or substantial portions of the Software.
+ *
+ * THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+ * IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+ * FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+ * AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+ * LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+ * OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+ * SOFTWARE.
+ */
+
+use std::collections::{BTreeMap, BTreeSet};
+use std::path::PathBuf;
+
+use anyhow::{anyhow, bail, Context};
+use log::{error, info};
+use tokio::fs;
+
+use crate::config::Configuration;
+use crate::easydep::{
+    GitHubCheckRequest, ServerConfigurationPushRequest, ServerConfigurationReloadRequest,
+    ServerConfigurationRequest, ServerUpgradeRequest,
+};
+use crate::executor::status_commands::open_status_client_connection;
+use crate::util::calendar_date::format_unix_timestamp;
+use crate::util::server_connector::execute_for_servers;
+use crate::util::server_selector::select_target_servers;
+
+/// Displays the effective configuration of the given server.
+///
+/// # Arguments
+/// * `configuration` - The client configuration.
+/// * `server_id` - The id of the server to display the configuration of.
+pub(crate) async fn display_server_configuration(
+    configuration: Configuration,
+    server_id: String,
+) -> anyhow::Result<()> {
+    let server_configuration = fetch_server_configuration(&configuration, &server_id).await?;
+    for line in server_configuration.lines() {
+        info!("[{}] {}", server_id, line);
+    }
+    Ok(())
+}
+
+/// Compares the effective configuration of the given servers, displaying all values that differ between them.
+///
+/// # Arguments
+/// * `configuration` - The client configuration.
+/// * `server_id` - The id of the first server to compare the configuration of.
+/// * `other_server_id` - The id of the second server to compare the configuration of.
+pub(crate) async fn diff_server_configurations(
+    configuration: Configuration,
+    server_id: String,
+    other_server_id: String,
+) -> anyhow::Result<()> {
+    let (server_configuration, other_server_configuration) = tokio::try_join!(
+        fetch_server_configuration(&configuration, &server_id),
+        fetch_server_configuration(&configuration, &other_server_id),
+    )?;
+    let server_values = flatten_configuration(&server_configuration)?;
+    let other_server_values = flatten_configuration(&other_server_configuration)?;
+
+    // compare the values of all keys that are set on at least one of the servers
+    let keys: BTreeSet<&String> = server_values
+        .keys()
+        .chain(other_server_values.keys())
+        .collect();
+    let mut difference_count = 0;
+    for key in keys {
+        let server_value = server_values.get(key);
+        let other_server_value = other_server_values.get(key);
+        if server_value != other_server_value {
+            difference_count += 1;
+            info!(
+                "--| {} : {} ({}) <-> {} ({})",
+                key,
+                server_value.map(String::as_str).unwrap_or("<unset>"),
+                server_id,
+                other_server_value.map(String::as_str).unwrap_or("<unset>"),
+                other_server_id
+            );
+        }
+    }
+
+    if difference_count == 0 {
+        info!(
+            "The configurations of {} and {} are identical",
+            server_id, other_server_id
+        );
+    } else {
+        info!(
+            "Found {} differences between the configurations of {} and {}",
+            difference_count, server_id, other_server_id
+        );
+    }
+    Ok(())
+}
+
+/// Installs the given configuration file on the given servers. The servers validate the configuration before
+/// installing it and restart afterwards to apply it.
+///
+/// # Arguments
+/// * `configuration` - The client configuration.
+/// * `configuration_file` - The path to the server configuration file to install.
+/// * `server_ids` - The ids of the servers to install the configuration on.
+pub(crate) async fn push_server_configuration(
+    configuration: Configuration,
+    configuration_file: PathBuf,
+    server_ids: Vec<String>,
+) -> anyhow::Result<()> {
+    let server_configuration = fs::read_to_string(&configuration_file)
+        .await
+        .with_context(|| format!("unable to read configuration file {:?}", configuration_file))?;
+    let target_servers = select_target_servers(&configuration, &server_ids)?;
+    execute_for_servers(
+        target_servers,
+        open_status_client_connection(&configuration),
+        move |server, mut client| {
+            let server_configuration = server_configuration.clone();
+            async move {
+                let response = client
+                    .push_server_configuration(ServerConfigurationPushRequest {
+                        configuration: server_configuration,
+                    })
+                    .await?;
+                info!(
+                    "[{}] Installed configuration (sha256: {}), the server restarts to apply it",
+                    server.id,
+                    response.get_ref().config_hash
+                );
+                Ok(())
+            }
+        },
+    )
+    .await
+}
+
+/// Reloads the configuration file of the given servers without restarting them. The servers validate the
+/// configuration and reject the reload if it changes settings that can only be applied by a restart.
+///
+/// # Arguments
+/// * `configuration` - The client configuration.
+/// * `server_ids` - The ids of the servers to reload the configuration of.
+pub(crate) async fn reload_server_configuration(
+    configuration: Configuration,
+    server_ids: Vec<String>,
+) -> anyhow::Result<()> {
+    let target_servers = select_target_servers(&configuration, &server_ids)?;
+    execute_for_servers(
+        target_servers,
+        open_status_client_connection(&configuration),
+        move |server, mut client| async move {
+            let response = client
+                .reload_server_configuration(ServerConfigurationReloadRequest {})
+                .await?;
+            let response_message = response.get_ref();
+            info!(
+                "[{}] Reloaded configuration (sha256: {}), loaded profiles: {}",
+                server.id,
+                response_message.config_hash,
+                response_message.deployment_configurations.join(", ")
+            );
+            Ok(())
+        },
+    )
+    .await
+}
+
+/// Checks the GitHub access of the given servers: the authentication as GitHub app and the access to the repositories
+/// of their profiles. Fails if one of the checks failed on a server.
+///
+/// # Arguments
+/// * `configuration` - The client configuration.
+/// * `server_ids` - The ids of the servers to check the GitHub access of.
+pub(crate) async fn check_servers_github_access(
+    configuration: Configuration,
+    server_ids: Vec<String>,
+) -> anyhow::Result<()> {
+    let target_servers = select_target_servers(&configuration, &server_ids)?;
+    execute_for_servers(
+        target_servers,
+        open_status_client_connection(&configuration),
+        move |server, mut client| async move {
+            let response = client.check_git_hub_access(GitHubCheckRequest {}).await?;
+            let response_message = response.get_ref();
+            if let Some(app_error) = &response_message.app_error {
+                error!("[{}] --| GitHub App : {}", server.id, app_error);
+                bail!("the server cannot authenticate as GitHub app")
+            }
+            info!(
+                "[{}] --| GitHub App : {}",
+                server.id,
+                response_message.app_name.as_deref().unwrap_or_default()
+            );
+
+            let mut failed_profiles = Vec::new();
+            for profile_check in &response_message.profile_checks {
+                let installation = profile_check
+                    .installation_id
+                    .map(|installation_id| installation_id.to_string())
+                    .unwrap_or_else(|| "-".to_string());
+                let contents_permission =
+                    profile_check.contents_permission.as_deref().unwrap_or("-");
+                let rate_limit = match (
+                    profile_check.rate_limit_remaining,
+                    profile_check.rate_limit_total,
+                    profile_check.rate_limit_reset_at,
+                ) {
+                    (Some(remaining), Some(total), Some(reset_at)) => format!(
+                        "{}/{} remaining (resets {})",
+                        remaining,
+                        total,
+                        format_unix_timestamp(reset_at)
+                    ),
+                    _ => "-".to_string(),
+                };
+                info!(
+                    "[{}] --| {} ({}) : installation {}, contents {}, rate limit {}",
+                    server.id,
+                    profile_check.profile,
+                    profile_check.repository,
+                    installation,
+                    contents_permission,
+                    rate_limit
+                );
+                if let Some(error) = &profile_check.error {
+                    error!("[{}] --| {} : {}", server.id, profile_check.profile, error);
+                    failed_profiles.push(profile_check.profile.as_str());
+                }
+            }
+            if !failed_profiles.is_empty() {
+                bail!(
+                    "GitHub access check failed for profile(s): {}",
+                    failed_profiles.join(", ")
+                )
+            }
+            Ok(())
+        },
+    )
+    .await
+}
+
+/// Upgrades the given servers to the given easydep release. The servers verify the downloaded binary before
+/// installing it and restart afterwards to apply it.
+///
+/// # Arguments
+/// * `configuration` - The client configuration.
+/// * `release_tag` - The tag of the easydep release to install, the latest release is installed if not given.
+/// * `server_ids` - The ids of the servers to upgrade.
+pub(crate) async fn upgrade_servers(
+    configuration: Configuration,
+    release_tag: Option<String>,
+    server_ids: Vec<String>,
+) -> anyhow::Result<()> {
+    let target_servers = select_target_servers(&configuration, &server_ids)?;
+    execute_for_servers(
+        target_servers,
+        open_status_client_connection(&configuration),
+        move |server, mut client| {
+            let release_tag = release_tag.clone();
+            async move {
+                let response = client
+                    .upgrade_server(ServerUpgradeRequest { release_tag })
+                    .await?;
+                info!(
+                    "[{}] Installed easydep {}, the server restarts to apply it",
+                    server.id,
+                    response.get_ref().release_tag
+                );
+                Ok(())
+            }
+        },
+    )
+    .await
+}
+
+/// Requests the effective configuration of the server with the given id.
+///
+/// # Arguments
+/// * `configuration` - The client configuration.
+/// * `server_id` - The id of the server to get the configuration of.
+async fn fetch_server_configuration(
+    configuration: &Configuration,
+    server_id: &String,
+) -> anyhow::Result<String> {
+    let server = configuration
+        .get_server_by_id(server_id)
+        .with_context(|| format!("unable to find server with id {}", server_id))?;
+    let mut client = open_status_client_connection(configuration)(server.clone())
+        .await
+        .with_context(|| format!("error while connecting to {}", server_id))?;
+    let response = client
+        .get_server_configuration(ServerConfigurationRequest {})
+        .await
+        .with_context(|| format!("error while executing request on {}", server_id))?;
+    Ok(response.into_inner().configuration)
+}
+
+/// Parses the given configuration and flattens it into the values keyed by their path in the configuration, for
+/// example `deployment_configs[0].target`.
+///
+/// # Arguments
+/// * `configuration` - The configuration in TOML format.
+fn flatten_configuration(configuration: &str) -> anyhow::Result<BTreeMap<String, String>> {
+    let configuration_table = configuration
+        .parse::<toml::Table>()
+        .map_err(|err| anyhow!("unable to parse server configuration: {}", err))?;
+    let mut flattened_values = BTreeMap::new();
+    for (key, value) in configuration_table {
+        flatten_value(key, value, &mut flattened_values);
+    }
+    Ok(flattened_values)
+}
+
+/// Flattens the given value into the given map, nested values are keyed by their path below the given key.
+///
+/// # Arguments
+/// * `key` - The path of the given value in the configuration.
+/// * `value` - The value to flatten.
+/// * `flattened_values` - The map to insert the flattened values into.
+fn flatten_value(key: String, value: toml::Value, flattened_values: &mut BTreeMap<String, String>) {
+    match value {
+        toml::Value::Table(table) => {
+            for (nested_key, nested_value) in table {
+                flatten_value(
+                    format!("{key}.{nested_key}"),
+                    nested_value,
+                    flattened_values,
+                );
+            }
+        }
+        toml::Value::Array(array) => {
+            for (index, nested_value) in array.into_iter().enumerate() {
+                flatten_value(format!("{key}[{index}]"), nested_value, flattened_values);
+            }
+        }
+        value => {
+            flattened_values.insert(key, value.to_string());
+        }
+    }
+}
@@ -22,15 +22,29 @@
  * SOFTWARE.
  */
 
+use std::collections::BTreeMap;
+use std::sync::{Arc, Mutex};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use anyhow::{anyhow, bail, Context};
+use futures::future::BoxFuture;
+use futures::FutureExt;
 use log::info;
-use tonic::transport::Channel;
+use tonic::service::interceptor::InterceptedService;
+use tonic::transport::{Channel, Endpoint};
 
 use crate::config::{Configuration, TargetServer};
 use crate::easydep::status_service_client::StatusServiceClient;
-use crate::easydep::{DeployCurrentAction, StatusRequest};
+use crate::easydep::{DeployCurrentAction, DeploymentState, QueuedDeployment, StatusRequest};
+use crate::util::calendar_date::format_unix_timestamp;
+use crate::util::metadata_interceptor::MetadataInterceptor;
 use crate::util::server_connector::execute_for_servers;
 use crate::util::server_selector::select_target_servers;
 
+/// The client type for the status gRPC service, attaching the identity of the invoking user to all requests.
+pub(crate) type StatusClient =
+    StatusServiceClient<InterceptedService<Channel, MetadataInterceptor>>;
+
 /// Displays the status information of the requested servers.
 ///
 /// # Arguments
@@ -43,7 +57,7 @@ pub(crate) async fn display_servers_status(
     let target_servers = select_target_servers(&configuration, &server_ids)?;
     execute_for_servers(
         target_servers,
-        open_status_client_connection,
+        open_status_client_connection(&configuration),
         |server, mut client| async move {
             let response = client.get_status(StatusRequest {}).await?;
             let response_message = response.get_ref();
@@ -52,6 +66,7 @@ pub(crate) async fn display_servers_status(
                     DeployCurrentAction::Idle => "idling".to_string(),
                     DeployCurrentAction::Deploying => "deploying".to_string(),
                     DeployCurrentAction::RollingBack => "rolling back".to_string(),
+                    DeployCurrentAction::RerunningScripts => "rerunning scripts".to_string(),
                 })
                 .unwrap_or_else(|_| "unknown".to_string());
 
@@ -60,11 +75,39 @@ pub(crate) async fn display_servers_status(
                 "[{}] --| Easydep Version              : {}",
                 server.id, response_message.version
             );
+            info!(
+                "[{}] --| Build                        : {} with {}",
+                server.id, response_message.build_timestamp, response_message.rustc_version
+            );
             info!(
                 "[{}] --| Available Deployment Targets : {}",
                 server.id,
                 response_message.deployment_configurations.join(", ")
             );
+            info!(
+                "[{}] --| Uptime                       : {}s",
+                server.id, response_message.uptime_seconds
+            );
+            if !response_message.capabilities.is_empty() {
+                info!(
+                    "[{}] --| Capabilities                 : {}",
+                    server.id,
+                    response_message.capabilities.join(", ")
+                );
+            }
+            info!(
+                "[{}] --| Configuration                : {} (sha256: {})",
+                server.id, response_message.config_path, response_message.config_hash
+            );
+            let config_loaded_seconds_ago = SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .unwrap_or_default()
+                .as_secs()
+                .saturating_sub(response_message.config_loaded_at);
+            info!(
+                "[{}] --| Configuration Loaded         : {}s ago",
+                server.id, config_loaded_seconds_ago
+            );
             info!(
                 "[{}] --| Current Status               : {}",
                 server.id, server_status
@@ -82,6 +125,68 @@ pub(crate) async fn display_servers_status(
                 );
             }
 
+            // display the git ref that is deployed, if the deployment was not started from a release
+            if let Some(git_ref) = &response_message.git_ref {
+                info!(
+                    "[{}] --| Deploying Git Ref            : {}",
+                    server.id, git_ref
+                );
+            }
+
+            // display who started the current action, if the server is currently working on something
+            if let Some(triggered_by) = &response_message.triggered_by {
+                info!(
+                    "[{}] --| Triggered By                 : {}",
+                    server.id, triggered_by
+                );
+            }
+
+            // display when the prepared deployment expires, if the server waits for the deployment to be published
+            if let Some(prepared_expires_in_seconds) = response_message.prepared_expires_in_seconds
+            {
+                info!(
+                    "[{}] --| Prepared Deployment Expires  : in {}s",
+                    server.id, prepared_expires_in_seconds
+                );
+            }
+
+            // display the states that the current deployment went through, if a deployment is being executed
+            for transition in &response_message.state_transitions {
+                let state = DeploymentState::try_from(transition.state)
+                    .map(|state| state.as_str_name().to_lowercase())
+                    .unwrap_or_else(|_| format!("unknown ({})", transition.state));
+                info!(
+                    "[{}] --| Deployment State             : {} since {}.{:03} UTC",
+                    server.id,
+                    state,
+                    format_unix_timestamp(transition.entered_at_millis / 1000),
+                    transition.entered_at_millis % 1000
+                );
+            }
+
+            // display the reason that was given when starting the current action, if any
+            if let Some(annotation) = &response_message.annotation {
+                match &annotation.ticket_reference {
+                    Some(ticket_reference) => info!(
+                        "[{}] --| Deployment Reason            : {} [{}]",
+                        server.id, annotation.message, ticket_reference
+                    ),
+                    None => info!(
+                        "[{}] --| Deployment Reason            : {}",
+                        server.id, annotation.message
+                    ),
+                }
+            }
+
+            // display the deployments that wait for the current action to complete, if any
+            for queued_deployment in &response_message.queued_deployments {
+                info!(
+                    "[{}] --| Queued Deployment            : {}",
+                    server.id,
+                    format_queued_deployment(queued_deployment)
+                );
+            }
+
             Ok(())
         },
     )
@@ -89,14 +194,216 @@ pub(crate) async fn display_servers_status(
     Ok(())
 }
 
-/// Opens a client connection for the status gRPC service to the endpoint of the given target server.
+/// Displays the deployments of the given profile that wait in the action queue of the requested servers.
+///
+/// # Arguments
+/// * `configuration` - The client configuration.
+/// * `profile` - The profile to display the queued deployments of.
+/// * `server_ids` - The ids of the servers to display the queued deployments of.
+pub(crate) async fn display_servers_deployment_queue(
+    configuration: Configuration,
+    profile: String,
+    server_ids: Vec<String>,
+) -> anyhow::Result<()> {
+    ensure_servers_support(&configuration, &server_ids, "queue").await?;
+    let target_servers = select_target_servers(&configuration, &server_ids)?;
+    execute_for_servers(
+        target_servers,
+        open_status_client_connection(&configuration),
+        move |server, mut client| {
+            let profile = profile.clone();
+            async move {
+                let response = client.get_status(StatusRequest {}).await?.into_inner();
+                let queued_deployments = response
+                    .queued_deployments
+                    .iter()
+                    .filter(|queued_deployment| queued_deployment.profile == profile)
+                    .collect::<Vec<_>>();
+                if queued_deployments.is_empty() {
+                    info!(
+                        "[{}] --| No deployments are queued for profile {}",
+                        server.id, profile
+                    );
+                }
+                for queued_deployment in queued_deployments {
+                    info!(
+                        "[{}] --| {}",
+                        server.id,
+                        format_queued_deployment(queued_deployment)
+                    );
+                }
+                Ok(())
+            }
+        },
+    )
+    .await?;
+    Ok(())
+}
+
+/// Formats the given queued deployment into a single line containing its position, release, the user that started it
+/// and the time since when it waits, plus the reason that was given when starting it.
+///
+/// # Arguments
+/// * `queued_deployment` - The queued deployment to format.
+fn format_queued_deployment(queued_deployment: &QueuedDeployment) -> String {
+    let release = match &queued_deployment.git_ref {
+        Some(git_ref) => format!("git ref {} (id: {})", git_ref, queued_deployment.release_id),
+        None => format!(
+            "{} (id: {})",
+            queued_deployment.release_tag, queued_deployment.release_id
+        ),
+    };
+    let mut formatted_deployment = format!(
+        "#{} {}: {} by {} since {} UTC",
+        queued_deployment.position,
+        queued_deployment.profile,
+        release,
+        queued_deployment.triggered_by,
+        format_unix_timestamp(queued_deployment.queued_at)
+    );
+    if let Some(annotation) = &queued_deployment.annotation {
+        formatted_deployment.push_str(&format!(" - {}", annotation.message));
+        if let Some(ticket_reference) = &annotation.ticket_reference {
+            formatted_deployment.push_str(&format!(" [{}]", ticket_reference));
+        }
+    }
+    formatted_deployment
+}
+
+/// Displays which easydep version is running on the requested servers, grouping the servers by their version. Servers
+/// that run an older version than the newest version in the fleet are flagged as outdated.
 ///
 /// # Arguments
-/// * `server` - The target server to connect to.
-async fn open_status_client_connection(
-    server: TargetServer,
-) -> anyhow::Result<StatusServiceClient<Channel>> {
-    StatusServiceClient::connect(server.address.clone())
-        .await
-        .map_err(Into::into)
+/// * `configuration` - The client configuration.
+/// * `server_ids` - The ids of the servers to report the version of.
+pub(crate) async fn display_servers_version_report(
+    configuration: Configuration,
+    server_ids: Vec<String>,
+) -> anyhow::Result<()> {
+    let target_servers = select_target_servers(&configuration, &server_ids)?;
+    let server_versions = Arc::new(Mutex::new(BTreeMap::<String, Vec<String>>::new()));
+    let execution_result = execute_for_servers(
+        target_servers,
+        open_status_client_connection(&configuration),
+        {
+            let server_versions = server_versions.clone();
+            move |server, mut client| {
+                let server_versions = server_versions.clone();
+                async move {
+                    let response = client.get_status(StatusRequest {}).await?.into_inner();
+                    if let Ok(mut server_versions) = server_versions.lock() {
+                        server_versions
+                            .entry(response.version)
+                            .or_default()
+                            .push(server.id);
+                    }
+                    Ok(())
+                }
+            }
+        },
+    )
+    .await;
+
+    // the newest version in the fleet is expected on all servers
+    let server_versions = server_versions
+        .lock()
+        .map_err(|_| anyhow!("unable to read the server versions"))?
+        .clone();
+    let newest_version = server_versions
+        .keys()
+        .map(|version| parse_version_numbers(version))
+        .max();
+    let mut outdated_server_count = 0;
+    for (version, mut server_ids) in server_versions {
+        server_ids.sort();
+        let outdated = Some(parse_version_numbers(&version)) != newest_version;
+        if outdated {
+            outdated_server_count += server_ids.len();
+        }
+        info!(
+            "--| {} ({} servers){} : {}",
+            version,
+            server_ids.len(),
+            if outdated { " - OUTDATED" } else { "" },
+            server_ids.join(", ")
+        );
+    }
+
+    if outdated_server_count > 0 {
+        info!("{} servers run an outdated version", outdated_server_count);
+    }
+
+    // servers that could not report their version might be outdated as well
+    execution_result.context("unable to get the version of all servers")
+}
+
+/// Ensures that all requested servers support the given optional feature, as reported in their status. Commands that
+/// use the feature should check it before sending any request, so that they are not executed on some of the servers
+/// only and fail on the servers running an older version.
+///
+/// # Arguments
+/// * `configuration` - The client configuration.
+/// * `server_ids` - The ids of the servers that must support the feature.
+/// * `capability` - The name of the feature that must be supported, for example `dry-run`.
+pub(crate) async fn ensure_servers_support(
+    configuration: &Configuration,
+    server_ids: &Vec<String>,
+    capability: &'static str,
+) -> anyhow::Result<()> {
+    let target_servers = select_target_servers(configuration, server_ids)?;
+    execute_for_servers(
+        target_servers,
+        open_status_client_connection(configuration),
+        move |server, mut client| async move {
+            let response = client.get_status(StatusRequest {}).await?.into_inner();
+            if !response
+                .capabilities
+                .iter()
+                .any(|server_capability| server_capability == capability)
+            {
+                bail!(
+                    "server {} (version {}) does not support {}, upgrade it to use this command",
+                    server.id,
+                    response.version,
+                    capability
+                )
+            }
+            Ok(())
+        },
+    )
+    .await
+    .with_context(|| format!("not all servers support {}", capability))
+}
+
+/// Parses the numeric components of the given easydep version (for example `1.2.0+abc123`), ignoring the build
+/// metadata. Components that are not numeric are treated as zero.
+///
+/// # Arguments
+/// * `version` - The version to parse.
+fn parse_version_numbers(version: &str) -> Vec<u64> {
+    let version = version.split('+').next().unwrap_or_default();
+    version
+        .split('.')
+        .map(|component| component.parse::<u64>().unwrap_or_default())
+        .collect()
+}
+
+/// Get a function that opens a client connection for the status gRPC service to the endpoint of a target server.
+/// The identity of the invoking user is attached to all requests sent through the opened connections.
+///
+/// # Arguments
+/// * `configuration` - The client configuration.
+pub(crate) fn open_status_client_connection(
+    configuration: &Configuration,
+) -> impl Fn(TargetServer) -> BoxFuture<'static, anyhow::Result<StatusClient>> + Clone + Send + 'static
+{
+    let interceptor = MetadataInterceptor::new(configuration);
+    move |server| {
+        let interceptor = interceptor.for_server(&server);
+        async move {
+            let channel = Endpoint::from_shared(server.address)?.connect().await?;
+            Ok(StatusServiceClient::with_interceptor(channel, interceptor))
+        }
+        .boxed()
+    }
 }
@@ -21,51 +21,94 @@
  * OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
  * SOFTWARE.
  */
+// tonic::Status is used as the error type of all streamed entries, which is larger than clippy likes
+#![allow(clippy::result_large_err)]
+
 use anyhow::Context;
-use clap::Parser;
+use clap::error::ErrorKind;
+use clap::{CommandFactory, FromArgMatches};
+use easydep_buildinfo::{BuildInfo, VersionFormat, LONG_VERSION};
 use env_logger::Env;
 use log::{error, info};
+use std::env;
 use std::process::exit;
+use std::time::Duration;
 
-use crate::cli::{Cli, ConfigCommands, DeployCommands, RootCommands};
+use crate::cli::{
+    resolve_command_name, Cli, ConfigCommands, DeployCommands, HistoryCommands, QueueCommands,
+    RootCommands, ServerCommands, ServerConfigCommands,
+};
 use crate::config::Configuration;
 use crate::executor::config_commands::{
-    add_server_to_config, display_configured_servers, remove_server_from_config,
+    add_server_to_config, decrypt_config, display_configured_servers, encrypt_config,
+    remove_server_from_config,
 };
 use crate::executor::deployment_commands::{
+    adopt_release_on_servers, approve_deployment_on_servers, audit_deployment_on_servers,
+    cancel_deployment_on_servers, cancel_queued_deployment_on_servers,
     delete_unpublished_deployment_on_servers, display_servers_deployment_status,
-    publish_deployment_on_servers, rollback_deployment_on_servers, start_deployment_on_servers,
+    exec_command_on_servers, export_deployment_history_of_servers, list_releases_on_servers,
+    mark_release_bad_on_servers, plan_deployment_on_servers, print_deployment_history_of_servers,
+    prune_releases_on_servers, publish_deployment_on_servers, replay_recorded_streams,
+    rerun_scripts_on_servers, rollback_deployment_on_servers, run_deployment_on_servers,
+    start_deployment_on_servers, start_ref_deployment_on_servers,
+};
+use crate::executor::login_commands::login_with_device_flow;
+use crate::executor::server_commands::{
+    check_servers_github_access, diff_server_configurations, display_server_configuration,
+    push_server_configuration, reload_server_configuration, upgrade_servers,
+};
+use crate::executor::status_commands::{
+    display_servers_deployment_queue, display_servers_status, display_servers_version_report,
 };
-use crate::executor::status_commands::display_servers_status;
+use crate::util::ansi_output::configure_ansi_output;
+use crate::util::output_aggregator::configure_output_aggregation;
+use crate::util::plugin_hooks::CommandPlugins;
+use crate::util::stream_recorder::configure_stream_recording;
 
 mod cli;
 pub(crate) mod config;
 pub(crate) mod executor;
 pub(crate) mod util;
 
-const GIT_SHA: &str = env!("GIT_HASH");
-const VERSION: &str = env!("CARGO_PKG_VERSION");
-
 pub(crate) mod easydep {
     tonic::include_proto!("easydep");
 }
 
 #[tokio::main]
 async fn main() -> anyhow::Result<()> {
+    // the version can be printed without a configuration and command, which are required otherwise
+    let cli_matches = Cli::command().subcommand_required(false).get_matches();
+    if let Some(version_format) = cli_matches.get_one::<VersionFormat>("version_format") {
+        println!(
+            "{}",
+            BuildInfo::new("easydep-client", &[]).format(*version_format)
+        );
+        return Ok(());
+    }
+    if cli_matches.subcommand().is_none() {
+        Cli::command()
+            .error(
+                ErrorKind::MissingSubcommand,
+                "a subcommand is required but one was not provided",
+            )
+            .exit();
+    }
+    let cli = Cli::from_arg_matches(&cli_matches).unwrap_or_else(|err| err.exit());
+
     // initializes the logger, using the "info" level if the RUST_LOG environment variable isn't set
+    configure_ansi_output(cli.ansi_mode);
+    configure_output_aggregation(cli.aggregate_output);
     env_logger::Builder::from_env(Env::default().default_filter_or("info"))
+        .write_style(cli.ansi_mode.logger_write_style())
         .format_module_path(false)
         .format_target(false)
         .format_timestamp_secs()
         .try_init()
         .context("unable to initialize logging")?;
-    info!(
-        "Running easydep version {} (git commit {})",
-        VERSION, GIT_SHA
-    );
+    info!("Running easydep version {}", LONG_VERSION);
 
     // load & validate the configuration from the specified file path, create it if it does not exist yet
-    let cli = Cli::parse();
     let configuration = if cli.configuration_path.exists() {
         let configuration = Configuration::load_from_file(&cli.configuration_path).await?;
         configuration.validate()?;
@@ -81,6 +124,22 @@ async fn main() -> anyhow::Result<()> {
         configuration
     };
 
+    // invoke the plugins configured for the command, they can reject the execution of the command
+    let command_plugins = CommandPlugins::for_command(
+        &configuration,
+        resolve_command_name(&cli_matches),
+        env::args().skip(1).collect(),
+    );
+    if let Err(err) = command_plugins.run_before_hooks().await {
+        error!("Command was rejected by a plugin: {:#}", err);
+        exit(1)
+    }
+
+    // record the streams received from the servers into the requested file
+    if let Some(record_path) = &cli.record_path {
+        configure_stream_recording(record_path)?;
+    }
+
     // execute the requested command and display the error message if an error occurred
     let command_execution_result = match cli.command {
         RootCommands::Config { action } => match action {
@@ -92,6 +151,7 @@ async fn main() -> anyhow::Result<()> {
                 server_id,
                 server_host,
                 server_tags,
+                token,
             } => {
                 add_server_to_config(
                     configuration,
@@ -99,12 +159,49 @@ async fn main() -> anyhow::Result<()> {
                     server_id,
                     server_host,
                     server_tags,
+                    token,
                 )
                 .await
             }
             ConfigCommands::Remove { server_id } => {
                 remove_server_from_config(configuration, cli.configuration_path, server_id).await
             }
+            ConfigCommands::Encrypt => encrypt_config(configuration, cli.configuration_path).await,
+            ConfigCommands::Decrypt => decrypt_config(configuration, cli.configuration_path).await,
+        },
+        RootCommands::Login => login_with_device_flow(configuration, cli.configuration_path).await,
+        RootCommands::Server { action } => match action {
+            ServerCommands::Config { action } => match action {
+                ServerConfigCommands::Show { server_id } => {
+                    display_server_configuration(configuration, server_id).await
+                }
+                ServerConfigCommands::Diff {
+                    server_id,
+                    other_server_id,
+                } => diff_server_configurations(configuration, server_id, other_server_id).await,
+                ServerConfigCommands::Push {
+                    configuration_file,
+                    server_ids,
+                } => push_server_configuration(configuration, configuration_file, server_ids).await,
+                ServerConfigCommands::Reload { server_ids } => {
+                    reload_server_configuration(configuration, server_ids).await
+                }
+            },
+            ServerCommands::Versions { server_ids } => {
+                display_servers_version_report(configuration, server_ids).await
+            }
+            ServerCommands::Upgrade {
+                release_tag,
+                server_ids,
+            } => upgrade_servers(configuration, release_tag, server_ids).await,
+            ServerCommands::GithubCheck { server_ids } => {
+                check_servers_github_access(configuration, server_ids).await
+            }
+            ServerCommands::Exec {
+                profile,
+                command_name,
+                server_ids,
+            } => exec_command_on_servers(configuration, profile, command_name, server_ids).await,
         },
         RootCommands::Status { server_ids } => {
             display_servers_status(configuration, server_ids).await
@@ -118,15 +215,125 @@ async fn main() -> anyhow::Result<()> {
                 profile,
                 release_id,
                 server_ids,
-            } => start_deployment_on_servers(configuration, profile, release_id, server_ids).await,
+                dry_run: true,
+                ..
+            } => plan_deployment_on_servers(configuration, profile, release_id, server_ids).await,
+            DeployCommands::Start {
+                profile,
+                release_id,
+                server_ids,
+                dry_run: false,
+                start_options,
+                annotation,
+            } => {
+                start_deployment_on_servers(
+                    configuration,
+                    profile,
+                    release_id,
+                    server_ids,
+                    start_options,
+                    annotation.into_annotation(),
+                )
+                .await
+            }
+            DeployCommands::StartRef {
+                profile,
+                git_ref,
+                server_ids,
+                dry_run,
+                start_options,
+                annotation,
+            } => {
+                start_ref_deployment_on_servers(
+                    configuration,
+                    profile,
+                    git_ref,
+                    server_ids,
+                    dry_run,
+                    start_options,
+                    annotation.into_annotation(),
+                )
+                .await
+            }
+            DeployCommands::Run {
+                profile,
+                release_id,
+                server_ids,
+                health_wait_seconds,
+                start_options,
+                annotation,
+            } => {
+                run_deployment_on_servers(
+                    configuration,
+                    profile,
+                    release_id,
+                    server_ids,
+                    health_wait_seconds.map(Duration::from_secs),
+                    start_options,
+                    annotation.into_annotation(),
+                )
+                .await
+            }
             DeployCommands::Publish {
                 release_id,
                 server_ids,
-            } => publish_deployment_on_servers(configuration, release_id, server_ids).await,
+                approval_tokens,
+                annotation,
+            } => {
+                publish_deployment_on_servers(
+                    configuration,
+                    release_id,
+                    server_ids,
+                    approval_tokens,
+                    annotation.into_annotation(),
+                )
+                .await
+            }
+            DeployCommands::Approve {
+                release_id,
+                server_ids,
+                annotation,
+            } => {
+                approve_deployment_on_servers(
+                    configuration,
+                    release_id,
+                    server_ids,
+                    annotation.into_annotation(),
+                )
+                .await
+            }
             DeployCommands::Rollback {
                 profile,
                 server_ids,
-            } => rollback_deployment_on_servers(configuration, profile, server_ids).await,
+                annotation,
+            } => {
+                rollback_deployment_on_servers(
+                    configuration,
+                    profile,
+                    server_ids,
+                    annotation.into_annotation(),
+                )
+                .await
+            }
+            DeployCommands::RerunScripts {
+                profile,
+                phase,
+                server_ids,
+                annotation,
+            } => {
+                rerun_scripts_on_servers(
+                    configuration,
+                    profile,
+                    phase.into_script_phase(),
+                    server_ids,
+                    annotation.into_annotation(),
+                )
+                .await
+            }
+            DeployCommands::Replay {
+                recording_path,
+                realtime,
+            } => replay_recorded_streams(&recording_path, realtime).await,
             DeployCommands::Delete {
                 release_id,
                 server_ids,
@@ -134,8 +341,128 @@ async fn main() -> anyhow::Result<()> {
                 delete_unpublished_deployment_on_servers(configuration, release_id, server_ids)
                     .await
             }
+            DeployCommands::Cancel {
+                release_id,
+                server_ids,
+            } => cancel_deployment_on_servers(configuration, release_id, server_ids).await,
+            DeployCommands::Queue {
+                action:
+                    Some(QueueCommands::Cancel {
+                        profile,
+                        release_id,
+                        server_ids,
+                    }),
+                ..
+            } => {
+                cancel_queued_deployment_on_servers(configuration, profile, release_id, server_ids)
+                    .await
+            }
+            DeployCommands::Queue {
+                action: None,
+                profile,
+                server_ids,
+            } => {
+                // the profile is required by the argument parser if no subcommand is given
+                display_servers_deployment_queue(
+                    configuration,
+                    profile.unwrap_or_default(),
+                    server_ids,
+                )
+                .await
+            }
+            DeployCommands::Audit {
+                profile,
+                server_ids,
+            } => audit_deployment_on_servers(configuration, profile, server_ids).await,
+            DeployCommands::Blacklist {
+                profile,
+                release_id,
+                reason,
+                server_ids,
+            } => {
+                mark_release_bad_on_servers(configuration, profile, release_id, reason, server_ids)
+                    .await
+            }
+            DeployCommands::Releases {
+                profile,
+                list_options,
+                server_ids,
+            } => list_releases_on_servers(configuration, profile, list_options, server_ids).await,
+            DeployCommands::Prune {
+                profile,
+                keep_releases,
+                max_disk_usage,
+                server_ids,
+            } => {
+                prune_releases_on_servers(
+                    configuration,
+                    profile,
+                    keep_releases,
+                    max_disk_usage,
+                    server_ids,
+                )
+                .await
+            }
+            DeployCommands::History {
+                action:
+                    Some(HistoryCommands::Export {
+                        profile,
+                        format,
+                        started_after,
+                        started_before,
+                        server_ids,
+                    }),
+                ..
+            } => {
+                export_deployment_history_of_servers(
+                    configuration,
+                    profile,
+                    format,
+                    started_after,
+                    started_before,
+                    server_ids,
+                )
+                .await
+            }
+            DeployCommands::History {
+                action: None,
+                profile,
+                page_size,
+                page_token,
+                server_ids,
+            } => {
+                // the profile is required by the argument parser if no subcommand is given
+                print_deployment_history_of_servers(
+                    configuration,
+                    profile.unwrap_or_default(),
+                    page_size,
+                    page_token,
+                    server_ids,
+                )
+                .await
+            }
+            DeployCommands::Adopt {
+                profile,
+                release_id,
+                source_directory,
+                adopt_options,
+                server_ids,
+            } => {
+                adopt_release_on_servers(
+                    configuration,
+                    profile,
+                    release_id,
+                    source_directory,
+                    adopt_options,
+                    server_ids,
+                )
+                .await
+            }
         },
     };
+    command_plugins
+        .run_after_hooks(&command_execution_result)
+        .await;
     if let Err(err) = command_execution_result {
         error!("Issue occurred while executing requested command: {}", err);
         exit(1)
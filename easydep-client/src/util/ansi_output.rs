@@ -0,0 +1,131 @@
+/*
+ * This file is part of easydep, licensed under the MIT License (MIT).
+ *
+ * Copyright (c) 2024 easybill GmbH
+ *
+ * Permission is hereby granted, free of charge, to any person obtaining a copy
+ * of this software and associated documentation files (the "Software"), to deal
+ * in the Software without restriction, including without limitation the rights
+ * to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+ * copies of the Software, and to permit persons to whom the Software is
+ * furnished to do so, subject to the following conditions:
+ *
+ * The above copyright notice and this permission notice shall be included in all
+ * copies or substantial portions of the Software.
+ *
+ * THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+ * IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+ * FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+ * AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+ * LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+ * OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+ * SOFTWARE.
+ */
+
+use std::io::IsTerminal;
+use std::sync::atomic::{AtomicBool, Ordering};
+
+use clap::ValueEnum;
+use env_logger::WriteStyle;
+
+const ANSI_ESCAPE: char = '\u{1b}';
+
+/// Whether ANSI escape sequences in the output of remote actions should be kept when printing them.
+static KEEP_ANSI_ESCAPE_SEQUENCES: AtomicBool = AtomicBool::new(true);
+
+/// The modes how ANSI escape sequences (for example colors) in the output of the client are handled.
+#[derive(ValueEnum, Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum AnsiMode {
+    /// Keep the escape sequences when the output is attached to a terminal, strip them otherwise.
+    Auto,
+    /// Always keep the escape sequences.
+    Always,
+    /// Always strip the escape sequences.
+    Never,
+}
+
+impl AnsiMode {
+    /// Get if ANSI escape sequences should be written to the log output (which is written to stderr) in this mode.
+    pub fn keeps_escape_sequences(&self) -> bool {
+        match self {
+            AnsiMode::Auto => std::io::stderr().is_terminal(),
+            AnsiMode::Always => true,
+            AnsiMode::Never => false,
+        }
+    }
+
+    /// Get the write style that should be used by the logger in this mode.
+    pub fn logger_write_style(&self) -> WriteStyle {
+        if self.keeps_escape_sequences() {
+            WriteStyle::Always
+        } else {
+            WriteStyle::Never
+        }
+    }
+}
+
+/// Configures how ANSI escape sequences in the output of remote actions are handled for the current process.
+///
+/// # Arguments
+/// * `ansi_mode` - The mode that should be used for all remote output that is printed after this call.
+pub(crate) fn configure_ansi_output(ansi_mode: AnsiMode) {
+    KEEP_ANSI_ESCAPE_SEQUENCES.store(ansi_mode.keeps_escape_sequences(), Ordering::Relaxed);
+}
+
+/// Prepares the given line that was received from a remote server to be printed, removing all ANSI escape sequences
+/// from it unless they should be kept based on the configured ansi mode.
+///
+/// # Arguments
+/// * `line` - The line received from the remote server.
+///
+/// # Returns
+/// * `String` - The line that can be printed to the log output.
+pub(crate) fn prepare_remote_output_line(line: &str) -> String {
+    if KEEP_ANSI_ESCAPE_SEQUENCES.load(Ordering::Relaxed) {
+        line.to_string()
+    } else {
+        strip_ansi_escape_sequences(line)
+    }
+}
+
+/// Removes all ANSI escape sequences from the given line. Control sequences (`ESC [ ... <final byte>`), operating
+/// system commands (`ESC ] ... BEL` or `ESC ] ... ESC \`) and two character sequences are removed.
+///
+/// # Arguments
+/// * `line` - The line to remove the escape sequences from.
+fn strip_ansi_escape_sequences(line: &str) -> String {
+    let mut stripped_line = String::with_capacity(line.len());
+    let mut chars = line.chars().peekable();
+    while let Some(char) = chars.next() {
+        if char != ANSI_ESCAPE {
+            stripped_line.push(char);
+            continue;
+        }
+
+        match chars.next() {
+            // control sequence: parameters and intermediate bytes followed by a final byte in the range @ to ~
+            Some('[') => {
+                for sequence_char in chars.by_ref() {
+                    if ('@'..='~').contains(&sequence_char) {
+                        break;
+                    }
+                }
+            }
+            // operating system command: terminated by BEL or by the string terminator ESC \
+            Some(']') => {
+                while let Some(sequence_char) = chars.next() {
+                    if sequence_char == '\u{7}' {
+                        break;
+                    }
+                    if sequence_char == ANSI_ESCAPE && chars.peek() == Some(&'\\') {
+                        chars.next();
+                        break;
+                    }
+                }
+            }
+            // any other escape sequence consists of the escape character and a single following character
+            _ => {}
+        }
+    }
+    stripped_line
+}
@@ -0,0 +1,112 @@
+/*
+ * This file is part of easydep, licensed under the MIT License (MIT).
+ *
+ * Copyright (c) 2024 easybill GmbH
+ *
+ * Permission is hereby granted, free of charge, to any person obtaining a copy
+ * of this software and associated documentation files (the "Software"), to deal
+ * in the Software without restriction, including without limitation the rights
+ * to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+ * copies of the Software, and to permit persons to whom the Software is
+ * furnished to do so, subject to the following conditions:
+ *
+ * The above copyright notice and this permission notice shall be included in all
+ * copies or substantial portions of the Software.
+ *
+ * THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+ * IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+ * FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+ * AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+ * LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+ * OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+ * SOFTWARE.
+ */
+
+/// The amount of seconds in a day.
+const SECONDS_PER_DAY: u64 = 24 * 60 * 60;
+
+/// Parses the given date in the format `YYYY-MM-DD` into the unix timestamp (in seconds) of the start of the day in
+/// UTC. Dates before 1970 are rejected.
+///
+/// # Arguments
+/// * `date` - The date to parse.
+pub(crate) fn parse_calendar_date(date: &str) -> Result<u64, String> {
+    let invalid_date = || format!("invalid date {date:?}, expected YYYY-MM-DD");
+    let mut date_parts = date.splitn(3, '-');
+    let mut next_part = || {
+        date_parts
+            .next()
+            .and_then(|part| part.parse::<u64>().ok())
+            .ok_or_else(invalid_date)
+    };
+    let (year, month, day) = (next_part()?, next_part()?, next_part()?);
+    if year < 1970 || !(1..=12).contains(&month) || day < 1 || day > days_in_month(year, month) {
+        return Err(invalid_date());
+    }
+
+    let days_before_year: u64 = (1970..year).map(days_in_year).sum();
+    let days_before_month: u64 = (1..month).map(|month| days_in_month(year, month)).sum();
+    Ok((days_before_year + days_before_month + day - 1) * SECONDS_PER_DAY)
+}
+
+/// Formats the given unix timestamp (in seconds) as `YYYY-MM-DD HH:MM:SS` in UTC.
+///
+/// # Arguments
+/// * `timestamp` - The unix timestamp to format.
+pub(crate) fn format_unix_timestamp(timestamp: u64) -> String {
+    let mut remaining_days = timestamp / SECONDS_PER_DAY;
+    let seconds_of_day = timestamp % SECONDS_PER_DAY;
+    let mut year = 1970;
+    while remaining_days >= days_in_year(year) {
+        remaining_days -= days_in_year(year);
+        year += 1;
+    }
+    let mut month = 1;
+    while remaining_days >= days_in_month(year, month) {
+        remaining_days -= days_in_month(year, month);
+        month += 1;
+    }
+    format!(
+        "{:04}-{:02}-{:02} {:02}:{:02}:{:02}",
+        year,
+        month,
+        remaining_days + 1,
+        seconds_of_day / 3600,
+        seconds_of_day % 3600 / 60,
+        seconds_of_day % 60
+    )
+}
+
+/// Get the amount of days in the given year.
+///
+/// # Arguments
+/// * `year` - The year to get the amount of days of.
+fn days_in_year(year: u64) -> u64 {
+    if is_leap_year(year) {
+        366
+    } else {
+        365
+    }
+}
+
+/// Get the amount of days in the given month of the given year.
+///
+/// # Arguments
+/// * `year` - The year in which the month is located.
+/// * `month` - The month (1-12) to get the amount of days of.
+fn days_in_month(year: u64, month: u64) -> u64 {
+    match month {
+        2 if is_leap_year(year) => 29,
+        2 => 28,
+        4 | 6 | 9 | 11 => 30,
+        _ => 31,
+    }
+}
+
+/// Checks if the given year is a leap year in the gregorian calendar.
+///
+/// # Arguments
+/// * `year` - The year to check.
+fn is_leap_year(year: u64) -> bool {
+    (year % 4 == 0 && year % 100 != 0) || year % 400 == 0
+}
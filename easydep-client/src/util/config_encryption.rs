@@ -0,0 +1,184 @@
+/*
+ * This file is part of easydep, licensed under the MIT License (MIT).
+ *
+ * Copyright (c) 2024 easybill GmbH
+ *
+ * Permission is hereby granted, free of charge, to any person obtaining a copy
+ * of this software and associated documentation files (the "Software"), to deal
+ * in the Software without restriction, including without limitation the rights
+ * to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+ * copies of the Software, and to permit persons to whom the Software is
+ * furnished to do so, subject to the following conditions:
+ *
+ * The above copyright notice and this permission notice shall be included in all
+ * copies or substantial portions of the Software.
+ *
+ * THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+ * IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+ * FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+ * AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+ * LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+ * OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+ * SOFTWARE.
+ */
+
+use std::env;
+use std::fmt::{Debug, Formatter};
+use std::num::NonZeroU32;
+
+use anyhow::{anyhow, bail, Context};
+use ring::aead::{Aad, LessSafeKey, Nonce, UnboundKey, AES_256_GCM, NONCE_LEN};
+use ring::pbkdf2;
+use ring::rand::{SecureRandom, SystemRandom};
+
+/// The bytes with which encrypted configuration files start, followed by the salt, the nonce and the ciphertext.
+const ENCRYPTED_CONFIGURATION_MAGIC: &[u8] = b"EASYDEP-ENCRYPTED-CONFIG-V1\n";
+/// The length of the random salt from which the encryption key is derived together with the passphrase.
+const SALT_LEN: usize = 16;
+/// The amount of PBKDF2 iterations to derive the encryption key from the passphrase.
+const KEY_DERIVATION_ITERATIONS: u32 = 600_000;
+/// The environment variable from which the passphrase is read instead of prompting for it.
+const PASSPHRASE_ENV_VARIABLE: &str = "EASYDEP_CONFIG_PASSPHRASE";
+
+/// The passphrase with which a configuration file is encrypted. The passphrase is not included in debug output.
+#[derive(Clone)]
+pub(crate) struct ConfigurationPassphrase(String);
+
+impl Debug for ConfigurationPassphrase {
+    fn fmt(&self, formatter: &mut Formatter<'_>) -> std::fmt::Result {
+        formatter.write_str("ConfigurationPassphrase([redacted])")
+    }
+}
+
+impl ConfigurationPassphrase {
+    /// Reads the passphrase from the `EASYDEP_CONFIG_PASSPHRASE` environment variable if set, prompts for it on the
+    /// terminal otherwise. When prompting for a new passphrase it must be entered twice.
+    ///
+    /// # Arguments
+    /// * `new_passphrase` - If a new passphrase is set, in which case it is confirmed when prompting.
+    pub fn read(new_passphrase: bool) -> anyhow::Result<Self> {
+        if let Ok(passphrase) = env::var(PASSPHRASE_ENV_VARIABLE) {
+            return Self::new(passphrase);
+        }
+
+        let passphrase = rpassword::prompt_password("Configuration passphrase: ")
+            .context("unable to read configuration passphrase")?;
+        if new_passphrase {
+            let confirmed_passphrase = rpassword::prompt_password("Confirm passphrase: ")
+                .context("unable to read configuration passphrase")?;
+            if passphrase != confirmed_passphrase {
+                bail!("the entered passphrases do not match")
+            }
+        }
+        Self::new(passphrase)
+    }
+
+    /// Constructs a new passphrase, returning an error if the given passphrase is empty.
+    ///
+    /// # Arguments
+    /// * `passphrase` - The passphrase.
+    fn new(passphrase: String) -> anyhow::Result<Self> {
+        if passphrase.is_empty() {
+            bail!("the configuration passphrase must not be empty")
+        }
+        Ok(Self(passphrase))
+    }
+
+    /// Derives the key to encrypt and decrypt a configuration file from this passphrase and the given salt.
+    ///
+    /// # Arguments
+    /// * `salt` - The salt stored in the encrypted configuration file.
+    fn derive_key(&self, salt: &[u8]) -> anyhow::Result<LessSafeKey> {
+        let iterations = NonZeroU32::new(KEY_DERIVATION_ITERATIONS).unwrap();
+        let mut key_bytes = [0u8; 32];
+        pbkdf2::derive(
+            pbkdf2::PBKDF2_HMAC_SHA256,
+            iterations,
+            salt,
+            self.0.as_bytes(),
+            &mut key_bytes,
+        );
+        let unbound_key = UnboundKey::new(&AES_256_GCM, &key_bytes)
+            .map_err(|_| anyhow!("unable to construct configuration encryption key"))?;
+        Ok(LessSafeKey::new(unbound_key))
+    }
+}
+
+/// Checks if the given content of a configuration file is encrypted.
+///
+/// # Arguments
+/// * `file_content` - The content of the configuration file.
+pub(crate) fn is_encrypted_configuration(file_content: &[u8]) -> bool {
+    file_content.starts_with(ENCRYPTED_CONFIGURATION_MAGIC)
+}
+
+/// Encrypts the given serialized configuration with the given passphrase, using AES-256-GCM with a key derived from the
+/// passphrase and a random salt using PBKDF2.
+///
+/// # Arguments
+/// * `configuration` - The serialized configuration to encrypt.
+/// * `passphrase` - The passphrase to encrypt the configuration with.
+pub(crate) fn encrypt_configuration(
+    configuration: &[u8],
+    passphrase: &ConfigurationPassphrase,
+) -> anyhow::Result<Vec<u8>> {
+    let random = SystemRandom::new();
+    let mut salt = [0u8; SALT_LEN];
+    let mut nonce = [0u8; NONCE_LEN];
+    random
+        .fill(&mut salt)
+        .and_then(|_| random.fill(&mut nonce))
+        .map_err(|_| anyhow!("unable to generate random configuration encryption parameters"))?;
+
+    let mut encrypted_configuration = configuration.to_vec();
+    passphrase
+        .derive_key(&salt)?
+        .seal_in_place_append_tag(
+            Nonce::assume_unique_for_key(nonce),
+            Aad::from(ENCRYPTED_CONFIGURATION_MAGIC),
+            &mut encrypted_configuration,
+        )
+        .map_err(|_| anyhow!("unable to encrypt configuration"))?;
+    Ok([
+        ENCRYPTED_CONFIGURATION_MAGIC,
+        &salt,
+        &nonce,
+        &encrypted_configuration,
+    ]
+    .concat())
+}
+
+/// Decrypts the given content of an encrypted configuration file with the given passphrase, returning an error if the
+/// passphrase is wrong or the file was modified.
+///
+/// # Arguments
+/// * `file_content` - The content of the encrypted configuration file.
+/// * `passphrase` - The passphrase the configuration was encrypted with.
+pub(crate) fn decrypt_configuration(
+    file_content: &[u8],
+    passphrase: &ConfigurationPassphrase,
+) -> anyhow::Result<Vec<u8>> {
+    let encrypted_content = file_content
+        .strip_prefix(ENCRYPTED_CONFIGURATION_MAGIC)
+        .context("configuration file is not encrypted")?;
+    if encrypted_content.len() < SALT_LEN + NONCE_LEN {
+        bail!("encrypted configuration file is truncated")
+    }
+    let (salt, encrypted_content) = encrypted_content.split_at(SALT_LEN);
+    let (nonce, encrypted_configuration) = encrypted_content.split_at(NONCE_LEN);
+    let nonce = Nonce::try_assume_unique_for_key(nonce)
+        .map_err(|_| anyhow!("invalid nonce in encrypted configuration file"))?;
+
+    let mut configuration = encrypted_configuration.to_vec();
+    let decrypted_length = passphrase
+        .derive_key(salt)?
+        .open_in_place(
+            nonce,
+            Aad::from(ENCRYPTED_CONFIGURATION_MAGIC),
+            &mut configuration,
+        )
+        .map_err(|_| anyhow!("unable to decrypt configuration, the passphrase is wrong"))?
+        .len();
+    configuration.truncate(decrypted_length);
+    Ok(configuration)
+}
@@ -0,0 +1,264 @@
+/*
+ * This file is part of easydep, licensed under the MIT License (MIT).
+ *
+ * Copyright (c) 2024 easybill GmbH
+ *
+ * Permission is hereby granted, free of charge, to any person obtaining a copy
+ * of this software and associated documentation files (the "Software"), to deal
+ * in the Software without restriction, including without limitation the rights
+ * to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+ * copies of the Software, and to permit persons to whom the Software is
+ * furnished to do so, subject to the following conditions:
+ *
+ * The above copyright notice and this permission notice shall be included in all
+ * copies or substantial portions of the Software.
+ *
+ * THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+ * IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+ * FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+ * AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+ * LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+ * OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+ * SOFTWARE.
+ */
+
+use std::collections::BTreeMap;
+use std::path::Path;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use log::{info, warn};
+use serde::Serialize;
+use tokio::fs::OpenOptions;
+use tokio::io::AsyncWriteExt;
+
+use crate::config::Configuration;
+
+/// The timing information of a deployment action executed on a single server.
+#[derive(Serialize, Clone, Debug)]
+pub(crate) struct ServerTiming {
+    /// The id of the server.
+    pub server_id: String,
+    /// If the action completed successfully on the server.
+    pub succeeded: bool,
+    /// The time it took the server to execute the action.
+    pub total_duration: Duration,
+    /// The time it took to execute each phase of the action, keyed by the display name of the phase.
+    pub phase_durations: BTreeMap<String, Duration>,
+}
+
+/// A single entry of the telemetry report, written for each server after a fleet deployment action.
+#[derive(Serialize, Debug)]
+struct TelemetryReportEntry<'a> {
+    /// The unix timestamp (in seconds) when the entry was written.
+    timestamp: u64,
+    /// The deployment command that was executed.
+    command: &'a str,
+    /// The id of the server.
+    server_id: &'a str,
+    /// If the action completed successfully on the server.
+    succeeded: bool,
+    /// The time (in seconds) it took the server to execute the action.
+    total_seconds: f64,
+    /// The time (in seconds) it took to execute each phase of the action.
+    phase_seconds: BTreeMap<&'a str, f64>,
+}
+
+/// Collects the timing information of a deployment action executed on multiple servers.
+#[derive(Clone, Debug, Default)]
+pub(crate) struct FleetTelemetry {
+    /// The timings reported by the servers so far.
+    server_timings: Arc<Mutex<Vec<ServerTiming>>>,
+}
+
+impl FleetTelemetry {
+    /// Records the timing information of a server.
+    ///
+    /// # Arguments
+    /// * `server_timing` - The timing information to record.
+    pub fn record(&self, server_timing: ServerTiming) {
+        if let Ok(mut server_timings) = self.server_timings.lock() {
+            server_timings.push(server_timing);
+        }
+    }
+
+    /// Displays the aggregated timing statistics of all recorded servers and appends the timings to the telemetry
+    /// report, if one is configured. Nothing is done if no timings were recorded.
+    ///
+    /// # Arguments
+    /// * `configuration` - The client configuration.
+    /// * `command` - The name of the deployment command that was executed.
+    pub async fn finish(&self, configuration: &Configuration, command: &str) {
+        let server_timings = match self.server_timings.lock() {
+            Ok(server_timings) => server_timings.clone(),
+            Err(_) => return,
+        };
+        if server_timings.is_empty() {
+            return;
+        }
+
+        display_summary(&server_timings);
+        if let Some(report_path) = &configuration.telemetry_report {
+            if let Err(err) = append_to_report(report_path, command, &server_timings).await {
+                warn!(
+                    "Unable to write telemetry report {}: {:?}",
+                    report_path, err
+                );
+            }
+        }
+    }
+}
+
+/// Displays the aggregated timing statistics of the given server timings.
+///
+/// # Arguments
+/// * `server_timings` - The timings to display the statistics of, must not be empty.
+fn display_summary(server_timings: &[ServerTiming]) {
+    let succeeded_count = server_timings
+        .iter()
+        .filter(|server_timing| server_timing.succeeded)
+        .count();
+    info!(
+        "--| Fleet Summary        : {} server(s), {} succeeded, {} failed",
+        server_timings.len(),
+        succeeded_count,
+        server_timings.len() - succeeded_count
+    );
+
+    let mut sorted_timings = server_timings.iter().collect::<Vec<&ServerTiming>>();
+    sorted_timings.sort_by_key(|server_timing| server_timing.total_duration);
+    if let (Some(fastest), Some(slowest)) = (sorted_timings.first(), sorted_timings.last()) {
+        info!(
+            "--| Fastest Server       : {} ({:.1}s)",
+            fastest.server_id,
+            fastest.total_duration.as_secs_f64()
+        );
+        info!(
+            "--| Slowest Server       : {} ({:.1}s)",
+            slowest.server_id,
+            slowest.total_duration.as_secs_f64()
+        );
+    }
+    let total_durations = server_timings
+        .iter()
+        .map(|server_timing| server_timing.total_duration)
+        .collect();
+    info!(
+        "--| Median Duration      : {:.1}s",
+        median(total_durations).as_secs_f64()
+    );
+
+    let mut phase_durations = BTreeMap::<&String, Vec<Duration>>::new();
+    for server_timing in server_timings {
+        for (phase, duration) in &server_timing.phase_durations {
+            phase_durations.entry(phase).or_default().push(*duration);
+        }
+    }
+    for (phase, durations) in phase_durations {
+        info!(
+            "--| Median {:<14}: {:.1}s",
+            phase,
+            median(durations).as_secs_f64()
+        );
+    }
+}
+
+/// Appends the given server timings to the telemetry report at the given path. Reports with a `.csv` extension are
+/// written as csv (the header is written when the file is created), all other reports are written as json lines.
+///
+/// # Arguments
+/// * `report_path` - The path of the report file.
+/// * `command` - The name of the deployment command that was executed.
+/// * `server_timings` - The timings to append to the report.
+async fn append_to_report(
+    report_path: &str,
+    command: &str,
+    server_timings: &[ServerTiming],
+) -> anyhow::Result<()> {
+    let report_path = Path::new(report_path);
+    let write_csv = report_path
+        .extension()
+        .is_some_and(|extension| extension.eq_ignore_ascii_case("csv"));
+    let write_csv_header = write_csv && !report_path.exists();
+    let timestamp = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|duration| duration.as_secs())
+        .unwrap_or_default();
+
+    let mut report_content = String::new();
+    if write_csv_header {
+        report_content
+            .push_str("timestamp,command,server_id,succeeded,total_seconds,phase_seconds\n");
+    }
+    for server_timing in server_timings {
+        let entry = TelemetryReportEntry {
+            timestamp,
+            command,
+            server_id: &server_timing.server_id,
+            succeeded: server_timing.succeeded,
+            total_seconds: server_timing.total_duration.as_secs_f64(),
+            phase_seconds: server_timing
+                .phase_durations
+                .iter()
+                .map(|(phase, duration)| (phase.as_str(), duration.as_secs_f64()))
+                .collect(),
+        };
+        if write_csv {
+            let phase_seconds = entry
+                .phase_seconds
+                .iter()
+                .map(|(phase, seconds)| format!("{}={:.3}", phase, seconds))
+                .collect::<Vec<String>>()
+                .join(";");
+            report_content.push_str(&format!(
+                "{},{},{},{},{:.3},{}\n",
+                entry.timestamp,
+                escape_csv_value(entry.command),
+                escape_csv_value(entry.server_id),
+                entry.succeeded,
+                entry.total_seconds,
+                escape_csv_value(&phase_seconds)
+            ));
+        } else {
+            report_content.push_str(&serde_json::to_string(&entry)?);
+            report_content.push('\n');
+        }
+    }
+
+    let mut report_file = OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(report_path)
+        .await?;
+    report_file.write_all(report_content.as_bytes()).await?;
+    Ok(())
+}
+
+/// Get the median of the given durations, `Duration::ZERO` if no durations are given.
+///
+/// # Arguments
+/// * `durations` - The durations to get the median of.
+fn median(mut durations: Vec<Duration>) -> Duration {
+    if durations.is_empty() {
+        return Duration::ZERO;
+    }
+    durations.sort();
+    let middle = durations.len() / 2;
+    if durations.len() % 2 == 0 {
+        (durations[middle - 1] + durations[middle]) / 2
+    } else {
+        durations[middle]
+    }
+}
+
+/// Quotes the given value for a csv file if it contains characters with a special meaning.
+///
+/// # Arguments
+/// * `value` - The value to escape.
+fn escape_csv_value(value: &str) -> String {
+    if value.contains([',', '"', '\n']) {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value.to_string()
+    }
+}
@@ -0,0 +1,128 @@
+/*
+ * This file is part of easydep, licensed under the MIT License (MIT).
+ *
+ * Copyright (c) 2024 easybill GmbH
+ *
+ * Permission is hereby granted, free of charge, to any person obtaining a copy
+ * of this software and associated documentation files (the "Software"), to deal
+ * in the Software without restriction, including without limitation the rights
+ * to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+ * copies of the Software, and to permit persons to whom the Software is
+ * furnished to do so, subject to the following conditions:
+ *
+ * The above copyright notice and this permission notice shall be included in all
+ * copies or substantial portions of the Software.
+ *
+ * THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+ * IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+ * FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+ * AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+ * LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+ * OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+ * SOFTWARE.
+ */
+
+use std::env;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use log::warn;
+use tonic::metadata::MetadataValue;
+use tonic::service::Interceptor;
+use tonic::{Request, Status};
+
+use crate::config::{Configuration, TargetServer};
+
+/// The metadata key in which the name of the operating system user invoking the client is sent.
+const USER_METADATA_KEY: &str = "easydep-user";
+/// The metadata key in which the operator name from the client configuration is sent.
+const OPERATOR_METADATA_KEY: &str = "easydep-operator";
+
+/// An interceptor that attaches the identity of the invoking user and the access token (if any) to every request sent
+/// to a server. The static api token of a server takes precedence over the access token.
+#[derive(Clone, Debug)]
+pub(crate) struct MetadataInterceptor {
+    /// The token sent to the server, either the access token obtained from the OpenID Connect provider (if logged in) or
+    /// the static api token of the server.
+    access_token: Option<String>,
+    /// The name of the operating system user that invoked the client.
+    os_user: Option<String>,
+    /// The operator name from the client configuration, if configured.
+    operator: Option<String>,
+}
+
+impl MetadataInterceptor {
+    /// Constructs a new interceptor, resolving the invoking user from the environment.
+    ///
+    /// # Arguments
+    /// * `configuration` - The client configuration, used to get the configured operator name.
+    pub fn new(configuration: &Configuration) -> Self {
+        let os_user = env::var("USER")
+            .or_else(|_| env::var("USERNAME"))
+            .ok()
+            .filter(|user| !user.trim().is_empty());
+
+        // the token is still sent when expired, the server will reject the request with a descriptive error
+        let access_token = configuration.oidc.as_ref().and_then(|oidc_settings| {
+            let now = SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map(|duration| duration.as_secs())
+                .unwrap_or_default();
+            if oidc_settings
+                .access_token_expires_at
+                .is_some_and(|expires_at| expires_at <= now)
+            {
+                warn!(
+                    "The stored access token is expired, use the login command to obtain a new one"
+                );
+            }
+            oidc_settings.access_token.clone()
+        });
+        Self {
+            access_token,
+            os_user,
+            operator: configuration.operator.clone(),
+        }
+    }
+
+    /// Get an interceptor for requests sent to the given server, which sends the static api token of the server
+    /// instead of the access token if one is configured for the server.
+    ///
+    /// # Arguments
+    /// * `server` - The server to which the requests are sent.
+    pub fn for_server(&self, server: &TargetServer) -> Self {
+        let mut interceptor = self.clone();
+        if let Some(token) = &server.token {
+            interceptor.access_token = Some(token.clone());
+        }
+        interceptor
+    }
+}
+
+impl Interceptor for MetadataInterceptor {
+    fn call(&mut self, mut request: Request<()>) -> Result<Request<()>, Status> {
+        // values that cannot be represented as ascii metadata values are silently dropped
+        let metadata = request.metadata_mut();
+        if let Some(os_user) = self
+            .os_user
+            .as_ref()
+            .and_then(|os_user| MetadataValue::try_from(os_user.as_str()).ok())
+        {
+            metadata.insert(USER_METADATA_KEY, os_user);
+        }
+        if let Some(operator) = self
+            .operator
+            .as_ref()
+            .and_then(|operator| MetadataValue::try_from(operator.as_str()).ok())
+        {
+            metadata.insert(OPERATOR_METADATA_KEY, operator);
+        }
+        if let Some(authorization) = self
+            .access_token
+            .as_ref()
+            .and_then(|token| MetadataValue::try_from(format!("Bearer {}", token)).ok())
+        {
+            metadata.insert("authorization", authorization);
+        }
+        Ok(request)
+    }
+}
@@ -22,6 +22,14 @@
  * SOFTWARE.
  */
 
+pub(crate) mod ansi_output;
+pub(crate) mod calendar_date;
+pub(crate) mod config_encryption;
+pub(crate) mod deployment_telemetry;
 pub(crate) mod input_validator;
+pub(crate) mod metadata_interceptor;
+pub(crate) mod output_aggregator;
+pub(crate) mod plugin_hooks;
 pub(crate) mod server_connector;
 pub(crate) mod server_selector;
+pub(crate) mod stream_recorder;
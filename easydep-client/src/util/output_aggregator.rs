@@ -0,0 +1,239 @@
+/*
+ * This file is part of easydep, licensed under the MIT License (MIT).
+ *
+ * Copyright (c) 2024 easybill GmbH
+ *
+ * Permission is hereby granted, free of charge, to any person obtaining a copy
+ * of this software and associated documentation files (the "Software"), to deal
+ * in the Software without restriction, including without limitation the rights
+ * to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+ * copies of the Software, and to permit persons to whom the Software is
+ * furnished to do so, subject to the following conditions:
+ *
+ * The above copyright notice and this permission notice shall be included in all
+ * copies or substantial portions of the Software.
+ *
+ * THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+ * IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+ * FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+ * AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+ * LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+ * OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+ * SOFTWARE.
+ */
+
+use std::collections::{BTreeMap, HashMap};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+
+use log::{log, Level};
+
+/// Whether the identical output lines of the servers should be collapsed into a single line.
+static AGGREGATE_OUTPUT: AtomicBool = AtomicBool::new(false);
+
+/// Prints the output lines received from the servers executing an action. If aggregation is enabled, the lines that
+/// multiple servers print at the same position of their output are collapsed into one line which names the amount of
+/// servers that printed it (for example `[18/20 servers @ Init Script] --| <line>`), lines that only one server printed
+/// are displayed as usual. A position is printed once every server reached it or finished, aggregated output is
+/// therefore only displayed as fast as the slowest server produces it.
+#[derive(Clone, Debug)]
+pub(crate) struct OutputAggregator {
+    /// The state of the aggregation, `None` if the lines are printed directly.
+    state: Option<Arc<Mutex<AggregationState>>>,
+}
+
+/// The lines that are waiting to be printed and the progress of the servers through their output.
+#[derive(Debug)]
+struct AggregationState {
+    /// The amount of servers whose output is aggregated.
+    server_count: usize,
+    /// The position of the next line that each server prints, `usize::MAX` for servers whose output ended.
+    next_positions: HashMap<String, usize>,
+    /// The lines that were not printed yet, keyed by their position in the output of the servers.
+    pending_lines: BTreeMap<usize, Vec<OutputLine>>,
+    /// The position of the next lines that are printed.
+    next_printed_position: usize,
+}
+
+/// A line of output printed by a server.
+#[derive(Debug)]
+struct OutputLine {
+    /// The id of the server that printed the line.
+    server_id: String,
+    /// The display name of the action that printed the line, if the line belongs to an action.
+    action_name: Option<String>,
+    /// The level at which the line is logged.
+    level: Level,
+    /// The content of the line.
+    content: String,
+}
+
+impl OutputAggregator {
+    /// Constructs a new output aggregator for the given servers. The lines are printed directly if aggregation was not
+    /// enabled or less than two servers are targeted.
+    ///
+    /// # Arguments
+    /// * `server_count` - The amount of servers whose output is printed.
+    pub fn for_servers(server_count: usize) -> Self {
+        let state = (AGGREGATE_OUTPUT.load(Ordering::Relaxed) && server_count > 1).then(|| {
+            Arc::new(Mutex::new(AggregationState {
+                server_count,
+                next_positions: HashMap::new(),
+                pending_lines: BTreeMap::new(),
+                next_printed_position: 0,
+            }))
+        });
+        Self { state }
+    }
+
+    /// Prints the given line of output of the given server, or keeps it until all servers reached its position.
+    ///
+    /// # Arguments
+    /// * `server_id` - The id of the server that printed the line.
+    /// * `action_name` - The display name of the action that printed the line, if the line belongs to an action.
+    /// * `level` - The level at which the line should be logged.
+    /// * `content` - The content of the line.
+    pub fn print_line(
+        &self,
+        server_id: &str,
+        action_name: Option<String>,
+        level: Level,
+        content: String,
+    ) {
+        let output_line = OutputLine {
+            server_id: server_id.to_string(),
+            action_name,
+            level,
+            content,
+        };
+        let Some(mut state) = self.lock_state() else {
+            print_output_lines(&[&output_line], 1);
+            return;
+        };
+
+        let next_position = state
+            .next_positions
+            .entry(output_line.server_id.clone())
+            .or_default();
+        let position = *next_position;
+        *next_position = next_position.saturating_add(1);
+        state
+            .pending_lines
+            .entry(position)
+            .or_default()
+            .push(output_line);
+        state.print_completed_positions();
+    }
+
+    /// Marks the output of the given server as ended, the positions the server did not reach are no longer waiting for
+    /// it.
+    ///
+    /// # Arguments
+    /// * `server_id` - The id of the server whose output ended.
+    pub fn finish_server(&self, server_id: &str) {
+        if let Some(mut state) = self.lock_state() {
+            state
+                .next_positions
+                .insert(server_id.to_string(), usize::MAX);
+            state.print_completed_positions();
+        }
+    }
+
+    /// Prints all lines that are still waiting for servers to reach their position, for example because the
+    /// connection to a server could not be established.
+    pub fn finish(&self) {
+        if let Some(mut state) = self.lock_state() {
+            let pending_lines = std::mem::take(&mut state.pending_lines);
+            let server_count = state.server_count;
+            for output_lines in pending_lines.into_values() {
+                print_aggregated_lines(output_lines, server_count);
+            }
+        }
+    }
+
+    /// Locks the aggregation state, returning `None` if aggregation is disabled.
+    fn lock_state(&self) -> Option<std::sync::MutexGuard<'_, AggregationState>> {
+        self.state.as_ref().and_then(|state| state.lock().ok())
+    }
+}
+
+impl AggregationState {
+    /// Prints the lines at all positions that every server either passed or will never reach as its output ended.
+    fn print_completed_positions(&mut self) {
+        while self.pending_lines.contains_key(&self.next_printed_position) {
+            let passed_servers = self
+                .next_positions
+                .values()
+                .filter(|next_position| **next_position > self.next_printed_position)
+                .count();
+            if passed_servers < self.server_count {
+                return;
+            }
+            if let Some(output_lines) = self.pending_lines.remove(&self.next_printed_position) {
+                print_aggregated_lines(output_lines, self.server_count);
+            }
+            self.next_printed_position += 1;
+        }
+    }
+}
+
+/// Prints the given lines that were printed by the servers at the same position of their output, collapsing the
+/// identical lines.
+///
+/// # Arguments
+/// * `output_lines` - The lines printed at the same position.
+/// * `server_count` - The amount of servers whose output is aggregated.
+fn print_aggregated_lines(output_lines: Vec<OutputLine>, server_count: usize) {
+    let mut line_groups = Vec::<Vec<&OutputLine>>::new();
+    for output_line in &output_lines {
+        let line_group = line_groups.iter_mut().find(|line_group| {
+            let grouped_line = line_group[0];
+            grouped_line.level == output_line.level
+                && grouped_line.action_name == output_line.action_name
+                && grouped_line.content == output_line.content
+        });
+        match line_group {
+            Some(line_group) => line_group.push(output_line),
+            None => line_groups.push(vec![output_line]),
+        }
+    }
+    for line_group in line_groups {
+        print_output_lines(&line_group, server_count);
+    }
+}
+
+/// Prints the given identical lines printed by one or more servers.
+///
+/// # Arguments
+/// * `output_lines` - The identical lines to print, must not be empty.
+/// * `server_count` - The amount of servers whose output is aggregated.
+fn print_output_lines(output_lines: &[&OutputLine], server_count: usize) {
+    let output_line = output_lines[0];
+    let origin = match output_lines.len() {
+        1 => output_line.server_id.clone(),
+        line_count => format!("{}/{} servers", line_count, server_count),
+    };
+    match &output_line.action_name {
+        Some(action_name) => log!(
+            output_line.level,
+            "[{} @ {}] --| {}",
+            origin,
+            action_name,
+            output_line.content
+        ),
+        None => log!(
+            output_line.level,
+            "[{}] --| {}",
+            origin,
+            output_line.content
+        ),
+    }
+}
+
+/// Configures if the identical output lines of the servers are collapsed for the current process.
+///
+/// # Arguments
+/// * `aggregate_output` - If the output lines should be collapsed.
+pub(crate) fn configure_output_aggregation(aggregate_output: bool) {
+    AGGREGATE_OUTPUT.store(aggregate_output, Ordering::Relaxed);
+}
@@ -0,0 +1,259 @@
+/*
+ * This file is part of easydep, licensed under the MIT License (MIT).
+ *
+ * Copyright (c) 2024 easybill GmbH
+ *
+ * Permission is hereby granted, free of charge, to any person obtaining a copy
+ * of this software and associated documentation files (the "Software"), to deal
+ * in the Software without restriction, including without limitation the rights
+ * to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+ * copies of the Software, and to permit persons to whom the Software is
+ * furnished to do so, subject to the following conditions:
+ *
+ * The above copyright notice and this permission notice shall be included in all
+ * copies or substantial portions of the Software.
+ *
+ * THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+ * IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+ * FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+ * AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+ * LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+ * OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+ * SOFTWARE.
+ */
+
+use std::process::Stdio;
+use std::time::Duration;
+
+use anyhow::{bail, Context};
+use log::{info, warn};
+use serde::{Deserialize, Serialize};
+use tokio::io::AsyncWriteExt;
+use tokio::process::Command;
+use tokio::time::timeout;
+
+use crate::config::{Configuration, PluginConfiguration};
+
+/// The points in the execution of a command at which plugins are invoked.
+#[derive(Serialize, Clone, Copy, Debug)]
+#[serde(rename_all = "snake_case")]
+enum PluginHook {
+    /// Invoked before the command is executed, the plugin can reject the execution of the command.
+    BeforeCommand,
+    /// Invoked after the command was executed, with the result of the command.
+    AfterCommand,
+}
+
+/// The json document that is written to the stdin of a plugin.
+#[derive(Serialize, Debug)]
+struct PluginRequest<'a> {
+    /// The hook for which the plugin is invoked.
+    hook: PluginHook,
+    /// The name of the executed command, for example `deploy start`.
+    command: &'a str,
+    /// The arguments that were passed to the client.
+    arguments: &'a [String],
+    /// The operator name from the client configuration, if configured.
+    operator: Option<&'a str>,
+    /// If the command completed successfully, only present for the after command hook.
+    succeeded: Option<bool>,
+    /// The error that occurred while executing the command, only present for the after command hook.
+    error: Option<String>,
+}
+
+/// The json document that a plugin can print to stdout.
+#[derive(Deserialize, Debug, Default)]
+struct PluginResponse {
+    /// If the command is allowed to be executed, only evaluated for the before command hook. Defaults to `true`.
+    allow: Option<bool>,
+    /// A message that is displayed to the user.
+    message: Option<String>,
+}
+
+/// The result of a plugin invocation.
+#[derive(Debug)]
+struct PluginOutcome {
+    /// If the plugin exited successfully and did not reject the command.
+    allowed: bool,
+    /// The message returned by the plugin, if any.
+    message: Option<String>,
+}
+
+/// The plugins that are invoked for the command that is executed by the client.
+#[derive(Debug)]
+pub(crate) struct CommandPlugins {
+    /// The plugins that are configured for the command.
+    plugins: Vec<PluginConfiguration>,
+    /// The name of the executed command, for example `deploy start`.
+    command: String,
+    /// The arguments that were passed to the client.
+    arguments: Vec<String>,
+    /// The operator name from the client configuration, if configured.
+    operator: Option<String>,
+}
+
+impl CommandPlugins {
+    /// Collects the plugins from the given configuration that should be invoked for the given command.
+    ///
+    /// # Arguments
+    /// * `configuration` - The client configuration containing the configured plugins.
+    /// * `command` - The name of the executed command, for example `deploy start`.
+    /// * `arguments` - The arguments that were passed to the client.
+    pub fn for_command(
+        configuration: &Configuration,
+        command: String,
+        arguments: Vec<String>,
+    ) -> Self {
+        let plugins = configuration
+            .plugins
+            .iter()
+            .filter(|plugin| {
+                plugin.commands.is_empty()
+                    || plugin
+                        .commands
+                        .iter()
+                        .any(|plugin_command| matches_command(plugin_command, &command))
+            })
+            .cloned()
+            .collect();
+        Self {
+            plugins,
+            command,
+            arguments,
+            operator: configuration.operator.clone(),
+        }
+    }
+
+    /// Invokes the before command hook of all plugins, returning an error if one of the plugins rejected the command
+    /// or could not be invoked. A failing plugin rejects the command, so that checks cannot be bypassed accidentally.
+    pub async fn run_before_hooks(&self) -> anyhow::Result<()> {
+        let request = self.build_request(PluginHook::BeforeCommand, None);
+        for plugin in &self.plugins {
+            let outcome = invoke_plugin(plugin, &request)
+                .await
+                .with_context(|| format!("unable to invoke plugin {}", plugin.name))?;
+            if !outcome.allowed {
+                bail!(
+                    "plugin {} rejected command {}: {}",
+                    plugin.name,
+                    self.command,
+                    outcome.message.as_deref().unwrap_or("no reason given")
+                )
+            }
+            if let Some(message) = &outcome.message {
+                info!("[plugin {}] --| {}", plugin.name, message);
+            }
+        }
+        Ok(())
+    }
+
+    /// Invokes the after command hook of all plugins. Failing plugins are only reported, as the command was already
+    /// executed at this point.
+    ///
+    /// # Arguments
+    /// * `command_result` - The result of the executed command.
+    pub async fn run_after_hooks(&self, command_result: &anyhow::Result<()>) {
+        let request = self.build_request(PluginHook::AfterCommand, Some(command_result));
+        for plugin in &self.plugins {
+            match invoke_plugin(plugin, &request).await {
+                Ok(outcome) => {
+                    if let Some(message) = &outcome.message {
+                        info!("[plugin {}] --| {}", plugin.name, message);
+                    }
+                    if !outcome.allowed {
+                        warn!(
+                            "Plugin {} reported a failure after the command",
+                            plugin.name
+                        );
+                    }
+                }
+                Err(err) => warn!("Unable to invoke plugin {}: {:?}", plugin.name, err),
+            }
+        }
+    }
+
+    /// Builds the request that is passed to the plugins for the given hook.
+    ///
+    /// # Arguments
+    /// * `hook` - The hook for which the plugins are invoked.
+    /// * `command_result` - The result of the executed command, if the command was executed already.
+    fn build_request(
+        &self,
+        hook: PluginHook,
+        command_result: Option<&anyhow::Result<()>>,
+    ) -> PluginRequest<'_> {
+        PluginRequest {
+            hook,
+            command: &self.command,
+            arguments: &self.arguments,
+            operator: self.operator.as_deref(),
+            succeeded: command_result.map(|result| result.is_ok()),
+            error: command_result
+                .and_then(|result| result.as_ref().err())
+                .map(|err| err.to_string()),
+        }
+    }
+}
+
+/// Invokes the given plugin with the given request on stdin and parses the response of the plugin from stdout. An
+/// empty stdout is treated as an empty response.
+///
+/// # Arguments
+/// * `plugin` - The plugin to invoke.
+/// * `request` - The request to pass to the plugin.
+///
+/// # Returns
+/// * `anyhow::Result<PluginOutcome>` - The outcome of the invocation, `Err` if the plugin could not be invoked.
+async fn invoke_plugin(
+    plugin: &PluginConfiguration,
+    request: &PluginRequest<'_>,
+) -> anyhow::Result<PluginOutcome> {
+    let request_json = serde_json::to_vec(request).context("unable to serialize plugin request")?;
+    let mut plugin_process = Command::new(&plugin.executable)
+        .args(&plugin.args)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::inherit())
+        .kill_on_drop(true)
+        .spawn()
+        .with_context(|| format!("unable to spawn {}", plugin.executable))?;
+    if let Some(mut stdin) = plugin_process.stdin.take() {
+        // plugins are not required to read the request, ignore that the pipe might be closed already
+        stdin.write_all(&request_json).await.ok();
+    }
+
+    let output = timeout(
+        Duration::from_secs(plugin.timeout_seconds),
+        plugin_process.wait_with_output(),
+    )
+    .await
+    .with_context(|| format!("plugin did not complete within {}s", plugin.timeout_seconds))??;
+    let stdout_output = String::from_utf8_lossy(&output.stdout);
+    let response = if stdout_output.trim().is_empty() {
+        PluginResponse::default()
+    } else {
+        serde_json::from_str::<PluginResponse>(&stdout_output)
+            .context("unable to parse plugin response")?
+    };
+    let message = match response.message {
+        Some(message) => Some(message),
+        None if !output.status.success() => Some(format!("plugin exited with {}", output.status)),
+        None => None,
+    };
+    Ok(PluginOutcome {
+        allowed: output.status.success() && response.allow.unwrap_or(true),
+        message,
+    })
+}
+
+/// Checks if the given configured plugin command matches the executed command, either exactly or as a parent command.
+///
+/// # Arguments
+/// * `plugin_command` - The command configured for the plugin, for example `deploy`.
+/// * `command` - The executed command, for example `deploy start`.
+fn matches_command(plugin_command: &str, command: &str) -> bool {
+    let mut command_parts = command.split_whitespace();
+    plugin_command
+        .split_whitespace()
+        .all(|plugin_command_part| command_parts.next() == Some(plugin_command_part))
+}
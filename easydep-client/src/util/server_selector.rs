@@ -22,7 +22,7 @@
  * SOFTWARE.
  */
 
-use std::collections::HashSet;
+use std::collections::{BTreeMap, HashSet};
 
 use anyhow::Context;
 
@@ -63,3 +63,25 @@ pub(crate) fn select_target_servers<'a>(
 
     Ok(target_servers)
 }
+
+/// Groups the given servers by their publish order (configured using `order:<n>` tags). The groups are ordered by the
+/// publish order, servers without an order tag are published last.
+///
+/// # Arguments
+/// * `target_servers` - The servers to group by their publish order.
+///
+/// # Returns
+/// * `Vec<HashSet<&TargetServer>>` - The servers that are published together, in the order they are published.
+pub(crate) fn group_servers_by_publish_order<'a>(
+    target_servers: HashSet<&'a TargetServer>,
+) -> anyhow::Result<Vec<HashSet<&'a TargetServer>>> {
+    let mut publish_groups = BTreeMap::<u32, HashSet<&'a TargetServer>>::new();
+    for target_server in target_servers {
+        let publish_order = target_server.get_publish_order()?.unwrap_or(u32::MAX);
+        publish_groups
+            .entry(publish_order)
+            .or_default()
+            .insert(target_server);
+    }
+    Ok(publish_groups.into_values().collect())
+}
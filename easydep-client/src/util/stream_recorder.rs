@@ -0,0 +1,217 @@
+/*
+ * This file is part of easydep, licensed under the MIT License (MIT).
+ *
+ * Copyright (c) 2024 easybill GmbH
+ *
+ * Permission is hereby granted, free of charge, to any person obtaining a copy
+ * of this software and associated documentation files (the "Software"), to deal
+ * in the Software without restriction, including without limitation the rights
+ * to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+ * copies of the Software, and to permit persons to whom the Software is
+ * furnished to do so, subject to the following conditions:
+ *
+ * The above copyright notice and this permission notice shall be included in all
+ * copies or substantial portions of the Software.
+ *
+ * THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+ * IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+ * FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+ * AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+ * LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+ * OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+ * SOFTWARE.
+ */
+
+use std::fs::File;
+use std::io::Write;
+use std::path::Path;
+use std::sync::{Mutex, OnceLock};
+use std::time::{Duration, Instant};
+
+use anyhow::Context;
+use log::warn;
+use serde::{Deserialize, Serialize};
+use tonic::{Code, Status};
+
+use crate::easydep::{ExecutedActionEntry, LogEntry};
+
+/// The recorder into which the streams received from the servers are written, if recording was requested.
+static STREAM_RECORDER: OnceLock<StreamRecorder> = OnceLock::new();
+
+/// Writes the entries of the streams received from the servers into a file, one json object per line.
+struct StreamRecorder {
+    /// The file into which the recorded entries are written.
+    file: Mutex<File>,
+    /// The time at which the recording was started, the entries are recorded relative to it.
+    started_at: Instant,
+}
+
+/// An entry of a stream received from a server, as stored in a recording.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub(crate) struct RecordedStreamEntry {
+    /// The id of the server from which the entry was received.
+    pub server_id: String,
+    /// The time (in milliseconds) after the start of the recording at which the entry was received.
+    pub elapsed_millis: u64,
+    /// The executed action entry that was received, if the server sent one.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub entry: Option<RecordedActionEntry>,
+    /// The error status that was received, if the server sent one.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error: Option<RecordedErrorStatus>,
+}
+
+/// An executed action entry stored in a recording.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub(crate) struct RecordedActionEntry {
+    /// The id of the release that was processed.
+    pub release_id: u64,
+    /// The action that was executed.
+    pub current_action: i32,
+    /// The status of the action that was executed.
+    pub action_status: i32,
+    /// The type of the stream on which the log line was written, if the entry has a log line.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub stream_type: Option<i32>,
+    /// The log line of the entry, if any.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub content: Option<String>,
+    /// The progress reported by the script, if any.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub progress_percent: Option<u32>,
+    /// The phase reported by the script, if any.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub phase: Option<String>,
+}
+
+/// An error status stored in a recording.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub(crate) struct RecordedErrorStatus {
+    /// The gRPC status code.
+    pub code: i32,
+    /// The message of the status.
+    pub message: String,
+}
+
+impl RecordedStreamEntry {
+    /// Get the time after the start of the recording at which this entry was received.
+    pub fn get_elapsed(&self) -> Duration {
+        Duration::from_millis(self.elapsed_millis)
+    }
+
+    /// Converts this recorded entry back into the entry of the stream it was received in.
+    pub fn into_stream_entry(self) -> Result<ExecutedActionEntry, Status> {
+        match (self.entry, self.error) {
+            (Some(entry), _) => Ok(ExecutedActionEntry {
+                release_id: entry.release_id,
+                current_action: entry.current_action,
+                action_status: entry.action_status,
+                action_log_entry: entry.content.map(|content| LogEntry {
+                    stream_type: entry.stream_type.unwrap_or_default(),
+                    content,
+                }),
+                progress_percent: entry.progress_percent,
+                phase: entry.phase,
+            }),
+            (None, Some(error)) => Err(Status::new(Code::from(error.code), error.message)),
+            (None, None) => Err(Status::data_loss(
+                "recorded entry has neither an entry nor an error",
+            )),
+        }
+    }
+}
+
+/// Starts recording the streams received from the servers into the given file, which is truncated if it exists.
+///
+/// # Arguments
+/// * `file_path` - The path of the file to record the streams into.
+pub(crate) fn configure_stream_recording(file_path: &Path) -> anyhow::Result<()> {
+    let file = File::create(file_path)
+        .with_context(|| format!("unable to create stream recording file {file_path:?}"))?;
+    let stream_recorder = StreamRecorder {
+        file: Mutex::new(file),
+        started_at: Instant::now(),
+    };
+    STREAM_RECORDER.set(stream_recorder).ok();
+    Ok(())
+}
+
+/// Records the given entry received from the server with the given id, if recording was requested. Errors while
+/// writing the recording are logged but do not affect the executed command.
+///
+/// # Arguments
+/// * `server_id` - The id of the server from which the entry was received.
+/// * `stream_entry` - The entry that was received.
+pub(crate) fn record_stream_entry(
+    server_id: &str,
+    stream_entry: &Result<ExecutedActionEntry, Status>,
+) {
+    let Some(stream_recorder) = STREAM_RECORDER.get() else {
+        return;
+    };
+
+    let (entry, error) = match stream_entry {
+        Ok(action_entry) => {
+            let recorded_entry = RecordedActionEntry {
+                release_id: action_entry.release_id,
+                current_action: action_entry.current_action,
+                action_status: action_entry.action_status,
+                stream_type: action_entry
+                    .action_log_entry
+                    .as_ref()
+                    .map(|log_entry| log_entry.stream_type),
+                content: action_entry
+                    .action_log_entry
+                    .as_ref()
+                    .map(|log_entry| log_entry.content.clone()),
+                progress_percent: action_entry.progress_percent,
+                phase: action_entry.phase.clone(),
+            };
+            (Some(recorded_entry), None)
+        }
+        Err(status) => {
+            let recorded_error = RecordedErrorStatus {
+                code: status.code().into(),
+                message: status.message().to_string(),
+            };
+            (None, Some(recorded_error))
+        }
+    };
+    let recorded_stream_entry = RecordedStreamEntry {
+        server_id: server_id.to_string(),
+        elapsed_millis: stream_recorder.started_at.elapsed().as_millis() as u64,
+        entry,
+        error,
+    };
+
+    let write_result = serde_json::to_string(&recorded_stream_entry)
+        .context("unable to serialize recorded entry")
+        .and_then(|serialized_entry| {
+            let mut file = stream_recorder.file.lock().unwrap();
+            writeln!(file, "{serialized_entry}").context("unable to write recorded entry")
+        });
+    if let Err(err) = write_result {
+        warn!("Unable to record stream entry of {server_id}: {err:#}");
+    }
+}
+
+/// Reads the entries of the recording stored in the given file.
+///
+/// # Arguments
+/// * `file_path` - The path of the file containing the recording.
+pub(crate) async fn read_recorded_stream_entries(
+    file_path: &Path,
+) -> anyhow::Result<Vec<RecordedStreamEntry>> {
+    let recording = tokio::fs::read_to_string(file_path)
+        .await
+        .with_context(|| format!("unable to read stream recording {file_path:?}"))?;
+    recording
+        .lines()
+        .enumerate()
+        .filter(|(_, line)| !line.trim().is_empty())
+        .map(|(line_index, line)| {
+            serde_json::from_str::<RecordedStreamEntry>(line)
+                .with_context(|| format!("invalid recorded entry in line {}", line_index + 1))
+        })
+        .collect()
+}
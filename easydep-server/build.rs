@@ -21,23 +21,18 @@
  * OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
  * SOFTWARE.
  */
-use std::process::Command;
+
+use std::env;
 
 fn main() -> Result<(), Box<dyn std::error::Error>> {
+    let mut proto_files = vec!["../proto/deploy.proto", "../proto/status.proto"];
+    if env::var_os("CARGO_FEATURE_CONCURRENCY_SIMULATION").is_some() {
+        proto_files.push("../proto/simulation.proto");
+    }
     tonic_build::configure()
         .build_server(true)
         .build_client(false)
-        .compile(
-            &["../proto/deploy.proto", "../proto/status.proto"],
-            &["../proto"],
-        )?;
-
-    // used to embed the git hash into the crate
-    let output = Command::new("git")
-        .args(["rev-parse", "--short", "HEAD"])
-        .output()?;
-    let git_hash = String::from_utf8(output.stdout)?;
-    println!("cargo:rustc-env=GIT_HASH={}", git_hash);
+        .compile(&proto_files, &["../proto"])?;
 
     Ok(())
 }
@@ -0,0 +1,94 @@
+/*
+ * This file is part of easydep, licensed under the MIT License (MIT).
+ *
+ * Copyright (c) 2024 easybill GmbH
+ *
+ * Permission is hereby granted, free of charge, to any person obtaining a copy
+ * of this software and associated documentation files (the "Software"), to deal
+ * in the Software without restriction, including without limitation the rights
+ * to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+ * copies of the Software, and to permit persons to whom the Software is
+ * furnished to do so, subject to the following conditions:
+ *
+ * The above copyright notice and this permission notice shall be included in all
+ * copies or substantial portions of the Software.
+ *
+ * THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+ * IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+ * FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+ * AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+ * LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+ * OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+ * SOFTWARE.
+ */
+
+use anyhow::{bail, Context};
+use ring::digest::{digest, SHA256};
+use tokio::fs;
+
+use crate::accessor::oidc_accessor::ValidatedToken;
+use crate::config::{AccessRole, ApiTokenConfiguration};
+
+/// A static token that was loaded from the disk. Only the digest of the token is kept in memory.
+#[derive(Clone, Debug)]
+struct ApiToken {
+    /// The name of the token.
+    name: String,
+    /// The SHA-256 digest of the token.
+    token_digest: Vec<u8>,
+    /// The role that is granted to clients authenticated with the token.
+    role: AccessRole,
+}
+
+/// An accessor for the static tokens configured on the server, used to validate bearer tokens sent by clients.
+#[derive(Clone, Debug)]
+pub(crate) struct ApiTokenAccessor {
+    tokens: Vec<ApiToken>,
+}
+
+impl ApiTokenAccessor {
+    /// Constructs a new api token accessor, reading the given tokens from their files.
+    ///
+    /// # Arguments
+    /// * `token_configurations` - The configurations of the tokens to load.
+    pub async fn new(token_configurations: &[ApiTokenConfiguration]) -> anyhow::Result<Self> {
+        let mut tokens = Vec::with_capacity(token_configurations.len());
+        for token_configuration in token_configurations {
+            let token = fs::read_to_string(&token_configuration.token_file)
+                .await
+                .with_context(|| {
+                    format!(
+                        "unable to read api token {} from {}",
+                        token_configuration.name, token_configuration.token_file
+                    )
+                })?;
+            let token = token.trim();
+            if token.is_empty() {
+                bail!("api token {} is empty", token_configuration.name)
+            }
+            tokens.push(ApiToken {
+                name: token_configuration.name.clone(),
+                token_digest: digest(&SHA256, token.as_bytes()).as_ref().to_vec(),
+                role: token_configuration.role,
+            });
+        }
+        Ok(Self { tokens })
+    }
+
+    /// Validates the given bearer token against the configured tokens, returning the name of the matching token as
+    /// subject and the role granted by it. Digests of the tokens are compared to not leak the configured tokens through
+    /// the comparison timing.
+    ///
+    /// # Arguments
+    /// * `token` - The bearer token sent by the client.
+    pub fn validate_token(&self, token: &str) -> Option<ValidatedToken> {
+        let token_digest = digest(&SHA256, token.as_bytes());
+        self.tokens
+            .iter()
+            .find(|api_token| api_token.token_digest == token_digest.as_ref())
+            .map(|api_token| ValidatedToken {
+                subject: api_token.name.clone(),
+                roles: vec![api_token.role],
+            })
+    }
+}
@@ -0,0 +1,107 @@
+/*
+ * This file is part of easydep, licensed under the MIT License (MIT).
+ *
+ * Copyright (c) 2024 easybill GmbH
+ *
+ * Permission is hereby granted, free of charge, to any person obtaining a copy
+ * of this software and associated documentation files (the "Software"), to deal
+ * in the Software without restriction, including without limitation the rights
+ * to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+ * copies of the Software, and to permit persons to whom the Software is
+ * furnished to do so, subject to the following conditions:
+ *
+ * The above copyright notice and this permission notice shall be included in all
+ * copies or substantial portions of the Software.
+ *
+ * THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+ * IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+ * FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+ * AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+ * LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+ * OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+ * SOFTWARE.
+ */
+
+use std::sync::Arc;
+
+use anyhow::{bail, Context};
+use tokio::sync::RwLock;
+
+use crate::config::{Configuration, ConfigurationSource};
+
+/// The holder of the configuration the server is running with, which allows to reload the deployment configurations
+/// from the configuration file without restarting the server (and dropping the connections of running actions).
+/// Actions that are already running keep using the configuration they were started with.
+#[derive(Clone, Debug)]
+pub(crate) struct ConfigurationAccessor {
+    loaded_configuration: Arc<RwLock<LoadedConfiguration>>,
+}
+
+/// A configuration and the information about the file from which it was loaded.
+#[derive(Debug)]
+struct LoadedConfiguration {
+    configuration: Arc<Configuration>,
+    source: ConfigurationSource,
+}
+
+impl ConfigurationAccessor {
+    /// Constructs a new holder for the given configuration that was loaded from the given source.
+    ///
+    /// # Arguments
+    /// * `configuration` - The configuration the server was started with.
+    /// * `configuration_source` - The information about the file from which the configuration was loaded.
+    pub fn new(configuration: Configuration, configuration_source: ConfigurationSource) -> Self {
+        let loaded_configuration = LoadedConfiguration {
+            configuration: Arc::new(configuration),
+            source: configuration_source,
+        };
+        Self {
+            loaded_configuration: Arc::new(RwLock::new(loaded_configuration)),
+        }
+    }
+
+    /// Get the configuration that is currently loaded.
+    pub async fn get_configuration(&self) -> Arc<Configuration> {
+        self.loaded_configuration.read().await.configuration.clone()
+    }
+
+    /// Get the information about the file from which the current configuration was loaded.
+    pub async fn get_configuration_source(&self) -> ConfigurationSource {
+        self.loaded_configuration.read().await.source.clone()
+    }
+
+    /// Reads and validates the configuration file again and replaces the current configuration with it. The reload
+    /// is rejected (and the current configuration kept) if the file is invalid or changes settings which can only be
+    /// applied by restarting the server.
+    ///
+    /// # Returns
+    /// * `ConfigurationSource` - The information about the file from which the configuration was reloaded.
+    pub async fn reload(&self) -> anyhow::Result<ConfigurationSource> {
+        let mut loaded_configuration = self.loaded_configuration.write().await;
+        let (configuration, configuration_source) =
+            Configuration::load_from_file(&loaded_configuration.source.path)
+                .await
+                .context("couldn't parse configuration file")?;
+        configuration
+            .validate()
+            .await
+            .context("issue detected while validating configuration")?;
+
+        let restart_required_changes = loaded_configuration
+            .configuration
+            .get_restart_required_changes(&configuration)
+            .context("couldn't compare configurations")?;
+        if !restart_required_changes.is_empty() {
+            bail!(
+                "changed settings can only be applied by restarting the server: {}",
+                restart_required_changes.join(", ")
+            )
+        }
+
+        *loaded_configuration = LoadedConfiguration {
+            configuration: Arc::new(configuration),
+            source: configuration_source.clone(),
+        };
+        Ok(configuration_source)
+    }
+}
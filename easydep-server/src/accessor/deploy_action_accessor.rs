@@ -22,21 +22,31 @@
  * SOFTWARE.
  */
 
+use std::collections::{BTreeMap, HashMap, VecDeque};
 use std::mem::discriminant;
-use std::sync::Arc;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::SystemTime;
 
 use octocrab::models::repos::Release;
-use tokio::sync::RwLock;
+use tokio::sync::{watch, RwLock, RwLockReadGuard};
 
+use crate::config::DeploymentConfiguration;
+use crate::easydep::DeployAnnotation;
 use crate::executor::deploy_executor::DeployExecutor;
+use crate::service::request_identity::RequestIdentity;
 
 /// The state of actions that can be executed by this service.
 #[derive(Clone, Debug)]
 pub(crate) enum CurrentAction {
     /// The executor is currently idling and not doing anything.
     Idle,
-    /// The executor is currently rolling back to an old release.
-    RollingBack(Box<Release>),
+    /// The executor is currently rolling back to an old release, using the given profile configuration. The rollback
+    /// was triggered by the given identity.
+    RollingBack(Box<Release>, Box<DeploymentConfiguration>, RequestIdentity),
+    /// The executor is currently executing the scripts of the published release again, using the given profile
+    /// configuration. The execution was triggered by the given identity.
+    RerunningScripts(Box<Release>, Box<DeploymentConfiguration>, RequestIdentity),
     /// The executor is currently deploying a fresh release.
     Executing(Arc<DeployExecutor>),
 }
@@ -45,13 +55,63 @@ pub(crate) enum CurrentAction {
 #[derive(Clone, Debug)]
 pub(crate) struct DeploymentStatusAccessor {
     inner: Arc<RwLock<CurrentAction>>,
+    /// Notified each time the current action or one of the action queues changed.
+    action_changed_sender: Arc<watch::Sender<()>>,
+    /// The actions waiting in the queue of each profile, in the order they are executed.
+    action_queues: Arc<Mutex<HashMap<String, VecDeque<QueueEntry>>>>,
+    /// The ticket that is assigned to the next queued action.
+    next_queue_ticket: Arc<AtomicU64>,
+}
+
+/// The details of a deployment that waits in the action queue of a profile, reported to the clients.
+#[derive(Clone, Debug)]
+pub(crate) struct QueuedActionDetails {
+    /// The id of the release that should be deployed.
+    pub release_id: u64,
+    /// The tag of the release that should be deployed.
+    pub release_tag: String,
+    /// The git ref that should be deployed if the deployment was started from a git ref instead of a release.
+    pub git_ref: Option<String>,
+    /// The reason that was given when starting the deployment, if any.
+    pub annotation: Option<DeployAnnotation>,
+    /// The identity that started the deployment.
+    pub triggered_by: RequestIdentity,
+    /// The time when the deployment joined the queue.
+    pub queued_at: SystemTime,
+}
+
+/// An action waiting in the action queue of a profile.
+#[derive(Clone, Debug)]
+struct QueueEntry {
+    /// The ticket that identifies the action in the queue.
+    ticket: u64,
+    /// The details of the queued deployment.
+    details: QueuedActionDetails,
+}
+
+/// A place in the action queue of a profile. The place is given up when this is dropped, for example when the client
+/// that requested the action disconnected while waiting.
+#[derive(Debug)]
+pub(crate) struct QueuedAction {
+    /// The id of the profile in whose queue the action is waiting.
+    profile: String,
+    /// The ticket that identifies the action in the queue.
+    ticket: u64,
+    /// The accessor that holds the queue.
+    status_accessor: DeploymentStatusAccessor,
+    /// The receiver that is notified when the current action or one of the action queues changed.
+    action_changed_receiver: watch::Receiver<()>,
 }
 
 impl DeploymentStatusAccessor {
     /// Constructs a new holder instance with the current action set to idle.
     pub fn new() -> Self {
+        let (action_changed_sender, _) = watch::channel(());
         Self {
             inner: Arc::new(RwLock::new(CurrentAction::Idle)),
+            action_changed_sender: Arc::new(action_changed_sender),
+            action_queues: Arc::new(Mutex::new(HashMap::new())),
+            next_queue_ticket: Arc::new(AtomicU64::new(0)),
         }
     }
 
@@ -60,10 +120,18 @@ impl DeploymentStatusAccessor {
         self.inner.read().await.clone()
     }
 
+    /// Get the current action and prevent it from being changed until the returned guard is dropped, for example to
+    /// modify the release directories only while no action is being executed. The guard must not be held while calling
+    /// other methods of this holder.
+    pub async fn lock_action(&self) -> RwLockReadGuard<'_, CurrentAction> {
+        self.inner.read().await
+    }
+
     /// Sets the current action of this holder.
     pub async fn set_action(&self, new_action: CurrentAction) {
         let mut guard = self.inner.write().await;
         *guard = new_action;
+        self.action_changed_sender.send_replace(());
     }
 
     /// Sets the current action to the given new action if the enum variant of the
@@ -80,9 +148,145 @@ impl DeploymentStatusAccessor {
         let current_enum_variant = discriminant(&*guard);
         if expected_enum_variant == current_enum_variant {
             *guard = new_action;
+            self.action_changed_sender.send_replace(());
             true
         } else {
             false
         }
     }
+
+    /// Adds an action to the end of the action queue of the given profile, returning `None` if the given amount of
+    /// actions is already waiting in the queue.
+    ///
+    /// # Arguments
+    /// * `profile` - The id of the profile in whose queue the action should wait.
+    /// * `max_queue_depth` - The maximum amount of actions that can wait in the queue of the profile.
+    /// * `details` - The details of the queued deployment, reported to the clients.
+    pub fn enqueue_action(
+        &self,
+        profile: &str,
+        max_queue_depth: usize,
+        details: QueuedActionDetails,
+    ) -> Option<QueuedAction> {
+        // subscribe before joining the queue to not miss a change that happens in between
+        let action_changed_receiver = self.action_changed_sender.subscribe();
+        let ticket = self.next_queue_ticket.fetch_add(1, Ordering::Relaxed);
+        {
+            let mut action_queues = self.action_queues.lock().ok()?;
+            let action_queue = action_queues.entry(profile.to_string()).or_default();
+            if action_queue.len() >= max_queue_depth {
+                return None;
+            }
+            action_queue.push_back(QueueEntry { ticket, details });
+        }
+        Some(QueuedAction {
+            profile: profile.to_string(),
+            ticket,
+            status_accessor: self.clone(),
+            action_changed_receiver,
+        })
+    }
+
+    /// Get the details of the actions waiting in the queue of each profile, in the order they are executed.
+    pub fn get_queued_actions(&self) -> BTreeMap<String, Vec<QueuedActionDetails>> {
+        let Ok(action_queues) = self.action_queues.lock() else {
+            return BTreeMap::new();
+        };
+        action_queues
+            .iter()
+            .filter(|(_, action_queue)| !action_queue.is_empty())
+            .map(|(profile, action_queue)| {
+                let queued_actions = action_queue
+                    .iter()
+                    .map(|entry| entry.details.clone())
+                    .collect();
+                (profile.clone(), queued_actions)
+            })
+            .collect()
+    }
+
+    /// Removes the deployments of the given release from the queue of the given profile, returning the amount of
+    /// removed deployments. The clients waiting for the removed deployments are notified that they were cancelled.
+    ///
+    /// # Arguments
+    /// * `profile` - The id of the profile from whose queue the deployments should be removed.
+    /// * `release_id` - The id of the release whose queued deployments should be removed.
+    pub fn cancel_queued_actions(&self, profile: &str, release_id: u64) -> usize {
+        let removed_actions = match self.action_queues.lock() {
+            Ok(mut action_queues) => match action_queues.get_mut(profile) {
+                Some(action_queue) => {
+                    let queue_length = action_queue.len();
+                    action_queue.retain(|entry| entry.details.release_id != release_id);
+                    queue_length - action_queue.len()
+                }
+                None => 0,
+            },
+            Err(_) => 0,
+        };
+        if removed_actions > 0 {
+            self.action_changed_sender.send_replace(());
+        }
+        removed_actions
+    }
+}
+
+impl QueuedAction {
+    /// Get the position (starting at 1) of this action in the queue of its profile, `0` if the action was removed from
+    /// the queue because it was cancelled.
+    pub fn get_position(&self) -> usize {
+        self.status_accessor
+            .action_queues
+            .lock()
+            .ok()
+            .and_then(|action_queues| {
+                action_queues.get(&self.profile).and_then(|action_queue| {
+                    action_queue
+                        .iter()
+                        .position(|entry| entry.ticket == self.ticket)
+                })
+            })
+            .map(|index| index + 1)
+            .unwrap_or_default()
+    }
+
+    /// Sets the current action to the given action if this action is the first one in the queue of its profile and
+    /// no other action is being executed. This action leaves the queue if the action was set.
+    ///
+    /// # Arguments
+    /// * `new_action` - The action to execute.
+    pub async fn try_set_action(&self, new_action: CurrentAction) -> bool {
+        if self.get_position() != 1
+            || !self
+                .status_accessor
+                .compare_and_set_action_by_variant(&CurrentAction::Idle, new_action)
+                .await
+        {
+            return false;
+        }
+
+        self.leave_queue();
+        true
+    }
+
+    /// Waits until the current action or one of the action queues changed, which might allow this action to be
+    /// executed or changed its position in the queue.
+    pub async fn changed(&mut self) {
+        self.action_changed_receiver.changed().await.ok();
+    }
+
+    /// Removes this action from the queue of its profile, notifying the other actions about their new position.
+    fn leave_queue(&self) {
+        if let Ok(mut action_queues) = self.status_accessor.action_queues.lock() {
+            if let Some(action_queue) = action_queues.get_mut(&self.profile) {
+                action_queue.retain(|entry| entry.ticket != self.ticket);
+            }
+        }
+        self.status_accessor.action_changed_sender.send_replace(());
+    }
+}
+
+impl Drop for QueuedAction {
+    fn drop(&mut self) {
+        self.leave_queue();
+    }
 }
@@ -21,8 +21,12 @@
  * OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
  * SOFTWARE.
  */
+
 use std::sync::Arc;
+use std::time::SystemTime;
 
+use anyhow::bail;
+use log::error;
 use tokio::sync::RwLock;
 
 /// The states a running deployment can be in.
@@ -34,33 +38,119 @@ pub(crate) enum DeployExecutionState {
     Published,
     Deleting,
     Deleted,
+    Cancelling,
+    Cancelled,
+    Failed,
+}
+
+impl DeployExecutionState {
+    /// Checks if a deployment in this state is allowed to switch to the given next state. A deployment is prepared
+    /// (or cancelled or failed while being prepared) and then either published or deleted, the final states cannot be
+    /// left.
+    ///
+    /// # Arguments
+    /// * `next_state` - The state to switch to.
+    ///
+    /// # Returns
+    /// * `bool` - `true` if the transition to the given state is allowed, `false` otherwise.
+    pub fn can_transition_to(&self, next_state: &DeployExecutionState) -> bool {
+        matches!(
+            (self, next_state),
+            (Self::Preparing, Self::Prepared)
+                | (Self::Preparing, Self::Cancelling)
+                | (Self::Preparing, Self::Failed)
+                | (Self::Prepared, Self::Publishing)
+                | (Self::Prepared, Self::Deleting)
+                | (Self::Publishing, Self::Published)
+                | (Self::Deleting, Self::Deleted)
+                | (Self::Cancelling, Self::Cancelled)
+        )
+    }
+}
+
+/// A state that a deployment entered.
+#[derive(Clone, Debug)]
+pub(crate) struct DeployStateTransition {
+    /// The state that was entered.
+    pub state: DeployExecutionState,
+    /// The time at which the state was entered.
+    pub entered_at: SystemTime,
+}
+
+/// The current state of a deployment and the states it went through.
+#[derive(Debug)]
+struct DeployStateMachine {
+    state: DeployExecutionState,
+    transitions: Vec<DeployStateTransition>,
+}
+
+impl DeployStateMachine {
+    /// Switches to the given new state and records the transition. This does not check if the transition is allowed.
+    ///
+    /// # Arguments
+    /// * `new_state` - The state to switch to.
+    fn enter(&mut self, new_state: DeployExecutionState) {
+        self.transitions.push(DeployStateTransition {
+            state: new_state.clone(),
+            entered_at: SystemTime::now(),
+        });
+        self.state = new_state;
+    }
 }
 
 /// The holder for the current status of a running deployment.
 #[derive(Clone, Debug)]
 pub(crate) struct DeployStatusAccessor {
-    inner: Arc<RwLock<DeployExecutionState>>,
+    inner: Arc<RwLock<DeployStateMachine>>,
 }
 
 impl DeployStatusAccessor {
     /// Creates a new deployment status accessor instance that is in the preparing state.
     pub fn new() -> Self {
+        let mut state_machine = DeployStateMachine {
+            state: DeployExecutionState::Preparing,
+            transitions: Vec::new(),
+        };
+        state_machine.enter(DeployExecutionState::Preparing);
         Self {
-            inner: Arc::new(RwLock::new(DeployExecutionState::Preparing)),
+            inner: Arc::new(RwLock::new(state_machine)),
         }
     }
 
-    /// Sets the given new state.
+    /// Get the current state.
+    pub async fn get_state(&self) -> DeployExecutionState {
+        self.inner.read().await.state.clone()
+    }
+
+    /// Get the states that the deployment entered, oldest first, starting with the preparing state.
+    pub async fn get_transitions(&self) -> Vec<DeployStateTransition> {
+        self.inner.read().await.transitions.clone()
+    }
+
+    /// Switches to the given new state if the transition from the current state is allowed. The state is unchanged
+    /// if the transition is not allowed.
     ///
     /// # Arguments
-    /// * `new_state` - The new state to set.
-    pub async fn set_state(&self, new_state: DeployExecutionState) {
+    /// * `new_state` - The new state to switch to.
+    ///
+    /// # Returns
+    /// * `anyhow::Result<()>` - `Ok` if the state was switched, `Err` if the transition is not allowed.
+    pub async fn transition_to(&self, new_state: DeployExecutionState) -> anyhow::Result<()> {
         let mut write_guard = self.inner.write().await;
-        *write_guard = new_state;
+        if !write_guard.state.can_transition_to(&new_state) {
+            bail!(
+                "illegal deployment state transition from {:?} to {:?}",
+                write_guard.state,
+                new_state
+            );
+        }
+        write_guard.enter(new_state);
+        Ok(())
     }
 
     /// Check if the current executor is in the given expected state, if that is the case the state is switched to the
     /// given new state and `true` is returned. If that is not the case the state is unchanged and `false` is returned.
+    /// Transitions that are not allowed are never executed and logged as an error.
     ///
     /// # Arguments
     /// * `expected_state` - The state that is expected, the switch only happens if matching the current state.
@@ -74,11 +164,14 @@ impl DeployStatusAccessor {
         new_state: DeployExecutionState,
     ) -> bool {
         let mut write_guard = self.inner.write().await;
-        if &*write_guard == expected_state {
-            *write_guard = new_state;
-            true
-        } else {
-            false
+        if write_guard.state != *expected_state {
+            return false;
+        }
+        if !expected_state.can_transition_to(&new_state) {
+            error!("Rejected illegal deployment state transition from {expected_state:?} to {new_state:?}");
+            return false;
         }
+        write_guard.enter(new_state);
+        true
     }
 }
@@ -22,33 +22,65 @@
  * SOFTWARE.
  */
 
-use std::path::PathBuf;
+use std::cmp::Reverse;
+#[cfg(unix)]
+use std::ffi::CString;
+#[cfg(unix)]
+use std::fs::Permissions;
+use std::io;
+use std::io::ErrorKind;
+#[cfg(unix)]
+use std::mem::MaybeUninit;
+#[cfg(unix)]
+use std::os::unix::ffi::OsStrExt;
+#[cfg(unix)]
+use std::os::unix::fs::MetadataExt;
+#[cfg(unix)]
+use std::os::unix::fs::PermissionsExt;
+use std::path::{Path, PathBuf};
 
 use anyhow::bail;
+use tokio::fs;
 use tokio::fs::read_dir;
 use tokio_stream::wrappers::ReadDirStream;
 use tokio_stream::StreamExt;
 
-use crate::config::{Configuration, DeploymentConfiguration};
+use crate::config::{Configuration, DeploymentConfiguration, SlotConfiguration};
 
 /// An accessor for deployments that are stored on the disk.
 #[derive(Clone, Debug)]
 pub struct DeploymentAccessor {
     deployment_base_dir: PathBuf,
+    directory_mode: Option<u32>,
 }
 
 impl DeploymentAccessor {
     /// Constructs a new deployment accessor with the given base directory.
     ///
     /// # Arguments
-    /// * `config` - The server configuration, used to get the deployment base directory.
+    /// * `config` - The server configuration, used to get the deployment base directory and directory mode.
     pub fn new(config: &Configuration) -> Self {
         let deployment_base_dir = PathBuf::from(&config.base_directory);
         Self {
             deployment_base_dir,
+            directory_mode: config.directory_mode,
         }
     }
 
+    /// Get the mode of the release directories and their parent directories, if configured.
+    pub fn get_directory_mode(&self) -> Option<u32> {
+        self.directory_mode
+    }
+
+    /// Creates the given directory and its missing parent directories, applying the configured directory mode to
+    /// each directory that is created.
+    ///
+    /// # Arguments
+    /// * `directory` - The directory to create.
+    pub async fn create_directory(&self, directory: &Path) -> io::Result<()> {
+        create_directory_with_mode(directory, self.directory_mode).await
+    }
+
     /// Get the path to the symlink directory based on the given deployment profile.
     ///
     /// # Arguments
@@ -58,6 +90,86 @@ impl DeploymentAccessor {
             .join(format!("current-{}", profile.target))
     }
 
+    /// Get the directory and the id of the release that is currently published with the given profile, `None` if no
+    /// release is published with the profile.
+    ///
+    /// # Arguments
+    /// * `profile` - The profile to get the published release of.
+    pub fn get_published_release_directory(
+        &self,
+        profile: &DeploymentConfiguration,
+    ) -> Option<(PathBuf, u64)> {
+        let release_directory =
+            std::fs::canonicalize(self.get_current_release_directory(profile)).ok()?;
+        let release_id = release_directory
+            .file_name()
+            .and_then(|directory_name| directory_name.to_str())
+            .and_then(parse_release_directory_name)?;
+        Some((release_directory, release_id))
+    }
+
+    /// Get the path to the symlink pointing to the release deployed in the given slot of the given profile.
+    ///
+    /// # Arguments
+    /// * `profile` - The profile to get the slot symlink path of.
+    /// * `slot` - The slot to get the symlink path of.
+    pub fn get_slot_release_directory(
+        &self,
+        profile: &DeploymentConfiguration,
+        slot: &SlotConfiguration,
+    ) -> PathBuf {
+        self.deployment_base_dir
+            .join(format!("current-{}-{}", profile.target, slot.name))
+    }
+
+    /// Selects the slot in which the next deployment of the given profile should be executed: the slot following the
+    /// slot of the currently published release, or the first slot if no release was published in a slot yet. Returns
+    /// `None` if the profile does not define slots.
+    ///
+    /// # Arguments
+    /// * `profile` - The profile to select the deployment slot of.
+    pub fn select_deployment_slot(
+        &self,
+        profile: &DeploymentConfiguration,
+    ) -> Option<SlotConfiguration> {
+        let current_release_directory = self.get_current_release_directory(profile);
+        let active_slot_index = self.find_release_slot_index(profile, &current_release_directory);
+        let next_slot_index =
+            active_slot_index.map_or(0, |index| (index + 1) % profile.slots.len());
+        profile.slots.get(next_slot_index).cloned()
+    }
+
+    /// Finds the slot of the given profile in which the given release directory is deployed.
+    ///
+    /// # Arguments
+    /// * `profile` - The profile to find the slot in.
+    /// * `release_directory` - The release directory to find the slot of.
+    pub fn find_release_slot(
+        &self,
+        profile: &DeploymentConfiguration,
+        release_directory: &Path,
+    ) -> Option<SlotConfiguration> {
+        self.find_release_slot_index(profile, release_directory)
+            .and_then(|index| profile.slots.get(index).cloned())
+    }
+
+    /// Finds the index of the slot of the given profile whose symlink points to the given release directory.
+    ///
+    /// # Arguments
+    /// * `profile` - The profile to find the slot in.
+    /// * `release_directory` - The release directory to find the slot of.
+    fn find_release_slot_index(
+        &self,
+        profile: &DeploymentConfiguration,
+        release_directory: &Path,
+    ) -> Option<usize> {
+        let release_directory = std::fs::canonicalize(release_directory).ok()?;
+        profile.slots.iter().position(|slot| {
+            let slot_directory = self.get_slot_release_directory(profile, slot);
+            std::fs::canonicalize(slot_directory).is_ok_and(|target| target == release_directory)
+        })
+    }
+
     /// Get the directory where the releases for the given profile are stored.
     ///
     /// # Arguments
@@ -68,18 +180,115 @@ impl DeploymentAccessor {
             .join(&profile.target)
     }
 
-    /// Get the path to the directory where the for the given profile is stored.
+    /// Get the directory where the build caches of the given profile are stored.
+    ///
+    /// # Arguments
+    /// * `profile` - The profile to get the build cache directory of.
+    pub fn get_build_cache_directory(&self, profile: &DeploymentConfiguration) -> PathBuf {
+        self.deployment_base_dir.join("cache").join(&profile.target)
+    }
+
+    /// Get the bare repository in which the git objects of the source repository of the given profile are cached
+    /// between releases. The cache is shared by all profiles deploying from the same repository.
+    ///
+    /// # Arguments
+    /// * `profile` - The profile to get the repository cache directory of.
+    pub fn get_repository_cache_directory(&self, profile: &DeploymentConfiguration) -> PathBuf {
+        self.deployment_base_dir
+            .join("repositories")
+            .join(&profile.source_repo_owner)
+            .join(format!("{}.git", profile.source_repo_name))
+    }
+
+    /// Get the disk usage (in bytes) of the repository cache of the given profile, see `get_repository_cache_directory`.
+    ///
+    /// # Arguments
+    /// * `profile` - The profile to get the disk usage of the repository cache of.
+    pub async fn get_repository_cache_disk_usage(
+        &self,
+        profile: &DeploymentConfiguration,
+    ) -> anyhow::Result<u64> {
+        let cache_directory = self.get_repository_cache_directory(profile);
+        let disk_usage =
+            tokio::task::spawn_blocking(move || get_disk_usage(&cache_directory)).await??;
+        Ok(disk_usage)
+    }
+
+    /// Get the amount of bytes that are available to the server on the file system of the deployment base directory.
+    pub fn get_available_disk_space(&self) -> io::Result<u64> {
+        get_available_disk_space(&self.deployment_base_dir)
+    }
+
+    /// Get the path to the directory where the given release for the given profile is stored. The name of the
+    /// directory is rendered from the release directory name template of the profile.
     ///
     /// # Arguments
     /// * `profile` - The profile to get the release subdirectory of.
     /// * `release_id` - The id of the release to get the release directory for.
+    /// * `release_tag` - The tag name of the release to get the release directory for.
     pub fn get_release_directory(
         &self,
         profile: &DeploymentConfiguration,
         release_id: &u64,
+        release_tag: &str,
     ) -> PathBuf {
-        self.get_releases_directory(profile)
-            .join(release_id.to_string())
+        let sanitized_tag: String = release_tag
+            .chars()
+            .map(|char| match char {
+                'a'..='z' | 'A'..='Z' | '0'..='9' | '.' | '-' | '_' => char,
+                _ => '_',
+            })
+            .collect();
+        let directory_name = profile
+            .release_directory_name
+            .replacen("{id}", &release_id.to_string(), 1)
+            .replace("{tag}", &sanitized_tag);
+        self.get_releases_directory(profile).join(directory_name)
+    }
+
+    /// Checks if a release directory exists for the release with the given id in the given profile, regardless of the
+    /// naming scheme that was used to create the directory.
+    ///
+    /// # Arguments
+    /// * `profile` - The profile to check the release directory in.
+    /// * `release_id` - The id of the release to check.
+    pub fn has_release_directory(
+        &self,
+        profile: &DeploymentConfiguration,
+        release_id: &u64,
+    ) -> bool {
+        match std::fs::read_dir(self.get_releases_directory(profile)) {
+            Ok(entries) => entries.flatten().any(|entry| {
+                entry
+                    .file_name()
+                    .to_str()
+                    .and_then(parse_release_directory_name)
+                    .is_some_and(|entry_release_id| entry_release_id == *release_id)
+            }),
+            Err(_) => false,
+        }
+    }
+
+    /// Get all entries (files and directories) that are stored in the releases directory of the given profile.
+    /// Returns an empty vec if the releases directory does not exist yet.
+    ///
+    /// # Arguments
+    /// * `profile` - The release profile to get the stored entries of.
+    pub async fn get_releases_directory_entries(
+        &self,
+        profile: &DeploymentConfiguration,
+    ) -> anyhow::Result<Vec<PathBuf>> {
+        let releases_directory = self.get_releases_directory(profile);
+        let directory_content = match read_dir(&releases_directory).await {
+            Ok(directory_content) => ReadDirStream::new(directory_content),
+            Err(err) if err.kind() == ErrorKind::NotFound => return Ok(Vec::new()),
+            Err(err) => bail!("unable to read entries from deploy directory: {err}"),
+        };
+        let entries = directory_content
+            .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+            .collect()
+            .await;
+        Ok(entries)
     }
 
     /// Get all release directories that were created for the given deployment profile.
@@ -99,7 +308,7 @@ impl DeploymentAccessor {
         };
 
         // find the directories that were created from a release
-        // (by checking if the directory names can be parsed as a numeric id)
+        // (by checking if the directory names start with a numeric id)
         let mut release_directories: Vec<(PathBuf, u64)> = Vec::new();
         while let Some(entry) = directory_content.next().await {
             if let Ok(entry) = entry {
@@ -114,7 +323,7 @@ impl DeploymentAccessor {
                         .file_name()
                         .and_then(|dir_name| dir_name.to_str().map(|name| name.to_string()))
                     {
-                        if let Ok(id) = dir_name.parse::<u64>() {
+                        if let Some(id) = parse_release_directory_name(&dir_name) {
                             release_directories.push((entry.path(), id));
                         }
                     }
@@ -123,7 +332,147 @@ impl DeploymentAccessor {
         }
 
         // sort the parsed release directories, descending
-        release_directories.sort_by(|left, right| right.1.cmp(&left.1));
+        release_directories.sort_by_key(|release_directory| Reverse(release_directory.1));
         Ok(release_directories)
     }
+
+    /// Get the disk usage (in bytes) of the given release directory and all files and directories in it. Symlinks are
+    /// not followed and files that are hard linked multiple times are counted each time.
+    ///
+    /// # Arguments
+    /// * `release_directory` - The release directory to get the disk usage of.
+    pub async fn get_release_disk_usage(&self, release_directory: &Path) -> anyhow::Result<u64> {
+        let release_directory = release_directory.to_path_buf();
+        let disk_usage =
+            tokio::task::spawn_blocking(move || get_disk_usage(&release_directory)).await??;
+        Ok(disk_usage)
+    }
+}
+
+/// Get the amount of bytes that are available to unprivileged users on the file system of the given path.
+///
+/// # Arguments
+/// * `path` - A path on the file system to get the available space of.
+#[cfg(unix)]
+pub fn get_available_disk_space(path: &Path) -> io::Result<u64> {
+    let path = CString::new(path.as_os_str().as_bytes())?;
+    let mut file_system_stats = MaybeUninit::<libc::statvfs>::uninit();
+    // SAFETY: the path is nul-terminated and the stats point to memory that can hold the statvfs struct
+    if unsafe { libc::statvfs(path.as_ptr(), file_system_stats.as_mut_ptr()) } != 0 {
+        return Err(io::Error::last_os_error());
+    }
+
+    // SAFETY: statvfs completed successfully, therefore the stats were initialized
+    let file_system_stats = unsafe { file_system_stats.assume_init() };
+    Ok(file_system_stats.f_bavail * file_system_stats.f_frsize)
+}
+
+/// Get the amount of bytes that are available on the file system of the given path, which is only supported on unix.
+///
+/// # Arguments
+/// * `path` - A path on the file system to get the available space of.
+#[cfg(not(unix))]
+pub fn get_available_disk_space(_path: &Path) -> io::Result<u64> {
+    Err(io::Error::new(
+        ErrorKind::Unsupported,
+        "the free disk space can only be determined on unix",
+    ))
+}
+
+/// Get the disk usage (in bytes) of the given path, including the content of the path if it is a directory.
+///
+/// # Arguments
+/// * `path` - The path to get the disk usage of.
+fn get_disk_usage(path: &Path) -> io::Result<u64> {
+    let mut disk_usage = 0;
+    let mut pending_paths = vec![path.to_path_buf()];
+    while let Some(pending_path) = pending_paths.pop() {
+        let metadata = std::fs::symlink_metadata(&pending_path)?;
+        disk_usage += get_allocated_size(&metadata);
+        if metadata.is_dir() {
+            for entry in std::fs::read_dir(&pending_path)? {
+                pending_paths.push(entry?.path());
+            }
+        }
+    }
+    Ok(disk_usage)
+}
+
+/// Get the amount of bytes that are allocated on the disk for the file described by the given metadata.
+///
+/// # Arguments
+/// * `metadata` - The metadata of the file to get the allocated size of.
+#[cfg(unix)]
+fn get_allocated_size(metadata: &std::fs::Metadata) -> u64 {
+    metadata.blocks() * 512
+}
+
+/// Get the size of the file described by the given metadata, as the allocated blocks are only known on unix. Sparse
+/// and compressed files are therefore reported with their full size.
+///
+/// # Arguments
+/// * `metadata` - The metadata of the file to get the size of.
+#[cfg(not(unix))]
+fn get_allocated_size(metadata: &std::fs::Metadata) -> u64 {
+    metadata.len()
+}
+
+/// Parses the id of the release from the given name of a release directory. The name either only consists of the
+/// release id (as created before the naming scheme was configurable) or starts with the release id, followed by a
+/// separator and further information about the release.
+///
+/// # Arguments
+/// * `directory_name` - The name of the release directory to parse.
+fn parse_release_directory_name(directory_name: &str) -> Option<u64> {
+    let id_length = directory_name
+        .find(|char: char| !char.is_ascii_digit())
+        .unwrap_or(directory_name.len());
+    directory_name[..id_length].parse::<u64>().ok()
+}
+
+/// Creates the given directory and its missing parent directories. The given mode (if any) is applied to each directory
+/// that is created, independent of the umask of the server.
+///
+/// # Arguments
+/// * `directory` - The directory to create.
+/// * `directory_mode` - The mode to apply to the created directories, if configured.
+pub(crate) async fn create_directory_with_mode(
+    directory: &Path,
+    directory_mode: Option<u32>,
+) -> io::Result<()> {
+    let mut missing_directories = Vec::new();
+    for ancestor in directory.ancestors() {
+        if fs::try_exists(ancestor).await? {
+            break;
+        }
+        missing_directories.push(ancestor);
+    }
+
+    fs::create_dir_all(directory).await?;
+    if let Some(directory_mode) = directory_mode {
+        for missing_directory in missing_directories {
+            set_directory_mode(missing_directory, directory_mode).await?;
+        }
+    }
+    Ok(())
+}
+
+/// Applies the given mode to the given directory, independent of the umask of the server.
+///
+/// # Arguments
+/// * `directory` - The directory to apply the mode to.
+/// * `directory_mode` - The mode to apply.
+#[cfg(unix)]
+pub(crate) async fn set_directory_mode(directory: &Path, directory_mode: u32) -> io::Result<()> {
+    fs::set_permissions(directory, Permissions::from_mode(directory_mode)).await
+}
+
+/// Does nothing, as directory modes are only supported on unix.
+///
+/// # Arguments
+/// * `directory` - The directory to apply the mode to.
+/// * `directory_mode` - The mode to apply.
+#[cfg(not(unix))]
+pub(crate) async fn set_directory_mode(_directory: &Path, _directory_mode: u32) -> io::Result<()> {
+    Ok(())
 }
@@ -0,0 +1,216 @@
+/*
+ * This file is part of easydep, licensed under the MIT License (MIT).
+ *
+ * Copyright (c) 2024 easybill GmbH
+ *
+ * Permission is hereby granted, free of charge, to any person obtaining a copy
+ * of this software and associated documentation files (the "Software"), to deal
+ * in the Software without restriction, including without limitation the rights
+ * to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+ * copies of the Software, and to permit persons to whom the Software is
+ * furnished to do so, subject to the following conditions:
+ *
+ * The above copyright notice and this permission notice shall be included in all
+ * copies or substantial portions of the Software.
+ *
+ * THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+ * IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+ * FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+ * AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+ * LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+ * OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+ * SOFTWARE.
+ */
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use anyhow::Context;
+use log::warn;
+use serde::{Deserialize, Serialize};
+use tokio::fs;
+use tokio::fs::OpenOptions;
+use tokio::io::AsyncWriteExt;
+use tokio::sync::Mutex;
+
+use crate::config::{Configuration, NotificationEvent};
+use crate::notification::deployment_notification::DeploymentNotification;
+
+/// An action that was executed for a deployment profile, recorded in the deployment history.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub(crate) struct DeploymentHistoryEntry {
+    /// The action that was executed, one of `prepare`, `publish`, `rollback` or `delete`.
+    pub action: String,
+    /// The id of the release on which the action was executed.
+    pub release_id: u64,
+    /// The tag name of the release on which the action was executed.
+    pub release_tag: String,
+    /// The unix timestamp (in seconds) when the action was started.
+    pub started_at: u64,
+    /// The unix timestamp (in seconds) when the action finished.
+    pub finished_at: u64,
+    /// If the action completed successfully.
+    pub succeeded: bool,
+    /// The identity that triggered the action.
+    pub triggered_by: String,
+    /// The name of the directory in `state/failed` in which the output of the failed action was archived, if any.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub failure_archive: Option<String>,
+    /// The reason that was given for the action, if any.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub reason: Option<String>,
+    /// The ticket that is associated with the action, if any.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub ticket_reference: Option<String>,
+    /// The git ref that was deployed if the release was deployed from a git ref.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub git_ref: Option<String>,
+    /// The metadata that was attached to the deployment of the release.
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    pub metadata: HashMap<String, String>,
+}
+
+/// A page of the deployment history of a profile.
+#[derive(Clone, Debug)]
+pub(crate) struct DeploymentHistoryPage {
+    /// The entries on this page, newest first.
+    pub entries: Vec<DeploymentHistoryEntry>,
+    /// The sequence number of the last entry on this page, if there are older entries.
+    pub next_sequence_number: Option<usize>,
+}
+
+/// An accessor for the history of the actions executed for each profile, stored as append-only file per profile in
+/// the state directory of the base directory. Each line of the file contains one entry, the sequence number of an
+/// entry is its line index.
+#[derive(Clone, Debug)]
+pub(crate) struct DeploymentHistoryAccessor {
+    history_directory: PathBuf,
+    write_lock: Arc<Mutex<()>>,
+}
+
+impl DeploymentHistoryAccessor {
+    /// Constructs a new deployment history accessor storing the history in the state directory of the base directory.
+    ///
+    /// # Arguments
+    /// * `config` - The server configuration, used to get the deployment base directory.
+    pub fn new(config: &Configuration) -> Self {
+        let history_directory = PathBuf::from(&config.base_directory)
+            .join("state")
+            .join("history");
+        Self {
+            history_directory,
+            write_lock: Arc::new(Mutex::new(())),
+        }
+    }
+
+    /// Appends an entry for the action described by the given notification to the history of its profile. Stuck
+    /// actions are not recorded, as they are still running.
+    ///
+    /// # Arguments
+    /// * `notification` - The notification about the finished action.
+    /// * `failure_archive` - The name of the archive of the failed action, if it was archived.
+    pub async fn record_action(
+        &self,
+        notification: &DeploymentNotification,
+        failure_archive: Option<String>,
+    ) -> anyhow::Result<()> {
+        if notification.event == NotificationEvent::Stuck {
+            return Ok(());
+        }
+
+        let finished_at = SystemTime::now().duration_since(UNIX_EPOCH)?.as_secs();
+        let history_entry = DeploymentHistoryEntry {
+            action: notification.action.clone(),
+            release_id: notification.release_id,
+            release_tag: notification.release_tag.clone(),
+            started_at: finished_at.saturating_sub(notification.duration_seconds),
+            finished_at,
+            succeeded: notification.event != NotificationEvent::Failed,
+            triggered_by: notification.triggered_by.clone(),
+            failure_archive,
+            reason: notification.reason.clone(),
+            ticket_reference: notification.ticket_reference.clone(),
+            git_ref: notification.git_ref.clone(),
+            metadata: notification.metadata.clone(),
+        };
+        let mut serialized_entry = serde_json::to_vec(&history_entry)?;
+        serialized_entry.push(b'\n');
+
+        let _write_guard = self.write_lock.lock().await;
+        fs::create_dir_all(&self.history_directory)
+            .await
+            .context("unable to create history directory")?;
+        let mut history_file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(self.get_history_file(&notification.profile))
+            .await
+            .context("unable to open history file")?;
+        history_file
+            .write_all(&serialized_entry)
+            .await
+            .context("unable to append history entry")?;
+        Ok(())
+    }
+
+    /// Get a page of the history of the given profile, newest entries first. Lines of the history file that cannot be
+    /// parsed and entries that are rejected by the given filter are skipped.
+    ///
+    /// # Arguments
+    /// * `profile` - The id of the profile to get the history of.
+    /// * `page_size` - The maximum amount of entries to return.
+    /// * `before_sequence_number` - Only entries with a lower sequence number are returned, if given.
+    /// * `filter` - A predicate to check if an entry should be returned.
+    pub async fn get_history_page(
+        &self,
+        profile: &str,
+        page_size: usize,
+        before_sequence_number: Option<usize>,
+        filter: impl Fn(&DeploymentHistoryEntry) -> bool,
+    ) -> anyhow::Result<DeploymentHistoryPage> {
+        let history_file = self.get_history_file(profile);
+        if !fs::try_exists(&history_file).await? {
+            return Ok(DeploymentHistoryPage {
+                entries: Vec::new(),
+                next_sequence_number: None,
+            });
+        }
+
+        let history_content = fs::read_to_string(&history_file)
+            .await
+            .context("unable to read history file")?;
+        let history_lines: Vec<&str> = history_content.lines().collect();
+        let end_sequence_number = before_sequence_number
+            .unwrap_or(history_lines.len())
+            .min(history_lines.len());
+        let mut entries = Vec::new();
+        let mut next_sequence_number = None;
+        for sequence_number in (0..end_sequence_number).rev() {
+            if entries.len() == page_size {
+                next_sequence_number = Some(sequence_number + 1);
+                break;
+            }
+            match serde_json::from_str(history_lines[sequence_number]) {
+                Ok(history_entry) if filter(&history_entry) => entries.push(history_entry),
+                Ok(_) => {}
+                Err(err) => warn!(
+                    "Skipping invalid entry {} of history file {:?}: {}",
+                    sequence_number, history_file, err
+                ),
+            }
+        }
+        Ok(DeploymentHistoryPage {
+            entries,
+            next_sequence_number,
+        })
+    }
+
+    /// Get the path to the file containing the history of the given profile.
+    ///
+    /// # Arguments
+    /// * `profile` - The id of the profile.
+    fn get_history_file(&self, profile: &str) -> PathBuf {
+        self.history_directory.join(format!("{}.jsonl", profile))
+    }
+}
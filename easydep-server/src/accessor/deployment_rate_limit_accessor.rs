@@ -0,0 +1,128 @@
+/*
+ * This file is part of easydep, licensed under the MIT License (MIT).
+ *
+ * Copyright (c) 2024 easybill GmbH
+ *
+ * Permission is hereby granted, free of charge, to any person obtaining a copy
+ * of this software and associated documentation files (the "Software"), to deal
+ * in the Software without restriction, including without limitation the rights
+ * to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+ * copies of the Software, and to permit persons to whom the Software is
+ * furnished to do so, subject to the following conditions:
+ *
+ * The above copyright notice and this permission notice shall be included in all
+ * copies or substantial portions of the Software.
+ *
+ * THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+ * IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+ * FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+ * AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+ * LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+ * OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+ * SOFTWARE.
+ */
+
+use std::collections::{HashMap, VecDeque};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use tokio::sync::Mutex;
+
+use crate::config::DeploymentConfiguration;
+
+/// The window in which the deployments of a profile are counted.
+const RATE_LIMIT_WINDOW: Duration = Duration::from_secs(60 * 60);
+
+/// Keeps track of the deployments started per profile to enforce the configured deployment rate limits, protecting
+/// the servers from runaway automations that repeatedly redeploy.
+#[derive(Clone, Debug)]
+pub(crate) struct DeploymentRateLimitAccessor {
+    /// The points in time at which deployments were started within the rate limit window, keyed by the profile id.
+    deployment_start_times: Arc<Mutex<HashMap<String, VecDeque<Instant>>>>,
+}
+
+impl DeploymentRateLimitAccessor {
+    /// Constructs a new rate limit accessor without any recorded deployments.
+    pub fn new() -> Self {
+        Self {
+            deployment_start_times: Arc::new(Mutex::new(HashMap::new())),
+        }
+    }
+
+    /// Checks if a deployment with the given profile can be started, without recording it. Used to reject deployments
+    /// early, before they occupy the server, the deployment must be recorded once it was actually started. If the
+    /// limit is reached and the deployment is not forced, the time until the next deployment is possible is returned
+    /// as error.
+    ///
+    /// # Arguments
+    /// * `deployment_configuration` - The deployment profile configuration used for the deployment.
+    /// * `force` - If the deployment can be started even if the limit is reached.
+    pub async fn check_deployment(
+        &self,
+        deployment_configuration: &DeploymentConfiguration,
+        force: bool,
+    ) -> Result<(), Duration> {
+        self.check_and_record_deployment(deployment_configuration, force, false)
+            .await
+    }
+
+    /// Records the start of a deployment with the given profile, unless the profile reached its limit of deployments
+    /// within the last hour. If the limit is reached and the deployment is not forced, the time until the next
+    /// deployment is possible is returned as error. Must only be called once the deployment occupies the server, to
+    /// not count deployments that are rejected afterwards.
+    ///
+    /// # Arguments
+    /// * `deployment_configuration` - The deployment profile configuration used for the deployment.
+    /// * `force` - If the deployment should be recorded even if the limit is reached.
+    pub async fn try_record_deployment(
+        &self,
+        deployment_configuration: &DeploymentConfiguration,
+        force: bool,
+    ) -> Result<(), Duration> {
+        self.check_and_record_deployment(deployment_configuration, force, true)
+            .await
+    }
+
+    /// Checks if a deployment with the given profile can be started and records it if requested.
+    ///
+    /// # Arguments
+    /// * `deployment_configuration` - The deployment profile configuration used for the deployment.
+    /// * `force` - If the deployment is allowed even if the limit is reached.
+    /// * `record` - If the deployment should be recorded if it is allowed.
+    async fn check_and_record_deployment(
+        &self,
+        deployment_configuration: &DeploymentConfiguration,
+        force: bool,
+        record: bool,
+    ) -> Result<(), Duration> {
+        let max_deployments = match deployment_configuration.max_deployments_per_hour {
+            Some(max_deployments) => max_deployments as usize,
+            None => return Ok(()),
+        };
+
+        let now = Instant::now();
+        let mut deployment_start_times = self.deployment_start_times.lock().await;
+        let profile_start_times = deployment_start_times
+            .entry(deployment_configuration.id.clone())
+            .or_default();
+        while profile_start_times
+            .front()
+            .is_some_and(|start_time| now.duration_since(*start_time) >= RATE_LIMIT_WINDOW)
+        {
+            profile_start_times.pop_front();
+        }
+
+        if !force && profile_start_times.len() >= max_deployments {
+            let retry_after = profile_start_times
+                .front()
+                .map(|start_time| RATE_LIMIT_WINDOW.saturating_sub(now.duration_since(*start_time)))
+                .unwrap_or(RATE_LIMIT_WINDOW);
+            return Err(retry_after);
+        }
+
+        if record {
+            profile_start_times.push_back(now);
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,198 @@
+/*
+ * This file is part of easydep, licensed under the MIT License (MIT).
+ *
+ * Copyright (c) 2024 easybill GmbH
+ *
+ * Permission is hereby granted, free of charge, to any person obtaining a copy
+ * of this software and associated documentation files (the "Software"), to deal
+ * in the Software without restriction, including without limitation the rights
+ * to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+ * copies of the Software, and to permit persons to whom the Software is
+ * furnished to do so, subject to the following conditions:
+ *
+ * The above copyright notice and this permission notice shall be included in all
+ * copies or substantial portions of the Software.
+ *
+ * THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+ * IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+ * FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+ * AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+ * LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+ * OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+ * SOFTWARE.
+ */
+
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use anyhow::Context;
+use log::warn;
+use serde::Serialize;
+use tokio::fs;
+use tokio::sync::Mutex;
+
+use crate::accessor::release_metadata_accessor::ReleaseMetadataAccessor;
+use crate::config::Configuration;
+use crate::notification::deployment_notification::DeploymentNotification;
+
+/// The name of the file in an archive that contains the output of the failed action.
+const OUTPUT_FILE_NAME: &str = "output.log";
+/// The name of the file in an archive that contains the metadata of the failed action.
+const METADATA_FILE_NAME: &str = "metadata.json";
+
+/// The metadata of a failed action that is stored in its archive.
+#[derive(Serialize, Debug)]
+struct FailedActionMetadata<'a> {
+    /// The unix timestamp (in seconds) when the action was archived.
+    archived_at: u64,
+    /// The notification about the failed action.
+    #[serde(flatten)]
+    notification: &'a DeploymentNotification,
+    /// The metadata that was attached to the deployment of the release.
+    release_metadata: HashMap<String, String>,
+}
+
+/// An accessor for the archives of failed actions, stored in the state directory of the base directory. Each archive
+/// is a directory containing the output and the metadata of the failed action, so that the failure can be analyzed
+/// after the release directory was deleted. The combined size of all archives is capped, the oldest archives are
+/// deleted when the cap is exceeded.
+#[derive(Clone, Debug)]
+pub(crate) struct FailedActionArchiveAccessor {
+    archive_directory: PathBuf,
+    max_bytes: u64,
+    release_metadata_accessor: ReleaseMetadataAccessor,
+    write_lock: Arc<Mutex<()>>,
+}
+
+impl FailedActionArchiveAccessor {
+    /// Constructs a new failed action archive accessor storing the archives in the state directory of the base
+    /// directory.
+    ///
+    /// # Arguments
+    /// * `config` - The server configuration, used to get the deployment base directory and the archive size cap.
+    pub fn new(config: &Configuration) -> Self {
+        let archive_directory = PathBuf::from(&config.base_directory)
+            .join("state")
+            .join("failed");
+        Self {
+            archive_directory,
+            max_bytes: config.failed_archive_max_bytes,
+            release_metadata_accessor: ReleaseMetadataAccessor::new(config),
+            write_lock: Arc::new(Mutex::new(())),
+        }
+    }
+
+    /// Archives the output and metadata of the failed action described by the given notification, deleting the
+    /// oldest archives afterwards if the size cap is exceeded.
+    ///
+    /// # Arguments
+    /// * `notification` - The notification about the failed action.
+    /// * `output_lines` - The lines of output reported by the failed action.
+    ///
+    /// # Returns
+    /// * `Some(name)` - The name of the created archive directory.
+    /// * `None` - If archiving is disabled.
+    pub async fn archive_failed_action(
+        &self,
+        notification: &DeploymentNotification,
+        output_lines: &[String],
+    ) -> anyhow::Result<Option<String>> {
+        if self.max_bytes == 0 {
+            return Ok(None);
+        }
+
+        let archived_at = SystemTime::now().duration_since(UNIX_EPOCH)?;
+        let release_metadata = self
+            .release_metadata_accessor
+            .get_target_metadata(&notification.target, &notification.release_id)
+            .await
+            .unwrap_or_else(|err| {
+                warn!("Unable to read release metadata for failure archive: {err:?}");
+                HashMap::new()
+            });
+        let metadata = FailedActionMetadata {
+            archived_at: archived_at.as_secs(),
+            notification,
+            release_metadata,
+        };
+        let archive_name = format!(
+            "{}-{}-{}-{}",
+            archived_at.as_millis(),
+            notification.profile,
+            notification.release_id,
+            notification.action
+        );
+
+        let _write_guard = self.write_lock.lock().await;
+        let archive_path = self.archive_directory.join(&archive_name);
+        fs::create_dir_all(&archive_path)
+            .await
+            .context("unable to create failure archive directory")?;
+        let mut output = output_lines.join("\n");
+        output.push('\n');
+        fs::write(archive_path.join(OUTPUT_FILE_NAME), output)
+            .await
+            .context("unable to write archived output")?;
+        fs::write(
+            archive_path.join(METADATA_FILE_NAME),
+            serde_json::to_vec_pretty(&metadata)?,
+        )
+        .await
+        .context("unable to write archived metadata")?;
+
+        self.prune_archives(&archive_name).await?;
+        Ok(Some(archive_name))
+    }
+
+    /// Deletes the oldest archives until the combined size of all archives is below the size cap. The archive with the
+    /// given name is never deleted.
+    ///
+    /// # Arguments
+    /// * `retained_archive` - The name of the archive that was just created.
+    async fn prune_archives(&self, retained_archive: &str) -> anyhow::Result<()> {
+        let mut archives = Vec::new();
+        let mut directory_content = fs::read_dir(&self.archive_directory).await?;
+        while let Some(entry) = directory_content.next_entry().await? {
+            if !entry.file_type().await?.is_dir() {
+                continue;
+            }
+            let archive_size = get_archive_size(&entry.path()).await?;
+            archives.push((
+                entry.file_name().to_string_lossy().to_string(),
+                archive_size,
+            ));
+        }
+
+        // archive names start with the creation timestamp, sorting them by name sorts them from oldest to newest
+        archives.sort();
+        let mut total_size: u64 = archives.iter().map(|(_, size)| size).sum();
+        for (archive_name, archive_size) in archives {
+            if total_size <= self.max_bytes {
+                break;
+            }
+            if archive_name == retained_archive {
+                continue;
+            }
+            fs::remove_dir_all(self.archive_directory.join(&archive_name))
+                .await
+                .with_context(|| format!("unable to delete failure archive {archive_name}"))?;
+            total_size -= archive_size;
+        }
+        Ok(())
+    }
+}
+
+/// Get the combined size of the files in the given archive directory.
+///
+/// # Arguments
+/// * `archive_path` - The path to the archive directory.
+async fn get_archive_size(archive_path: &Path) -> anyhow::Result<u64> {
+    let mut archive_size = 0;
+    let mut directory_content = fs::read_dir(archive_path).await?;
+    while let Some(entry) = directory_content.next_entry().await? {
+        archive_size += entry.metadata().await?.len();
+    }
+    Ok(archive_size)
+}
@@ -22,19 +22,62 @@
  * SOFTWARE.
  */
 
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use anyhow::{bail, Context};
 use jsonwebtoken::EncodingKey;
+use log::warn;
 use octocrab::models::repos::Release;
-use octocrab::models::{AppId, Installation};
+use octocrab::models::{AppId, Installation, Rate};
 use octocrab::Octocrab;
-use secrecy::SecretString;
+use reqwest::header::{ACCEPT, AUTHORIZATION};
+use ring::digest::{Context as DigestContext, SHA256};
+use secrecy::{ExposeSecret, SecretString};
 use tokio::fs;
+use tokio::io::{AsyncWrite, AsyncWriteExt};
+use tokio::sync::RwLock;
 
 use crate::config::{Configuration, DeploymentConfiguration};
+use crate::hex::encode_hex;
+
+/// The user agent sent to GitHub when downloading release assets, GitHub rejects requests without one.
+const ASSET_DOWNLOAD_USER_AGENT: &str = "easydep-server";
+/// The time after which establishing the connection to download a release asset is aborted.
+const ASSET_DOWNLOAD_CONNECT_TIMEOUT: Duration = Duration::from_secs(30);
 
 /// An accessor for content stored on GitHub which can be accessed from a GitHub app. Only methods that are directly
 /// related to the deployment process are exposed.
+#[derive(Clone)]
 pub struct GitHubAccessor {
     github_client: Octocrab,
+    release_cache_ttl: Duration,
+    release_cache: Arc<RwLock<HashMap<ReleaseCacheKey, CachedRelease>>>,
+}
+
+/// The key of a cached release: the owner and name of the repository and the id of the release.
+type ReleaseCacheKey = (String, String, u64);
+
+/// A release that was fetched from GitHub and the time when it was fetched.
+#[derive(Clone)]
+struct CachedRelease {
+    release: Release,
+    fetched_at: Instant,
+}
+
+/// The result of checking the access to the source repository of a deployment configuration. The checks stop at the
+/// first check that failed, the information collected by the earlier checks is kept.
+#[derive(Default, Debug)]
+pub(crate) struct RepositoryAccessReport {
+    /// A description of the check that failed and how to resolve it, `None` if all checks succeeded.
+    pub error: Option<String>,
+    /// The id of the app installation that grants access to the repository.
+    pub installation_id: Option<u64>,
+    /// The permission that the installation grants on the contents of the repository.
+    pub contents_permission: Option<String>,
+    /// The rate limit of the installation.
+    pub rate_limit: Option<Rate>,
 }
 
 impl GitHubAccessor {
@@ -48,7 +91,11 @@ impl GitHubAccessor {
         let github_client = Octocrab::builder()
             .app(AppId::from(config.github_app_id), gh_app_rsa_key)
             .build()?;
-        Ok(Self { github_client })
+        Ok(Self {
+            github_client,
+            release_cache_ttl: Duration::from_secs(config.github_release_cache_ttl_seconds),
+            release_cache: Arc::new(RwLock::new(HashMap::new())),
+        })
     }
 
     /// Get the app installation token that can be used to make git https requests to repos the underlying app has access to.
@@ -67,7 +114,9 @@ impl GitHubAccessor {
         Ok(token)
     }
 
-    /// Get the release with the given id in the repo associated with the given deployment configuration.
+    /// Get the release with the given id in the repo associated with the given deployment configuration. Releases are
+    /// cached for the configured time, if GitHub cannot be reached the last fetched information of the release is
+    /// returned even if it expired.
     ///
     /// # Arguments
     /// * `release_id` - The id of the release to get.
@@ -76,6 +125,54 @@ impl GitHubAccessor {
         &self,
         release_id: &u64,
         deploy_config: &DeploymentConfiguration,
+    ) -> anyhow::Result<Release> {
+        let cache_key = (
+            deploy_config.source_repo_owner.clone(),
+            deploy_config.source_repo_name.clone(),
+            *release_id,
+        );
+        let cached_release = self.release_cache.read().await.get(&cache_key).cloned();
+        if let Some(cached_release) = &cached_release {
+            if cached_release.fetched_at.elapsed() < self.release_cache_ttl {
+                return Ok(cached_release.release.clone());
+            }
+        }
+
+        match self.fetch_release_by_id(release_id, deploy_config).await {
+            Ok(release) => {
+                let cached_release = CachedRelease {
+                    release: release.clone(),
+                    fetched_at: Instant::now(),
+                };
+                self.release_cache
+                    .write()
+                    .await
+                    .insert(cache_key, cached_release);
+                Ok(release)
+            }
+            Err(err) => match cached_release {
+                Some(cached_release) => {
+                    warn!(
+                        "Unable to fetch release {} from GitHub, using cached information: {err:?}",
+                        release_id
+                    );
+                    Ok(cached_release.release)
+                }
+                None => Err(err),
+            },
+        }
+    }
+
+    /// Fetches the release with the given id in the repo associated with the given deployment configuration from
+    /// GitHub, bypassing the release cache.
+    ///
+    /// # Arguments
+    /// * `release_id` - The id of the release to fetch.
+    /// * `deploy_config` - The deployment config for which the release should be fetched.
+    async fn fetch_release_by_id(
+        &self,
+        release_id: &u64,
+        deploy_config: &DeploymentConfiguration,
     ) -> anyhow::Result<Release> {
         let installation = self.find_installation(deploy_config).await?;
         let app_scoped_client = self.github_client.installation(installation.id);
@@ -90,6 +187,264 @@ impl GitHubAccessor {
         Ok(release)
     }
 
+    /// Get the id of the newest release in the repo associated with the given deployment configuration, returning 0
+    /// if no release was created in the repo yet.
+    ///
+    /// # Arguments
+    /// * `deploy_config` - The deployment config for which the newest release id should be retrieved.
+    pub async fn get_newest_release_id(
+        &self,
+        deploy_config: &DeploymentConfiguration,
+    ) -> anyhow::Result<u64> {
+        let installation = self.find_installation(deploy_config).await?;
+        let app_scoped_client = self.github_client.installation(installation.id);
+        let releases = app_scoped_client
+            .repos(
+                &deploy_config.source_repo_owner,
+                &deploy_config.source_repo_name,
+            )
+            .releases()
+            .list()
+            .per_page(1)
+            .send()
+            .await?;
+        Ok(releases
+            .items
+            .first()
+            .map(|release| release.id.0)
+            .unwrap_or_default())
+    }
+
+    /// Resolves the given git ref (tag, branch or commit SHA) to the full SHA of the commit it points to in the repo
+    /// associated with the given deployment configuration.
+    ///
+    /// # Arguments
+    /// * `git_ref` - The git ref to resolve.
+    /// * `deploy_config` - The deployment config in whose repo the git ref should be resolved.
+    pub async fn resolve_commit_sha(
+        &self,
+        git_ref: &str,
+        deploy_config: &DeploymentConfiguration,
+    ) -> anyhow::Result<String> {
+        let installation = self.find_installation(deploy_config).await?;
+        let app_scoped_client = self.github_client.installation(installation.id);
+        let commit = app_scoped_client
+            .commits(
+                &deploy_config.source_repo_owner,
+                &deploy_config.source_repo_name,
+            )
+            .get(git_ref)
+            .await?;
+        Ok(commit.sha)
+    }
+
+    /// Get the paths of the files that changed between the given base and head commit in the repo associated with the
+    /// given deployment configuration. Returns `None` if GitHub did not report the changed files, for example if too
+    /// many files were changed.
+    ///
+    /// # Arguments
+    /// * `base_commit` - The commit to compare from.
+    /// * `head_commit` - The commit to compare to.
+    /// * `deploy_config` - The deployment config in whose repo the commits should be compared.
+    pub async fn get_changed_files(
+        &self,
+        base_commit: &str,
+        head_commit: &str,
+        deploy_config: &DeploymentConfiguration,
+    ) -> anyhow::Result<Option<Vec<String>>> {
+        let installation = self.find_installation(deploy_config).await?;
+        let app_scoped_client = self.github_client.installation(installation.id);
+        let comparison = app_scoped_client
+            .commits(
+                &deploy_config.source_repo_owner,
+                &deploy_config.source_repo_name,
+            )
+            .compare(base_commit, head_commit)
+            .send()
+            .await?;
+        Ok(comparison.files.map(|files| {
+            files
+                .into_iter()
+                .flat_map(|file| [Some(file.filename), file.previous_filename])
+                .flatten()
+                .collect()
+        }))
+    }
+
+    /// Checks if the given path (a file or directory) exists in the repo associated with the given deployment
+    /// configuration.
+    ///
+    /// # Arguments
+    /// * `deploy_config` - The deployment config in whose repo the path should be checked.
+    /// * `path` - The path to check, relative to the repository root.
+    /// * `git_ref` - The git ref on which the path should be checked, the default branch if not given.
+    pub async fn path_exists(
+        &self,
+        deploy_config: &DeploymentConfiguration,
+        path: &str,
+        git_ref: Option<&str>,
+    ) -> anyhow::Result<bool> {
+        let installation = self.find_installation(deploy_config).await?;
+        let app_scoped_client = self.github_client.installation(installation.id);
+        let repo_handler = app_scoped_client.repos(
+            &deploy_config.source_repo_owner,
+            &deploy_config.source_repo_name,
+        );
+        let mut content_request = repo_handler.get_content().path(path);
+        if let Some(git_ref) = git_ref {
+            content_request = content_request.r#ref(git_ref);
+        }
+        match content_request.send().await {
+            Ok(_) => Ok(true),
+            Err(octocrab::Error::GitHub { source, .. }) if source.status_code.as_u16() == 404 => {
+                Ok(false)
+            }
+            Err(err) => Err(err.into()),
+        }
+    }
+
+    /// Downloads the asset with the given name that is attached to the given release into the given writer. The
+    /// download is authenticated with the given installation token, which allows to download assets of private repos.
+    ///
+    /// # Arguments
+    /// * `release` - The release to which the asset is attached.
+    /// * `asset_name` - The file name of the asset to download.
+    /// * `github_access_token` - The installation token of the repo the release belongs to.
+    /// * `writer` - The writer into which the content of the asset is written.
+    ///
+    /// # Returns
+    /// * `String` - The hex encoded SHA-256 checksum of the downloaded content.
+    pub async fn download_release_asset<W: AsyncWrite + Unpin>(
+        release: &Release,
+        asset_name: &str,
+        github_access_token: &SecretString,
+        writer: &mut W,
+    ) -> anyhow::Result<String> {
+        let asset = release
+            .assets
+            .iter()
+            .find(|asset| asset.name == asset_name)
+            .with_context(|| format!("release {} has no asset {}", release.tag_name, asset_name))?;
+        let http_client = reqwest::Client::builder()
+            .user_agent(ASSET_DOWNLOAD_USER_AGENT)
+            .connect_timeout(ASSET_DOWNLOAD_CONNECT_TIMEOUT)
+            .build()?;
+
+        // the api url of the asset redirects to the storage of the content, the token is not sent to that host
+        let mut response = http_client
+            .get(asset.url.as_str())
+            .header(ACCEPT, "application/octet-stream")
+            .header(
+                AUTHORIZATION,
+                format!("Bearer {}", github_access_token.expose_secret()),
+            )
+            .send()
+            .await?
+            .error_for_status()
+            .with_context(|| format!("unable to download asset {}", asset_name))?;
+        let mut digest_context = DigestContext::new(&SHA256);
+        while let Some(chunk) = response.chunk().await? {
+            digest_context.update(&chunk);
+            writer.write_all(&chunk).await?;
+        }
+        writer.flush().await?;
+        Ok(encode_hex(digest_context.finish().as_ref()))
+    }
+
+    /// Get the name of the GitHub app, which requires the app to authenticate with its id and private key.
+    pub async fn get_app_name(&self) -> anyhow::Result<String> {
+        let app = self.github_client.current().app().await.context(
+            "unable to authenticate as GitHub app, check the github_app_id and the pem key",
+        )?;
+        Ok(app.name)
+    }
+
+    /// Checks that the app can access the repository of the given deployment configuration: the app must be installed
+    /// on the repository, an installation token must be issued, the repository must be readable with the token and the
+    /// installation must grant access to the contents of the repository. The rate limit of the installation is
+    /// reported as well.
+    ///
+    /// # Arguments
+    /// * `deploy_config` - The deployment configuration whose repository access should be checked.
+    pub async fn check_repository_access(
+        &self,
+        deploy_config: &DeploymentConfiguration,
+    ) -> RepositoryAccessReport {
+        let mut access_report = RepositoryAccessReport::default();
+        if let Err(err) = self
+            .collect_repository_access(deploy_config, &mut access_report)
+            .await
+        {
+            access_report.error = Some(describe_github_error(&err));
+        }
+        access_report
+    }
+
+    /// Executes the checks of the repository access for the given deployment configuration, collecting the results
+    /// into the given report until a check fails.
+    ///
+    /// # Arguments
+    /// * `deploy_config` - The deployment configuration whose repository access should be checked.
+    /// * `access_report` - The report to collect the results of the checks into.
+    async fn collect_repository_access(
+        &self,
+        deploy_config: &DeploymentConfiguration,
+        access_report: &mut RepositoryAccessReport,
+    ) -> anyhow::Result<()> {
+        let repository = format!(
+            "{}/{}",
+            deploy_config.source_repo_owner, deploy_config.source_repo_name
+        );
+        let installation = self.find_installation(deploy_config).await.with_context(|| {
+            format!(
+                "the app is not installed on {}, install it on the repository or grant the installation access to it",
+                repository
+            )
+        })?;
+        access_report.installation_id = Some(installation.id.0);
+        access_report.contents_permission = installation.permissions.contents.clone();
+
+        self.github_client
+            .installation_and_token(installation.id)
+            .await
+            .with_context(|| {
+                format!(
+                    "unable to issue a token for installation {}, check that the installation is not suspended",
+                    installation.id
+                )
+            })?;
+        let app_scoped_client = self.github_client.installation(installation.id);
+        let rate_limit = app_scoped_client
+            .ratelimit()
+            .get()
+            .await
+            .context("unable to get the rate limit of the installation")?
+            .resources
+            .core;
+        let rate_limit_exhausted = rate_limit.remaining == 0;
+        access_report.rate_limit = Some(rate_limit);
+        if rate_limit_exhausted {
+            bail!("the installation exhausted its api rate limit, requests to GitHub fail until it resets")
+        }
+        app_scoped_client
+            .repos(
+                &deploy_config.source_repo_owner,
+                &deploy_config.source_repo_name,
+            )
+            .get()
+            .await
+            .with_context(|| {
+                format!(
+                    "unable to read {} with the installation token, grant the installation access to it",
+                    repository
+                )
+            })?;
+        if access_report.contents_permission.is_none() {
+            bail!("the installation grants no access to the repository contents, grant the app the contents permission")
+        }
+        Ok(())
+    }
+
     /// Finds the GitHub app installation for the repository in the given deployment configuration.
     ///
     /// # Arguments
@@ -109,3 +464,18 @@ impl GitHubAccessor {
         Ok(installation)
     }
 }
+
+/// Describes the given error of a GitHub request in a single line, containing the context and the causes of the error.
+/// The errors of the GitHub client can contain a backtrace, which is omitted.
+///
+/// # Arguments
+/// * `err` - The error to describe.
+pub(crate) fn describe_github_error(err: &anyhow::Error) -> String {
+    let description = format!("{err:#}");
+    description
+        .lines()
+        .next()
+        .unwrap_or(&description)
+        .trim_end()
+        .to_string()
+}
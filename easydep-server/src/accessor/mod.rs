@@ -22,7 +22,19 @@
  * SOFTWARE.
  */
 
+pub(crate) mod api_token_accessor;
+pub(crate) mod configuration_accessor;
 pub(crate) mod deploy_action_accessor;
 pub(crate) mod deploy_status_accessor;
 pub(crate) mod deployment_accessor;
+pub(crate) mod deployment_history_accessor;
+pub(crate) mod deployment_rate_limit_accessor;
+pub(crate) mod failed_action_archive_accessor;
 pub(crate) mod github_accessor;
+pub(crate) mod oidc_accessor;
+pub(crate) mod prepared_deployment_accessor;
+pub(crate) mod ref_deployment_accessor;
+pub(crate) mod release_metadata_accessor;
+pub(crate) mod release_tombstone_accessor;
+pub(crate) mod secret_accessor;
+pub(crate) mod server_restart_accessor;
@@ -0,0 +1,197 @@
+/*
+ * This file is part of easydep, licensed under the MIT License (MIT).
+ *
+ * Copyright (c) 2024 easybill GmbH
+ *
+ * Permission is hereby granted, free of charge, to any person obtaining a copy
+ * of this software and associated documentation files (the "Software"), to deal
+ * in the Software without restriction, including without limitation the rights
+ * to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+ * copies of the Software, and to permit persons to whom the Software is
+ * furnished to do so, subject to the following conditions:
+ *
+ * The above copyright notice and this permission notice shall be included in all
+ * copies or substantial portions of the Software.
+ *
+ * THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+ * IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+ * FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+ * AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+ * LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+ * OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+ * SOFTWARE.
+ */
+
+use std::collections::HashMap;
+use std::str::FromStr;
+use std::sync::{Arc, RwLock};
+use std::time::Duration;
+
+use anyhow::{anyhow, bail, Context};
+use jsonwebtoken::jwk::JwkSet;
+use jsonwebtoken::{decode, decode_header, Algorithm, DecodingKey, Validation};
+use log::{error, info};
+use serde::Deserialize;
+use serde_json::Value;
+
+use crate::config::{AccessRole, OidcConfiguration};
+
+/// The subset of the OpenID Connect discovery document that is needed to validate tokens.
+#[derive(Deserialize, Debug)]
+struct DiscoveryDocument {
+    /// The url where the signing keys of the issuer are published.
+    jwks_uri: String,
+}
+
+/// The information about a client that was extracted from a successfully validated token.
+#[derive(Clone, Debug)]
+pub(crate) struct ValidatedToken {
+    /// The subject (`sub` claim) of the token.
+    pub subject: String,
+    /// The roles that were granted to the subject on this server.
+    pub roles: Vec<AccessRole>,
+}
+
+/// An accessor for the signing keys of an OpenID Connect provider, used to validate bearer tokens sent by clients.
+#[derive(Clone, Debug)]
+pub(crate) struct OidcAccessor {
+    config: OidcConfiguration,
+    http_client: reqwest::Client,
+    signing_keys: Arc<RwLock<JwkSet>>,
+}
+
+impl OidcAccessor {
+    /// Constructs a new accessor for the given settings and initially loads the signing keys of the issuer.
+    ///
+    /// # Arguments
+    /// * `config` - The OpenID Connect settings from the server configuration.
+    pub async fn new(config: &OidcConfiguration) -> anyhow::Result<Self> {
+        let accessor = Self {
+            config: config.clone(),
+            http_client: reqwest::Client::new(),
+            signing_keys: Arc::new(RwLock::new(JwkSet { keys: Vec::new() })),
+        };
+        accessor.refresh_signing_keys().await?;
+        Ok(accessor)
+    }
+
+    /// Starts a background task that refreshes the signing keys of the issuer in the configured interval.
+    pub fn start_signing_key_refresh_task(&self) {
+        let accessor = self.clone();
+        let refresh_interval = Duration::from_secs(self.config.key_refresh_interval_seconds);
+        tokio::spawn(async move {
+            let mut interval = tokio::time::interval(refresh_interval);
+            interval.tick().await; // the first tick completes immediately, keys were loaded already
+            loop {
+                interval.tick().await;
+                if let Err(err) = accessor.refresh_signing_keys().await {
+                    error!("Unable to refresh OIDC signing keys: {err:?}");
+                }
+            }
+        });
+    }
+
+    /// Downloads the current signing keys of the issuer, replacing the previously known keys.
+    pub async fn refresh_signing_keys(&self) -> anyhow::Result<()> {
+        let discovery_url = format!(
+            "{}/.well-known/openid-configuration",
+            self.config.issuer.trim_end_matches('/')
+        );
+        let discovery_document: DiscoveryDocument = self
+            .http_client
+            .get(&discovery_url)
+            .send()
+            .await?
+            .error_for_status()?
+            .json()
+            .await
+            .context("unable to parse OIDC discovery document")?;
+        let signing_keys: JwkSet = self
+            .http_client
+            .get(&discovery_document.jwks_uri)
+            .send()
+            .await?
+            .error_for_status()?
+            .json()
+            .await
+            .context("unable to parse OIDC signing keys")?;
+
+        info!(
+            "Loaded {} OIDC signing keys from {}",
+            signing_keys.keys.len(),
+            discovery_document.jwks_uri
+        );
+        let mut write_guard = self
+            .signing_keys
+            .write()
+            .map_err(|_| anyhow!("signing key lock is poisoned"))?;
+        *write_guard = signing_keys;
+        Ok(())
+    }
+
+    /// Validates the given token, returning the subject and granted roles if the token is valid.
+    ///
+    /// # Arguments
+    /// * `token` - The encoded token to validate.
+    pub fn validate_token(&self, token: &str) -> anyhow::Result<ValidatedToken> {
+        let header = decode_header(token)?;
+        let key_id = header.kid.context("token does not specify a key id")?;
+        let (decoding_key, key_algorithm) = {
+            let read_guard = self
+                .signing_keys
+                .read()
+                .map_err(|_| anyhow!("signing key lock is poisoned"))?;
+            let signing_key = read_guard
+                .find(&key_id)
+                .with_context(|| format!("token signed with unknown key {key_id}"))?;
+            (
+                DecodingKey::from_jwk(signing_key)?,
+                signing_key.common.key_algorithm,
+            )
+        };
+
+        // the algorithm in the token header is chosen by the sender, only accept the algorithm declared by the key
+        let allowed_algorithms = match key_algorithm {
+            Some(key_algorithm) => {
+                let algorithm =
+                    Algorithm::from_str(&key_algorithm.to_string()).with_context(|| {
+                        format!("signing key {key_id} uses unsupported algorithm {key_algorithm}")
+                    })?;
+                vec![algorithm]
+            }
+            None => self.config.allowed_algorithms.clone(),
+        };
+        if !allowed_algorithms.contains(&header.alg) {
+            bail!(
+                "token signed with algorithm {:?} which is not allowed for key {key_id}",
+                header.alg
+            )
+        }
+
+        let mut validation = Validation::default();
+        validation.algorithms = allowed_algorithms;
+        validation.set_issuer(&[&self.config.issuer]);
+        validation.set_audience(&[&self.config.audience]);
+        validation.set_required_spec_claims(&["exp", "iss", "aud", "sub"]);
+        let claims = decode::<HashMap<String, Value>>(token, &decoding_key, &validation)?.claims;
+
+        let subject = match claims.get("sub") {
+            Some(Value::String(subject)) => subject.clone(),
+            _ => bail!("token subject is not a string"),
+        };
+        let role_names: Vec<&str> = match claims.get(&self.config.role_claim) {
+            Some(Value::String(role_name)) => vec![role_name.as_str()],
+            Some(Value::Array(role_names)) => role_names
+                .iter()
+                .filter_map(|role_name| role_name.as_str())
+                .collect(),
+            _ => Vec::new(),
+        };
+        let roles = role_names
+            .into_iter()
+            .filter_map(|role_name| self.config.role_mappings.get(role_name))
+            .copied()
+            .collect();
+        Ok(ValidatedToken { subject, roles })
+    }
+}
@@ -0,0 +1,129 @@
+/*
+ * This file is part of easydep, licensed under the MIT License (MIT).
+ *
+ * Copyright (c) 2024 easybill GmbH
+ *
+ * Permission is hereby granted, free of charge, to any person obtaining a copy
+ * of this software and associated documentation files (the "Software"), to deal
+ * in the Software without restriction, including without limitation the rights
+ * to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+ * copies of the Software, and to permit persons to whom the Software is
+ * furnished to do so, subject to the following conditions:
+ *
+ * The above copyright notice and this permission notice shall be included in all
+ * copies or substantial portions of the Software.
+ *
+ * THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+ * IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+ * FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+ * AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+ * LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+ * OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+ * SOFTWARE.
+ */
+
+use std::path::PathBuf;
+
+use anyhow::Context;
+use octocrab::models::repos::Release;
+use serde::{Deserialize, Serialize};
+use tokio::fs;
+
+use crate::config::Configuration;
+use crate::service::request_identity::RequestIdentity;
+
+/// The state of a deployment that was prepared and waits to be published or deleted, persisted to restore the
+/// deployment after the server restarted.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub(crate) struct PreparedDeployment {
+    /// The id of the deployment profile configuration used for the deployment.
+    pub profile: String,
+    /// The name of the slot in which the deployment was prepared, if the profile uses slots.
+    pub slot: Option<String>,
+    /// The release that was prepared.
+    pub release: Release,
+    /// The directory into which the release was prepared.
+    pub deployment_directory: PathBuf,
+    /// The reason that was given when starting the deployment, if any.
+    pub annotation_message: Option<String>,
+    /// The reference to the ticket that was given along with the reason, if any.
+    pub annotation_ticket_reference: Option<String>,
+    /// The identity of the user that started the deployment.
+    pub triggered_by: RequestIdentity,
+    /// The git ref that is deployed if the deployment was not started from a release.
+    pub git_ref: Option<String>,
+    /// The unix timestamp (in seconds) when the prepared deployment expires, if it expires.
+    pub expires_at: Option<u64>,
+}
+
+/// An accessor for the prepared deployment that is stored in the state directory of the base directory. As only one
+/// action can be executed at a time, at most one prepared deployment is stored.
+#[derive(Clone, Debug)]
+pub(crate) struct PreparedDeploymentAccessor {
+    state_file: PathBuf,
+}
+
+impl PreparedDeploymentAccessor {
+    /// Constructs a new prepared deployment accessor storing the prepared deployment in the state directory of the
+    /// base directory.
+    ///
+    /// # Arguments
+    /// * `config` - The server configuration, used to get the deployment base directory.
+    pub fn new(config: &Configuration) -> Self {
+        let state_file = PathBuf::from(&config.base_directory)
+            .join("state")
+            .join("prepared-deployment.json");
+        Self { state_file }
+    }
+
+    /// Get the prepared deployment that was stored, returning `None` if no deployment is prepared.
+    pub async fn get_prepared_deployment(&self) -> anyhow::Result<Option<PreparedDeployment>> {
+        if !fs::try_exists(&self.state_file).await? {
+            return Ok(None);
+        }
+
+        let state_file_content = fs::read(&self.state_file).await?;
+        let prepared_deployment =
+            serde_json::from_slice(&state_file_content).with_context(|| {
+                format!(
+                    "unable to parse prepared deployment file {:?}",
+                    self.state_file
+                )
+            })?;
+        Ok(Some(prepared_deployment))
+    }
+
+    /// Stores the given prepared deployment, replacing the deployment that was stored before.
+    ///
+    /// # Arguments
+    /// * `prepared_deployment` - The prepared deployment to store.
+    pub async fn store_prepared_deployment(
+        &self,
+        prepared_deployment: &PreparedDeployment,
+    ) -> anyhow::Result<()> {
+        let serialized_deployment = serde_json::to_vec_pretty(prepared_deployment)?;
+        if let Some(state_directory) = self.state_file.parent() {
+            fs::create_dir_all(state_directory)
+                .await
+                .context("unable to create state directory")?;
+        }
+
+        // write to a temporary file first to not leave a partially written state file behind
+        let temporary_file = self.state_file.with_extension("json.tmp");
+        fs::write(&temporary_file, serialized_deployment)
+            .await
+            .context("unable to write prepared deployment file")?;
+        fs::rename(&temporary_file, &self.state_file)
+            .await
+            .context("unable to replace prepared deployment file")?;
+        Ok(())
+    }
+
+    /// Removes the stored prepared deployment, as it was published or deleted.
+    pub async fn remove_prepared_deployment(&self) -> anyhow::Result<()> {
+        if fs::try_exists(&self.state_file).await? {
+            fs::remove_file(&self.state_file).await?;
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,291 @@
+/*
+ * This file is part of easydep, licensed under the MIT License (MIT).
+ *
+ * Copyright (c) 2024 easybill GmbH
+ *
+ * Permission is hereby granted, free of charge, to any person obtaining a copy
+ * of this software and associated documentation files (the "Software"), to deal
+ * in the Software without restriction, including without limitation the rights
+ * to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+ * copies of the Software, and to permit persons to whom the Software is
+ * furnished to do so, subject to the following conditions:
+ *
+ * The above copyright notice and this permission notice shall be included in all
+ * copies or substantial portions of the Software.
+ *
+ * THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+ * IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+ * FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+ * AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+ * LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+ * OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+ * SOFTWARE.
+ */
+
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use anyhow::{bail, Context};
+use octocrab::models::repos::Release;
+use octocrab::models::ReleaseId;
+use serde::{Deserialize, Serialize};
+use tokio::fs;
+use tokio::sync::Mutex;
+
+use crate::config::{Configuration, DeploymentConfiguration};
+
+/// A deployment that was started from a git ref (tag, branch or commit SHA) rather than from a GitHub release.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub(crate) struct RefDeployment {
+    /// The id that was assigned to the deployment, used in place of a release id.
+    pub release_id: u64,
+    /// The git ref that was requested to be deployed.
+    pub git_ref: String,
+    /// The SHA of the commit that the git ref resolved to.
+    pub commit_sha: String,
+    /// The unix timestamp (in seconds) when the deployment was started.
+    pub created_at: u64,
+}
+
+impl RefDeployment {
+    /// Constructs a release from this ref deployment. The tag name of the release is the requested git ref and the
+    /// target commitish is the resolved commit SHA.
+    ///
+    /// # Arguments
+    /// * `profile` - The profile in which the ref was deployed.
+    pub fn to_release(&self, profile: &DeploymentConfiguration) -> anyhow::Result<Release> {
+        let commit_url = format!(
+            "https://github.com/{}/{}/commit/{}",
+            profile.source_repo_owner, profile.source_repo_name, self.commit_sha
+        );
+        Ok(Release {
+            url: commit_url.parse()?,
+            html_url: commit_url.parse()?,
+            assets_url: commit_url.parse()?,
+            upload_url: commit_url,
+            tarball_url: None,
+            zipball_url: None,
+            id: ReleaseId(self.release_id),
+            node_id: String::new(),
+            tag_name: self.git_ref.clone(),
+            target_commitish: self.commit_sha.clone(),
+            name: Some(format!("{} ({})", self.git_ref, self.commit_sha)),
+            body: None,
+            draft: false,
+            prerelease: false,
+            created_at: None,
+            published_at: None,
+            author: None,
+            assets: Vec::new(),
+        })
+    }
+}
+
+/// An accessor for the deployments that were started from a git ref, stored per deployment target on the disk.
+#[derive(Clone, Debug)]
+pub(crate) struct RefDeploymentAccessor {
+    ref_deployment_directory: PathBuf,
+    write_lock: Arc<Mutex<()>>,
+}
+
+impl RefDeploymentAccessor {
+    /// Constructs a new ref deployment accessor storing the deployments in the state directory of the base directory.
+    ///
+    /// # Arguments
+    /// * `config` - The server configuration, used to get the deployment base directory.
+    pub fn new(config: &Configuration) -> Self {
+        let ref_deployment_directory = PathBuf::from(&config.base_directory)
+            .join("state")
+            .join("ref-deployments");
+        Self {
+            ref_deployment_directory,
+            write_lock: Arc::new(Mutex::new(())),
+        }
+    }
+
+    /// Get the ref deployment with the given id in the target of the given profile, returning `None` if the id
+    /// does not belong to a deployment that was started from a git ref.
+    ///
+    /// # Arguments
+    /// * `profile` - The profile to get the ref deployment in.
+    /// * `release_id` - The id of the ref deployment.
+    pub async fn get_ref_deployment(
+        &self,
+        profile: &DeploymentConfiguration,
+        release_id: &u64,
+    ) -> anyhow::Result<Option<RefDeployment>> {
+        let ref_deployments = self.read_ref_deployments(profile).await?;
+        Ok(ref_deployments
+            .into_iter()
+            .find(|ref_deployment| ref_deployment.release_id == *release_id))
+    }
+
+    /// Get the most recent deployment of the given git ref in the target of the given profile, returning `None` if the
+    /// git ref was never deployed.
+    ///
+    /// # Arguments
+    /// * `profile` - The profile to get the ref deployment in.
+    /// * `git_ref` - The git ref to get the most recent deployment of.
+    pub async fn get_latest_ref_deployment(
+        &self,
+        profile: &DeploymentConfiguration,
+        git_ref: &str,
+    ) -> anyhow::Result<Option<RefDeployment>> {
+        let ref_deployments = self.read_ref_deployments(profile).await?;
+        Ok(ref_deployments
+            .into_iter()
+            .filter(|ref_deployment| ref_deployment.git_ref == git_ref)
+            .max_by_key(|ref_deployment| ref_deployment.release_id))
+    }
+
+    /// Records the given planned deployment of a git ref in the target of the given profile, for example once the
+    /// deployment was actually started. Fails if the id of the deployment was assigned to another recorded deployment
+    /// in the meantime.
+    ///
+    /// # Arguments
+    /// * `profile` - The profile in which the git ref is deployed.
+    /// * `ref_deployment` - The planned deployment to record.
+    pub async fn record_ref_deployment(
+        &self,
+        profile: &DeploymentConfiguration,
+        ref_deployment: &RefDeployment,
+    ) -> anyhow::Result<()> {
+        let _write_guard = self.write_lock.lock().await;
+        let mut ref_deployments = self.read_ref_deployments(profile).await?;
+        if ref_deployments
+            .iter()
+            .any(|recorded| recorded.release_id == ref_deployment.release_id)
+        {
+            bail!(
+                "id {} was assigned to another ref deployment",
+                ref_deployment.release_id
+            )
+        }
+        ref_deployments.push(ref_deployment.clone());
+        self.write_ref_deployments(profile, &ref_deployments).await
+    }
+
+    /// Constructs the deployment of the given git ref that would be recorded in the target of the given profile,
+    /// without recording it. The id of the deployment is the id following the given base id which is neither used by
+    /// a recorded ref deployment nor rejected by the given predicate, but might be assigned to another deployment that
+    /// is recorded in the meantime.
+    ///
+    /// # Arguments
+    /// * `profile` - The profile in which the git ref would be deployed.
+    /// * `git_ref` - The git ref that would be deployed.
+    /// * `commit_sha` - The SHA of the commit that the git ref resolved to.
+    /// * `base_release_id` - The id after which the id of the deployment should be assigned.
+    /// * `is_id_used` - A predicate to check if an id is used elsewhere, for example by an existing directory.
+    pub async fn plan_ref_deployment(
+        &self,
+        profile: &DeploymentConfiguration,
+        git_ref: String,
+        commit_sha: String,
+        base_release_id: u64,
+        is_id_used: impl Fn(u64) -> bool,
+    ) -> anyhow::Result<RefDeployment> {
+        let ref_deployments = self.read_ref_deployments(profile).await?;
+        Ok(new_ref_deployment(
+            &ref_deployments,
+            git_ref,
+            commit_sha,
+            base_release_id,
+            is_id_used,
+        ))
+    }
+
+    /// Reads all ref deployments that are stored for the target of the given profile.
+    ///
+    /// # Arguments
+    /// * `profile` - The profile to read the ref deployments of.
+    async fn read_ref_deployments(
+        &self,
+        profile: &DeploymentConfiguration,
+    ) -> anyhow::Result<Vec<RefDeployment>> {
+        let ref_deployment_file = self.get_ref_deployment_file(profile);
+        if !fs::try_exists(&ref_deployment_file).await? {
+            return Ok(Vec::new());
+        }
+
+        let ref_deployment_file_content = fs::read(&ref_deployment_file).await?;
+        let ref_deployments =
+            serde_json::from_slice(&ref_deployment_file_content).with_context(|| {
+                format!(
+                    "unable to parse ref deployment file {:?}",
+                    ref_deployment_file
+                )
+            })?;
+        Ok(ref_deployments)
+    }
+
+    /// Writes the given ref deployments into the file of the target of the given profile, replacing the stored ones.
+    ///
+    /// # Arguments
+    /// * `profile` - The profile to write the ref deployments of.
+    /// * `ref_deployments` - The ref deployments to write.
+    async fn write_ref_deployments(
+        &self,
+        profile: &DeploymentConfiguration,
+        ref_deployments: &[RefDeployment],
+    ) -> anyhow::Result<()> {
+        let serialized_ref_deployments = serde_json::to_vec_pretty(ref_deployments)?;
+        fs::create_dir_all(&self.ref_deployment_directory)
+            .await
+            .context("unable to create ref deployment directory")?;
+        fs::write(
+            self.get_ref_deployment_file(profile),
+            serialized_ref_deployments,
+        )
+        .await
+        .context("unable to write ref deployment file")?;
+        Ok(())
+    }
+
+    /// Get the path to the file in which the ref deployments of the target of the given profile are stored. Ref
+    /// deployments are stored per target as the release directories are shared between profiles with the same target.
+    ///
+    /// # Arguments
+    /// * `profile` - The profile to get the ref deployment file path of.
+    fn get_ref_deployment_file(&self, profile: &DeploymentConfiguration) -> PathBuf {
+        self.ref_deployment_directory
+            .join(format!("{}.json", profile.target))
+    }
+}
+
+/// Constructs a new deployment of the given git ref, started now. The id of the deployment is the id following the
+/// given base id which is neither used by one of the given ref deployments nor rejected by the given predicate.
+///
+/// # Arguments
+/// * `ref_deployments` - The ref deployments that are recorded for the target of the deployment.
+/// * `git_ref` - The git ref that is deployed.
+/// * `commit_sha` - The SHA of the commit that the git ref resolved to.
+/// * `base_release_id` - The id after which the id of the deployment should be assigned.
+/// * `is_id_used` - A predicate to check if an id is used elsewhere, for example by an existing directory.
+fn new_ref_deployment(
+    ref_deployments: &[RefDeployment],
+    git_ref: String,
+    commit_sha: String,
+    base_release_id: u64,
+    is_id_used: impl Fn(u64) -> bool,
+) -> RefDeployment {
+    let mut release_id = base_release_id + 1;
+    while is_id_used(release_id)
+        || ref_deployments
+            .iter()
+            .any(|ref_deployment| ref_deployment.release_id == release_id)
+    {
+        release_id += 1;
+    }
+
+    let created_at = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|duration| duration.as_secs())
+        .unwrap_or_default();
+    RefDeployment {
+        release_id,
+        git_ref,
+        commit_sha,
+        created_at,
+    }
+}
@@ -0,0 +1,168 @@
+/*
+ * This file is part of easydep, licensed under the MIT License (MIT).
+ *
+ * Copyright (c) 2024 easybill GmbH
+ *
+ * Permission is hereby granted, free of charge, to any person obtaining a copy
+ * of this software and associated documentation files (the "Software"), to deal
+ * in the Software without restriction, including without limitation the rights
+ * to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+ * copies of the Software, and to permit persons to whom the Software is
+ * furnished to do so, subject to the following conditions:
+ *
+ * The above copyright notice and this permission notice shall be included in all
+ * copies or substantial portions of the Software.
+ *
+ * THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+ * IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+ * FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+ * AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+ * LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+ * OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+ * SOFTWARE.
+ */
+
+use std::collections::HashMap;
+use std::path::PathBuf;
+
+use anyhow::{bail, Context};
+use tokio::fs;
+
+use crate::config::{Configuration, DeploymentConfiguration};
+
+/// The maximum amount of metadata entries that can be attached to a deployment.
+const MAX_METADATA_ENTRIES: usize = 32;
+/// The maximum length of a metadata key.
+const MAX_METADATA_KEY_LENGTH: usize = 64;
+/// The maximum length of a metadata value.
+const MAX_METADATA_VALUE_LENGTH: usize = 1024;
+
+/// An accessor for the metadata that was attached to deployments, stored per release of a deployment target on the
+/// disk.
+#[derive(Clone, Debug)]
+pub(crate) struct ReleaseMetadataAccessor {
+    metadata_directory: PathBuf,
+}
+
+impl ReleaseMetadataAccessor {
+    /// Constructs a new metadata accessor storing the metadata in the state directory of the base directory.
+    ///
+    /// # Arguments
+    /// * `config` - The server configuration, used to get the deployment base directory.
+    pub fn new(config: &Configuration) -> Self {
+        let metadata_directory = PathBuf::from(&config.base_directory)
+            .join("state")
+            .join("release-metadata");
+        Self { metadata_directory }
+    }
+
+    /// Get the metadata that was attached to the deployment of the given release in the target of the given profile.
+    /// An empty map is returned if no metadata was attached.
+    ///
+    /// # Arguments
+    /// * `profile` - The profile to get the release metadata in.
+    /// * `release_id` - The id of the release to get the metadata of.
+    pub async fn get_metadata(
+        &self,
+        profile: &DeploymentConfiguration,
+        release_id: &u64,
+    ) -> anyhow::Result<HashMap<String, String>> {
+        self.get_target_metadata(&profile.target, release_id).await
+    }
+
+    /// Get the metadata that was attached to the deployment of the given release in the given deployment target. An
+    /// empty map is returned if no metadata was attached.
+    ///
+    /// # Arguments
+    /// * `target` - The deployment target to get the release metadata in.
+    /// * `release_id` - The id of the release to get the metadata of.
+    pub async fn get_target_metadata(
+        &self,
+        target: &str,
+        release_id: &u64,
+    ) -> anyhow::Result<HashMap<String, String>> {
+        let metadata_file = self.get_metadata_file(target, release_id);
+        if !fs::try_exists(&metadata_file).await? {
+            return Ok(HashMap::new());
+        }
+
+        let metadata_file_content = fs::read(&metadata_file).await?;
+        let metadata = serde_json::from_slice(&metadata_file_content)
+            .with_context(|| format!("unable to parse metadata file {:?}", metadata_file))?;
+        Ok(metadata)
+    }
+
+    /// Stores the given metadata for the given release in the target of the given profile, replacing the metadata
+    /// that was stored for a previous deployment of the release. Nothing is stored if the given metadata is empty.
+    ///
+    /// # Arguments
+    /// * `profile` - The profile in which the release is deployed.
+    /// * `release_id` - The id of the deployed release.
+    /// * `metadata` - The metadata to store.
+    pub async fn store_metadata(
+        &self,
+        profile: &DeploymentConfiguration,
+        release_id: &u64,
+        metadata: &HashMap<String, String>,
+    ) -> anyhow::Result<()> {
+        let metadata_file = self.get_metadata_file(&profile.target, release_id);
+        if metadata.is_empty() {
+            if fs::try_exists(&metadata_file).await? {
+                fs::remove_file(&metadata_file).await?;
+            }
+            return Ok(());
+        }
+
+        let serialized_metadata = serde_json::to_vec_pretty(metadata)?;
+        fs::create_dir_all(self.metadata_directory.join(&profile.target))
+            .await
+            .context("unable to create release metadata directory")?;
+        fs::write(&metadata_file, serialized_metadata)
+            .await
+            .context("unable to write release metadata file")?;
+        Ok(())
+    }
+
+    /// Get the path to the file in which the metadata of the given release in the given deployment target is stored.
+    ///
+    /// # Arguments
+    /// * `target` - The deployment target to get the metadata file path in.
+    /// * `release_id` - The id of the release to get the metadata file path of.
+    fn get_metadata_file(&self, target: &str, release_id: &u64) -> PathBuf {
+        self.metadata_directory
+            .join(target)
+            .join(format!("{}.json", release_id))
+    }
+}
+
+/// Validates that the given metadata can be attached to a deployment, returning an error describing the first invalid
+/// entry if that is not the case.
+///
+/// # Arguments
+/// * `metadata` - The metadata to validate.
+pub(crate) fn validate_metadata(metadata: &HashMap<String, String>) -> anyhow::Result<()> {
+    if metadata.len() > MAX_METADATA_ENTRIES {
+        bail!(
+            "at most {} metadata entries are allowed",
+            MAX_METADATA_ENTRIES
+        )
+    }
+    for (key, value) in metadata {
+        let valid_key = !key.is_empty()
+            && key.len() <= MAX_METADATA_KEY_LENGTH
+            && key
+                .chars()
+                .all(|char| char.is_ascii_alphanumeric() || matches!(char, '.' | '_' | '-'));
+        if !valid_key {
+            bail!("metadata key {:?} is invalid", key)
+        }
+        if value.chars().count() > MAX_METADATA_VALUE_LENGTH {
+            bail!(
+                "value of metadata key {} exceeds {} characters",
+                key,
+                MAX_METADATA_VALUE_LENGTH
+            )
+        }
+    }
+    Ok(())
+}
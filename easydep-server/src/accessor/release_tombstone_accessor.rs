@@ -0,0 +1,158 @@
+/*
+ * This file is part of easydep, licensed under the MIT License (MIT).
+ *
+ * Copyright (c) 2024 easybill GmbH
+ *
+ * Permission is hereby granted, free of charge, to any person obtaining a copy
+ * of this software and associated documentation files (the "Software"), to deal
+ * in the Software without restriction, including without limitation the rights
+ * to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+ * copies of the Software, and to permit persons to whom the Software is
+ * furnished to do so, subject to the following conditions:
+ *
+ * The above copyright notice and this permission notice shall be included in all
+ * copies or substantial portions of the Software.
+ *
+ * THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+ * IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+ * FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+ * AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+ * LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+ * OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+ * SOFTWARE.
+ */
+
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use anyhow::Context;
+use serde::{Deserialize, Serialize};
+use tokio::fs;
+use tokio::sync::Mutex;
+
+use crate::config::{Configuration, DeploymentConfiguration};
+use crate::service::request_identity::RequestIdentity;
+
+/// A release that was marked as bad for a deployment profile and must not be deployed again.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub(crate) struct ReleaseTombstone {
+    /// The id of the release that was marked as bad.
+    pub release_id: u64,
+    /// The reason why the release was marked as bad.
+    pub reason: String,
+    /// The unix timestamp (in seconds) when the release was marked as bad.
+    pub marked_at: u64,
+    /// The identity of the user that marked the release as bad.
+    #[serde(default)]
+    pub marked_by: String,
+}
+
+/// An accessor for the releases that were marked as bad, stored per profile on the disk.
+#[derive(Clone, Debug)]
+pub(crate) struct ReleaseTombstoneAccessor {
+    tombstone_directory: PathBuf,
+    write_lock: Arc<Mutex<()>>,
+}
+
+impl ReleaseTombstoneAccessor {
+    /// Constructs a new tombstone accessor storing the tombstones in the state directory of the base directory.
+    ///
+    /// # Arguments
+    /// * `config` - The server configuration, used to get the deployment base directory.
+    pub fn new(config: &Configuration) -> Self {
+        let tombstone_directory = PathBuf::from(&config.base_directory)
+            .join("state")
+            .join("tombstones");
+        Self {
+            tombstone_directory,
+            write_lock: Arc::new(Mutex::new(())),
+        }
+    }
+
+    /// Get the tombstone of the given release in the given profile, returning `None` if the release was not marked
+    /// as bad.
+    ///
+    /// # Arguments
+    /// * `profile` - The profile to get the tombstone of the release in.
+    /// * `release_id` - The id of the release to get the tombstone of.
+    pub async fn get_tombstone(
+        &self,
+        profile: &DeploymentConfiguration,
+        release_id: &u64,
+    ) -> anyhow::Result<Option<ReleaseTombstone>> {
+        let tombstones = self.read_tombstones(profile).await?;
+        Ok(tombstones
+            .into_iter()
+            .find(|tombstone| tombstone.release_id == *release_id))
+    }
+
+    /// Marks the given release as bad in the given profile. If the release was already marked as bad the recorded
+    /// reason is replaced with the given reason.
+    ///
+    /// # Arguments
+    /// * `profile` - The profile in which the release should be marked as bad.
+    /// * `release_id` - The id of the release to mark as bad.
+    /// * `reason` - The reason why the release is marked as bad.
+    /// * `marked_by` - The identity of the user that marked the release as bad.
+    pub async fn mark_release_bad(
+        &self,
+        profile: &DeploymentConfiguration,
+        release_id: u64,
+        reason: String,
+        marked_by: &RequestIdentity,
+    ) -> anyhow::Result<ReleaseTombstone> {
+        let _write_guard = self.write_lock.lock().await;
+        let mut tombstones = self.read_tombstones(profile).await?;
+        tombstones.retain(|tombstone| tombstone.release_id != release_id);
+
+        let marked_at = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|duration| duration.as_secs())
+            .unwrap_or_default();
+        let tombstone = ReleaseTombstone {
+            release_id,
+            reason,
+            marked_at,
+            marked_by: marked_by.to_string(),
+        };
+        tombstones.push(tombstone.clone());
+
+        let serialized_tombstones = serde_json::to_vec_pretty(&tombstones)?;
+        fs::create_dir_all(&self.tombstone_directory)
+            .await
+            .context("unable to create tombstone directory")?;
+        fs::write(self.get_tombstone_file(profile), serialized_tombstones)
+            .await
+            .context("unable to write tombstone file")?;
+        Ok(tombstone)
+    }
+
+    /// Reads all tombstones that are stored for the given profile.
+    ///
+    /// # Arguments
+    /// * `profile` - The profile to read the tombstones of.
+    async fn read_tombstones(
+        &self,
+        profile: &DeploymentConfiguration,
+    ) -> anyhow::Result<Vec<ReleaseTombstone>> {
+        let tombstone_file = self.get_tombstone_file(profile);
+        if !fs::try_exists(&tombstone_file).await? {
+            return Ok(Vec::new());
+        }
+
+        let tombstone_file_content = fs::read(&tombstone_file).await?;
+        let tombstones = serde_json::from_slice(&tombstone_file_content)
+            .with_context(|| format!("unable to parse tombstone file {:?}", tombstone_file))?;
+        Ok(tombstones)
+    }
+
+    /// Get the path to the file in which the tombstones of the given profile are stored.
+    ///
+    /// # Arguments
+    /// * `profile` - The profile to get the tombstone file path of.
+    fn get_tombstone_file(&self, profile: &DeploymentConfiguration) -> PathBuf {
+        self.tombstone_directory
+            .join(format!("{}.json", profile.id))
+    }
+}
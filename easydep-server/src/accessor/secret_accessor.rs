@@ -0,0 +1,162 @@
+/*
+ * This file is part of easydep, licensed under the MIT License (MIT).
+ *
+ * Copyright (c) 2024 easybill GmbH
+ *
+ * Permission is hereby granted, free of charge, to any person obtaining a copy
+ * of this software and associated documentation files (the "Software"), to deal
+ * in the Software without restriction, including without limitation the rights
+ * to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+ * copies of the Software, and to permit persons to whom the Software is
+ * furnished to do so, subject to the following conditions:
+ *
+ * The above copyright notice and this permission notice shall be included in all
+ * copies or substantial portions of the Software.
+ *
+ * THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+ * IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+ * FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+ * AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+ * LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+ * OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+ * SOFTWARE.
+ */
+
+use std::env;
+
+use anyhow::{bail, Context};
+use secrecy::SecretString;
+use serde_json::Value;
+use tokio::fs;
+use tokio::process::Command;
+
+use crate::config::{Configuration, SecretReference, SecretSource, VaultConfiguration};
+
+/// A secret that was resolved from a secret provider.
+#[derive(Clone, Debug)]
+pub(crate) struct ResolvedSecret {
+    /// The reference from which the secret was resolved.
+    pub reference: SecretReference,
+    /// The value of the secret.
+    pub value: SecretString,
+}
+
+/// An accessor to resolve secrets that are referenced in deployment configurations from the secret providers.
+#[derive(Clone, Debug)]
+pub(crate) struct SecretAccessor {
+    vault_configuration: Option<VaultConfiguration>,
+    http_client: reqwest::Client,
+}
+
+impl SecretAccessor {
+    /// Constructs a new secret accessor using the provider settings from the given server configuration.
+    ///
+    /// # Arguments
+    /// * `config` - The server configuration.
+    pub fn new(config: &Configuration) -> Self {
+        Self {
+            vault_configuration: config.vault.clone(),
+            http_client: reqwest::Client::new(),
+        }
+    }
+
+    /// Resolves all the given secret references, returning an error if one of them cannot be resolved.
+    ///
+    /// # Arguments
+    /// * `references` - The references of the secrets to resolve.
+    pub async fn resolve_secrets(
+        &self,
+        references: &[SecretReference],
+    ) -> anyhow::Result<Vec<ResolvedSecret>> {
+        let mut resolved_secrets = Vec::with_capacity(references.len());
+        for reference in references {
+            let value = match reference.source {
+                SecretSource::Vault => self.resolve_vault_secret(&reference.reference).await,
+                SecretSource::Ssm => self.resolve_ssm_secret(&reference.reference).await,
+                SecretSource::File => self.resolve_file_secret(&reference.reference).await,
+            }
+            .with_context(|| format!("unable to resolve secret {}", reference.reference))?;
+            resolved_secrets.push(ResolvedSecret {
+                reference: reference.clone(),
+                value: SecretString::new(value),
+            });
+        }
+        Ok(resolved_secrets)
+    }
+
+    /// Resolves a secret from the KV v2 engine of vault. The reference must be in the form `<path>#<key>`.
+    ///
+    /// # Arguments
+    /// * `reference` - The reference to the secret in vault.
+    async fn resolve_vault_secret(&self, reference: &str) -> anyhow::Result<String> {
+        let vault_configuration = self
+            .vault_configuration
+            .as_ref()
+            .context("vault is not configured")?;
+        let (secret_path, secret_key) = reference
+            .split_once('#')
+            .context("vault secret reference must be in the form <path>#<key>")?;
+        let vault_token = match &vault_configuration.token_file {
+            Some(token_file) => fs::read_to_string(token_file).await?.trim().to_string(),
+            None => env::var("VAULT_TOKEN").context("VAULT_TOKEN is not set")?,
+        };
+
+        let secret_url = format!(
+            "{}/v1/{}",
+            vault_configuration.address.trim_end_matches('/'),
+            secret_path.trim_start_matches('/')
+        );
+        let response: Value = self
+            .http_client
+            .get(secret_url)
+            .header("X-Vault-Token", vault_token)
+            .send()
+            .await?
+            .error_for_status()?
+            .json()
+            .await?;
+        match response.pointer(&format!("/data/data/{secret_key}")) {
+            Some(Value::String(secret_value)) => Ok(secret_value.clone()),
+            Some(_) => bail!("vault secret key {secret_key} is not a string"),
+            None => bail!("vault secret has no key {secret_key}"),
+        }
+    }
+
+    /// Resolves a parameter from the AWS SSM parameter store using the aws cli.
+    ///
+    /// # Arguments
+    /// * `reference` - The name of the parameter.
+    async fn resolve_ssm_secret(&self, reference: &str) -> anyhow::Result<String> {
+        let output = Command::new("aws")
+            .arg("ssm")
+            .arg("get-parameter")
+            .arg("--with-decryption")
+            .arg("--name")
+            .arg(reference)
+            .arg("--query")
+            .arg("Parameter.Value")
+            .arg("--output")
+            .arg("text")
+            .output()
+            .await
+            .context("unable to spawn aws cli")?;
+        if !output.status.success() {
+            let stderr_output = String::from_utf8_lossy(output.stderr.as_slice());
+            bail!(
+                "aws cli exited with {}: {}",
+                output.status,
+                stderr_output.trim()
+            )
+        }
+        Ok(String::from_utf8(output.stdout)?.trim_end().to_string())
+    }
+
+    /// Resolves a secret from a file on the local disk, removing the trailing line break.
+    ///
+    /// # Arguments
+    /// * `reference` - The path to the file containing the secret.
+    async fn resolve_file_secret(&self, reference: &str) -> anyhow::Result<String> {
+        let file_content = fs::read_to_string(reference).await?;
+        Ok(file_content.trim_end_matches(['\r', '\n']).to_string())
+    }
+}
@@ -0,0 +1,63 @@
+/*
+ * This file is part of easydep, licensed under the MIT License (MIT).
+ *
+ * Copyright (c) 2024 easybill GmbH
+ *
+ * Permission is hereby granted, free of charge, to any person obtaining a copy
+ * of this software and associated documentation files (the "Software"), to deal
+ * in the Software without restriction, including without limitation the rights
+ * to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+ * copies of the Software, and to permit persons to whom the Software is
+ * furnished to do so, subject to the following conditions:
+ *
+ * The above copyright notice and this permission notice shall be included in all
+ * copies or substantial portions of the Software.
+ *
+ * THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+ * IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+ * FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+ * AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+ * LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+ * OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+ * SOFTWARE.
+ */
+
+use std::sync::Arc;
+use std::time::Duration;
+
+use tokio::sync::Notify;
+use tokio::time::sleep;
+
+/// The time to wait before a requested restart is executed, giving the server time to respond to the request that
+/// triggered the restart.
+const RESTART_DELAY: Duration = Duration::from_secs(1);
+
+/// The holder for restart requests of the server. The server exits when a restart is requested and relies on its
+/// supervisor (for example systemd) to start it again.
+#[derive(Clone, Debug)]
+pub(crate) struct ServerRestartAccessor {
+    restart_requested: Arc<Notify>,
+}
+
+impl ServerRestartAccessor {
+    /// Constructs a new holder instance without a requested restart.
+    pub fn new() -> Self {
+        Self {
+            restart_requested: Arc::new(Notify::new()),
+        }
+    }
+
+    /// Requests a restart of the server, which is executed after a short delay.
+    pub fn request_restart(&self) {
+        let restart_requested = self.restart_requested.clone();
+        tokio::spawn(async move {
+            sleep(RESTART_DELAY).await;
+            restart_requested.notify_one();
+        });
+    }
+
+    /// Waits until a restart of the server was requested.
+    pub async fn wait_for_restart_request(&self) {
+        self.restart_requested.notified().await
+    }
+}
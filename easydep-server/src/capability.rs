@@ -0,0 +1,130 @@
+/*
+ * This file is part of easydep, licensed under the MIT License (MIT).
+ *
+ * Copyright (c) 2024 easybill GmbH
+ *
+ * Permission is hereby granted, free of charge, to any person obtaining a copy
+ * of this software and associated documentation files (the "Software"), to deal
+ * in the Software without restriction, including without limitation the rights
+ * to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+ * copies of the Software, and to permit persons to whom the Software is
+ * furnished to do so, subject to the following conditions:
+ *
+ * The above copyright notice and this permission notice shall be included in all
+ * copies or substantial portions of the Software.
+ *
+ * THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+ * IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+ * FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+ * AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+ * LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+ * OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+ * SOFTWARE.
+ */
+
+use anyhow::{bail, Context};
+use easydep_buildinfo::VERSION;
+
+/// The capabilities supported by this server. Releases and deployment manifests can require capabilities to prevent
+/// deployments on servers that would silently ignore parts of the deployment configuration. These are unrelated to the
+/// features reported to the clients in the server status, which are used to refuse unsupported client commands.
+pub(crate) const MANIFEST_CAPABILITIES: &[&str] = &[
+    "secrets",
+    "publish_hooks",
+    "prepared_ttl",
+    "ref_deploys",
+    "branch_tracking",
+    "path_filters",
+    "sparse_checkout",
+    "build",
+    "containers",
+    "script_directives",
+    "manifest",
+    "artifacts",
+];
+
+/// The prefix of the line in a release body declaring the minimum required server version.
+const RELEASE_MINIMUM_VERSION_PREFIX: &str = "easydep-minimum-version:";
+/// The prefix of the line in a release body declaring the required server capabilities (comma separated).
+const RELEASE_CAPABILITIES_PREFIX: &str = "easydep-capabilities:";
+
+/// The server version and capabilities required to deploy a release.
+#[derive(Clone, Debug, Default)]
+pub(crate) struct DeploymentRequirements {
+    /// The minimum version of the easydep server that is required.
+    pub minimum_easydep_version: Option<String>,
+    /// The capabilities that the server must support.
+    pub required_capabilities: Vec<String>,
+}
+
+impl DeploymentRequirements {
+    /// Parses the requirements declared in the body of a release. The requirements are declared on separate lines in
+    /// the form `easydep-minimum-version: <version>` and `easydep-capabilities: <capability>, <capability>`.
+    ///
+    /// # Arguments
+    /// * `release_body` - The body of the release to parse the requirements from.
+    pub fn from_release_body(release_body: &str) -> Self {
+        let mut requirements = Self::default();
+        for line in release_body.lines().map(str::trim) {
+            if let Some(minimum_version) = line.strip_prefix(RELEASE_MINIMUM_VERSION_PREFIX) {
+                requirements.minimum_easydep_version = Some(minimum_version.trim().to_string());
+            } else if let Some(capabilities) = line.strip_prefix(RELEASE_CAPABILITIES_PREFIX) {
+                let capabilities = capabilities
+                    .split(',')
+                    .map(str::trim)
+                    .filter(|capability| !capability.is_empty())
+                    .map(str::to_string);
+                requirements.required_capabilities.extend(capabilities);
+            }
+        }
+        requirements
+    }
+
+    /// Checks if this server satisfies the requirements, returning an error describing the unmet requirements if not.
+    pub fn check(&self) -> anyhow::Result<()> {
+        if let Some(minimum_version) = &self.minimum_easydep_version {
+            let required_version = parse_version(minimum_version)
+                .with_context(|| format!("invalid minimum easydep version {minimum_version}"))?;
+            let server_version = parse_version(VERSION)?;
+            if server_version < required_version {
+                bail!(
+                    "easydep {} or newer is required, server is running {}",
+                    minimum_version,
+                    VERSION
+                );
+            }
+        }
+
+        let missing_capabilities = self
+            .required_capabilities
+            .iter()
+            .filter(|capability| !MANIFEST_CAPABILITIES.contains(&capability.as_str()))
+            .cloned()
+            .collect::<Vec<String>>();
+        if !missing_capabilities.is_empty() {
+            bail!(
+                "server {} does not support the required capabilities {}",
+                VERSION,
+                missing_capabilities.join(", ")
+            );
+        }
+
+        Ok(())
+    }
+}
+
+/// Parses the given version string into its numeric components, ignoring pre-release and build metadata.
+///
+/// # Arguments
+/// * `version` - The version string to parse, for example `1.2.3`.
+fn parse_version(version: &str) -> anyhow::Result<Vec<u64>> {
+    version
+        .trim()
+        .trim_start_matches('v')
+        .split(['-', '+'])
+        .next()
+        .unwrap_or_default()
+        .split('.')
+        .map(|component| Ok(component.parse::<u64>()?))
+        .collect()
+}
@@ -21,16 +21,39 @@
  * OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
  * SOFTWARE.
  */
-use std::collections::HashSet;
+use std::collections::{BTreeSet, HashMap, HashSet};
 use std::path::{Path, PathBuf};
 use std::str;
+use std::time::SystemTime;
 
 use anyhow::bail;
+use glob::{MatchOptions, Pattern};
+use jsonwebtoken::Algorithm;
 use log::info;
+use ring::digest::{digest, SHA256};
 use serde::{Deserialize, Serialize};
 use tokio::fs;
 use tokio::process::Command;
 
+/// The suffix of the file in which the previous configuration is kept after a new configuration was installed, until
+/// the server started successfully with the new configuration.
+const PREVIOUS_CONFIGURATION_FILE_SUFFIX: &str = ".previous";
+/// The suffix of the file to which a new configuration is written before it replaces the current configuration.
+const PENDING_CONFIGURATION_FILE_SUFFIX: &str = ".pending";
+/// The value that replaces values which might contain credentials in a redacted configuration.
+const REDACTED_VALUE: &str = "<redacted>";
+
+/// Information about the file from which the configuration was loaded.
+#[derive(Clone, Debug)]
+pub(crate) struct ConfigurationSource {
+    /// The path to the configuration file.
+    pub path: String,
+    /// The time when the configuration file was loaded.
+    pub loaded_at: SystemTime,
+    /// The hex encoded SHA-256 hash of the content of the configuration file.
+    pub content_hash: String,
+}
+
 /// The global configuration for the current EasyDep instance.
 #[derive(Serialize, Deserialize, Clone, Debug)]
 pub(crate) struct Configuration {
@@ -42,8 +65,74 @@ pub(crate) struct Configuration {
     pub github_app_id: u64,
     /// The private key of the GitHub app in PEM format.
     pub github_app_pem_key_path: String,
+    /// The time (in seconds) for which release information fetched from
+    /// GitHub is cached.
+    #[serde(default = "default_github_release_cache_ttl_seconds")]
+    pub github_release_cache_ttl_seconds: u64,
     /// The amount of releases to keep locally on each server.
     pub retained_releases: u16,
+    /// The umask of the server process, inherited by the git and script processes and
+    /// applied to all files and directories they create (for example `0o022`). If not
+    /// given, the umask with which the server was started is kept.
+    pub umask: Option<u32>,
+    /// The time (in seconds) after which a lifecycle script is terminated and fails the action.
+    /// Can be overridden by each deployment configuration. If not given scripts never time out.
+    pub script_timeout_seconds: Option<u64>,
+    /// The time (in seconds) that timed out scripts get to exit after being sent `SIGTERM`,
+    /// before they are killed using `SIGKILL`. Defaults to 10 seconds.
+    #[serde(default = "default_script_kill_grace_period_seconds")]
+    pub script_kill_grace_period_seconds: u64,
+    /// The mode of the release directories and their parent directories created for
+    /// deployments (for example `0o755`). If not given, the mode resulting from the
+    /// umask is kept.
+    pub directory_mode: Option<u32>,
+    /// The minimum free disk space (in MiB) on the file system of the base directory that is
+    /// required to obtain the content of a deployment. Deployments are rejected before cloning
+    /// if less space is available. If not given, the free disk space is not checked.
+    pub min_free_disk_mb: Option<u64>,
+    /// The OpenID Connect settings used to authenticate clients. If not
+    /// given, requests to the server are not authenticated.
+    pub oidc: Option<OidcConfiguration>,
+    /// The static tokens that authenticate clients in addition to the tokens
+    /// issued by the OpenID Connect provider, for example for CI pipelines.
+    #[serde(default)]
+    pub api_tokens: Vec<ApiTokenConfiguration>,
+    /// The settings to access a HashiCorp Vault server, required if any
+    /// deployment configuration references secrets stored in vault.
+    pub vault: Option<VaultConfiguration>,
+    /// The settings of the task that cleans up release directories that are
+    /// no longer tracked. If not given, the task is not running.
+    pub orphan_cleanup: Option<OrphanCleanupConfiguration>,
+    /// The settings of the task that runs the git maintenance of the repository
+    /// caches. If not given, the task is not running.
+    pub repository_cache_maintenance: Option<RepositoryCacheMaintenanceConfiguration>,
+    /// The settings of the watchdog that detects actions which are stuck in
+    /// the same state. If not given, the watchdog is not running.
+    pub watchdog: Option<WatchdogConfiguration>,
+    /// The settings of the notifications sent for deployment lifecycle
+    /// events. If not given, no notifications are sent.
+    pub notifications: Option<NotificationConfiguration>,
+    /// The settings of the sinks to which deployment metrics are pushed.
+    /// If not given, no metrics are pushed.
+    pub metrics: Option<MetricsConfiguration>,
+    /// The settings to upgrade the server binary to another easydep release.
+    /// If not given, the server cannot be upgraded remotely.
+    pub self_update: Option<SelfUpdateConfiguration>,
+    /// The settings of the git commands executed by the server. If not given,
+    /// the git executable on the PATH is used without additional settings.
+    pub git: Option<GitConfiguration>,
+    /// The settings of the http listener that receives the release webhooks
+    /// of GitHub. If not given, no webhooks are received.
+    pub github_webhook: Option<GitHubWebhookConfiguration>,
+    /// Indicates if the deep checks of the environment (write access to the base directory,
+    /// symlink capability, executables and GitHub access of the profiles) are executed when
+    /// the server starts. If one of the checks fails, the server does not start.
+    #[serde(default)]
+    pub self_test_on_startup: bool,
+    /// The maximum size (in bytes) of all archives of failed actions combined. The oldest archives are
+    /// deleted when the size is exceeded, archiving is disabled if set to 0.
+    #[serde(default = "default_failed_archive_max_bytes")]
+    pub failed_archive_max_bytes: u64,
     /// The deployment configurations that are defined. Each
     /// map key is the name of the configuration, mapped to
     /// the associated configuration.
@@ -56,11 +145,20 @@ pub(crate) struct DeploymentConfiguration {
     /// The identifier of this deployment configuration.
     pub id: String,
     /// The name of the deployment target, used for example in directories.
-    /// This name can be re-used for multiple configurations.
+    /// This name can be re-used for multiple configurations, which then share
+    /// a single pool of releases and a single current release: publishing or
+    /// rolling back with one of the configurations affects all of them, and
+    /// the retention applies to the releases of all of them combined.
     pub target: String,
     /// Indicates if this configuration cannot be directly used for deployment
     /// and only for other configurations to extend it.
+    #[serde(default)]
     pub extend_only: bool,
+    /// The id of the configuration from which this configuration inherits all
+    /// settings it does not set itself. Tables are merged recursively, all
+    /// other values (including arrays) of this configuration replace the
+    /// inherited values. The `id` and `extend_only` are never inherited.
+    pub extends: Option<String>,
     /// The owner name of the repository from where the deployment
     /// can be triggered. Release ids when triggering a release will
     /// be resolved against this repository setting.
@@ -85,32 +183,815 @@ pub(crate) struct DeploymentConfiguration {
     /// The extended configuration is executed first.
     pub extended_script_configurations: Vec<String>,
     /// The symlinks that should be created as part of this configuration.
-    symlinks: Vec<String>,
+    symlinks: Vec<SymlinkDefinition>,
+    /// The environment variables provided to the scripts of this configuration, for example to run
+    /// the same scripts with different settings for staging and production.
+    #[serde(default)]
+    pub environment: HashMap<String, String>,
+    /// The path to a file on the server from which additional environment variables (`KEY=value`
+    /// lines) are read and provided to the scripts of this configuration. The variables of the
+    /// `environment` take precedence over the variables of the file.
+    pub env_file: Option<PathBuf>,
+    /// The secrets that should be resolved and provided to the scripts of this configuration.
+    #[serde(default)]
+    pub secrets: Vec<SecretReference>,
+    /// The http calls that should be executed after a deployment was published.
+    #[serde(default)]
+    pub publish_hooks: Vec<PublishHookConfiguration>,
+    /// The time (in seconds) after which a prepared deployment that was not published
+    /// is automatically deleted. If not given prepared deployments never expire.
+    #[serde(default)]
+    pub prepared_ttl_seconds: Option<u64>,
+    /// The time (in seconds) after which a lifecycle script of this configuration is terminated
+    /// and fails the action. Overrides the script timeout of the server configuration.
+    #[serde(default)]
+    pub script_timeout_seconds: Option<u64>,
+    /// The named commands that can be executed in the directory of the published release on
+    /// request, for example to clear caches without logging in to the server.
+    #[serde(default)]
+    pub exec_commands: Vec<ExecCommandConfiguration>,
+    /// Indicates if deployments can be started from a git ref (tag, branch or commit SHA)
+    /// instead of a release, for example for emergency hotfixes.
+    #[serde(default)]
+    pub allow_ref_deploys: bool,
+    /// Indicates if releases that are published in the source repository are deployed and
+    /// published automatically with this configuration, reported by the GitHub release webhook.
+    #[serde(default)]
+    pub deploy_published_releases: bool,
+    /// Indicates if deployments prepared with this configuration must be approved before they
+    /// can be published. Publish requests must carry the token issued when approving the deployment.
+    #[serde(default)]
+    pub require_approval: bool,
+    /// The name of a branch whose head should be tracked. If given, every new commit
+    /// on the branch is deployed and published automatically using this profile.
+    pub track_branch: Option<String>,
+    /// The interval (in seconds) in which the head of the tracked branch is polled.
+    #[serde(default = "default_track_branch_poll_interval_seconds")]
+    pub track_branch_poll_interval_seconds: u64,
+    /// The paths (relative to the repository root) that are relevant for this configuration. If
+    /// given, the tracked branch is only deployed if a new commit touches one of the paths.
+    #[serde(default)]
+    pub path_filters: Vec<String>,
+    /// The directories (relative to the repository root) that should be checked out. If given,
+    /// only these directories and the top-level files of the repository are checked out.
+    #[serde(default)]
+    pub sparse_paths: Vec<String>,
+    /// Indicates if the git objects of the source repository are cached in a bare repository
+    /// under the base directory, which is updated with the changes since the last release and
+    /// cloned from locally, instead of cloning each release from GitHub.
+    #[serde(default)]
+    pub repository_cache: bool,
+    /// Indicates if the tag of a release must carry a valid signature, verified using `git verify-tag`
+    /// after the checkout. Deployments of releases whose tag cannot be verified are aborted.
+    #[serde(default)]
+    pub require_signed_tags: bool,
+    /// The GnuPG home directory containing the keyring with the trusted public keys, used when
+    /// verifying the signatures of tags. If not given, the keyring of the server user is used.
+    pub signed_tags_gnupg_home: Option<String>,
+    /// How the content of the releases is obtained: by cloning the source repository (`git`) or by
+    /// downloading and extracting a release asset (`artifact`). Defaults to `git`.
+    #[serde(default)]
+    pub mode: DeploymentMode,
+    /// The release asset that is deployed when using the `artifact` mode.
+    pub artifact: Option<ArtifactConfiguration>,
+    /// The build phase of this configuration, executed after the init scripts.
+    /// If not given, no build phase is executed.
+    pub build: Option<BuildConfiguration>,
+    /// The container in which the lifecycle scripts should be executed. If
+    /// not given, the scripts are executed directly on the host.
+    pub container: Option<ContainerConfiguration>,
+    /// The processing that is applied to the log output of actions executed with this
+    /// configuration, before it is streamed to the client or used in notifications. If not
+    /// given, the log output is not processed.
+    pub log_processing: Option<LogProcessingConfiguration>,
+    /// The scheduling priorities of the git and script processes spawned for this
+    /// configuration. If not given, the processes inherit the priorities of the server.
+    pub process_priority: Option<ProcessPriorityConfiguration>,
+    /// The settings of the git commands executed for this configuration, overriding
+    /// the git settings of the server. If not given, the server settings are used.
+    pub git: Option<GitConfiguration>,
+    /// The ownership and permissions that are applied to the checked-out files before
+    /// the init scripts are executed. If not given, the files keep the ownership and
+    /// permissions with which they were checked out.
+    pub file_permissions: Option<FilePermissionsConfiguration>,
+    /// The settings to check out the repository using ssh and a deploy key instead
+    /// of https and the GitHub app token. If not given, https is used.
+    pub ssh_checkout: Option<SshCheckoutConfiguration>,
+    /// The maximum amount of deployments that can be started with this configuration within
+    /// an hour. If not given, the amount of deployments is not limited.
+    pub max_deployments_per_hour: Option<u32>,
+    /// The maximum amount of deployments started with this configuration that wait for the
+    /// running action to complete, executed in the order they were started. If zero, starting
+    /// a deployment fails while another action is running.
+    #[serde(default)]
+    pub action_queue_depth: usize,
+    /// The template of the names of the release directories. Must start with
+    /// the `{id}` placeholder, optionally followed by a separator and the
+    /// `{tag}` placeholder (for example `{id}-{tag}`).
+    #[serde(default = "default_release_directory_name")]
+    pub release_directory_name: String,
+    /// Indicates if the scripts directory of this configuration and its init
+    /// script must exist in the deployed release. If set, deployments in which
+    /// they are missing fail instead of silently executing no scripts.
+    #[serde(default)]
+    pub require_scripts: bool,
+    /// Indicates if the syntax of all scripts of this configuration (including the ones of
+    /// extended configurations) is checked using `bash -n` when preparing a deployment,
+    /// before the symlinks are created and any script is executed.
+    #[serde(default)]
+    pub check_script_syntax: bool,
+    /// The slots in which the releases of this configuration are deployed
+    /// alternately, for example to run a blue and a green instance of the
+    /// same application. Each deployment is executed in the slot following
+    /// the active slot, publishing it makes the slot active.
+    #[serde(default)]
+    pub slots: Vec<SlotConfiguration>,
+    /// The slot in which the current deployment is executed, resolved when
+    /// the deployment is started.
+    #[serde(skip)]
+    pub deployment_slot: Option<SlotConfiguration>,
+}
+
+/// A slot in which the releases of a deployment configuration can be deployed, allowing to serve multiple instances of
+/// the same application on one host. Switching the traffic between the slots is left to the publish hooks.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub(crate) struct SlotConfiguration {
+    /// The name of the slot, provided to the scripts and publish hooks.
+    pub name: String,
+    /// The environment variables provided to the scripts of releases deployed in this slot.
+    #[serde(default)]
+    pub env: HashMap<String, String>,
+    /// The symlinks that should be created for releases deployed in this slot, in addition
+    /// to the symlinks of the deployment configuration.
+    #[serde(default)]
+    symlinks: Vec<SymlinkDefinition>,
+}
+
+/// The settings of the container in which lifecycle scripts are executed. The deployment directory is bind-mounted
+/// into the container and used as the working directory of the scripts.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub(crate) struct ContainerConfiguration {
+    /// The image of the container, for example `node:20`.
+    pub image: String,
+    /// The container runtime executable to run the container with, for example `docker` or `podman`.
+    #[serde(default = "default_container_runtime")]
+    pub runtime: String,
+    /// The path inside the container at which the deployment directory is mounted.
+    #[serde(default = "default_container_workdir")]
+    pub workdir: String,
+    /// Additional arguments that are passed to the run command of the container runtime.
+    #[serde(default)]
+    pub extra_args: Vec<String>,
+}
+
+/// The processing steps that are applied to each log line of an action, in the order they are declared here.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub(crate) struct LogProcessingConfiguration {
+    /// Log lines containing one of these values are dropped.
+    #[serde(default)]
+    pub dropped_lines: Vec<String>,
+    /// The values that are replaced in the log lines, for example credentials that are printed by scripts.
+    #[serde(default)]
+    pub redacted_values: Vec<String>,
+    /// If ANSI escape sequences (for example colors) should be removed from the log lines.
+    #[serde(default)]
+    pub strip_ansi: bool,
+    /// The maximum amount of characters of a log line, longer lines are truncated.
+    pub max_line_length: Option<usize>,
+    /// The tag that is prepended to each log line, for example to identify the profile in aggregated logs.
+    pub tag: Option<String>,
+}
+
+/// The scheduling priorities of the git and script processes spawned for a deployment configuration, for example to
+/// keep a heavy build during the prepare phase from degrading the application that is served from the current release.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub(crate) struct ProcessPriorityConfiguration {
+    /// The niceness of the processes, from -20 (highest priority) to 19 (lowest priority).
+    pub nice: Option<i32>,
+    /// The io scheduling class of the processes, only supported on Linux.
+    pub io_class: Option<IoPriorityClass>,
+    /// The priority of the processes within the best effort io class, from 0 (highest priority) to 7 (lowest priority).
+    pub io_priority: Option<u8>,
+}
+
+/// The settings of the http listener that receives the `release` webhooks of GitHub, to deploy the releases published
+/// in the source repositories of the deployment configurations automatically.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub(crate) struct GitHubWebhookConfiguration {
+    /// The host and port to which the http listener should be bound.
+    pub bind_host: String,
+    /// The path at which the webhooks are received.
+    #[serde(default = "default_github_webhook_path")]
+    pub path: String,
+    /// The path to the file containing the secret with which GitHub signs the webhook payloads.
+    pub secret_file: String,
+}
+
+/// The settings of the git commands that are executed to check out releases, for example on hosts where git is not on
+/// the PATH or where custom certificate authorities or proxies are required.
+#[derive(Serialize, Deserialize, Clone, Debug, Default)]
+pub(crate) struct GitConfiguration {
+    /// The path to the git executable. If not given, the git executable on the PATH is used.
+    pub executable: Option<String>,
+    /// The environment variables provided to the git commands, for example `GIT_SSL_CAINFO`.
+    #[serde(default)]
+    pub env: HashMap<String, String>,
+    /// The git configuration values provided to the git commands, for example `http.proxy`.
+    #[serde(default)]
+    pub config: HashMap<String, String>,
+}
+
+/// The ownership and permissions that are applied to the files of a deployment after the checkout, for example because
+/// the files are checked out as the user running the server but must be readable by the user of a web server.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub(crate) struct FilePermissionsConfiguration {
+    /// The user that should own the files. If not given, the owning user is not changed.
+    pub owner: Option<String>,
+    /// The group that should own the files. If not given, the owning group is not changed.
+    pub group: Option<String>,
+    /// The mode that is applied to all files and directories, in the format accepted by `chmod`
+    /// (for example `u=rwX,g=rX,o=`). If not given, the permissions are not changed.
+    pub mode: Option<String>,
+    /// If the SELinux security contexts of the files should be restored using `restorecon`.
+    #[serde(default)]
+    pub restore_selinux_context: bool,
+}
+
+/// The settings to check out the repository of a deployment configuration from a `git@github.com:` remote, for example
+/// for organizations in which the GitHub app cannot access the repository contents.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub(crate) struct SshCheckoutConfiguration {
+    /// The path to the private deploy key of the repository. If not given, the keys provided by the
+    /// ssh agent (`SSH_AUTH_SOCK`) are used.
+    pub private_key_path: Option<String>,
+    /// The path to the known hosts file to verify the host key of GitHub with. If not given, the
+    /// known hosts of the user running the server are used.
+    pub known_hosts_file: Option<String>,
+}
+
+/// A command that can be executed by name in the directory of the release published with a deployment configuration.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub(crate) struct ExecCommandConfiguration {
+    /// The name by which the command is requested, for example `clear-cache`.
+    pub name: String,
+    /// The program to execute, followed by its arguments. The command is executed without a shell.
+    pub command: Vec<String>,
+}
+
+/// The io scheduling classes that can be assigned to the processes of a deployment configuration.
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, Eq, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub(crate) enum IoPriorityClass {
+    /// The processes get io time based on their io priority.
+    BestEffort,
+    /// The processes only get io time when no other process needs it.
+    Idle,
+}
+
+/// The settings of the build phase of a deployment configuration. The build phase executes the `build` lifecycle
+/// scripts, the configured cache directories are persisted outside the release directory between releases.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub(crate) struct BuildConfiguration {
+    /// The paths (relative to the deployment directory) of the directories that are cached between builds.
+    #[serde(default)]
+    pub cache_paths: Vec<String>,
+    /// How the cached directories are provided to the build.
+    #[serde(default)]
+    pub cache_strategy: BuildCacheStrategy,
+}
+
+/// The strategies to provide cached directories to a build.
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, Default, Eq, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub(crate) enum BuildCacheStrategy {
+    /// The cached directories are copied into the deployment directory before the build and copied
+    /// back into the cache after a successful build. Each release has its own copy of the directories.
+    #[default]
+    Copy,
+    /// The cached directories are symlinked into the deployment directory, all releases share the same directories.
+    Symlink,
+}
+
+/// The ways in which the content of the releases of a deployment configuration can be obtained.
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, Default, Eq, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub(crate) enum DeploymentMode {
+    /// The source repository is cloned at the tag of the release.
+    #[default]
+    Git,
+    /// A prebuilt archive attached to the release is downloaded and extracted, no git checkout is made.
+    Artifact,
+}
+
+/// The release asset that is deployed by a deployment configuration using the `artifact` mode. The asset must be a tar
+/// archive (optionally compressed) whose checksum is attached to the release as well.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub(crate) struct ArtifactConfiguration {
+    /// The name of the release asset to deploy, for example `dist.tar.gz`.
+    pub asset_name: String,
+    /// The name of the release asset containing the hex encoded SHA-256 checksum of the deployed
+    /// asset, in the format written by `sha256sum`. Defaults to `<asset_name>.sha256`.
+    pub checksum_asset_name: Option<String>,
+}
+
+impl ArtifactConfiguration {
+    /// Get the name of the release asset containing the checksum of the deployed asset.
+    pub fn get_checksum_asset_name(&self) -> String {
+        self.checksum_asset_name
+            .clone()
+            .unwrap_or_else(|| format!("{}.sha256", self.asset_name))
+    }
+}
+
+/// An http call that is executed after a deployment was published, for example to purge a CDN cache or to notify
+/// an external service about the new release. The url, header values and body can contain the placeholders
+/// `{{release_id}}`, `{{release_tag}}`, `{{release_name}}`, `{{release_commitish}}`, `{{profile}}`, `{{target}}` and
+/// `{{slot}}`, the values inserted into the body are escaped for json strings.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub(crate) struct PublishHookConfiguration {
+    /// The name of the hook, used to identify the hook in the log output.
+    pub name: String,
+    /// The url to call.
+    pub url: String,
+    /// The http method to use for the call.
+    #[serde(default = "default_publish_hook_method")]
+    pub method: String,
+    /// The headers to send with the request.
+    #[serde(default)]
+    pub headers: HashMap<String, String>,
+    /// The body to send with the request, if any.
+    pub body: Option<String>,
+    /// The maximum amount of attempts to call the hook before it is considered as failed.
+    #[serde(default = "default_publish_hook_max_attempts")]
+    pub max_attempts: u32,
+    /// The delay (in seconds) to wait between two attempts to call the hook.
+    #[serde(default = "default_publish_hook_retry_delay_seconds")]
+    pub retry_delay_seconds: u64,
+    /// The time (in seconds) after which a single call to the hook is aborted.
+    #[serde(default = "default_publish_hook_timeout_seconds")]
+    pub timeout_seconds: u64,
+}
+
+/// A reference to a secret stored in a secret provider which is provided to the lifecycle scripts either as an
+/// environment variable or as a file rendered into the deployment directory (or both).
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub(crate) struct SecretReference {
+    /// The provider in which the secret is stored.
+    pub source: SecretSource,
+    /// The reference to the secret in the provider. For vault this is `<path>#<key>`, for ssm the parameter name
+    /// and for file sources the absolute path to the file containing the secret.
+    pub reference: String,
+    /// The name of the environment variable in which the secret should be provided to the scripts.
+    pub env: Option<String>,
+    /// The path, relative to the deployment directory, of the file into which the secret should be rendered.
+    pub file: Option<String>,
+}
+
+/// The providers from which secrets can be resolved.
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, Eq, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub(crate) enum SecretSource {
+    /// A key of a secret stored in the KV v2 engine of HashiCorp Vault.
+    Vault,
+    /// A parameter stored in the AWS SSM parameter store, resolved using the aws cli.
+    Ssm,
+    /// A file on the local disk which content is the secret.
+    File,
+}
+
+/// The settings of the task that periodically scans the release directories for entries that are neither the current
+/// release, the release of a slot, a retained release nor an active deployment (for example leftovers from crashes).
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub(crate) struct OrphanCleanupConfiguration {
+    /// The interval (in seconds) in which the release directories are scanned.
+    #[serde(default = "default_orphan_cleanup_interval_seconds")]
+    pub interval_seconds: u64,
+    /// If orphaned entries should be removed, if false they are only reported in the log.
+    #[serde(default)]
+    pub remove_orphans: bool,
+}
+
+/// The settings of the task that periodically runs the git maintenance of the repository caches shared by the profiles
+/// using `repository_cache`, removing the objects that are no longer referenced and packing the remaining ones so that
+/// the caches do not grow without limit.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub(crate) struct RepositoryCacheMaintenanceConfiguration {
+    /// The interval (in seconds) in which the maintenance is executed.
+    #[serde(default = "default_repository_cache_maintenance_interval_seconds")]
+    pub interval_seconds: u64,
+}
+
+/// The settings of the watchdog that periodically checks if the current action stays in the same state for too long,
+/// for example because a script hangs, to notify about it and optionally reset the action.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub(crate) struct WatchdogConfiguration {
+    /// The interval (in seconds) in which the current action is checked.
+    #[serde(default = "default_watchdog_check_interval_seconds")]
+    pub check_interval_seconds: u64,
+    /// The time (in seconds) after which an action that stays in the same state is considered stuck.
+    #[serde(default = "default_watchdog_stuck_threshold_seconds")]
+    pub stuck_threshold_seconds: u64,
+    /// The time (in seconds) after which an action that stays in the same state is reset to idle, allowing new
+    /// actions to be executed. If not given, stuck actions are only reported.
+    pub force_reset_after_seconds: Option<u64>,
+}
+
+/// The settings of the notifications that are sent for deployment lifecycle events.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub(crate) struct NotificationConfiguration {
+    /// The name of this server used in the notifications. Defaults to the hostname of the server.
+    pub server_name: Option<String>,
+    /// The handlebars templates of the notification messages, keyed by the event. Events without a template use
+    /// the built-in default message.
+    #[serde(default)]
+    pub templates: HashMap<NotificationEvent, String>,
+    /// The webhooks to which notifications are sent.
+    #[serde(default)]
+    pub webhooks: Vec<WebhookNotifierConfiguration>,
+    /// The smtp servers through which notifications are sent as emails.
+    #[serde(default)]
+    pub emails: Vec<EmailNotifierConfiguration>,
+    /// The alerting services in which incidents are opened when a deployment fails.
+    #[serde(default)]
+    pub alerts: Vec<AlertNotifierConfiguration>,
+}
+
+/// A webhook to which the notification messages are posted.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub(crate) struct WebhookNotifierConfiguration {
+    /// The name of the webhook, used in log messages.
+    pub name: String,
+    /// The url to post the notifications to.
+    pub url: String,
+    /// The headers to send with each request.
+    #[serde(default)]
+    pub headers: HashMap<String, String>,
+    /// The events to notify about. If empty, all events are notified.
+    #[serde(default)]
+    pub events: Vec<NotificationEvent>,
+    /// The ids of the deployment profiles to notify about. If empty, all profiles are notified.
+    #[serde(default)]
+    pub profiles: Vec<String>,
+    /// The handlebars templates of the notification messages for this webhook, overriding the global templates.
+    #[serde(default)]
+    pub templates: HashMap<NotificationEvent, String>,
+    /// The format of the json payload posted to the webhook. Defaults to the Slack-compatible format.
+    #[serde(default)]
+    pub format: WebhookFormat,
+}
+
+/// The formats of the json payloads posted to notification webhooks.
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, Default, Eq, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub(crate) enum WebhookFormat {
+    /// The rendered message as `text` field, understood by Slack-compatible incoming webhooks.
+    #[default]
+    Slack,
+    /// All fields of the notification and the rendered message as `message` field, for generic consumers.
+    Json,
+}
+
+/// An smtp server through which the notification messages are sent as emails.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub(crate) struct EmailNotifierConfiguration {
+    /// The name of the email notifier, used in log messages.
+    pub name: String,
+    /// The host of the smtp server.
+    pub host: String,
+    /// The port of the smtp server. Defaults to the default port of the tls mode.
+    pub port: Option<u16>,
+    /// How the connection to the smtp server is secured.
+    #[serde(default)]
+    pub tls: EmailTlsMode,
+    /// The username to authenticate at the smtp server with. If not given, no authentication is used.
+    pub username: Option<String>,
+    /// The path to a file containing the password to authenticate at the smtp server with.
+    pub password_file: Option<String>,
+    /// The address from which the emails are sent.
+    pub from: String,
+    /// The addresses to which the emails are sent for all profiles.
+    #[serde(default)]
+    pub recipients: Vec<String>,
+    /// Additional addresses to which the emails are sent, keyed by the id of the deployment profile.
+    #[serde(default)]
+    pub profile_recipients: HashMap<String, Vec<String>>,
+    /// The handlebars template of the email subject.
+    #[serde(default = "default_email_subject")]
+    pub subject: String,
+    /// The events to notify about. If empty, all events are notified.
+    #[serde(default)]
+    pub events: Vec<NotificationEvent>,
+    /// The ids of the deployment profiles to notify about. If empty, all profiles are notified.
+    #[serde(default)]
+    pub profiles: Vec<String>,
+    /// The handlebars templates of the email bodies, overriding the global templates.
+    #[serde(default)]
+    pub templates: HashMap<NotificationEvent, String>,
+}
+
+/// The ways in which the connection to an smtp server can be secured.
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, Default, Eq, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub(crate) enum EmailTlsMode {
+    /// The connection is upgraded to tls using STARTTLS (port 587 by default).
+    #[default]
+    StartTls,
+    /// The connection uses tls from the start (port 465 by default).
+    Tls,
+    /// The connection is not encrypted (port 25 by default), only use this for local relays.
+    None,
+}
+
+/// An alerting service in which an incident is opened when an action of a deployment fails. The incident is resolved
+/// when the same release is published successfully afterwards.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub(crate) struct AlertNotifierConfiguration {
+    /// The name of the alert notifier, used in log messages.
+    pub name: String,
+    /// The alerting service in which the incidents are opened.
+    pub provider: AlertProvider,
+    /// The path to a file containing the integration key (PagerDuty) or api key (Opsgenie).
+    pub key_file: String,
+    /// The base url of the api of the alerting service. Defaults to the public api of the provider.
+    pub api_url: Option<String>,
+    /// The severity (PagerDuty) or priority (Opsgenie) of the opened incidents.
+    pub severity: Option<String>,
+    /// The ids of the deployment profiles to open incidents for. If empty, incidents are opened for all profiles.
+    #[serde(default)]
+    pub profiles: Vec<String>,
+    /// The handlebars templates of the incident descriptions, overriding the global templates.
+    #[serde(default)]
+    pub templates: HashMap<NotificationEvent, String>,
+}
+
+/// The alerting services in which incidents can be opened.
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, Eq, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub(crate) enum AlertProvider {
+    /// Incidents are opened using the PagerDuty Events API v2.
+    PagerDuty,
+    /// Incidents are opened using the Opsgenie Alert API.
+    Opsgenie,
+}
+
+/// The settings to upgrade the server binary to a release of easydep published on GitHub. Each release must provide
+/// the server binary as asset, alongside a `<asset>.sha256` checksum file and a `<asset>.sig` file containing the hex
+/// encoded ed25519 signature of the binary.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub(crate) struct SelfUpdateConfiguration {
+    /// The owner of the repository from which the easydep releases are downloaded.
+    #[serde(default = "default_self_update_repo_owner")]
+    pub repo_owner: String,
+    /// The name of the repository from which the easydep releases are downloaded.
+    #[serde(default = "default_self_update_repo_name")]
+    pub repo_name: String,
+    /// The name of the release asset containing the server binary for this host.
+    pub asset_name: String,
+    /// The hex encoded ed25519 public key with which the server binaries must be signed.
+    pub signing_public_key: String,
+}
+
+/// The settings of the sinks to which deployment metrics are pushed, for environments in which the servers cannot be
+/// scraped. The metrics are pushed after each deployment lifecycle event.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub(crate) struct MetricsConfiguration {
+    /// The Prometheus pushgateway to push the metrics to.
+    pub pushgateway: Option<PushgatewayConfiguration>,
+    /// The StatsD server to send the metrics to.
+    pub statsd: Option<StatsdConfiguration>,
+}
+
+/// A Prometheus pushgateway to which the metrics are pushed.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub(crate) struct PushgatewayConfiguration {
+    /// The url of the pushgateway, for example `http://pushgateway:9091`.
+    pub url: String,
+    /// The job label of the pushed metrics.
+    #[serde(default = "default_pushgateway_job")]
+    pub job: String,
+}
+
+/// A StatsD server to which the metrics are sent.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub(crate) struct StatsdConfiguration {
+    /// The address of the StatsD server, for example `127.0.0.1:8125`.
+    pub address: String,
+    /// The prefix of the metric names.
+    #[serde(default = "default_statsd_prefix")]
+    pub prefix: String,
+}
+
+/// The deployment lifecycle events about which notifications can be sent.
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, Eq, PartialEq, Hash)]
+#[serde(rename_all = "snake_case")]
+pub(crate) enum NotificationEvent {
+    /// A deployment was prepared and is ready to be published.
+    Prepared,
+    /// A deployment was published.
+    Published,
+    /// Executing an action of a deployment failed.
+    Failed,
+    /// A profile was rolled back to the previous release.
+    RolledBack,
+    /// A prepared deployment was deleted.
+    Deleted,
+    /// An action did not progress for longer than the stuck threshold of the watchdog.
+    Stuck,
+}
+
+impl NotificationEvent {
+    /// Get all events about which notifications can be sent.
+    pub fn all() -> [NotificationEvent; 6] {
+        [
+            Self::Prepared,
+            Self::Published,
+            Self::Failed,
+            Self::RolledBack,
+            Self::Deleted,
+            Self::Stuck,
+        ]
+    }
+
+    /// Get the name of this event as used in the configuration.
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::Prepared => "prepared",
+            Self::Published => "published",
+            Self::Failed => "failed",
+            Self::RolledBack => "rolled_back",
+            Self::Deleted => "deleted",
+            Self::Stuck => "stuck",
+        }
+    }
+}
+
+/// The settings to access a HashiCorp Vault server.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub(crate) struct VaultConfiguration {
+    /// The address of the vault server, for example `https://vault.example.com:8200`.
+    pub address: String,
+    /// The path to a file containing the vault token. If not given the token
+    /// is read from the `VAULT_TOKEN` environment variable.
+    pub token_file: Option<String>,
+}
+
+/// The settings to authenticate clients using JWT bearer tokens issued by an OpenID Connect provider.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub(crate) struct OidcConfiguration {
+    /// The url of the token issuer, used to discover the signing keys and
+    /// validated against the `iss` claim of the provided tokens.
+    pub issuer: String,
+    /// The audience that must be present in the `aud` claim of the provided tokens.
+    pub audience: String,
+    /// The name of the claim that contains the roles of the authenticated user.
+    #[serde(default = "default_role_claim")]
+    pub role_claim: String,
+    /// The roles that are granted on this server, keyed by the role name in the role claim.
+    pub role_mappings: HashMap<String, AccessRole>,
+    /// The interval (in seconds) in which the signing keys of the issuer are refreshed.
+    #[serde(default = "default_key_refresh_interval_seconds")]
+    pub key_refresh_interval_seconds: u64,
+    /// The algorithms that tokens signed with a key that does not declare its algorithm can be signed with. Tokens
+    /// signed with a key declaring its algorithm must always use the declared algorithm.
+    #[serde(default = "default_oidc_allowed_algorithms")]
+    pub allowed_algorithms: Vec<Algorithm>,
+}
+
+/// A static token that authenticates clients which send it as bearer token.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub(crate) struct ApiTokenConfiguration {
+    /// The name of the token, used as the authenticated subject of requests sent with it.
+    pub name: String,
+    /// The path to a file containing the token.
+    pub token_file: String,
+    /// The role that is granted to clients authenticated with the token.
+    pub role: AccessRole,
+}
+
+/// The roles that can be granted to an authenticated client. Each role includes the permissions of the lower roles.
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, Eq, PartialEq, Ord, PartialOrd)]
+#[serde(rename_all = "snake_case")]
+pub(crate) enum AccessRole {
+    /// Allowed to read status information from the server.
+    Viewer,
+    /// Allowed to execute deployment actions on the server.
+    Deployer,
+    /// Allowed to execute administrative actions on the server.
+    Admin,
 }
 
 /// Represents a symlink that can be provided to a deployment configuration.
 /// These symlinks are created before any scripts are executed.
-#[derive(Debug, Clone)]
+#[derive(Serialize, Deserialize, Debug, Clone)]
 pub(crate) struct Symlink {
     /// The source path in the directory being deployed which
     /// should be linked to the provided target path.
     pub source: String,
     /// The path to which the symlink should point.
     pub target: String,
+    /// If the deployment should fail when the symlink cannot be created or its target does not exist.
+    #[serde(default)]
+    pub required: bool,
+    /// If the target directory should be created (including its parents) when it does not exist.
+    #[serde(default)]
+    pub create_target_dir: bool,
+}
+
+/// The ways a symlink can be defined in a configuration: either as a `<source>:<target>` string or as a table
+/// which allows to specify the options of the symlink.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(untagged)]
+pub(crate) enum SymlinkDefinition {
+    /// A symlink in the `<source>:<target>` format.
+    Short(String),
+    /// A symlink with all options.
+    Detailed(Symlink),
 }
 
 impl Configuration {
     /// Loads the main configuration from the given file path. This
     /// method returns an error in case the given file path cannot
-    /// be read or the configuration cannot be parsed.
+    /// be read or the configuration cannot be parsed. Information about
+    /// the loaded file is returned alongside the configuration.
     ///
     /// # Arguments
     ///
     /// * `file_path` - The path to the file to load the configuration from.
-    pub async fn load_from_file(file_path: impl AsRef<Path>) -> anyhow::Result<Self> {
-        let toml_file_content = fs::read_to_string(file_path).await?;
-        let parsed_configuration: Configuration = toml::from_str(&toml_file_content)?;
-        Ok(parsed_configuration)
+    pub async fn load_from_file(
+        file_path: impl AsRef<Path>,
+    ) -> anyhow::Result<(Self, ConfigurationSource)> {
+        let toml_file_content = fs::read_to_string(&file_path).await?;
+        let parsed_configuration = Configuration::parse(&toml_file_content)?;
+        let configuration_source = ConfigurationSource {
+            path: file_path.as_ref().to_string_lossy().to_string(),
+            loaded_at: SystemTime::now(),
+            content_hash: hash_configuration_content(&toml_file_content),
+        };
+        Ok((parsed_configuration, configuration_source))
+    }
+
+    /// Parses the given configuration content, resolving the settings that deployment configurations inherit from the
+    /// configurations they extend. An error is returned if the content cannot be parsed, or if a deployment
+    /// configuration extends an unknown configuration or itself (directly or indirectly).
+    ///
+    /// # Arguments
+    ///
+    /// * `content` - The toml content of the configuration.
+    pub fn parse(content: &str) -> anyhow::Result<Self> {
+        let mut configuration_table: toml::Table = toml::from_str(content)?;
+        resolve_profile_inheritance(&mut configuration_table)?;
+        let configuration = Configuration::deserialize(configuration_table)?;
+        Ok(configuration)
+    }
+
+    /// Atomically replaces the configuration file at the given path with the given content. The current
+    /// configuration file is kept so that it can be restored if the server fails to start with the new
+    /// configuration.
+    ///
+    /// # Arguments
+    ///
+    /// * `file_path` - The path to the configuration file to replace.
+    /// * `file_content` - The content of the new configuration file.
+    pub async fn install_file(file_path: &str, file_content: &str) -> anyhow::Result<()> {
+        let pending_file_path = format!("{}{}", file_path, PENDING_CONFIGURATION_FILE_SUFFIX);
+        let previous_file_path = format!("{}{}", file_path, PREVIOUS_CONFIGURATION_FILE_SUFFIX);
+        fs::write(&pending_file_path, file_content).await?;
+        fs::copy(file_path, &previous_file_path).await?;
+        fs::rename(&pending_file_path, file_path).await?;
+        Ok(())
+    }
+
+    /// Restores the configuration file that was replaced by the last installed configuration file, if the server
+    /// did not start successfully with the installed configuration since then.
+    ///
+    /// # Arguments
+    ///
+    /// * `file_path` - The path to the configuration file to restore.
+    ///
+    /// # Returns
+    ///
+    /// * `bool` - `true` if the previous configuration file was restored, `false` if there is none.
+    pub async fn restore_previous_file(file_path: &str) -> anyhow::Result<bool> {
+        let previous_file_path = format!("{}{}", file_path, PREVIOUS_CONFIGURATION_FILE_SUFFIX);
+        if !fs::try_exists(&previous_file_path).await? {
+            return Ok(false);
+        }
+        fs::rename(&previous_file_path, file_path).await?;
+        Ok(true)
+    }
+
+    /// Removes the configuration file that was replaced by the last installed configuration file, as the server
+    /// started successfully and is serving with the installed configuration.
+    ///
+    /// # Arguments
+    ///
+    /// * `file_path` - The path to the configuration file that was installed.
+    pub async fn discard_previous_file(file_path: &str) -> anyhow::Result<()> {
+        let previous_file_path = format!("{}{}", file_path, PREVIOUS_CONFIGURATION_FILE_SUFFIX);
+        if fs::try_exists(&previous_file_path).await? {
+            fs::remove_file(&previous_file_path).await?;
+        }
+        Ok(())
     }
 
     /// Validates this configuration, returning the first validation error.
@@ -134,17 +1015,483 @@ impl Configuration {
             }
         }
 
-        // ensure that git is installed
-        match Command::new("git").arg("--version").output().await {
-            Ok(output) if output.status.success() => {
-                info!(
-                    "Detected {}",
-                    String::from_utf8_lossy(output.stdout.as_slice()).trim()
-                );
+        // check if all extended configurations exist and that no configuration extends itself
+        for deployment_config in &self.deployment_configs {
+            let mut inheritance_chain = vec![&deployment_config.id];
+            let mut parent_id = deployment_config.extends.as_ref();
+            while let Some(current_parent_id) = parent_id {
+                if inheritance_chain.contains(&current_parent_id) {
+                    bail!(
+                        "deployment configuration {} extends itself: {} -> {}",
+                        deployment_config.id,
+                        inheritance_chain
+                            .iter()
+                            .map(|id| id.as_str())
+                            .collect::<Vec<_>>()
+                            .join(" -> "),
+                        current_parent_id
+                    )
+                }
+                let parent_config = match self
+                    .deployment_configs
+                    .iter()
+                    .find(|config| &config.id == current_parent_id)
+                {
+                    Some(parent_config) => parent_config,
+                    None => bail!(
+                        "deployment configuration {} extends unknown configuration {}",
+                        inheritance_chain[inheritance_chain.len() - 1],
+                        current_parent_id
+                    ),
+                };
+                inheritance_chain.push(current_parent_id);
+                parent_id = parent_config.extends.as_ref();
             }
-            Ok(output) => bail!("git version check received unexpected {}", output.status),
-            Err(err) => bail!("unable to detect running git version: {}", err),
-        };
+        }
+
+        // check if all api tokens can be identified by their name
+        let mut known_api_token_names = HashSet::<&String>::new();
+        for api_token in &self.api_tokens {
+            if api_token.name.trim().is_empty() {
+                bail!("the name of an api token must not be empty")
+            }
+            if !known_api_token_names.insert(&api_token.name) {
+                bail!("detected duplicate api token name: {}", api_token.name)
+            }
+        }
+
+        // check if all secret references can be provided to scripts
+        for deployment_config in &self.deployment_configs {
+            for secret in &deployment_config.secrets {
+                if secret.env.is_none() && secret.file.is_none() {
+                    bail!(
+                        "secret {} in deployment configuration {} is neither provided as env nor as file",
+                        secret.reference,
+                        deployment_config.id
+                    )
+                }
+                if secret.source == SecretSource::Vault && self.vault.is_none() {
+                    bail!(
+                        "deployment configuration {} references a vault secret, but vault is not configured",
+                        deployment_config.id
+                    )
+                }
+            }
+        }
+
+        // check if the exec commands can be identified by their name and executed
+        for deployment_config in &self.deployment_configs {
+            let mut known_exec_command_names = HashSet::<&String>::new();
+            for exec_command in &deployment_config.exec_commands {
+                if exec_command.command.is_empty() {
+                    bail!(
+                        "exec command {} of deployment configuration {} does not specify a program",
+                        exec_command.name,
+                        deployment_config.id
+                    )
+                }
+                if !known_exec_command_names.insert(&exec_command.name) {
+                    bail!(
+                        "detected duplicate exec command name {} in deployment configuration {}",
+                        exec_command.name,
+                        deployment_config.id
+                    )
+                }
+            }
+        }
+
+        // check if the script timeouts leave the scripts time to execute
+        if self.script_timeout_seconds == Some(0) {
+            bail!("the script timeout must be at least 1 second")
+        }
+        for deployment_config in &self.deployment_configs {
+            if deployment_config.script_timeout_seconds == Some(0) {
+                bail!(
+                    "the script timeout of deployment configuration {} must be at least 1 second",
+                    deployment_config.id
+                )
+            }
+        }
+
+        // check if branch tracking is only enabled for profiles that can be deployed
+        for deployment_config in &self.deployment_configs {
+            if deployment_config.track_branch.is_some() && deployment_config.extend_only {
+                bail!(
+                    "deployment configuration {} tracks a branch but can only be extended",
+                    deployment_config.id
+                )
+            }
+        }
+
+        // check if the environment variables of the scripts can be set and their env files exist
+        for deployment_config in &self.deployment_configs {
+            for env_name in deployment_config.environment.keys() {
+                if env_name.is_empty() || env_name.contains('=') {
+                    bail!(
+                        "deployment configuration {} contains invalid environment variable name {:?}",
+                        deployment_config.id,
+                        env_name
+                    )
+                }
+            }
+            if let Some(env_file) = &deployment_config.env_file {
+                if !fs::try_exists(env_file).await.unwrap_or(false) {
+                    bail!(
+                        "deployment configuration {} references missing env file {}",
+                        deployment_config.id,
+                        env_file.display()
+                    )
+                }
+            }
+        }
+
+        // check if the deploy keys and known hosts files used for ssh checkouts exist
+        for deployment_config in &self.deployment_configs {
+            if let Some(ssh_checkout_config) = &deployment_config.ssh_checkout {
+                let ssh_files = [
+                    &ssh_checkout_config.private_key_path,
+                    &ssh_checkout_config.known_hosts_file,
+                ];
+                for ssh_file in ssh_files.into_iter().flatten() {
+                    if !fs::try_exists(ssh_file).await.unwrap_or(false) {
+                        bail!(
+                            "deployment configuration {} references missing ssh file {}",
+                            deployment_config.id,
+                            ssh_file
+                        )
+                    }
+                }
+            }
+        }
+
+        // check if published releases are only deployed for profiles that can be deployed and receive the webhooks
+        for deployment_config in &self.deployment_configs {
+            if deployment_config.deploy_published_releases {
+                if deployment_config.extend_only {
+                    bail!(
+                        "deployment configuration {} deploys published releases but can only be extended",
+                        deployment_config.id
+                    )
+                }
+                if self.github_webhook.is_none() {
+                    bail!(
+                        "deployment configuration {} deploys published releases, but the github webhook is not configured",
+                        deployment_config.id
+                    )
+                }
+            }
+        }
+
+        // check if the configurations sharing a target can share their releases: the release ids are only unique
+        // within one repository, the directory names must be consistent for all releases in the shared pool and only
+        // one of the configurations can track a branch, as they share the current release
+        let mut target_configs = HashMap::<&String, &DeploymentConfiguration>::new();
+        let mut branch_tracking_configs = HashMap::<&String, &DeploymentConfiguration>::new();
+        for deployment_config in self
+            .deployment_configs
+            .iter()
+            .filter(|config| !config.extend_only)
+        {
+            if deployment_config.track_branch.is_some() {
+                if let Some(other_config) =
+                    branch_tracking_configs.insert(&deployment_config.target, deployment_config)
+                {
+                    bail!(
+                        "deployment configurations {} and {} share target {} but both track a branch",
+                        other_config.id,
+                        deployment_config.id,
+                        deployment_config.target
+                    )
+                }
+            }
+            let other_config = *target_configs
+                .entry(&deployment_config.target)
+                .or_insert(deployment_config);
+            if deployment_config.source_repo_owner != other_config.source_repo_owner
+                || deployment_config.source_repo_name != other_config.source_repo_name
+            {
+                bail!(
+                    "deployment configurations {} and {} share target {} but deploy from different repositories",
+                    other_config.id,
+                    deployment_config.id,
+                    deployment_config.target
+                )
+            }
+            if deployment_config.release_directory_name != other_config.release_directory_name {
+                bail!(
+                    "deployment configurations {} and {} share target {} but use different release directory names",
+                    other_config.id,
+                    deployment_config.id,
+                    deployment_config.target
+                )
+            }
+        }
+
+        // check if the profiles deploying artifacts declare the asset and use no option that requires a git checkout
+        for deployment_config in &self.deployment_configs {
+            if deployment_config.mode != DeploymentMode::Artifact {
+                continue;
+            }
+            if deployment_config.artifact.is_none() {
+                bail!(
+                    "deployment configuration {} uses the artifact mode but declares no artifact",
+                    deployment_config.id
+                )
+            }
+            let git_only_options = [
+                ("allow_ref_deploys", deployment_config.allow_ref_deploys),
+                ("track_branch", deployment_config.track_branch.is_some()),
+                ("sparse_paths", !deployment_config.sparse_paths.is_empty()),
+                ("ssh_checkout", deployment_config.ssh_checkout.is_some()),
+                ("repository_cache", deployment_config.repository_cache),
+                ("require_signed_tags", deployment_config.require_signed_tags),
+                (
+                    "revision_file_name",
+                    deployment_config.revision_file_name.is_some(),
+                ),
+            ];
+            for (option_name, option_used) in git_only_options {
+                if option_used {
+                    bail!(
+                        "deployment configuration {} uses the artifact mode, which does not support {}",
+                        deployment_config.id,
+                        option_name
+                    )
+                }
+            }
+        }
+
+        // check if the profiles requiring an approval are only published on request, deployments that are published
+        // automatically cannot wait for an approval
+        for deployment_config in &self.deployment_configs {
+            if deployment_config.require_approval
+                && (deployment_config.deploy_published_releases
+                    || deployment_config.track_branch.is_some())
+            {
+                bail!(
+                    "deployment configuration {} requires approvals, which cannot be combined with deploy_published_releases and track_branch",
+                    deployment_config.id
+                )
+            }
+        }
+
+        // check if the profiles requiring signed tags only deploy releases, as deployments of git refs and tracked
+        // branches have no tag whose signature could be verified
+        for deployment_config in &self.deployment_configs {
+            if deployment_config.require_signed_tags
+                && (deployment_config.allow_ref_deploys || deployment_config.track_branch.is_some())
+            {
+                bail!(
+                    "deployment configuration {} requires signed tags, which cannot be combined with allow_ref_deploys and track_branch",
+                    deployment_config.id
+                )
+            }
+        }
+
+        // check if the slot names are unique and can be used in directory names
+        for deployment_config in &self.deployment_configs {
+            let mut known_slot_names = HashSet::<&String>::new();
+            for slot in &deployment_config.slots {
+                let valid_slot_name = !slot.name.is_empty()
+                    && slot
+                        .name
+                        .chars()
+                        .all(|char| char.is_ascii_alphanumeric() || char == '-' || char == '_');
+                if !valid_slot_name || !known_slot_names.insert(&slot.name) {
+                    bail!(
+                        "slot name {} in deployment configuration {} is invalid or duplicated",
+                        slot.name,
+                        deployment_config.id
+                    )
+                }
+            }
+        }
+
+        // check if all branch patterns can be parsed
+        for deployment_config in &self.deployment_configs {
+            let branch_patterns = deployment_config
+                .allowed_repo_branches
+                .iter()
+                .chain(&deployment_config.denied_repo_branches);
+            for branch_pattern in branch_patterns {
+                if let Err(err) = Pattern::new(branch_pattern) {
+                    bail!(
+                        "branch pattern {} in deployment configuration {} is invalid: {}",
+                        branch_pattern,
+                        deployment_config.id,
+                        err
+                    )
+                }
+            }
+        }
+
+        // check if the release directory names can be parsed back into the release ids
+        for deployment_config in &self.deployment_configs {
+            let directory_name = &deployment_config.release_directory_name;
+            let id_separator = directory_name
+                .strip_prefix("{id}")
+                .and_then(|remainder| remainder.chars().next());
+            let remaining_placeholders =
+                directory_name.replacen("{id}", "", 1).replace("{tag}", "");
+            if !directory_name.starts_with("{id}")
+                || id_separator
+                    .is_some_and(|separator| separator.is_ascii_digit() || separator == '{')
+                || remaining_placeholders.contains(['{', '}', '/'])
+            {
+                bail!(
+                    "release directory name {} of deployment configuration {} must start with {{id}}, followed by a separator and {{tag}}",
+                    directory_name,
+                    deployment_config.id
+                )
+            }
+        }
+
+        // check if the process priorities are in the ranges supported by the os
+        for deployment_config in &self.deployment_configs {
+            if let Some(process_priority) = &deployment_config.process_priority {
+                if process_priority
+                    .nice
+                    .is_some_and(|nice| !(-20..=19).contains(&nice))
+                {
+                    bail!(
+                        "niceness of deployment configuration {} must be between -20 and 19",
+                        deployment_config.id
+                    )
+                }
+                if let Some(io_priority) = process_priority.io_priority {
+                    if io_priority > 7 {
+                        bail!(
+                            "io priority of deployment configuration {} must be between 0 and 7",
+                            deployment_config.id
+                        )
+                    }
+                    if process_priority.io_class != Some(IoPriorityClass::BestEffort) {
+                        bail!(
+                            "io priority of deployment configuration {} requires the best_effort io class",
+                            deployment_config.id
+                        )
+                    }
+                }
+            }
+        }
+
+        // check if all publish hooks can be executed
+        for deployment_config in &self.deployment_configs {
+            for publish_hook in &deployment_config.publish_hooks {
+                if reqwest::Method::from_bytes(publish_hook.method.as_bytes()).is_err() {
+                    bail!(
+                        "publish hook {} in deployment configuration {} uses invalid http method {}",
+                        publish_hook.name,
+                        deployment_config.id,
+                        publish_hook.method
+                    )
+                }
+                if publish_hook.max_attempts == 0 {
+                    bail!(
+                        "publish hook {} in deployment configuration {} must be attempted at least once",
+                        publish_hook.name,
+                        deployment_config.id
+                    )
+                }
+            }
+        }
+
+        // check if the names of the notifiers are unique, they are used to identify the notifier templates
+        if let Some(notification_config) = &self.notifications {
+            let mut known_notifier_names = HashSet::<&String>::new();
+            let webhook_names = notification_config
+                .webhooks
+                .iter()
+                .map(|webhook| &webhook.name);
+            let email_names = notification_config.emails.iter().map(|email| &email.name);
+            let alert_names = notification_config.alerts.iter().map(|alert| &alert.name);
+            for notifier_name in webhook_names.chain(email_names).chain(alert_names) {
+                if !known_notifier_names.insert(notifier_name) {
+                    bail!("detected duplicate notifier name: {}", notifier_name)
+                }
+            }
+            for email_config in &notification_config.emails {
+                if email_config.username.is_some() != email_config.password_file.is_some() {
+                    bail!(
+                        "email notifier {} must either set both username and password_file or none of them",
+                        email_config.name
+                    )
+                }
+            }
+        }
+
+        // check if tokens issued by the OpenID Connect provider can be validated at all
+        if self
+            .oidc
+            .as_ref()
+            .is_some_and(|oidc_config| oidc_config.allowed_algorithms.is_empty())
+        {
+            bail!("at least one algorithm must be allowed for tokens issued by the OpenID Connect provider")
+        }
+
+        // check if stuck actions are only reset after they were detected as stuck
+        if let Some(watchdog_config) = &self.watchdog {
+            if let Some(force_reset_after_seconds) = watchdog_config.force_reset_after_seconds {
+                if force_reset_after_seconds < watchdog_config.stuck_threshold_seconds {
+                    bail!(
+                        "the watchdog must not reset actions before they reach the stuck threshold"
+                    )
+                }
+            }
+        }
+
+        // check if the umask and directory mode are valid file mode bits
+        if self.umask.is_some_and(|umask| umask > 0o777) {
+            bail!("the umask must be an octal value between 0o000 and 0o777")
+        }
+        if self
+            .directory_mode
+            .is_some_and(|directory_mode| directory_mode > 0o7777)
+        {
+            bail!("the directory mode must be an octal value between 0o0000 and 0o7777")
+        }
+
+        // check if the signing key of the server binaries can be parsed
+        if let Some(self_update_config) = &self.self_update {
+            let public_key = &self_update_config.signing_public_key;
+            if public_key.len() != 64 || !public_key.chars().all(|char| char.is_ascii_hexdigit()) {
+                bail!("the self update signing public key must be a hex encoded ed25519 public key")
+            }
+        }
+
+        // ensure that git is installed, for each git executable that is configured
+        let server_git_config = self.git.clone().unwrap_or_default();
+        let mut git_executables = HashSet::new();
+        git_executables.insert(server_git_config.executable().to_string());
+        for deployment_config in &self.deployment_configs {
+            let git_config = server_git_config.merged_with(deployment_config.git.as_ref());
+            git_executables.insert(git_config.executable().to_string());
+        }
+        for git_executable in git_executables {
+            match Command::new(&git_executable)
+                .arg("--version")
+                .output()
+                .await
+            {
+                Ok(output) if output.status.success() => {
+                    info!(
+                        "Detected {} at {}",
+                        String::from_utf8_lossy(output.stdout.as_slice()).trim(),
+                        git_executable
+                    );
+                }
+                Ok(output) => bail!(
+                    "git version check of {} received unexpected {}",
+                    git_executable,
+                    output.status
+                ),
+                Err(err) => bail!(
+                    "unable to detect running git version of {}: {}",
+                    git_executable,
+                    err
+                ),
+            };
+        }
 
         Ok(())
     }
@@ -162,6 +1509,41 @@ impl Configuration {
             .cloned()
     }
 
+    /// Get all configured deployment configurations, including the ones that can only be extended.
+    pub fn get_deployment_configurations(&self) -> &[DeploymentConfiguration] {
+        &self.deployment_configs
+    }
+
+    /// Get a copy of this configuration in which all values that might contain credentials (header values, webhook
+    /// and publish hook urls and environment variables) are redacted, for example to display the configuration to
+    /// clients.
+    pub fn to_redacted(&self) -> Self {
+        let mut redacted_configuration = self.clone();
+        for deployment_config in &mut redacted_configuration.deployment_configs {
+            for publish_hook in &mut deployment_config.publish_hooks {
+                publish_hook.url = REDACTED_VALUE.to_string();
+                redact_values(&mut publish_hook.headers);
+            }
+            redact_values(&mut deployment_config.environment);
+            for slot in &mut deployment_config.slots {
+                redact_values(&mut slot.env);
+            }
+            if let Some(git_config) = &mut deployment_config.git {
+                git_config.redact();
+            }
+        }
+        if let Some(git_config) = &mut redacted_configuration.git {
+            git_config.redact();
+        }
+        if let Some(notification_config) = &mut redacted_configuration.notifications {
+            for webhook in &mut notification_config.webhooks {
+                webhook.url = REDACTED_VALUE.to_string();
+                redact_values(&mut webhook.headers);
+            }
+        }
+        redacted_configuration
+    }
+
     /// Get the ids of the configured deployment configurations.
     pub fn get_deployment_configuration_ids(&self) -> Vec<String> {
         self.deployment_configs
@@ -170,37 +1552,427 @@ impl Configuration {
             .map(|config| config.id.clone())
             .collect()
     }
+
+    /// Get the settings that differ between this and the given configuration and can only be applied by restarting
+    /// the server: all settings except the deployment configurations, which are applied when they are used, and the
+    /// branches tracked by the deployment configurations, as the tracking tasks are only started once.
+    ///
+    /// # Arguments
+    /// * `other` - The configuration to compare this configuration with.
+    ///
+    /// # Returns
+    /// * `Vec<String>` - The names of the changed settings that require a restart.
+    pub fn get_restart_required_changes(&self, other: &Self) -> anyhow::Result<Vec<String>> {
+        let (toml::Value::Table(settings), toml::Value::Table(other_settings)) =
+            (toml::Value::try_from(self)?, toml::Value::try_from(other)?)
+        else {
+            bail!("configuration is not serialized as table")
+        };
+        let setting_names: BTreeSet<&String> =
+            settings.keys().chain(other_settings.keys()).collect();
+        let mut changed_settings = setting_names
+            .into_iter()
+            .filter(|setting_name| *setting_name != "deployment_configs")
+            .filter(|setting_name| settings.get(*setting_name) != other_settings.get(*setting_name))
+            .cloned()
+            .collect::<Vec<String>>();
+
+        let tracked_branches = |configuration: &Self| {
+            configuration
+                .deployment_configs
+                .iter()
+                .filter_map(|config| {
+                    let tracked_branch = config.track_branch.clone()?;
+                    Some((
+                        config.id.clone(),
+                        tracked_branch,
+                        config.track_branch_poll_interval_seconds,
+                    ))
+                })
+                .collect::<BTreeSet<(String, String, u64)>>()
+        };
+        if tracked_branches(self) != tracked_branches(other) {
+            changed_settings.push("deployment_configs.track_branch".to_string());
+        }
+        Ok(changed_settings)
+    }
+}
+
+impl GitConfiguration {
+    /// Get the path to the git executable, `git` if no executable is configured.
+    pub fn executable(&self) -> &str {
+        self.executable.as_deref().unwrap_or("git")
+    }
+
+    /// Returns a copy of this configuration with the given configuration applied on top of it. The executable of the
+    /// given configuration takes precedence, the environment variables and git configuration values are combined.
+    ///
+    /// # Arguments
+    /// * `other` - The configuration that overrides this configuration, if any.
+    pub fn merged_with(&self, other: Option<&GitConfiguration>) -> GitConfiguration {
+        let mut merged_configuration = self.clone();
+        if let Some(other) = other {
+            if other.executable.is_some() {
+                merged_configuration.executable = other.executable.clone();
+            }
+            merged_configuration.env.extend(other.env.clone());
+            merged_configuration.config.extend(other.config.clone());
+        }
+        merged_configuration
+    }
+
+    /// Redacts the environment variables and git configuration values, which might contain credentials (for example
+    /// a proxy url with a password).
+    fn redact(&mut self) {
+        redact_values(&mut self.env);
+        redact_values(&mut self.config);
+    }
 }
 
 impl DeploymentConfiguration {
     /// Checks if the given branch is allowed to trigger a deployment
     /// using this deployment configuration. Note that denied branches
-    /// are checked before allowed branches.
+    /// are checked before allowed branches. The configured branches can
+    /// be glob patterns (for example `release/*`).
     ///
     /// # Arguments
     /// * `branch_name` - The name of the branch to check.
-    pub fn is_branch_allowed_to_use_config(&self, branch_name: &String) -> bool {
-        if self.denied_repo_branches.contains(branch_name) {
+    pub fn is_branch_allowed_to_use_config(&self, branch_name: &str) -> bool {
+        if matches_any_branch_pattern(&self.denied_repo_branches, branch_name) {
             false
         } else {
             self.allowed_repo_branches.is_empty()
-                || self.allowed_repo_branches.contains(branch_name)
+                || matches_any_branch_pattern(&self.allowed_repo_branches, branch_name)
         }
     }
 
-    /// Parses the symlinks that are provided to this configuration.
+    /// Checks if one of the given changed files is located in one of the paths filters of this configuration. Always
+    /// returns true if no path filters are configured.
+    ///
+    /// # Arguments
+    /// * `changed_files` - The paths of the changed files, relative to the repository root.
+    pub fn is_change_relevant(&self, changed_files: &[String]) -> bool {
+        if self.path_filters.is_empty() {
+            return true;
+        }
+
+        changed_files.iter().any(|changed_file| {
+            self.path_filters.iter().any(|path_filter| {
+                let path_filter = path_filter.trim_matches('/');
+                changed_file == path_filter || changed_file.starts_with(&format!("{path_filter}/"))
+            })
+        })
+    }
+
+    /// Returns a copy of this configuration in which deployments are executed in the given slot.
+    ///
+    /// # Arguments
+    /// * `deployment_slot` - The slot in which deployments should be executed, if any.
+    pub fn with_deployment_slot(&self, deployment_slot: Option<SlotConfiguration>) -> Self {
+        Self {
+            deployment_slot,
+            ..self.clone()
+        }
+    }
+
+    /// Parses the symlinks that are provided to this configuration, including the symlinks of the deployment slot.
     pub fn get_symlinks(&self) -> Vec<Symlink> {
+        let slot_symlinks = self
+            .deployment_slot
+            .iter()
+            .flat_map(|deployment_slot| &deployment_slot.symlinks);
         self.symlinks
             .iter()
-            .map(|part| part.split_once(':'))
-            .filter(|split| split.is_some())
-            .map(|split| {
-                let (source, target) = split.unwrap();
-                Symlink {
-                    source: source.to_string(),
-                    target: target.to_string(),
+            .chain(slot_symlinks)
+            .filter_map(|definition| match definition {
+                SymlinkDefinition::Short(part) => {
+                    part.split_once(':').map(|(source, target)| Symlink {
+                        source: source.to_string(),
+                        target: target.to_string(),
+                        required: false,
+                        create_target_dir: false,
+                    })
                 }
+                SymlinkDefinition::Detailed(symlink) => Some(symlink.clone()),
             })
             .collect()
     }
 }
+
+/// Checks if the given branch name matches one of the given branch patterns. A `*` in a pattern does not match the `/`
+/// separating the parts of a branch name, invalid patterns are only matched literally.
+///
+/// # Arguments
+/// * `branch_patterns` - The glob patterns to match the branch name against.
+/// * `branch_name` - The name of the branch to match.
+fn matches_any_branch_pattern(branch_patterns: &[String], branch_name: &str) -> bool {
+    let match_options = MatchOptions {
+        require_literal_separator: true,
+        ..MatchOptions::new()
+    };
+    branch_patterns
+        .iter()
+        .any(|branch_pattern| match Pattern::new(branch_pattern) {
+            Ok(pattern) => pattern.matches_with(branch_name, match_options),
+            Err(_) => branch_pattern == branch_name,
+        })
+}
+
+/// Get the hex encoded SHA-256 hash of the given configuration file content.
+///
+/// # Arguments
+/// * `file_content` - The content of the configuration file.
+pub fn hash_configuration_content(file_content: &str) -> String {
+    digest(&SHA256, file_content.as_bytes())
+        .as_ref()
+        .iter()
+        .map(|byte| format!("{:02x}", byte))
+        .collect()
+}
+
+/// Replaces each deployment configuration in the given configuration table that extends another configuration with the
+/// result of merging it into the (resolved) extended configuration, so that it contains all inherited settings.
+///
+/// # Arguments
+/// * `configuration_table` - The parsed configuration table whose deployment configurations should be resolved.
+fn resolve_profile_inheritance(configuration_table: &mut toml::Table) -> anyhow::Result<()> {
+    let profile_tables = match configuration_table.get("deployment_configs") {
+        Some(toml::Value::Array(profiles)) => profiles
+            .iter()
+            .filter_map(|profile| profile.as_table())
+            .filter_map(|profile| Some((profile.get("id")?.as_str()?.to_string(), profile.clone())))
+            .collect::<Vec<_>>(),
+        _ => return Ok(()),
+    };
+
+    let mut resolved_profiles = HashMap::new();
+    for (profile_id, _) in &profile_tables {
+        resolve_profile(
+            profile_id,
+            &profile_tables,
+            &mut resolved_profiles,
+            &mut Vec::new(),
+        )?;
+    }
+    if let Some(toml::Value::Array(profiles)) = configuration_table.get_mut("deployment_configs") {
+        for profile in profiles.iter_mut() {
+            let profile_id = profile
+                .get("id")
+                .and_then(|id| id.as_str())
+                .map(|id| id.to_string());
+            if let Some(resolved_profile) = profile_id.and_then(|id| resolved_profiles.get(&id)) {
+                *profile = toml::Value::Table(resolved_profile.clone());
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Resolves the settings of the deployment configuration with the given id, merging it into the configurations it
+/// extends. Resolved configurations are cached in the given map.
+///
+/// # Arguments
+/// * `profile_id` - The id of the deployment configuration to resolve.
+/// * `profile_tables` - The ids and tables of all deployment configurations, as declared in the configuration.
+/// * `resolved_profiles` - The deployment configurations that were already resolved, by their id.
+/// * `inheritance_chain` - The ids of the configurations that are currently resolved and extend the configuration.
+fn resolve_profile(
+    profile_id: &str,
+    profile_tables: &[(String, toml::Table)],
+    resolved_profiles: &mut HashMap<String, toml::Table>,
+    inheritance_chain: &mut Vec<String>,
+) -> anyhow::Result<toml::Table> {
+    if let Some(resolved_profile) = resolved_profiles.get(profile_id) {
+        return Ok(resolved_profile.clone());
+    }
+    if inheritance_chain.iter().any(|id| id == profile_id) {
+        bail!(
+            "deployment configuration {} extends itself: {} -> {}",
+            profile_id,
+            inheritance_chain.join(" -> "),
+            profile_id
+        )
+    }
+    let profile_table = match profile_tables.iter().find(|(id, _)| id == profile_id) {
+        Some((_, profile_table)) => profile_table.clone(),
+        None => bail!(
+            "deployment configuration {} extends unknown configuration {}",
+            inheritance_chain
+                .last()
+                .map(|id| id.as_str())
+                .unwrap_or_default(),
+            profile_id
+        ),
+    };
+
+    let resolved_profile = match profile_table.get("extends") {
+        Some(toml::Value::String(parent_id)) => {
+            inheritance_chain.push(profile_id.to_string());
+            let mut parent_table = resolve_profile(
+                parent_id,
+                profile_tables,
+                resolved_profiles,
+                inheritance_chain,
+            )?;
+            inheritance_chain.pop();
+
+            // the identity of the parent and if it can only be extended is not inherited
+            parent_table.remove("id");
+            parent_table.remove("extend_only");
+            merge_tables(&mut parent_table, profile_table);
+            parent_table
+        }
+        _ => profile_table,
+    };
+    resolved_profiles.insert(profile_id.to_string(), resolved_profile.clone());
+    Ok(resolved_profile)
+}
+
+/// Merges the given overriding table into the given base table. Tables that exist in both are merged recursively, all
+/// other values of the overriding table replace the values of the base table.
+///
+/// # Arguments
+/// * `base_table` - The table to merge the overriding values into.
+/// * `overriding_table` - The table whose values take precedence.
+fn merge_tables(base_table: &mut toml::Table, overriding_table: toml::Table) {
+    for (key, overriding_value) in overriding_table {
+        match (base_table.get_mut(&key), overriding_value) {
+            (Some(toml::Value::Table(base_value)), toml::Value::Table(overriding_value)) => {
+                merge_tables(base_value, overriding_value)
+            }
+            (_, overriding_value) => {
+                base_table.insert(key, overriding_value);
+            }
+        }
+    }
+}
+
+/// Replaces all values of the given map with the redacted placeholder, keeping the keys.
+///
+/// # Arguments
+/// * `values` - The map whose values should be redacted.
+fn redact_values(values: &mut HashMap<String, String>) {
+    for value in values.values_mut() {
+        *value = REDACTED_VALUE.to_string();
+    }
+}
+
+/// The default name of the claim containing the roles of an authenticated user.
+fn default_role_claim() -> String {
+    "roles".to_string()
+}
+
+/// The default interval in which the signing keys of the token issuer are refreshed.
+fn default_key_refresh_interval_seconds() -> u64 {
+    600
+}
+
+/// The default algorithms that tokens signed with a key not declaring its algorithm can be signed with.
+fn default_oidc_allowed_algorithms() -> Vec<Algorithm> {
+    vec![Algorithm::RS256]
+}
+
+/// The default http method used to call publish hooks.
+fn default_publish_hook_method() -> String {
+    "POST".to_string()
+}
+
+/// The default amount of attempts to call a publish hook.
+fn default_publish_hook_max_attempts() -> u32 {
+    3
+}
+
+/// The default delay between two attempts to call a publish hook.
+fn default_publish_hook_retry_delay_seconds() -> u64 {
+    5
+}
+
+/// The default timeout of a single call to a publish hook.
+fn default_publish_hook_timeout_seconds() -> u64 {
+    30
+}
+
+/// The default interval in which release directories are scanned for orphaned entries.
+fn default_orphan_cleanup_interval_seconds() -> u64 {
+    3600
+}
+
+/// The default interval in which the git maintenance of the repository caches is executed.
+fn default_repository_cache_maintenance_interval_seconds() -> u64 {
+    86400
+}
+
+/// The default interval in which the watchdog checks the current action.
+fn default_watchdog_check_interval_seconds() -> u64 {
+    60
+}
+
+/// The default time after which the watchdog considers an action that stays in the same state stuck.
+fn default_watchdog_stuck_threshold_seconds() -> u64 {
+    3600
+}
+
+/// The default owner of the repository from which easydep releases are downloaded.
+fn default_self_update_repo_owner() -> String {
+    "easybill".to_string()
+}
+
+/// The default name of the repository from which easydep releases are downloaded.
+fn default_self_update_repo_name() -> String {
+    "easydep".to_string()
+}
+
+/// The default interval in which the head of a tracked branch is polled.
+fn default_track_branch_poll_interval_seconds() -> u64 {
+    60
+}
+
+/// The default path at which the GitHub webhooks are received.
+fn default_github_webhook_path() -> String {
+    "/github/webhook".to_string()
+}
+
+/// The default container runtime used to execute lifecycle scripts in containers.
+fn default_container_runtime() -> String {
+    "docker".to_string()
+}
+
+/// The default path at which the deployment directory is mounted into containers.
+fn default_container_workdir() -> String {
+    "/workspace".to_string()
+}
+
+/// The default template of the subject of notification emails.
+fn default_email_subject() -> String {
+    "[easydep] {{event}}: {{release_tag}} on {{server}} ({{profile}})".to_string()
+}
+
+/// The default job label of metrics pushed to a pushgateway.
+fn default_pushgateway_job() -> String {
+    "easydep".to_string()
+}
+
+/// The default prefix of metrics sent to StatsD.
+fn default_statsd_prefix() -> String {
+    "easydep".to_string()
+}
+
+/// The default time for which release information fetched from GitHub is cached.
+fn default_github_release_cache_ttl_seconds() -> u64 {
+    60
+}
+
+/// The default time that timed out scripts get to exit before they are killed.
+fn default_script_kill_grace_period_seconds() -> u64 {
+    10
+}
+
+/// The default maximum size of all archives of failed actions combined.
+fn default_failed_archive_max_bytes() -> u64 {
+    100 * 1024 * 1024
+}
+
+/// The default template of the names of the release directories.
+fn default_release_directory_name() -> String {
+    "{id}".to_string()
+}
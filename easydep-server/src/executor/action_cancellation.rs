@@ -0,0 +1,58 @@
+/*
+ * This file is part of easydep, licensed under the MIT License (MIT).
+ *
+ * Copyright (c) 2024 easybill GmbH
+ *
+ * Permission is hereby granted, free of charge, to any person obtaining a copy
+ * of this software and associated documentation files (the "Software"), to deal
+ * in the Software without restriction, including without limitation the rights
+ * to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+ * copies of the Software, and to permit persons to whom the Software is
+ * furnished to do so, subject to the following conditions:
+ *
+ * The above copyright notice and this permission notice shall be included in all
+ * copies or substantial portions of the Software.
+ *
+ * THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+ * IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+ * FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+ * AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+ * LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+ * OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+ * SOFTWARE.
+ */
+
+use std::sync::Arc;
+
+use tokio::sync::watch;
+
+/// A signal to cancel the external processes of an action. All clones of a cancellation share the same state.
+#[derive(Clone, Debug)]
+pub(crate) struct ActionCancellation {
+    cancelled_sender: Arc<watch::Sender<bool>>,
+}
+
+impl ActionCancellation {
+    /// Constructs a new cancellation that was not cancelled yet.
+    pub fn new() -> Self {
+        let (cancelled_sender, _) = watch::channel(false);
+        Self {
+            cancelled_sender: Arc::new(cancelled_sender),
+        }
+    }
+
+    /// Cancels the action, the processes that are currently running or started afterwards are terminated.
+    pub fn cancel(&self) {
+        self.cancelled_sender.send_replace(true);
+    }
+
+    /// Waits until the action is cancelled, returns immediately if the action was cancelled already.
+    pub async fn cancelled(&self) {
+        let mut cancelled_receiver = self.cancelled_sender.subscribe();
+        // the sender is kept alive by this cancellation, waiting can only fail if it was dropped
+        cancelled_receiver
+            .wait_for(|cancelled| *cancelled)
+            .await
+            .ok();
+    }
+}
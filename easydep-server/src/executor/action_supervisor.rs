@@ -0,0 +1,90 @@
+/*
+ * This file is part of easydep, licensed under the MIT License (MIT).
+ *
+ * Copyright (c) 2024 easybill GmbH
+ *
+ * Permission is hereby granted, free of charge, to any person obtaining a copy
+ * of this software and associated documentation files (the "Software"), to deal
+ * in the Software without restriction, including without limitation the rights
+ * to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+ * copies of the Software, and to permit persons to whom the Software is
+ * furnished to do so, subject to the following conditions:
+ *
+ * The above copyright notice and this permission notice shall be included in all
+ * copies or substantial portions of the Software.
+ *
+ * THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+ * IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+ * FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+ * AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+ * LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+ * OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+ * SOFTWARE.
+ */
+
+use std::any::Any;
+use std::future::Future;
+use std::panic::AssertUnwindSafe;
+
+use futures::FutureExt;
+use log::error;
+use tokio::sync::mpsc::Sender;
+use tonic::Status;
+
+use crate::accessor::deploy_action_accessor::{CurrentAction, DeploymentStatusAccessor};
+use crate::easydep::ExecutedActionEntry;
+
+/// Executes the given action, catching a panic of it. If the action panics the current action is reset to idle (as
+/// it would otherwise stay in the executing state forever) and an error entry is sent to the given sender, which
+/// marks the action as failed in the recorded outcome and ends the stream of the client.
+///
+/// # Arguments
+/// * `action_name` - The name of the action, used in the log and error messages.
+/// * `deployment_status_accessor` - The accessor for the action that is currently being executed.
+/// * `data_sender` - The sender for the executed action entries of the action.
+/// * `action` - The action to execute.
+///
+/// # Returns
+/// * `Option<T>` - The result of the action, `None` if the action panicked.
+pub async fn supervise_action<F, T>(
+    action_name: &str,
+    deployment_status_accessor: &DeploymentStatusAccessor,
+    data_sender: &Sender<Result<ExecutedActionEntry, Status>>,
+    action: F,
+) -> Option<T>
+where
+    F: Future<Output = T>,
+{
+    match AssertUnwindSafe(action).catch_unwind().await {
+        Ok(action_result) => Some(action_result),
+        Err(panic_payload) => {
+            let panic_message = describe_panic_payload(panic_payload.as_ref());
+            error!("The {action_name} action panicked: {panic_message}");
+            deployment_status_accessor
+                .set_action(CurrentAction::Idle)
+                .await;
+
+            let error_message =
+                format!("{action_name} action failed unexpectedly: {panic_message}");
+            data_sender
+                .send(Err(Status::internal(error_message)))
+                .await
+                .ok();
+            None
+        }
+    }
+}
+
+/// Get the message of a panic from the given payload, if the panic was raised with a message.
+///
+/// # Arguments
+/// * `panic_payload` - The payload of the panic.
+fn describe_panic_payload(panic_payload: &(dyn Any + Send)) -> &str {
+    if let Some(panic_message) = panic_payload.downcast_ref::<&str>() {
+        panic_message
+    } else if let Some(panic_message) = panic_payload.downcast_ref::<String>() {
+        panic_message
+    } else {
+        "unknown panic"
+    }
+}
@@ -0,0 +1,221 @@
+/*
+ * This file is part of easydep, licensed under the MIT License (MIT).
+ *
+ * Copyright (c) 2024 easybill GmbH
+ *
+ * Permission is hereby granted, free of charge, to any person obtaining a copy
+ * of this software and associated documentation files (the "Software"), to deal
+ * in the Software without restriction, including without limitation the rights
+ * to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+ * copies of the Software, and to permit persons to whom the Software is
+ * furnished to do so, subject to the following conditions:
+ *
+ * The above copyright notice and this permission notice shall be included in all
+ * copies or substantial portions of the Software.
+ *
+ * THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+ * IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+ * FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+ * AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+ * LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+ * OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+ * SOFTWARE.
+ */
+
+use std::time::Duration;
+
+use log::{error, warn};
+use tokio::time::Instant;
+
+use crate::accessor::deploy_action_accessor::{CurrentAction, DeploymentStatusAccessor};
+use crate::accessor::deploy_status_accessor::DeployExecutionState;
+use crate::config::WatchdogConfiguration;
+use crate::notification::deployment_notification::DeploymentNotification;
+use crate::notification::notification_dispatcher::NotificationDispatcher;
+
+/// The state of the current action as observed by the watchdog. An action that does not change this state does not
+/// make any progress.
+#[derive(Clone, Debug, Eq, PartialEq)]
+enum ObservedActionState {
+    /// A deployment of the release with the given id is executed and in the given state.
+    Executing(u64, DeployExecutionState),
+    /// A rollback to the release with the given id is executed.
+    RollingBack(u64),
+    /// The scripts of the published release with the given id are executed again.
+    RerunningScripts(u64),
+}
+
+impl ObservedActionState {
+    /// Get the name of the action that is executed in this state, as used in notifications.
+    fn get_action_name(&self) -> &'static str {
+        match self {
+            Self::Executing(
+                _,
+                DeployExecutionState::Publishing | DeployExecutionState::Published,
+            ) => "publish",
+            Self::Executing(_, DeployExecutionState::Deleting | DeployExecutionState::Deleted) => {
+                "delete"
+            }
+            Self::Executing(
+                _,
+                DeployExecutionState::Cancelling | DeployExecutionState::Cancelled,
+            ) => "cancel",
+            Self::Executing(_, _) => "prepare",
+            Self::RollingBack(_) => "rollback",
+            Self::RerunningScripts(_) => "rerun-scripts",
+        }
+    }
+}
+
+/// An action state that was observed by the watchdog.
+#[derive(Debug)]
+struct ActionObservation {
+    /// The state of the action that was observed.
+    state: ObservedActionState,
+    /// The time when the state was first observed.
+    observed_since: Instant,
+    /// If a notification about the action being stuck was already sent.
+    reported: bool,
+}
+
+/// Starts the task that periodically checks if the current action stays in the same state for longer than the
+/// configured threshold, notifying about stuck actions and resetting them if configured.
+///
+/// # Arguments
+/// * `watchdog_configuration` - The configuration of the watchdog.
+/// * `deployment_status_accessor` - The accessor for the action that is currently being executed.
+/// * `notification_dispatcher` - The dispatcher to notify about stuck actions.
+pub fn start_action_watchdog_task(
+    watchdog_configuration: WatchdogConfiguration,
+    deployment_status_accessor: DeploymentStatusAccessor,
+    notification_dispatcher: NotificationDispatcher,
+) {
+    let check_interval = Duration::from_secs(watchdog_configuration.check_interval_seconds);
+    let stuck_threshold = Duration::from_secs(watchdog_configuration.stuck_threshold_seconds);
+    let force_reset_after = watchdog_configuration
+        .force_reset_after_seconds
+        .map(Duration::from_secs);
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval(check_interval);
+        let mut last_observation: Option<ActionObservation> = None;
+        loop {
+            interval.tick().await;
+            let current_action = deployment_status_accessor.get_action().await;
+            let current_state = match observe_action_state(&current_action).await {
+                Some(current_state) => current_state,
+                None => {
+                    last_observation = None;
+                    continue;
+                }
+            };
+
+            // start a new observation if the action made progress since the last check
+            let observation = match last_observation.take() {
+                Some(observation) if observation.state == current_state => observation,
+                _ => ActionObservation {
+                    state: current_state,
+                    observed_since: Instant::now(),
+                    reported: false,
+                },
+            };
+            let stuck_duration = observation.observed_since.elapsed();
+            if stuck_duration < stuck_threshold {
+                last_observation = Some(observation);
+                continue;
+            }
+
+            if !observation.reported {
+                warn!(
+                    "Current action {:?} did not progress for {} seconds",
+                    observation.state,
+                    stuck_duration.as_secs()
+                );
+                if let Some(notification) = build_stuck_notification(
+                    &current_action,
+                    observation.state.get_action_name(),
+                    stuck_duration,
+                ) {
+                    notification_dispatcher.dispatch(notification);
+                }
+            }
+
+            // reset the action if it is stuck for too long, allowing new actions to be executed again
+            if force_reset_after
+                .is_some_and(|force_reset_after| stuck_duration >= force_reset_after)
+                && deployment_status_accessor
+                    .compare_and_set_action_by_variant(&current_action, CurrentAction::Idle)
+                    .await
+            {
+                error!(
+                    "Reset current action {:?} after it did not progress for {} seconds",
+                    observation.state,
+                    stuck_duration.as_secs()
+                );
+                continue;
+            }
+            last_observation = Some(ActionObservation {
+                reported: true,
+                ..observation
+            });
+        }
+    });
+}
+
+/// Get the observable state of the given action, `None` if the action is not expected to make progress on its own
+/// (the executor is idle or a prepared deployment is waiting to be published).
+///
+/// # Arguments
+/// * `current_action` - The action that is currently being executed.
+async fn observe_action_state(current_action: &CurrentAction) -> Option<ObservedActionState> {
+    match current_action {
+        CurrentAction::Idle => None,
+        CurrentAction::RollingBack(release, _, _) => {
+            Some(ObservedActionState::RollingBack(release.id.0))
+        }
+        CurrentAction::RerunningScripts(release, _, _) => {
+            Some(ObservedActionState::RerunningScripts(release.id.0))
+        }
+        CurrentAction::Executing(executor) => {
+            match executor.get_status_accessor().get_state().await {
+                DeployExecutionState::Prepared => None,
+                state => Some(ObservedActionState::Executing(
+                    executor.get_release_id(),
+                    state,
+                )),
+            }
+        }
+    }
+}
+
+/// Builds the notification about the given action being stuck for the given duration.
+///
+/// # Arguments
+/// * `current_action` - The action that is stuck.
+/// * `action_name` - The name of the action that is stuck.
+/// * `stuck_duration` - The time for which the action did not progress.
+fn build_stuck_notification(
+    current_action: &CurrentAction,
+    action_name: &str,
+    stuck_duration: Duration,
+) -> Option<DeploymentNotification> {
+    match current_action {
+        CurrentAction::Idle => None,
+        CurrentAction::RollingBack(release, deployment_configuration, triggered_by)
+        | CurrentAction::RerunningScripts(release, deployment_configuration, triggered_by) => {
+            Some(DeploymentNotification::from_stuck_action(
+                action_name,
+                release,
+                deployment_configuration,
+                triggered_by,
+                stuck_duration,
+            ))
+        }
+        CurrentAction::Executing(executor) => Some(DeploymentNotification::from_stuck_action(
+            action_name,
+            executor.get_release(),
+            executor.get_deployment_configuration(),
+            executor.get_triggered_by(),
+            stuck_duration,
+        )),
+    }
+}
@@ -0,0 +1,231 @@
+/*
+ * This file is part of easydep, licensed under the MIT License (MIT).
+ *
+ * Copyright (c) 2024 easybill GmbH
+ *
+ * Permission is hereby granted, free of charge, to any person obtaining a copy
+ * of this software and associated documentation files (the "Software"), to deal
+ * in the Software without restriction, including without limitation the rights
+ * to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+ * copies of the Software, and to permit persons to whom the Software is
+ * furnished to do so, subject to the following conditions:
+ *
+ * The above copyright notice and this permission notice shall be included in all
+ * copies or substantial portions of the Software.
+ *
+ * THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+ * IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+ * FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+ * AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+ * LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+ * OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+ * SOFTWARE.
+ */
+use std::sync::Arc;
+use std::time::Instant;
+
+use anyhow::{bail, Context};
+use log::{error, info, warn};
+use tokio::sync::mpsc::{channel, Receiver};
+use tokio::task::JoinHandle;
+use tonic::Status;
+
+use crate::accessor::deploy_action_accessor::{CurrentAction, DeploymentStatusAccessor};
+use crate::accessor::deploy_status_accessor::DeployExecutionState;
+use crate::accessor::deployment_rate_limit_accessor::DeploymentRateLimitAccessor;
+use crate::accessor::ref_deployment_accessor::{RefDeployment, RefDeploymentAccessor};
+use crate::config::{DeploymentConfiguration, NotificationEvent};
+use crate::easydep::{ActionStatus, ExecutedActionEntry};
+use crate::executor::action_supervisor::supervise_action;
+use crate::executor::deploy_executor::DeployExecutor;
+use crate::notification::action_outcome_recorder::record_action_outcome;
+use crate::notification::deployment_notification::DeploymentNotification;
+use crate::notification::notification_dispatcher::NotificationDispatcher;
+
+/// Prepares and directly publishes the deployment of the given executor without a client being involved, for example
+/// for deployments triggered by the server itself. The deployment is deleted again if the preparation did not succeed.
+/// The output of the actions is written into the log.
+///
+/// # Arguments
+/// * `deployment_executor` - The executor of the deployment to prepare and publish.
+/// * `deployment_configuration` - The deployment profile configuration of the deployment.
+/// * `deployment_status_accessor` - The accessor for the action that is currently being executed.
+/// * `notification_dispatcher` - The dispatcher to notify about the deployment.
+/// * `deployment_rate_limit_accessor` - The accessor to record the deployment in the rate limit of its profile.
+/// * `ref_deployment` - The planned deployment of a git ref and the accessor to record it with, if the deployment is
+///   a deployment of a git ref.
+pub(crate) async fn prepare_and_publish_deployment(
+    deployment_executor: Arc<DeployExecutor>,
+    deployment_configuration: &DeploymentConfiguration,
+    deployment_status_accessor: &DeploymentStatusAccessor,
+    notification_dispatcher: &NotificationDispatcher,
+    deployment_rate_limit_accessor: &DeploymentRateLimitAccessor,
+    ref_deployment: Option<(&RefDeploymentAccessor, &RefDeployment)>,
+) -> anyhow::Result<()> {
+    let release_id = deployment_executor.get_release_id();
+
+    // mark the service as executing the deployment, unless a client was faster
+    let deployment_action = CurrentAction::Executing(deployment_executor.clone());
+    if !deployment_status_accessor
+        .compare_and_set_action_by_variant(&CurrentAction::Idle, deployment_action)
+        .await
+    {
+        bail!("another action was started first")
+    }
+
+    // only deployments that occupy the server count towards the rate limit of the profile
+    if let Err(retry_after) = deployment_rate_limit_accessor
+        .try_record_deployment(deployment_configuration, false)
+        .await
+    {
+        deployment_status_accessor
+            .set_action(CurrentAction::Idle)
+            .await;
+        bail!(
+            "profile reached its deployment rate limit, retry in {} seconds",
+            retry_after.as_secs()
+        )
+    }
+
+    // ref deployments are only recorded once they occupy the server to not leave records of skipped deployments
+    if let Some((ref_deployment_accessor, ref_deployment)) = ref_deployment {
+        if let Err(err) = ref_deployment_accessor
+            .record_ref_deployment(deployment_configuration, ref_deployment)
+            .await
+        {
+            deployment_status_accessor
+                .set_action(CurrentAction::Idle)
+                .await;
+            return Err(err.context("unable to record ref deployment"));
+        }
+    }
+
+    // prepare the deployment, delete it again if the preparation did not succeed
+    let started_at = Instant::now();
+    let (data_sender, data_receiver) = channel::<Result<ExecutedActionEntry, Status>>(50);
+    let prepare_output = log_executed_actions(data_receiver);
+    let (recording_sender, outcome_handle) =
+        record_action_outcome(data_sender, deployment_configuration);
+    supervise_action(
+        "prepare",
+        deployment_status_accessor,
+        &recording_sender,
+        deployment_executor.prepare_deployment(recording_sender.clone()),
+    )
+    .await;
+    drop(recording_sender);
+    let prepare_outcome = outcome_handle.await.unwrap_or_default();
+    let prepare_succeeded = prepare_output.await.context("unable to await output")?;
+    // only failed preparations are notified about, successful ones are only recorded in the history
+    let prepare_failed = prepare_outcome.failed;
+    let prepare_notification = DeploymentNotification::from_outcome(
+        NotificationEvent::Prepared,
+        "prepare",
+        deployment_executor.get_release(),
+        deployment_configuration,
+        deployment_executor.get_triggered_by(),
+        started_at.elapsed(),
+        prepare_outcome,
+    )
+    .with_annotation(deployment_executor.get_annotation())
+    .with_git_ref(deployment_executor.get_git_ref().cloned());
+    if prepare_failed {
+        notification_dispatcher.dispatch(prepare_notification);
+    } else {
+        notification_dispatcher.record_history(&prepare_notification);
+    }
+    if !prepare_succeeded {
+        warn!("Preparing release {} failed, deleting it", release_id);
+        if deployment_executor
+            .get_status_accessor()
+            .compare_and_set_state(
+                &DeployExecutionState::Prepared,
+                DeployExecutionState::Deleting,
+            )
+            .await
+        {
+            let (data_sender, data_receiver) = channel::<Result<ExecutedActionEntry, Status>>(50);
+            let delete_output = log_executed_actions(data_receiver);
+            deployment_executor.delete_deployment(data_sender).await;
+            delete_output.await.ok();
+        }
+        deployment_status_accessor
+            .set_action(CurrentAction::Idle)
+            .await;
+        bail!("preparing the deployment did not succeed")
+    }
+
+    // publish the prepared deployment
+    if deployment_executor
+        .get_status_accessor()
+        .compare_and_set_state(
+            &DeployExecutionState::Prepared,
+            DeployExecutionState::Publishing,
+        )
+        .await
+    {
+        let (data_sender, data_receiver) = channel::<Result<ExecutedActionEntry, Status>>(50);
+        let publish_output = log_executed_actions(data_receiver);
+        let (recording_sender, outcome_handle) =
+            record_action_outcome(data_sender, deployment_configuration);
+        supervise_action(
+            "publish",
+            deployment_status_accessor,
+            &recording_sender,
+            deployment_executor.publish_deployment(recording_sender.clone()),
+        )
+        .await;
+        drop(recording_sender);
+        let publish_notification = DeploymentNotification::from_outcome(
+            NotificationEvent::Published,
+            "publish",
+            deployment_executor.get_release(),
+            deployment_configuration,
+            deployment_executor.get_triggered_by(),
+            started_at.elapsed(),
+            outcome_handle.await.unwrap_or_default(),
+        )
+        .with_annotation(deployment_executor.get_annotation())
+        .with_git_ref(deployment_executor.get_git_ref().cloned());
+        notification_dispatcher.dispatch(publish_notification);
+        publish_output.await.ok();
+        info!(
+            "Published release {} with profile {}",
+            release_id, deployment_configuration.id
+        );
+    }
+    deployment_status_accessor
+        .set_action(CurrentAction::Idle)
+        .await;
+    Ok(())
+}
+
+/// Writes the executed action entries received by the given receiver into the log until all senders are dropped.
+/// The returned handle resolves to `true` if no error or failed action was received.
+///
+/// # Arguments
+/// * `data_receiver` - The receiver for the executed action entries.
+fn log_executed_actions(
+    mut data_receiver: Receiver<Result<ExecutedActionEntry, Status>>,
+) -> JoinHandle<bool> {
+    tokio::spawn(async move {
+        let mut succeeded = true;
+        while let Some(entry) = data_receiver.recv().await {
+            match entry {
+                Ok(action_entry) => {
+                    if let Some(log_entry) = action_entry.action_log_entry {
+                        info!("[{}] {}", action_entry.release_id, log_entry.content);
+                    }
+                    if action_entry.action_status == i32::from(ActionStatus::CompletedFailure) {
+                        succeeded = false;
+                    }
+                }
+                Err(status) => {
+                    error!("{}", status.message());
+                    succeeded = false;
+                }
+            }
+        }
+        succeeded
+    })
+}
@@ -0,0 +1,242 @@
+/*
+ * This file is part of easydep, licensed under the MIT License (MIT).
+ *
+ * Copyright (c) 2024 easybill GmbH
+ *
+ * Permission is hereby granted, free of charge, to any person obtaining a copy
+ * of this software and associated documentation files (the "Software"), to deal
+ * in the Software without restriction, including without limitation the rights
+ * to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+ * copies of the Software, and to permit persons to whom the Software is
+ * furnished to do so, subject to the following conditions:
+ *
+ * The above copyright notice and this permission notice shall be included in all
+ * copies or substantial portions of the Software.
+ *
+ * THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+ * IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+ * FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+ * AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+ * LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+ * OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+ * SOFTWARE.
+ */
+
+use std::sync::Arc;
+use std::time::Duration;
+
+use log::{debug, error, info, warn};
+
+use crate::accessor::configuration_accessor::ConfigurationAccessor;
+use crate::accessor::deploy_action_accessor::{CurrentAction, DeploymentStatusAccessor};
+use crate::accessor::deployment_accessor::DeploymentAccessor;
+use crate::accessor::deployment_rate_limit_accessor::DeploymentRateLimitAccessor;
+use crate::accessor::github_accessor::GitHubAccessor;
+use crate::accessor::ref_deployment_accessor::RefDeploymentAccessor;
+use crate::config::{Configuration, DeploymentConfiguration};
+use crate::easydep::DeployAnnotation;
+use crate::executor::automatic_deployment_executor::prepare_and_publish_deployment;
+use crate::executor::deploy_executor::DeployExecutor;
+use crate::notification::notification_dispatcher::NotificationDispatcher;
+use crate::service::request_identity::RequestIdentity;
+
+/// The name of the identity that is recorded for deployments triggered by branch tracking.
+const BRANCH_TRACKING_IDENTITY: &str = "easydep branch tracking";
+
+/// Starts the tasks that poll the heads of the branches tracked by the configured deployment profiles and deploy
+/// every new commit on them. Each poll uses the current configuration of the tracking profile, changes to the
+/// tracked branches require a restart.
+///
+/// # Arguments
+/// * `configuration_accessor` - The accessor for the current server configuration.
+/// * `github_accessor` - The accessor for the GitHub api.
+/// * `deployment_status_accessor` - The accessor for the action that is currently being executed.
+/// * `notification_dispatcher` - The dispatcher to notify about the deployments of the tracked branches.
+/// * `deployment_rate_limit_accessor` - The accessor for the deployment rate limits of the profiles.
+pub async fn start_branch_tracking_tasks(
+    configuration_accessor: &ConfigurationAccessor,
+    github_accessor: &GitHubAccessor,
+    deployment_status_accessor: &DeploymentStatusAccessor,
+    notification_dispatcher: &NotificationDispatcher,
+    deployment_rate_limit_accessor: &DeploymentRateLimitAccessor,
+) {
+    let global_configuration = configuration_accessor.get_configuration().await;
+    for deployment_configuration in global_configuration.get_deployment_configurations() {
+        if let Some(tracked_branch) = &deployment_configuration.track_branch {
+            info!(
+                "Tracking branch {} for deployment profile {}",
+                tracked_branch, deployment_configuration.id
+            );
+            let configuration_accessor = configuration_accessor.clone();
+            let profile_id = deployment_configuration.id.clone();
+            let tracked_branch = tracked_branch.clone();
+            let github_accessor = github_accessor.clone();
+            let deployment_status_accessor = deployment_status_accessor.clone();
+            let notification_dispatcher = notification_dispatcher.clone();
+            let deployment_rate_limit_accessor = deployment_rate_limit_accessor.clone();
+            let poll_interval =
+                Duration::from_secs(deployment_configuration.track_branch_poll_interval_seconds);
+            tokio::spawn(async move {
+                let mut interval = tokio::time::interval(poll_interval);
+                loop {
+                    interval.tick().await;
+                    let global_configuration = configuration_accessor.get_configuration().await;
+                    let Some(deployment_configuration) =
+                        global_configuration.get_deployment_configuration(&profile_id)
+                    else {
+                        continue;
+                    };
+                    if let Err(err) = deploy_branch_head(
+                        &global_configuration,
+                        &deployment_configuration,
+                        &tracked_branch,
+                        &github_accessor,
+                        &deployment_status_accessor,
+                        &notification_dispatcher,
+                        &deployment_rate_limit_accessor,
+                    )
+                    .await
+                    {
+                        error!(
+                            "Unable to deploy head of branch {} with profile {}: {err:?}",
+                            tracked_branch, profile_id
+                        );
+                    }
+                }
+            });
+        }
+    }
+}
+
+/// Deploys and publishes the head commit of the given branch unless it was already deployed. The deployment is
+/// skipped if another action is currently being executed or the profile reached its deployment rate limit, it will
+/// be retried on the next poll.
+///
+/// # Arguments
+/// * `global_configuration` - The server configuration.
+/// * `deployment_configuration` - The deployment profile configuration that tracks the branch.
+/// * `tracked_branch` - The name of the tracked branch.
+/// * `github_accessor` - The accessor for the GitHub api.
+/// * `deployment_status_accessor` - The accessor for the action that is currently being executed.
+/// * `notification_dispatcher` - The dispatcher to notify about the deployment.
+/// * `deployment_rate_limit_accessor` - The accessor for the deployment rate limits of the profiles.
+async fn deploy_branch_head(
+    global_configuration: &Configuration,
+    deployment_configuration: &DeploymentConfiguration,
+    tracked_branch: &String,
+    github_accessor: &GitHubAccessor,
+    deployment_status_accessor: &DeploymentStatusAccessor,
+    notification_dispatcher: &NotificationDispatcher,
+    deployment_rate_limit_accessor: &DeploymentRateLimitAccessor,
+) -> anyhow::Result<()> {
+    if !matches!(
+        deployment_status_accessor.get_action().await,
+        CurrentAction::Idle
+    ) {
+        return Ok(());
+    }
+
+    // check if the head commit of the branch changed since the last deployment of the branch
+    let ref_deployment_accessor = RefDeploymentAccessor::new(global_configuration);
+    let head_commit_sha = github_accessor
+        .resolve_commit_sha(tracked_branch, deployment_configuration)
+        .await?;
+    let last_ref_deployment = ref_deployment_accessor
+        .get_latest_ref_deployment(deployment_configuration, tracked_branch)
+        .await?;
+    if let Some(last_ref_deployment) = &last_ref_deployment {
+        if last_ref_deployment.commit_sha == head_commit_sha {
+            return Ok(());
+        }
+
+        // skip the deployment if none of the changes since the last deployment touch the relevant paths
+        if !deployment_configuration.path_filters.is_empty() {
+            let changed_files = github_accessor
+                .get_changed_files(
+                    &last_ref_deployment.commit_sha,
+                    &head_commit_sha,
+                    deployment_configuration,
+                )
+                .await?;
+            if let Some(changed_files) = changed_files {
+                if !deployment_configuration.is_change_relevant(&changed_files) {
+                    debug!(
+                        "Skipping commit {} of branch {} as no relevant path changed",
+                        head_commit_sha, tracked_branch
+                    );
+                    return Ok(());
+                }
+            }
+        }
+    }
+
+    // skip the deployment if the profile reached its limit of deployments
+    if let Err(retry_after) = deployment_rate_limit_accessor
+        .check_deployment(deployment_configuration, false)
+        .await
+    {
+        warn!(
+            "Skipping commit {} of branch {} as profile {} reached its deployment rate limit, retrying in {} seconds",
+            head_commit_sha,
+            tracked_branch,
+            deployment_configuration.id,
+            retry_after.as_secs()
+        );
+        return Ok(());
+    }
+
+    // plan the deployment of the new head commit and construct the executor for it, the deployment is only recorded
+    // once it occupies the server
+    let deployment_accessor = DeploymentAccessor::new(global_configuration);
+    let newest_release_id = github_accessor
+        .get_newest_release_id(deployment_configuration)
+        .await?;
+    let ref_deployment = ref_deployment_accessor
+        .plan_ref_deployment(
+            deployment_configuration,
+            tracked_branch.clone(),
+            head_commit_sha,
+            newest_release_id,
+            |release_id| {
+                deployment_accessor.has_release_directory(deployment_configuration, &release_id)
+            },
+        )
+        .await?;
+    let release = ref_deployment.to_release(deployment_configuration)?;
+    let github_access_token = github_accessor
+        .read_github_app_installation_token(deployment_configuration)
+        .await?;
+    let annotation = DeployAnnotation {
+        message: format!(
+            "Continuous deployment of branch {} (commit {})",
+            tracked_branch, ref_deployment.commit_sha
+        ),
+        ticket_reference: None,
+    };
+    let deployment_executor = Arc::new(DeployExecutor::new(
+        release,
+        github_access_token,
+        global_configuration.clone(),
+        deployment_configuration.clone(),
+        Some(annotation),
+        RequestIdentity::internal(BRANCH_TRACKING_IDENTITY),
+        Some(tracked_branch.clone()),
+    ));
+
+    info!(
+        "Deploying commit {} of branch {} as release {} with profile {}",
+        ref_deployment.commit_sha,
+        tracked_branch,
+        ref_deployment.release_id,
+        deployment_configuration.id
+    );
+    prepare_and_publish_deployment(
+        deployment_executor,
+        deployment_configuration,
+        deployment_status_accessor,
+        notification_dispatcher,
+        deployment_rate_limit_accessor,
+        Some((&ref_deployment_accessor, &ref_deployment)),
+    )
+    .await
+}
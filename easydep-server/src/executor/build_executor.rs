@@ -0,0 +1,161 @@
+/*
+ * This file is part of easydep, licensed under the MIT License (MIT).
+ *
+ * Copyright (c) 2024 easybill GmbH
+ *
+ * Permission is hereby granted, free of charge, to any person obtaining a copy
+ * of this software and associated documentation files (the "Software"), to deal
+ * in the Software without restriction, including without limitation the rights
+ * to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+ * copies of the Software, and to permit persons to whom the Software is
+ * furnished to do so, subject to the following conditions:
+ *
+ * The above copyright notice and this permission notice shall be included in all
+ * copies or substantial portions of the Software.
+ *
+ * THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+ * IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+ * FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+ * AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+ * LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+ * OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+ * SOFTWARE.
+ */
+
+use std::path::{Path, PathBuf};
+
+use anyhow::bail;
+use log::{error, info};
+use octocrab::models::repos::Release;
+use symlink::{remove_symlink_dir, symlink_dir};
+use tokio::fs;
+use tokio::process::Command;
+use tokio::sync::mpsc::Sender;
+use tonic::Status;
+
+use crate::accessor::deployment_accessor::DeploymentAccessor;
+use crate::config::{BuildCacheStrategy, DeploymentConfiguration};
+use crate::easydep::ExecutedActionEntry;
+use crate::executor::command_runner::ExecutionEnvironment;
+use crate::executor::script_executor::{execute_scripts, ScriptType};
+
+/// Executes the build phase of a deployment, if configured. The cached directories are provided to the deployment
+/// before the build scripts are executed and, when copying the caches, stored back after a successful build.
+///
+/// # Arguments
+/// * `release` - The release that is currently being deployed.
+/// * `deployment_directory` - The directory in which the deployment is stored.
+/// * `deployment_accessor` - The accessor for deployments stored on the disk.
+/// * `deployment_configuration` - The deployment profile configuration for the current deployment.
+/// * `execution_environment` - The environment to resolve the secrets and run the external commands with.
+/// * `output_sender` - The sender to which log line output should be sent.
+///
+/// # Returns
+/// * `bool` - `true` if no build phase is configured or the build completed successfully, `false` otherwise.
+pub async fn execute_build(
+    release: &Release,
+    deployment_directory: &PathBuf,
+    deployment_accessor: &DeploymentAccessor,
+    deployment_configuration: &DeploymentConfiguration,
+    execution_environment: &ExecutionEnvironment,
+    output_sender: &Sender<Result<ExecutedActionEntry, Status>>,
+) -> bool {
+    let build_configuration = match &deployment_configuration.build {
+        Some(build_configuration) => build_configuration,
+        None => return true,
+    };
+
+    // provide the cached directories to the deployment, a missing cache only slows down the build
+    let cache_directory = deployment_accessor.get_build_cache_directory(deployment_configuration);
+    for cache_path in &build_configuration.cache_paths {
+        let cached_path = cache_directory.join(cache_path);
+        let deployed_path = deployment_directory.join(cache_path);
+        let restore_result = match build_configuration.cache_strategy {
+            BuildCacheStrategy::Copy => copy_directory(&cached_path, &deployed_path).await,
+            BuildCacheStrategy::Symlink => link_directory(&cached_path, &deployed_path).await,
+        };
+        if let Err(err) = restore_result {
+            error!("Unable to provide build cache {}: {err:?}", cache_path);
+        }
+    }
+
+    // execute the build scripts
+    let build_succeeded = execute_scripts(
+        release,
+        &ScriptType::Build,
+        deployment_directory,
+        deployment_configuration,
+        execution_environment,
+        output_sender,
+    )
+    .await
+    .is_success();
+
+    // store the build outputs as the new cache, only if the build succeeded to not persist broken caches
+    if build_succeeded && build_configuration.cache_strategy == BuildCacheStrategy::Copy {
+        for cache_path in &build_configuration.cache_paths {
+            let cached_path = cache_directory.join(cache_path);
+            let deployed_path = deployment_directory.join(cache_path);
+            if let Err(err) = copy_directory(&deployed_path, &cached_path).await {
+                error!("Unable to store build cache {}: {err:?}", cache_path);
+            }
+        }
+    }
+
+    build_succeeded
+}
+
+/// Replaces the target directory with a copy of the source directory, preserving all file attributes. Nothing is
+/// done if the source directory does not exist.
+///
+/// # Arguments
+/// * `source` - The directory to copy.
+/// * `target` - The path to copy the directory to.
+async fn copy_directory(source: &Path, target: &Path) -> anyhow::Result<()> {
+    if !fs::try_exists(source).await? {
+        return Ok(());
+    }
+
+    if fs::try_exists(target).await? {
+        fs::remove_dir_all(target).await?;
+    }
+    if let Some(parent) = target.parent() {
+        fs::create_dir_all(parent).await?;
+    }
+
+    info!("Copying build cache {:?} -> {:?}", source, target);
+    let output = Command::new("cp")
+        .arg("-a")
+        .arg(source)
+        .arg(target)
+        .output()
+        .await?;
+    if !output.status.success() {
+        let stderr_output = String::from_utf8_lossy(output.stderr.as_slice());
+        bail!("cp exited with {}: {}", output.status, stderr_output.trim())
+    }
+    Ok(())
+}
+
+/// Replaces the target path with a symlink to the given cache directory, creating the cache directory if needed.
+///
+/// # Arguments
+/// * `cache_directory` - The cache directory to link to.
+/// * `target` - The path where the symlink should be created.
+async fn link_directory(cache_directory: &Path, target: &Path) -> anyhow::Result<()> {
+    fs::create_dir_all(cache_directory).await?;
+    if let Some(parent) = target.parent() {
+        fs::create_dir_all(parent).await?;
+    }
+
+    if fs::symlink_metadata(target)
+        .await
+        .is_ok_and(|metadata| metadata.is_dir())
+    {
+        fs::remove_dir_all(target).await?;
+    } else {
+        remove_symlink_dir(target).ok();
+    }
+    symlink_dir(cache_directory, target)?;
+    Ok(())
+}
@@ -0,0 +1,297 @@
+/*
+ * This file is part of easydep, licensed under the MIT License (MIT).
+ *
+ * Copyright (c) 2024 easybill GmbH
+ *
+ * Permission is hereby granted, free of charge, to any person obtaining a copy
+ * of this software and associated documentation files (the "Software"), to deal
+ * in the Software without restriction, including without limitation the rights
+ * to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+ * copies of the Software, and to permit persons to whom the Software is
+ * furnished to do so, subject to the following conditions:
+ *
+ * The above copyright notice and this permission notice shall be included in all
+ * copies or substantial portions of the Software.
+ *
+ * THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+ * IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+ * FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+ * AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+ * LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+ * OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+ * SOFTWARE.
+ */
+
+use std::fmt::Debug;
+use std::io;
+use std::path::PathBuf;
+use std::process::Output;
+use std::sync::Arc;
+use std::time::Duration;
+
+use tokio::process::{Child, Command};
+
+use crate::accessor::secret_accessor::SecretAccessor;
+use crate::config::{Configuration, DeploymentConfiguration, GitConfiguration};
+use crate::executor::action_cancellation::ActionCancellation;
+
+/// Runs the external commands (git, bash, container runtimes) that are executed during a deployment. Abstracting the
+/// execution allows to replace the spawned processes, for example to test the deployment flow without git or bash.
+#[tonic::async_trait]
+pub(crate) trait CommandRunner: Debug + Send + Sync {
+    /// Spawns the given command, the stdio configuration of the command is kept. On unix the spawned process must be the
+    /// leader of a new process group, which allows to terminate the process and all processes started by it.
+    ///
+    /// # Arguments
+    /// * `command` - The command to spawn.
+    fn spawn(&self, command: &mut Command) -> io::Result<Child>;
+
+    /// Executes the given command to completion, collecting its output.
+    ///
+    /// # Arguments
+    /// * `command` - The command to execute.
+    async fn output(&self, command: &mut Command) -> io::Result<Output>;
+}
+
+/// The command runner that spawns the given commands as processes.
+#[derive(Clone, Debug)]
+pub(crate) struct ProcessCommandRunner;
+
+#[tonic::async_trait]
+impl CommandRunner for ProcessCommandRunner {
+    fn spawn(&self, command: &mut Command) -> io::Result<Child> {
+        // process groups are only available on unix, elsewhere only the spawned process itself can be terminated
+        #[cfg(unix)]
+        command.process_group(0);
+        command.spawn()
+    }
+
+    async fn output(&self, command: &mut Command) -> io::Result<Output> {
+        command.output().await
+    }
+}
+
+/// The environment in which the lifecycle scripts and other external commands of a deployment are executed.
+#[derive(Clone, Debug)]
+pub(crate) struct ExecutionEnvironment {
+    /// The accessor to resolve the secrets provided to the scripts.
+    pub secret_accessor: SecretAccessor,
+    /// The runner of the external commands.
+    pub command_runner: Arc<dyn CommandRunner>,
+    /// The signal to terminate the external commands when the action is cancelled.
+    pub cancellation: ActionCancellation,
+    /// The git settings of the server, which can be overridden by each deployment configuration.
+    pub git_configuration: GitConfiguration,
+    /// The time after which the lifecycle scripts are terminated, if they should time out.
+    pub script_timeout: Option<Duration>,
+    /// The time that timed out scripts get to exit before they are killed.
+    pub script_kill_grace_period: Duration,
+    /// The directory of the release that was published before the deployed release, if known.
+    pub previous_release_directory: Option<PathBuf>,
+}
+
+impl ExecutionEnvironment {
+    /// Constructs a new execution environment that spawns the external commands as processes.
+    ///
+    /// # Arguments
+    /// * `config` - The server configuration, used to construct the secret accessor and to get the git and script settings.
+    pub fn new(config: &Configuration) -> Self {
+        Self {
+            secret_accessor: SecretAccessor::new(config),
+            command_runner: Arc::new(ProcessCommandRunner),
+            cancellation: ActionCancellation::new(),
+            git_configuration: config.git.clone().unwrap_or_default(),
+            script_timeout: config.script_timeout_seconds.map(Duration::from_secs),
+            script_kill_grace_period: Duration::from_secs(config.script_kill_grace_period_seconds),
+            previous_release_directory: None,
+        }
+    }
+
+    /// Get the git settings that apply to the git commands executed for the given deployment configuration.
+    ///
+    /// # Arguments
+    /// * `deployment_configuration` - The deployment configuration that might override the git settings.
+    pub fn resolve_git_configuration(
+        &self,
+        deployment_configuration: &DeploymentConfiguration,
+    ) -> GitConfiguration {
+        self.git_configuration
+            .merged_with(deployment_configuration.git.as_ref())
+    }
+
+    /// Returns a copy of this environment in which the script timeout of the given deployment configuration applies,
+    /// if it overrides the script timeout of the server.
+    ///
+    /// # Arguments
+    /// * `deployment_configuration` - The deployment configuration that might override the script timeout.
+    pub fn with_script_timeout_of(
+        &self,
+        deployment_configuration: &DeploymentConfiguration,
+    ) -> Self {
+        Self {
+            script_timeout: deployment_configuration
+                .script_timeout_seconds
+                .map(Duration::from_secs)
+                .or(self.script_timeout),
+            ..self.clone()
+        }
+    }
+
+    /// Returns a copy of this environment in which the given directory is provided to the scripts as the directory of
+    /// the previously published release.
+    ///
+    /// # Arguments
+    /// * `previous_release_directory` - The directory of the previously published release, if any.
+    pub fn with_previous_release_directory(
+        &self,
+        previous_release_directory: Option<PathBuf>,
+    ) -> Self {
+        Self {
+            previous_release_directory,
+            ..self.clone()
+        }
+    }
+
+    /// Returns a copy of this environment with a new cancellation signal, for example to execute commands that
+    /// should still run after the action was cancelled.
+    pub fn with_new_cancellation(&self) -> Self {
+        Self {
+            cancellation: ActionCancellation::new(),
+            ..self.clone()
+        }
+    }
+    /// Returns a copy of this environment in which the external commands are run by the given runner, for example to
+    /// record the commands in tests.
+    ///
+    /// # Arguments
+    /// * `command_runner` - The runner of the external commands.
+    #[cfg(test)]
+    pub fn with_command_runner(&self, command_runner: Arc<dyn CommandRunner>) -> Self {
+        Self {
+            command_runner,
+            ..self.clone()
+        }
+    }
+}
+
+/// An external command that was passed to the [`RecordingCommandRunner`].
+#[cfg(test)]
+#[derive(Clone, Debug)]
+pub(crate) struct RecordedCommand {
+    /// The program that should have been executed.
+    pub program: String,
+    /// The arguments passed to the program.
+    pub args: Vec<String>,
+    /// The working directory of the command, if set.
+    pub current_dir: Option<PathBuf>,
+    /// The environment variables that were explicitly set for the command.
+    pub envs: std::collections::HashMap<String, String>,
+}
+
+/// The response of the [`RecordingCommandRunner`] to a recorded command.
+#[cfg(test)]
+#[derive(Clone, Debug, Default)]
+pub(crate) struct CommandResponse {
+    /// The exit code with which the command completes.
+    pub exit_code: i32,
+    /// The output that the command writes to stdout.
+    pub stdout: String,
+}
+
+/// The function that decides how the [`RecordingCommandRunner`] responds to a command. It can also simulate the side
+/// effects of the command, for example create the directory that git would have cloned into.
+#[cfg(test)]
+type CommandResponder = Box<dyn Fn(&RecordedCommand) -> CommandResponse + Send + Sync>;
+
+/// The command runner used in tests, which records the given commands instead of executing them. Each command is
+/// replaced by a shell that only prints the output and exits with the code that the responder returns for it, which
+/// makes the deployment flow deterministic and independent of git, bash and the network.
+#[cfg(test)]
+pub(crate) struct RecordingCommandRunner {
+    recorded_commands: std::sync::Mutex<Vec<RecordedCommand>>,
+    responder: CommandResponder,
+}
+
+#[cfg(test)]
+impl RecordingCommandRunner {
+    /// Constructs a new recording command runner that responds to the commands using the given responder.
+    ///
+    /// # Arguments
+    /// * `responder` - The function that decides how to respond to each recorded command.
+    pub fn new(
+        responder: impl Fn(&RecordedCommand) -> CommandResponse + Send + Sync + 'static,
+    ) -> Self {
+        Self {
+            recorded_commands: std::sync::Mutex::new(Vec::new()),
+            responder: Box::new(responder),
+        }
+    }
+
+    /// Get the commands that were recorded so far, in the order in which they were passed to this runner.
+    pub fn get_recorded_commands(&self) -> Vec<RecordedCommand> {
+        self.recorded_commands.lock().unwrap().clone()
+    }
+
+    /// Records the given command and builds the shell command that simulates it according to the responder.
+    ///
+    /// # Arguments
+    /// * `command` - The command to record.
+    fn record_and_respond(&self, command: &Command) -> Command {
+        let std_command = command.as_std();
+        let recorded_command = RecordedCommand {
+            program: std_command.get_program().to_string_lossy().to_string(),
+            args: std_command
+                .get_args()
+                .map(|arg| arg.to_string_lossy().to_string())
+                .collect(),
+            current_dir: std_command.get_current_dir().map(PathBuf::from),
+            envs: std_command
+                .get_envs()
+                .filter_map(|(key, value)| {
+                    Some((
+                        key.to_string_lossy().to_string(),
+                        value?.to_string_lossy().to_string(),
+                    ))
+                })
+                .collect(),
+        };
+        let response = (self.responder)(&recorded_command);
+        self.recorded_commands
+            .lock()
+            .unwrap()
+            .push(recorded_command);
+
+        let mut response_command = Command::new("sh");
+        response_command
+            .arg("-c")
+            .arg("printf '%s' \"$1\"; exit \"$2\"")
+            .arg("recorded-command")
+            .arg(response.stdout)
+            .arg(response.exit_code.to_string())
+            .stdin(std::process::Stdio::null())
+            .stdout(std::process::Stdio::piped())
+            .stderr(std::process::Stdio::piped());
+        response_command
+    }
+}
+
+#[cfg(test)]
+impl Debug for RecordingCommandRunner {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("RecordingCommandRunner")
+            .field("recorded_commands", &self.recorded_commands)
+            .finish_non_exhaustive()
+    }
+}
+
+#[cfg(test)]
+#[tonic::async_trait]
+impl CommandRunner for RecordingCommandRunner {
+    fn spawn(&self, command: &mut Command) -> io::Result<Child> {
+        self.record_and_respond(command).spawn()
+    }
+
+    async fn output(&self, command: &mut Command) -> io::Result<Output> {
+        self.record_and_respond(command).output().await
+    }
+}
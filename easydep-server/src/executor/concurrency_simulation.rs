@@ -0,0 +1,632 @@
+/*
+ * This file is part of easydep, licensed under the MIT License (MIT).
+ *
+ * Copyright (c) 2024 easybill GmbH
+ *
+ * Permission is hereby granted, free of charge, to any person obtaining a copy
+ * of this software and associated documentation files (the "Software"), to deal
+ * in the Software without restriction, including without limitation the rights
+ * to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+ * copies of the Software, and to permit persons to whom the Software is
+ * furnished to do so, subject to the following conditions:
+ *
+ * The above copyright notice and this permission notice shall be included in all
+ * copies or substantial portions of the Software.
+ *
+ * THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+ * IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+ * FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+ * AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+ * LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+ * OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+ * SOFTWARE.
+ */
+
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, SystemTime};
+
+use secrecy::SecretString;
+use tokio::task::JoinSet;
+
+use crate::accessor::deploy_action_accessor::{
+    CurrentAction, DeploymentStatusAccessor, QueuedActionDetails,
+};
+use crate::accessor::deploy_status_accessor::DeployExecutionState;
+use crate::accessor::ref_deployment_accessor::RefDeployment;
+use crate::config::{Configuration, DeploymentConfiguration};
+use crate::executor::deploy_executor::DeployExecutor;
+use crate::service::request_identity::RequestIdentity;
+
+/// The release id that is assigned to the first simulated deployment.
+const FIRST_SIMULATED_RELEASE_ID: u64 = 1;
+/// The minimum time after which a simulated prepared deployment expires if it was neither published nor deleted.
+const MIN_PREPARED_EXPIRY: Duration = Duration::from_millis(10);
+
+/// The requests that simulated clients send, each using the same compare-and-set transitions as the matching request
+/// of the deployment service.
+#[derive(Clone, Copy, Debug)]
+enum SimulatedRequest {
+    Start,
+    Publish,
+    Delete,
+    Rollback,
+}
+
+impl SimulatedRequest {
+    /// Get the name of this request as reported in the simulation result.
+    fn name(&self) -> &'static str {
+        match self {
+            SimulatedRequest::Start => "start",
+            SimulatedRequest::Publish => "publish",
+            SimulatedRequest::Delete => "delete",
+            SimulatedRequest::Rollback => "rollback",
+        }
+    }
+}
+
+/// The options of a concurrency simulation.
+#[derive(Clone, Debug)]
+pub(crate) struct SimulationOptions {
+    /// The amount of clients that send requests concurrently.
+    pub clients: u32,
+    /// The amount of requests that each client sends.
+    pub requests_per_client: u32,
+    /// The seed for the random choice of requests and delays.
+    pub seed: u64,
+    /// The maximum time that each simulated step of an action takes.
+    pub max_step_delay: Duration,
+    /// If deployments are started through the action queue of the profile.
+    pub use_action_queue: bool,
+}
+
+/// The result of a concurrency simulation.
+#[derive(Clone, Debug, Default)]
+pub(crate) struct SimulationReport {
+    /// The amount of requests that were accepted, by request name.
+    pub accepted_requests: HashMap<String, u64>,
+    /// The amount of requests that were rejected, by request name.
+    pub rejected_requests: HashMap<String, u64>,
+    /// The descriptions of the invariants that were violated.
+    pub violations: Vec<String>,
+}
+
+/// Simulates clients that concurrently start, publish, delete and roll back deployments. The simulation uses its own
+/// deployment status accessor and real deployment executors, but the executors only switch their states after a random
+/// delay instead of executing scripts. Prepared deployments expire like in the deployment service, which also ensures
+/// that clients waiting in the action queue can continue after all other clients finished.
+pub(crate) struct ConcurrencySimulation {
+    global_configuration: Configuration,
+    deployment_configuration: DeploymentConfiguration,
+    options: SimulationOptions,
+    deployment_status_accessor: DeploymentStatusAccessor,
+    /// The amount of actions that currently think they own the current action, must never exceed one.
+    running_actions: AtomicU64,
+    /// The release id that is assigned to the next simulated deployment.
+    next_release_id: AtomicU64,
+    /// The tasks that expire the prepared deployments.
+    expiry_tasks: Mutex<JoinSet<()>>,
+    report: Mutex<SimulationReport>,
+}
+
+impl ConcurrencySimulation {
+    /// Constructs a new concurrency simulation.
+    ///
+    /// # Arguments
+    /// * `global_configuration` - The server configuration.
+    /// * `deployment_configuration` - The profile configuration that is used for the simulated deployments.
+    /// * `options` - The options of the simulation.
+    pub fn new(
+        global_configuration: Configuration,
+        deployment_configuration: DeploymentConfiguration,
+        options: SimulationOptions,
+    ) -> Self {
+        Self {
+            global_configuration,
+            deployment_configuration,
+            options,
+            deployment_status_accessor: DeploymentStatusAccessor::new(),
+            running_actions: AtomicU64::new(0),
+            next_release_id: AtomicU64::new(FIRST_SIMULATED_RELEASE_ID),
+            expiry_tasks: Mutex::new(JoinSet::new()),
+            report: Mutex::new(SimulationReport::default()),
+        }
+    }
+
+    /// Runs the simulation until all clients sent their requests and all actions completed.
+    ///
+    /// # Returns
+    /// * `SimulationReport` - The requests that were accepted and rejected and the invariants that were violated.
+    pub async fn run(self: Arc<Self>) -> anyhow::Result<SimulationReport> {
+        let mut clients = JoinSet::new();
+        for client_index in 0..self.options.clients {
+            let simulation = self.clone();
+            clients.spawn(async move { simulation.run_client(client_index).await });
+        }
+        while let Some(client_result) = clients.join_next().await {
+            client_result??;
+        }
+
+        // the expiry tasks can be taken out after all clients finished, no new ones are spawned afterwards
+        let mut expiry_tasks = std::mem::take(
+            &mut *self
+                .expiry_tasks
+                .lock()
+                .map_err(|_| anyhow::anyhow!("unable to access the expiry tasks"))?,
+        );
+        while let Some(expiry_result) = expiry_tasks.join_next().await {
+            expiry_result?;
+        }
+
+        if !matches!(
+            self.deployment_status_accessor.get_action().await,
+            CurrentAction::Idle
+        ) {
+            self.record_violation(
+                "an action is still running after all clients finished".to_string(),
+            );
+        }
+        let running_actions = self.running_actions.load(Ordering::SeqCst);
+        if running_actions != 0 {
+            self.record_violation(format!(
+                "{running_actions} action(s) still own the current action after all clients finished"
+            ));
+        }
+
+        let report = self
+            .report
+            .lock()
+            .map_err(|_| anyhow::anyhow!("unable to access the simulation report"))?
+            .clone();
+        Ok(report)
+    }
+
+    /// Sends the requests of one simulated client, one after another.
+    ///
+    /// # Arguments
+    /// * `client_index` - The index of the client, used to derive the random number sequence of the client.
+    async fn run_client(self: Arc<Self>, client_index: u32) -> anyhow::Result<()> {
+        let mut random = SimulationRandom::new(self.options.seed, client_index);
+        for _ in 0..self.options.requests_per_client {
+            let request = match random.next_below(4) {
+                0 => SimulatedRequest::Start,
+                1 => SimulatedRequest::Publish,
+                2 => SimulatedRequest::Delete,
+                _ => SimulatedRequest::Rollback,
+            };
+            let accepted = match request {
+                SimulatedRequest::Start => self.clone().simulate_start(&mut random).await?,
+                SimulatedRequest::Publish => {
+                    self.simulate_finish(
+                        &mut random,
+                        request,
+                        DeployExecutionState::Publishing,
+                        DeployExecutionState::Published,
+                    )
+                    .await
+                }
+                SimulatedRequest::Delete => {
+                    self.simulate_finish(
+                        &mut random,
+                        request,
+                        DeployExecutionState::Deleting,
+                        DeployExecutionState::Deleted,
+                    )
+                    .await
+                }
+                SimulatedRequest::Rollback => self.simulate_rollback(&mut random).await,
+            };
+            self.record_request(request.name(), accepted);
+            self.simulate_step(&mut random).await;
+        }
+        Ok(())
+    }
+
+    /// Simulates a request to start a deployment, either rejecting it if another action is running or waiting in the
+    /// action queue of the profile, depending on the options. The started deployment is prepared and then stays the
+    /// current action until it is published, deleted or expired.
+    ///
+    /// # Arguments
+    /// * `random` - The random number source of the client.
+    ///
+    /// # Returns
+    /// * `bool` - `true` if the deployment was started, `false` if it was rejected.
+    async fn simulate_start(
+        self: Arc<Self>,
+        random: &mut SimulationRandom,
+    ) -> anyhow::Result<bool> {
+        let release_id = self.next_release_id.fetch_add(1, Ordering::SeqCst);
+        let simulated_deployment = RefDeployment {
+            release_id,
+            git_ref: format!("simulated-{release_id}"),
+            commit_sha: String::new(),
+            created_at: 0,
+        };
+        let deployment_executor = Arc::new(DeployExecutor::new(
+            simulated_deployment.to_release(&self.deployment_configuration)?,
+            SecretString::new(String::new()),
+            self.global_configuration.clone(),
+            self.deployment_configuration.clone(),
+            None,
+            RequestIdentity::internal("concurrency simulation"),
+            None,
+        ));
+        let deployment_action = CurrentAction::Executing(deployment_executor.clone());
+
+        if self.options.use_action_queue {
+            let max_queue_depth = self.options.clients as usize;
+            let queued_action_details = QueuedActionDetails {
+                release_id,
+                release_tag: deployment_executor.get_release().tag_name.clone(),
+                git_ref: deployment_executor.get_git_ref().cloned(),
+                annotation: None,
+                triggered_by: deployment_executor.get_triggered_by().clone(),
+                queued_at: SystemTime::now(),
+            };
+            let Some(mut queued_action) = self.deployment_status_accessor.enqueue_action(
+                &self.deployment_configuration.id,
+                max_queue_depth,
+                queued_action_details,
+            ) else {
+                return Ok(false);
+            };
+            while !queued_action
+                .try_set_action(deployment_action.clone())
+                .await
+            {
+                queued_action.changed().await;
+            }
+        } else if !self
+            .deployment_status_accessor
+            .compare_and_set_action_by_variant(&CurrentAction::Idle, deployment_action)
+            .await
+        {
+            return Ok(false);
+        }
+        self.acquire_action("start");
+
+        self.simulate_step(random).await;
+        if !deployment_executor
+            .get_status_accessor()
+            .compare_and_set_state(
+                &DeployExecutionState::Preparing,
+                DeployExecutionState::Prepared,
+            )
+            .await
+        {
+            self.record_violation(format!(
+                "deployment {release_id} left the preparing state while being prepared"
+            ));
+        }
+
+        let expires_after = (self.options.max_step_delay * 10).max(MIN_PREPARED_EXPIRY);
+        let simulation = self.clone();
+        let mut expiry_tasks = self
+            .expiry_tasks
+            .lock()
+            .map_err(|_| anyhow::anyhow!("unable to access the expiry tasks"))?;
+        expiry_tasks.spawn(async move {
+            tokio::time::sleep(expires_after).await;
+            simulation.simulate_expiry(deployment_executor).await;
+        });
+        Ok(true)
+    }
+
+    /// Simulates a request to publish or delete the current deployment, which is only accepted if the deployment is
+    /// prepared.
+    ///
+    /// # Arguments
+    /// * `random` - The random number source of the client.
+    /// * `request` - The request that is simulated.
+    /// * `running_state` - The state of the deployment while the request is being executed.
+    /// * `final_state` - The state of the deployment after the request was executed.
+    ///
+    /// # Returns
+    /// * `bool` - `true` if the request was accepted, `false` if it was rejected.
+    async fn simulate_finish(
+        &self,
+        random: &mut SimulationRandom,
+        request: SimulatedRequest,
+        running_state: DeployExecutionState,
+        final_state: DeployExecutionState,
+    ) -> bool {
+        let deployment_executor = match self.deployment_status_accessor.get_action().await {
+            CurrentAction::Executing(executor) => executor,
+            _ => return false,
+        };
+        self.simulate_step(random).await;
+        if !deployment_executor
+            .get_status_accessor()
+            .compare_and_set_state(&DeployExecutionState::Prepared, running_state.clone())
+            .await
+        {
+            return false;
+        }
+
+        self.finish_deployment(
+            random,
+            request.name(),
+            &deployment_executor,
+            running_state,
+            final_state,
+        )
+        .await;
+        true
+    }
+
+    /// Simulates the expiry of a prepared deployment, which deletes the deployment unless it was published or deleted
+    /// in the meantime.
+    ///
+    /// # Arguments
+    /// * `deployment_executor` - The executor of the deployment that expired.
+    async fn simulate_expiry(&self, deployment_executor: Arc<DeployExecutor>) {
+        let accepted = deployment_executor
+            .get_status_accessor()
+            .compare_and_set_state(
+                &DeployExecutionState::Prepared,
+                DeployExecutionState::Deleting,
+            )
+            .await;
+        if accepted {
+            let mut random = SimulationRandom::new(
+                self.options.seed,
+                deployment_executor.get_release_id() as u32,
+            );
+            self.finish_deployment(
+                &mut random,
+                "expire",
+                &deployment_executor,
+                DeployExecutionState::Deleting,
+                DeployExecutionState::Deleted,
+            )
+            .await;
+        }
+        self.record_request("expire", accepted);
+    }
+
+    /// Executes the publishing or deletion of the given deployment after its state was switched to the given running
+    /// state, then switches the deployment service back to idle.
+    ///
+    /// # Arguments
+    /// * `random` - The random number source of the client.
+    /// * `request_name` - The name of the request that is executed.
+    /// * `deployment_executor` - The executor of the deployment.
+    /// * `running_state` - The state of the deployment while the request is being executed.
+    /// * `final_state` - The state of the deployment after the request was executed.
+    async fn finish_deployment(
+        &self,
+        random: &mut SimulationRandom,
+        request_name: &str,
+        deployment_executor: &DeployExecutor,
+        running_state: DeployExecutionState,
+        final_state: DeployExecutionState,
+    ) {
+        // the deployment can only be prepared while it is the current action, switching its state must not be
+        // possible once another action took over
+        let release_id = deployment_executor.get_release_id();
+        match self.deployment_status_accessor.get_action().await {
+            CurrentAction::Executing(executor) if executor.get_release_id() == release_id => {}
+            _ => self.record_violation(format!(
+                "{request_name} of deployment {release_id} was accepted while it is not the current action"
+            )),
+        }
+
+        self.simulate_step(random).await;
+        if !deployment_executor
+            .get_status_accessor()
+            .compare_and_set_state(&running_state, final_state)
+            .await
+        {
+            self.record_violation(format!(
+                "deployment {release_id} left the {running_state:?} state during {request_name}"
+            ));
+        }
+        self.release_action(request_name).await;
+    }
+
+    /// Simulates a request to roll back to a previous release, which is only accepted if no other action is running.
+    ///
+    /// # Arguments
+    /// * `random` - The random number source of the client.
+    ///
+    /// # Returns
+    /// * `bool` - `true` if the rollback was executed, `false` if it was rejected.
+    async fn simulate_rollback(&self, random: &mut SimulationRandom) -> bool {
+        let simulated_deployment = RefDeployment {
+            release_id: 0,
+            git_ref: "simulated-rollback".to_string(),
+            commit_sha: String::new(),
+            created_at: 0,
+        };
+        let Ok(release) = simulated_deployment.to_release(&self.deployment_configuration) else {
+            return false;
+        };
+        let rollback_action = CurrentAction::RollingBack(
+            Box::new(release),
+            Box::new(self.deployment_configuration.clone()),
+            RequestIdentity::internal("concurrency simulation"),
+        );
+        if !self
+            .deployment_status_accessor
+            .compare_and_set_action_by_variant(&CurrentAction::Idle, rollback_action)
+            .await
+        {
+            return false;
+        }
+
+        self.acquire_action("rollback");
+        self.simulate_step(random).await;
+        self.release_action("rollback").await;
+        true
+    }
+
+    /// Records that the calling action took over the current action, recording a violation if another action did
+    /// not release it yet.
+    ///
+    /// # Arguments
+    /// * `request_name` - The name of the request that took over the current action.
+    fn acquire_action(&self, request_name: &str) {
+        let running_actions = self.running_actions.fetch_add(1, Ordering::SeqCst);
+        if running_actions != 0 {
+            self.record_violation(format!(
+                "{request_name} took over the current action while {running_actions} other action(s) were running"
+            ));
+        }
+    }
+
+    /// Releases the current action of the calling action and switches the deployment service back to idle.
+    ///
+    /// # Arguments
+    /// * `request_name` - The name of the request that releases the current action.
+    async fn release_action(&self, request_name: &str) {
+        // released before switching to idle, another action can take over directly after the switch
+        let running_actions = self.running_actions.fetch_sub(1, Ordering::SeqCst);
+        if running_actions != 1 {
+            self.record_violation(format!(
+                "{request_name} released the current action while {running_actions} action(s) were running"
+            ));
+        }
+        self.deployment_status_accessor
+            .set_action(CurrentAction::Idle)
+            .await;
+    }
+
+    /// Simulates one step of an action, which yields to the other clients and waits for a random time up to the
+    /// maximum step delay.
+    ///
+    /// # Arguments
+    /// * `random` - The random number source of the client.
+    async fn simulate_step(&self, random: &mut SimulationRandom) {
+        let max_delay_millis = self.options.max_step_delay.as_millis() as u64;
+        if max_delay_millis == 0 {
+            tokio::task::yield_now().await;
+        } else {
+            let delay_millis = random.next_below(max_delay_millis + 1);
+            tokio::time::sleep(Duration::from_millis(delay_millis)).await;
+        }
+    }
+
+    /// Counts the given request as accepted or rejected.
+    ///
+    /// # Arguments
+    /// * `request_name` - The name of the request.
+    /// * `accepted` - If the request was accepted.
+    fn record_request(&self, request_name: &str, accepted: bool) {
+        if let Ok(mut report) = self.report.lock() {
+            let counters = if accepted {
+                &mut report.accepted_requests
+            } else {
+                &mut report.rejected_requests
+            };
+            *counters.entry(request_name.to_string()).or_default() += 1;
+        }
+    }
+
+    /// Records that an invariant was violated during the simulation.
+    ///
+    /// # Arguments
+    /// * `violation` - The description of the violated invariant.
+    fn record_violation(&self, violation: String) {
+        if let Ok(mut report) = self.report.lock() {
+            report.violations.push(violation);
+        }
+    }
+}
+
+/// A small xorshift random number generator, so that a simulation can be repeated with the same seed.
+struct SimulationRandom {
+    state: u64,
+}
+
+impl SimulationRandom {
+    /// Constructs a new random number generator for the given seed and client.
+    ///
+    /// # Arguments
+    /// * `seed` - The seed of the simulation.
+    /// * `client_index` - The index of the client, so that each client gets its own sequence.
+    fn new(seed: u64, client_index: u32) -> Self {
+        // the state of a xorshift generator must never be zero
+        let state = (seed ^ (u64::from(client_index) + 1).wrapping_mul(0x9E37_79B9_7F4A_7C15)) | 1;
+        Self { state }
+    }
+
+    /// Get the next random number that is less than the given bound.
+    ///
+    /// # Arguments
+    /// * `bound` - The exclusive upper bound of the random number, must not be zero.
+    fn next_below(&mut self, bound: u64) -> u64 {
+        self.state ^= self.state << 13;
+        self.state ^= self.state >> 7;
+        self.state ^= self.state << 17;
+        self.state % bound
+    }
+}
+
+#[cfg(all(test, feature = "concurrency-simulation"))]
+mod tests {
+    use std::sync::Arc;
+    use std::time::Duration;
+
+    use crate::config::Configuration;
+
+    use super::{ConcurrencySimulation, SimulationOptions};
+
+    /// Runs a simulation with the given options using a profile without any scripts, asserting that no invariant
+    /// was violated.
+    async fn run_simulation_without_violations(use_action_queue: bool) {
+        let configuration = Configuration::parse(
+            r#"
+            bind_host = "127.0.0.1:6666"
+            base_directory = "/tmp/easydep-test-simulation"
+            github_app_id = 1
+            github_app_pem_key_path = "/dev/null"
+            retained_releases = 5
+
+            [[deployment_configs]]
+            id = "test"
+            target = "test"
+            source_repo_owner = "easybill"
+            source_repo_name = "easydep"
+            allowed_repo_branches = []
+            denied_repo_branches = []
+            extended_script_configurations = []
+            symlinks = []
+            "#,
+        )
+        .unwrap();
+        let deployment_configuration = configuration
+            .get_deployment_configuration(&"test".to_string())
+            .unwrap();
+        let options = SimulationOptions {
+            clients: 8,
+            requests_per_client: 25,
+            seed: 42,
+            max_step_delay: Duration::from_millis(2),
+            use_action_queue,
+        };
+        let simulation = Arc::new(ConcurrencySimulation::new(
+            configuration,
+            deployment_configuration,
+            options,
+        ));
+
+        let report = simulation.run().await.unwrap();
+
+        assert!(report.violations.is_empty(), "{:?}", report.violations);
+        assert!(report
+            .accepted_requests
+            .get("start")
+            .is_some_and(|accepted| *accepted > 0));
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+    async fn simulation_without_action_queue_has_no_violations() {
+        run_simulation_without_violations(false).await;
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+    async fn simulation_with_action_queue_has_no_violations() {
+        run_simulation_without_violations(true).await;
+    }
+}
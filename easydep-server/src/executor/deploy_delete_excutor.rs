@@ -32,6 +32,7 @@ use tonic::Status;
 
 use crate::config::DeploymentConfiguration;
 use crate::easydep::ExecutedActionEntry;
+use crate::executor::command_runner::ExecutionEnvironment;
 use crate::executor::script_executor::{execute_scripts, ScriptType};
 
 /// Calls the delete script of the deployment and removes the deployment directory after.
@@ -40,11 +41,13 @@ use crate::executor::script_executor::{execute_scripts, ScriptType};
 /// * `release` - The release associated with the deployment.
 /// * `deployment_directory` - The directory where the deployment is checked out.
 /// * `deployment_configuration` - The deployment profile configuration used for the current deployment.
+/// * `execution_environment` - The environment to resolve the secrets and run the external commands with.
 /// * `output_sender` - The sender to send status information to which will be sent to the client.
 pub async fn delete_deployment(
     release: &Release,
     deployment_directory: &PathBuf,
     deployment_configuration: &DeploymentConfiguration,
+    execution_environment: &ExecutionEnvironment,
     output_sender: &Sender<Result<ExecutedActionEntry, Status>>,
 ) {
     // execute the rollback scripts
@@ -53,6 +56,7 @@ pub async fn delete_deployment(
         &ScriptType::Delete,
         deployment_directory,
         deployment_configuration,
+        execution_environment,
         output_sender,
     )
     .await;
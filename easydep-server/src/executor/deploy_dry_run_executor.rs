@@ -0,0 +1,158 @@
+/*
+ * This file is part of easydep, licensed under the MIT License (MIT).
+ *
+ * Copyright (c) 2024 easybill GmbH
+ *
+ * Permission is hereby granted, free of charge, to any person obtaining a copy
+ * of this software and associated documentation files (the "Software"), to deal
+ * in the Software without restriction, including without limitation the rights
+ * to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+ * copies of the Software, and to permit persons to whom the Software is
+ * furnished to do so, subject to the following conditions:
+ *
+ * The above copyright notice and this permission notice shall be included in all
+ * copies or substantial portions of the Software.
+ *
+ * THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+ * IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+ * FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+ * AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+ * LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+ * OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+ * SOFTWARE.
+ */
+
+use std::path::Path;
+
+use octocrab::models::repos::Release;
+use tokio::sync::mpsc::Sender;
+use tonic::Status;
+
+use crate::config::{DeploymentConfiguration, DeploymentMode};
+use crate::easydep::{Action, ActionStatus, ExecutedActionEntry, LogEntry, LogType};
+use crate::executor::script_executor::{get_script_paths, ScriptType};
+
+/// Streams the steps that a deployment of the given release would execute to the given output sender, without
+/// executing any of them. Each step is reported as log entry of the action that would execute it. As the content of
+/// the release is not obtained, the scripts are reported with the note that they are only executed if present.
+///
+/// # Arguments
+/// * `release` - The release whose deployment is planned.
+/// * `deployment_directory` - The directory in which the deployment would be stored.
+/// * `deployment_configuration` - The deployment profile configuration for the planned deployment.
+/// * `output_sender` - The sender to which the planned steps should be sent.
+pub async fn stream_deployment_plan(
+    release: &Release,
+    deployment_directory: &Path,
+    deployment_configuration: &DeploymentConfiguration,
+    output_sender: &Sender<Result<ExecutedActionEntry, Status>>,
+) {
+    // the content of the release, either cloned from the repository or extracted from the release artifact
+    match deployment_configuration.mode {
+        DeploymentMode::Git => {
+            let checkout_step = format!(
+                "would clone {} (target {}) into {:?}",
+                release.tag_name, release.target_commitish, deployment_directory
+            );
+            send_plan_entry(release, Action::GitClone, checkout_step, output_sender).await;
+            if deployment_configuration.require_signed_tags {
+                let verification_step =
+                    format!("would verify the signature of tag {}", release.tag_name);
+                send_plan_entry(release, Action::GitClone, verification_step, output_sender).await;
+            }
+        }
+        DeploymentMode::Artifact => {
+            let download_step = format!(
+                "would download and extract the artifact of {} into {:?}",
+                release.tag_name, deployment_directory
+            );
+            send_plan_entry(
+                release,
+                Action::ArtifactDownload,
+                download_step,
+                output_sender,
+            )
+            .await;
+        }
+    }
+
+    if deployment_configuration.check_script_syntax {
+        send_plan_entry(
+            release,
+            Action::InitScript,
+            "would check the syntax of the scripts using bash -n".to_string(),
+            output_sender,
+        )
+        .await;
+    }
+
+    // the symlinks and file permissions, applied before the init scripts are executed
+    for symlink in deployment_configuration.get_symlinks() {
+        let source_path = deployment_directory.join(&symlink.source);
+        let target_note = if symlink.create_target_dir {
+            ", creating the target if missing"
+        } else {
+            ""
+        };
+        let symlink_step = format!(
+            "would create symlink {:?} -> {:?}{}",
+            source_path, symlink.target, target_note
+        );
+        send_plan_entry(release, Action::SymlinkCreate, symlink_step, output_sender).await;
+    }
+    if deployment_configuration.file_permissions.is_some() {
+        send_plan_entry(
+            release,
+            Action::FilePermissions,
+            "would apply the configured ownership and permissions".to_string(),
+            output_sender,
+        )
+        .await;
+    }
+
+    // the scripts, including the ones of extended configurations, in the order in which they would be executed
+    let mut script_types = vec![(ScriptType::Init, Action::InitScript, "")];
+    if deployment_configuration.build.is_some() {
+        script_types.push((ScriptType::Build, Action::BuildScript, ""));
+    }
+    script_types.push((
+        ScriptType::Publish,
+        Action::FinishScript,
+        " when the deployment is published",
+    ));
+    for (script_type, script_action, script_note) in script_types {
+        for script_path in get_script_paths(&script_type, deployment_configuration) {
+            let script_step = format!("would execute {} if present{}", script_path, script_note);
+            send_plan_entry(release, script_action, script_step, output_sender).await;
+        }
+    }
+}
+
+/// Sends a planned step of the given action to the given output sender.
+///
+/// # Arguments
+/// * `release` - The release whose deployment is planned.
+/// * `action` - The action that would execute the step.
+/// * `content` - The description of the planned step.
+/// * `output_sender` - The sender to which the step should be sent.
+async fn send_plan_entry(
+    release: &Release,
+    action: Action,
+    content: String,
+    output_sender: &Sender<Result<ExecutedActionEntry, Status>>,
+) {
+    output_sender
+        .send(Ok(ExecutedActionEntry {
+            release_id: release.id.0,
+            current_action: i32::from(action),
+            action_status: i32::from(ActionStatus::Running),
+            action_log_entry: Some(LogEntry {
+                stream_type: i32::from(LogType::Stdout),
+                content,
+            }),
+            progress_percent: None,
+            phase: None,
+        }))
+        .await
+        .ok();
+}
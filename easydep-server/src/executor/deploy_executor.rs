@@ -22,20 +22,35 @@
  * SOFTWARE.
  */
 
+use std::io::ErrorKind;
 use std::path::PathBuf;
+use std::sync::Arc;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
 
+use anyhow::{anyhow, Context};
+use log::{error, warn};
 use octocrab::models::repos::Release;
+use ring::rand::{SecureRandom, SystemRandom};
 use secrecy::SecretString;
+use tokio::fs;
 use tokio::sync::mpsc::Sender;
+use tokio::sync::RwLock;
 use tonic::Status;
 
 use crate::accessor::deploy_status_accessor::{DeployExecutionState, DeployStatusAccessor};
 use crate::accessor::deployment_accessor::DeploymentAccessor;
+use crate::accessor::prepared_deployment_accessor::{
+    PreparedDeployment, PreparedDeploymentAccessor,
+};
 use crate::config::{Configuration, DeploymentConfiguration};
-use crate::easydep::ExecutedActionEntry;
+use crate::easydep::{DeployAnnotation, ExecutedActionEntry};
+use crate::executor::build_executor::execute_build;
+use crate::executor::command_runner::ExecutionEnvironment;
 use crate::executor::deploy_delete_excutor::delete_deployment;
-use crate::executor::deploy_init_executor::init_deployment;
+use crate::executor::deploy_init_executor::{init_deployment, CheckoutOptions, BYTES_PER_MIB};
 use crate::executor::deploy_publish_executor::publish_deployment;
+use crate::executor::script_executor::{execute_scripts, ScriptType};
+use crate::service::request_identity::RequestIdentity;
 
 /// Holds the information about a single deployment.
 #[derive(Clone, Debug)]
@@ -54,6 +69,23 @@ pub(crate) struct DeployExecutor {
     deployment_configuration: DeploymentConfiguration,
     /// The status accessor for the current deployment.
     deployment_status_accessor: DeployStatusAccessor,
+    /// The environment to resolve the secrets and run the external commands of the deployment with.
+    execution_environment: ExecutionEnvironment,
+    /// The reason that was given when starting the deployment, if any.
+    annotation: Option<DeployAnnotation>,
+    /// The identity of the user that started the deployment.
+    triggered_by: RequestIdentity,
+    /// The git ref that is deployed if the deployment was not started from a release.
+    git_ref: Option<String>,
+    /// The SHA (or a prefix of it) of the commit that must be checked out, if pinned when starting the deployment.
+    expected_commit_sha: Option<String>,
+    /// The point in time when the prepared deployment expires, if the deployment is prepared and expires.
+    prepared_expires_at: Arc<RwLock<Option<Instant>>>,
+    /// The accessor to persist the deployment while it is prepared, to restore it after a server restart.
+    prepared_deployment_accessor: PreparedDeploymentAccessor,
+    /// The token issued when approving the deployment, if it was approved. Not persisted, deployments restored after
+    /// a server restart must be approved again.
+    approval_token: Arc<RwLock<Option<String>>>,
 }
 
 impl DeployExecutor {
@@ -63,18 +95,31 @@ impl DeployExecutor {
     /// * `release` - The release that is being deployed.
     /// * `github_access_token` - An access token for git https operations for the target repository of the release.
     /// * `global_configuration` - The server configuration.
-    /// * `deployment_accessor` - The accessor for deployment information stored on the disk.
     /// * `deployment_configuration` - The deployment profile configuration for the current release.
+    /// * `annotation` - The reason that was given when starting the deployment, if any.
+    /// * `triggered_by` - The identity of the user that started the deployment.
+    /// * `git_ref` - The git ref that is deployed if the deployment was not started from a release.
     pub fn new(
         release: Release,
         github_access_token: SecretString,
         global_configuration: Configuration,
-        deployment_accessor: DeploymentAccessor,
         deployment_configuration: DeploymentConfiguration,
+        annotation: Option<DeployAnnotation>,
+        triggered_by: RequestIdentity,
+        git_ref: Option<String>,
     ) -> Self {
-        let deployment_directory =
-            deployment_accessor.get_release_directory(&deployment_configuration, &release.id.0);
+        let deployment_accessor = DeploymentAccessor::new(&global_configuration);
+        let deployment_slot = deployment_accessor.select_deployment_slot(&deployment_configuration);
+        let deployment_configuration =
+            deployment_configuration.with_deployment_slot(deployment_slot);
+        let deployment_directory = deployment_accessor.get_release_directory(
+            &deployment_configuration,
+            &release.id.0,
+            &release.tag_name,
+        );
         let deployment_status_accessor = DeployStatusAccessor::new();
+        let execution_environment = ExecutionEnvironment::new(&global_configuration);
+        let prepared_deployment_accessor = PreparedDeploymentAccessor::new(&global_configuration);
         Self {
             release,
             deployment_directory,
@@ -83,9 +128,98 @@ impl DeployExecutor {
             deployment_accessor,
             deployment_configuration,
             deployment_status_accessor,
+            execution_environment,
+            annotation,
+            triggered_by,
+            git_ref,
+            expected_commit_sha: None,
+            prepared_expires_at: Arc::new(RwLock::new(None)),
+            prepared_deployment_accessor,
+            approval_token: Arc::new(RwLock::new(None)),
         }
     }
 
+    /// Restores a deployment executor in the prepared state from the given persisted prepared deployment, for example
+    /// after the server restarted. The restored deployment can be published or deleted, but not prepared again.
+    ///
+    /// # Arguments
+    /// * `prepared_deployment` - The persisted prepared deployment to restore.
+    /// * `global_configuration` - The server configuration.
+    pub async fn restore(
+        prepared_deployment: PreparedDeployment,
+        global_configuration: Configuration,
+    ) -> anyhow::Result<Self> {
+        let deployment_configuration = global_configuration
+            .get_deployment_configuration(&prepared_deployment.profile)
+            .ok_or_else(|| {
+                anyhow!(
+                    "deployment configuration {} is no longer registered",
+                    prepared_deployment.profile
+                )
+            })?;
+        let deployment_slot = match &prepared_deployment.slot {
+            Some(slot_name) => Some(
+                deployment_configuration
+                    .slots
+                    .iter()
+                    .find(|slot| &slot.name == slot_name)
+                    .cloned()
+                    .with_context(|| format!("slot {} is no longer configured", slot_name))?,
+            ),
+            None => None,
+        };
+        let deployment_configuration =
+            deployment_configuration.with_deployment_slot(deployment_slot);
+
+        // the expiry is persisted as a unix timestamp, as instants cannot be persisted
+        let prepared_expires_at = prepared_deployment.expires_at.map(|expires_at| {
+            let now = SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map(|duration| duration.as_secs())
+                .unwrap_or_default();
+            Instant::now() + Duration::from_secs(expires_at.saturating_sub(now))
+        });
+        let annotation = prepared_deployment
+            .annotation_message
+            .map(|message| DeployAnnotation {
+                message,
+                ticket_reference: prepared_deployment.annotation_ticket_reference,
+            });
+
+        // the access token is only needed to prepare the deployment, which is already done
+        let deployment_status_accessor = DeployStatusAccessor::new();
+        deployment_status_accessor
+            .transition_to(DeployExecutionState::Prepared)
+            .await?;
+        Ok(Self {
+            release: prepared_deployment.release,
+            deployment_directory: prepared_deployment.deployment_directory,
+            github_access_token: SecretString::new(String::new()),
+            deployment_accessor: DeploymentAccessor::new(&global_configuration),
+            execution_environment: ExecutionEnvironment::new(&global_configuration),
+            prepared_deployment_accessor: PreparedDeploymentAccessor::new(&global_configuration),
+            global_configuration,
+            deployment_configuration,
+            deployment_status_accessor,
+            annotation,
+            triggered_by: prepared_deployment.triggered_by,
+            git_ref: prepared_deployment.git_ref,
+            expected_commit_sha: None,
+            prepared_expires_at: Arc::new(RwLock::new(prepared_expires_at)),
+            approval_token: Arc::new(RwLock::new(None)),
+        })
+    }
+
+    /// Pins the commit that must be checked out for this deployment, the deployment fails before executing any script
+    /// if a different commit is checked out.
+    ///
+    /// # Arguments
+    /// * `expected_commit_sha` - The SHA (or a prefix of it) of the commit that must be checked out, if any.
+    pub fn with_expected_commit_sha(mut self, expected_commit_sha: Option<String>) -> Self {
+        self.expected_commit_sha = expected_commit_sha;
+        self
+    }
+
     /// Get the id of the release that is being deployed.
     pub fn get_release_id(&self) -> u64 {
         self.release.id.0
@@ -96,6 +230,63 @@ impl DeployExecutor {
         &self.release
     }
 
+    /// Get the reason that was given when starting this deployment, if any.
+    pub fn get_annotation(&self) -> Option<&DeployAnnotation> {
+        self.annotation.as_ref()
+    }
+
+    /// Get the identity of the user that started this deployment.
+    pub fn get_triggered_by(&self) -> &RequestIdentity {
+        &self.triggered_by
+    }
+
+    /// Get the git ref that is deployed if this deployment was not started from a release.
+    pub fn get_git_ref(&self) -> Option<&String> {
+        self.git_ref.as_ref()
+    }
+
+    /// Get the deployment profile configuration used for this deployment.
+    pub fn get_deployment_configuration(&self) -> &DeploymentConfiguration {
+        &self.deployment_configuration
+    }
+
+    /// Get the time remaining until the prepared deployment expires. Returns `None` if the deployment
+    /// is not prepared or the deployment profile does not define a time-to-live for prepared deployments.
+    pub async fn get_prepared_time_remaining(&self) -> Option<Duration> {
+        let prepared_expires_at = *self.prepared_expires_at.read().await;
+        match self.deployment_status_accessor.get_state().await {
+            DeployExecutionState::Prepared => prepared_expires_at
+                .map(|expires_at| expires_at.saturating_duration_since(Instant::now())),
+            _ => None,
+        }
+    }
+
+    /// Approves this deployment, issuing a new random token that must be passed to publish it. A token that was
+    /// issued by a previous approval is no longer valid afterward.
+    pub async fn approve(&self) -> anyhow::Result<String> {
+        let mut token_bytes = [0u8; 16];
+        SystemRandom::new()
+            .fill(&mut token_bytes)
+            .map_err(|_| anyhow!("unable to generate approval token"))?;
+        let approval_token = token_bytes
+            .iter()
+            .map(|byte| format!("{:02x}", byte))
+            .collect::<String>();
+        *self.approval_token.write().await = Some(approval_token.clone());
+        Ok(approval_token)
+    }
+
+    /// Checks if one of the given tokens was issued when approving this deployment.
+    ///
+    /// # Arguments
+    /// * `approval_tokens` - The tokens that were passed to publish the deployment.
+    pub async fn is_approved(&self, approval_tokens: &[String]) -> bool {
+        match &*self.approval_token.read().await {
+            Some(approval_token) => approval_tokens.contains(approval_token),
+            None => false,
+        }
+    }
+
     /// Get the status accessor associated with this deployment executor.
     pub fn get_status_accessor(&self) -> &DeployStatusAccessor {
         &self.deployment_status_accessor
@@ -103,6 +294,8 @@ impl DeployExecutor {
 
     /// Starts to prepare this deployment. This method does not make
     /// any status checks and assumes that they have been done before.
+    /// If the preparation fails the deployment switches to the failed
+    /// state and its directory is removed, it is not persisted.
     ///
     /// # Arguments
     /// * `output_sender` - The sender for output log lines that are logged by scripts run in the steps.
@@ -110,17 +303,222 @@ impl DeployExecutor {
         &self,
         output_sender: Sender<Result<ExecutedActionEntry, Status>>,
     ) {
-        init_deployment(
+        let checkout_options = CheckoutOptions {
+            is_ref_deployment: self.git_ref.is_some(),
+            expected_commit_sha: self.expected_commit_sha.clone(),
+            directory_mode: self.deployment_accessor.get_directory_mode(),
+            repository_cache_directory: self.deployment_configuration.repository_cache.then(|| {
+                self.deployment_accessor
+                    .get_repository_cache_directory(&self.deployment_configuration)
+            }),
+            base_directory: PathBuf::from(&self.global_configuration.base_directory),
+            min_free_disk_bytes: self
+                .global_configuration
+                .min_free_disk_mb
+                .map(|min_free_disk_mb| min_free_disk_mb.saturating_mul(BYTES_PER_MIB)),
+        };
+        let execution_environment = self.resolve_script_execution_environment();
+
+        // a directory that exists before the deployment belongs to another deployment and must be kept on failure
+        let directory_existed = fs::try_exists(&self.deployment_directory)
+            .await
+            .unwrap_or(true);
+        let prepared = init_deployment(
             &self.release,
             &self.deployment_directory,
             &self.github_access_token,
             &self.deployment_configuration,
+            &execution_environment,
+            &checkout_options,
             &output_sender,
         )
-        .await;
-        self.deployment_status_accessor
-            .set_state(DeployExecutionState::Prepared)
+        .await
+            && execute_build(
+                &self.release,
+                &self.deployment_directory,
+                &self.deployment_accessor,
+                &self.deployment_configuration,
+                &execution_environment,
+                &output_sender,
+            )
             .await;
+        if !prepared {
+            if self
+                .deployment_status_accessor
+                .compare_and_set_state(
+                    &DeployExecutionState::Preparing,
+                    DeployExecutionState::Failed,
+                )
+                .await
+            {
+                if !directory_existed {
+                    self.remove_deployment_directory("failed").await;
+                }
+                return;
+            }
+        } else if let Some(prepared_ttl_seconds) =
+            self.deployment_configuration.prepared_ttl_seconds
+        {
+            let expires_at = Instant::now() + Duration::from_secs(prepared_ttl_seconds);
+            *self.prepared_expires_at.write().await = Some(expires_at);
+        }
+        if self
+            .deployment_status_accessor
+            .compare_and_set_state(
+                &DeployExecutionState::Preparing,
+                DeployExecutionState::Prepared,
+            )
+            .await
+        {
+            self.persist_prepared_deployment().await;
+            return;
+        }
+
+        // the deployment was cancelled while being prepared
+        self.clean_up_cancelled_deployment(&output_sender).await;
+        if let Err(err) = self
+            .deployment_status_accessor
+            .transition_to(DeployExecutionState::Cancelled)
+            .await
+        {
+            error!(
+                "Unable to switch state of deployment {}: {err}",
+                self.release.id.0
+            );
+        }
+    }
+
+    /// Cancels this deployment while it is being prepared, terminating the running external processes. The cancel
+    /// scripts are executed and the deployment directory is removed once the preparation stopped.
+    ///
+    /// # Returns
+    /// * `bool` - `true` if the deployment was cancelled, `false` if it is not being prepared.
+    pub async fn cancel_deployment(&self) -> bool {
+        if !self
+            .deployment_status_accessor
+            .compare_and_set_state(
+                &DeployExecutionState::Preparing,
+                DeployExecutionState::Cancelling,
+            )
+            .await
+        {
+            return false;
+        }
+        self.execution_environment.cancellation.cancel();
+        true
+    }
+
+    /// Executes the cancel scripts of this deployment and removes the deployment directory, as the deployment was
+    /// cancelled while being prepared.
+    ///
+    /// # Arguments
+    /// * `output_sender` - The sender for output log lines that are logged by the cancel scripts.
+    async fn clean_up_cancelled_deployment(
+        &self,
+        output_sender: &Sender<Result<ExecutedActionEntry, Status>>,
+    ) {
+        // the cancel scripts must not be terminated by the cancellation of the deployment
+        let cancel_environment = self
+            .resolve_script_execution_environment()
+            .with_new_cancellation();
+        execute_scripts(
+            &self.release,
+            &ScriptType::Cancel,
+            &self.deployment_directory,
+            &self.deployment_configuration,
+            &cancel_environment,
+            output_sender,
+        )
+        .await;
+        self.remove_deployment_directory("cancelled").await;
+    }
+
+    /// Removes the directory of this deployment, as the deployment ended while being prepared. A directory that does
+    /// not exist is ignored.
+    ///
+    /// # Arguments
+    /// * `reason` - The reason why the deployment ended, used in the log message on failure.
+    async fn remove_deployment_directory(&self, reason: &str) {
+        if let Err(err) = fs::remove_dir_all(&self.deployment_directory).await {
+            if err.kind() != ErrorKind::NotFound {
+                error!(
+                    "Unable to remove directory {:?} of {} deployment: {}",
+                    self.deployment_directory, reason, err
+                );
+            }
+        }
+    }
+
+    /// Get the environment in which the scripts of this deployment are executed, providing the directory of the
+    /// release that is currently published with the profile as previous release. Must be resolved before the
+    /// deployment is published, as the published release changes afterwards.
+    fn resolve_script_execution_environment(&self) -> ExecutionEnvironment {
+        let previous_release_directory = self
+            .deployment_accessor
+            .get_published_release_directory(&self.deployment_configuration)
+            .filter(|(_, release_id)| *release_id != self.release.id.0)
+            .map(|(release_directory, _)| release_directory);
+        self.execution_environment
+            .with_previous_release_directory(previous_release_directory)
+    }
+
+    /// Persists this deployment as prepared deployment, allowing to publish or delete it after a server restart. A
+    /// failure is only logged, as the deployment can still be published or deleted while the server is running.
+    async fn persist_prepared_deployment(&self) {
+        let expires_at =
+            self.deployment_configuration
+                .prepared_ttl_seconds
+                .and_then(|prepared_ttl_seconds| {
+                    SystemTime::now()
+                        .duration_since(UNIX_EPOCH)
+                        .ok()
+                        .map(|duration| duration.as_secs() + prepared_ttl_seconds)
+                });
+        let prepared_deployment = PreparedDeployment {
+            profile: self.deployment_configuration.id.clone(),
+            slot: self
+                .deployment_configuration
+                .deployment_slot
+                .as_ref()
+                .map(|slot| slot.name.clone()),
+            release: self.release.clone(),
+            deployment_directory: self.deployment_directory.clone(),
+            annotation_message: self
+                .annotation
+                .as_ref()
+                .map(|annotation| annotation.message.clone()),
+            annotation_ticket_reference: self
+                .annotation
+                .as_ref()
+                .and_then(|annotation| annotation.ticket_reference.clone()),
+            triggered_by: self.triggered_by.clone(),
+            git_ref: self.git_ref.clone(),
+            expires_at,
+        };
+        if let Err(err) = self
+            .prepared_deployment_accessor
+            .store_prepared_deployment(&prepared_deployment)
+            .await
+        {
+            warn!(
+                "Unable to persist prepared deployment {}: {:?}",
+                self.release.id.0, err
+            );
+        }
+    }
+
+    /// Removes the persisted prepared deployment, as this deployment was published or deleted.
+    async fn remove_persisted_prepared_deployment(&self) {
+        if let Err(err) = self
+            .prepared_deployment_accessor
+            .remove_prepared_deployment()
+            .await
+        {
+            warn!(
+                "Unable to remove persisted prepared deployment {}: {:?}",
+                self.release.id.0, err
+            );
+        }
     }
 
     /// Publishes this deployment. This method does not make
@@ -138,12 +536,21 @@ impl DeployExecutor {
             &self.global_configuration,
             &self.deployment_accessor,
             &self.deployment_configuration,
+            &self.resolve_script_execution_environment(),
             &output_sender,
         )
         .await;
-        self.deployment_status_accessor
-            .set_state(DeployExecutionState::Published)
-            .await;
+        self.remove_persisted_prepared_deployment().await;
+        if let Err(err) = self
+            .deployment_status_accessor
+            .transition_to(DeployExecutionState::Published)
+            .await
+        {
+            error!(
+                "Unable to switch state of deployment {}: {err}",
+                self.release.id.0
+            );
+        }
     }
 
     /// Deletes this deployment. This method does not make
@@ -159,11 +566,20 @@ impl DeployExecutor {
             &self.release,
             &self.deployment_directory,
             &self.deployment_configuration,
+            &self.resolve_script_execution_environment(),
             &output_sender,
         )
         .await;
-        self.deployment_status_accessor
-            .set_state(DeployExecutionState::Deleted)
-            .await;
+        self.remove_persisted_prepared_deployment().await;
+        if let Err(err) = self
+            .deployment_status_accessor
+            .transition_to(DeployExecutionState::Deleted)
+            .await
+        {
+            error!(
+                "Unable to switch state of deployment {}: {err}",
+                self.release.id.0
+            );
+        }
     }
 }
@@ -34,6 +34,9 @@ use tonic::Status;
 use crate::accessor::deployment_accessor::DeploymentAccessor;
 use crate::config::{Configuration, DeploymentConfiguration};
 use crate::easydep::ExecutedActionEntry;
+use crate::executor::command_runner::ExecutionEnvironment;
+use crate::executor::manifest_executor::execute_manifest_post_publish;
+use crate::executor::publish_hook_executor::execute_publish_hooks;
 use crate::executor::script_executor::{execute_scripts, ScriptType};
 
 /// Executes all steps required to publish a deployment (script execution, symlink creation, etc.).
@@ -45,15 +48,20 @@ use crate::executor::script_executor::{execute_scripts, ScriptType};
 /// * `global_configuration` - The server configuration.
 /// * `deployment_accessor` - The accessor for deployments stored on the disk.
 /// * `deployment_configuration` - The deployment profile configuration for the current deployment.
+/// * `execution_environment` - The environment to resolve the secrets and run the external commands with.
 /// * `output_sender` - The sender to which log line output should be sent.
+///
+/// # Returns
+/// * `bool` - `true` if the deployment was published successfully, `false` otherwise.
 pub async fn publish_deployment(
     release: &Release,
     deployment_directory: &PathBuf,
     global_configuration: &Configuration,
     deployment_accessor: &DeploymentAccessor,
     deployment_configuration: &DeploymentConfiguration,
+    execution_environment: &ExecutionEnvironment,
     output_sender: &Sender<Result<ExecutedActionEntry, Status>>,
-) {
+) -> bool {
     // symlink the "current" directory to the pulled deployed directory
     let published_directory =
         deployment_accessor.get_current_release_directory(deployment_configuration);
@@ -64,18 +72,47 @@ pub async fn publish_deployment(
             .send(Err(Status::internal(error_message)))
             .await
             .ok();
-        return;
+        return false;
+    }
+
+    // point the symlink of the slot in which the deployment was executed to the deployed directory
+    if let Some(deployment_slot) = &deployment_configuration.deployment_slot {
+        let slot_directory = deployment_accessor
+            .get_slot_release_directory(deployment_configuration, deployment_slot);
+        remove_symlink_dir(&slot_directory).ok();
+        if let Err(err) = symlink_dir(deployment_directory, slot_directory) {
+            let error_message = format!("unable to symlink slot directory: {err}");
+            output_sender
+                .send(Err(Status::internal(error_message)))
+                .await
+                .ok();
+            return false;
+        }
     }
 
-    // execute the scripts provided for publishing
-    execute_scripts(
+    // execute the scripts provided for publishing, the remaining steps are skipped if one of them failed
+    let script_execution_result = execute_scripts(
         release,
         &ScriptType::Publish,
         deployment_directory,
         deployment_configuration,
+        execution_environment,
         output_sender,
     )
     .await;
+    if !script_execution_result.is_success() {
+        return false;
+    }
+
+    // execute the warmup commands and health check declared in the deployment manifest, the publish hooks
+    // are only called when the published deployment is healthy
+    let command_runner = execution_environment.command_runner.as_ref();
+    if !execute_manifest_post_publish(release, deployment_directory, command_runner, output_sender)
+        .await
+    {
+        return false;
+    }
+    execute_publish_hooks(release, deployment_configuration, output_sender).await;
 
     // remove the oldest release if needed
     if global_configuration.retained_releases > 1 {
@@ -86,6 +123,7 @@ pub async fn publish_deployment(
         )
         .await;
     }
+    true
 }
 
 /// Discards the oldest release stored on the disk unless the stored
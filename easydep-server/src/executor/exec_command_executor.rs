@@ -0,0 +1,113 @@
+/*
+ * This file is part of easydep, licensed under the MIT License (MIT).
+ *
+ * Copyright (c) 2024 easybill GmbH
+ *
+ * Permission is hereby granted, free of charge, to any person obtaining a copy
+ * of this software and associated documentation files (the "Software"), to deal
+ * in the Software without restriction, including without limitation the rights
+ * to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+ * copies of the Software, and to permit persons to whom the Software is
+ * furnished to do so, subject to the following conditions:
+ *
+ * The above copyright notice and this permission notice shall be included in all
+ * copies or substantial portions of the Software.
+ *
+ * THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+ * IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+ * FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+ * AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+ * LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+ * OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+ * SOFTWARE.
+ */
+
+use std::path::Path;
+use std::process::Stdio;
+
+use tokio::process::Command;
+use tokio::sync::mpsc::Sender;
+use tonic::Status;
+
+use crate::config::{DeploymentConfiguration, ExecCommandConfiguration};
+use crate::easydep::{Action, ExecutedActionEntry};
+use crate::executor::command_runner::ExecutionEnvironment;
+use crate::executor::process_priority::apply_process_priority;
+use crate::process_streamer::ProcessStreamer;
+
+/// Executes the given configured command in the given release directory, streaming its output to the given sender.
+/// The command is executed without a shell and is subject to the script timeout of the deployment configuration.
+///
+/// # Arguments
+/// * `release_id` - The id of the release in whose directory the command is executed.
+/// * `release_directory` - The directory of the release to execute the command in.
+/// * `exec_command` - The configured command to execute.
+/// * `deployment_configuration` - The deployment profile configuration that configures the command.
+/// * `execution_environment` - The environment to run the command with.
+/// * `output_sender` - The sender to which log line output should be sent.
+///
+/// # Returns
+/// * `bool` - `true` if the command completed successfully, `false` otherwise.
+pub async fn execute_exec_command(
+    release_id: u64,
+    release_directory: &Path,
+    exec_command: &ExecCommandConfiguration,
+    deployment_configuration: &DeploymentConfiguration,
+    execution_environment: &ExecutionEnvironment,
+    output_sender: &Sender<Result<ExecutedActionEntry, Status>>,
+) -> bool {
+    let Some((program, arguments)) = exec_command.command.split_first() else {
+        let error_message = format!("exec command {} has no program", exec_command.name);
+        output_sender
+            .send(Err(Status::internal(error_message)))
+            .await
+            .ok();
+        return false;
+    };
+
+    let mut command = Command::new(program);
+    command
+        .args(arguments)
+        .current_dir(release_directory)
+        .stderr(Stdio::piped())
+        .stdout(Stdio::piped());
+    apply_process_priority(
+        &mut command,
+        deployment_configuration.process_priority.as_ref(),
+    );
+    let execution_environment =
+        execution_environment.with_script_timeout_of(deployment_configuration);
+    match execution_environment.command_runner.spawn(&mut command) {
+        Ok(command_process) => {
+            let mut command_streamer = ProcessStreamer::new(
+                Action::ExecCommand,
+                release_id,
+                command_process,
+                output_sender.clone(),
+            )
+            .with_timeout(
+                execution_environment.script_timeout,
+                execution_environment.script_kill_grace_period,
+            );
+            if let Err(err) = command_streamer.await_child_and_stream().await {
+                let error_message =
+                    format!("issue while executing command {}: {err}", exec_command.name);
+                output_sender
+                    .send(Err(Status::internal(error_message)))
+                    .await
+                    .ok();
+                return false;
+            }
+            true
+        }
+        Err(err) => {
+            let error_message =
+                format!("issue while spawning command {}: {err}", exec_command.name);
+            output_sender
+                .send(Err(Status::internal(error_message)))
+                .await
+                .ok();
+            false
+        }
+    }
+}
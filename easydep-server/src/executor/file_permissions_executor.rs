@@ -0,0 +1,128 @@
+/*
+ * This file is part of easydep, licensed under the MIT License (MIT).
+ *
+ * Copyright (c) 2024 easybill GmbH
+ *
+ * Permission is hereby granted, free of charge, to any person obtaining a copy
+ * of this software and associated documentation files (the "Software"), to deal
+ * in the Software without restriction, including without limitation the rights
+ * to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+ * copies of the Software, and to permit persons to whom the Software is
+ * furnished to do so, subject to the following conditions:
+ *
+ * The above copyright notice and this permission notice shall be included in all
+ * copies or substantial portions of the Software.
+ *
+ * THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+ * IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+ * FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+ * AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+ * LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+ * OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+ * SOFTWARE.
+ */
+use std::path::PathBuf;
+use std::process::Stdio;
+
+use octocrab::models::repos::Release;
+use tokio::process::Command;
+use tokio::sync::mpsc::Sender;
+use tonic::Status;
+
+use crate::config::{DeploymentConfiguration, FilePermissionsConfiguration};
+use crate::easydep::{Action, ExecutedActionEntry};
+use crate::executor::command_runner::ExecutionEnvironment;
+use crate::executor::process_priority::apply_process_priority;
+use crate::process_streamer::ProcessStreamer;
+
+/// The script that applies the ownership and permissions to the deployment directory. Symlinks are not followed, which
+/// keeps the targets of the configured symlinks untouched.
+const FILE_PERMISSIONS_SCRIPT: &str = r#"set -e
+if [ -n "$2" ]; then echo "changing owner to $2"; chown -R -P -- "$2" "$1"; fi
+if [ -n "$3" ]; then echo "changing mode to $3"; find "$1" \( -type f -o -type d \) -exec chmod -- "$3" {} +; fi
+if [ "$4" = "true" ]; then echo "restoring selinux security contexts"; restorecon -R "$1"; fi"#;
+
+/// Applies the ownership and permissions configured in the given deployment configuration to the files in the given
+/// deployment directory. Nothing is done if the deployment configuration does not configure file permissions.
+///
+/// # Arguments
+/// * `release` - The release that is currently being deployed.
+/// * `deployment_directory` - The directory in which the deployment is stored.
+/// * `deployment_configuration` - The deployment profile configuration for the current deployment.
+/// * `execution_environment` - The environment to run the external commands with.
+/// * `output_sender` - The sender to which log line output should be sent.
+///
+/// # Returns
+/// * `bool` - `true` if the permissions were applied successfully or none are configured, `false` otherwise.
+pub async fn apply_file_permissions(
+    release: &Release,
+    deployment_directory: &PathBuf,
+    deployment_configuration: &DeploymentConfiguration,
+    execution_environment: &ExecutionEnvironment,
+    output_sender: &Sender<Result<ExecutedActionEntry, Status>>,
+) -> bool {
+    let Some(file_permissions) = &deployment_configuration.file_permissions else {
+        return true;
+    };
+
+    let mut file_permissions_command = Command::new("bash");
+    file_permissions_command
+        .arg("-c")
+        .arg(FILE_PERMISSIONS_SCRIPT)
+        .arg("file-permissions")
+        .arg(deployment_directory)
+        .arg(build_owner_spec(file_permissions))
+        .arg(file_permissions.mode.as_deref().unwrap_or_default())
+        .arg(file_permissions.restore_selinux_context.to_string())
+        .stderr(Stdio::piped())
+        .stdout(Stdio::piped());
+    apply_process_priority(
+        &mut file_permissions_command,
+        deployment_configuration.process_priority.as_ref(),
+    );
+    match execution_environment
+        .command_runner
+        .spawn(&mut file_permissions_command)
+    {
+        Ok(file_permissions_process) => {
+            let mut file_permissions_streamer = ProcessStreamer::new(
+                Action::FilePermissions,
+                release.id.0,
+                file_permissions_process,
+                output_sender.clone(),
+            )
+            .with_cancellation(execution_environment.cancellation.clone());
+            if let Err(err) = file_permissions_streamer.await_child_and_stream().await {
+                let error_message = format!("issue while applying file permissions: {err}");
+                output_sender
+                    .send(Err(Status::internal(error_message)))
+                    .await
+                    .ok();
+                return false;
+            }
+            true
+        }
+        Err(err) => {
+            let error_message = format!("issue while spawning file permissions process: {err}");
+            output_sender
+                .send(Err(Status::internal(error_message)))
+                .await
+                .ok();
+            false
+        }
+    }
+}
+
+/// Builds the owner specification passed to `chown` (`<owner>`, `<owner>:<group>` or `:<group>`), empty if neither
+/// an owner nor a group is configured.
+///
+/// # Arguments
+/// * `file_permissions` - The configured file permissions.
+fn build_owner_spec(file_permissions: &FilePermissionsConfiguration) -> String {
+    match (&file_permissions.owner, &file_permissions.group) {
+        (Some(owner), Some(group)) => format!("{owner}:{group}"),
+        (Some(owner), None) => owner.clone(),
+        (None, Some(group)) => format!(":{group}"),
+        (None, None) => String::new(),
+    }
+}
@@ -0,0 +1,86 @@
+/*
+ * This file is part of easydep, licensed under the MIT License (MIT).
+ *
+ * Copyright (c) 2024 easybill GmbH
+ *
+ * Permission is hereby granted, free of charge, to any person obtaining a copy
+ * of this software and associated documentation files (the "Software"), to deal
+ * in the Software without restriction, including without limitation the rights
+ * to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+ * copies of the Software, and to permit persons to whom the Software is
+ * furnished to do so, subject to the following conditions:
+ *
+ * The above copyright notice and this permission notice shall be included in all
+ * copies or substantial portions of the Software.
+ *
+ * THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+ * IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+ * FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+ * AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+ * LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+ * OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+ * SOFTWARE.
+ */
+use tokio::process::Command;
+
+use crate::config::{GitConfiguration, SshCheckoutConfiguration};
+
+/// Constructs a new command that executes the configured git executable, with the configured environment variables
+/// and git configuration values applied.
+///
+/// # Arguments
+/// * `git_configuration` - The git settings that apply to the command.
+pub(crate) fn new_git_command(git_configuration: &GitConfiguration) -> Command {
+    let mut command = Command::new(git_configuration.executable());
+    apply_git_environment(&mut command, git_configuration);
+    command
+}
+
+/// Applies the configured environment variables and git configuration values to the given command. The git
+/// configuration values are provided through the `GIT_CONFIG_*` environment variables, which makes them apply to all
+/// git commands executed by the command (for example from within a shell script).
+///
+/// # Arguments
+/// * `command` - The command to apply the git environment to.
+/// * `git_configuration` - The git settings to apply.
+pub(crate) fn apply_git_environment(command: &mut Command, git_configuration: &GitConfiguration) {
+    command.envs(&git_configuration.env);
+    if !git_configuration.config.is_empty() {
+        command.env(
+            "GIT_CONFIG_COUNT",
+            git_configuration.config.len().to_string(),
+        );
+        for (index, (key, value)) in git_configuration.config.iter().enumerate() {
+            command
+                .env(format!("GIT_CONFIG_KEY_{index}"), key)
+                .env(format!("GIT_CONFIG_VALUE_{index}"), value);
+        }
+    }
+}
+
+/// Builds the ssh command that git uses to connect to the remote of an ssh checkout, provided as `GIT_SSH_COMMAND`. The
+/// command never prompts (for example for a passphrase or an unknown host key) and only uses the configured deploy key,
+/// if one is configured.
+///
+/// # Arguments
+/// * `ssh_checkout` - The ssh checkout settings of the deployment configuration.
+pub(crate) fn build_ssh_command(ssh_checkout: &SshCheckoutConfiguration) -> String {
+    let mut ssh_command = "ssh -o BatchMode=yes".to_string();
+    if let Some(private_key_path) = &ssh_checkout.private_key_path {
+        ssh_command.push_str(" -o IdentitiesOnly=yes -i ");
+        ssh_command.push_str(&quote_shell_argument(private_key_path));
+    }
+    if let Some(known_hosts_file) = &ssh_checkout.known_hosts_file {
+        ssh_command.push_str(" -o StrictHostKeyChecking=yes -o UserKnownHostsFile=");
+        ssh_command.push_str(&quote_shell_argument(known_hosts_file));
+    }
+    ssh_command
+}
+
+/// Quotes the given argument for the shell that git executes the ssh command with.
+///
+/// # Arguments
+/// * `argument` - The argument to quote.
+fn quote_shell_argument(argument: &str) -> String {
+    format!("'{}'", argument.replace('\'', "'\\''"))
+}
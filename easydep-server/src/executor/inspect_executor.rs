@@ -0,0 +1,190 @@
+/*
+ * This file is part of easydep, licensed under the MIT License (MIT).
+ *
+ * Copyright (c) 2024 easybill GmbH
+ *
+ * Permission is hereby granted, free of charge, to any person obtaining a copy
+ * of this software and associated documentation files (the "Software"), to deal
+ * in the Software without restriction, including without limitation the rights
+ * to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+ * copies of the Software, and to permit persons to whom the Software is
+ * furnished to do so, subject to the following conditions:
+ *
+ * The above copyright notice and this permission notice shall be included in all
+ * copies or substantial portions of the Software.
+ *
+ * THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+ * IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+ * FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+ * AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+ * LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+ * OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+ * SOFTWARE.
+ */
+
+use std::collections::{BTreeMap, HashSet};
+
+use log::{error, info, warn};
+use tokio::fs;
+
+use crate::accessor::deployment_accessor::DeploymentAccessor;
+use crate::accessor::release_metadata_accessor::ReleaseMetadataAccessor;
+use crate::accessor::release_tombstone_accessor::ReleaseTombstoneAccessor;
+use crate::config::{Configuration, DeploymentConfiguration};
+use crate::executor::orphan_cleanup_executor::find_orphaned_entries;
+
+/// Prints the state of the deployments stored in the base directory, read directly from the disk: the current release
+/// links, the size of the repository cache, the stored releases with their bad markers and metadata and the orphaned
+/// entries of each profile. This works
+/// without the gRPC service, for example while the server is down during an incident.
+///
+/// # Arguments
+/// * `configuration` - The server configuration.
+pub async fn inspect_base_directory(configuration: &Configuration) {
+    let deployment_accessor = DeploymentAccessor::new(configuration);
+    let release_tombstone_accessor = ReleaseTombstoneAccessor::new(configuration);
+    let release_metadata_accessor = ReleaseMetadataAccessor::new(configuration);
+    info!("Inspecting base directory {}", configuration.base_directory);
+
+    // multiple profiles can share the same target, the orphaned entries of each target are only reported once
+    let mut inspected_targets = HashSet::new();
+    for deployment_configuration in configuration.get_deployment_configurations() {
+        if deployment_configuration.extend_only {
+            continue;
+        }
+
+        let profile = &deployment_configuration.id;
+        info!(
+            "[{}] --| Target               : {}",
+            profile, deployment_configuration.target
+        );
+        inspect_release_links(&deployment_accessor, deployment_configuration).await;
+        if deployment_configuration.repository_cache {
+            let cache_directory =
+                deployment_accessor.get_repository_cache_directory(deployment_configuration);
+            match deployment_accessor
+                .get_repository_cache_disk_usage(deployment_configuration)
+                .await
+            {
+                Ok(disk_usage) => info!(
+                    "[{}] --| Repository Cache     : {} ({} bytes)",
+                    profile,
+                    cache_directory.display(),
+                    disk_usage
+                ),
+                Err(_) => info!(
+                    "[{}] --| Repository Cache     : {} (not created yet)",
+                    profile,
+                    cache_directory.display()
+                ),
+            }
+        }
+
+        // list the stored releases, newest first
+        let current_release_directory = fs::canonicalize(
+            deployment_accessor.get_current_release_directory(deployment_configuration),
+        )
+        .await
+        .ok();
+        match deployment_accessor
+            .get_release_directories_for_profile(deployment_configuration)
+            .await
+        {
+            Ok(release_directories) if release_directories.is_empty() => {
+                info!("[{}] --| Releases             : none", profile);
+            }
+            Ok(release_directories) => {
+                for (release_directory, release_id) in release_directories {
+                    let mut release_notes = Vec::<String>::new();
+                    if fs::canonicalize(&release_directory).await.ok() == current_release_directory
+                    {
+                        release_notes.push("current".to_string());
+                    }
+                    if let Ok(Some(tombstone)) = release_tombstone_accessor
+                        .get_tombstone(deployment_configuration, &release_id)
+                        .await
+                    {
+                        release_notes.push(format!("marked bad: {}", tombstone.reason));
+                    }
+                    if let Ok(metadata) = release_metadata_accessor
+                        .get_metadata(deployment_configuration, &release_id)
+                        .await
+                    {
+                        for (key, value) in metadata.iter().collect::<BTreeMap<_, _>>() {
+                            release_notes.push(format!("{key}={value}"));
+                        }
+                    }
+                    info!(
+                        "[{}] --| Release              : {} (id: {}){}",
+                        profile,
+                        release_directory.display(),
+                        release_id,
+                        if release_notes.is_empty() {
+                            String::new()
+                        } else {
+                            format!(" [{}]", release_notes.join(", "))
+                        }
+                    );
+                }
+            }
+            Err(err) => error!("[{}] Unable to list releases: {err:?}", profile),
+        }
+
+        // report the entries that are neither the current release, a slot release nor a retained release
+        if !inspected_targets.insert(&deployment_configuration.target) {
+            continue;
+        }
+        match find_orphaned_entries(
+            configuration,
+            &deployment_accessor,
+            deployment_configuration,
+        )
+        .await
+        {
+            Ok(orphaned_entries) => {
+                for orphaned_entry in orphaned_entries {
+                    warn!(
+                        "[{}] --| Orphaned Entry       : {}",
+                        profile,
+                        orphaned_entry.display()
+                    );
+                }
+            }
+            Err(err) => error!("[{}] Unable to scan for orphaned entries: {err:?}", profile),
+        }
+    }
+}
+
+/// Prints the targets of the current release link and the slot links of the given profile.
+///
+/// # Arguments
+/// * `deployment_accessor` - The accessor for deployments stored on the disk.
+/// * `deployment_configuration` - The profile to print the release links of.
+async fn inspect_release_links(
+    deployment_accessor: &DeploymentAccessor,
+    deployment_configuration: &DeploymentConfiguration,
+) {
+    let mut release_links = vec![(
+        "current".to_string(),
+        deployment_accessor.get_current_release_directory(deployment_configuration),
+    )];
+    for slot in &deployment_configuration.slots {
+        release_links.push((
+            format!("slot {}", slot.name),
+            deployment_accessor.get_slot_release_directory(deployment_configuration, slot),
+        ));
+    }
+    for (link_name, link_path) in release_links {
+        let link_target = match fs::read_link(&link_path).await {
+            Ok(link_target) => link_target.display().to_string(),
+            Err(_) => "not set".to_string(),
+        };
+        info!(
+            "[{}] --| Link {:<16}: {} -> {}",
+            deployment_configuration.id,
+            link_name,
+            link_path.display(),
+            link_target
+        );
+    }
+}
@@ -0,0 +1,318 @@
+/*
+ * This file is part of easydep, licensed under the MIT License (MIT).
+ *
+ * Copyright (c) 2024 easybill GmbH
+ *
+ * Permission is hereby granted, free of charge, to any person obtaining a copy
+ * of this software and associated documentation files (the "Software"), to deal
+ * in the Software without restriction, including without limitation the rights
+ * to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+ * copies of the Software, and to permit persons to whom the Software is
+ * furnished to do so, subject to the following conditions:
+ *
+ * The above copyright notice and this permission notice shall be included in all
+ * copies or substantial portions of the Software.
+ *
+ * THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+ * IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+ * FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+ * AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+ * LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+ * OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+ * SOFTWARE.
+ */
+
+use std::path::{Path, PathBuf};
+use std::process::Stdio;
+use std::time::Duration;
+
+use anyhow::{bail, Context};
+use octocrab::models::repos::Release;
+use serde::Deserialize;
+use tokio::fs;
+use tokio::process::Command;
+use tokio::sync::mpsc::Sender;
+use tokio::time::sleep;
+use tonic::Status;
+
+use crate::capability::DeploymentRequirements;
+use crate::config::DeploymentConfiguration;
+use crate::easydep::{Action, ActionStatus, ExecutedActionEntry, LogEntry, LogType};
+use crate::executor::command_runner::CommandRunner;
+use crate::process_streamer::ProcessStreamer;
+
+/// The path of the manifest file, relative to the root of the deployed repository.
+const MANIFEST_FILE_PATH: &str = ".easydep/manifest.toml";
+
+/// The deployment manifest that can be provided by the deployed repository to declare how it should be deployed.
+#[derive(Deserialize, Clone, Debug, Default)]
+pub(crate) struct DeploymentManifest {
+    /// The minimum version of the easydep server that is required to deploy the repository.
+    pub minimum_easydep_version: Option<String>,
+    /// The capabilities that the easydep server must support to deploy the repository.
+    #[serde(default)]
+    pub required_capabilities: Vec<String>,
+    /// The names of the environment variables that must be provided to the lifecycle scripts.
+    #[serde(default)]
+    pub required_env: Vec<String>,
+    /// The commands to execute in the deployment directory after the deployment was published.
+    #[serde(default)]
+    pub warmup_commands: Vec<String>,
+    /// The health check to execute after the warmup commands were executed.
+    pub health_check: Option<ManifestHealthCheck>,
+}
+
+/// The health check that is declared in the deployment manifest.
+#[derive(Deserialize, Clone, Debug)]
+pub(crate) struct ManifestHealthCheck {
+    /// The url that must respond with a successful status code for the deployment to be considered healthy.
+    pub url: String,
+    /// The maximum amount of attempts to reach the url.
+    #[serde(default = "default_health_check_attempts")]
+    pub attempts: u32,
+    /// The seconds to wait between two attempts.
+    #[serde(default = "default_health_check_interval_seconds")]
+    pub interval_seconds: u64,
+    /// The seconds after which a single attempt times out.
+    #[serde(default = "default_health_check_timeout_seconds")]
+    pub timeout_seconds: u64,
+}
+
+/// Loads the deployment manifest from the given deployment directory.
+///
+/// # Arguments
+/// * `deployment_directory` - The directory in which the deployment is stored.
+///
+/// # Returns
+/// * `Option<DeploymentManifest>` - The parsed manifest, `None` if the repository does not provide a manifest.
+pub async fn load_manifest(
+    deployment_directory: &Path,
+) -> anyhow::Result<Option<DeploymentManifest>> {
+    let manifest_path = deployment_directory.join(MANIFEST_FILE_PATH);
+    if !fs::try_exists(&manifest_path).await? {
+        return Ok(None);
+    }
+
+    let manifest_content = fs::read_to_string(&manifest_path)
+        .await
+        .with_context(|| format!("unable to read manifest {manifest_path:?}"))?;
+    let manifest = toml::from_str::<DeploymentManifest>(&manifest_content)
+        .with_context(|| format!("unable to parse manifest {manifest_path:?}"))?;
+    Ok(Some(manifest))
+}
+
+/// Validates that the given manifest can be honored by this server for the given deployment configuration.
+///
+/// # Arguments
+/// * `manifest` - The manifest to validate.
+/// * `deployment_configuration` - The deployment profile configuration for the current deployment.
+pub fn validate_manifest(
+    manifest: &DeploymentManifest,
+    deployment_configuration: &DeploymentConfiguration,
+) -> anyhow::Result<()> {
+    let requirements = DeploymentRequirements {
+        minimum_easydep_version: manifest.minimum_easydep_version.clone(),
+        required_capabilities: manifest.required_capabilities.clone(),
+    };
+    requirements.check()?;
+
+    let missing_env = manifest
+        .required_env
+        .iter()
+        .filter(|env_name| {
+            let provided_as_secret = deployment_configuration
+                .secrets
+                .iter()
+                .any(|secret| secret.env.as_ref() == Some(env_name));
+            !provided_as_secret && std::env::var_os(env_name).is_none()
+        })
+        .cloned()
+        .collect::<Vec<String>>();
+    if !missing_env.is_empty() {
+        bail!(
+            "manifest requires the environment variables {} which are not provided",
+            missing_env.join(", ")
+        );
+    }
+
+    Ok(())
+}
+
+/// Executes the warmup commands and the health check declared in the manifest of the given deployment directory.
+/// Both steps are skipped if the deployed repository does not provide a manifest.
+///
+/// # Arguments
+/// * `release` - The release that was published.
+/// * `deployment_directory` - The directory in which the deployment is stored.
+/// * `command_runner` - The runner to spawn the warmup commands with.
+/// * `output_sender` - The sender to which log line output should be sent.
+///
+/// # Returns
+/// * `bool` - `true` if all warmup commands and the health check completed successfully, `false` otherwise.
+pub async fn execute_manifest_post_publish(
+    release: &Release,
+    deployment_directory: &PathBuf,
+    command_runner: &dyn CommandRunner,
+    output_sender: &Sender<Result<ExecutedActionEntry, Status>>,
+) -> bool {
+    let manifest = match load_manifest(deployment_directory).await {
+        Ok(Some(manifest)) => manifest,
+        Ok(None) => return true,
+        Err(err) => {
+            let error_message = format!("unable to load deployment manifest: {err:?}");
+            output_sender
+                .send(Err(Status::internal(error_message)))
+                .await
+                .ok();
+            return false;
+        }
+    };
+
+    // execute the warmup commands in the declared order, stop at the first failing command
+    for warmup_command in &manifest.warmup_commands {
+        let mut command = Command::new("bash");
+        command
+            .arg("-c")
+            .arg(warmup_command)
+            .current_dir(deployment_directory)
+            .stderr(Stdio::piped())
+            .stdout(Stdio::piped());
+        match command_runner.spawn(&mut command) {
+            Ok(warmup_process) => {
+                let mut process_streamer = ProcessStreamer::new(
+                    Action::Warmup,
+                    release.id.0,
+                    warmup_process,
+                    output_sender.clone(),
+                );
+                if process_streamer.await_child_and_stream().await.is_err() {
+                    return false;
+                }
+            }
+            Err(err) => {
+                let error_message = format!("unable to spawn warmup command: {err}");
+                output_sender
+                    .send(Err(Status::internal(error_message)))
+                    .await
+                    .ok();
+                return false;
+            }
+        }
+    }
+
+    // execute the declared health check
+    if let Some(health_check) = &manifest.health_check {
+        send_health_check_entry(release, ActionStatus::Started, None, output_sender).await;
+        let healthy = execute_health_check(release, health_check, output_sender).await;
+        let action_status = if healthy {
+            ActionStatus::CompletedSuccess
+        } else {
+            ActionStatus::CompletedFailure
+        };
+        send_health_check_entry(release, action_status, None, output_sender).await;
+        return healthy;
+    }
+
+    true
+}
+
+/// Calls the health check url until it responds successfully or the configured attempts are exhausted.
+///
+/// # Arguments
+/// * `release` - The release that was published.
+/// * `health_check` - The health check to execute.
+/// * `output_sender` - The sender to which log line output should be sent.
+async fn execute_health_check(
+    release: &Release,
+    health_check: &ManifestHealthCheck,
+    output_sender: &Sender<Result<ExecutedActionEntry, Status>>,
+) -> bool {
+    let http_client = reqwest::Client::new();
+    for attempt in 1..=health_check.attempts {
+        let request = http_client
+            .get(&health_check.url)
+            .timeout(Duration::from_secs(health_check.timeout_seconds));
+        let (stream_type, log_line, healthy) = match request.send().await {
+            Ok(response) => (
+                if response.status().is_success() {
+                    LogType::Stdout
+                } else {
+                    LogType::Stderr
+                },
+                format!(
+                    "Health check {} responded with {} (attempt {}/{})",
+                    health_check.url,
+                    response.status(),
+                    attempt,
+                    health_check.attempts
+                ),
+                response.status().is_success(),
+            ),
+            Err(err) => (
+                LogType::Stderr,
+                format!(
+                    "Unable to reach health check {}: {} (attempt {}/{})",
+                    health_check.url, err, attempt, health_check.attempts
+                ),
+                false,
+            ),
+        };
+
+        let log_entry = LogEntry {
+            stream_type: stream_type.into(),
+            content: log_line,
+        };
+        send_health_check_entry(
+            release,
+            ActionStatus::Running,
+            Some(log_entry),
+            output_sender,
+        )
+        .await;
+        if healthy {
+            return true;
+        }
+
+        if attempt < health_check.attempts {
+            sleep(Duration::from_secs(health_check.interval_seconds)).await;
+        }
+    }
+
+    false
+}
+
+/// Sends an executed action entry for the health check action to the given sender.
+///
+/// # Arguments
+/// * `release` - The release that was published.
+/// * `action_status` - The status of the health check action.
+/// * `log_entry` - The log entry to attach to the entry, if any.
+/// * `output_sender` - The sender to which the entry should be sent.
+async fn send_health_check_entry(
+    release: &Release,
+    action_status: ActionStatus,
+    log_entry: Option<LogEntry>,
+    output_sender: &Sender<Result<ExecutedActionEntry, Status>>,
+) {
+    let action_entry = ExecutedActionEntry {
+        release_id: release.id.0,
+        current_action: Action::HealthCheck.into(),
+        action_status: action_status.into(),
+        action_log_entry: log_entry,
+        progress_percent: None,
+        phase: None,
+    };
+    output_sender.send(Ok(action_entry)).await.ok();
+}
+
+fn default_health_check_attempts() -> u32 {
+    10
+}
+
+fn default_health_check_interval_seconds() -> u64 {
+    3
+}
+
+fn default_health_check_timeout_seconds() -> u64 {
+    5
+}
@@ -22,8 +22,31 @@
  * SOFTWARE.
  */
 
+pub(crate) mod action_cancellation;
+pub(crate) mod action_supervisor;
+pub(crate) mod action_watchdog;
+pub(crate) mod automatic_deployment_executor;
+pub(crate) mod branch_tracking_executor;
+pub(crate) mod build_executor;
+pub(crate) mod command_runner;
+#[cfg(feature = "concurrency-simulation")]
+pub(crate) mod concurrency_simulation;
 pub(crate) mod deploy_delete_excutor;
+pub(crate) mod deploy_dry_run_executor;
 pub(crate) mod deploy_executor;
 pub(crate) mod deploy_init_executor;
 pub(crate) mod deploy_publish_executor;
+pub(crate) mod exec_command_executor;
+pub(crate) mod file_permissions_executor;
+pub(crate) mod git_command;
+pub(crate) mod inspect_executor;
+pub(crate) mod manifest_executor;
+pub(crate) mod orphan_cleanup_executor;
+pub(crate) mod process_priority;
+pub(crate) mod publish_hook_executor;
+pub(crate) mod release_adoption_executor;
+pub(crate) mod release_prune_executor;
+pub(crate) mod repository_cache_executor;
 pub(crate) mod script_executor;
+pub(crate) mod self_test_executor;
+pub(crate) mod self_update_executor;
@@ -0,0 +1,267 @@
+/*
+ * This file is part of easydep, licensed under the MIT License (MIT).
+ *
+ * Copyright (c) 2024 easybill GmbH
+ *
+ * Permission is hereby granted, free of charge, to any person obtaining a copy
+ * of this software and associated documentation files (the "Software"), to deal
+ * in the Software without restriction, including without limitation the rights
+ * to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+ * copies of the Software, and to permit persons to whom the Software is
+ * furnished to do so, subject to the following conditions:
+ *
+ * The above copyright notice and this permission notice shall be included in all
+ * copies or substantial portions of the Software.
+ *
+ * THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+ * IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+ * FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+ * AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+ * LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+ * OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+ * SOFTWARE.
+ */
+
+use std::collections::HashSet;
+use std::path::PathBuf;
+use std::time::Duration;
+
+use log::{error, info, warn};
+use tokio::fs;
+
+use crate::accessor::configuration_accessor::ConfigurationAccessor;
+use crate::accessor::deploy_action_accessor::{CurrentAction, DeploymentStatusAccessor};
+use crate::accessor::deployment_accessor::DeploymentAccessor;
+use crate::config::{Configuration, DeploymentConfiguration, OrphanCleanupConfiguration};
+
+/// Starts the task that periodically scans the release directories for orphaned entries and removes or reports them.
+///
+/// # Arguments
+/// * `configuration_accessor` - The accessor for the current server configuration.
+/// * `cleanup_configuration` - The configuration of the cleanup task.
+/// * `deployment_accessor` - The accessor for deployments stored on the disk.
+/// * `deployment_status_accessor` - The accessor for the action that is currently being executed.
+pub fn start_orphan_cleanup_task(
+    configuration_accessor: ConfigurationAccessor,
+    cleanup_configuration: OrphanCleanupConfiguration,
+    deployment_accessor: DeploymentAccessor,
+    deployment_status_accessor: DeploymentStatusAccessor,
+) {
+    let cleanup_interval = Duration::from_secs(cleanup_configuration.interval_seconds);
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval(cleanup_interval);
+        loop {
+            interval.tick().await;
+            let global_configuration = configuration_accessor.get_configuration().await;
+            cleanup_orphaned_entries(
+                &global_configuration,
+                &cleanup_configuration,
+                &deployment_accessor,
+                &deployment_status_accessor,
+            )
+            .await;
+        }
+    });
+}
+
+/// Scans the release directories of all deployment targets for entries that are neither the current release, a slot
+/// release, a retained release nor an active deployment. The scan is skipped while an action is being executed, and no
+/// action can be started while the orphaned entries of a target are being removed.
+///
+/// # Arguments
+/// * `global_configuration` - The server configuration.
+/// * `cleanup_configuration` - The configuration of the cleanup task.
+/// * `deployment_accessor` - The accessor for deployments stored on the disk.
+/// * `deployment_status_accessor` - The accessor for the action that is currently being executed.
+async fn cleanup_orphaned_entries(
+    global_configuration: &Configuration,
+    cleanup_configuration: &OrphanCleanupConfiguration,
+    deployment_accessor: &DeploymentAccessor,
+    deployment_status_accessor: &DeploymentStatusAccessor,
+) {
+    // multiple profiles can share the same target, each target only needs to be scanned once
+    let mut scanned_targets = HashSet::new();
+    for deployment_configuration in global_configuration.get_deployment_configurations() {
+        if !scanned_targets.insert(&deployment_configuration.target) {
+            continue;
+        }
+
+        // never touch the release directories while an action is running as entries might be in use, the action is
+        // locked until the entries of the target were removed to prevent an action from starting in between
+        let current_action = deployment_status_accessor.lock_action().await;
+        if !matches!(*current_action, CurrentAction::Idle) {
+            info!("Skipping orphaned release cleanup as an action is currently being executed");
+            return;
+        }
+
+        let orphaned_entries = match find_orphaned_entries(
+            global_configuration,
+            deployment_accessor,
+            deployment_configuration,
+        )
+        .await
+        {
+            Ok(orphaned_entries) => orphaned_entries,
+            Err(err) => {
+                error!(
+                    "Unable to scan release directory of target {}: {err:?}",
+                    deployment_configuration.target
+                );
+                continue;
+            }
+        };
+        for entry in orphaned_entries {
+            if cleanup_configuration.remove_orphans {
+                info!("Removing orphaned release directory entry {:?}", entry);
+                let remove_result = if entry.is_dir() {
+                    fs::remove_dir_all(&entry).await
+                } else {
+                    fs::remove_file(&entry).await
+                };
+                if let Err(err) = remove_result {
+                    error!("Unable to remove orphaned entry {:?}: {}", entry, err);
+                }
+            } else {
+                warn!("Detected orphaned release directory entry {:?}", entry);
+            }
+        }
+    }
+}
+
+/// Finds the entries in the releases directory of the target of the given profile that are neither the current release,
+/// the release of a slot of a profile deploying to the target nor a retained release. This method does not check if an
+/// action is currently being executed.
+///
+/// # Arguments
+/// * `global_configuration` - The server configuration.
+/// * `deployment_accessor` - The accessor for deployments stored on the disk.
+/// * `deployment_configuration` - The profile of which the target should be scanned.
+///
+/// # Returns
+/// * `anyhow::Result<Vec<PathBuf>>` - The orphaned entries, `Err` if the releases directory cannot be read.
+pub async fn find_orphaned_entries(
+    global_configuration: &Configuration,
+    deployment_accessor: &DeploymentAccessor,
+    deployment_configuration: &DeploymentConfiguration,
+) -> anyhow::Result<Vec<PathBuf>> {
+    // collect the entries that are still tracked: the current release, the slot releases & the retained releases
+    let mut tracked_entries = HashSet::<PathBuf>::new();
+    let mut release_links =
+        vec![deployment_accessor.get_current_release_directory(deployment_configuration)];
+    for target_configuration in global_configuration
+        .get_deployment_configurations()
+        .iter()
+        .filter(|target_configuration| {
+            target_configuration.target == deployment_configuration.target
+        })
+    {
+        for slot in &target_configuration.slots {
+            release_links
+                .push(deployment_accessor.get_slot_release_directory(target_configuration, slot));
+        }
+    }
+    for release_link in release_links {
+        if let Ok(release_link_target) = fs::canonicalize(&release_link).await {
+            tracked_entries.insert(release_link_target);
+        }
+    }
+    let release_directories = deployment_accessor
+        .get_release_directories_for_profile(deployment_configuration)
+        .await?;
+    let retained_releases = global_configuration.retained_releases as usize;
+    for (release_directory, _) in release_directories.into_iter().take(retained_releases) {
+        if let Ok(release_directory) = fs::canonicalize(&release_directory).await {
+            tracked_entries.insert(release_directory);
+        }
+    }
+
+    // all entries that are not tracked are orphaned
+    let mut orphaned_entries = Vec::new();
+    for entry in deployment_accessor
+        .get_releases_directory_entries(deployment_configuration)
+        .await?
+    {
+        let canonical_entry = fs::canonicalize(&entry).await.unwrap_or(entry.clone());
+        if !tracked_entries.contains(&canonical_entry) {
+            orphaned_entries.push(entry);
+        }
+    }
+    Ok(orphaned_entries)
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use std::os::unix::fs::symlink;
+
+    use crate::accessor::deployment_accessor::DeploymentAccessor;
+    use crate::config::Configuration;
+
+    use super::find_orphaned_entries;
+
+    #[tokio::test]
+    async fn find_orphaned_entries_keeps_slot_releases() {
+        let base_directory =
+            std::env::temp_dir().join(format!("easydep-test-orphans-{}", std::process::id()));
+        std::fs::remove_dir_all(&base_directory).ok();
+        let releases_directory = base_directory.join("releases").join("test");
+        for release_directory_name in ["1-v1", "2-v2", "3-v3"] {
+            std::fs::create_dir_all(releases_directory.join(release_directory_name)).unwrap();
+        }
+        symlink(
+            releases_directory.join("3-v3"),
+            base_directory.join("current-test"),
+        )
+        .unwrap();
+        symlink(
+            releases_directory.join("3-v3"),
+            base_directory.join("current-test-blue"),
+        )
+        .unwrap();
+        symlink(
+            releases_directory.join("1-v1"),
+            base_directory.join("current-test-green"),
+        )
+        .unwrap();
+        let configuration = Configuration::parse(&format!(
+            r#"
+            bind_host = "127.0.0.1:6666"
+            base_directory = "{}"
+            github_app_id = 1
+            github_app_pem_key_path = "/dev/null"
+            retained_releases = 1
+
+            [[deployment_configs]]
+            id = "test"
+            target = "test"
+            source_repo_owner = "easybill"
+            source_repo_name = "easydep"
+            allowed_repo_branches = []
+            denied_repo_branches = []
+            extended_script_configurations = []
+            symlinks = []
+
+            [[deployment_configs.slots]]
+            name = "blue"
+
+            [[deployment_configs.slots]]
+            name = "green"
+            "#,
+            base_directory.display()
+        ))
+        .unwrap();
+        let deployment_configuration = configuration
+            .get_deployment_configuration(&"test".to_string())
+            .unwrap();
+
+        let orphaned_entries = find_orphaned_entries(
+            &configuration,
+            &DeploymentAccessor::new(&configuration),
+            &deployment_configuration,
+        )
+        .await
+        .unwrap();
+
+        assert_eq!(orphaned_entries, [releases_directory.join("2-v2")]);
+        std::fs::remove_dir_all(&base_directory).ok();
+    }
+}
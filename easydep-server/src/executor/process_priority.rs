@@ -0,0 +1,132 @@
+/*
+ * This file is part of easydep, licensed under the MIT License (MIT).
+ *
+ * Copyright (c) 2024 easybill GmbH
+ *
+ * Permission is hereby granted, free of charge, to any person obtaining a copy
+ * of this software and associated documentation files (the "Software"), to deal
+ * in the Software without restriction, including without limitation the rights
+ * to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+ * copies of the Software, and to permit persons to whom the Software is
+ * furnished to do so, subject to the following conditions:
+ *
+ * The above copyright notice and this permission notice shall be included in all
+ * copies or substantial portions of the Software.
+ *
+ * THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+ * IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+ * FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+ * AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+ * LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+ * OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+ * SOFTWARE.
+ */
+
+#[cfg(unix)]
+use std::io;
+
+use tokio::process::Command;
+
+#[cfg(unix)]
+use crate::config::IoPriorityClass;
+use crate::config::ProcessPriorityConfiguration;
+
+/// The shift of the io scheduling class in the io priority value passed to `ioprio_set`.
+#[cfg(target_os = "linux")]
+const IOPRIO_CLASS_SHIFT: libc::c_int = 13;
+/// The `ioprio_set` target type to set the io priority of a single process.
+#[cfg(target_os = "linux")]
+const IOPRIO_WHO_PROCESS: libc::c_int = 1;
+/// The io scheduling class in which processes get io time based on their priority.
+#[cfg(unix)]
+const IOPRIO_CLASS_BE: libc::c_int = 2;
+/// The io scheduling class in which processes only get io time when no other process needs it.
+#[cfg(unix)]
+const IOPRIO_CLASS_IDLE: libc::c_int = 3;
+/// The io priority within the best effort class that is used by the kernel if no priority is set explicitly.
+#[cfg(unix)]
+const DEFAULT_BEST_EFFORT_IO_PRIORITY: u8 = 4;
+
+/// Applies the given process priorities to the given command, the priorities are set in the spawned process before the
+/// executable is started. Nothing is done if no priorities are configured.
+///
+/// # Arguments
+/// * `command` - The command to apply the priorities to.
+/// * `process_priority` - The priorities to apply, if configured for the deployment configuration.
+#[cfg(unix)]
+pub(crate) fn apply_process_priority(
+    command: &mut Command,
+    process_priority: Option<&ProcessPriorityConfiguration>,
+) {
+    let Some(process_priority) = process_priority else {
+        return;
+    };
+
+    let nice = process_priority.nice;
+    let io_priority_value = process_priority.io_class.map(|io_class| match io_class {
+        IoPriorityClass::BestEffort => {
+            let io_priority = process_priority
+                .io_priority
+                .unwrap_or(DEFAULT_BEST_EFFORT_IO_PRIORITY);
+            (IOPRIO_CLASS_BE, io_priority as libc::c_int)
+        }
+        IoPriorityClass::Idle => (IOPRIO_CLASS_IDLE, 0),
+    });
+
+    // SAFETY: the closure only issues the setpriority and ioprio_set syscalls, which are async-signal-safe
+    unsafe {
+        command.pre_exec(move || {
+            if let Some(nice) = nice {
+                if libc::setpriority(libc::PRIO_PROCESS, 0, nice) == -1 {
+                    return Err(io::Error::last_os_error());
+                }
+            }
+            if let Some((io_class, io_priority)) = io_priority_value {
+                set_io_priority(io_class, io_priority)?;
+            }
+            Ok(())
+        });
+    }
+}
+
+/// Process priorities are only supported on unix, they are ignored on other systems.
+///
+/// # Arguments
+/// * `command` - The command to apply the priorities to.
+/// * `process_priority` - The priorities to apply, if configured for the deployment configuration.
+#[cfg(not(unix))]
+pub(crate) fn apply_process_priority(
+    _command: &mut Command,
+    _process_priority: Option<&ProcessPriorityConfiguration>,
+) {
+}
+
+/// Sets the io priority of the current process to the given class and priority.
+///
+/// # Arguments
+/// * `io_class` - The io scheduling class to use.
+/// * `io_priority` - The priority within the io scheduling class.
+#[cfg(target_os = "linux")]
+fn set_io_priority(io_class: libc::c_int, io_priority: libc::c_int) -> io::Result<()> {
+    let io_priority_value = (io_class << IOPRIO_CLASS_SHIFT) | io_priority;
+    // SAFETY: ioprio_set takes three integer arguments and does not access any memory of the process
+    let result = unsafe {
+        libc::syscall(
+            libc::SYS_ioprio_set,
+            IOPRIO_WHO_PROCESS,
+            0,
+            io_priority_value,
+        )
+    };
+    if result == -1 {
+        Err(io::Error::last_os_error())
+    } else {
+        Ok(())
+    }
+}
+
+/// Io priorities are only supported on Linux, they are ignored on other unix systems.
+#[cfg(all(unix, not(target_os = "linux")))]
+fn set_io_priority(_io_class: libc::c_int, _io_priority: libc::c_int) -> io::Result<()> {
+    Ok(())
+}
@@ -0,0 +1,255 @@
+/*
+ * This file is part of easydep, licensed under the MIT License (MIT).
+ *
+ * Copyright (c) 2024 easybill GmbH
+ *
+ * Permission is hereby granted, free of charge, to any person obtaining a copy
+ * of this software and associated documentation files (the "Software"), to deal
+ * in the Software without restriction, including without limitation the rights
+ * to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+ * copies of the Software, and to permit persons to whom the Software is
+ * furnished to do so, subject to the following conditions:
+ *
+ * The above copyright notice and this permission notice shall be included in all
+ * copies or substantial portions of the Software.
+ *
+ * THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+ * IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+ * FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+ * AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+ * LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+ * OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+ * SOFTWARE.
+ */
+
+use std::time::Duration;
+
+use anyhow::bail;
+use octocrab::models::repos::Release;
+use reqwest::Method;
+use tokio::sync::mpsc::Sender;
+use tokio::time::sleep;
+use tonic::Status;
+
+use crate::config::{DeploymentConfiguration, PublishHookConfiguration};
+use crate::easydep::{Action, ActionStatus, ExecutedActionEntry, LogEntry, LogType};
+
+/// Executes all publish hooks that are configured for the given deployment configuration. The hooks are executed in
+/// the configured order, a failing hook does not prevent the execution of the following hooks.
+///
+/// # Arguments
+/// * `release` - The release that was published.
+/// * `deployment_configuration` - The deployment profile configuration for the current deployment.
+/// * `output_sender` - The sender to which log line output should be sent.
+pub async fn execute_publish_hooks(
+    release: &Release,
+    deployment_configuration: &DeploymentConfiguration,
+    output_sender: &Sender<Result<ExecutedActionEntry, Status>>,
+) {
+    if deployment_configuration.publish_hooks.is_empty() {
+        return;
+    }
+
+    let http_client = reqwest::Client::new();
+    for publish_hook in &deployment_configuration.publish_hooks {
+        send_hook_entry(release, ActionStatus::Started, None, output_sender).await;
+        let action_status = match execute_publish_hook(
+            &http_client,
+            release,
+            deployment_configuration,
+            publish_hook,
+            output_sender,
+        )
+        .await
+        {
+            Ok(_) => ActionStatus::CompletedSuccess,
+            Err(err) => {
+                let log_line = format!("Publish hook {} failed: {}", publish_hook.name, err);
+                send_hook_log_line(release, LogType::Stderr, log_line, output_sender).await;
+                ActionStatus::CompletedFailure
+            }
+        };
+        send_hook_entry(release, action_status, None, output_sender).await;
+    }
+}
+
+/// Executes a single publish hook, retrying the call until it succeeds or the configured attempts are exhausted.
+///
+/// # Arguments
+/// * `http_client` - The http client to execute the call with.
+/// * `release` - The release that was published.
+/// * `deployment_configuration` - The deployment profile configuration for the current deployment.
+/// * `publish_hook` - The configuration of the hook to execute.
+/// * `output_sender` - The sender to which log line output should be sent.
+async fn execute_publish_hook(
+    http_client: &reqwest::Client,
+    release: &Release,
+    deployment_configuration: &DeploymentConfiguration,
+    publish_hook: &PublishHookConfiguration,
+    output_sender: &Sender<Result<ExecutedActionEntry, Status>>,
+) -> anyhow::Result<()> {
+    let method = Method::from_bytes(publish_hook.method.as_bytes())?;
+    let url = render_template(
+        &publish_hook.url,
+        release,
+        deployment_configuration,
+        str::to_owned,
+    );
+    // only the host of the url is logged, as hook urls often contain a secret (for example slack webhooks)
+    let url_host = reqwest::Url::parse(&url)
+        .ok()
+        .and_then(|parsed_url| parsed_url.host_str().map(str::to_owned))
+        .unwrap_or_else(|| "invalid url".to_string());
+    let max_attempts = publish_hook.max_attempts;
+    for attempt in 1..=max_attempts {
+        let log_line = format!(
+            "Calling publish hook {} ({} {}), attempt {}/{}",
+            publish_hook.name, method, url_host, attempt, max_attempts
+        );
+        send_hook_log_line(release, LogType::Stdout, log_line, output_sender).await;
+
+        let mut request = http_client
+            .request(method.clone(), &url)
+            .timeout(Duration::from_secs(publish_hook.timeout_seconds));
+        for (header_name, header_value) in &publish_hook.headers {
+            let header_value = render_template(
+                header_value,
+                release,
+                deployment_configuration,
+                str::to_owned,
+            );
+            request = request.header(header_name, header_value);
+        }
+        if let Some(body) = &publish_hook.body {
+            request = request.body(render_template(
+                body,
+                release,
+                deployment_configuration,
+                encode_json_string_content,
+            ));
+        }
+
+        match request.send().await {
+            Ok(response) if response.status().is_success() => {
+                let log_line = format!("Server responded with {}", response.status());
+                send_hook_log_line(release, LogType::Stdout, log_line, output_sender).await;
+                return Ok(());
+            }
+            Ok(response) => {
+                let log_line = format!("Server responded with {}", response.status());
+                send_hook_log_line(release, LogType::Stderr, log_line, output_sender).await;
+            }
+            Err(err) => {
+                let log_line = format!("Unable to call publish hook: {}", err.without_url());
+                send_hook_log_line(release, LogType::Stderr, log_line, output_sender).await;
+            }
+        }
+
+        if attempt < max_attempts {
+            sleep(Duration::from_secs(publish_hook.retry_delay_seconds)).await;
+        }
+    }
+
+    bail!("all {} attempts failed", max_attempts)
+}
+
+/// Replaces the release placeholders in the given template.
+///
+/// # Arguments
+/// * `template` - The template in which the placeholders should be replaced.
+/// * `release` - The release that was published.
+/// * `deployment_configuration` - The deployment profile configuration for the current deployment.
+/// * `encode_value` - The function to encode the placeholder values with before inserting them into the template.
+fn render_template(
+    template: &str,
+    release: &Release,
+    deployment_configuration: &DeploymentConfiguration,
+    encode_value: fn(&str) -> String,
+) -> String {
+    template
+        .replace("{{release_id}}", &release.id.0.to_string())
+        .replace("{{release_tag}}", &encode_value(&release.tag_name))
+        .replace(
+            "{{release_name}}",
+            &encode_value(release.name.as_deref().unwrap_or_default()),
+        )
+        .replace(
+            "{{release_commitish}}",
+            &encode_value(&release.target_commitish),
+        )
+        .replace("{{profile}}", &encode_value(&deployment_configuration.id))
+        .replace(
+            "{{target}}",
+            &encode_value(&deployment_configuration.target),
+        )
+        .replace(
+            "{{slot}}",
+            &encode_value(
+                deployment_configuration
+                    .deployment_slot
+                    .as_ref()
+                    .map(|deployment_slot| deployment_slot.name.as_str())
+                    .unwrap_or_default(),
+            ),
+        )
+}
+
+/// Escapes the given value so that it can be inserted into a json string in the hook body, preventing that quotes or
+/// control characters in a release name produce an invalid body or inject additional fields.
+///
+/// # Arguments
+/// * `value` - The value to escape.
+fn encode_json_string_content(value: &str) -> String {
+    let json_string = serde_json::Value::from(value).to_string();
+    json_string[1..json_string.len() - 1].to_string()
+}
+
+/// Sends a log line associated with the publish hook action to the given sender.
+///
+/// # Arguments
+/// * `release` - The release that was published.
+/// * `stream_type` - The log stream type to send the log line as.
+/// * `content` - The content of the log line.
+/// * `output_sender` - The sender to which the log line should be sent.
+async fn send_hook_log_line(
+    release: &Release,
+    stream_type: LogType,
+    content: String,
+    output_sender: &Sender<Result<ExecutedActionEntry, Status>>,
+) {
+    let log_entry = LogEntry {
+        stream_type: stream_type.into(),
+        content,
+    };
+    send_hook_entry(
+        release,
+        ActionStatus::Running,
+        Some(log_entry),
+        output_sender,
+    )
+    .await;
+}
+
+/// Sends an executed action entry for the publish hook action to the given sender.
+///
+/// # Arguments
+/// * `release` - The release that was published.
+/// * `action_status` - The status of the publish hook action.
+/// * `log_entry` - The log entry to attach to the entry, if any.
+/// * `output_sender` - The sender to which the entry should be sent.
+async fn send_hook_entry(
+    release: &Release,
+    action_status: ActionStatus,
+    log_entry: Option<LogEntry>,
+    output_sender: &Sender<Result<ExecutedActionEntry, Status>>,
+) {
+    let action_entry = ExecutedActionEntry {
+        release_id: release.id.0,
+        current_action: Action::PublishHook.into(),
+        action_status: action_status.into(),
+        action_log_entry: log_entry,
+        progress_percent: None,
+        phase: None,
+    };
+    output_sender.send(Ok(action_entry)).await.ok();
+}
@@ -0,0 +1,117 @@
+/*
+ * This file is part of easydep, licensed under the MIT License (MIT).
+ *
+ * Copyright (c) 2024 easybill GmbH
+ *
+ * Permission is hereby granted, free of charge, to any person obtaining a copy
+ * of this software and associated documentation files (the "Software"), to deal
+ * in the Software without restriction, including without limitation the rights
+ * to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+ * copies of the Software, and to permit persons to whom the Software is
+ * furnished to do so, subject to the following conditions:
+ *
+ * The above copyright notice and this permission notice shall be included in all
+ * copies or substantial portions of the Software.
+ *
+ * THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+ * IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+ * FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+ * AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+ * LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+ * OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+ * SOFTWARE.
+ */
+
+use std::path::{Path, PathBuf};
+
+use anyhow::{bail, Context};
+use symlink::{remove_symlink_dir, symlink_dir};
+use tokio::fs;
+
+use crate::accessor::deployment_accessor::DeploymentAccessor;
+use crate::config::DeploymentConfiguration;
+
+/// Moves the given existing directory into the releases directory of the given profile, making it a known release
+/// that can be published, rolled back to and cleaned up like a deployed release. The directory must be located on the
+/// same filesystem as the base directory, as it is moved instead of copied.
+///
+/// # Arguments
+/// * `deployment_accessor` - The accessor for deployments stored on the disk.
+/// * `deployment_configuration` - The deployment profile configuration to adopt the release into.
+/// * `release_id` - The id of the adopted release.
+/// * `release_tag` - The tag name of the adopted release.
+/// * `source_directory` - The absolute path of the directory to adopt.
+///
+/// # Returns
+/// The path of the release directory to which the adopted directory was moved.
+pub async fn adopt_release_directory(
+    deployment_accessor: &DeploymentAccessor,
+    deployment_configuration: &DeploymentConfiguration,
+    release_id: &u64,
+    release_tag: &str,
+    source_directory: &Path,
+) -> anyhow::Result<PathBuf> {
+    if !source_directory.is_absolute() {
+        bail!("source directory must be an absolute path");
+    }
+    let source_metadata = fs::symlink_metadata(source_directory)
+        .await
+        .with_context(|| format!("unable to read source directory {:?}", source_directory))?;
+    if !source_metadata.is_dir() {
+        bail!("source {:?} is not a directory", source_directory);
+    }
+
+    // the source must not be a directory that is already managed by easydep
+    let releases_directory = deployment_accessor.get_releases_directory(deployment_configuration);
+    let canonical_source_directory = fs::canonicalize(source_directory).await?;
+    if let Ok(canonical_releases_directory) = fs::canonicalize(&releases_directory).await {
+        if canonical_source_directory.starts_with(canonical_releases_directory) {
+            bail!("source directory is already located in the releases directory");
+        }
+    }
+    if deployment_accessor.has_release_directory(deployment_configuration, release_id) {
+        bail!(
+            "a release directory for release {} exists already",
+            release_id
+        );
+    }
+
+    let release_directory = deployment_accessor.get_release_directory(
+        deployment_configuration,
+        release_id,
+        release_tag,
+    );
+    deployment_accessor
+        .create_directory(&releases_directory)
+        .await
+        .context("unable to create releases directory")?;
+    if let Err(err) = fs::rename(source_directory, &release_directory).await {
+        // the error code of moves across file systems is only known on unix, the generic error is reported elsewhere
+        #[cfg(unix)]
+        if err.raw_os_error() == Some(libc::EXDEV) {
+            bail!("source directory must be located on the same filesystem as the base directory");
+        }
+        bail!("unable to move source directory into the releases directory: {err}");
+    }
+
+    Ok(release_directory)
+}
+
+/// Points the current release symlink of the given profile to the given adopted release directory.
+///
+/// # Arguments
+/// * `deployment_accessor` - The accessor for deployments stored on the disk.
+/// * `deployment_configuration` - The deployment profile configuration in which the release was adopted.
+/// * `release_directory` - The directory of the adopted release.
+pub fn publish_adopted_release(
+    deployment_accessor: &DeploymentAccessor,
+    deployment_configuration: &DeploymentConfiguration,
+    release_directory: &Path,
+) -> anyhow::Result<()> {
+    let published_directory =
+        deployment_accessor.get_current_release_directory(deployment_configuration);
+    remove_symlink_dir(&published_directory).ok();
+    symlink_dir(release_directory, published_directory)
+        .context("unable to symlink release directory")?;
+    Ok(())
+}
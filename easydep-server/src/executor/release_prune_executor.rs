@@ -0,0 +1,228 @@
+/*
+ * This file is part of easydep, licensed under the MIT License (MIT).
+ *
+ * Copyright (c) 2024 easybill GmbH
+ *
+ * Permission is hereby granted, free of charge, to any person obtaining a copy
+ * of this software and associated documentation files (the "Software"), to deal
+ * in the Software without restriction, including without limitation the rights
+ * to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+ * copies of the Software, and to permit persons to whom the Software is
+ * furnished to do so, subject to the following conditions:
+ *
+ * The above copyright notice and this permission notice shall be included in all
+ * copies or substantial portions of the Software.
+ *
+ * THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+ * IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+ * FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+ * AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+ * LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+ * OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+ * SOFTWARE.
+ */
+
+use std::collections::HashSet;
+use std::path::PathBuf;
+
+use log::{error, info};
+use tokio::fs;
+use tokio::sync::mpsc::Sender;
+use tonic::Status;
+
+use crate::accessor::deployment_accessor::DeploymentAccessor;
+use crate::config::DeploymentConfiguration;
+use crate::easydep::{Action, ActionStatus, ExecutedActionEntry, LogEntry, LogType};
+
+/// The limits down to which the stored releases of a profile are pruned.
+#[derive(Clone, Debug)]
+pub(crate) struct PruneLimits {
+    /// The maximum amount of releases that should remain stored.
+    pub keep_releases: Option<usize>,
+    /// The maximum disk usage (in bytes) of all stored releases.
+    pub max_disk_usage_bytes: Option<u64>,
+}
+
+/// Removes the oldest releases of the given profile until the given limits are met. The published release and the
+/// releases deployed in a slot are never removed, even if the limits cannot be met without removing them. Each removed
+/// release is reported to the given output sender.
+///
+/// # Arguments
+/// * `prune_limits` - The limits down to which the stored releases are pruned.
+/// * `deployment_accessor` - The accessor for deployments stored on the disk.
+/// * `deployment_configuration` - The profile whose stored releases should be pruned.
+/// * `output_sender` - The sender to which the removed releases should be reported.
+///
+/// # Returns
+/// * `bool` - `true` if all releases that exceeded the limits were removed, `false` otherwise.
+pub async fn prune_releases(
+    prune_limits: &PruneLimits,
+    deployment_accessor: &DeploymentAccessor,
+    deployment_configuration: &DeploymentConfiguration,
+    output_sender: &Sender<Result<ExecutedActionEntry, Status>>,
+) -> bool {
+    send_prune_entry(0, ActionStatus::Started, None, output_sender).await;
+    let release_directories = match deployment_accessor
+        .get_release_directories_for_profile(deployment_configuration)
+        .await
+    {
+        Ok(release_directories) => release_directories,
+        Err(err) => {
+            let error_message = format!("unable to read the stored releases: {err}");
+            send_prune_entry(
+                0,
+                ActionStatus::CompletedFailure,
+                Some((LogType::Stderr, error_message)),
+                output_sender,
+            )
+            .await;
+            return false;
+        }
+    };
+
+    // the disk usage is only needed (and expensive to compute) if a disk usage limit was requested
+    let mut release_disk_usages = Vec::with_capacity(release_directories.len());
+    for (release_directory, _) in &release_directories {
+        let disk_usage = match prune_limits.max_disk_usage_bytes {
+            Some(_) => deployment_accessor
+                .get_release_disk_usage(release_directory)
+                .await
+                .unwrap_or_else(|err| {
+                    error!("Unable to get disk usage of {release_directory:?}: {err:?}");
+                    0
+                }),
+            None => 0,
+        };
+        release_disk_usages.push(disk_usage);
+    }
+
+    let protected_directories = collect_protected_directories(
+        &release_directories,
+        deployment_accessor,
+        deployment_configuration,
+    )
+    .await;
+    let mut stored_releases = release_directories.len();
+    let mut stored_disk_usage: u64 = release_disk_usages.iter().sum();
+    let mut removal_failed = false;
+    let mut removed_releases = 0;
+
+    // the release directories are sorted newest first, the oldest ones are removed first
+    for ((release_directory, release_id), disk_usage) in
+        release_directories.iter().zip(release_disk_usages).rev()
+    {
+        let exceeds_release_count = prune_limits
+            .keep_releases
+            .is_some_and(|keep_releases| stored_releases > keep_releases);
+        let exceeds_disk_usage = prune_limits
+            .max_disk_usage_bytes
+            .is_some_and(|max_disk_usage_bytes| stored_disk_usage > max_disk_usage_bytes);
+        if !exceeds_release_count && !exceeds_disk_usage {
+            break;
+        }
+        if protected_directories.contains(release_directory) {
+            continue;
+        }
+
+        info!("Pruning stored release {release_id} in {release_directory:?}");
+        match fs::remove_dir_all(release_directory).await {
+            Ok(_) => {
+                stored_releases -= 1;
+                stored_disk_usage = stored_disk_usage.saturating_sub(disk_usage);
+                removed_releases += 1;
+                let message = format!("Removed release {release_id} ({release_directory:?})");
+                send_prune_entry(
+                    *release_id,
+                    ActionStatus::Running,
+                    Some((LogType::Stdout, message)),
+                    output_sender,
+                )
+                .await;
+            }
+            Err(err) => {
+                error!("Unable to prune release directory {release_directory:?}: {err:?}");
+                removal_failed = true;
+                let error_message = format!("Unable to remove release {release_id}: {err}");
+                send_prune_entry(
+                    *release_id,
+                    ActionStatus::Running,
+                    Some((LogType::Stderr, error_message)),
+                    output_sender,
+                )
+                .await;
+            }
+        }
+    }
+
+    let summary = format!(
+        "Removed {removed_releases} release(s), {stored_releases} release(s) remain stored"
+    );
+    let (final_status, stream_type) = if removal_failed {
+        (ActionStatus::CompletedFailure, LogType::Stderr)
+    } else {
+        (ActionStatus::CompletedSuccess, LogType::Stdout)
+    };
+    send_prune_entry(0, final_status, Some((stream_type, summary)), output_sender).await;
+    !removal_failed
+}
+
+/// Collects the release directories of the given profile that must not be pruned: the published release and the
+/// releases that are deployed in a slot.
+///
+/// # Arguments
+/// * `release_directories` - The release directories that are stored for the profile.
+/// * `deployment_accessor` - The accessor for deployments stored on the disk.
+/// * `deployment_configuration` - The profile whose stored releases are pruned.
+async fn collect_protected_directories(
+    release_directories: &[(PathBuf, u64)],
+    deployment_accessor: &DeploymentAccessor,
+    deployment_configuration: &DeploymentConfiguration,
+) -> HashSet<PathBuf> {
+    let mut protected_directories = HashSet::new();
+    let published_release_directory =
+        deployment_accessor.get_published_release_directory(deployment_configuration);
+    for (release_directory, _) in release_directories {
+        let canonical_directory = fs::canonicalize(release_directory)
+            .await
+            .unwrap_or_else(|_| release_directory.clone());
+        let is_published = published_release_directory
+            .as_ref()
+            .is_some_and(|(published_directory, _)| *published_directory == canonical_directory);
+        let is_slot_deployment = deployment_accessor
+            .find_release_slot(deployment_configuration, &canonical_directory)
+            .is_some();
+        if is_published || is_slot_deployment {
+            protected_directories.insert(release_directory.clone());
+        }
+    }
+    protected_directories
+}
+
+/// Sends an entry of the release prune action to the given output sender.
+///
+/// # Arguments
+/// * `release_id` - The id of the release that the entry is about, zero if the entry is about the whole action.
+/// * `action_status` - The status of the release prune action.
+/// * `log_entry` - The stream and content of the log entry to send with the status, if any.
+/// * `output_sender` - The sender to which the entry should be sent.
+async fn send_prune_entry(
+    release_id: u64,
+    action_status: ActionStatus,
+    log_entry: Option<(LogType, String)>,
+    output_sender: &Sender<Result<ExecutedActionEntry, Status>>,
+) {
+    output_sender
+        .send(Ok(ExecutedActionEntry {
+            release_id,
+            current_action: i32::from(Action::ReleasePrune),
+            action_status: i32::from(action_status),
+            action_log_entry: log_entry.map(|(stream_type, content)| LogEntry {
+                stream_type: i32::from(stream_type),
+                content,
+            }),
+            progress_percent: None,
+            phase: None,
+        }))
+        .await
+        .ok();
+}
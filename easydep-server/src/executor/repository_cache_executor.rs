@@ -0,0 +1,178 @@
+/*
+ * This file is part of easydep, licensed under the MIT License (MIT).
+ *
+ * Copyright (c) 2024 easybill GmbH
+ *
+ * Permission is hereby granted, free of charge, to any person obtaining a copy
+ * of this software and associated documentation files (the "Software"), to deal
+ * in the Software without restriction, including without limitation the rights
+ * to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+ * copies of the Software, and to permit persons to whom the Software is
+ * furnished to do so, subject to the following conditions:
+ *
+ * The above copyright notice and this permission notice shall be included in all
+ * copies or substantial portions of the Software.
+ *
+ * THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+ * IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+ * FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+ * AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+ * LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+ * OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+ * SOFTWARE.
+ */
+
+use std::collections::HashSet;
+use std::process::Stdio;
+use std::time::Duration;
+
+use anyhow::bail;
+use log::{error, info};
+
+use crate::accessor::configuration_accessor::ConfigurationAccessor;
+use crate::accessor::deploy_action_accessor::{CurrentAction, DeploymentStatusAccessor};
+use crate::accessor::deployment_accessor::DeploymentAccessor;
+use crate::config::{
+    Configuration, DeploymentConfiguration, RepositoryCacheMaintenanceConfiguration,
+};
+use crate::executor::git_command::new_git_command;
+use crate::executor::process_priority::apply_process_priority;
+
+/// Starts the task that periodically runs the git maintenance of the repository caches and reports their size.
+///
+/// # Arguments
+/// * `configuration_accessor` - The accessor for the current server configuration.
+/// * `maintenance_configuration` - The configuration of the maintenance task.
+/// * `deployment_accessor` - The accessor for deployments stored on the disk.
+/// * `deployment_status_accessor` - The accessor for the action that is currently being executed.
+pub fn start_repository_cache_maintenance_task(
+    configuration_accessor: ConfigurationAccessor,
+    maintenance_configuration: RepositoryCacheMaintenanceConfiguration,
+    deployment_accessor: DeploymentAccessor,
+    deployment_status_accessor: DeploymentStatusAccessor,
+) {
+    let maintenance_interval = Duration::from_secs(maintenance_configuration.interval_seconds);
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval(maintenance_interval);
+        loop {
+            interval.tick().await;
+            let global_configuration = configuration_accessor.get_configuration().await;
+            maintain_repository_caches(
+                &global_configuration,
+                &deployment_accessor,
+                &deployment_status_accessor,
+            )
+            .await;
+        }
+    });
+}
+
+/// Runs the git maintenance of the repository caches of all profiles using one, logging the disk usage of each cache
+/// before and after the maintenance. The maintenance is skipped while an action is being executed.
+///
+/// # Arguments
+/// * `global_configuration` - The server configuration.
+/// * `deployment_accessor` - The accessor for deployments stored on the disk.
+/// * `deployment_status_accessor` - The accessor for the action that is currently being executed.
+async fn maintain_repository_caches(
+    global_configuration: &Configuration,
+    deployment_accessor: &DeploymentAccessor,
+    deployment_status_accessor: &DeploymentStatusAccessor,
+) {
+    // multiple profiles can deploy from the same repository, each cache only needs to be maintained once
+    let mut maintained_caches = HashSet::new();
+    for deployment_configuration in global_configuration.get_deployment_configurations() {
+        let cache_directory =
+            deployment_accessor.get_repository_cache_directory(deployment_configuration);
+        if !deployment_configuration.repository_cache
+            || !cache_directory.is_dir()
+            || !maintained_caches.insert(cache_directory.clone())
+        {
+            continue;
+        }
+
+        // releases are cloned from the caches, don't compete with a running action for the repository
+        if !matches!(
+            deployment_status_accessor.get_action().await,
+            CurrentAction::Idle
+        ) {
+            info!("Skipping repository cache maintenance as an action is currently being executed");
+            return;
+        }
+
+        let disk_usage_before = deployment_accessor
+            .get_repository_cache_disk_usage(deployment_configuration)
+            .await
+            .unwrap_or_default();
+        if let Err(err) = run_repository_cache_gc(
+            global_configuration,
+            deployment_accessor,
+            deployment_configuration,
+        )
+        .await
+        {
+            error!(
+                "Unable to run maintenance of repository cache {}: {err:?}",
+                cache_directory.display()
+            );
+            continue;
+        }
+        match deployment_accessor
+            .get_repository_cache_disk_usage(deployment_configuration)
+            .await
+        {
+            Ok(disk_usage) => info!(
+                "Repository cache {} uses {} bytes after maintenance ({} bytes before)",
+                cache_directory.display(),
+                disk_usage,
+                disk_usage_before
+            ),
+            Err(err) => error!(
+                "Unable to get disk usage of repository cache {}: {err:?}",
+                cache_directory.display()
+            ),
+        }
+    }
+}
+
+/// Runs `git gc` in the repository cache of the given profile, which packs the cached objects and removes the objects
+/// that are no longer referenced, for example by branches or tags that were deleted in the source repository. Objects
+/// are only removed after the default grace period of git, a fetch running concurrently can never lose objects.
+///
+/// # Arguments
+/// * `global_configuration` - The server configuration.
+/// * `deployment_accessor` - The accessor for deployments stored on the disk.
+/// * `deployment_configuration` - The profile whose repository cache should be maintained.
+async fn run_repository_cache_gc(
+    global_configuration: &Configuration,
+    deployment_accessor: &DeploymentAccessor,
+    deployment_configuration: &DeploymentConfiguration,
+) -> anyhow::Result<()> {
+    let git_configuration = global_configuration
+        .git
+        .clone()
+        .unwrap_or_default()
+        .merged_with(deployment_configuration.git.as_ref());
+    let mut gc_command = new_git_command(&git_configuration);
+    gc_command
+        .arg("--git-dir")
+        .arg(deployment_accessor.get_repository_cache_directory(deployment_configuration))
+        .arg("gc")
+        .arg("--quiet")
+        .stdin(Stdio::null())
+        .stdout(Stdio::null())
+        .stderr(Stdio::piped());
+    apply_process_priority(
+        &mut gc_command,
+        deployment_configuration.process_priority.as_ref(),
+    );
+    let gc_output = gc_command.output().await?;
+    if !gc_output.status.success() {
+        bail!(
+            "git gc exited with {}: {}",
+            gc_output.status,
+            String::from_utf8_lossy(&gc_output.stderr).trim()
+        )
+    }
+    Ok(())
+}
@@ -22,18 +22,25 @@
  * SOFTWARE.
  */
 
-use std::path::PathBuf;
+use std::io::ErrorKind;
+use std::path::{Path, PathBuf};
 use std::process::Stdio;
 
-use anyhow::bail;
+use anyhow::{bail, Context};
 use octocrab::models::repos::Release;
+use secrecy::ExposeSecret;
 use tokio::fs;
+use tokio::fs::OpenOptions;
+use tokio::io::AsyncWriteExt;
 use tokio::process::Command;
 use tokio::sync::mpsc::Sender;
 use tonic::Status;
 
+use crate::accessor::secret_accessor::ResolvedSecret;
 use crate::config::DeploymentConfiguration;
-use crate::easydep::{Action, ExecutedActionEntry};
+use crate::easydep::{Action, ActionStatus, ExecutedActionEntry, LogEntry, LogType};
+use crate::executor::command_runner::ExecutionEnvironment;
+use crate::executor::process_priority::apply_process_priority;
 use crate::process_streamer::ProcessStreamer;
 
 /// The type of scripts that can be executed.
@@ -44,155 +51,819 @@ pub(crate) enum ScriptType {
     Publish,
     /// The script executed when deleting a deployment.
     Delete,
+    /// The script executed to build a deployment.
+    Build,
+    /// The script executed when a deployment is cancelled while being prepared.
+    Cancel,
+}
+
+/// The status of a single lifecycle script after the scripts of a profile were executed.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub(crate) enum ScriptStatus {
+    /// The script does not exist in the deployment and was skipped.
+    Missing,
+    /// The script was executed and completed successfully.
+    Succeeded,
+    /// The script could not be executed or did not complete successfully.
+    Failed,
+}
+
+/// The result of a single lifecycle script.
+#[derive(Clone, Debug)]
+pub(crate) struct ScriptResult {
+    /// The path of the script, relative to the deployment directory.
+    pub script_path: String,
+    /// The status of the script.
+    pub status: ScriptStatus,
+}
+
+/// The result of executing the lifecycle scripts of a profile.
+#[derive(Clone, Debug, Default)]
+pub(crate) struct ScriptExecutionResult {
+    /// The results of the scripts in execution order. Scripts following a failed script are not executed and not
+    /// included in the results.
+    pub script_results: Vec<ScriptResult>,
+    /// Indicates if the scripts could not be executed at all, for example because the secrets could not be resolved.
+    pub setup_failed: bool,
+}
+
+impl ScriptExecutionResult {
+    /// Checks if the scripts could be executed and none of them failed.
+    pub fn is_success(&self) -> bool {
+        !self.setup_failed
+            && self
+                .script_results
+                .iter()
+                .all(|script_result| script_result.status != ScriptStatus::Failed)
+    }
+
+    /// Summarizes the statuses of the scripts that exist in the deployment, returns `None` if no script was executed.
+    pub fn summarize(&self) -> Option<String> {
+        let executed_scripts: Vec<String> = self
+            .script_results
+            .iter()
+            .filter(|script_result| script_result.status != ScriptStatus::Missing)
+            .map(|script_result| {
+                let status = match script_result.status {
+                    ScriptStatus::Succeeded => "succeeded",
+                    _ => "failed",
+                };
+                format!("{} {}", script_result.script_path, status)
+            })
+            .collect();
+        if executed_scripts.is_empty() {
+            None
+        } else {
+            Some(executed_scripts.join(", "))
+        }
+    }
 }
 
 /// Executes the given scripts for the given release profile.
-/// This includes the scripts that are coming from extended configurations.
+/// This includes the scripts that are coming from extended configurations. The execution stops at the first script
+/// that fails.
 ///
 /// # Arguments
 /// * `release` - The release that is currently being deployed.
 /// * `script_type` - The type of scripts to execute.
 /// * `deployment_directory` - The directory in which the deployment is stored.
 /// * `deployment_configuration` - The deployment profile configuration for the current deployment.
+/// * `execution_environment` - The environment to resolve the secrets and run the scripts with.
 /// * `output_sender` - The sender to which log line output should be sent.
+///
+/// # Returns
+/// * `ScriptExecutionResult` - The statuses of the executed scripts.
 pub async fn execute_scripts(
     release: &Release,
     script_type: &ScriptType,
     deployment_directory: &PathBuf,
     deployment_configuration: &DeploymentConfiguration,
+    execution_environment: &ExecutionEnvironment,
     output_sender: &Sender<Result<ExecutedActionEntry, Status>>,
-) {
-    let (script_action, script_action_name) = match script_type {
-        ScriptType::Init => (Action::InitScript, "init".to_string()),
-        ScriptType::Publish => (Action::FinishScript, "publish".to_string()),
-        ScriptType::Delete => (Action::DeleteScript, "delete".to_string()),
-    };
+) -> ScriptExecutionResult {
+    let (script_action, script_action_name) = get_script_action(script_type);
 
-    // execute the extended scripts first
-    let extended_configurations = &deployment_configuration.extended_script_configurations;
-    for extended_configuration in extended_configurations {
-        let script_path = get_script_path(extended_configuration, &script_action_name);
-        if check_and_execute_script(
-            release,
-            &script_path,
-            &script_action,
-            deployment_directory,
-            output_sender,
-        )
+    // all scripts, including the ones of extended configurations, use the script timeout of the executed configuration
+    let execution_environment =
+        &execution_environment.with_script_timeout_of(deployment_configuration);
+
+    // resolve the secrets right before executing the scripts and render the requested secret files
+    let setup_failed = ScriptExecutionResult {
+        script_results: Vec::new(),
+        setup_failed: true,
+    };
+    let resolved_secrets = match execution_environment
+        .secret_accessor
+        .resolve_secrets(&deployment_configuration.secrets)
         .await
-        .is_err()
+    {
+        Ok(resolved_secrets) => resolved_secrets,
+        Err(err) => {
+            let error_message = format!("unable to resolve secrets for scripts: {err:?}");
+            output_sender
+                .send(Err(Status::internal(error_message)))
+                .await
+                .ok();
+            return setup_failed;
+        }
+    };
+    let profile_environment = match resolve_profile_environment(deployment_configuration).await {
+        Ok(profile_environment) => profile_environment,
+        Err(err) => {
+            let error_message = format!("unable to resolve environment for scripts: {err:?}");
+            output_sender
+                .send(Err(Status::internal(error_message)))
+                .await
+                .ok();
+            return setup_failed;
+        }
+    };
+    if let Err(err) = render_secret_files(&resolved_secrets, deployment_directory).await {
+        let error_message = format!("unable to render secret files: {err:?}");
+        output_sender
+            .send(Err(Status::internal(error_message)))
+            .await
+            .ok();
+        return setup_failed;
+    }
+
+    // execute the extended scripts first, followed by the main script
+    let mut script_results = Vec::new();
+    for script_path in get_script_paths(script_type, deployment_configuration) {
+        let status = if fs::try_exists(deployment_directory.join(&script_path))
+            .await
+            .unwrap_or(false)
         {
-            return;
+            let script_command = build_script_command(
+                release,
+                &script_path,
+                deployment_directory,
+                deployment_configuration,
+                execution_environment,
+                &profile_environment,
+                &resolved_secrets,
+            );
+            execute_script(
+                release,
+                &script_path,
+                &script_action,
+                script_command,
+                &resolved_secrets,
+                execution_environment,
+                output_sender,
+            )
+            .await
+        } else {
+            ScriptStatus::Missing
+        };
+        script_results.push(ScriptResult {
+            script_path,
+            status,
+        });
+        if status == ScriptStatus::Failed {
+            break;
         }
     }
 
-    // execute the main script
-    let main_script_path = get_script_path(&deployment_configuration.id, &script_action_name);
-    check_and_execute_script(
-        release,
-        &main_script_path,
-        &script_action,
-        deployment_directory,
-        output_sender,
-    )
-    .await
-    .ok();
+    // report the statuses of the executed scripts
+    let execution_result = ScriptExecutionResult {
+        script_results,
+        setup_failed: false,
+    };
+    if let Some(summary) = execution_result.summarize() {
+        let action_entry = ExecutedActionEntry {
+            release_id: release.id.0,
+            current_action: script_action.into(),
+            action_status: ActionStatus::Running.into(),
+            action_log_entry: Some(LogEntry {
+                stream_type: LogType::Stdout.into(),
+                content: format!("Executed {} scripts: {}", script_action_name, summary),
+            }),
+            progress_percent: None,
+            phase: None,
+        };
+        output_sender.send(Ok(action_entry)).await.ok();
+    }
+    execution_result
 }
 
-/// Checks if the script at the given file path exists and executes it if that is the case.
+/// Ensures that the scripts directory of the given deployment profile and its init script exist in the given
+/// deployment directory, returning an error describing the missing file if that is not the case.
 ///
 /// # Arguments
-/// * `release` - The release that is currently being deployed.
-/// * `script_path` - The path where the script file should be located.
-/// * `script_action` - The script action that is represented by the script.
 /// * `deployment_directory` - The directory in which the deployment is stored.
-/// * `output_sender` - The sender to which log line output should be sent.
-async fn check_and_execute_script(
-    release: &Release,
-    script_path: &String,
-    script_action: &Action,
-    deployment_directory: &PathBuf,
-    output_sender: &Sender<Result<ExecutedActionEntry, Status>>,
+/// * `deployment_configuration` - The deployment profile configuration for the current deployment.
+pub async fn ensure_scripts_present(
+    deployment_directory: &Path,
+    deployment_configuration: &DeploymentConfiguration,
 ) -> anyhow::Result<()> {
-    let full_script_path = deployment_directory.join(script_path);
-    if let Ok(script_file_exists) = fs::try_exists(full_script_path).await {
-        if script_file_exists {
-            if let Err(err) = execute_script(
-                release,
-                script_path,
-                script_action,
-                deployment_directory,
-                output_sender,
-            )
-            .await
-            {
-                let error_message = format!("unable to execute script at {script_path:?}: {err}");
-                output_sender
-                    .send(Err(Status::internal(error_message)))
-                    .await
-                    .ok();
-                bail!("issue executing script")
+    let scripts_directory = format!(".easydep/{}", deployment_configuration.id);
+    if !fs::metadata(deployment_directory.join(&scripts_directory))
+        .await
+        .is_ok_and(|metadata| metadata.is_dir())
+    {
+        bail!("scripts directory {scripts_directory} does not exist")
+    }
+
+    let init_script_path = get_script_path(&deployment_configuration.id, &"init".to_string());
+    if !fs::try_exists(deployment_directory.join(&init_script_path)).await? {
+        bail!("init script {init_script_path} does not exist")
+    }
+    Ok(())
+}
+
+/// Checks the syntax of all scripts of the given deployment profile (including the ones of extended configurations)
+/// that exist in the given deployment directory using `bash -n`, without executing them. All scripts are checked, the
+/// returned error lists the syntax errors of every script that failed the check.
+///
+/// # Arguments
+/// * `deployment_directory` - The directory in which the deployment is stored.
+/// * `deployment_configuration` - The deployment profile configuration for the current deployment.
+/// * `execution_environment` - The environment to run the syntax checks with.
+///
+/// # Returns
+/// * `usize` - The amount of scripts whose syntax was checked.
+pub async fn check_scripts_syntax(
+    deployment_directory: &Path,
+    deployment_configuration: &DeploymentConfiguration,
+    execution_environment: &ExecutionEnvironment,
+) -> anyhow::Result<usize> {
+    let script_types = [
+        ScriptType::Init,
+        ScriptType::Build,
+        ScriptType::Publish,
+        ScriptType::Delete,
+        ScriptType::Cancel,
+    ];
+    let mut checked_scripts = 0;
+    let mut syntax_errors = Vec::new();
+    for script_type in &script_types {
+        for script_path in get_script_paths(script_type, deployment_configuration) {
+            if !fs::try_exists(deployment_directory.join(&script_path)).await? {
+                continue;
+            }
+            let mut syntax_check_command = Command::new("bash");
+            syntax_check_command
+                .arg("-n")
+                .arg(&script_path)
+                .current_dir(deployment_directory);
+            let output = execution_environment
+                .command_runner
+                .output(&mut syntax_check_command)
+                .await
+                .with_context(|| format!("unable to check syntax of {script_path}"))?;
+            checked_scripts += 1;
+            if !output.status.success() {
+                let stderr_output = String::from_utf8_lossy(&output.stderr);
+                syntax_errors.push(stderr_output.trim().to_string());
             }
         }
     }
-    Ok(())
+    if !syntax_errors.is_empty() {
+        bail!(
+            "{} of {} scripts contain syntax errors:\n{}",
+            syntax_errors.len(),
+            checked_scripts,
+            syntax_errors.join("\n")
+        )
+    }
+    Ok(checked_scripts)
 }
 
-/// Executes a script. This method assumes that the script file exists. `bash` is used to execute the script.
+/// Executes the given script command using the given command runner and streams the output of the script.
 ///
 /// # Arguments
 /// * `release` - The release that is currently being deployed.
-/// * `script_path` - The path where the script file should be located.
+/// * `script_path` - The path where the script file is located.
 /// * `script_action` - The script action that is represented by the script.
-/// * `deployment_directory` - The directory in which the deployment is stored.
+/// * `script_command` - The command that executes the script.
+/// * `resolved_secrets` - The secrets provided to the script, secrets are redacted from the log output.
+/// * `execution_environment` - The environment to spawn the script command in, the script is terminated when the
+///   action of the environment is cancelled.
 /// * `output_sender` - The sender to which log line output should be sent.
 async fn execute_script(
     release: &Release,
     script_path: &String,
     script_action: &Action,
-    deployment_directory: &PathBuf,
+    mut script_command: Command,
+    resolved_secrets: &[ResolvedSecret],
+    execution_environment: &ExecutionEnvironment,
     output_sender: &Sender<Result<ExecutedActionEntry, Status>>,
-) -> anyhow::Result<()> {
-    match Command::new("bash")
-        .arg(script_path)
-        .current_dir(deployment_directory)
-        .stderr(Stdio::piped())
-        .stdout(Stdio::piped())
-        .spawn()
+) -> ScriptStatus {
+    let execution_result = match execution_environment
+        .command_runner
+        .spawn(&mut script_command)
     {
         Ok(script_process) => {
+            let redacted_values = resolved_secrets
+                .iter()
+                .map(|resolved_secret| resolved_secret.value.expose_secret().clone())
+                .collect();
             let mut process_streamer = ProcessStreamer::new(
                 *script_action,
                 release.id.0,
                 script_process,
                 output_sender.clone(),
+            )
+            .with_redacted_values(redacted_values)
+            .with_cancellation(execution_environment.cancellation.clone())
+            .with_timeout(
+                execution_environment.script_timeout,
+                execution_environment.script_kill_grace_period,
             );
-            if let Err(err) = process_streamer.await_child_and_stream().await {
-                let error_message = format!("issue while waiting for script to complete: {err}");
-                output_sender
-                    .send(Err(Status::internal(error_message)))
-                    .await
-                    .ok();
-                Err(err)
-            } else {
-                Ok(())
-            }
+            process_streamer
+                .await_child_and_stream()
+                .await
+                .context("issue while waiting for script to complete")
         }
+        Err(err) => Err(err).context("unable to spawn process to execute lifecycle script"),
+    };
+
+    match execution_result {
+        Ok(()) => ScriptStatus::Succeeded,
         Err(err) => {
-            let error_message =
-                format!("unable to spawn process to execute lifecycle script: {err}");
+            let error_message = format!("unable to execute script at {script_path:?}: {err:#}");
             output_sender
                 .send(Err(Status::internal(error_message)))
                 .await
                 .ok();
-            Err(err.into())
+            ScriptStatus::Failed
         }
     }
 }
 
+/// Builds the command to execute the given script with `bash`, either directly on the host or in the container
+/// configured for the deployment profile. The information about the deployed release is provided to the script as
+/// environment variables. This method assumes that the script file exists.
+///
+/// # Arguments
+/// * `release` - The release that is currently being deployed.
+/// * `script_path` - The path where the script file is located.
+/// * `deployment_directory` - The directory in which the deployment is stored.
+/// * `deployment_configuration` - The deployment profile configuration for the current deployment.
+/// * `execution_environment` - The environment providing the directory of the previously published release.
+/// * `profile_environment` - The environment variables of the deployment configuration.
+/// * `resolved_secrets` - The secrets to provide to the script as environment variables.
+fn build_script_command(
+    release: &Release,
+    script_path: &String,
+    deployment_directory: &PathBuf,
+    deployment_configuration: &DeploymentConfiguration,
+    execution_environment: &ExecutionEnvironment,
+    profile_environment: &[(String, String)],
+    resolved_secrets: &[ResolvedSecret],
+) -> Command {
+    // scripts in a container see the deployment directory at the working directory of the container
+    let release_id = release.id.0.to_string();
+    let deploy_directory = match &deployment_configuration.container {
+        Some(container_configuration) => container_configuration.workdir.clone(),
+        None => deployment_directory.display().to_string(),
+    };
+    // the variables of the profile are applied first, the variables provided by easydep take precedence
+    let mut script_environment = profile_environment
+        .iter()
+        .map(|(env_name, env_value)| (env_name.as_str(), env_value.as_str()))
+        .collect::<Vec<_>>();
+    script_environment.extend([
+        ("EASYDEP_RELEASE_ID", release_id.as_str()),
+        ("EASYDEP_TAG_NAME", release.tag_name.as_str()),
+        ("EASYDEP_PROFILE", deployment_configuration.id.as_str()),
+        ("EASYDEP_TARGET_COMMIT", release.target_commitish.as_str()),
+        ("EASYDEP_DEPLOY_DIR", deploy_directory.as_str()),
+    ]);
+    let previous_release_directory = execution_environment
+        .previous_release_directory
+        .as_ref()
+        .map(|directory| directory.display().to_string());
+    if let Some(previous_release_directory) = &previous_release_directory {
+        script_environment.push(("EASYDEP_PREVIOUS_RELEASE_DIR", previous_release_directory));
+    }
+    let path_filters = deployment_configuration.path_filters.join(",");
+    if !path_filters.is_empty() {
+        script_environment.push(("EASYDEP_PATH_FILTERS", &path_filters));
+    }
+    if let Some(deployment_slot) = &deployment_configuration.deployment_slot {
+        script_environment.push(("EASYDEP_SLOT", &deployment_slot.name));
+        for (env_name, env_value) in &deployment_slot.env {
+            script_environment.push((env_name, env_value));
+        }
+    }
+    for resolved_secret in resolved_secrets {
+        if let Some(env_name) = &resolved_secret.reference.env {
+            script_environment.push((env_name, resolved_secret.value.expose_secret()));
+        }
+    }
+
+    let mut command = match &deployment_configuration.container {
+        Some(container_configuration) => {
+            // the environment variables are only passed by name, the runtime reads the values from its environment
+            let mut command = Command::new(&container_configuration.runtime);
+            command
+                .arg("run")
+                .arg("--rm")
+                .arg("--volume")
+                .arg(format!(
+                    "{}:{}",
+                    deployment_directory.display(),
+                    container_configuration.workdir
+                ))
+                .arg("--workdir")
+                .arg(&container_configuration.workdir);
+            for (env_name, _) in &script_environment {
+                command.arg("--env").arg(env_name);
+            }
+            command
+                .args(&container_configuration.extra_args)
+                .arg(&container_configuration.image)
+                .arg("bash")
+                .arg(script_path);
+            command
+        }
+        None => {
+            let mut command = Command::new("bash");
+            command.arg(script_path);
+            command
+        }
+    };
+    command
+        .envs(script_environment)
+        .current_dir(deployment_directory)
+        .stderr(Stdio::piped())
+        .stdout(Stdio::piped());
+    apply_process_priority(
+        &mut command,
+        deployment_configuration.process_priority.as_ref(),
+    );
+    command
+}
+
+/// Resolves the environment variables that are provided to the scripts of the given deployment configuration: the
+/// variables read from the env file of the configuration (if any), followed by the variables of its environment.
+///
+/// # Arguments
+/// * `deployment_configuration` - The deployment configuration to resolve the environment variables of.
+///
+/// # Returns
+/// * `Vec<(String, String)>` - The names and values of the environment variables, later entries take precedence.
+async fn resolve_profile_environment(
+    deployment_configuration: &DeploymentConfiguration,
+) -> anyhow::Result<Vec<(String, String)>> {
+    let mut profile_environment = Vec::new();
+    if let Some(env_file) = &deployment_configuration.env_file {
+        let env_file_content = fs::read_to_string(env_file)
+            .await
+            .with_context(|| format!("unable to read env file {}", env_file.display()))?;
+        for (line_index, line) in env_file_content.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+
+            // lines can optionally be prefixed with export, values can optionally be quoted
+            let line = line.strip_prefix("export ").unwrap_or(line);
+            let Some((env_name, env_value)) = line
+                .split_once('=')
+                .filter(|(env_name, _)| !env_name.trim().is_empty())
+            else {
+                bail!(
+                    "invalid line {} in env file {}: expected KEY=value",
+                    line_index + 1,
+                    env_file.display()
+                )
+            };
+            let env_value = env_value.trim();
+            let env_value = ['"', '\'']
+                .iter()
+                .find_map(|quote| {
+                    env_value
+                        .strip_prefix(*quote)
+                        .and_then(|value| value.strip_suffix(*quote))
+                })
+                .unwrap_or(env_value);
+            profile_environment.push((env_name.trim().to_string(), env_value.to_string()));
+        }
+    }
+
+    let mut configured_environment = deployment_configuration
+        .environment
+        .iter()
+        .map(|(env_name, env_value)| (env_name.clone(), env_value.clone()))
+        .collect::<Vec<_>>();
+    configured_environment.sort();
+    profile_environment.extend(configured_environment);
+    Ok(profile_environment)
+}
+
+/// Writes the secrets that should be provided as files into the deployment directory. On unix systems the files are
+/// created to be only readable by the owner, before the secret is written into them. Existing files at the paths of the
+/// secrets are replaced, their permissions (or link targets) are not reused.
+///
+/// # Arguments
+/// * `resolved_secrets` - The resolved secrets to render.
+/// * `deployment_directory` - The directory in which the deployment is stored.
+async fn render_secret_files(
+    resolved_secrets: &[ResolvedSecret],
+    deployment_directory: &Path,
+) -> anyhow::Result<()> {
+    for resolved_secret in resolved_secrets {
+        if let Some(file) = &resolved_secret.reference.file {
+            let secret_file_path = deployment_directory.join(file);
+            if let Some(parent) = secret_file_path.parent() {
+                fs::create_dir_all(parent).await?;
+            }
+            match fs::remove_file(&secret_file_path).await {
+                Err(err) if err.kind() != ErrorKind::NotFound => {
+                    return Err(err).with_context(|| {
+                        format!("unable to replace secret file {:?}", secret_file_path)
+                    })
+                }
+                _ => {}
+            }
+
+            let mut open_options = OpenOptions::new();
+            open_options.write(true).create_new(true);
+            #[cfg(unix)]
+            open_options.mode(0o600);
+            let mut secret_file = open_options
+                .open(&secret_file_path)
+                .await
+                .with_context(|| format!("unable to create secret file {:?}", secret_file_path))?;
+            secret_file
+                .write_all(resolved_secret.value.expose_secret().as_bytes())
+                .await
+                .with_context(|| format!("unable to write secret file {:?}", secret_file_path))?;
+            secret_file.flush().await?;
+        }
+    }
+    Ok(())
+}
+
+/// Get the action that is reported for the scripts of the given type and the name of their script files.
+///
+/// # Arguments
+/// * `script_type` - The type of scripts to get the action of.
+pub fn get_script_action(script_type: &ScriptType) -> (Action, String) {
+    match script_type {
+        ScriptType::Init => (Action::InitScript, "init".to_string()),
+        ScriptType::Publish => (Action::FinishScript, "publish".to_string()),
+        ScriptType::Delete => (Action::DeleteScript, "delete".to_string()),
+        ScriptType::Build => (Action::BuildScript, "build".to_string()),
+        ScriptType::Cancel => (Action::CancelScript, "cancel".to_string()),
+    }
+}
+
+/// Get the paths (relative to the deployment directory) of the scripts of the given type that are executed for the
+/// given profile, in execution order: the scripts of the extended configurations first, followed by the main script.
+///
+/// # Arguments
+/// * `script_type` - The type of scripts to get the paths of.
+/// * `deployment_configuration` - The deployment profile configuration to get the script paths for.
+pub fn get_script_paths(
+    script_type: &ScriptType,
+    deployment_configuration: &DeploymentConfiguration,
+) -> Vec<String> {
+    let (_, script_action_name) = get_script_action(script_type);
+    deployment_configuration
+        .extended_script_configurations
+        .iter()
+        .chain([&deployment_configuration.id])
+        .map(|script_configuration| get_script_path(script_configuration, &script_action_name))
+        .collect()
+}
+
 fn get_script_path(script_configuration: &String, script_action_name: &String) -> String {
     format!(
         ".easydep/{}/{}.sh",
         script_configuration, script_action_name
     )
 }
+
+#[cfg(test)]
+mod tests {
+    use std::path::{Path, PathBuf};
+    use std::sync::Arc;
+
+    use secrecy::SecretString;
+    use tokio::sync::mpsc::channel;
+
+    use crate::accessor::ref_deployment_accessor::RefDeployment;
+    use crate::accessor::secret_accessor::ResolvedSecret;
+    use crate::config::{Configuration, DeploymentConfiguration, SecretReference, SecretSource};
+    use crate::executor::command_runner::{
+        CommandResponse, ExecutionEnvironment, RecordingCommandRunner,
+    };
+
+    use super::{execute_scripts, render_secret_files, ScriptStatus, ScriptType};
+
+    /// Creates a deployment directory for a test containing the given scripts, removing the directory of a previous
+    /// run of the test.
+    fn create_deployment_directory(test_name: &str, script_paths: &[&str]) -> PathBuf {
+        let deployment_directory =
+            std::env::temp_dir().join(format!("easydep-test-{}-{}", test_name, std::process::id()));
+        std::fs::remove_dir_all(&deployment_directory).ok();
+        for script_path in script_paths {
+            let script_path = deployment_directory.join(script_path);
+            std::fs::create_dir_all(script_path.parent().unwrap()).unwrap();
+            std::fs::write(script_path, "").unwrap();
+        }
+        deployment_directory
+    }
+
+    /// Parses a configuration with a single `test` profile that executes the scripts of the `base` configuration first.
+    fn parse_configuration(base_directory: &Path) -> (Configuration, DeploymentConfiguration) {
+        let configuration = Configuration::parse(&format!(
+            r#"
+            bind_host = "127.0.0.1:6666"
+            base_directory = "{}"
+            github_app_id = 1
+            github_app_pem_key_path = "/dev/null"
+            retained_releases = 5
+
+            [[deployment_configs]]
+            id = "test"
+            target = "test"
+            source_repo_owner = "easybill"
+            source_repo_name = "easydep"
+            allowed_repo_branches = []
+            denied_repo_branches = []
+            extended_script_configurations = ["base"]
+            symlinks = []
+            "#,
+            base_directory.display()
+        ))
+        .unwrap();
+        let deployment_configuration = configuration
+            .get_deployment_configuration(&"test".to_string())
+            .unwrap();
+        (configuration, deployment_configuration)
+    }
+
+    #[tokio::test]
+    async fn execute_scripts_executes_extended_scripts_first() {
+        let deployment_directory = create_deployment_directory(
+            "scripts-order",
+            &[".easydep/base/init.sh", ".easydep/test/init.sh"],
+        );
+        let (configuration, deployment_configuration) = parse_configuration(&deployment_directory);
+        let release = RefDeployment {
+            release_id: 1,
+            git_ref: "v1.0.0".to_string(),
+            commit_sha: "0123456789abcdef".to_string(),
+            created_at: 0,
+        }
+        .to_release(&deployment_configuration)
+        .unwrap();
+        let command_runner = Arc::new(RecordingCommandRunner::new(|_| CommandResponse::default()));
+        let execution_environment =
+            ExecutionEnvironment::new(&configuration).with_command_runner(command_runner.clone());
+        let (output_sender, _output_receiver) = channel(100);
+
+        let execution_result = execute_scripts(
+            &release,
+            &ScriptType::Init,
+            &deployment_directory,
+            &deployment_configuration,
+            &execution_environment,
+            &output_sender,
+        )
+        .await;
+
+        assert!(execution_result.is_success());
+        let recorded_commands = command_runner.get_recorded_commands();
+        let executed_scripts: Vec<&String> = recorded_commands
+            .iter()
+            .map(|recorded_command| &recorded_command.args[0])
+            .collect();
+        assert_eq!(
+            executed_scripts,
+            [".easydep/base/init.sh", ".easydep/test/init.sh"]
+        );
+        for recorded_command in &recorded_commands {
+            assert_eq!(recorded_command.program, "bash");
+            assert_eq!(recorded_command.envs["EASYDEP_RELEASE_ID"], "1");
+            assert_eq!(recorded_command.envs["EASYDEP_TAG_NAME"], "v1.0.0");
+            assert_eq!(recorded_command.envs["EASYDEP_PROFILE"], "test");
+        }
+        std::fs::remove_dir_all(&deployment_directory).ok();
+    }
+
+    #[tokio::test]
+    async fn execute_scripts_skips_missing_scripts() {
+        let deployment_directory =
+            create_deployment_directory("scripts-missing", &[".easydep/test/init.sh"]);
+        let (configuration, deployment_configuration) = parse_configuration(&deployment_directory);
+        let release = RefDeployment {
+            release_id: 1,
+            git_ref: "v1.0.0".to_string(),
+            commit_sha: "0123456789abcdef".to_string(),
+            created_at: 0,
+        }
+        .to_release(&deployment_configuration)
+        .unwrap();
+        let command_runner = Arc::new(RecordingCommandRunner::new(|_| CommandResponse::default()));
+        let execution_environment =
+            ExecutionEnvironment::new(&configuration).with_command_runner(command_runner.clone());
+        let (output_sender, _output_receiver) = channel(100);
+
+        let execution_result = execute_scripts(
+            &release,
+            &ScriptType::Init,
+            &deployment_directory,
+            &deployment_configuration,
+            &execution_environment,
+            &output_sender,
+        )
+        .await;
+
+        assert!(execution_result.is_success());
+        let script_statuses: Vec<ScriptStatus> = execution_result
+            .script_results
+            .iter()
+            .map(|script_result| script_result.status)
+            .collect();
+        assert_eq!(
+            script_statuses,
+            [ScriptStatus::Missing, ScriptStatus::Succeeded]
+        );
+        assert_eq!(command_runner.get_recorded_commands().len(), 1);
+        std::fs::remove_dir_all(&deployment_directory).ok();
+    }
+
+    #[tokio::test]
+    async fn execute_scripts_stops_at_failed_script() {
+        let deployment_directory = create_deployment_directory(
+            "scripts-failure",
+            &[".easydep/base/init.sh", ".easydep/test/init.sh"],
+        );
+        let (configuration, deployment_configuration) = parse_configuration(&deployment_directory);
+        let release = RefDeployment {
+            release_id: 1,
+            git_ref: "v1.0.0".to_string(),
+            commit_sha: "0123456789abcdef".to_string(),
+            created_at: 0,
+        }
+        .to_release(&deployment_configuration)
+        .unwrap();
+        let command_runner = Arc::new(RecordingCommandRunner::new(|_| CommandResponse {
+            exit_code: 1,
+            stdout: "failed".to_string(),
+        }));
+        let execution_environment =
+            ExecutionEnvironment::new(&configuration).with_command_runner(command_runner.clone());
+        let (output_sender, _output_receiver) = channel(100);
+
+        let execution_result = execute_scripts(
+            &release,
+            &ScriptType::Init,
+            &deployment_directory,
+            &deployment_configuration,
+            &execution_environment,
+            &output_sender,
+        )
+        .await;
+
+        // the script of the profile is not executed after the extended script failed
+        assert!(!execution_result.is_success());
+        assert_eq!(execution_result.script_results.len(), 1);
+        assert_eq!(
+            execution_result.script_results[0].status,
+            ScriptStatus::Failed
+        );
+        assert_eq!(command_runner.get_recorded_commands().len(), 1);
+        std::fs::remove_dir_all(&deployment_directory).ok();
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn render_secret_files_replaces_existing_files_readable_by_others() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let deployment_directory = create_deployment_directory("secret-files", &[]);
+        let secret_file_path = deployment_directory.join("config/secret.txt");
+        std::fs::create_dir_all(secret_file_path.parent().unwrap()).unwrap();
+        std::fs::write(&secret_file_path, "previous content").unwrap();
+        std::fs::set_permissions(&secret_file_path, std::fs::Permissions::from_mode(0o644))
+            .unwrap();
+        let resolved_secrets = [ResolvedSecret {
+            reference: SecretReference {
+                source: SecretSource::File,
+                reference: "/dev/null".to_string(),
+                env: None,
+                file: Some("config/secret.txt".to_string()),
+            },
+            value: SecretString::from("secret".to_string()),
+        }];
+
+        render_secret_files(&resolved_secrets, &deployment_directory)
+            .await
+            .unwrap();
+
+        let secret_file_metadata = std::fs::metadata(&secret_file_path).unwrap();
+        assert_eq!(secret_file_metadata.permissions().mode() & 0o777, 0o600);
+        assert_eq!(
+            std::fs::read_to_string(&secret_file_path).unwrap(),
+            "secret"
+        );
+        std::fs::remove_dir_all(&deployment_directory).ok();
+    }
+}
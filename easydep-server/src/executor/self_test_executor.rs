@@ -0,0 +1,323 @@
+/*
+ * This file is part of easydep, licensed under the MIT License (MIT).
+ *
+ * Copyright (c) 2024 easybill GmbH
+ *
+ * Permission is hereby granted, free of charge, to any person obtaining a copy
+ * of this software and associated documentation files (the "Software"), to deal
+ * in the Software without restriction, including without limitation the rights
+ * to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+ * copies of the Software, and to permit persons to whom the Software is
+ * furnished to do so, subject to the following conditions:
+ *
+ * The above copyright notice and this permission notice shall be included in all
+ * copies or substantial portions of the Software.
+ *
+ * THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+ * IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+ * FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+ * AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+ * LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+ * OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+ * SOFTWARE.
+ */
+
+use std::collections::BTreeSet;
+use std::path::Path;
+use std::process::Stdio;
+
+use anyhow::{anyhow, bail, Context};
+use log::{error, info, warn};
+use symlink::{remove_symlink_file, symlink_file};
+use tokio::fs;
+use tokio::process::Command;
+
+use crate::accessor::github_accessor::{describe_github_error, GitHubAccessor};
+use crate::config::{Configuration, DeploymentConfiguration, DeploymentMode};
+use crate::executor::command_runner::ExecutionEnvironment;
+
+/// The name of the file that is written into the base directory to check write access.
+const SELF_TEST_FILE_NAME: &str = ".easydep-self-test";
+/// The name of the symlink that is created in the base directory to check the symlink capability.
+const SELF_TEST_LINK_NAME: &str = ".easydep-self-test-link";
+
+/// Runs the deep checks of the environment the server runs in: write access to the base directory, the capability to
+/// create symlinks, the availability of the executables used during deployments and the access to GitHub for each
+/// deployment profile. Each check is logged with its result, failed checks describe how to resolve them.
+///
+/// # Arguments
+/// * `configuration` - The validated server configuration.
+pub async fn run_self_test(configuration: &Configuration) -> anyhow::Result<()> {
+    let base_directory = Path::new(&configuration.base_directory);
+    let mut check_results = vec![
+        (
+            "base directory write access".to_string(),
+            check_write_access(base_directory).await,
+        ),
+        (
+            "symlink capability".to_string(),
+            check_symlink_capability(base_directory).await,
+        ),
+        (
+            "bash availability".to_string(),
+            check_executable("bash").await,
+        ),
+    ];
+    let deployment_configurations = configuration
+        .get_deployment_configurations()
+        .iter()
+        .filter(|config| !config.extend_only)
+        .collect::<Vec<_>>();
+
+    // the profiles can use different git executables
+    let execution_environment = ExecutionEnvironment::new(configuration);
+    let git_executables = deployment_configurations
+        .iter()
+        .filter(|config| config.mode == DeploymentMode::Git)
+        .map(|config| {
+            execution_environment
+                .resolve_git_configuration(config)
+                .executable
+                .unwrap_or_else(|| "git".to_string())
+        })
+        .collect::<BTreeSet<String>>();
+    for git_executable in git_executables {
+        let check_name = format!("{} availability", git_executable);
+        check_results.push((check_name, check_executable(&git_executable).await));
+    }
+    if deployment_configurations
+        .iter()
+        .any(|config| config.mode == DeploymentMode::Artifact)
+    {
+        check_results.push((
+            "tar availability".to_string(),
+            check_executable("tar").await,
+        ));
+    }
+
+    collect_github_checks(configuration, false, &mut check_results).await;
+    report_check_results("Self-test", check_results)
+}
+
+/// Validates the given configuration without running the server or checking the local environment, so that
+/// configuration changes can be checked before they are installed: the GitHub app key must be readable, the app must
+/// authenticate, each deployment profile must have access to its repository and the scripts directories of the git
+/// profiles must exist in the repositories. Each check is logged with its result.
+///
+/// # Arguments
+/// * `configuration` - The validated server configuration.
+pub async fn run_validation(configuration: &Configuration) -> anyhow::Result<()> {
+    let mut check_results = Vec::new();
+    collect_github_checks(configuration, true, &mut check_results).await;
+    report_check_results("Validation", check_results)
+}
+
+/// Checks the GitHub app key, the authentication of the GitHub app and the repository access of each deployment
+/// profile, optionally checking that the scripts directories of the git profiles exist in their repositories.
+///
+/// # Arguments
+/// * `configuration` - The validated server configuration.
+/// * `check_scripts` - If the scripts directories of the profiles should be checked.
+/// * `check_results` - The results to add the results of the checks to.
+async fn collect_github_checks(
+    configuration: &Configuration,
+    check_scripts: bool,
+    check_results: &mut Vec<(String, anyhow::Result<()>)>,
+) {
+    // the access of the profiles can only be checked if the app can authenticate
+    let github_accessor = match GitHubAccessor::new(configuration).await {
+        Ok(github_accessor) => github_accessor,
+        Err(err) => {
+            let err =
+                err.context("unable to load the GitHub app key, check github_app_pem_key_path");
+            check_results.push(("GitHub app key".to_string(), Err(err)));
+            return;
+        }
+    };
+    check_results.push(("GitHub app key".to_string(), Ok(())));
+    let app_name = match github_accessor.get_app_name().await {
+        Ok(app_name) => app_name,
+        Err(err) => {
+            let err = anyhow!(describe_github_error(&err));
+            check_results.push(("GitHub app".to_string(), Err(err)));
+            return;
+        }
+    };
+    check_results.push((format!("GitHub app {}", app_name), Ok(())));
+
+    let deployment_configurations = configuration
+        .get_deployment_configurations()
+        .iter()
+        .filter(|config| !config.extend_only);
+    for deployment_configuration in deployment_configurations {
+        let access_report = github_accessor
+            .check_repository_access(deployment_configuration)
+            .await;
+        let has_access = access_report.error.is_none();
+        let check_result = match access_report.error {
+            Some(error) => Err(anyhow!(error)),
+            None => Ok(()),
+        };
+        let check_name = format!(
+            "GitHub access of profile {} ({}/{})",
+            deployment_configuration.id,
+            deployment_configuration.source_repo_owner,
+            deployment_configuration.source_repo_name
+        );
+        check_results.push((check_name, check_result));
+
+        // artifact profiles deploy the scripts contained in the artifact
+        if check_scripts && has_access && deployment_configuration.mode == DeploymentMode::Git {
+            let check_name = format!("scripts of profile {}", deployment_configuration.id);
+            let check_result =
+                check_scripts_directories(&github_accessor, deployment_configuration).await;
+            check_results.push((check_name, check_result));
+        }
+    }
+}
+
+/// Checks that the scripts directories of the given git profile (including the directories of the extended script
+/// configurations) exist in its repository, on the tracked branch or the default branch. Missing directories are
+/// only an error if the profile requires scripts, as the scripts are optional otherwise.
+///
+/// # Arguments
+/// * `github_accessor` - The accessor to read the repository contents with.
+/// * `deployment_configuration` - The profile whose scripts directories should be checked.
+async fn check_scripts_directories(
+    github_accessor: &GitHubAccessor,
+    deployment_configuration: &DeploymentConfiguration,
+) -> anyhow::Result<()> {
+    let script_configurations = deployment_configuration
+        .extended_script_configurations
+        .iter()
+        .chain([&deployment_configuration.id]);
+    let git_ref = deployment_configuration.track_branch.as_deref();
+    for script_configuration in script_configurations {
+        let scripts_directory = format!(".easydep/{}", script_configuration);
+        let directory_exists = github_accessor
+            .path_exists(deployment_configuration, &scripts_directory, git_ref)
+            .await
+            .map_err(|err| anyhow!(describe_github_error(&err)))?;
+        if directory_exists {
+            continue;
+        }
+
+        let branch = git_ref.unwrap_or("the default branch");
+        if deployment_configuration.require_scripts {
+            bail!(
+                "scripts directory {} does not exist on {}, but the profile requires scripts",
+                scripts_directory,
+                branch
+            )
+        }
+        warn!(
+            "Scripts directory {} of profile {} does not exist on {}, no scripts are executed",
+            scripts_directory, deployment_configuration.id, branch
+        );
+    }
+    Ok(())
+}
+
+/// Logs the results of the given checks, failing if one of them failed.
+///
+/// # Arguments
+/// * `label` - The label of the checks that prefixes the logged results.
+/// * `check_results` - The names of the executed checks and their results.
+fn report_check_results(
+    label: &str,
+    check_results: Vec<(String, anyhow::Result<()>)>,
+) -> anyhow::Result<()> {
+    let mut failed_checks = 0;
+    for (check_name, check_result) in &check_results {
+        match check_result {
+            Ok(()) => info!("{} passed: {}", label, check_name),
+            Err(err) => {
+                failed_checks += 1;
+                error!("{} failed: {}: {err:#}", label, check_name);
+            }
+        }
+    }
+    if failed_checks > 0 {
+        bail!(
+            "{} of {} {} checks failed",
+            failed_checks,
+            check_results.len(),
+            label.to_lowercase()
+        )
+    }
+    Ok(())
+}
+
+/// Checks that files can be created, written and removed in the given directory.
+///
+/// # Arguments
+/// * `base_directory` - The directory to check the write access to.
+async fn check_write_access(base_directory: &Path) -> anyhow::Result<()> {
+    fs::create_dir_all(base_directory).await.with_context(|| {
+        format!(
+            "unable to create {:?}, check the permissions of its parent",
+            base_directory
+        )
+    })?;
+    let test_file = base_directory.join(SELF_TEST_FILE_NAME);
+    fs::write(&test_file, b"easydep").await.with_context(|| {
+        format!(
+            "unable to write into {:?}, check its owner and permissions",
+            base_directory
+        )
+    })?;
+    fs::remove_file(&test_file)
+        .await
+        .with_context(|| format!("unable to remove {:?}", test_file))?;
+    Ok(())
+}
+
+/// Checks that symlinks (which are used to publish releases) can be created and removed in the given directory.
+///
+/// # Arguments
+/// * `base_directory` - The directory to check the symlink capability in.
+async fn check_symlink_capability(base_directory: &Path) -> anyhow::Result<()> {
+    let test_file = base_directory.join(SELF_TEST_FILE_NAME);
+    let test_link = base_directory.join(SELF_TEST_LINK_NAME);
+    fs::write(&test_file, b"easydep")
+        .await
+        .with_context(|| format!("unable to write into {:?}", base_directory))?;
+    remove_symlink_file(&test_link).ok();
+    let link_result = symlink_file(&test_file, &test_link)
+        .with_context(|| {
+            format!(
+                "unable to create symlinks in {:?}, check the file system",
+                base_directory
+            )
+        })
+        .and_then(|_| {
+            remove_symlink_file(&test_link)
+                .with_context(|| format!("unable to remove {:?}", test_link))
+        });
+    fs::remove_file(&test_file).await.ok();
+    link_result
+}
+
+/// Checks that the given executable can be found and executed.
+///
+/// # Arguments
+/// * `executable` - The name of the executable to check.
+async fn check_executable(executable: &str) -> anyhow::Result<()> {
+    let status = Command::new(executable)
+        .arg("--version")
+        .stdin(Stdio::null())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .status()
+        .await
+        .with_context(|| {
+            format!(
+                "unable to execute {}, install it or add it to the PATH",
+                executable
+            )
+        })?;
+    if !status.success() {
+        bail!("{} --version exited with {}", executable, status)
+    }
+    Ok(())
+}
@@ -0,0 +1,200 @@
+/*
+ * This file is part of easydep, licensed under the MIT License (MIT).
+ *
+ * Copyright (c) 2024 easybill GmbH
+ *
+ * Permission is hereby granted, free of charge, to any person obtaining a copy
+ * of this software and associated documentation files (the "Software"), to deal
+ * in the Software without restriction, including without limitation the rights
+ * to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+ * copies of the Software, and to permit persons to whom the Software is
+ * furnished to do so, subject to the following conditions:
+ *
+ * The above copyright notice and this permission notice shall be included in all
+ * copies or substantial portions of the Software.
+ *
+ * THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+ * IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+ * FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+ * AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+ * LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+ * OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+ * SOFTWARE.
+ */
+
+use std::env;
+#[cfg(unix)]
+use std::fs::Permissions;
+#[cfg(unix)]
+use std::os::unix::fs::PermissionsExt;
+use std::path::PathBuf;
+use std::time::Duration;
+
+use anyhow::{anyhow, bail, Context};
+use log::info;
+use ring::digest::{digest, SHA256};
+use ring::signature::{UnparsedPublicKey, ED25519};
+use serde::Deserialize;
+use tokio::fs;
+
+use crate::config::SelfUpdateConfiguration;
+use crate::hex::{decode_hex, encode_hex};
+
+/// The base url of the GitHub api from which the easydep releases are resolved.
+const GITHUB_API_URL: &str = "https://api.github.com";
+/// The user agent sent to GitHub, which rejects requests without one.
+const SELF_UPDATE_USER_AGENT: &str = "easydep-server";
+/// The time after which a request to download a release or one of its assets is aborted.
+const SELF_UPDATE_TIMEOUT: Duration = Duration::from_secs(300);
+/// The suffix of the release asset containing the checksum of the server binary.
+const CHECKSUM_ASSET_SUFFIX: &str = ".sha256";
+/// The suffix of the release asset containing the signature of the server binary.
+const SIGNATURE_ASSET_SUFFIX: &str = ".sig";
+/// The suffix of the file to which the new server binary is written before it replaces the running binary.
+const PENDING_BINARY_SUFFIX: &str = ".pending";
+
+/// A release of easydep as returned by the GitHub api.
+#[derive(Deserialize, Debug)]
+struct EasydepRelease {
+    /// The name of the tag of the release.
+    tag_name: String,
+    /// The assets that are attached to the release.
+    assets: Vec<EasydepReleaseAsset>,
+}
+
+/// An asset attached to a release of easydep.
+#[derive(Deserialize, Debug)]
+struct EasydepReleaseAsset {
+    /// The file name of the asset.
+    name: String,
+    /// The url from which the asset can be downloaded.
+    browser_download_url: String,
+}
+
+/// Downloads the server binary of the given easydep release, verifies its checksum and signature and replaces the
+/// binary of the running server with it. The new binary is used once the server is restarted. The signature covers the
+/// release tag followed by a newline and the binary, so that a signed binary cannot be replayed as another release.
+///
+/// # Arguments
+/// * `self_update_configuration` - The settings to upgrade the server binary.
+/// * `release_tag` - The tag of the release to install, the latest release is installed if not given.
+///
+/// # Returns
+/// * `String` - The tag of the release that was installed.
+pub async fn install_server_release(
+    self_update_configuration: &SelfUpdateConfiguration,
+    release_tag: Option<&str>,
+) -> anyhow::Result<String> {
+    let http_client = reqwest::Client::builder()
+        .user_agent(SELF_UPDATE_USER_AGENT)
+        .timeout(SELF_UPDATE_TIMEOUT)
+        .build()?;
+    let repository_url = format!(
+        "{}/repos/{}/{}",
+        GITHUB_API_URL, self_update_configuration.repo_owner, self_update_configuration.repo_name
+    );
+    let release_url = match release_tag {
+        Some(release_tag) => {
+            // the tag is inserted into the request path, restrict it to the characters used in release tags
+            if !is_valid_release_tag(release_tag) {
+                bail!("invalid release tag: {}", release_tag)
+            }
+            format!("{}/releases/tags/{}", repository_url, release_tag)
+        }
+        None => format!("{}/releases/latest", repository_url),
+    };
+    let release = http_client
+        .get(release_url)
+        .send()
+        .await?
+        .error_for_status()?
+        .json::<EasydepRelease>()
+        .await
+        .context("unable to resolve easydep release")?;
+    info!("Downloading server binary of easydep {}", release.tag_name);
+
+    // download the binary with its checksum and signature
+    let asset_name = &self_update_configuration.asset_name;
+    let binary = download_release_asset(&http_client, &release, asset_name).await?;
+    let checksum_asset_name = format!("{}{}", asset_name, CHECKSUM_ASSET_SUFFIX);
+    let checksum_file =
+        download_release_asset(&http_client, &release, &checksum_asset_name).await?;
+    let signature_asset_name = format!("{}{}", asset_name, SIGNATURE_ASSET_SUFFIX);
+    let signature_file =
+        download_release_asset(&http_client, &release, &signature_asset_name).await?;
+
+    // verify that the binary is complete & was signed by the trusted key
+    let expected_checksum = String::from_utf8_lossy(&checksum_file)
+        .split_whitespace()
+        .next()
+        .unwrap_or_default()
+        .to_string();
+    let actual_checksum = encode_hex(digest(&SHA256, &binary).as_ref());
+    if !expected_checksum.eq_ignore_ascii_case(&actual_checksum) {
+        bail!(
+            "checksum of {} does not match: expected {}, got {}",
+            asset_name,
+            expected_checksum,
+            actual_checksum
+        )
+    }
+    let public_key = decode_hex(&self_update_configuration.signing_public_key)?;
+    let signature = decode_hex(String::from_utf8_lossy(&signature_file).trim())?;
+    let mut signed_message = format!("{}\n", release.tag_name).into_bytes();
+    signed_message.extend_from_slice(&binary);
+    UnparsedPublicKey::new(&ED25519, public_key)
+        .verify(&signed_message, &signature)
+        .map_err(|_| anyhow!("signature of {} is invalid", asset_name))?;
+
+    // replace the running binary, which keeps running until the server is restarted
+    let current_binary_path = env::current_exe().context("unable to resolve server binary")?;
+    let mut pending_binary_path = current_binary_path.clone().into_os_string();
+    pending_binary_path.push(PENDING_BINARY_SUFFIX);
+    let pending_binary_path = PathBuf::from(pending_binary_path);
+    fs::write(&pending_binary_path, &binary).await?;
+    // executables are only marked as such on unix
+    #[cfg(unix)]
+    fs::set_permissions(&pending_binary_path, Permissions::from_mode(0o755)).await?;
+    fs::rename(&pending_binary_path, &current_binary_path).await?;
+    Ok(release.tag_name)
+}
+
+/// Downloads the asset with the given name from the given release.
+///
+/// # Arguments
+/// * `http_client` - The http client to download the asset with.
+/// * `release` - The release from which the asset should be downloaded.
+/// * `asset_name` - The file name of the asset to download.
+async fn download_release_asset(
+    http_client: &reqwest::Client,
+    release: &EasydepRelease,
+    asset_name: &str,
+) -> anyhow::Result<Vec<u8>> {
+    let asset = release
+        .assets
+        .iter()
+        .find(|asset| asset.name == asset_name)
+        .with_context(|| format!("release {} has no asset {}", release.tag_name, asset_name))?;
+    let asset_content = http_client
+        .get(&asset.browser_download_url)
+        .send()
+        .await?
+        .error_for_status()?
+        .bytes()
+        .await
+        .with_context(|| format!("unable to download asset {}", asset_name))?;
+    Ok(asset_content.to_vec())
+}
+
+/// Checks if the given release tag is not empty and only consists of ascii alphanumeric characters, dots, dashes,
+/// underscores and plus signs, and does not start with a dot.
+///
+/// # Arguments
+/// * `release_tag` - The release tag to check.
+fn is_valid_release_tag(release_tag: &str) -> bool {
+    !release_tag.is_empty()
+        && !release_tag.starts_with('.')
+        && release_tag
+            .chars()
+            .all(|char| char.is_ascii_alphanumeric() || matches!(char, '.' | '-' | '_' | '+'))
+}
@@ -0,0 +1,230 @@
+/*
+ * This file is part of easydep, licensed under the MIT License (MIT).
+ *
+ * Copyright (c) 2024 easybill GmbH
+ *
+ * Permission is hereby granted, free of charge, to any person obtaining a copy
+ * of this software and associated documentation files (the "Software"), to deal
+ * in the Software without restriction, including without limitation the rights
+ * to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+ * copies of the Software, and to permit persons to whom the Software is
+ * furnished to do so, subject to the following conditions:
+ *
+ * The above copyright notice and this permission notice shall be included in all
+ * copies or substantial portions of the Software.
+ *
+ * THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+ * IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+ * FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+ * AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+ * LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+ * OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+ * SOFTWARE.
+ */
+
+use std::fmt::Debug;
+
+use crate::config::LogProcessingConfiguration;
+use crate::easydep::{ExecutedActionEntry, LogEntry};
+
+/// The value that replaces redacted values in the log lines.
+const REDACTED_VALUE: &str = "***";
+/// The marker appended to truncated log lines.
+const TRUNCATION_MARKER: &str = " [truncated]";
+/// The character that introduces an ANSI escape sequence.
+const ANSI_ESCAPE: char = '\u{1b}';
+
+/// A step in the chain that processes the log lines of an action.
+trait LogLineProcessor: Debug + Send + Sync {
+    /// Processes the given log line, returning `None` if the line should be dropped.
+    ///
+    /// # Arguments
+    /// * `line` - The log line to process.
+    fn process(&self, line: String) -> Option<String>;
+}
+
+/// Drops all log lines that contain one of the given values.
+#[derive(Debug)]
+struct LineDropper {
+    dropped_lines: Vec<String>,
+}
+
+impl LogLineProcessor for LineDropper {
+    fn process(&self, line: String) -> Option<String> {
+        let dropped = self
+            .dropped_lines
+            .iter()
+            .any(|dropped_line| line.contains(dropped_line));
+        (!dropped).then_some(line)
+    }
+}
+
+/// Replaces the given values in all log lines.
+#[derive(Debug)]
+struct ValueRedactor {
+    redacted_values: Vec<String>,
+}
+
+impl LogLineProcessor for ValueRedactor {
+    fn process(&self, line: String) -> Option<String> {
+        let redacted_line = self
+            .redacted_values
+            .iter()
+            .filter(|redacted_value| !redacted_value.is_empty())
+            .fold(line, |line, redacted_value| {
+                line.replace(redacted_value, REDACTED_VALUE)
+            });
+        Some(redacted_line)
+    }
+}
+
+/// Removes ANSI escape sequences from all log lines.
+#[derive(Debug)]
+struct AnsiStripper;
+
+impl LogLineProcessor for AnsiStripper {
+    fn process(&self, line: String) -> Option<String> {
+        Some(strip_ansi_escape_sequences(&line))
+    }
+}
+
+/// Truncates log lines that are longer than the given amount of characters.
+#[derive(Debug)]
+struct LineTruncator {
+    max_line_length: usize,
+}
+
+impl LogLineProcessor for LineTruncator {
+    fn process(&self, line: String) -> Option<String> {
+        match line.char_indices().nth(self.max_line_length) {
+            Some((truncation_index, _)) => Some(format!(
+                "{}{}",
+                &line[..truncation_index],
+                TRUNCATION_MARKER
+            )),
+            None => Some(line),
+        }
+    }
+}
+
+/// Prepends the given tag to all log lines.
+#[derive(Debug)]
+struct LineTagger {
+    tag: String,
+}
+
+impl LogLineProcessor for LineTagger {
+    fn process(&self, line: String) -> Option<String> {
+        Some(format!("[{}] {}", self.tag, line))
+    }
+}
+
+/// The chain of processors that are applied to the log lines of an action, as configured in a deployment profile.
+#[derive(Debug, Default)]
+pub(crate) struct LogProcessor {
+    processors: Vec<Box<dyn LogLineProcessor>>,
+}
+
+impl LogProcessor {
+    /// Constructs the processor chain from the given configuration. The chain is empty if no configuration is given.
+    ///
+    /// # Arguments
+    /// * `log_processing_configuration` - The log processing configuration of the deployment profile, if any.
+    pub fn new(log_processing_configuration: Option<&LogProcessingConfiguration>) -> Self {
+        let configuration = match log_processing_configuration {
+            Some(configuration) => configuration,
+            None => return Self::default(),
+        };
+
+        let mut processors: Vec<Box<dyn LogLineProcessor>> = Vec::new();
+        if !configuration.dropped_lines.is_empty() {
+            processors.push(Box::new(LineDropper {
+                dropped_lines: configuration.dropped_lines.clone(),
+            }));
+        }
+        if !configuration.redacted_values.is_empty() {
+            processors.push(Box::new(ValueRedactor {
+                redacted_values: configuration.redacted_values.clone(),
+            }));
+        }
+        if configuration.strip_ansi {
+            processors.push(Box::new(AnsiStripper));
+        }
+        if let Some(max_line_length) = configuration.max_line_length {
+            processors.push(Box::new(LineTruncator { max_line_length }));
+        }
+        if let Some(tag) = &configuration.tag {
+            processors.push(Box::new(LineTagger { tag: tag.clone() }));
+        }
+        Self { processors }
+    }
+
+    /// Applies the processor chain to the log line of the given entry. Returns `None` if the entry only contains a
+    /// log line that was dropped, entries reporting a status change are always kept (without their log line).
+    ///
+    /// # Arguments
+    /// * `entry` - The executed action entry to process.
+    pub fn process_entry(&self, mut entry: ExecutedActionEntry) -> Option<ExecutedActionEntry> {
+        let log_entry = match entry.action_log_entry.take() {
+            Some(log_entry) => log_entry,
+            None => return Some(entry),
+        };
+        let processed_content = self
+            .processors
+            .iter()
+            .try_fold(log_entry.content, |line, processor| processor.process(line));
+        match processed_content {
+            Some(content) => {
+                entry.action_log_entry = Some(LogEntry {
+                    stream_type: log_entry.stream_type,
+                    content,
+                });
+                Some(entry)
+            }
+            None if entry.progress_percent.is_some() || entry.phase.is_some() => Some(entry),
+            None => None,
+        }
+    }
+}
+
+/// Removes all ANSI escape sequences from the given line. Control sequences (`ESC [ ... <final byte>`), operating
+/// system commands (`ESC ] ... BEL` or `ESC ] ... ESC \`) and two character sequences are removed.
+///
+/// # Arguments
+/// * `line` - The line to remove the escape sequences from.
+fn strip_ansi_escape_sequences(line: &str) -> String {
+    let mut stripped_line = String::with_capacity(line.len());
+    let mut chars = line.chars().peekable();
+    while let Some(char) = chars.next() {
+        if char != ANSI_ESCAPE {
+            stripped_line.push(char);
+            continue;
+        }
+
+        match chars.next() {
+            // control sequence: parameters and intermediate bytes followed by a final byte in the range @ to ~
+            Some('[') => {
+                for sequence_char in chars.by_ref() {
+                    if ('@'..='~').contains(&sequence_char) {
+                        break;
+                    }
+                }
+            }
+            // operating system command: terminated by BEL or by the string terminator ESC \
+            Some(']') => {
+                while let Some(sequence_char) = chars.next() {
+                    if sequence_char == '\u{7}' {
+                        break;
+                    }
+                    if sequence_char == ANSI_ESCAPE && chars.peek() == Some(&'\\') {
+                        chars.next();
+                        break;
+                    }
+                }
+            }
+            // any other escape sequence consists of the escape character and a single following character
+            _ => {}
+        }
+    }
+    stripped_line
+}
@@ -0,0 +1,149 @@
+/*
+ * This file is part of easydep, licensed under the MIT License (MIT).
+ *
+ * Copyright (c) 2024 easybill GmbH
+ *
+ * Permission is hereby granted, free of charge, to any person obtaining a copy
+ * of this software and associated documentation files (the "Software"), to deal
+ * in the Software without restriction, including without limitation the rights
+ * to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+ * copies of the Software, and to permit persons to whom the Software is
+ * furnished to do so, subject to the following conditions:
+ *
+ * The above copyright notice and this permission notice shall be included in all
+ * copies or substantial portions of the Software.
+ *
+ * THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+ * IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+ * FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+ * AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+ * LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+ * OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+ * SOFTWARE.
+ */
+
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+
+use anyhow::Context;
+use env_logger::{Env, Logger};
+#[cfg(unix)]
+use log::warn;
+use log::{info, LevelFilter, Log, Metadata, Record};
+#[cfg(unix)]
+use tokio::signal::unix::{signal, SignalKind};
+
+/// The module whose log level is raised to debug while debug logging is enabled at runtime.
+const DEBUG_LOG_MODULE: &str = "easydep_server";
+
+/// A logger which can switch between the configured log filter and debug logging at runtime, without having to
+/// restart the server (and losing the state of a possibly stuck deployment).
+#[derive(Debug)]
+struct ToggleableLogger {
+    /// The logger using the filters configured through the RUST_LOG environment variable.
+    configured_logger: Logger,
+    /// The logger using the configured filters with debug logging enabled for easydep.
+    debug_logger: Logger,
+    /// If debug logging is currently enabled.
+    debug_enabled: Arc<AtomicBool>,
+}
+
+impl ToggleableLogger {
+    /// Get the logger that should be used based on the current debug logging state.
+    fn active_logger(&self) -> &Logger {
+        if self.debug_enabled.load(Ordering::Relaxed) {
+            &self.debug_logger
+        } else {
+            &self.configured_logger
+        }
+    }
+}
+
+impl Log for ToggleableLogger {
+    fn enabled(&self, metadata: &Metadata) -> bool {
+        self.active_logger().enabled(metadata)
+    }
+
+    fn log(&self, record: &Record) {
+        self.active_logger().log(record)
+    }
+
+    fn flush(&self) {
+        self.active_logger().flush()
+    }
+}
+
+/// A handle to toggle debug logging of the installed logger at runtime.
+#[derive(Clone, Debug)]
+pub(crate) struct LogLevelToggle {
+    /// The maximum level of the logger using the configured filters.
+    configured_max_level: LevelFilter,
+    /// The maximum level of the logger with debug logging enabled.
+    debug_max_level: LevelFilter,
+    /// If debug logging is currently enabled.
+    debug_enabled: Arc<AtomicBool>,
+}
+
+impl LogLevelToggle {
+    /// Switches between the configured log filter and debug logging, returning if debug logging is now enabled.
+    pub fn toggle_debug_logging(&self) -> bool {
+        let debug_enabled = !self.debug_enabled.fetch_xor(true, Ordering::Relaxed);
+        if debug_enabled {
+            log::set_max_level(self.debug_max_level);
+        } else {
+            log::set_max_level(self.configured_max_level);
+        }
+        debug_enabled
+    }
+
+    /// Starts a task which toggles debug logging each time the process receives a SIGHUP signal.
+    #[cfg(unix)]
+    pub fn start_signal_listener_task(&self) -> anyhow::Result<()> {
+        let mut hangup_signal =
+            signal(SignalKind::hangup()).context("unable to listen for SIGHUP signals")?;
+        let log_level_toggle = self.clone();
+        tokio::spawn(async move {
+            while hangup_signal.recv().await.is_some() {
+                if log_level_toggle.toggle_debug_logging() {
+                    warn!("Received SIGHUP, debug logging is now enabled");
+                } else {
+                    warn!("Received SIGHUP, debug logging is now disabled");
+                }
+            }
+        });
+        info!("Send SIGHUP to toggle debug logging at runtime");
+        Ok(())
+    }
+
+    /// Does nothing, as SIGHUP signals only exist on unix. Debug logging cannot be toggled at runtime.
+    #[cfg(not(unix))]
+    pub fn start_signal_listener_task(&self) -> anyhow::Result<()> {
+        info!("Debug logging cannot be toggled at runtime on this platform");
+        Ok(())
+    }
+}
+
+/// Initializes the logger, using the "info" level if the RUST_LOG environment variable isn't set. The returned handle
+/// can be used to toggle debug logging at runtime.
+pub(crate) fn init_logging() -> anyhow::Result<LogLevelToggle> {
+    let env = || Env::default().default_filter_or("info");
+    let configured_logger = env_logger::Builder::from_env(env()).build();
+    let debug_logger = env_logger::Builder::from_env(env())
+        .filter_module(DEBUG_LOG_MODULE, LevelFilter::Debug)
+        .build();
+
+    let debug_enabled = Arc::new(AtomicBool::new(false));
+    let log_level_toggle = LogLevelToggle {
+        configured_max_level: configured_logger.filter(),
+        debug_max_level: debug_logger.filter().max(configured_logger.filter()),
+        debug_enabled: debug_enabled.clone(),
+    };
+    let toggleable_logger = ToggleableLogger {
+        configured_logger,
+        debug_logger,
+        debug_enabled,
+    };
+    log::set_boxed_logger(Box::new(toggleable_logger)).context("unable to install logger")?;
+    log::set_max_level(log_level_toggle.configured_max_level);
+    Ok(log_level_toggle)
+}
@@ -21,32 +21,65 @@
  * OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
  * SOFTWARE.
  */
-use std::future::IntoFuture;
+// tonic::Status is used as the error type of all streamed entries, which is larger than clippy likes
+#![allow(clippy::result_large_err)]
+
+use std::future::{Future, IntoFuture};
 use std::net::SocketAddr;
 use std::process::exit;
 
 use anyhow::Context;
-use clap::Parser;
-use env_logger::Env;
+use clap::{CommandFactory, FromArgMatches, Parser, Subcommand};
+use easydep_buildinfo::{version_string, BuildInfo, VersionFormat, LONG_VERSION};
 use log::{error, info};
+use tokio::net::TcpListener;
+use tokio_stream::wrappers::TcpListenerStream;
 use tonic::transport::Server;
 
+use crate::accessor::api_token_accessor::ApiTokenAccessor;
+use crate::accessor::configuration_accessor::ConfigurationAccessor;
 use crate::accessor::deploy_action_accessor::DeploymentStatusAccessor;
+use crate::accessor::deployment_accessor::DeploymentAccessor;
+use crate::accessor::deployment_rate_limit_accessor::DeploymentRateLimitAccessor;
 use crate::accessor::github_accessor::GitHubAccessor;
-use crate::config::Configuration;
+use crate::accessor::oidc_accessor::OidcAccessor;
+use crate::accessor::server_restart_accessor::ServerRestartAccessor;
+use crate::capability::MANIFEST_CAPABILITIES;
+use crate::config::{Configuration, ConfigurationSource};
 use crate::easydep::deployment_service_server::DeploymentServiceServer;
+#[cfg(feature = "concurrency-simulation")]
+use crate::easydep::simulation_service_server::SimulationServiceServer;
 use crate::easydep::status_service_server::StatusServiceServer;
+use crate::executor::action_watchdog::start_action_watchdog_task;
+use crate::executor::branch_tracking_executor::start_branch_tracking_tasks;
+use crate::executor::inspect_executor::inspect_base_directory;
+use crate::executor::orphan_cleanup_executor::start_orphan_cleanup_task;
+use crate::executor::repository_cache_executor::start_repository_cache_maintenance_task;
+use crate::executor::self_test_executor::{run_self_test, run_validation};
+use crate::logging::init_logging;
+use crate::notification::notification_dispatcher::NotificationDispatcher;
+use crate::service::auth_interceptor::AuthInterceptor;
 use crate::service::deployment_service::DeploymentServiceImpl;
+use crate::service::github_webhook_service::GitHubWebhookService;
+#[cfg(feature = "concurrency-simulation")]
+use crate::service::simulation_service::SimulationServiceImpl;
 use crate::service::status_service::StatusServiceImpl;
 
 mod accessor;
+mod capability;
 mod config;
 mod executor;
+mod hex;
+mod log_processor;
+mod logging;
+mod metrics;
+mod notification;
 mod process_streamer;
 mod service;
 
-const GIT_SHA: &str = env!("GIT_HASH");
-const VERSION: &str = env!("CARGO_PKG_VERSION");
+/// The exit code used when the server exits to be restarted by its supervisor, for example after a new configuration
+/// was installed.
+const RESTART_EXIT_CODE: i32 = 75;
 
 pub(crate) mod easydep {
     tonic::include_proto!("easydep");
@@ -56,66 +89,351 @@ pub(crate) mod easydep {
 #[derive(Parser, Clone, Debug)]
 struct CommandLineOptions {
     /// The path were the main configuration file is located.
-    #[arg(long = "config-path", env = "EASYDEP_CONFIG_PATH")]
+    #[arg(
+        long = "config-path",
+        env = "EASYDEP_CONFIG_PATH",
+        required = false,
+        required_unless_present = "version_format"
+    )]
     pub configuration_path: String,
+    /// Prints the version, build and protocol information and the capabilities of the server in the given format
+    /// (`human` if omitted) and exits, no configuration is required.
+    #[arg(
+        short = 'V',
+        long = "version",
+        value_enum,
+        num_args = 0..=1,
+        require_equals = true,
+        default_missing_value = "human"
+    )]
+    pub version_format: Option<VersionFormat>,
+    /// Runs the deep checks of the environment (write access to the base directory, symlink capability, executables
+    /// and GitHub access of the profiles) and exits with their result instead of running the server.
+    #[arg(long = "self-test")]
+    pub self_test: bool,
+    /// The command to execute instead of running the server.
+    #[command(subcommand)]
+    pub command: Option<ServerCommand>,
+}
+
+/// The commands that can be executed instead of running the server.
+#[derive(Subcommand, Clone, Debug)]
+enum ServerCommand {
+    /// Prints the releases, release links, bad markers, metadata, repository cache sizes and orphaned entries stored in
+    /// the base directory, read directly from the disk. Works while the server is not running.
+    Inspect,
+    /// Validates the configuration without running the server: parses and validates the configuration, checks that the
+    /// GitHub app key is readable, that the app can authenticate and access the repository of each profile, and that
+    /// the scripts directories exist in the repositories. Exits with a non-zero code if one of the checks failed.
+    #[command(alias = "check")]
+    Validate,
 }
 
 #[tokio::main]
 async fn main() -> anyhow::Result<()> {
+    // the version can be printed without a configuration, which is required otherwise
+    let command_line_matches = CommandLineOptions::command().get_matches();
+    if let Some(version_format) = command_line_matches.get_one::<VersionFormat>("version_format") {
+        let build_info = BuildInfo::new("easydep-server", MANIFEST_CAPABILITIES);
+        println!("{}", build_info.format(*version_format));
+        return Ok(());
+    }
+    let command_line_options = CommandLineOptions::from_arg_matches(&command_line_matches)
+        .unwrap_or_else(|err| err.exit());
+
     // initializes the logger, using the "info" level if the RUST_LOG environment variable isn't set
-    env_logger::Builder::from_env(Env::default().default_filter_or("info"))
-        .try_init()
-        .context("unable to initialize logging")?;
-    info!(
-        "Running easydep version {} (git commit {})",
-        VERSION, GIT_SHA
-    );
+    let log_level_toggle = init_logging().context("unable to initialize logging")?;
+    log_level_toggle.start_signal_listener_task()?;
+    info!("Running easydep version {}", LONG_VERSION);
 
     info!("Loading configuration...");
-    let command_line_options = CommandLineOptions::parse();
-    let configuration = Configuration::load_from_file(&command_line_options.configuration_path)
-        .await
-        .context("couldn't parse configuration file")?;
-    configuration
-        .validate()
+    let configuration_path = &command_line_options.configuration_path;
+    if let Some(ServerCommand::Inspect) = command_line_options.command {
+        // the configuration is not validated, inspecting must work even if the server cannot start
+        let (configuration, _) = Configuration::load_from_file(configuration_path)
+            .await
+            .context("couldn't parse configuration file")?;
+        inspect_base_directory(&configuration).await;
+        return Ok(());
+    }
+    if let Some(ServerCommand::Validate) = command_line_options.command {
+        let (configuration, _) = load_configuration(configuration_path).await?;
+        let exit_code = match run_validation(&configuration).await {
+            Ok(()) => {
+                info!("Configuration {} is valid", configuration_path);
+                0
+            }
+            Err(err) => {
+                error!("{err:#}");
+                1
+            }
+        };
+        exit(exit_code)
+    }
+    if command_line_options.self_test {
+        let (configuration, _) = load_configuration(configuration_path).await?;
+        let exit_code = match run_self_test(&configuration).await {
+            Ok(()) => {
+                info!("All self-test checks passed");
+                0
+            }
+            Err(err) => {
+                error!("{err:#}");
+                1
+            }
+        };
+        exit(exit_code)
+    }
+    let (configuration, configuration_source) = match load_configuration(configuration_path).await {
+        Ok(loaded_configuration) => loaded_configuration,
+        Err(err) => {
+            // fall back to the previous configuration if the configuration was just replaced by a pushed one
+            if !Configuration::restore_previous_file(configuration_path)
+                .await
+                .context("couldn't restore previous configuration file")?
+            {
+                return Err(err);
+            }
+            error!(
+                "Unable to load installed configuration, restored previous configuration: {err:?}"
+            );
+            load_configuration(configuration_path).await?
+        }
+    };
+    // the previous configuration is only discarded once the server is serving, it is restored if the server fails to
+    // start with the installed configuration so that the supervisor restarts the server with the previous one
+    let (tonic_serve_future, server_restart_accessor) =
+        match start_server(configuration, configuration_source).await {
+            Ok(started_server) => started_server,
+            Err(err) => {
+                if Configuration::restore_previous_file(configuration_path)
+                    .await
+                    .context("couldn't restore previous configuration file")?
+                {
+                    error!("Unable to start server, restored previous configuration");
+                }
+                return Err(err);
+            }
+        };
+    Configuration::discard_previous_file(configuration_path)
         .await
-        .context("issue detected while validating configuration")?;
+        .context("couldn't remove previous configuration file")?;
+    let exit_code = tokio::select! {
+        _ = tonic_serve_future => {
+            error!("Tonic server http endpoint failed");
+            100
+        }
+        _ = tokio::signal::ctrl_c() => {
+            info!("Quit signal received, exiting!");
+            0
+        }
+        _ = server_restart_accessor.wait_for_restart_request() => {
+            info!("Restart requested, exiting to be restarted by the supervisor!");
+            RESTART_EXIT_CODE
+        }
+    };
+    exit(exit_code)
+}
+
+/// Initializes the accessors and services using the given configuration and binds the gRPC server and the GitHub
+/// webhook listener, returning the future serving the gRPC requests once all of them were initialized.
+///
+/// # Arguments
+/// * `configuration` - The loaded and validated configuration.
+/// * `configuration_source` - The information about the file from which the configuration was loaded.
+async fn start_server(
+    configuration: Configuration,
+    configuration_source: ConfigurationSource,
+) -> anyhow::Result<(
+    impl Future<Output = Result<(), tonic::transport::Error>>,
+    ServerRestartAccessor,
+)> {
+    // set the umask of the server process, all spawned git and script processes inherit it
+    #[cfg(unix)]
+    if let Some(umask) = configuration.umask {
+        info!("Setting process umask to {:#o}...", umask);
+        // SAFETY: umask has no memory safety requirements and cannot fail, it is set before any task or process is
+        // spawned so that no file is created concurrently with the previous mask
+        unsafe { libc::umask(umask as libc::mode_t) };
+    }
+    #[cfg(not(unix))]
+    if configuration.umask.is_some() {
+        log::warn!("The umask is only supported on unix, ignoring it");
+    }
+    if configuration.self_test_on_startup {
+        info!("Running self-test...");
+        run_self_test(&configuration)
+            .await
+            .context("self-test failed, check the logged checks")?;
+    }
     let bind_address = configuration
         .bind_host
         .parse::<SocketAddr>()
         .context("couldn't parse provided host address")?;
 
-    let version_string = format!("{}+{}", VERSION, GIT_SHA);
-    let deployment_configurations = configuration.get_deployment_configuration_ids();
+    let version_string = version_string();
+    let configuration_accessor =
+        ConfigurationAccessor::new(configuration.clone(), configuration_source);
     let deploy_status_accessor = DeploymentStatusAccessor::new();
+    let server_restart_accessor = ServerRestartAccessor::new();
+    info!("Preparing GitHub api client...");
+    let github_accessor = GitHubAccessor::new(&configuration)
+        .await
+        .context("couldn't initialize GitHub client")?;
     let status_service = StatusServiceImpl::new(
         version_string,
-        deployment_configurations,
+        configuration_accessor.clone(),
         deploy_status_accessor.clone(),
+        server_restart_accessor.clone(),
+        github_accessor.clone(),
     );
+    #[cfg(feature = "concurrency-simulation")]
+    let simulation_service = SimulationServiceImpl::new(configuration_accessor.clone());
 
-    info!("Preparing GitHub api client...");
-    let github_accessor = GitHubAccessor::new(&configuration)
+    let oidc_accessor = match &configuration.oidc {
+        Some(oidc_configuration) => {
+            info!(
+                "Loading OIDC signing keys from {}...",
+                oidc_configuration.issuer
+            );
+            let oidc_accessor = OidcAccessor::new(oidc_configuration)
+                .await
+                .context("couldn't initialize OIDC token validation")?;
+            oidc_accessor.start_signing_key_refresh_task();
+            Some(oidc_accessor)
+        }
+        None => None,
+    };
+    let api_token_accessor = if configuration.api_tokens.is_empty() {
+        None
+    } else {
+        info!("Loading {} api token(s)...", configuration.api_tokens.len());
+        let api_token_accessor = ApiTokenAccessor::new(&configuration.api_tokens)
+            .await
+            .context("couldn't load api tokens")?;
+        Some(api_token_accessor)
+    };
+    let auth_interceptor = AuthInterceptor::new(oidc_accessor, api_token_accessor);
+
+    if let Some(orphan_cleanup_configuration) = &configuration.orphan_cleanup {
+        info!(
+            "Starting orphaned release cleanup every {} seconds...",
+            orphan_cleanup_configuration.interval_seconds
+        );
+        start_orphan_cleanup_task(
+            configuration_accessor.clone(),
+            orphan_cleanup_configuration.clone(),
+            DeploymentAccessor::new(&configuration),
+            deploy_status_accessor.clone(),
+        );
+    }
+    if let Some(maintenance_configuration) = &configuration.repository_cache_maintenance {
+        info!(
+            "Starting repository cache maintenance every {} seconds...",
+            maintenance_configuration.interval_seconds
+        );
+        start_repository_cache_maintenance_task(
+            configuration_accessor.clone(),
+            maintenance_configuration.clone(),
+            DeploymentAccessor::new(&configuration),
+            deploy_status_accessor.clone(),
+        );
+    }
+
+    let notification_dispatcher =
+        NotificationDispatcher::new(&configuration).context("couldn't initialize notifications")?;
+    if let Some(watchdog_configuration) = &configuration.watchdog {
+        info!(
+            "Starting action watchdog every {} seconds...",
+            watchdog_configuration.check_interval_seconds
+        );
+        start_action_watchdog_task(
+            watchdog_configuration.clone(),
+            deploy_status_accessor.clone(),
+            notification_dispatcher.clone(),
+        );
+    }
+    let deployment_rate_limit_accessor = DeploymentRateLimitAccessor::new();
+    start_branch_tracking_tasks(
+        &configuration_accessor,
+        &github_accessor,
+        &deploy_status_accessor,
+        &notification_dispatcher,
+        &deployment_rate_limit_accessor,
+    )
+    .await;
+    if let Some(github_webhook_configuration) = &configuration.github_webhook {
+        info!(
+            "Binding GitHub webhook listener to {}...",
+            github_webhook_configuration.bind_host
+        );
+        let webhook_service = GitHubWebhookService::new(
+            github_webhook_configuration,
+            &configuration_accessor,
+            &github_accessor,
+            &deploy_status_accessor,
+            &notification_dispatcher,
+            &deployment_rate_limit_accessor,
+        )
         .await
-        .context("couldn't initialize GitHub client")?;
-    let deployment_service =
-        DeploymentServiceImpl::new(configuration, github_accessor, deploy_status_accessor).await;
+        .context("couldn't initialize GitHub webhook listener")?;
+        let webhook_listener = TcpListener::bind(&github_webhook_configuration.bind_host)
+            .await
+            .context("couldn't bind GitHub webhook listener")?;
+        webhook_service.start_serving(webhook_listener);
+    }
+    let deployment_service = DeploymentServiceImpl::new(
+        configuration_accessor,
+        github_accessor,
+        deploy_status_accessor,
+        notification_dispatcher,
+        deployment_rate_limit_accessor,
+    )
+    .await;
+    deployment_service.restore_prepared_deployment().await;
 
     info!("Binding gRPC server to {}...", bind_address);
-    let tonic_serve_future = Server::builder()
-        .add_service(StatusServiceServer::new(status_service))
-        .add_service(DeploymentServiceServer::new(deployment_service))
-        .serve(bind_address)
-        .into_future();
-    let exit_code = tokio::select! {
-        _ = tonic_serve_future => {
-            error!("Tonic server http endpoint failed");
-            100
-        }
-        _ = tokio::signal::ctrl_c() => {
-            info!("Quit signal received, exiting!");
-            0
-        }
+    let tonic_router = Server::builder()
+        .add_service(StatusServiceServer::with_interceptor(
+            status_service,
+            auth_interceptor.clone(),
+        ))
+        .add_service(DeploymentServiceServer::with_interceptor(
+            deployment_service,
+            auth_interceptor.clone(),
+        ));
+    #[cfg(feature = "concurrency-simulation")]
+    let tonic_router = {
+        log::warn!(
+            "The concurrency simulation service is enabled, do not use this build in production"
+        );
+        tonic_router.add_service(SimulationServiceServer::with_interceptor(
+            simulation_service,
+            auth_interceptor,
+        ))
     };
-    exit(exit_code)
+    let grpc_listener = TcpListener::bind(bind_address)
+        .await
+        .context("couldn't bind gRPC server")?;
+    let tonic_serve_future = tonic_router
+        .serve_with_incoming(TcpListenerStream::new(grpc_listener))
+        .into_future();
+    Ok((tonic_serve_future, server_restart_accessor))
+}
+
+/// Loads and validates the configuration from the given file.
+///
+/// # Arguments
+/// * `configuration_path` - The path to the configuration file.
+async fn load_configuration(
+    configuration_path: &str,
+) -> anyhow::Result<(Configuration, ConfigurationSource)> {
+    let (configuration, configuration_source) = Configuration::load_from_file(configuration_path)
+        .await
+        .context("couldn't parse configuration file")?;
+    configuration
+        .validate()
+        .await
+        .context("issue detected while validating configuration")?;
+    Ok((configuration, configuration_source))
 }
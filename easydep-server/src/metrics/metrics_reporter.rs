@@ -0,0 +1,121 @@
+/*
+ * This file is part of easydep, licensed under the MIT License (MIT).
+ *
+ * Copyright (c) 2024 easybill GmbH
+ *
+ * Permission is hereby granted, free of charge, to any person obtaining a copy
+ * of this software and associated documentation files (the "Software"), to deal
+ * in the Software without restriction, including without limitation the rights
+ * to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+ * copies of the Software, and to permit persons to whom the Software is
+ * furnished to do so, subject to the following conditions:
+ *
+ * The above copyright notice and this permission notice shall be included in all
+ * copies or substantial portions of the Software.
+ *
+ * THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+ * IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+ * FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+ * AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+ * LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+ * OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+ * SOFTWARE.
+ */
+
+use std::collections::BTreeMap;
+use std::sync::Arc;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use log::warn;
+use tokio::sync::Mutex;
+
+use crate::config::MetricsConfiguration;
+use crate::metrics::pushgateway_exporter::push_to_pushgateway;
+use crate::metrics::statsd_exporter::send_to_statsd;
+use crate::notification::deployment_notification::DeploymentNotification;
+
+/// The deployment metrics recorded since the server started.
+#[derive(Clone, Debug, Default)]
+pub(crate) struct RecordedMetrics {
+    /// The amount of lifecycle events, keyed by the profile and the event name.
+    pub event_counts: BTreeMap<(String, &'static str), u64>,
+    /// The unix timestamp (in seconds) of the last lifecycle event, keyed by the profile and the event name.
+    pub last_event_timestamps: BTreeMap<(String, &'static str), u64>,
+    /// The duration (in seconds) of the last execution of an action, keyed by the profile and the action name.
+    pub action_durations: BTreeMap<(String, String), u64>,
+}
+
+/// Records metrics about deployment lifecycle events and pushes them to the configured sinks.
+#[derive(Clone, Debug)]
+pub(crate) struct MetricsReporter {
+    /// The settings of the sinks to which the metrics are pushed.
+    metrics_configuration: Arc<MetricsConfiguration>,
+    /// The metrics recorded since the server started.
+    recorded_metrics: Arc<Mutex<RecordedMetrics>>,
+    /// The http client to push the metrics with.
+    http_client: reqwest::Client,
+}
+
+impl MetricsReporter {
+    /// Constructs a new metrics reporter that pushes the metrics to the given sinks.
+    ///
+    /// # Arguments
+    /// * `metrics_configuration` - The settings of the sinks to push the metrics to.
+    pub fn new(metrics_configuration: &MetricsConfiguration) -> Self {
+        Self {
+            metrics_configuration: Arc::new(metrics_configuration.clone()),
+            recorded_metrics: Arc::new(Mutex::new(RecordedMetrics::default())),
+            http_client: reqwest::Client::new(),
+        }
+    }
+
+    /// Records the metrics of the given lifecycle event and pushes them to the configured sinks in the background.
+    /// Failures to push the metrics are only logged.
+    ///
+    /// # Arguments
+    /// * `notification` - The notification about the lifecycle event to record.
+    pub fn record(&self, notification: &DeploymentNotification) {
+        let reporter = self.clone();
+        let notification = notification.clone();
+        tokio::spawn(async move {
+            let recorded_metrics = {
+                let mut recorded_metrics = reporter.recorded_metrics.lock().await;
+                let event_key = (notification.profile.clone(), notification.event.name());
+                *recorded_metrics
+                    .event_counts
+                    .entry(event_key.clone())
+                    .or_default() += 1;
+                let timestamp = SystemTime::now()
+                    .duration_since(UNIX_EPOCH)
+                    .map(|duration| duration.as_secs())
+                    .unwrap_or_default();
+                recorded_metrics
+                    .last_event_timestamps
+                    .insert(event_key, timestamp);
+                let action_key = (notification.profile.clone(), notification.action.clone());
+                recorded_metrics
+                    .action_durations
+                    .insert(action_key, notification.duration_seconds);
+                recorded_metrics.clone()
+            };
+
+            if let Some(pushgateway) = &reporter.metrics_configuration.pushgateway {
+                if let Err(err) = push_to_pushgateway(
+                    &reporter.http_client,
+                    pushgateway,
+                    &notification.server,
+                    &recorded_metrics,
+                )
+                .await
+                {
+                    warn!("Unable to push metrics to {}: {err:?}", pushgateway.url);
+                }
+            }
+            if let Some(statsd) = &reporter.metrics_configuration.statsd {
+                if let Err(err) = send_to_statsd(statsd, &notification).await {
+                    warn!("Unable to send metrics to {}: {err:?}", statsd.address);
+                }
+            }
+        });
+    }
+}
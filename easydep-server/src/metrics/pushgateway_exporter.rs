@@ -0,0 +1,108 @@
+/*
+ * This file is part of easydep, licensed under the MIT License (MIT).
+ *
+ * Copyright (c) 2024 easybill GmbH
+ *
+ * Permission is hereby granted, free of charge, to any person obtaining a copy
+ * of this software and associated documentation files (the "Software"), to deal
+ * in the Software without restriction, including without limitation the rights
+ * to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+ * copies of the Software, and to permit persons to whom the Software is
+ * furnished to do so, subject to the following conditions:
+ *
+ * The above copyright notice and this permission notice shall be included in all
+ * copies or substantial portions of the Software.
+ *
+ * THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+ * IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+ * FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+ * AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+ * LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+ * OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+ * SOFTWARE.
+ */
+
+use std::fmt::Write;
+use std::time::Duration;
+
+use anyhow::bail;
+
+use crate::config::PushgatewayConfiguration;
+use crate::metrics::metrics_reporter::RecordedMetrics;
+
+/// The time after which pushing the metrics to the pushgateway is aborted.
+const PUSHGATEWAY_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Pushes the given metrics to the given pushgateway, replacing the metrics that were previously pushed by this server.
+///
+/// # Arguments
+/// * `http_client` - The http client to push the metrics with.
+/// * `pushgateway` - The configuration of the pushgateway.
+/// * `instance` - The name of this server, used as instance label.
+/// * `recorded_metrics` - The metrics to push.
+pub async fn push_to_pushgateway(
+    http_client: &reqwest::Client,
+    pushgateway: &PushgatewayConfiguration,
+    instance: &str,
+    recorded_metrics: &RecordedMetrics,
+) -> anyhow::Result<()> {
+    let mut body = String::new();
+    writeln!(body, "# TYPE easydep_deployment_events_total counter")?;
+    for ((profile, event), count) in &recorded_metrics.event_counts {
+        writeln!(
+            body,
+            "easydep_deployment_events_total{{profile=\"{}\",event=\"{}\"}} {}",
+            escape_label_value(profile),
+            event,
+            count
+        )?;
+    }
+    writeln!(body, "# TYPE easydep_last_event_timestamp_seconds gauge")?;
+    for ((profile, event), timestamp) in &recorded_metrics.last_event_timestamps {
+        writeln!(
+            body,
+            "easydep_last_event_timestamp_seconds{{profile=\"{}\",event=\"{}\"}} {}",
+            escape_label_value(profile),
+            event,
+            timestamp
+        )?;
+    }
+    writeln!(body, "# TYPE easydep_action_duration_seconds gauge")?;
+    for ((profile, action), duration) in &recorded_metrics.action_durations {
+        writeln!(
+            body,
+            "easydep_action_duration_seconds{{profile=\"{}\",action=\"{}\"}} {}",
+            escape_label_value(profile),
+            escape_label_value(action),
+            duration
+        )?;
+    }
+
+    let push_url = format!(
+        "{}/metrics/job/{}/instance/{}",
+        pushgateway.url.trim_end_matches('/'),
+        pushgateway.job,
+        instance
+    );
+    let response = http_client
+        .put(push_url)
+        .timeout(PUSHGATEWAY_TIMEOUT)
+        .body(body)
+        .send()
+        .await?;
+    if !response.status().is_success() {
+        bail!("pushgateway responded with {}", response.status())
+    }
+    Ok(())
+}
+
+/// Escapes the given label value for the Prometheus text exposition format.
+///
+/// # Arguments
+/// * `value` - The label value to escape.
+fn escape_label_value(value: &str) -> String {
+    value
+        .replace('\\', "\\\\")
+        .replace('"', "\\\"")
+        .replace('\n', "\\n")
+}
@@ -0,0 +1,71 @@
+/*
+ * This file is part of easydep, licensed under the MIT License (MIT).
+ *
+ * Copyright (c) 2024 easybill GmbH
+ *
+ * Permission is hereby granted, free of charge, to any person obtaining a copy
+ * of this software and associated documentation files (the "Software"), to deal
+ * in the Software without restriction, including without limitation the rights
+ * to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+ * copies of the Software, and to permit persons to whom the Software is
+ * furnished to do so, subject to the following conditions:
+ *
+ * The above copyright notice and this permission notice shall be included in all
+ * copies or substantial portions of the Software.
+ *
+ * THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+ * IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+ * FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+ * AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+ * LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+ * OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+ * SOFTWARE.
+ */
+
+use tokio::net::UdpSocket;
+
+use crate::config::StatsdConfiguration;
+use crate::notification::deployment_notification::DeploymentNotification;
+
+/// Sends the metrics of the given lifecycle event to the given StatsD server. The event is counted in the metric
+/// `<prefix>.<profile>.<event>` and the duration of the action is sent as `<prefix>.<profile>.<action>.duration`.
+///
+/// # Arguments
+/// * `statsd` - The configuration of the StatsD server.
+/// * `notification` - The notification about the lifecycle event.
+pub async fn send_to_statsd(
+    statsd: &StatsdConfiguration,
+    notification: &DeploymentNotification,
+) -> anyhow::Result<()> {
+    let metric_prefix = format!(
+        "{}.{}",
+        statsd.prefix,
+        sanitize_metric_name(&notification.profile)
+    );
+    let payload = format!(
+        "{prefix}.{event}:1|c\n{prefix}.{action}.duration:{duration}|ms",
+        prefix = metric_prefix,
+        event = notification.event.name(),
+        action = sanitize_metric_name(&notification.action),
+        duration = notification.duration_seconds * 1000,
+    );
+
+    let socket = UdpSocket::bind("0.0.0.0:0").await?;
+    socket
+        .send_to(payload.as_bytes(), statsd.address.as_str())
+        .await?;
+    Ok(())
+}
+
+/// Replaces all characters in the given name that have a special meaning in StatsD metric names.
+///
+/// # Arguments
+/// * `name` - The name to sanitize.
+fn sanitize_metric_name(name: &str) -> String {
+    name.chars()
+        .map(|char| match char {
+            'a'..='z' | 'A'..='Z' | '0'..='9' | '_' | '-' => char,
+            _ => '_',
+        })
+        .collect()
+}
@@ -0,0 +1,165 @@
+/*
+ * This file is part of easydep, licensed under the MIT License (MIT).
+ *
+ * Copyright (c) 2024 easybill GmbH
+ *
+ * Permission is hereby granted, free of charge, to any person obtaining a copy
+ * of this software and associated documentation files (the "Software"), to deal
+ * in the Software without restriction, including without limitation the rights
+ * to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+ * copies of the Software, and to permit persons to whom the Software is
+ * furnished to do so, subject to the following conditions:
+ *
+ * The above copyright notice and this permission notice shall be included in all
+ * copies or substantial portions of the Software.
+ *
+ * THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+ * IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+ * FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+ * AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+ * LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+ * OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+ * SOFTWARE.
+ */
+
+use std::collections::VecDeque;
+
+use tokio::sync::mpsc::{channel, Sender};
+use tokio::task::JoinHandle;
+use tonic::Status;
+
+use crate::config::DeploymentConfiguration;
+use crate::easydep::{Action, ActionStatus, ExecutedActionEntry, LogType};
+use crate::log_processor::LogProcessor;
+
+/// The maximum amount of lines that are kept in the failure excerpt of an action outcome.
+const FAILURE_EXCERPT_LINES: usize = 10;
+/// The maximum size (in bytes) of the output that is kept in the failure log of an action outcome, older lines are
+/// dropped when exceeded.
+const FAILURE_LOG_MAX_BYTES: usize = 1024 * 1024;
+
+/// The outcome of an action that was executed for a deployment, recorded from the executed action entries.
+#[derive(Clone, Debug, Default)]
+pub(crate) struct ActionOutcome {
+    /// If an error or a failed action was reported while executing the action.
+    pub failed: bool,
+    /// The last error messages and stderr lines that were reported while executing the action.
+    pub failure_excerpt: Option<String>,
+    /// The (possibly truncated) output of the action, only recorded if the action failed.
+    pub failure_log: Option<Vec<String>>,
+}
+
+/// Records the outcome of an action while forwarding all executed action entries to the given sender. The returned
+/// sender should be passed to the action, the returned handle resolves to the outcome once all senders are dropped.
+/// Entries are still recorded if the receiver of the given sender was dropped (for example when the client
+/// disconnected). The log processing of the given deployment profile is applied to the entries before they are
+/// recorded and forwarded.
+///
+/// # Arguments
+/// * `output_sender` - The sender to which all executed action entries are forwarded.
+/// * `deployment_configuration` - The deployment profile configuration used for the action.
+pub fn record_action_outcome(
+    output_sender: Sender<Result<ExecutedActionEntry, Status>>,
+    deployment_configuration: &DeploymentConfiguration,
+) -> (
+    Sender<Result<ExecutedActionEntry, Status>>,
+    JoinHandle<ActionOutcome>,
+) {
+    let (recording_sender, mut recording_receiver) =
+        channel::<Result<ExecutedActionEntry, Status>>(50);
+    let log_processor = LogProcessor::new(deployment_configuration.log_processing.as_ref());
+    let recording_task = tokio::spawn(async move {
+        let mut failed = false;
+        let mut excerpt_lines = VecDeque::<String>::with_capacity(FAILURE_EXCERPT_LINES);
+        let mut log_lines = VecDeque::<String>::new();
+        let mut log_size = 0;
+        while let Some(entry) = recording_receiver.recv().await {
+            let entry = match entry {
+                Ok(action_entry) => match log_processor.process_entry(action_entry) {
+                    Some(action_entry) => Ok(action_entry),
+                    None => continue,
+                },
+                Err(status) => Err(status),
+            };
+            let excerpt_line = match &entry {
+                Ok(action_entry) => {
+                    if action_entry.action_status == i32::from(ActionStatus::CompletedFailure) {
+                        failed = true;
+                    }
+                    action_entry
+                        .action_log_entry
+                        .as_ref()
+                        .filter(|log_entry| log_entry.stream_type == i32::from(LogType::Stderr))
+                        .map(|log_entry| log_entry.content.clone())
+                }
+                Err(status) => {
+                    failed = true;
+                    Some(status.message().to_string())
+                }
+            };
+            if let Some(excerpt_line) = excerpt_line {
+                if excerpt_lines.len() == FAILURE_EXCERPT_LINES {
+                    excerpt_lines.pop_front();
+                }
+                excerpt_lines.push_back(excerpt_line);
+            }
+            let log_line = format_log_line(&entry);
+            log_size += log_line.len();
+            log_lines.push_back(log_line);
+            while log_size > FAILURE_LOG_MAX_BYTES {
+                match log_lines.pop_front() {
+                    Some(dropped_line) => log_size -= dropped_line.len(),
+                    None => break,
+                }
+            }
+
+            output_sender.send(entry).await.ok();
+        }
+
+        let failure_excerpt = if failed && !excerpt_lines.is_empty() {
+            Some(Vec::from(excerpt_lines).join("\n"))
+        } else {
+            None
+        };
+        let failure_log = if failed {
+            Some(Vec::from(log_lines))
+        } else {
+            None
+        };
+        ActionOutcome {
+            failed,
+            failure_excerpt,
+            failure_log,
+        }
+    });
+    (recording_sender, recording_task)
+}
+
+/// Formats the given executed action entry as line of the failure log, prefixed with the action and the log stream
+/// or status of the entry.
+///
+/// # Arguments
+/// * `entry` - The entry to format.
+fn format_log_line(entry: &Result<ExecutedActionEntry, Status>) -> String {
+    let action_entry = match entry {
+        Ok(action_entry) => action_entry,
+        Err(status) => return format!("[ERROR] {}", status.message()),
+    };
+    let action = Action::try_from(action_entry.current_action)
+        .map(|action| action.as_str_name().to_string())
+        .unwrap_or_else(|err| format!("UNKNOWN_{}", err.0));
+    match &action_entry.action_log_entry {
+        Some(log_entry) => {
+            let stream = LogType::try_from(log_entry.stream_type)
+                .map(|stream| stream.as_str_name())
+                .unwrap_or("UNKNOWN");
+            format!("[{}] [{}] {}", action, stream, log_entry.content)
+        }
+        None => {
+            let status = ActionStatus::try_from(action_entry.action_status)
+                .map(|status| status.as_str_name())
+                .unwrap_or("UNKNOWN");
+            format!("[{}] {}", action, status)
+        }
+    }
+}
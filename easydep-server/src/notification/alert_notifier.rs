@@ -0,0 +1,148 @@
+/*
+ * This file is part of easydep, licensed under the MIT License (MIT).
+ *
+ * Copyright (c) 2024 easybill GmbH
+ *
+ * Permission is hereby granted, free of charge, to any person obtaining a copy
+ * of this software and associated documentation files (the "Software"), to deal
+ * in the Software without restriction, including without limitation the rights
+ * to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+ * copies of the Software, and to permit persons to whom the Software is
+ * furnished to do so, subject to the following conditions:
+ *
+ * The above copyright notice and this permission notice shall be included in all
+ * copies or substantial portions of the Software.
+ *
+ * THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+ * IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+ * FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+ * AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+ * LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+ * OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+ * SOFTWARE.
+ */
+
+use std::time::Duration;
+
+use anyhow::bail;
+use serde_json::json;
+use tokio::fs;
+
+use crate::config::{AlertNotifierConfiguration, AlertProvider};
+use crate::notification::deployment_notification::DeploymentNotification;
+
+/// The time after which a request to an alerting service is aborted.
+const ALERT_TIMEOUT: Duration = Duration::from_secs(10);
+/// The default base url of the PagerDuty Events API.
+const PAGER_DUTY_API_URL: &str = "https://events.pagerduty.com";
+/// The default base url of the Opsgenie Alert API.
+const OPSGENIE_API_URL: &str = "https://api.opsgenie.com";
+/// The maximum length of the message of an Opsgenie alert.
+const OPSGENIE_MESSAGE_MAX_LENGTH: usize = 130;
+
+/// The action to execute for an incident in an alerting service.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub(crate) enum AlertAction {
+    /// Opens the incident, or adds to an already opened incident with the same deduplication key.
+    Trigger,
+    /// Resolves the incident with the deduplication key.
+    Resolve,
+}
+
+/// Opens or resolves the incident for the release of the given notification in the given alerting service. The
+/// incidents are deduplicated based on the profile and the release, so that a release that fails repeatedly
+/// only opens a single incident.
+///
+/// # Arguments
+/// * `http_client` - The http client to send the request with.
+/// * `alert` - The configuration of the alerting service.
+/// * `alert_action` - The action to execute for the incident.
+/// * `notification` - The notification the incident is about.
+/// * `message` - The rendered notification message, used as the description of the incident.
+pub async fn send_alert(
+    http_client: &reqwest::Client,
+    alert: &AlertNotifierConfiguration,
+    alert_action: AlertAction,
+    notification: &DeploymentNotification,
+    message: String,
+) -> anyhow::Result<()> {
+    let key = fs::read_to_string(&alert.key_file).await?;
+    let key = key.trim();
+    let dedup_key = format!(
+        "easydep-{}-{}",
+        notification.profile, notification.release_id
+    );
+    let summary = message.lines().next().unwrap_or_default().to_string();
+
+    let request = match alert.provider {
+        AlertProvider::PagerDuty => {
+            let api_url = alert.api_url.as_deref().unwrap_or(PAGER_DUTY_API_URL);
+            let body = match alert_action {
+                AlertAction::Trigger => json!({
+                    "routing_key": key,
+                    "event_action": "trigger",
+                    "dedup_key": dedup_key,
+                    "payload": {
+                        "summary": summary,
+                        "source": notification.server,
+                        "severity": alert.severity.as_deref().unwrap_or("critical"),
+                        "component": notification.profile,
+                        "custom_details": {
+                            "message": message,
+                            "release_id": notification.release_id,
+                            "release_tag": notification.release_tag,
+                            "action": notification.action,
+                            "triggered_by": notification.triggered_by,
+                        },
+                    },
+                }),
+                AlertAction::Resolve => json!({
+                    "routing_key": key,
+                    "event_action": "resolve",
+                    "dedup_key": dedup_key,
+                }),
+            };
+            http_client
+                .post(format!("{}/v2/enqueue", api_url.trim_end_matches('/')))
+                .json(&body)
+        }
+        AlertProvider::Opsgenie => {
+            let api_url = alert.api_url.as_deref().unwrap_or(OPSGENIE_API_URL);
+            let api_url = api_url.trim_end_matches('/');
+            let request = match alert_action {
+                AlertAction::Trigger => {
+                    let body = json!({
+                        "message": summary.chars().take(OPSGENIE_MESSAGE_MAX_LENGTH).collect::<String>(),
+                        "alias": dedup_key,
+                        "description": message,
+                        "source": notification.server,
+                        "priority": alert.severity.as_deref().unwrap_or("P1"),
+                        "tags": ["easydep", notification.profile],
+                        "details": {
+                            "release_id": notification.release_id.to_string(),
+                            "release_tag": notification.release_tag,
+                            "action": notification.action,
+                            "triggered_by": notification.triggered_by,
+                        },
+                    });
+                    http_client
+                        .post(format!("{}/v2/alerts", api_url))
+                        .json(&body)
+                }
+                AlertAction::Resolve => http_client
+                    .post(format!(
+                        "{}/v2/alerts/{}/close?identifierType=alias",
+                        api_url, dedup_key
+                    ))
+                    .json(&json!({ "source": notification.server })),
+            };
+            request.header("Authorization", format!("GenieKey {}", key))
+        }
+    };
+
+    let response = request.timeout(ALERT_TIMEOUT).send().await?;
+    if !response.status().is_success() {
+        bail!("alerting service responded with {}", response.status())
+    }
+    Ok(())
+}
@@ -0,0 +1,189 @@
+/*
+ * This file is part of easydep, licensed under the MIT License (MIT).
+ *
+ * Copyright (c) 2024 easybill GmbH
+ *
+ * Permission is hereby granted, free of charge, to any person obtaining a copy
+ * of this software and associated documentation files (the "Software"), to deal
+ * in the Software without restriction, including without limitation the rights
+ * to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+ * copies of the Software, and to permit persons to whom the Software is
+ * furnished to do so, subject to the following conditions:
+ *
+ * The above copyright notice and this permission notice shall be included in all
+ * copies or substantial portions of the Software.
+ *
+ * THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+ * IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+ * FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+ * AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+ * LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+ * OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+ * SOFTWARE.
+ */
+
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::Duration;
+
+use octocrab::models::repos::Release;
+use serde::Serialize;
+
+use crate::config::{DeploymentConfiguration, NotificationEvent};
+use crate::easydep::DeployAnnotation;
+use crate::notification::action_outcome_recorder::ActionOutcome;
+use crate::service::request_identity::RequestIdentity;
+
+/// A notification about a deployment lifecycle event. All fields are available as variables in the templates of the
+/// notification messages.
+#[derive(Serialize, Clone, Debug)]
+pub(crate) struct DeploymentNotification {
+    /// The event that is notified about.
+    pub event: NotificationEvent,
+    /// The action that was executed, one of `prepare`, `publish`, `rollback` or `delete`.
+    pub action: String,
+    /// The id of the deployment profile.
+    pub profile: String,
+    /// The target of the deployment profile.
+    pub target: String,
+    /// The id of the release.
+    pub release_id: u64,
+    /// The tag name of the release.
+    pub release_tag: String,
+    /// The name of the release, if any.
+    pub release_name: Option<String>,
+    /// The name of the server on which the action was executed, filled when the notification is dispatched.
+    pub server: String,
+    /// The identity that triggered the action.
+    pub triggered_by: String,
+    /// The time (in seconds) it took to execute the action.
+    pub duration_seconds: u64,
+    /// The last error messages and stderr lines reported by the action, if the action failed.
+    pub failure_excerpt: Option<String>,
+    /// The output of the action that is archived if the action failed, not available in the templates.
+    #[serde(skip)]
+    pub failure_log: Option<Arc<Vec<String>>>,
+    /// The reason that was given when starting, publishing or rolling back, if any.
+    pub reason: Option<String>,
+    /// The ticket that is associated with the action, if any.
+    pub ticket_reference: Option<String>,
+    /// The git ref that was deployed if the release was deployed from a git ref. Recorded in the history, not
+    /// available in the templates.
+    #[serde(skip)]
+    pub git_ref: Option<String>,
+    /// The metadata that was attached to the deployment of the release. Recorded in the history, not available in the
+    /// templates.
+    #[serde(skip)]
+    pub metadata: HashMap<String, String>,
+}
+
+impl DeploymentNotification {
+    /// Constructs a new notification for the given action outcome. The notification is about the given event if the
+    /// action succeeded, otherwise the notification is about the failure of the action.
+    ///
+    /// # Arguments
+    /// * `event` - The event to notify about if the action succeeded.
+    /// * `action` - The name of the action that was executed.
+    /// * `release` - The release on which the action was executed.
+    /// * `deployment_configuration` - The deployment profile configuration used for the action.
+    /// * `triggered_by` - The identity that triggered the action.
+    /// * `duration` - The time it took to execute the action.
+    /// * `outcome` - The recorded outcome of the action.
+    pub fn from_outcome(
+        event: NotificationEvent,
+        action: &str,
+        release: &Release,
+        deployment_configuration: &DeploymentConfiguration,
+        triggered_by: &RequestIdentity,
+        duration: Duration,
+        outcome: ActionOutcome,
+    ) -> Self {
+        let event = if outcome.failed {
+            NotificationEvent::Failed
+        } else {
+            event
+        };
+        Self {
+            event,
+            action: action.to_string(),
+            profile: deployment_configuration.id.clone(),
+            target: deployment_configuration.target.clone(),
+            release_id: release.id.0,
+            release_tag: release.tag_name.clone(),
+            release_name: release.name.clone(),
+            server: String::new(),
+            triggered_by: triggered_by.to_string(),
+            duration_seconds: duration.as_secs(),
+            failure_excerpt: outcome.failure_excerpt,
+            failure_log: outcome.failure_log.map(Arc::new),
+            reason: None,
+            ticket_reference: None,
+            git_ref: None,
+            metadata: HashMap::new(),
+        }
+    }
+
+    /// Constructs a new notification about an action that did not progress for the given duration.
+    ///
+    /// # Arguments
+    /// * `action` - The name of the action that is stuck.
+    /// * `release` - The release on which the action is executed.
+    /// * `deployment_configuration` - The deployment profile configuration used for the action.
+    /// * `triggered_by` - The identity that triggered the action.
+    /// * `stuck_duration` - The time for which the action did not progress.
+    pub fn from_stuck_action(
+        action: &str,
+        release: &Release,
+        deployment_configuration: &DeploymentConfiguration,
+        triggered_by: &RequestIdentity,
+        stuck_duration: Duration,
+    ) -> Self {
+        Self {
+            event: NotificationEvent::Stuck,
+            action: action.to_string(),
+            profile: deployment_configuration.id.clone(),
+            target: deployment_configuration.target.clone(),
+            release_id: release.id.0,
+            release_tag: release.tag_name.clone(),
+            release_name: release.name.clone(),
+            server: String::new(),
+            triggered_by: triggered_by.to_string(),
+            duration_seconds: stuck_duration.as_secs(),
+            failure_excerpt: None,
+            failure_log: None,
+            reason: None,
+            ticket_reference: None,
+            git_ref: None,
+            metadata: HashMap::new(),
+        }
+    }
+
+    /// Sets the reason and ticket reference of this notification to the ones of the given annotation.
+    ///
+    /// # Arguments
+    /// * `annotation` - The annotation that was given for the action, if any.
+    pub fn with_annotation(mut self, annotation: Option<&DeployAnnotation>) -> Self {
+        self.reason = annotation.map(|annotation| annotation.message.clone());
+        self.ticket_reference =
+            annotation.and_then(|annotation| annotation.ticket_reference.clone());
+        self
+    }
+
+    /// Sets the git ref that was deployed, if the release was deployed from a git ref.
+    ///
+    /// # Arguments
+    /// * `git_ref` - The git ref that was deployed, if any.
+    pub fn with_git_ref(mut self, git_ref: Option<String>) -> Self {
+        self.git_ref = git_ref;
+        self
+    }
+
+    /// Sets the metadata that was attached to the deployment of the release.
+    ///
+    /// # Arguments
+    /// * `metadata` - The metadata of the deployment.
+    pub fn with_metadata(mut self, metadata: HashMap<String, String>) -> Self {
+        self.metadata = metadata;
+        self
+    }
+}
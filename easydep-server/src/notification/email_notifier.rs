@@ -0,0 +1,91 @@
+/*
+ * This file is part of easydep, licensed under the MIT License (MIT).
+ *
+ * Copyright (c) 2024 easybill GmbH
+ *
+ * Permission is hereby granted, free of charge, to any person obtaining a copy
+ * of this software and associated documentation files (the "Software"), to deal
+ * in the Software without restriction, including without limitation the rights
+ * to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+ * copies of the Software, and to permit persons to whom the Software is
+ * furnished to do so, subject to the following conditions:
+ *
+ * The above copyright notice and this permission notice shall be included in all
+ * copies or substantial portions of the Software.
+ *
+ * THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+ * IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+ * FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+ * AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+ * LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+ * OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+ * SOFTWARE.
+ */
+
+use std::time::Duration;
+
+use lettre::message::header::ContentType;
+use lettre::message::Mailbox;
+use lettre::transport::smtp::authentication::Credentials;
+use lettre::{AsyncSmtpTransport, AsyncTransport, Message, Tokio1Executor};
+use tokio::fs;
+
+use crate::config::{EmailNotifierConfiguration, EmailTlsMode};
+
+/// The time after which sending a notification email is aborted.
+const SMTP_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// Sends the given notification message as plain text email through the given smtp server. The email is sent to the
+/// recipients configured for all profiles and the recipients configured for the given profile. Nothing is sent if
+/// there are no recipients.
+///
+/// # Arguments
+/// * `email` - The configuration of the smtp server to send the email through.
+/// * `profile` - The id of the deployment profile the notification is about.
+/// * `subject` - The rendered subject of the email.
+/// * `message` - The rendered notification message.
+pub async fn send_email_notification(
+    email: &EmailNotifierConfiguration,
+    profile: &String,
+    subject: String,
+    message: String,
+) -> anyhow::Result<()> {
+    let profile_recipients = email.profile_recipients.get(profile).into_iter().flatten();
+    let recipients = email
+        .recipients
+        .iter()
+        .chain(profile_recipients)
+        .collect::<Vec<&String>>();
+    if recipients.is_empty() {
+        return Ok(());
+    }
+
+    let mut message_builder = Message::builder()
+        .from(email.from.parse::<Mailbox>()?)
+        .subject(subject)
+        .header(ContentType::TEXT_PLAIN);
+    for recipient in recipients {
+        message_builder = message_builder.to(recipient.parse::<Mailbox>()?);
+    }
+    let email_message = message_builder.body(message)?;
+
+    let mut transport_builder = match email.tls {
+        EmailTlsMode::StartTls => {
+            AsyncSmtpTransport::<Tokio1Executor>::starttls_relay(&email.host)?
+        }
+        EmailTlsMode::Tls => AsyncSmtpTransport::<Tokio1Executor>::relay(&email.host)?,
+        EmailTlsMode::None => AsyncSmtpTransport::<Tokio1Executor>::builder_dangerous(&email.host),
+    };
+    if let Some(port) = email.port {
+        transport_builder = transport_builder.port(port);
+    }
+    if let (Some(username), Some(password_file)) = (&email.username, &email.password_file) {
+        let password = fs::read_to_string(password_file).await?;
+        let credentials = Credentials::new(username.clone(), password.trim().to_string());
+        transport_builder = transport_builder.credentials(credentials);
+    }
+
+    let transport = transport_builder.timeout(Some(SMTP_TIMEOUT)).build();
+    transport.send(email_message).await?;
+    Ok(())
+}
@@ -0,0 +1,369 @@
+/*
+ * This file is part of easydep, licensed under the MIT License (MIT).
+ *
+ * Copyright (c) 2024 easybill GmbH
+ *
+ * Permission is hereby granted, free of charge, to any person obtaining a copy
+ * of this software and associated documentation files (the "Software"), to deal
+ * in the Software without restriction, including without limitation the rights
+ * to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+ * copies of the Software, and to permit persons to whom the Software is
+ * furnished to do so, subject to the following conditions:
+ *
+ * The above copyright notice and this permission notice shall be included in all
+ * copies or substantial portions of the Software.
+ *
+ * THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+ * IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+ * FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+ * AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+ * LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+ * OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+ * SOFTWARE.
+ */
+
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use anyhow::Context;
+use handlebars::Handlebars;
+use log::warn;
+
+use crate::accessor::deployment_history_accessor::DeploymentHistoryAccessor;
+use crate::accessor::failed_action_archive_accessor::FailedActionArchiveAccessor;
+use crate::config::{
+    AlertNotifierConfiguration, Configuration, EmailNotifierConfiguration,
+    NotificationConfiguration, NotificationEvent, WebhookNotifierConfiguration,
+};
+use crate::metrics::metrics_reporter::MetricsReporter;
+use crate::notification::alert_notifier::{send_alert, AlertAction};
+use crate::notification::deployment_notification::DeploymentNotification;
+use crate::notification::email_notifier::send_email_notification;
+use crate::notification::webhook_notifier::send_webhook_notification;
+
+/// The name of the template set containing the global templates.
+const GLOBAL_TEMPLATE_SET: &str = "global";
+/// The name of the template containing the subject of notification emails.
+const EMAIL_SUBJECT_TEMPLATE: &str = "subject";
+/// The events that are sent to alerting services, failures and stuck actions open an incident and successful publishes
+/// resolve it.
+const ALERT_EVENTS: [NotificationEvent; 3] = [
+    NotificationEvent::Failed,
+    NotificationEvent::Stuck,
+    NotificationEvent::Published,
+];
+
+/// Dispatches notifications about deployment lifecycle events to the configured notifiers.
+#[derive(Clone, Debug)]
+pub(crate) struct NotificationDispatcher {
+    /// The notification settings, `None` if notifications are disabled.
+    notification_configuration: Option<Arc<NotificationConfiguration>>,
+    /// The registry containing the compiled message templates.
+    template_registry: Arc<Handlebars<'static>>,
+    /// The name of this server that is used in the notifications.
+    server_name: String,
+    /// The http client to send notifications with.
+    http_client: reqwest::Client,
+    /// The reporter to record the metrics of the lifecycle events, `None` if metrics are disabled.
+    metrics_reporter: Option<MetricsReporter>,
+    /// The accessor to record the finished actions in the deployment history.
+    history_accessor: DeploymentHistoryAccessor,
+    /// The accessor to archive the output and metadata of failed actions.
+    archive_accessor: FailedActionArchiveAccessor,
+}
+
+impl NotificationDispatcher {
+    /// Constructs a new notification dispatcher for the given configuration, compiling all configured templates. An
+    /// error is returned if one of the templates is invalid.
+    ///
+    /// # Arguments
+    /// * `config` - The server configuration.
+    pub fn new(config: &Configuration) -> anyhow::Result<Self> {
+        let mut template_registry = Handlebars::new();
+        template_registry.register_escape_fn(handlebars::no_escape);
+        for event in NotificationEvent::all() {
+            let template_name = get_template_name(GLOBAL_TEMPLATE_SET, event.name());
+            template_registry
+                .register_template_string(&template_name, get_default_template(&event))
+                .context("unable to compile default notification template")?;
+        }
+
+        let notification_configuration = config.notifications.clone();
+        if let Some(notification_configuration) = &notification_configuration {
+            register_templates(
+                &mut template_registry,
+                GLOBAL_TEMPLATE_SET,
+                &notification_configuration.templates,
+            )?;
+            for webhook in &notification_configuration.webhooks {
+                register_templates(&mut template_registry, &webhook.name, &webhook.templates)?;
+            }
+            for alert in &notification_configuration.alerts {
+                register_templates(&mut template_registry, &alert.name, &alert.templates)?;
+            }
+            for email in &notification_configuration.emails {
+                register_templates(&mut template_registry, &email.name, &email.templates)?;
+                let subject_template_name = get_template_name(&email.name, EMAIL_SUBJECT_TEMPLATE);
+                template_registry
+                    .register_template_string(&subject_template_name, &email.subject)
+                    .with_context(|| format!("invalid email subject of {}", email.name))?;
+            }
+        }
+
+        let server_name = notification_configuration
+            .as_ref()
+            .and_then(|notification_configuration| notification_configuration.server_name.clone())
+            .unwrap_or_else(read_hostname);
+        Ok(Self {
+            notification_configuration: notification_configuration.map(Arc::new),
+            template_registry: Arc::new(template_registry),
+            server_name,
+            http_client: reqwest::Client::new(),
+            metrics_reporter: config.metrics.as_ref().map(MetricsReporter::new),
+            history_accessor: DeploymentHistoryAccessor::new(config),
+            archive_accessor: FailedActionArchiveAccessor::new(config),
+        })
+    }
+
+    /// Dispatches the given notification to all notifiers that are interested in it and records its metrics and the
+    /// deployment history. The notifications are sent in the background, failures are only logged.
+    ///
+    /// # Arguments
+    /// * `notification` - The notification to dispatch.
+    pub fn dispatch(&self, mut notification: DeploymentNotification) {
+        notification.server = self.server_name.clone();
+        if let Some(metrics_reporter) = &self.metrics_reporter {
+            metrics_reporter.record(&notification);
+        }
+        self.record_history(&notification);
+
+        let notification_configuration = match &self.notification_configuration {
+            Some(notification_configuration) => notification_configuration.clone(),
+            None => return,
+        };
+        let dispatcher = self.clone();
+        tokio::spawn(async move {
+            for webhook in &notification_configuration.webhooks {
+                if is_subscribed(&webhook.events, &webhook.profiles, &notification) {
+                    if let Err(err) = dispatcher.notify_webhook(webhook, &notification).await {
+                        warn!("Unable to send notification to {}: {err:?}", webhook.name);
+                    }
+                }
+            }
+            for email in &notification_configuration.emails {
+                if is_subscribed(&email.events, &email.profiles, &notification) {
+                    if let Err(err) = dispatcher.notify_email(email, &notification).await {
+                        warn!("Unable to send notification to {}: {err:?}", email.name);
+                    }
+                }
+            }
+            for alert in &notification_configuration.alerts {
+                if is_subscribed(&ALERT_EVENTS, &alert.profiles, &notification) {
+                    if let Err(err) = dispatcher.notify_alert(alert, &notification).await {
+                        warn!("Unable to send alert to {}: {err:?}", alert.name);
+                    }
+                }
+            }
+        });
+    }
+
+    /// Records the action described by the given notification in the deployment history, without sending the
+    /// notification. The output of failed actions is archived first and referenced from the history entry. The entry
+    /// is written in the background, failures are only logged.
+    ///
+    /// # Arguments
+    /// * `notification` - The notification about the finished action.
+    pub fn record_history(&self, notification: &DeploymentNotification) {
+        let history_accessor = self.history_accessor.clone();
+        let archive_accessor = self.archive_accessor.clone();
+        let notification = notification.clone();
+        tokio::spawn(async move {
+            let mut failure_archive = None;
+            if let Some(failure_log) = &notification.failure_log {
+                match archive_accessor
+                    .archive_failed_action(&notification, failure_log)
+                    .await
+                {
+                    Ok(archive_name) => failure_archive = archive_name,
+                    Err(err) => warn!(
+                        "Unable to archive failed {} of release {} in {}: {err:?}",
+                        notification.action, notification.release_id, notification.profile
+                    ),
+                }
+            }
+            if let Err(err) = history_accessor
+                .record_action(&notification, failure_archive)
+                .await
+            {
+                warn!(
+                    "Unable to record {} of release {} in the history of {}: {err:?}",
+                    notification.action, notification.release_id, notification.profile
+                );
+            }
+        });
+    }
+
+    /// Sends the given notification to the given webhook.
+    ///
+    /// # Arguments
+    /// * `webhook` - The configuration of the webhook to notify.
+    /// * `notification` - The notification to send.
+    async fn notify_webhook(
+        &self,
+        webhook: &WebhookNotifierConfiguration,
+        notification: &DeploymentNotification,
+    ) -> anyhow::Result<()> {
+        let message = self.render_message(&webhook.name, notification)?;
+        send_webhook_notification(&self.http_client, webhook, notification, message).await
+    }
+
+    /// Sends the given notification as email through the given smtp server.
+    ///
+    /// # Arguments
+    /// * `email` - The configuration of the smtp server to send the email through.
+    /// * `notification` - The notification to send.
+    async fn notify_email(
+        &self,
+        email: &EmailNotifierConfiguration,
+        notification: &DeploymentNotification,
+    ) -> anyhow::Result<()> {
+        let subject_template_name = get_template_name(&email.name, EMAIL_SUBJECT_TEMPLATE);
+        let subject = self
+            .template_registry
+            .render(&subject_template_name, notification)?;
+        let message = self.render_message(&email.name, notification)?;
+        send_email_notification(email, &notification.profile, subject, message).await
+    }
+
+    /// Opens the incident for a failed action in the given alerting service, or resolves it if the release was
+    /// published successfully.
+    ///
+    /// # Arguments
+    /// * `alert` - The configuration of the alerting service.
+    /// * `notification` - The notification the incident is about.
+    async fn notify_alert(
+        &self,
+        alert: &AlertNotifierConfiguration,
+        notification: &DeploymentNotification,
+    ) -> anyhow::Result<()> {
+        let alert_action = match notification.event {
+            NotificationEvent::Failed | NotificationEvent::Stuck => AlertAction::Trigger,
+            _ => AlertAction::Resolve,
+        };
+        let message = self.render_message(&alert.name, notification)?;
+        send_alert(
+            &self.http_client,
+            alert,
+            alert_action,
+            notification,
+            message,
+        )
+        .await
+    }
+
+    /// Renders the message of the given notification, using the template of the given notifier if it defines one
+    /// and the global template otherwise.
+    ///
+    /// # Arguments
+    /// * `notifier_name` - The name of the notifier for which the message is rendered.
+    /// * `notification` - The notification to render the message of.
+    fn render_message(
+        &self,
+        notifier_name: &str,
+        notification: &DeploymentNotification,
+    ) -> anyhow::Result<String> {
+        let notifier_template_name = get_template_name(notifier_name, notification.event.name());
+        let template_name = if self.template_registry.has_template(&notifier_template_name) {
+            notifier_template_name
+        } else {
+            get_template_name(GLOBAL_TEMPLATE_SET, notification.event.name())
+        };
+        let message = self
+            .template_registry
+            .render(&template_name, notification)
+            .with_context(|| format!("unable to render notification template {template_name}"))?;
+        Ok(message)
+    }
+}
+
+/// Checks if a notifier with the given event and profile filters is subscribed to the given notification. Empty
+/// filters match all events or profiles.
+///
+/// # Arguments
+/// * `events` - The events the notifier is subscribed to.
+/// * `profiles` - The ids of the deployment profiles the notifier is subscribed to.
+/// * `notification` - The notification to check.
+pub fn is_subscribed(
+    events: &[NotificationEvent],
+    profiles: &[String],
+    notification: &DeploymentNotification,
+) -> bool {
+    (events.is_empty() || events.contains(&notification.event))
+        && (profiles.is_empty() || profiles.contains(&notification.profile))
+}
+
+/// Compiles the given templates and registers them in the given template set.
+///
+/// # Arguments
+/// * `template_registry` - The registry to register the templates in.
+/// * `template_set` - The name of the template set, either the global set or the name of a notifier.
+/// * `templates` - The templates to register, keyed by the event they are used for.
+fn register_templates(
+    template_registry: &mut Handlebars<'static>,
+    template_set: &str,
+    templates: &HashMap<NotificationEvent, String>,
+) -> anyhow::Result<()> {
+    for (event, template) in templates {
+        let template_name = get_template_name(template_set, event.name());
+        template_registry
+            .register_template_string(&template_name, template)
+            .with_context(|| format!("invalid notification template {template_name}"))?;
+    }
+    Ok(())
+}
+
+/// Get the name under which the given template is registered in the given template set.
+///
+/// # Arguments
+/// * `template_set` - The name of the template set, either the global set or the name of a notifier.
+/// * `template` - The name of the template in the set, usually the name of the event it is used for.
+fn get_template_name(template_set: &str, template: &str) -> String {
+    format!("{}/{}", template_set, template)
+}
+
+/// Get the template that is used for the given event if no template is configured for it.
+///
+/// # Arguments
+/// * `event` - The event to get the default template of.
+fn get_default_template(event: &NotificationEvent) -> &'static str {
+    match event {
+        NotificationEvent::Prepared => {
+            "Release {{release_tag}} was prepared on {{server}} using profile {{profile}} (triggered by {{triggered_by}}){{#if reason}}\nReason: {{reason}}{{#if ticket_reference}} [{{ticket_reference}}]{{/if}}{{/if}}"
+        }
+        NotificationEvent::Published => {
+            "Release {{release_tag}} was published on {{server}} using profile {{profile}} (triggered by {{triggered_by}}){{#if reason}}\nReason: {{reason}}{{#if ticket_reference}} [{{ticket_reference}}]{{/if}}{{/if}}"
+        }
+        NotificationEvent::Failed => {
+            "Executing {{action}} of release {{release_tag}} failed on {{server}} using profile {{profile}} (triggered by {{triggered_by}}){{#if reason}}\nReason: {{reason}}{{#if ticket_reference}} [{{ticket_reference}}]{{/if}}{{/if}}{{#if failure_excerpt}}\n{{failure_excerpt}}{{/if}}"
+        }
+        NotificationEvent::RolledBack => {
+            "Profile {{profile}} was rolled back to release {{release_tag}} on {{server}} (triggered by {{triggered_by}}){{#if reason}}\nReason: {{reason}}{{#if ticket_reference}} [{{ticket_reference}}]{{/if}}{{/if}}"
+        }
+        NotificationEvent::Deleted => {
+            "Prepared release {{release_tag}} was deleted on {{server}} using profile {{profile}} (triggered by {{triggered_by}})"
+        }
+        NotificationEvent::Stuck => {
+            "Executing {{action}} of release {{release_tag}} on {{server}} using profile {{profile}} did not progress for {{duration_seconds}} seconds (triggered by {{triggered_by}})"
+        }
+    }
+}
+
+/// Reads the hostname of this server, falling back to `unknown` if the hostname cannot be read.
+fn read_hostname() -> String {
+    std::env::var("HOSTNAME")
+        .ok()
+        .or_else(|| std::fs::read_to_string("/etc/hostname").ok())
+        .map(|hostname| hostname.trim().to_string())
+        .filter(|hostname| !hostname.is_empty())
+        .unwrap_or_else(|| "unknown".to_string())
+}
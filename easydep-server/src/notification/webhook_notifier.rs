@@ -0,0 +1,72 @@
+/*
+ * This file is part of easydep, licensed under the MIT License (MIT).
+ *
+ * Copyright (c) 2024 easybill GmbH
+ *
+ * Permission is hereby granted, free of charge, to any person obtaining a copy
+ * of this software and associated documentation files (the "Software"), to deal
+ * in the Software without restriction, including without limitation the rights
+ * to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+ * copies of the Software, and to permit persons to whom the Software is
+ * furnished to do so, subject to the following conditions:
+ *
+ * The above copyright notice and this permission notice shall be included in all
+ * copies or substantial portions of the Software.
+ *
+ * THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+ * IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+ * FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+ * AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+ * LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+ * OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+ * SOFTWARE.
+ */
+
+use std::time::Duration;
+
+use anyhow::bail;
+use serde_json::json;
+
+use crate::config::{WebhookFormat, WebhookNotifierConfiguration};
+use crate::notification::deployment_notification::DeploymentNotification;
+
+/// The time after which sending a notification to a webhook is aborted.
+const WEBHOOK_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Posts the given notification message to the given webhook. Using the Slack format, the message is sent as the
+/// `text` field of a json object, which is understood by Slack-compatible incoming webhooks. Using the json format, all
+/// fields of the notification are sent alongside the message, so that generic consumers can process them.
+///
+/// # Arguments
+/// * `http_client` - The http client to send the notification with.
+/// * `webhook` - The configuration of the webhook to send the notification to.
+/// * `notification` - The notification to send.
+/// * `message` - The rendered notification message.
+pub async fn send_webhook_notification(
+    http_client: &reqwest::Client,
+    webhook: &WebhookNotifierConfiguration,
+    notification: &DeploymentNotification,
+    message: String,
+) -> anyhow::Result<()> {
+    let payload = match webhook.format {
+        WebhookFormat::Slack => json!({ "text": message }),
+        WebhookFormat::Json => {
+            let mut payload = serde_json::to_value(notification)?;
+            payload["message"] = json!(message);
+            payload
+        }
+    };
+    let mut request = http_client
+        .post(&webhook.url)
+        .timeout(WEBHOOK_TIMEOUT)
+        .json(&payload);
+    for (header_name, header_value) in &webhook.headers {
+        request = request.header(header_name, header_value);
+    }
+
+    let response = request.send().await?;
+    if !response.status().is_success() {
+        bail!("webhook responded with {}", response.status())
+    }
+    Ok(())
+}
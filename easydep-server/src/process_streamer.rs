@@ -22,17 +22,91 @@
  * SOFTWARE.
  */
 
+use std::future::pending;
+use std::io;
 use std::io::Error;
+use std::process::ExitStatus;
+use std::time::Duration;
 
 use anyhow::{anyhow, Context};
+use log::warn;
 use tokio::io::{AsyncBufReadExt, BufReader};
 use tokio::process::Child;
 use tokio::sync::mpsc::Sender;
+use tokio::time::{sleep, timeout};
 use tokio_stream::wrappers::LinesStream;
 use tokio_stream::StreamExt;
 use tonic::Status;
 
 use crate::easydep::{Action, ActionStatus, ExecutedActionEntry, LogEntry, LogType};
+use crate::executor::action_cancellation::ActionCancellation;
+
+/// The default time that terminated processes get to exit after being asked to terminate, before they are killed.
+const PROCESS_TERMINATION_GRACE_PERIOD: Duration = Duration::from_secs(10);
+/// The prefix of log lines that are interpreted as directives rather than being streamed as a log line.
+const SCRIPT_DIRECTIVE_PREFIX: &str = "::easydep::";
+
+/// A directive that a script can print to report structured information, in the form `::easydep::<name> <value>`.
+#[derive(Debug, Clone, Eq, PartialEq)]
+enum ScriptDirective {
+    /// Reports the progress of the script in percent, for example `::easydep::progress 42`.
+    Progress(u32),
+    /// Reports the name of the phase the script entered, for example `::easydep::phase build`.
+    Phase(String),
+}
+
+impl ScriptDirective {
+    /// Parses the directive from the given log line, returning `None` if the line is not a known directive.
+    ///
+    /// # Arguments
+    /// * `line` - The log line to parse.
+    fn parse(line: &str) -> Option<Self> {
+        let directive = line.trim().strip_prefix(SCRIPT_DIRECTIVE_PREFIX)?;
+        let (name, value) = directive.split_once(' ')?;
+        let value = value.trim();
+        match name {
+            "progress" => value
+                .parse::<u32>()
+                .ok()
+                .map(|progress| Self::Progress(progress.min(100))),
+            "phase" if !value.is_empty() => Some(Self::Phase(value.to_string())),
+            _ => None,
+        }
+    }
+
+    /// Converts this directive into an executed action entry reporting the directive value.
+    ///
+    /// # Arguments
+    /// * `release_id` - The id of the release being executed.
+    /// * `current_action` - The action that is currently being executed.
+    fn into_executed_action_entry(
+        self,
+        release_id: u64,
+        current_action: Action,
+    ) -> ExecutedActionEntry {
+        let (progress_percent, phase) = match self {
+            Self::Progress(progress) => (Some(progress), None),
+            Self::Phase(phase) => (None, Some(phase)),
+        };
+        ExecutedActionEntry {
+            release_id,
+            current_action: current_action.into(),
+            action_status: ActionStatus::Running.into(),
+            action_log_entry: None,
+            progress_percent,
+            phase,
+        }
+    }
+}
+
+/// The reasons why the process group of a child process was terminated before the child process exited on its own.
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+enum ProcessTermination {
+    /// The action that started the process was cancelled.
+    Cancelled,
+    /// The process did not exit within its timeout.
+    TimedOut,
+}
 
 /// A streamer that streams `ExecutedActionEntry`s to a gRPC client from a spawned child process.
 pub(crate) struct ProcessStreamer {
@@ -40,6 +114,10 @@ pub(crate) struct ProcessStreamer {
     release_id: u64,
     child_process: Child,
     sender: Sender<Result<ExecutedActionEntry, Status>>,
+    redacted_values: Vec<String>,
+    cancellation: Option<ActionCancellation>,
+    execution_timeout: Option<Duration>,
+    termination_grace_period: Duration,
 }
 
 impl ProcessStreamer {
@@ -61,9 +139,53 @@ impl ProcessStreamer {
             release_id,
             child_process,
             sender,
+            redacted_values: Vec::new(),
+            cancellation: None,
+            execution_timeout: None,
+            termination_grace_period: PROCESS_TERMINATION_GRACE_PERIOD,
         }
     }
 
+    /// Sets the signal on which the child process and all processes in its process group are terminated, for example
+    /// when the deployment that started the process is cancelled.
+    ///
+    /// # Arguments
+    /// * `cancellation` - The signal to terminate the child process on.
+    pub(crate) fn with_cancellation(mut self, cancellation: ActionCancellation) -> Self {
+        self.cancellation = Some(cancellation);
+        self
+    }
+
+    /// Sets the time after which the child process and all processes in its process group are terminated if the child
+    /// process did not exit yet. The processes are killed if they do not exit within the given grace period after
+    /// being asked to terminate.
+    ///
+    /// # Arguments
+    /// * `execution_timeout` - The time after which the processes are terminated, `None` if they should not time out.
+    /// * `termination_grace_period` - The time the processes get to exit before they are killed.
+    pub(crate) fn with_timeout(
+        mut self,
+        execution_timeout: Option<Duration>,
+        termination_grace_period: Duration,
+    ) -> Self {
+        self.execution_timeout = execution_timeout;
+        self.termination_grace_period = termination_grace_period;
+        self
+    }
+
+    /// Sets the values that should be redacted from the log lines captured from the child process, for example
+    /// secrets that were provided to the process.
+    ///
+    /// # Arguments
+    /// * `redacted_values` - The values to replace in captured log lines.
+    pub(crate) fn with_redacted_values(mut self, redacted_values: Vec<String>) -> Self {
+        self.redacted_values = redacted_values
+            .into_iter()
+            .filter(|value| !value.is_empty())
+            .collect();
+        self
+    }
+
     /// Waits for the underlying child process to complete and streams the log output of it into the underlying sender.
     /// This method returns an error if some error occurs or the underlying process does not finish successfully.
     pub(crate) async fn await_child_and_stream(&mut self) -> anyhow::Result<()> {
@@ -87,20 +209,33 @@ impl ProcessStreamer {
             .take()
             .context("Child process has no stderr available")?;
 
-        let stdout_stream = LinesStream::new(BufReader::new(stdout).lines())
-            .map(|entry| Self::construct_log_entry(entry, LogType::Stdout));
-        let stderr_stream = LinesStream::new(BufReader::new(stderr).lines())
-            .map(|entry| Self::construct_log_entry(entry, LogType::Stderr));
+        let stdout_redacted_values = self.redacted_values.clone();
+        let stdout_stream = LinesStream::new(BufReader::new(stdout).lines()).map(move |entry| {
+            let entry = entry.map(|line| Self::redact_line(line, &stdout_redacted_values));
+            Self::construct_log_entry(entry, LogType::Stdout)
+        });
+        let stderr_redacted_values = self.redacted_values.clone();
+        let stderr_stream = LinesStream::new(BufReader::new(stderr).lines()).map(move |entry| {
+            let entry = entry.map(|line| Self::redact_line(line, &stderr_redacted_values));
+            Self::construct_log_entry(entry, LogType::Stderr)
+        });
 
         let action = self.action;
         let release_id = self.release_id;
         let mut combined_stream = stdout_stream.merge(stderr_stream).map(move |log_entry| {
-            Self::construct_executed_action_entry(
-                release_id,
-                action,
-                ActionStatus::Running,
-                Some(log_entry),
-            )
+            let directive = log_entry
+                .as_ref()
+                .ok()
+                .and_then(|log_entry| ScriptDirective::parse(&log_entry.content));
+            match directive {
+                Some(directive) => Ok(directive.into_executed_action_entry(release_id, action)),
+                None => Self::construct_executed_action_entry(
+                    release_id,
+                    action,
+                    ActionStatus::Running,
+                    Some(log_entry),
+                ),
+            }
         });
 
         let sender = self.sender.clone();
@@ -112,8 +247,83 @@ impl ProcessStreamer {
             }
         });
 
-        let (_, process_result) = tokio::join!(stream_task, self.child_process.wait());
+        let cancellation = self.cancellation.clone();
+        let wait_for_cancellation = async move {
+            match cancellation {
+                Some(cancellation) => cancellation.cancelled().await,
+                None => pending().await,
+            }
+        };
+        let execution_timeout = self.execution_timeout;
+        let wait_for_timeout = async move {
+            match execution_timeout {
+                Some(execution_timeout) => sleep(execution_timeout).await,
+                None => pending().await,
+            }
+        };
+        let process_result = tokio::select! {
+            process_result = self.child_process.wait() => Ok(process_result),
+            _ = wait_for_cancellation => Err(ProcessTermination::Cancelled),
+            _ = wait_for_timeout => Err(ProcessTermination::TimedOut),
+        };
+        let (process_result, termination) = match process_result {
+            Ok(process_result) => (process_result, None),
+            Err(termination) => (self.terminate_process_group().await, Some(termination)),
+        };
+
+        // processes that left the process group might still hold the output streams open
+        if termination.is_some() {
+            let stream_abort_handle = stream_task.abort_handle();
+            if timeout(self.termination_grace_period, stream_task)
+                .await
+                .is_err()
+            {
+                stream_abort_handle.abort();
+            }
+        } else {
+            stream_task.await.ok();
+        }
+
         match process_result {
+            Ok(exit_status) if termination == Some(ProcessTermination::Cancelled) => {
+                let log_entry = Self::construct_log_entry(
+                    Ok(format!(
+                        "Process was cancelled and finished with {}",
+                        exit_status
+                    )),
+                    LogType::Stderr,
+                );
+                let action_entry = Self::construct_executed_action_entry(
+                    self.release_id,
+                    self.action,
+                    ActionStatus::CompletedFailure,
+                    Some(log_entry),
+                );
+                self.sender.send(action_entry).await.ok();
+                Err(anyhow!("process was cancelled"))
+            }
+            Ok(exit_status) if termination == Some(ProcessTermination::TimedOut) => {
+                let execution_timeout = self.execution_timeout.unwrap_or_default();
+                let log_entry = Self::construct_log_entry(
+                    Ok(format!(
+                        "Process timed out after {} seconds and finished with {}",
+                        execution_timeout.as_secs(),
+                        exit_status
+                    )),
+                    LogType::Stderr,
+                );
+                let action_entry = Self::construct_executed_action_entry(
+                    self.release_id,
+                    self.action,
+                    ActionStatus::CompletedFailure,
+                    Some(log_entry),
+                );
+                self.sender.send(action_entry).await.ok();
+                Err(anyhow!(
+                    "process timed out after {} seconds",
+                    execution_timeout.as_secs()
+                ))
+            }
             Ok(exit_status) => {
                 let log_entry = Self::construct_log_entry(
                     Ok(format!("Process finished with {}", exit_status)),
@@ -156,6 +366,47 @@ impl ProcessStreamer {
         }
     }
 
+    /// Terminates the process group of the child process, killing it if the processes do not exit within the grace
+    /// period. The child process is the leader of its process group, as spawned by the command runner.
+    #[cfg(unix)]
+    async fn terminate_process_group(&mut self) -> io::Result<ExitStatus> {
+        if let Some(process_id) = self.child_process.id() {
+            let process_group_id = -(process_id as libc::pid_t);
+            // SAFETY: kill has no memory safety requirements, the negative id addresses the process group
+            unsafe { libc::kill(process_group_id, libc::SIGTERM) };
+            match timeout(self.termination_grace_period, self.child_process.wait()).await {
+                Ok(process_result) => return process_result,
+                Err(_) => {
+                    warn!("Terminated process {process_id} did not exit in time, killing it");
+                    // SAFETY: see above
+                    unsafe { libc::kill(process_group_id, libc::SIGKILL) };
+                }
+            }
+        }
+        self.child_process.wait().await
+    }
+
+    /// Kills the child process, as process groups are only available on unix. Processes started by the child process
+    /// are not terminated.
+    #[cfg(not(unix))]
+    async fn terminate_process_group(&mut self) -> io::Result<ExitStatus> {
+        if let Err(err) = self.child_process.kill().await {
+            warn!("Unable to kill process: {err}");
+        }
+        self.child_process.wait().await
+    }
+
+    /// Replaces all occurrences of the given redacted values in the given log line.
+    ///
+    /// # Arguments
+    /// * `line` - The captured log line to redact.
+    /// * `redacted_values` - The values to replace in the log line.
+    fn redact_line(line: String, redacted_values: &[String]) -> String {
+        redacted_values.iter().fold(line, |line, redacted_value| {
+            line.replace(redacted_value, "[redacted]")
+        })
+    }
+
     /// Constructs a new log entry from the given captured log line, returning
     /// back the error if the log line was not captured successfully.
     ///
@@ -194,6 +445,8 @@ impl ProcessStreamer {
                     current_action: current_action.into(),
                     action_status: status.into(),
                     action_log_entry: None,
+                    progress_percent: None,
+                    phase: None,
                 };
                 Ok(action_entry)
             }
@@ -203,6 +456,8 @@ impl ProcessStreamer {
                     current_action: current_action.into(),
                     action_status: status.into(),
                     action_log_entry: Some(log_entry),
+                    progress_percent: None,
+                    phase: None,
                 })
                 .map_err(|err| Status::internal(format!("{:?}", err))),
         }
@@ -0,0 +1,137 @@
+/*
+ * This file is part of easydep, licensed under the MIT License (MIT).
+ *
+ * Copyright (c) 2024 easybill GmbH
+ *
+ * Permission is hereby granted, free of charge, to any person obtaining a copy
+ * of this software and associated documentation files (the "Software"), to deal
+ * in the Software without restriction, including without limitation the rights
+ * to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+ * copies of the Software, and to permit persons to whom the Software is
+ * furnished to do so, subject to the following conditions:
+ *
+ * The above copyright notice and this permission notice shall be included in all
+ * copies or substantial portions of the Software.
+ *
+ * THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+ * IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+ * FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+ * AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+ * LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+ * OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+ * SOFTWARE.
+ */
+
+use log::warn;
+use tonic::service::Interceptor;
+use tonic::{Request, Status};
+
+use crate::accessor::api_token_accessor::ApiTokenAccessor;
+use crate::accessor::oidc_accessor::OidcAccessor;
+use crate::config::AccessRole;
+
+/// The principal that was authenticated for a request, attached to the request extensions by the `AuthInterceptor`.
+#[derive(Clone, Debug)]
+pub(crate) struct AuthenticatedPrincipal {
+    /// The authenticated subject, not present if authentication is disabled.
+    pub subject: Option<String>,
+    /// The roles that are granted to the principal.
+    pub roles: Vec<AccessRole>,
+}
+
+impl AuthenticatedPrincipal {
+    /// Checks if the principal was granted the given role (or a role that includes it).
+    ///
+    /// # Arguments
+    /// * `role` - The role to check.
+    pub fn has_role(&self, role: AccessRole) -> bool {
+        self.roles.iter().any(|granted_role| *granted_role >= role)
+    }
+}
+
+/// An interceptor that authenticates all requests sent to the gRPC services.
+#[derive(Clone, Debug)]
+pub(crate) struct AuthInterceptor {
+    oidc_accessor: Option<OidcAccessor>,
+    api_token_accessor: Option<ApiTokenAccessor>,
+}
+
+impl AuthInterceptor {
+    /// Constructs a new auth interceptor. If neither an OIDC accessor nor an api token accessor is given, all requests
+    /// are allowed.
+    ///
+    /// # Arguments
+    /// * `oidc_accessor` - The accessor used to validate the bearer tokens issued by the OIDC provider.
+    /// * `api_token_accessor` - The accessor used to validate the static tokens configured on the server.
+    pub fn new(
+        oidc_accessor: Option<OidcAccessor>,
+        api_token_accessor: Option<ApiTokenAccessor>,
+    ) -> Self {
+        Self {
+            oidc_accessor,
+            api_token_accessor,
+        }
+    }
+}
+
+impl Interceptor for AuthInterceptor {
+    fn call(&mut self, mut request: Request<()>) -> Result<Request<()>, Status> {
+        if self.oidc_accessor.is_none() && self.api_token_accessor.is_none() {
+            request.extensions_mut().insert(AuthenticatedPrincipal {
+                subject: None,
+                roles: vec![AccessRole::Admin],
+            });
+            return Ok(request);
+        }
+
+        let bearer_token = request
+            .metadata()
+            .get("authorization")
+            .and_then(|value| value.to_str().ok())
+            .and_then(|value| value.strip_prefix("Bearer "))
+            .map(|value| value.trim().to_string())
+            .ok_or_else(|| Status::unauthenticated("missing bearer token"))?;
+
+        // static tokens are checked first as they are cheaper to validate than the signed OIDC tokens
+        let api_token = self
+            .api_token_accessor
+            .as_ref()
+            .and_then(|api_token_accessor| api_token_accessor.validate_token(&bearer_token));
+        let validated_token = match (api_token, &self.oidc_accessor) {
+            (Some(api_token), _) => api_token,
+            (None, Some(oidc_accessor)) => match oidc_accessor.validate_token(&bearer_token) {
+                Ok(validated_token) => validated_token,
+                Err(err) => {
+                    warn!("Rejected request with invalid bearer token: {err}");
+                    return Err(Status::unauthenticated("invalid bearer token"));
+                }
+            },
+            (None, None) => {
+                warn!("Rejected request with unknown api token");
+                return Err(Status::unauthenticated("invalid bearer token"));
+            }
+        };
+
+        request.extensions_mut().insert(AuthenticatedPrincipal {
+            subject: Some(validated_token.subject),
+            roles: validated_token.roles,
+        });
+        Ok(request)
+    }
+}
+
+/// Ensures that the principal that sent the given request was granted the given role, returning a permission denied
+/// status if that is not the case.
+///
+/// # Arguments
+/// * `request` - The request to check the authenticated principal of.
+/// * `role` - The role that is required to execute the request.
+pub(crate) fn require_role<T>(request: &Request<T>, role: AccessRole) -> Result<(), Status> {
+    match request.extensions().get::<AuthenticatedPrincipal>() {
+        Some(principal) if principal.has_role(role) => Ok(()),
+        _ => Err(Status::permission_denied(format!(
+            "the {:?} role is required to execute this request",
+            role
+        ))),
+    }
+}
@@ -22,47 +22,284 @@
  * SOFTWARE.
  */
 
+use std::collections::HashMap;
+use std::path::Path;
 use std::sync::Arc;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
 
-use log::{error, info};
+use octocrab::models::repos::Release;
+
+use log::{error, info, warn};
 use tokio::fs;
-use tokio::sync::mpsc::channel;
+use tokio::sync::mpsc::{channel, Sender};
+use tokio::time::sleep;
 use tokio_stream::wrappers::ReceiverStream;
 use tonic::{Request, Response, Status};
 
-use crate::accessor::deploy_action_accessor::{CurrentAction, DeploymentStatusAccessor};
+use crate::accessor::configuration_accessor::ConfigurationAccessor;
+use crate::accessor::deploy_action_accessor::{
+    CurrentAction, DeploymentStatusAccessor, QueuedActionDetails,
+};
 use crate::accessor::deploy_status_accessor::DeployExecutionState;
 use crate::accessor::deployment_accessor::DeploymentAccessor;
+use crate::accessor::deployment_history_accessor::DeploymentHistoryAccessor;
+use crate::accessor::deployment_rate_limit_accessor::DeploymentRateLimitAccessor;
 use crate::accessor::github_accessor::GitHubAccessor;
-use crate::config::Configuration;
+use crate::accessor::prepared_deployment_accessor::PreparedDeploymentAccessor;
+use crate::accessor::ref_deployment_accessor::{RefDeployment, RefDeploymentAccessor};
+use crate::accessor::release_metadata_accessor::{validate_metadata, ReleaseMetadataAccessor};
+use crate::accessor::release_tombstone_accessor::ReleaseTombstoneAccessor;
+use crate::capability::DeploymentRequirements;
+use crate::config::{AccessRole, DeploymentConfiguration, NotificationEvent};
 use crate::easydep::deployment_service_server::DeploymentService;
 use crate::easydep::{
-    DeployDeleteRequest, DeployPublishRequest, DeployRollbackRequest, DeployStartRequest,
-    DeployStatusRequest, DeployStatusResponse, ExecutedActionEntry,
+    Action, ActionStatus, AdoptReleaseRequest, AdoptReleaseResponse, DeployAnnotation,
+    DeployApproveRequest, DeployApproveResponse, DeployCancelQueuedRequest,
+    DeployCancelQueuedResponse, DeployCancelRequest, DeployCancelResponse, DeployDeleteRequest,
+    DeployPlanCheck, DeployPlanEntry, DeployPlanRequest, DeployPlanResponse, DeployPublishRequest,
+    DeployRerunScriptsRequest, DeployRollbackRequest, DeployStartRequest, DeployStatusRequest,
+    DeployStatusResponse, DeploymentHistoryEntry, DeploymentHistoryRequest,
+    DeploymentHistoryResponse, ExecCommandRequest, ExecutedActionEntry, ListReleasesRequest,
+    ListReleasesResponse, LogEntry, LogType, MarkReleaseBadRequest, MarkReleaseBadResponse,
+    PruneReleasesRequest, RollbackCandidate, ScriptPhase, StoredRelease,
 };
+use crate::executor::action_supervisor::supervise_action;
+use crate::executor::command_runner::ExecutionEnvironment;
+use crate::executor::deploy_dry_run_executor::stream_deployment_plan;
 use crate::executor::deploy_executor::DeployExecutor;
+use crate::executor::deploy_init_executor::{check_free_disk_space, BYTES_PER_MIB};
 use crate::executor::deploy_publish_executor::publish_deployment;
+use crate::executor::exec_command_executor::execute_exec_command;
+use crate::executor::release_adoption_executor::{
+    adopt_release_directory, publish_adopted_release,
+};
+use crate::executor::release_prune_executor::{prune_releases, PruneLimits};
 use crate::executor::script_executor::{execute_scripts, ScriptType};
+use crate::notification::action_outcome_recorder::record_action_outcome;
+use crate::notification::deployment_notification::DeploymentNotification;
+use crate::notification::notification_dispatcher::NotificationDispatcher;
+use crate::service::auth_interceptor::require_role;
+use crate::service::request_identity::RequestIdentity;
+
+/// The minimum amount of bytes that must be available in the base directory for a planned deployment to pass, unless
+/// a minimum free disk space is configured.
+const MIN_PLANNED_DISK_SPACE_BYTES: u64 = 1024 * 1024 * 1024;
+/// The amount of stored releases that are returned per page if the request does not specify a page size.
+const DEFAULT_RELEASE_PAGE_SIZE: usize = 50;
+/// The maximum amount of stored releases that are returned per page.
+const MAX_RELEASE_PAGE_SIZE: u32 = 500;
+/// The amount of history entries that are returned per page if the request does not specify a page size.
+const DEFAULT_HISTORY_PAGE_SIZE: usize = 50;
+/// The maximum amount of history entries that are returned per page.
+const MAX_HISTORY_PAGE_SIZE: u32 = 500;
+/// The time to wait for a cancelled deployment to be cleaned up before responding to the cancel request.
+const CANCEL_COMPLETION_TIMEOUT: Duration = Duration::from_secs(60);
+/// The name of the identity that is recorded for prepared deployments that are deleted after they expired.
+const PREPARED_EXPIRY_IDENTITY: &str = "easydep prepared deployment expiry";
 
 pub struct DeploymentServiceImpl {
-    config: Configuration,
+    configuration_accessor: ConfigurationAccessor,
     github_accessor: GitHubAccessor,
     deployment_accessor: DeploymentAccessor,
     deployment_status_accessor: DeploymentStatusAccessor,
+    release_tombstone_accessor: ReleaseTombstoneAccessor,
+    release_metadata_accessor: ReleaseMetadataAccessor,
+    ref_deployment_accessor: RefDeploymentAccessor,
+    prepared_deployment_accessor: PreparedDeploymentAccessor,
+    deployment_history_accessor: DeploymentHistoryAccessor,
+    execution_environment: ExecutionEnvironment,
+    notification_dispatcher: NotificationDispatcher,
+    deployment_rate_limit_accessor: DeploymentRateLimitAccessor,
 }
 
 impl DeploymentServiceImpl {
     pub async fn new(
-        config: Configuration,
+        configuration_accessor: ConfigurationAccessor,
         github_accessor: GitHubAccessor,
         deployment_status_accessor: DeploymentStatusAccessor,
+        notification_dispatcher: NotificationDispatcher,
+        deployment_rate_limit_accessor: DeploymentRateLimitAccessor,
     ) -> Self {
+        let config = configuration_accessor.get_configuration().await;
         let deployment_accessor = DeploymentAccessor::new(&config);
+        let release_tombstone_accessor = ReleaseTombstoneAccessor::new(&config);
+        let release_metadata_accessor = ReleaseMetadataAccessor::new(&config);
+        let ref_deployment_accessor = RefDeploymentAccessor::new(&config);
+        let prepared_deployment_accessor = PreparedDeploymentAccessor::new(&config);
+        let deployment_history_accessor = DeploymentHistoryAccessor::new(&config);
+        let execution_environment = ExecutionEnvironment::new(&config);
         Self {
-            config,
+            configuration_accessor,
             github_accessor,
             deployment_accessor,
             deployment_status_accessor,
+            release_tombstone_accessor,
+            release_metadata_accessor,
+            ref_deployment_accessor,
+            prepared_deployment_accessor,
+            deployment_history_accessor,
+            execution_environment,
+            notification_dispatcher,
+            deployment_rate_limit_accessor,
+        }
+    }
+
+    /// Restores the deployment that was prepared before the server restarted, allowing to publish or delete it. The
+    /// prepared deployment is deleted right away if it expired while the server was down.
+    pub async fn restore_prepared_deployment(&self) {
+        let prepared_deployment = match self
+            .prepared_deployment_accessor
+            .get_prepared_deployment()
+            .await
+        {
+            Ok(Some(prepared_deployment)) => prepared_deployment,
+            Ok(None) => return,
+            Err(err) => {
+                error!("Unable to read persisted prepared deployment: {err:?}");
+                return;
+            }
+        };
+        let release_id = prepared_deployment.release.id.0;
+        let global_configuration = self.configuration_accessor.get_configuration().await;
+        let deployment_executor = match DeployExecutor::restore(
+            prepared_deployment,
+            global_configuration.as_ref().clone(),
+        )
+        .await
+        {
+            Ok(deployment_executor) => Arc::new(deployment_executor),
+            Err(err) => {
+                error!("Unable to restore prepared deployment {release_id}: {err:?}");
+                return;
+            }
+        };
+        self.deployment_status_accessor
+            .set_action(CurrentAction::Executing(deployment_executor.clone()))
+            .await;
+        info!(
+            "Restored prepared deployment {} of profile {}",
+            release_id,
+            deployment_executor.get_deployment_configuration().id
+        );
+
+        if let Some(prepared_time_remaining) =
+            deployment_executor.get_prepared_time_remaining().await
+        {
+            let deployment_status_accessor = self.deployment_status_accessor.clone();
+            let notification_dispatcher = self.notification_dispatcher.clone();
+            let release_metadata_accessor = self.release_metadata_accessor.clone();
+            tokio::spawn(async move {
+                sleep(prepared_time_remaining).await;
+                expire_prepared_deployment(
+                    &deployment_executor,
+                    &deployment_status_accessor,
+                    &notification_dispatcher,
+                    &release_metadata_accessor,
+                )
+                .await;
+            });
+        }
+    }
+
+    /// Ensures that the given release was not marked as bad for the given profile, returning a failed precondition
+    /// status containing the recorded reason if that is the case.
+    ///
+    /// # Arguments
+    /// * `deploy_config` - The deployment profile configuration to check the release in.
+    /// * `release_id` - The id of the release to check.
+    async fn ensure_release_not_marked_bad(
+        &self,
+        deploy_config: &DeploymentConfiguration,
+        release_id: &u64,
+    ) -> Result<(), Status> {
+        match self
+            .release_tombstone_accessor
+            .get_tombstone(deploy_config, release_id)
+            .await
+        {
+            Ok(None) => Ok(()),
+            Ok(Some(tombstone)) => {
+                let error_message = format!(
+                    "release {} was marked as bad for profile {}: {}",
+                    release_id, deploy_config.id, tombstone.reason
+                );
+                Err(Status::failed_precondition(error_message))
+            }
+            Err(err) => {
+                let error_message = format!("unable to read bad release markers: {err}");
+                Err(Status::internal(error_message))
+            }
+        }
+    }
+
+    /// Resolves the given git ref and plans a new deployment of it, which must be recorded once it was started. The
+    /// deployment gets the id following the newest release of the repository (or the next free id after it), so that
+    /// it sorts after the already deployed releases.
+    ///
+    /// # Arguments
+    /// * `deploy_config` - The deployment profile configuration in which the git ref is deployed.
+    /// * `git_ref` - The git ref to deploy.
+    async fn plan_ref_deployment(
+        &self,
+        deploy_config: &DeploymentConfiguration,
+        git_ref: &str,
+    ) -> Result<RefDeployment, Status> {
+        let commit_sha = match self
+            .github_accessor
+            .resolve_commit_sha(git_ref, deploy_config)
+            .await
+        {
+            Ok(commit_sha) => commit_sha,
+            Err(err) => {
+                let error_message = format!("unable to resolve git ref {git_ref}: {err:?}");
+                return Err(Status::failed_precondition(error_message));
+            }
+        };
+        let newest_release_id = match self
+            .github_accessor
+            .get_newest_release_id(deploy_config)
+            .await
+        {
+            Ok(newest_release_id) => newest_release_id,
+            Err(err) => {
+                let error_message = format!("unable to resolve newest release: {err:?}");
+                return Err(Status::internal(error_message));
+            }
+        };
+        plan_ref_deployment(
+            &self.ref_deployment_accessor,
+            &self.deployment_accessor,
+            deploy_config,
+            git_ref.to_string(),
+            commit_sha,
+            newest_release_id,
+        )
+        .await
+    }
+
+    /// Get the release with the given id for the given profile. The release is constructed from the recorded ref
+    /// deployment if the id belongs to a deployment that was started from a git ref, otherwise it is fetched from
+    /// GitHub.
+    ///
+    /// # Arguments
+    /// * `release_id` - The id of the release to get.
+    /// * `deploy_config` - The deployment profile configuration to get the release for.
+    async fn get_release(
+        &self,
+        release_id: &u64,
+        deploy_config: &DeploymentConfiguration,
+    ) -> anyhow::Result<Release> {
+        match self
+            .ref_deployment_accessor
+            .get_ref_deployment(deploy_config, release_id)
+            .await?
+        {
+            Some(ref_deployment) => ref_deployment.to_release(deploy_config),
+            None => {
+                self.github_accessor
+                    .get_release_by_id(release_id, deploy_config)
+                    .await
+            }
         }
     }
 }
@@ -75,17 +312,51 @@ impl DeploymentService for DeploymentServiceImpl {
         &self,
         request: Request<DeployStartRequest>,
     ) -> Result<Response<Self::StartDeploymentStream>, Status> {
+        require_role(&request, AccessRole::Deployer)?;
         let request_message = request.get_ref();
+        let request_identity = RequestIdentity::from_request(&request);
         let release_id = &request_message.release_id;
         let release_profile = &request_message.profile;
+        let requested_release = match &request_message.r#ref {
+            Some(git_ref) => format!("git ref {}", git_ref),
+            None => format!("release {}", release_id),
+        };
         info!(
-            "received request to init deployment for release {} with profile {}",
-            release_id, release_profile
+            "received request from {} to {} deployment for {} with profile {}{}",
+            request_identity,
+            if request_message.dry_run {
+                "plan"
+            } else {
+                "init"
+            },
+            requested_release,
+            release_profile,
+            format_annotation(&request_message.annotation)
         );
 
+        // check if the expected commit is a (possibly abbreviated) commit sha
+        if let Some(expected_commit_sha) = &request_message.expected_commit_sha {
+            if !(7..=40).contains(&expected_commit_sha.len())
+                || !expected_commit_sha
+                    .chars()
+                    .all(|char| char.is_ascii_hexdigit())
+            {
+                return Err(Status::invalid_argument(
+                    "expected commit must be a commit sha with at least 7 characters",
+                ));
+            }
+        }
+
+        // check if the metadata can be stored with the deployment
+        if let Err(err) = validate_metadata(&request_message.metadata) {
+            return Err(Status::invalid_argument(err.to_string()));
+        }
+
         // get the requested deployment profile configuration & the requested release information
         // read the GitHub access token to ensure we can even execute a deployment for the requested repository
-        let deploy_config = match self.config.get_deployment_configuration(release_profile) {
+        let global_configuration = self.configuration_accessor.get_configuration().await;
+        let deploy_config = match global_configuration.get_deployment_configuration(release_profile)
+        {
             Some(deployment_configuration) => deployment_configuration,
             None => {
                 return Err(Status::failed_precondition(
@@ -93,17 +364,117 @@ impl DeploymentService for DeploymentServiceImpl {
                 ))
             }
         };
-        let release = match self
-            .github_accessor
-            .get_release_by_id(release_id, &deploy_config)
-            .await
-        {
-            Ok(release) => release,
-            Err(err) => {
-                let error_message = format!("unable to find requested release: {err:?}");
-                return Err(Status::failed_precondition(error_message));
+
+        // check if the profile can only be used by extending it, not directly
+        if deploy_config.extend_only {
+            return Err(Status::failed_precondition(
+                "the requested deployment profile cannot be used directly",
+            ));
+        }
+
+        // check if the profile reached its limit of deployments, which can only be overridden by admins
+        // dry runs are not executed and therefore do not count towards the limit, other deployments are only
+        // recorded once they occupy the server to not count rejected deployments
+        let override_rate_limit = request_message.override_rate_limit;
+        if override_rate_limit {
+            require_role(&request, AccessRole::Admin)?;
+        }
+        if !request_message.dry_run {
+            if let Err(retry_after) = self
+                .deployment_rate_limit_accessor
+                .check_deployment(&deploy_config, override_rate_limit)
+                .await
+            {
+                return Err(rate_limit_status(&deploy_config, retry_after));
+            }
+        }
+
+        let mut planned_ref_deployment = None;
+        let release = match &request_message.r#ref {
+            Some(git_ref) => {
+                // deployments from git refs skip the branch checks and must be explicitly allowed by the profile
+                if !deploy_config.allow_ref_deploys {
+                    return Err(Status::failed_precondition(
+                        "the requested deployment profile does not allow deployments from git refs",
+                    ));
+                }
+                let ref_deployment = self.plan_ref_deployment(&deploy_config, git_ref).await?;
+                let release = ref_deployment_release(&ref_deployment, &deploy_config)?;
+                planned_ref_deployment = Some(ref_deployment);
+                release
+            }
+            None => {
+                self.ensure_release_not_marked_bad(&deploy_config, release_id)
+                    .await?;
+                let release = match self
+                    .github_accessor
+                    .get_release_by_id(release_id, &deploy_config)
+                    .await
+                {
+                    Ok(release) => release,
+                    Err(err) => {
+                        let error_message = format!("unable to find requested release: {err:?}");
+                        return Err(Status::failed_precondition(error_message));
+                    }
+                };
+
+                // check if the deployment profile can actually use the requested branch
+                if !deploy_config.is_branch_allowed_to_use_config(&release.target_commitish) {
+                    return Err(Status::failed_precondition(
+                        "branch is not allowed to use requested deployment configuration",
+                    ));
+                }
+
+                // check if the server satisfies the requirements declared in the release
+                let release_body = release.body.as_deref().unwrap_or_default();
+                let requirements = DeploymentRequirements::from_release_body(release_body);
+                if let Err(err) = requirements.check() {
+                    let error_message = format!("release cannot be deployed on this server: {err}");
+                    return Err(Status::failed_precondition(error_message));
+                }
+                release
             }
         };
+
+        // only report the steps of the deployment for dry runs, without reading a token or touching the disk
+        if request_message.dry_run {
+            let deployment_slot = self
+                .deployment_accessor
+                .select_deployment_slot(&deploy_config);
+            let deploy_config = deploy_config.with_deployment_slot(deployment_slot);
+            let deployment_directory = self.deployment_accessor.get_release_directory(
+                &deploy_config,
+                &release.id.0,
+                &release.tag_name,
+            );
+            if self
+                .deployment_accessor
+                .has_release_directory(&deploy_config, &release.id.0)
+            {
+                return Err(Status::failed_precondition(
+                    "deployment directory already exists, deployment was likely triggered already",
+                ));
+            }
+            let (data_sender, data_receiver) = channel::<Result<ExecutedActionEntry, Status>>(50);
+            tokio::spawn(async move {
+                stream_deployment_plan(
+                    &release,
+                    &deployment_directory,
+                    &deploy_config,
+                    &data_sender,
+                )
+                .await;
+            });
+            return Ok(Response::new(ReceiverStream::new(data_receiver)));
+        }
+
+        // reject the deployment right away if the disk is too full, it is checked again before obtaining the content
+        if let Some(min_free_disk_mb) = global_configuration.min_free_disk_mb {
+            check_free_disk_space(
+                Path::new(&global_configuration.base_directory),
+                min_free_disk_mb.saturating_mul(BYTES_PER_MIB),
+            )?;
+        }
         let github_access_token = match self
             .github_accessor
             .read_github_app_installation_token(&deploy_config)
@@ -116,49 +487,219 @@ impl DeploymentService for DeploymentServiceImpl {
             }
         };
 
-        // check if the profile can only be used by extending it, not directly
-        if deploy_config.extend_only {
-            return Err(Status::failed_precondition(
-                "the requested deployment profile cannot be used directly",
-            ));
-        }
-
-        // check if the deployment profile can actually use the requested branch
-        if !deploy_config.is_branch_allowed_to_use_config(&release.target_commitish) {
-            return Err(Status::failed_precondition(
-                "branch is not allowed to use requested deployment configuration",
-            ));
-        }
-
         // prepare the data needed for the deployment
         let (data_sender, data_receiver) = channel::<Result<ExecutedActionEntry, Status>>(50);
-        let deployment_executor = DeployExecutor::new(
-            release,
-            github_access_token,
-            self.config.clone(),
-            self.deployment_accessor.clone(),
-            deploy_config,
-        );
+        let release_id = release.id.0;
+        let global_configuration = global_configuration.as_ref().clone();
+        let annotation = request_message.annotation.clone();
+        let git_ref = request_message.r#ref.clone();
+        let expected_commit_sha = request_message.expected_commit_sha.clone();
+        let queued_action_details = QueuedActionDetails {
+            release_id,
+            release_tag: release.tag_name.clone(),
+            git_ref: git_ref.clone(),
+            annotation: annotation.clone(),
+            triggered_by: request_identity.clone(),
+            queued_at: SystemTime::now(),
+        };
+        let new_deployment_executor = {
+            let deploy_config = deploy_config.clone();
+            move |release: Release, github_access_token| {
+                DeployExecutor::new(
+                    release,
+                    github_access_token,
+                    global_configuration.clone(),
+                    deploy_config.clone(),
+                    annotation.clone(),
+                    request_identity.clone(),
+                    git_ref.clone(),
+                )
+                .with_expected_commit_sha(expected_commit_sha.clone())
+            }
+        };
+        let started_deployment_recorder = StartedDeploymentRecorder {
+            deploy_config: deploy_config.clone(),
+            override_rate_limit,
+            deployment_rate_limit_accessor: self.deployment_rate_limit_accessor.clone(),
+            ref_deployment_accessor: self.ref_deployment_accessor.clone(),
+            deployment_status_accessor: self.deployment_status_accessor.clone(),
+        };
+        let metadata = request_message.metadata.clone();
+        let release_metadata_accessor = self.release_metadata_accessor.clone();
+        let deployment_status_accessor = self.deployment_status_accessor.clone();
+        let notification_dispatcher = self.notification_dispatcher.clone();
 
-        // check if another action is already running to prevent
+        // without a queue, check if another action is already running to prevent
         // issues with them getting in the way of each other
-        let deployment_executor_arc = Arc::new(deployment_executor);
-        let deployment_action = CurrentAction::Executing(deployment_executor_arc.clone());
-        if !self
-            .deployment_status_accessor
-            .compare_and_set_action_by_variant(&CurrentAction::Idle, deployment_action)
-            .await
-        {
-            return Err(Status::failed_precondition(
-                "another action was started first, try again afterwards",
+        if deploy_config.action_queue_depth == 0 {
+            let deployment_executor_arc =
+                Arc::new(new_deployment_executor(release, github_access_token));
+            let deployment_action = CurrentAction::Executing(deployment_executor_arc.clone());
+            if !self
+                .deployment_status_accessor
+                .compare_and_set_action_by_variant(&CurrentAction::Idle, deployment_action)
+                .await
+            {
+                return Err(Status::failed_precondition(
+                    "another action was started first, try again afterwards",
+                ));
+            }
+            started_deployment_recorder
+                .record(planned_ref_deployment.as_ref(), &data_sender)
+                .await?;
+            tokio::spawn(execute_deployment(
+                deployment_executor_arc,
+                metadata,
+                release_metadata_accessor,
+                deployment_status_accessor,
+                notification_dispatcher,
+                data_sender,
             ));
+            return Ok(Response::new(ReceiverStream::new(data_receiver)));
         }
 
-        // execute the deployment
+        // otherwise wait in the queue of the profile until the deployment is the next one and no action is running
+        let Some(mut queued_action) = self.deployment_status_accessor.enqueue_action(
+            &deploy_config.id,
+            deploy_config.action_queue_depth,
+            queued_action_details,
+        ) else {
+            let error_message = format!(
+                "the action queue of profile {} is full, try again afterwards",
+                deploy_config.id
+            );
+            return Err(Status::resource_exhausted(error_message));
+        };
+        let github_accessor = self.github_accessor.clone();
+        let ref_deployment_accessor = self.ref_deployment_accessor.clone();
+        let deployment_accessor = self.deployment_accessor.clone();
         tokio::spawn(async move {
-            deployment_executor_arc
-                .prepare_deployment(data_sender)
-                .await;
+            let mut github_access_token = Some(github_access_token);
+            let mut reported_position = 0;
+            let deployment_executor_arc = loop {
+                let position = queued_action.get_position();
+                if position == 0 {
+                    info!(
+                        "Queued deployment of release {} for profile {} was cancelled",
+                        release_id, deploy_config.id
+                    );
+                    data_sender
+                        .send(Err(Status::cancelled(
+                            "the queued deployment was cancelled",
+                        )))
+                        .await
+                        .ok();
+                    return;
+                }
+                if position == 1
+                    && matches!(
+                        deployment_status_accessor.get_action().await,
+                        CurrentAction::Idle
+                    )
+                {
+                    // installation tokens expire, read a new one if the deployment had to wait
+                    let github_access_token = match github_access_token.take() {
+                        Some(github_access_token) => Ok(github_access_token),
+                        None => {
+                            github_accessor
+                                .read_github_app_installation_token(&deploy_config)
+                                .await
+                        }
+                    };
+                    let github_access_token = match github_access_token {
+                        Ok(github_access_token) => github_access_token,
+                        Err(err) => {
+                            let error_message =
+                                format!("unable to get github access token: {}", err);
+                            data_sender
+                                .send(Err(Status::internal(error_message)))
+                                .await
+                                .ok();
+                            return;
+                        }
+                    };
+
+                    // the planned id of a ref deployment might have been assigned to a deployment that was queued
+                    // before, plan it again to get the next free id
+                    let (release, ref_deployment) = match &planned_ref_deployment {
+                        Some(planned_ref_deployment) => {
+                            let replanned_ref_deployment = plan_ref_deployment(
+                                &ref_deployment_accessor,
+                                &deployment_accessor,
+                                &deploy_config,
+                                planned_ref_deployment.git_ref.clone(),
+                                planned_ref_deployment.commit_sha.clone(),
+                                planned_ref_deployment.release_id - 1,
+                            )
+                            .await
+                            .and_then(|ref_deployment| {
+                                ref_deployment_release(&ref_deployment, &deploy_config)
+                                    .map(|release| (release, Some(ref_deployment)))
+                            });
+                            match replanned_ref_deployment {
+                                Ok(replanned_ref_deployment) => replanned_ref_deployment,
+                                Err(status) => {
+                                    data_sender.send(Err(status)).await.ok();
+                                    return;
+                                }
+                            }
+                        }
+                        None => (release.clone(), None),
+                    };
+                    let deployment_executor_arc =
+                        Arc::new(new_deployment_executor(release, github_access_token));
+                    let deployment_action =
+                        CurrentAction::Executing(deployment_executor_arc.clone());
+                    if queued_action.try_set_action(deployment_action).await {
+                        // the limit might have been reached by other deployments while this one was queued
+                        if let Err(status) = started_deployment_recorder
+                            .record(ref_deployment.as_ref(), &data_sender)
+                            .await
+                        {
+                            data_sender.send(Err(status)).await.ok();
+                            return;
+                        }
+                        break deployment_executor_arc;
+                    }
+                } else if position != reported_position {
+                    reported_position = position;
+                    let action_entry = construct_queue_entry(
+                        release_id,
+                        format!(
+                            "Queued at position {}, waiting for the running action to complete",
+                            position
+                        ),
+                    );
+                    data_sender.send(Ok(action_entry)).await.ok();
+                }
+
+                // give up the place in the queue if the client is no longer waiting for the deployment
+                tokio::select! {
+                    _ = queued_action.changed() => {}
+                    _ = data_sender.closed() => {
+                        info!(
+                            "Client disconnected while release {} was queued for profile {}",
+                            release_id, deploy_config.id
+                        );
+                        return;
+                    }
+                }
+            };
+            drop(queued_action);
+            if reported_position != 0 {
+                let action_entry =
+                    construct_queue_entry(release_id, "Starting queued deployment".to_string());
+                data_sender.send(Ok(action_entry)).await.ok();
+            }
+            execute_deployment(
+                deployment_executor_arc,
+                metadata,
+                release_metadata_accessor,
+                deployment_status_accessor,
+                notification_dispatcher,
+                data_sender,
+            )
+            .await;
         });
         Ok(Response::new(ReceiverStream::new(data_receiver)))
     }
@@ -169,9 +710,16 @@ impl DeploymentService for DeploymentServiceImpl {
         &self,
         request: Request<DeployPublishRequest>,
     ) -> Result<Response<Self::PublishDeploymentStream>, Status> {
+        require_role(&request, AccessRole::Deployer)?;
         let request_message = request.get_ref();
+        let request_identity = RequestIdentity::from_request(&request);
         let release_id = request_message.release_id;
-        info!("Received request to publish deployment {}", release_id);
+        info!(
+            "Received request from {} to publish deployment {}{}",
+            request_identity,
+            release_id,
+            format_annotation(&request_message.annotation)
+        );
 
         // get the previously triggered deployment & validate it is in the correct state to be published
         let deployment_executor = match self.deployment_status_accessor.get_action().await {
@@ -184,6 +732,22 @@ impl DeploymentService for DeploymentServiceImpl {
                 ))
             }
         };
+        self.ensure_release_not_marked_bad(
+            deployment_executor.get_deployment_configuration(),
+            &release_id,
+        )
+        .await?;
+        if deployment_executor
+            .get_deployment_configuration()
+            .require_approval
+            && !deployment_executor
+                .is_approved(&request_message.approval_tokens)
+                .await
+        {
+            return Err(Status::permission_denied(
+                "the deployment must be approved before it can be published, pass the token issued when approving it",
+            ));
+        }
         if !deployment_executor
             .get_status_accessor()
             .compare_and_set_state(
@@ -199,10 +763,44 @@ impl DeploymentService for DeploymentServiceImpl {
 
         // trigger the publishing step of the deployment
         let deploy_status_accessor = self.deployment_status_accessor.clone();
+        let notification_dispatcher = self.notification_dispatcher.clone();
+        let release_metadata_accessor = self.release_metadata_accessor.clone();
+        let annotation = request_message.annotation.clone();
         let (data_sender, data_receiver) = channel::<Result<ExecutedActionEntry, Status>>(50);
         tokio::spawn(async move {
-            deployment_executor.publish_deployment(data_sender).await;
+            let started_at = Instant::now();
+            let (recording_sender, outcome_handle) = record_action_outcome(
+                data_sender,
+                deployment_executor.get_deployment_configuration(),
+            );
+            supervise_action(
+                "publish",
+                &deploy_status_accessor,
+                &recording_sender,
+                deployment_executor.publish_deployment(recording_sender.clone()),
+            )
+            .await;
+            drop(recording_sender);
             deploy_status_accessor.set_action(CurrentAction::Idle).await;
+            let metadata = read_release_metadata(
+                &release_metadata_accessor,
+                deployment_executor.get_deployment_configuration(),
+                deployment_executor.get_release_id(),
+            )
+            .await;
+            let notification = DeploymentNotification::from_outcome(
+                NotificationEvent::Published,
+                "publish",
+                deployment_executor.get_release(),
+                deployment_executor.get_deployment_configuration(),
+                &request_identity,
+                started_at.elapsed(),
+                outcome_handle.await.unwrap_or_default(),
+            )
+            .with_annotation(annotation.as_ref())
+            .with_git_ref(deployment_executor.get_git_ref().cloned())
+            .with_metadata(metadata);
+            notification_dispatcher.dispatch(notification);
         });
         Ok(Response::new(ReceiverStream::new(data_receiver)))
     }
@@ -213,15 +811,21 @@ impl DeploymentService for DeploymentServiceImpl {
         &self,
         request: Request<DeployRollbackRequest>,
     ) -> Result<Response<Self::RollbackDeploymentStream>, Status> {
+        require_role(&request, AccessRole::Deployer)?;
         let request_message = request.get_ref();
+        let request_identity = RequestIdentity::from_request(&request);
         let release_profile = &request_message.profile;
         info!(
-            "received request to rollback to previous deployment on profile {}",
-            release_profile
+            "received request from {} to rollback to previous deployment on profile {}{}",
+            request_identity,
+            release_profile,
+            format_annotation(&request_message.annotation)
         );
 
         // get the requested deployment profile configuration & the requested release information
-        let deploy_config = match self.config.get_deployment_configuration(release_profile) {
+        let global_configuration = self.configuration_accessor.get_configuration().await;
+        let deploy_config = match global_configuration.get_deployment_configuration(release_profile)
+        {
             Some(deployment_configuration) => deployment_configuration,
             None => {
                 return Err(Status::failed_precondition(
@@ -257,11 +861,15 @@ impl DeploymentService for DeploymentServiceImpl {
                 return Err(Status::internal(error_message));
             }
         };
-        let github_release_info = match self
-            .github_accessor
-            .get_release_by_id(&prev_release_id, &deploy_config)
-            .await
-        {
+        self.ensure_release_not_marked_bad(&deploy_config, &prev_release_id)
+            .await?;
+
+        // execute the rollback in the slot in which the previous release is deployed
+        let prev_release_slot = self
+            .deployment_accessor
+            .find_release_slot(&deploy_config, &prev_release_directory);
+        let deploy_config = deploy_config.with_deployment_slot(prev_release_slot);
+        let github_release_info = match self.get_release(&prev_release_id, &deploy_config).await {
             Ok(release) => release,
             Err(err) => {
                 let error_message = format!(
@@ -274,7 +882,11 @@ impl DeploymentService for DeploymentServiceImpl {
 
         // check if another action is already running to prevent issues with them getting in the way of each other
         let release_boxed = Box::new(github_release_info);
-        let rollback_action = CurrentAction::RollingBack(release_boxed.clone());
+        let rollback_action = CurrentAction::RollingBack(
+            release_boxed.clone(),
+            Box::new(deploy_config.clone()),
+            request_identity.clone(),
+        );
         if !self
             .deployment_status_accessor
             .compare_and_set_action_by_variant(&CurrentAction::Idle, rollback_action)
@@ -287,34 +899,206 @@ impl DeploymentService for DeploymentServiceImpl {
 
         // execute the deployment init script again and instantly publish the deployment
         // this works under the assumption that the deployment directory exists as it was just resolved
-        let global_config = self.config.clone();
+        let global_config = global_configuration.as_ref().clone();
         let deployment_accessor = self.deployment_accessor.clone();
         let deployment_status_accessor = self.deployment_status_accessor.clone();
+        // the scripts of the rollback see the release that is rolled back as previous release
+        let execution_environment = self
+            .execution_environment
+            .with_previous_release_directory(Some(curr_release_directory.clone()));
+        let notification_dispatcher = self.notification_dispatcher.clone();
+        let release_metadata_accessor = self.release_metadata_accessor.clone();
+        let annotation = request_message.annotation.clone();
+        let git_ref = match self
+            .ref_deployment_accessor
+            .get_ref_deployment(&deploy_config, &prev_release_id)
+            .await
+        {
+            Ok(ref_deployment) => ref_deployment.map(|ref_deployment| ref_deployment.git_ref),
+            Err(err) => {
+                warn!(
+                    "Unable to resolve git ref of release {}: {:?}",
+                    prev_release_id, err
+                );
+                None
+            }
+        };
         let (data_sender, data_receiver) = channel::<Result<ExecutedActionEntry, Status>>(50);
         tokio::spawn(async move {
-            execute_scripts(
+            let started_at = Instant::now();
+            let (recording_sender, outcome_handle) =
+                record_action_outcome(data_sender, &deploy_config);
+            let rollback = async {
+                let script_execution_result = execute_scripts(
+                    &release_boxed,
+                    &ScriptType::Init,
+                    &prev_release_directory,
+                    &deploy_config,
+                    &execution_environment,
+                    &recording_sender,
+                )
+                .await;
+
+                // the current release is only removed if the previous release was published successfully, to keep
+                // the current release in place when the init scripts of the previous release fail
+                script_execution_result.is_success()
+                    && publish_deployment(
+                        &release_boxed,
+                        &prev_release_directory,
+                        &global_config,
+                        &deployment_accessor,
+                        &deploy_config,
+                        &execution_environment,
+                        &recording_sender,
+                    )
+                    .await
+            };
+            let rolled_back = supervise_action(
+                "rollback",
+                &deployment_status_accessor,
+                &recording_sender,
+                rollback,
+            )
+            .await
+            .unwrap_or(false);
+            drop(recording_sender);
+            if rolled_back {
+                if let Err(err) = fs::remove_dir_all(&curr_release_directory).await {
+                    error!(
+                        "Unable to delete old release directory {:?}: {}, ",
+                        curr_release_directory, err
+                    );
+                }
+            }
+            deployment_status_accessor
+                .set_action(CurrentAction::Idle)
+                .await;
+            let metadata =
+                read_release_metadata(&release_metadata_accessor, &deploy_config, prev_release_id)
+                    .await;
+            let notification = DeploymentNotification::from_outcome(
+                NotificationEvent::RolledBack,
+                "rollback",
                 &release_boxed,
-                &ScriptType::Init,
-                &prev_release_directory,
                 &deploy_config,
-                &data_sender,
+                &request_identity,
+                started_at.elapsed(),
+                outcome_handle.await.unwrap_or_default(),
             )
-            .await;
-            publish_deployment(
+            .with_annotation(annotation.as_ref())
+            .with_git_ref(git_ref)
+            .with_metadata(metadata);
+            notification_dispatcher.dispatch(notification);
+        });
+        Ok(Response::new(ReceiverStream::new(data_receiver)))
+    }
+
+    type RerunScriptsStream = ReceiverStream<Result<ExecutedActionEntry, Status>>;
+
+    async fn rerun_scripts(
+        &self,
+        request: Request<DeployRerunScriptsRequest>,
+    ) -> Result<Response<Self::RerunScriptsStream>, Status> {
+        require_role(&request, AccessRole::Deployer)?;
+        let request_message = request.get_ref();
+        let request_identity = RequestIdentity::from_request(&request);
+        let release_profile = &request_message.profile;
+        let (script_type, phase_name) = match request_message.phase() {
+            ScriptPhase::Publish => (ScriptType::Publish, "publish"),
+            ScriptPhase::Init => (ScriptType::Init, "init"),
+        };
+        info!(
+            "received request from {} to rerun the {} scripts of the published release on profile {}{}",
+            request_identity,
+            phase_name,
+            release_profile,
+            format_annotation(&request_message.annotation)
+        );
+
+        // get the requested deployment profile configuration
+        let global_configuration = self.configuration_accessor.get_configuration().await;
+        let deploy_config = match global_configuration.get_deployment_configuration(release_profile)
+        {
+            Some(deployment_configuration) => deployment_configuration,
+            None => {
+                return Err(Status::failed_precondition(
+                    "requested deployment config is not registered",
+                ))
+            }
+        };
+
+        // check if the profile can only be used by extending it, not directly
+        if deploy_config.extend_only {
+            return Err(Status::failed_precondition(
+                "the requested deployment profile cannot be used directly",
+            ));
+        }
+
+        // get the release that is currently published, the scripts are executed in the slot of the release
+        let (release_directory, release_id) = match self
+            .deployment_accessor
+            .get_published_release_directory(&deploy_config)
+        {
+            Some(published_release) => published_release,
+            None => {
+                return Err(Status::failed_precondition(
+                    "no release is published with the requested profile",
+                ))
+            }
+        };
+        let release_slot = self
+            .deployment_accessor
+            .find_release_slot(&deploy_config, &release_directory);
+        let deploy_config = deploy_config.with_deployment_slot(release_slot);
+        let github_release_info = match self.get_release(&release_id, &deploy_config).await {
+            Ok(release) => release,
+            Err(err) => {
+                let error_message = format!(
+                    "Unable to resolve GitHub release for published release {}: {}",
+                    release_id, err
+                );
+                return Err(Status::failed_precondition(error_message));
+            }
+        };
+
+        // check if another action is already running to prevent issues with them getting in the way of each other
+        let release_boxed = Box::new(github_release_info);
+        let rerun_action = CurrentAction::RerunningScripts(
+            release_boxed.clone(),
+            Box::new(deploy_config.clone()),
+            request_identity,
+        );
+        if !self
+            .deployment_status_accessor
+            .compare_and_set_action_by_variant(&CurrentAction::Idle, rerun_action)
+            .await
+        {
+            return Err(Status::failed_precondition(
+                "another action was started first, try again afterwards",
+            ));
+        }
+
+        // execute the scripts of the requested phase in the published release directory, without
+        // changing the published release or calling the publish hooks
+        let deployment_status_accessor = self.deployment_status_accessor.clone();
+        let execution_environment = self.execution_environment.clone();
+        let (data_sender, data_receiver) = channel::<Result<ExecutedActionEntry, Status>>(50);
+        tokio::spawn(async move {
+            let rerun = execute_scripts(
                 &release_boxed,
-                &prev_release_directory,
-                &global_config,
-                &deployment_accessor,
+                &script_type,
+                &release_directory,
                 &deploy_config,
+                &execution_environment,
                 &data_sender,
+            );
+            supervise_action(
+                "rerun-scripts",
+                &deployment_status_accessor,
+                &data_sender,
+                rerun,
             )
             .await;
-            if let Err(err) = fs::remove_dir_all(&curr_release_directory).await {
-                error!(
-                    "Unable to delete old release directory {:?}: {}, ",
-                    curr_release_directory, err
-                );
-            }
             deployment_status_accessor
                 .set_action(CurrentAction::Idle)
                 .await;
@@ -328,11 +1112,13 @@ impl DeploymentService for DeploymentServiceImpl {
         &self,
         request: Request<DeployDeleteRequest>,
     ) -> Result<Response<Self::DeleteUnpublishedDeploymentStream>, Status> {
+        require_role(&request, AccessRole::Deployer)?;
         let request_message = request.get_ref();
+        let request_identity = RequestIdentity::from_request(&request);
         let release_id = request_message.release_id;
         info!(
-            "Received request to deleted unpublished deployment {}",
-            release_id
+            "Received request from {} to deleted unpublished deployment {}",
+            request_identity, release_id
         );
 
         // get the previously triggered deployment & validate it is in the correct state to be rolled back
@@ -361,58 +1147,170 @@ impl DeploymentService for DeploymentServiceImpl {
 
         // trigger the deletion
         let deployment_status_accessor = self.deployment_status_accessor.clone();
+        let notification_dispatcher = self.notification_dispatcher.clone();
+        let release_metadata_accessor = self.release_metadata_accessor.clone();
         let (data_sender, data_receiver) = channel::<Result<ExecutedActionEntry, Status>>(50);
         tokio::spawn(async move {
-            deployment_executor.delete_deployment(data_sender).await;
+            let started_at = Instant::now();
+            let (recording_sender, outcome_handle) = record_action_outcome(
+                data_sender,
+                deployment_executor.get_deployment_configuration(),
+            );
+            supervise_action(
+                "delete",
+                &deployment_status_accessor,
+                &recording_sender,
+                deployment_executor.delete_deployment(recording_sender.clone()),
+            )
+            .await;
+            drop(recording_sender);
             deployment_status_accessor
                 .set_action(CurrentAction::Idle)
                 .await;
+            let metadata = read_release_metadata(
+                &release_metadata_accessor,
+                deployment_executor.get_deployment_configuration(),
+                deployment_executor.get_release_id(),
+            )
+            .await;
+            let notification = DeploymentNotification::from_outcome(
+                NotificationEvent::Deleted,
+                "delete",
+                deployment_executor.get_release(),
+                deployment_executor.get_deployment_configuration(),
+                &request_identity,
+                started_at.elapsed(),
+                outcome_handle.await.unwrap_or_default(),
+            )
+            .with_git_ref(deployment_executor.get_git_ref().cloned())
+            .with_metadata(metadata);
+            notification_dispatcher.dispatch(notification);
         });
         Ok(Response::new(ReceiverStream::new(data_receiver)))
     }
 
-    async fn get_deployment_status(
+    async fn cancel_deployment(
         &self,
-        request: Request<DeployStatusRequest>,
-    ) -> Result<Response<DeployStatusResponse>, Status> {
-        // get the requested deployment config
+        request: Request<DeployCancelRequest>,
+    ) -> Result<Response<DeployCancelResponse>, Status> {
+        require_role(&request, AccessRole::Deployer)?;
         let request_message = request.get_ref();
-        let deploy_config = match self
-            .config
-            .get_deployment_configuration(&request_message.profile)
-        {
-            Some(deployment_configuration) => deployment_configuration,
-            None => {
-                return Err(Status::failed_precondition(
-                    "requested deployment config is not registered",
-                ))
-            }
-        };
+        let request_identity = RequestIdentity::from_request(&request);
+        let release_id = request_message.release_id;
+        info!(
+            "Received request from {} to cancel deployment {}",
+            request_identity, release_id
+        );
+
+        let deployment_executor = match self.deployment_status_accessor.get_action().await {
+            CurrentAction::Executing(executor) if executor.get_release_id() == release_id => {
+                executor
+            }
+            _ => {
+                return Err(Status::failed_precondition(
+                    "no deployment or another deployment is currently being executed",
+                ))
+            }
+        };
+        if !deployment_executor.cancel_deployment().await {
+            return Err(Status::failed_precondition(
+                "the deployment is not being prepared and cannot be cancelled",
+            ));
+        }
+
+        // wait for the preparation to stop and the cancelled deployment to be cleaned up
+        let cancelled_at = Instant::now();
+        let mut completed = false;
+        while cancelled_at.elapsed() < CANCEL_COMPLETION_TIMEOUT {
+            completed = match self.deployment_status_accessor.get_action().await {
+                CurrentAction::Executing(executor) => !Arc::ptr_eq(&executor, &deployment_executor),
+                _ => true,
+            };
+            if completed {
+                break;
+            }
+            sleep(Duration::from_millis(500)).await;
+        }
+
+        let response = DeployCancelResponse {
+            release_id,
+            completed,
+        };
+        Ok(Response::new(response))
+    }
+
+    async fn cancel_queued_deployment(
+        &self,
+        request: Request<DeployCancelQueuedRequest>,
+    ) -> Result<Response<DeployCancelQueuedResponse>, Status> {
+        require_role(&request, AccessRole::Deployer)?;
+        let request_message = request.get_ref();
+        let request_identity = RequestIdentity::from_request(&request);
+        info!(
+            "Received request from {} to cancel the queued deployments of release {} for profile {}",
+            request_identity, request_message.release_id, request_message.profile
+        );
+
+        let cancelled_deployments = self
+            .deployment_status_accessor
+            .cancel_queued_actions(&request_message.profile, request_message.release_id);
+        if cancelled_deployments == 0 {
+            return Err(Status::not_found(
+                "no deployment of the release is queued for the profile",
+            ));
+        }
+
+        let response = DeployCancelQueuedResponse {
+            cancelled_deployments: cancelled_deployments as u32,
+        };
+        Ok(Response::new(response))
+    }
+
+    async fn get_deployment_status(
+        &self,
+        request: Request<DeployStatusRequest>,
+    ) -> Result<Response<DeployStatusResponse>, Status> {
+        require_role(&request, AccessRole::Viewer)?;
+        // get the requested deployment config
+        let request_message = request.get_ref();
+        let deploy_config = match self
+            .configuration_accessor
+            .get_configuration()
+            .await
+            .get_deployment_configuration(&request_message.profile)
+        {
+            Some(deployment_configuration) => deployment_configuration,
+            None => {
+                return Err(Status::failed_precondition(
+                    "requested deployment config is not registered",
+                ))
+            }
+        };
 
         // get the id of the last deployed release
-        let last_deployed_release_id = match self
+        let release_directories = match self
             .deployment_accessor
             .get_release_directories_for_profile(&deploy_config)
             .await
         {
-            Ok(release_directories) => match release_directories.first() {
-                Some(release_directory) => release_directory.1,
-                None => {
-                    return Err(Status::failed_precondition(
-                        "no release executed with profile yet",
-                    ))
-                }
-            },
+            Ok(release_directories) => release_directories,
             Err(err) => {
                 let error_message = format!("unable to resolve deployed releases: {err}");
                 return Err(Status::internal(error_message));
             }
         };
+        let last_deployed_release_id = match release_directories.first() {
+            Some(release_directory) => release_directory.1,
+            None => {
+                return Err(Status::failed_precondition(
+                    "no release executed with profile yet",
+                ))
+            }
+        };
 
         // get the release information from GitHub
         let github_release_info = match self
-            .github_accessor
-            .get_release_by_id(&last_deployed_release_id, &deploy_config)
+            .get_release(&last_deployed_release_id, &deploy_config)
             .await
         {
             Ok(release) => release,
@@ -422,12 +1320,1184 @@ impl DeploymentService for DeploymentServiceImpl {
             }
         };
 
+        // collect the previous releases that can be rolled back to, the release information is optional here
+        let mut rollback_candidates = Vec::new();
+        for (_, release_id) in release_directories.iter().skip(1) {
+            let tag_name = match self.get_release(release_id, &deploy_config).await {
+                Ok(release) => release.tag_name,
+                Err(err) => {
+                    warn!(
+                        "Unable to resolve release info for rollback candidate {release_id}: {err}"
+                    );
+                    String::new()
+                }
+            };
+            let marked_bad = matches!(
+                self.release_tombstone_accessor
+                    .get_tombstone(&deploy_config, release_id)
+                    .await,
+                Ok(Some(_))
+            );
+            rollback_candidates.push(RollbackCandidate {
+                release_id: *release_id,
+                tag_name,
+                marked_bad,
+            });
+        }
+
+        let metadata = self
+            .release_metadata_accessor
+            .get_metadata(&deploy_config, &last_deployed_release_id)
+            .await
+            .unwrap_or_else(|err| {
+                warn!("Unable to read metadata of release {last_deployed_release_id}: {err:?}");
+                HashMap::new()
+            });
+
         let response = DeployStatusResponse {
             profile: deploy_config.id,
             release_id: last_deployed_release_id,
             tag_name: github_release_info.tag_name,
             target_commit: github_release_info.target_commitish,
+            rollback_candidates,
+            metadata,
+        };
+        Ok(Response::new(response))
+    }
+
+    async fn mark_release_bad(
+        &self,
+        request: Request<MarkReleaseBadRequest>,
+    ) -> Result<Response<MarkReleaseBadResponse>, Status> {
+        require_role(&request, AccessRole::Deployer)?;
+        let request_message = request.get_ref();
+        let request_identity = RequestIdentity::from_request(&request);
+        let release_id = request_message.release_id;
+        info!(
+            "Received request from {} to mark release {} as bad for profile {}",
+            request_identity, release_id, request_message.profile
+        );
+
+        // get the requested deployment config
+        let deploy_config = match self
+            .configuration_accessor
+            .get_configuration()
+            .await
+            .get_deployment_configuration(&request_message.profile)
+        {
+            Some(deployment_configuration) => deployment_configuration,
+            None => {
+                return Err(Status::failed_precondition(
+                    "requested deployment config is not registered",
+                ))
+            }
+        };
+
+        // a reason must be given to tell others why the release cannot be deployed
+        let reason = request_message.reason.trim().to_string();
+        if reason.is_empty() {
+            return Err(Status::invalid_argument(
+                "a reason must be given when marking a release as bad",
+            ));
+        }
+
+        if let Err(err) = self
+            .release_tombstone_accessor
+            .mark_release_bad(&deploy_config, release_id, reason, &request_identity)
+            .await
+        {
+            let error_message = format!("unable to mark release as bad: {err}");
+            return Err(Status::internal(error_message));
+        }
+
+        let response = MarkReleaseBadResponse {
+            profile: deploy_config.id,
+            release_id,
+        };
+        Ok(Response::new(response))
+    }
+
+    async fn approve_deployment(
+        &self,
+        request: Request<DeployApproveRequest>,
+    ) -> Result<Response<DeployApproveResponse>, Status> {
+        require_role(&request, AccessRole::Admin)?;
+        let request_message = request.get_ref();
+        let request_identity = RequestIdentity::from_request(&request);
+        let release_id = request_message.release_id;
+        info!(
+            "Received request from {} to approve deployment {}{}",
+            request_identity,
+            release_id,
+            format_annotation(&request_message.annotation)
+        );
+
+        // only prepared deployments of profiles that require an approval can be approved
+        let deployment_executor = match self.deployment_status_accessor.get_action().await {
+            CurrentAction::Executing(executor) if executor.get_release_id() == release_id => {
+                executor
+            }
+            _ => {
+                return Err(Status::failed_precondition(
+                    "no deployment or another deployment is currently being executed",
+                ))
+            }
+        };
+        let deploy_config = deployment_executor.get_deployment_configuration();
+        if !deploy_config.require_approval {
+            return Err(Status::failed_precondition(
+                "the profile of the deployment does not require an approval",
+            ));
+        }
+        if deployment_executor.get_status_accessor().get_state().await
+            != DeployExecutionState::Prepared
+        {
+            return Err(Status::failed_precondition(
+                "only prepared deployments can be approved",
+            ));
+        }
+
+        let approval_token = match deployment_executor.approve().await {
+            Ok(approval_token) => approval_token,
+            Err(err) => {
+                let error_message = format!("unable to approve deployment: {err}");
+                return Err(Status::internal(error_message));
+            }
+        };
+        info!(
+            "Deployment {} of profile {} was approved by {}",
+            release_id, deploy_config.id, request_identity
+        );
+        let response = DeployApproveResponse {
+            profile: deploy_config.id.clone(),
+            release_id,
+            approval_token,
+        };
+        Ok(Response::new(response))
+    }
+
+    async fn plan_deployment(
+        &self,
+        request: Request<DeployPlanRequest>,
+    ) -> Result<Response<DeployPlanResponse>, Status> {
+        require_role(&request, AccessRole::Deployer)?;
+        let request_message = request.get_ref();
+        let release_id = request_message.release_id;
+        let deploy_config = match self
+            .configuration_accessor
+            .get_configuration()
+            .await
+            .get_deployment_configuration(&request_message.profile)
+        {
+            Some(deployment_configuration) if !deployment_configuration.extend_only => {
+                deployment_configuration
+            }
+            Some(_) => {
+                return Err(Status::failed_precondition(
+                    "the requested deployment profile cannot be used directly",
+                ))
+            }
+            None => {
+                return Err(Status::failed_precondition(
+                    "requested deployment config is not registered",
+                ))
+            }
+        };
+        let deployment_slot = self
+            .deployment_accessor
+            .select_deployment_slot(&deploy_config);
+        let deploy_config = deploy_config.with_deployment_slot(deployment_slot);
+        let mut entries = Vec::<DeployPlanEntry>::new();
+        let mut checks = Vec::<DeployPlanCheck>::new();
+
+        // check if the deployment could be started right now
+        let current_action = self.deployment_status_accessor.get_action().await;
+        let server_idle = matches!(current_action, CurrentAction::Idle);
+        checks.push(plan_check(
+            "server idle",
+            server_idle,
+            if server_idle {
+                ""
+            } else {
+                "another action is running"
+            },
+        ));
+
+        // check if the release can be deployed with the profile
+        let tombstone = self
+            .release_tombstone_accessor
+            .get_tombstone(&deploy_config, &release_id)
+            .await;
+        checks.push(match tombstone {
+            Ok(None) => plan_check("release not marked bad", true, ""),
+            Ok(Some(tombstone)) => plan_check("release not marked bad", false, &tombstone.reason),
+            Err(err) => plan_check("release not marked bad", false, &err.to_string()),
+        });
+        match self
+            .github_accessor
+            .get_release_by_id(&release_id, &deploy_config)
+            .await
+        {
+            Ok(release) => {
+                checks.push(plan_check("release resolvable", true, ""));
+                checks.push(plan_check(
+                    "branch allowed",
+                    deploy_config.is_branch_allowed_to_use_config(&release.target_commitish),
+                    &release.target_commitish,
+                ));
+                let release_body = release.body.as_deref().unwrap_or_default();
+                let requirements = DeploymentRequirements::from_release_body(release_body);
+                checks.push(match requirements.check() {
+                    Ok(()) => plan_check("requirements satisfied", true, ""),
+                    Err(err) => plan_check("requirements satisfied", false, &err.to_string()),
+                });
+
+                let release_directory = self.deployment_accessor.get_release_directory(
+                    &deploy_config,
+                    &release.id.0,
+                    &release.tag_name,
+                );
+                entries.push(plan_entry(
+                    "release",
+                    format!("{} (id: {})", release.tag_name, release.id.0),
+                ));
+                entries.push(plan_entry("target commitish", release.target_commitish));
+                entries.push(plan_entry(
+                    "release directory",
+                    release_directory.display().to_string(),
+                ));
+            }
+            Err(err) => checks.push(plan_check("release resolvable", false, &err.to_string())),
+        }
+        let release_deployed = self
+            .deployment_accessor
+            .has_release_directory(&deploy_config, &release_id);
+        checks.push(plan_check(
+            "release not deployed yet",
+            !release_deployed,
+            if release_deployed {
+                "a release directory for the release exists already"
+            } else {
+                ""
+            },
+        ));
+
+        // check if the environment of the deployment is prepared on this server
+        if let Some(deployment_slot) = &deploy_config.deployment_slot {
+            entries.push(plan_entry("slot", deployment_slot.name.clone()));
+        }
+        for symlink in deploy_config.get_symlinks() {
+            let target_exists = fs::try_exists(&symlink.target).await.unwrap_or(false);
+            checks.push(plan_check(
+                &format!("symlink target {} exists", symlink.target),
+                target_exists || symlink.create_target_dir,
+                if !target_exists && symlink.create_target_dir {
+                    "created during the deployment"
+                } else {
+                    ""
+                },
+            ));
+            entries.push(plan_entry(
+                &format!("symlink {}", symlink.source),
+                symlink.target,
+            ));
+        }
+        // the configured minimum free disk space is required when the deployment is executed, without one the
+        // plan warns about less than the planned minimum
+        let min_free_disk_bytes = self
+            .configuration_accessor
+            .get_configuration()
+            .await
+            .min_free_disk_mb
+            .map_or(MIN_PLANNED_DISK_SPACE_BYTES, |min_free_disk_mb| {
+                min_free_disk_mb.saturating_mul(BYTES_PER_MIB)
+            });
+        checks.push(match self.deployment_accessor.get_available_disk_space() {
+            Ok(available_bytes) => plan_check(
+                "disk space available",
+                available_bytes >= min_free_disk_bytes,
+                &format!(
+                    "{:.1} GiB available",
+                    available_bytes as f64 / (1024.0 * 1024.0 * 1024.0)
+                ),
+            ),
+            Err(err) => plan_check("disk space available", false, &err.to_string()),
+        });
+
+        let response = DeployPlanResponse {
+            profile: deploy_config.id,
+            entries,
+            checks,
         };
         Ok(Response::new(response))
     }
+
+    async fn adopt_release(
+        &self,
+        request: Request<AdoptReleaseRequest>,
+    ) -> Result<Response<AdoptReleaseResponse>, Status> {
+        require_role(&request, AccessRole::Admin)?;
+        let request_message = request.get_ref();
+        let request_identity = RequestIdentity::from_request(&request);
+        let release_id = request_message.release_id;
+        info!(
+            "Received request from {} to adopt directory {} as release {} for profile {}",
+            request_identity, request_message.source_directory, release_id, request_message.profile
+        );
+
+        // check if the metadata can be stored with the adopted release
+        if let Err(err) = validate_metadata(&request_message.metadata) {
+            return Err(Status::invalid_argument(err.to_string()));
+        }
+
+        let deploy_config = match self
+            .configuration_accessor
+            .get_configuration()
+            .await
+            .get_deployment_configuration(&request_message.profile)
+        {
+            Some(deployment_configuration) if !deployment_configuration.extend_only => {
+                deployment_configuration
+            }
+            Some(_) => {
+                return Err(Status::failed_precondition(
+                    "the requested deployment profile cannot be used directly",
+                ))
+            }
+            None => {
+                return Err(Status::failed_precondition(
+                    "requested deployment config is not registered",
+                ))
+            }
+        };
+
+        // moving directories into the releases directory must not interfere with a running action
+        if !matches!(
+            self.deployment_status_accessor.get_action().await,
+            CurrentAction::Idle
+        ) {
+            return Err(Status::failed_precondition(
+                "another action is currently running, try again afterwards",
+            ));
+        }
+
+        let release_tag = match &request_message.tag_name {
+            Some(tag_name) => tag_name.clone(),
+            None => match self.get_release(&release_id, &deploy_config).await {
+                Ok(release) => release.tag_name,
+                Err(err) => {
+                    let error_message = format!("unable to find requested release: {err:?}");
+                    return Err(Status::failed_precondition(error_message));
+                }
+            },
+        };
+        let release_directory = match adopt_release_directory(
+            &self.deployment_accessor,
+            &deploy_config,
+            &release_id,
+            &release_tag,
+            Path::new(&request_message.source_directory),
+        )
+        .await
+        {
+            Ok(release_directory) => release_directory,
+            Err(err) => {
+                let error_message = format!("unable to adopt release directory: {err}");
+                return Err(Status::failed_precondition(error_message));
+            }
+        };
+        info!(
+            "Adopted directory {} as release {} in {:?}",
+            request_message.source_directory, release_id, release_directory
+        );
+
+        if let Err(err) = self
+            .release_metadata_accessor
+            .store_metadata(&deploy_config, &release_id, &request_message.metadata)
+            .await
+        {
+            warn!("Unable to store metadata of adopted release {release_id}: {err}");
+        }
+        if request_message.publish {
+            if let Err(err) = publish_adopted_release(
+                &self.deployment_accessor,
+                &deploy_config,
+                &release_directory,
+            ) {
+                let error_message = format!("unable to publish adopted release: {err}");
+                return Err(Status::internal(error_message));
+            }
+        }
+
+        let response = AdoptReleaseResponse {
+            profile: deploy_config.id,
+            release_id,
+            release_directory: release_directory.display().to_string(),
+            published: request_message.publish,
+        };
+        Ok(Response::new(response))
+    }
+
+    async fn list_releases(
+        &self,
+        request: Request<ListReleasesRequest>,
+    ) -> Result<Response<ListReleasesResponse>, Status> {
+        require_role(&request, AccessRole::Viewer)?;
+        let request_message = request.get_ref();
+        let deploy_config = match self
+            .configuration_accessor
+            .get_configuration()
+            .await
+            .get_deployment_configuration(&request_message.profile)
+        {
+            Some(deployment_configuration) => deployment_configuration,
+            None => {
+                return Err(Status::failed_precondition(
+                    "requested deployment config is not registered",
+                ))
+            }
+        };
+
+        // the page token is the id of the last release of the previous page, as release ids are
+        // ascending the token stays valid when new releases are stored in the meantime
+        let page_size = match request_message.page_size {
+            0 => DEFAULT_RELEASE_PAGE_SIZE,
+            page_size => page_size.min(MAX_RELEASE_PAGE_SIZE) as usize,
+        };
+        let last_listed_release_id = if request_message.page_token.is_empty() {
+            None
+        } else {
+            match request_message.page_token.parse::<u64>() {
+                Ok(release_id) => Some(release_id),
+                Err(_) => return Err(Status::invalid_argument("invalid page token")),
+            }
+        };
+
+        let releases_directory = self
+            .deployment_accessor
+            .get_releases_directory(&deploy_config);
+        let release_directories = if fs::try_exists(&releases_directory).await.unwrap_or(false) {
+            match self
+                .deployment_accessor
+                .get_release_directories_for_profile(&deploy_config)
+                .await
+            {
+                Ok(release_directories) => release_directories,
+                Err(err) => {
+                    let error_message = format!("unable to resolve stored releases: {err}");
+                    return Err(Status::internal(error_message));
+                }
+            }
+        } else {
+            Vec::new()
+        };
+        let current_release_directory = fs::canonicalize(
+            self.deployment_accessor
+                .get_current_release_directory(&deploy_config),
+        )
+        .await
+        .ok();
+
+        let mut releases = Vec::<StoredRelease>::new();
+        let mut next_page_token = String::new();
+        for (release_directory, release_id) in release_directories {
+            if last_listed_release_id.is_some_and(|last_release_id| release_id >= last_release_id) {
+                continue;
+            }
+
+            // apply the requested filters
+            let stored_at = fs::metadata(&release_directory)
+                .await
+                .and_then(|metadata| metadata.modified())
+                .ok()
+                .and_then(|modified| modified.duration_since(UNIX_EPOCH).ok())
+                .map(|duration| duration.as_secs())
+                .unwrap_or_default();
+            if request_message
+                .stored_after
+                .is_some_and(|stored_after| stored_at < stored_after)
+                || request_message
+                    .stored_before
+                    .is_some_and(|stored_before| stored_at >= stored_before)
+            {
+                continue;
+            }
+            let marked_bad = matches!(
+                self.release_tombstone_accessor
+                    .get_tombstone(&deploy_config, &release_id)
+                    .await,
+                Ok(Some(_))
+            );
+            if request_message
+                .marked_bad
+                .is_some_and(|requested_marked_bad| requested_marked_bad != marked_bad)
+            {
+                continue;
+            }
+
+            // another matching release exists, continue with it on the next page
+            if releases.len() == page_size {
+                if let Some(last_release) = releases.last() {
+                    next_page_token = last_release.release_id.to_string();
+                }
+                break;
+            }
+
+            let current = current_release_directory.is_some()
+                && fs::canonicalize(&release_directory).await.ok() == current_release_directory;
+            let metadata = self
+                .release_metadata_accessor
+                .get_metadata(&deploy_config, &release_id)
+                .await
+                .unwrap_or_else(|err| {
+                    warn!("Unable to read metadata of release {release_id}: {err:?}");
+                    HashMap::new()
+                });
+            let release_tag = self
+                .github_accessor
+                .get_release_by_id(&release_id, &deploy_config)
+                .await
+                .map(|release| release.tag_name)
+                .ok();
+            let disk_usage_bytes = self
+                .deployment_accessor
+                .get_release_disk_usage(&release_directory)
+                .await
+                .unwrap_or_else(|err| {
+                    warn!("Unable to get disk usage of release {release_id}: {err:?}");
+                    0
+                });
+            releases.push(StoredRelease {
+                release_id,
+                directory_name: release_directory
+                    .file_name()
+                    .map(|file_name| file_name.to_string_lossy().to_string())
+                    .unwrap_or_default(),
+                stored_at,
+                current,
+                marked_bad,
+                metadata,
+                release_tag,
+                disk_usage_bytes,
+            });
+        }
+
+        let response = ListReleasesResponse {
+            profile: deploy_config.id,
+            releases,
+            next_page_token,
+        };
+        Ok(Response::new(response))
+    }
+
+    async fn get_deployment_history(
+        &self,
+        request: Request<DeploymentHistoryRequest>,
+    ) -> Result<Response<DeploymentHistoryResponse>, Status> {
+        require_role(&request, AccessRole::Viewer)?;
+        let request_message = request.get_ref();
+        let deploy_config = match self
+            .configuration_accessor
+            .get_configuration()
+            .await
+            .get_deployment_configuration(&request_message.profile)
+        {
+            Some(deployment_configuration) => deployment_configuration,
+            None => {
+                return Err(Status::failed_precondition(
+                    "requested deployment config is not registered",
+                ))
+            }
+        };
+
+        // the page token is the sequence number of the last entry of the previous page, as entries are only
+        // appended the token stays valid when new entries are recorded in the meantime
+        let page_size = match request_message.page_size {
+            0 => DEFAULT_HISTORY_PAGE_SIZE,
+            page_size => page_size.min(MAX_HISTORY_PAGE_SIZE) as usize,
+        };
+        let before_sequence_number = if request_message.page_token.is_empty() {
+            None
+        } else {
+            match request_message.page_token.parse::<usize>() {
+                Ok(sequence_number) => Some(sequence_number),
+                Err(_) => return Err(Status::invalid_argument("invalid page token")),
+            }
+        };
+
+        let history_page = match self
+            .deployment_history_accessor
+            .get_history_page(
+                &deploy_config.id,
+                page_size,
+                before_sequence_number,
+                |history_entry| {
+                    request_message.started_after.map_or(true, |started_after| {
+                        history_entry.started_at >= started_after
+                    }) && request_message
+                        .started_before
+                        .map_or(true, |started_before| {
+                            history_entry.started_at < started_before
+                        })
+                },
+            )
+            .await
+        {
+            Ok(history_page) => history_page,
+            Err(err) => {
+                let error_message = format!("unable to read deployment history: {err:?}");
+                return Err(Status::internal(error_message));
+            }
+        };
+        let entries = history_page
+            .entries
+            .into_iter()
+            .map(|history_entry| DeploymentHistoryEntry {
+                action: history_entry.action,
+                release_id: history_entry.release_id,
+                release_tag: history_entry.release_tag,
+                started_at: history_entry.started_at,
+                finished_at: history_entry.finished_at,
+                succeeded: history_entry.succeeded,
+                triggered_by: history_entry.triggered_by,
+                failure_archive: history_entry.failure_archive,
+                reason: history_entry.reason,
+                ticket_reference: history_entry.ticket_reference,
+                git_ref: history_entry.git_ref,
+                metadata: history_entry.metadata,
+            })
+            .collect();
+        let response = DeploymentHistoryResponse {
+            profile: deploy_config.id,
+            entries,
+            next_page_token: history_page
+                .next_sequence_number
+                .map(|sequence_number| sequence_number.to_string())
+                .unwrap_or_default(),
+        };
+        Ok(Response::new(response))
+    }
+
+    type ExecCommandStream = ReceiverStream<Result<ExecutedActionEntry, Status>>;
+
+    async fn exec_command(
+        &self,
+        request: Request<ExecCommandRequest>,
+    ) -> Result<Response<Self::ExecCommandStream>, Status> {
+        require_role(&request, AccessRole::Deployer)?;
+        let request_message = request.get_ref();
+        let request_identity = RequestIdentity::from_request(&request);
+        info!(
+            "received request from {} to execute command {} on profile {}",
+            request_identity, request_message.command_name, request_message.profile
+        );
+
+        // get the requested deployment profile configuration & the requested command, only configured
+        // commands can be executed
+        let deploy_config = match self
+            .configuration_accessor
+            .get_configuration()
+            .await
+            .get_deployment_configuration(&request_message.profile)
+        {
+            Some(deployment_configuration) => deployment_configuration,
+            None => {
+                return Err(Status::failed_precondition(
+                    "requested deployment config is not registered",
+                ))
+            }
+        };
+        let exec_command = match deploy_config
+            .exec_commands
+            .iter()
+            .find(|exec_command| exec_command.name == request_message.command_name)
+        {
+            Some(exec_command) => exec_command.clone(),
+            None => {
+                return Err(Status::not_found(
+                    "requested command is not configured for the deployment config",
+                ))
+            }
+        };
+
+        // resolve the published release once, the command keeps running in its directory even if another
+        // release is published in the meantime
+        let (release_directory, release_id) = match self
+            .deployment_accessor
+            .get_published_release_directory(&deploy_config)
+        {
+            Some(published_release) => published_release,
+            None => {
+                return Err(Status::failed_precondition(
+                    "no release is published with the requested profile",
+                ))
+            }
+        };
+
+        // commands do not occupy the action slot and are not terminated when a running deployment is cancelled
+        let execution_environment = self.execution_environment.with_new_cancellation();
+        let (data_sender, data_receiver) = channel::<Result<ExecutedActionEntry, Status>>(50);
+        tokio::spawn(async move {
+            execute_exec_command(
+                release_id,
+                &release_directory,
+                &exec_command,
+                &deploy_config,
+                &execution_environment,
+                &data_sender,
+            )
+            .await;
+        });
+        Ok(Response::new(ReceiverStream::new(data_receiver)))
+    }
+
+    type PruneReleasesStream = ReceiverStream<Result<ExecutedActionEntry, Status>>;
+
+    async fn prune_releases(
+        &self,
+        request: Request<PruneReleasesRequest>,
+    ) -> Result<Response<Self::PruneReleasesStream>, Status> {
+        require_role(&request, AccessRole::Deployer)?;
+        let request_message = request.get_ref();
+        let request_identity = RequestIdentity::from_request(&request);
+        info!(
+            "Received request from {} to prune the stored releases of profile {}",
+            request_identity, request_message.profile
+        );
+        if request_message.keep_releases.is_none() && request_message.max_disk_usage_bytes.is_none()
+        {
+            return Err(Status::invalid_argument(
+                "either the amount of releases to keep or the maximum disk usage must be given",
+            ));
+        }
+        if request_message.keep_releases == Some(0) {
+            return Err(Status::invalid_argument(
+                "at least one release must be kept",
+            ));
+        }
+
+        let deploy_config = match self
+            .configuration_accessor
+            .get_configuration()
+            .await
+            .get_deployment_configuration(&request_message.profile)
+        {
+            Some(deployment_configuration) => deployment_configuration,
+            None => {
+                return Err(Status::failed_precondition(
+                    "requested deployment config is not registered",
+                ))
+            }
+        };
+
+        // never touch the release directories while an action is running as they might be in use
+        if !matches!(
+            self.deployment_status_accessor.get_action().await,
+            CurrentAction::Idle
+        ) {
+            return Err(Status::failed_precondition(
+                "cannot prune releases while an action is being executed",
+            ));
+        }
+
+        let prune_limits = PruneLimits {
+            keep_releases: request_message
+                .keep_releases
+                .map(|keep_releases| keep_releases as usize),
+            max_disk_usage_bytes: request_message.max_disk_usage_bytes,
+        };
+        let deployment_accessor = self.deployment_accessor.clone();
+        let (data_sender, data_receiver) = channel::<Result<ExecutedActionEntry, Status>>(50);
+        tokio::spawn(async move {
+            prune_releases(
+                &prune_limits,
+                &deployment_accessor,
+                &deploy_config,
+                &data_sender,
+            )
+            .await;
+        });
+        Ok(Response::new(ReceiverStream::new(data_receiver)))
+    }
+}
+
+/// Constructs an entry of a deployment plan.
+///
+/// # Arguments
+/// * `name` - The name of the planned step.
+/// * `value` - The value of the planned step.
+fn plan_entry(name: &str, value: String) -> DeployPlanEntry {
+    DeployPlanEntry {
+        name: name.to_string(),
+        value,
+    }
+}
+
+/// Constructs the result of a check of a prerequisite of a deployment plan.
+///
+/// # Arguments
+/// * `name` - The name of the check.
+/// * `passed` - If the prerequisite is satisfied.
+/// * `detail` - The details of the check result, can be empty.
+fn plan_check(name: &str, passed: bool, detail: &str) -> DeployPlanCheck {
+    DeployPlanCheck {
+        name: name.to_string(),
+        passed,
+        detail: detail.to_string(),
+    }
+}
+
+/// Deletes the given prepared deployment and switches the deployment service back to idle, unless the deployment was
+/// published or deleted in the meantime.
+///
+/// # Arguments
+/// * `deployment_executor` - The executor of the prepared deployment that expired.
+/// * `deployment_status_accessor` - The accessor for the current action of the deployment service.
+/// * `notification_dispatcher` - The dispatcher to notify about the deletion of the deployment.
+/// * `release_metadata_accessor` - The accessor to read the metadata of the deployment for the history with.
+async fn expire_prepared_deployment(
+    deployment_executor: &DeployExecutor,
+    deployment_status_accessor: &DeploymentStatusAccessor,
+    notification_dispatcher: &NotificationDispatcher,
+    release_metadata_accessor: &ReleaseMetadataAccessor,
+) {
+    if !deployment_executor
+        .get_status_accessor()
+        .compare_and_set_state(
+            &DeployExecutionState::Prepared,
+            DeployExecutionState::Deleting,
+        )
+        .await
+    {
+        return;
+    }
+
+    // no client is listening for the output of the deletion, the output is discarded
+    info!(
+        "Prepared deployment {} expired, deleting it",
+        deployment_executor.get_release_id()
+    );
+    let started_at = Instant::now();
+    let (data_sender, _) = channel::<Result<ExecutedActionEntry, Status>>(1);
+    let (recording_sender, outcome_handle) = record_action_outcome(
+        data_sender,
+        deployment_executor.get_deployment_configuration(),
+    );
+    supervise_action(
+        "delete",
+        deployment_status_accessor,
+        &recording_sender,
+        deployment_executor.delete_deployment(recording_sender.clone()),
+    )
+    .await;
+    drop(recording_sender);
+    deployment_status_accessor
+        .set_action(CurrentAction::Idle)
+        .await;
+    let metadata = read_release_metadata(
+        release_metadata_accessor,
+        deployment_executor.get_deployment_configuration(),
+        deployment_executor.get_release_id(),
+    )
+    .await;
+    let notification = DeploymentNotification::from_outcome(
+        NotificationEvent::Deleted,
+        "delete",
+        deployment_executor.get_release(),
+        deployment_executor.get_deployment_configuration(),
+        &RequestIdentity::internal(PREPARED_EXPIRY_IDENTITY),
+        started_at.elapsed(),
+        outcome_handle.await.unwrap_or_default(),
+    )
+    .with_git_ref(deployment_executor.get_git_ref().cloned())
+    .with_metadata(metadata);
+    notification_dispatcher.dispatch(notification);
+}
+
+/// Executes the given deployment that was set as current action: stores its metadata, prepares it and deletes the
+/// prepared deployment if it is not published in time.
+///
+/// # Arguments
+/// * `deployment_executor` - The executor of the deployment, set as the current action.
+/// * `metadata` - The metadata to store with the release.
+/// * `release_metadata_accessor` - The accessor to store the metadata of the release with.
+/// * `deployment_status_accessor` - The accessor for the action that is currently being executed.
+/// * `notification_dispatcher` - The dispatcher to notify about the deployment.
+/// * `data_sender` - The sender to which the executed action entries are sent.
+async fn execute_deployment(
+    deployment_executor: Arc<DeployExecutor>,
+    metadata: HashMap<String, String>,
+    release_metadata_accessor: ReleaseMetadataAccessor,
+    deployment_status_accessor: DeploymentStatusAccessor,
+    notification_dispatcher: NotificationDispatcher,
+    data_sender: Sender<Result<ExecutedActionEntry, Status>>,
+) {
+    // store the metadata of the deployment, a failure is not worth aborting the deployment for
+    if let Err(err) = release_metadata_accessor
+        .store_metadata(
+            deployment_executor.get_deployment_configuration(),
+            &deployment_executor.get_release_id(),
+            &metadata,
+        )
+        .await
+    {
+        warn!(
+            "Unable to store metadata of release {}: {:?}",
+            deployment_executor.get_release_id(),
+            err
+        );
+    }
+
+    // execute the deployment, deleting the prepared deployment if it is not published in time
+    let started_at = Instant::now();
+    let (recording_sender, outcome_handle) = record_action_outcome(
+        data_sender,
+        deployment_executor.get_deployment_configuration(),
+    );
+    let prepare_completed = supervise_action(
+        "prepare",
+        &deployment_status_accessor,
+        &recording_sender,
+        deployment_executor.prepare_deployment(recording_sender.clone()),
+    )
+    .await
+    .is_some();
+    drop(recording_sender);
+    let notification = DeploymentNotification::from_outcome(
+        NotificationEvent::Prepared,
+        "prepare",
+        deployment_executor.get_release(),
+        deployment_executor.get_deployment_configuration(),
+        deployment_executor.get_triggered_by(),
+        started_at.elapsed(),
+        outcome_handle.await.unwrap_or_default(),
+    )
+    .with_annotation(deployment_executor.get_annotation())
+    .with_git_ref(deployment_executor.get_git_ref().cloned())
+    .with_metadata(metadata);
+    notification_dispatcher.dispatch(notification);
+    if !prepare_completed {
+        return;
+    }
+    // cancelled and failed deployments were removed, free the action slot for the next action
+    if matches!(
+        deployment_executor.get_status_accessor().get_state().await,
+        DeployExecutionState::Cancelled | DeployExecutionState::Failed
+    ) {
+        deployment_status_accessor
+            .set_action(CurrentAction::Idle)
+            .await;
+        return;
+    }
+    if let Some(prepared_time_remaining) = deployment_executor.get_prepared_time_remaining().await {
+        sleep(prepared_time_remaining).await;
+        expire_prepared_deployment(
+            &deployment_executor,
+            &deployment_status_accessor,
+            &notification_dispatcher,
+            &release_metadata_accessor,
+        )
+        .await;
+    }
+}
+
+/// Reads the metadata that was attached to the deployment of the given release, to record it in the history. An empty
+/// map is returned if the metadata cannot be read.
+///
+/// # Arguments
+/// * `release_metadata_accessor` - The accessor for the release metadata.
+/// * `deploy_config` - The deployment profile configuration in which the release is deployed.
+/// * `release_id` - The id of the release to read the metadata of.
+async fn read_release_metadata(
+    release_metadata_accessor: &ReleaseMetadataAccessor,
+    deploy_config: &DeploymentConfiguration,
+    release_id: u64,
+) -> HashMap<String, String> {
+    match release_metadata_accessor
+        .get_metadata(deploy_config, &release_id)
+        .await
+    {
+        Ok(metadata) => metadata,
+        Err(err) => {
+            warn!(
+                "Unable to read metadata of release {}: {:?}",
+                release_id, err
+            );
+            HashMap::new()
+        }
+    }
+}
+
+/// Records a deployment once it occupies the server, so that deployments which are rejected before do not count
+/// towards the rate limit of their profile and do not leave ref deployments behind.
+struct StartedDeploymentRecorder {
+    /// The deployment profile configuration of the deployment.
+    deploy_config: DeploymentConfiguration,
+    /// If the deployment may exceed the rate limit of its profile.
+    override_rate_limit: bool,
+    /// The accessor to record the deployment in the rate limit of its profile.
+    deployment_rate_limit_accessor: DeploymentRateLimitAccessor,
+    /// The accessor to record the deployment if it was started from a git ref.
+    ref_deployment_accessor: RefDeploymentAccessor,
+    /// The accessor for the current action, switched back to idle if the deployment cannot be recorded.
+    deployment_status_accessor: DeploymentStatusAccessor,
+}
+
+impl StartedDeploymentRecorder {
+    /// Records the deployment that just occupied the server: counts it towards the rate limit of its profile and
+    /// records the planned ref deployment (if started from a git ref), reporting the id assigned to it to the client.
+    /// The server is switched back to idle if the deployment cannot be recorded.
+    ///
+    /// # Arguments
+    /// * `ref_deployment` - The planned deployment of a git ref, if the deployment was started from a git ref.
+    /// * `data_sender` - The sender to report the id assigned to the ref deployment to.
+    async fn record(
+        &self,
+        ref_deployment: Option<&RefDeployment>,
+        data_sender: &Sender<Result<ExecutedActionEntry, Status>>,
+    ) -> Result<(), Status> {
+        if let Err(retry_after) = self
+            .deployment_rate_limit_accessor
+            .try_record_deployment(&self.deploy_config, self.override_rate_limit)
+            .await
+        {
+            self.deployment_status_accessor
+                .set_action(CurrentAction::Idle)
+                .await;
+            return Err(rate_limit_status(&self.deploy_config, retry_after));
+        }
+
+        let Some(ref_deployment) = ref_deployment else {
+            return Ok(());
+        };
+        if let Err(err) = self
+            .ref_deployment_accessor
+            .record_ref_deployment(&self.deploy_config, ref_deployment)
+            .await
+        {
+            self.deployment_status_accessor
+                .set_action(CurrentAction::Idle)
+                .await;
+            let error_message = format!("unable to record ref deployment: {err}");
+            return Err(Status::internal(error_message));
+        }
+
+        // report the id assigned to the deployment, which is needed to publish or delete it
+        let log_entry = LogEntry {
+            stream_type: LogType::Stdout.into(),
+            content: format!(
+                "Deploying git ref {} (commit {}) as release {}",
+                ref_deployment.git_ref, ref_deployment.commit_sha, ref_deployment.release_id
+            ),
+        };
+        let action_entry = ExecutedActionEntry {
+            release_id: ref_deployment.release_id,
+            current_action: Action::GitClone.into(),
+            action_status: ActionStatus::Running.into(),
+            action_log_entry: Some(log_entry),
+            progress_percent: None,
+            phase: None,
+        };
+        data_sender.send(Ok(action_entry)).await.ok();
+        Ok(())
+    }
+}
+
+/// Plans a new deployment of the given git ref in the target of the given profile, without recording it. The
+/// deployment gets the id following the given base id which is neither used by a recorded ref deployment nor by an
+/// existing release directory.
+///
+/// # Arguments
+/// * `ref_deployment_accessor` - The accessor for the recorded ref deployments.
+/// * `deployment_accessor` - The accessor for the release directories.
+/// * `deploy_config` - The deployment profile configuration in which the git ref is deployed.
+/// * `git_ref` - The git ref to deploy.
+/// * `commit_sha` - The SHA of the commit that the git ref resolved to.
+/// * `base_release_id` - The id after which the id of the deployment should be assigned.
+async fn plan_ref_deployment(
+    ref_deployment_accessor: &RefDeploymentAccessor,
+    deployment_accessor: &DeploymentAccessor,
+    deploy_config: &DeploymentConfiguration,
+    git_ref: String,
+    commit_sha: String,
+    base_release_id: u64,
+) -> Result<RefDeployment, Status> {
+    ref_deployment_accessor
+        .plan_ref_deployment(
+            deploy_config,
+            git_ref,
+            commit_sha,
+            base_release_id,
+            |release_id| deployment_accessor.has_release_directory(deploy_config, &release_id),
+        )
+        .await
+        .map_err(|err| {
+            let error_message = format!("unable to plan ref deployment: {err}");
+            Status::internal(error_message)
+        })
+}
+
+/// Constructs the release that is deployed for the given planned ref deployment.
+///
+/// # Arguments
+/// * `ref_deployment` - The planned ref deployment.
+/// * `deploy_config` - The deployment profile configuration in which the git ref is deployed.
+fn ref_deployment_release(
+    ref_deployment: &RefDeployment,
+    deploy_config: &DeploymentConfiguration,
+) -> Result<Release, Status> {
+    ref_deployment.to_release(deploy_config).map_err(|err| {
+        let error_message = format!("unable to construct release from git ref: {err}");
+        Status::internal(error_message)
+    })
+}
+
+/// Constructs the status that rejects a deployment because its profile reached its limit of deployments per hour.
+///
+/// # Arguments
+/// * `deploy_config` - The deployment profile configuration that reached its limit.
+/// * `retry_after` - The time until the next deployment with the profile is possible.
+fn rate_limit_status(deploy_config: &DeploymentConfiguration, retry_after: Duration) -> Status {
+    let error_message = format!(
+        "profile {} reached its limit of {} deployments per hour, retry in {} seconds",
+        deploy_config.id,
+        deploy_config.max_deployments_per_hour.unwrap_or_default(),
+        retry_after.as_secs()
+    );
+    Status::resource_exhausted(error_message)
+}
+
+/// Constructs an executed action entry that reports the state of a queued deployment.
+///
+/// # Arguments
+/// * `release_id` - The id of the queued release.
+/// * `message` - The message describing the state of the queued deployment.
+fn construct_queue_entry(release_id: u64, message: String) -> ExecutedActionEntry {
+    ExecutedActionEntry {
+        release_id,
+        current_action: Action::Queue.into(),
+        action_status: ActionStatus::Running.into(),
+        action_log_entry: Some(LogEntry {
+            stream_type: LogType::Stdout.into(),
+            content: message,
+        }),
+        progress_percent: None,
+        phase: None,
+    }
+}
+
+/// Formats the given optional annotation for log messages. An empty string is returned if no annotation is given.
+///
+/// # Arguments
+/// * `annotation` - The annotation that was supplied with a request, if any.
+fn format_annotation(annotation: &Option<DeployAnnotation>) -> String {
+    match annotation {
+        Some(annotation) => match &annotation.ticket_reference {
+            Some(ticket_reference) => {
+                format!(" (reason: {} [{}])", annotation.message, ticket_reference)
+            }
+            None => format!(" (reason: {})", annotation.message),
+        },
+        None => String::new(),
+    }
 }
@@ -0,0 +1,420 @@
+/*
+ * This file is part of easydep, licensed under the MIT License (MIT).
+ *
+ * Copyright (c) 2024 easybill GmbH
+ *
+ * Permission is hereby granted, free of charge, to any person obtaining a copy
+ * of this software and associated documentation files (the "Software"), to deal
+ * in the Software without restriction, including without limitation the rights
+ * to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+ * copies of the Software, and to permit persons to whom the Software is
+ * furnished to do so, subject to the following conditions:
+ *
+ * The above copyright notice and this permission notice shall be included in all
+ * copies or substantial portions of the Software.
+ *
+ * THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+ * IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+ * FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+ * AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+ * LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+ * OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+ * SOFTWARE.
+ */
+use std::convert::Infallible;
+use std::sync::Arc;
+
+use anyhow::{bail, Context};
+use http_body_util::{BodyExt, Full, Limited};
+use hyper::body::{Bytes, Incoming};
+use hyper::server::conn::http1;
+use hyper::service::service_fn;
+use hyper::{Method, Request, Response, StatusCode};
+use hyper_util::rt::TokioIo;
+use log::{debug, error, info, warn};
+use ring::hmac;
+use serde::Deserialize;
+use tokio::fs;
+use tokio::net::TcpListener;
+
+use crate::accessor::configuration_accessor::ConfigurationAccessor;
+use crate::accessor::deploy_action_accessor::{CurrentAction, DeploymentStatusAccessor};
+use crate::accessor::deployment_rate_limit_accessor::DeploymentRateLimitAccessor;
+use crate::accessor::github_accessor::GitHubAccessor;
+use crate::accessor::release_tombstone_accessor::ReleaseTombstoneAccessor;
+use crate::capability::DeploymentRequirements;
+use crate::config::{DeploymentConfiguration, GitHubWebhookConfiguration};
+use crate::easydep::DeployAnnotation;
+use crate::executor::automatic_deployment_executor::prepare_and_publish_deployment;
+use crate::executor::deploy_executor::DeployExecutor;
+use crate::hex::decode_hex;
+use crate::notification::notification_dispatcher::NotificationDispatcher;
+use crate::service::request_identity::RequestIdentity;
+
+/// The name of the identity that is recorded for deployments triggered by the GitHub webhook.
+const GITHUB_WEBHOOK_IDENTITY: &str = "easydep github webhook";
+/// The maximum size (in bytes) of a webhook payload that is accepted.
+const MAX_WEBHOOK_PAYLOAD_SIZE: usize = 1024 * 1024;
+/// The header containing the name of the event that triggered the webhook.
+const GITHUB_EVENT_HEADER: &str = "X-GitHub-Event";
+/// The header containing the HMAC-SHA256 signature of the webhook payload.
+const GITHUB_SIGNATURE_HEADER: &str = "X-Hub-Signature-256";
+/// The prefix of the signature in the signature header.
+const GITHUB_SIGNATURE_PREFIX: &str = "sha256=";
+
+/// The payload of a `release` webhook, only containing the fields that are needed to trigger the deployments.
+#[derive(Deserialize, Debug)]
+struct ReleaseWebhookPayload {
+    /// The action that was performed on the release, for example `published`.
+    action: String,
+    /// The release on which the action was performed.
+    release: ReleaseWebhookRelease,
+    /// The repository in which the release is located.
+    repository: ReleaseWebhookRepository,
+}
+
+/// The release of a `release` webhook.
+#[derive(Deserialize, Debug)]
+struct ReleaseWebhookRelease {
+    /// The id of the release.
+    id: u64,
+    /// The name of the tag associated with the release.
+    tag_name: String,
+}
+
+/// The repository of a `release` webhook.
+#[derive(Deserialize, Debug)]
+struct ReleaseWebhookRepository {
+    /// The name of the repository.
+    name: String,
+    /// The owner of the repository.
+    owner: ReleaseWebhookRepositoryOwner,
+}
+
+/// The owner of the repository of a `release` webhook.
+#[derive(Deserialize, Debug)]
+struct ReleaseWebhookRepositoryOwner {
+    /// The login name of the owner.
+    login: String,
+}
+
+/// Receives the `release` webhooks of GitHub and deploys the published releases with the deployment configurations that
+/// set `deploy_published_releases`. Each deployment is prepared and directly published.
+#[derive(Clone)]
+pub(crate) struct GitHubWebhookService {
+    /// The path at which the webhooks are received.
+    path: String,
+    /// The key to verify the signatures of the webhook payloads with.
+    signing_key: hmac::Key,
+    /// The accessor for the current server configuration.
+    configuration_accessor: ConfigurationAccessor,
+    /// The accessor for the GitHub api.
+    github_accessor: GitHubAccessor,
+    /// The accessor for the action that is currently being executed.
+    deployment_status_accessor: DeploymentStatusAccessor,
+    /// The dispatcher to notify about the deployments.
+    notification_dispatcher: NotificationDispatcher,
+    /// The accessor for the deployment rate limits of the profiles.
+    deployment_rate_limit_accessor: DeploymentRateLimitAccessor,
+    /// The accessor for the releases that were marked as bad.
+    release_tombstone_accessor: ReleaseTombstoneAccessor,
+}
+
+impl GitHubWebhookService {
+    /// Constructs a new webhook service, reading the webhook secret from the configured file.
+    ///
+    /// # Arguments
+    /// * `webhook_configuration` - The settings of the webhook listener.
+    /// * `configuration_accessor` - The accessor for the current server configuration.
+    /// * `github_accessor` - The accessor for the GitHub api.
+    /// * `deployment_status_accessor` - The accessor for the action that is currently being executed.
+    /// * `notification_dispatcher` - The dispatcher to notify about the deployments.
+    /// * `deployment_rate_limit_accessor` - The accessor for the deployment rate limits of the profiles.
+    pub async fn new(
+        webhook_configuration: &GitHubWebhookConfiguration,
+        configuration_accessor: &ConfigurationAccessor,
+        github_accessor: &GitHubAccessor,
+        deployment_status_accessor: &DeploymentStatusAccessor,
+        notification_dispatcher: &NotificationDispatcher,
+        deployment_rate_limit_accessor: &DeploymentRateLimitAccessor,
+    ) -> anyhow::Result<Self> {
+        let webhook_secret = fs::read_to_string(&webhook_configuration.secret_file)
+            .await
+            .with_context(|| {
+                format!(
+                    "unable to read webhook secret from {}",
+                    webhook_configuration.secret_file
+                )
+            })?;
+        let webhook_secret = webhook_secret.trim();
+        if webhook_secret.is_empty() {
+            bail!(
+                "webhook secret file {} is empty",
+                webhook_configuration.secret_file
+            )
+        }
+
+        let global_configuration = configuration_accessor.get_configuration().await;
+        Ok(Self {
+            path: webhook_configuration.path.clone(),
+            signing_key: hmac::Key::new(hmac::HMAC_SHA256, webhook_secret.as_bytes()),
+            configuration_accessor: configuration_accessor.clone(),
+            github_accessor: github_accessor.clone(),
+            deployment_status_accessor: deployment_status_accessor.clone(),
+            notification_dispatcher: notification_dispatcher.clone(),
+            deployment_rate_limit_accessor: deployment_rate_limit_accessor.clone(),
+            release_tombstone_accessor: ReleaseTombstoneAccessor::new(&global_configuration),
+        })
+    }
+
+    /// Starts the task that accepts the http connections on the given listener and handles the webhook requests
+    /// received on them.
+    ///
+    /// # Arguments
+    /// * `listener` - The listener on which the http connections are accepted.
+    pub fn start_serving(self, listener: TcpListener) {
+        tokio::spawn(async move {
+            loop {
+                let (stream, remote_address) = match listener.accept().await {
+                    Ok(connection) => connection,
+                    Err(err) => {
+                        error!("Unable to accept webhook connection: {err}");
+                        continue;
+                    }
+                };
+                let webhook_service = self.clone();
+                tokio::spawn(async move {
+                    let request_handler = service_fn(move |request| {
+                        let webhook_service = webhook_service.clone();
+                        async move { Ok::<_, Infallible>(webhook_service.handle_request(request).await) }
+                    });
+                    if let Err(err) = http1::Builder::new()
+                        .serve_connection(TokioIo::new(stream), request_handler)
+                        .await
+                    {
+                        debug!("Webhook connection from {} failed: {err}", remote_address);
+                    }
+                });
+            }
+        });
+    }
+
+    /// Handles a single webhook request. The signature of the payload is verified before the payload is parsed,
+    /// deployments triggered by the webhook are executed in the background after the response was sent.
+    ///
+    /// # Arguments
+    /// * `request` - The received request.
+    async fn handle_request(&self, request: Request<Incoming>) -> Response<Full<Bytes>> {
+        if request.uri().path() != self.path {
+            return build_response(StatusCode::NOT_FOUND, "not found");
+        }
+        if request.method() != Method::POST {
+            return build_response(StatusCode::METHOD_NOT_ALLOWED, "method not allowed");
+        }
+
+        // read the event name and signature before the body is consumed
+        let event_name = request
+            .headers()
+            .get(GITHUB_EVENT_HEADER)
+            .and_then(|value| value.to_str().ok())
+            .unwrap_or_default()
+            .to_string();
+        let signature = request
+            .headers()
+            .get(GITHUB_SIGNATURE_HEADER)
+            .and_then(|value| value.to_str().ok())
+            .and_then(|value| value.strip_prefix(GITHUB_SIGNATURE_PREFIX))
+            .and_then(|value| decode_hex(value).ok());
+        let payload = match Limited::new(request.into_body(), MAX_WEBHOOK_PAYLOAD_SIZE)
+            .collect()
+            .await
+        {
+            Ok(body) => body.to_bytes(),
+            Err(err) => {
+                let error_message = format!("unable to read payload: {err}");
+                return build_response(StatusCode::BAD_REQUEST, &error_message);
+            }
+        };
+
+        // verify that the payload was signed with the configured secret
+        let signature_valid = signature
+            .is_some_and(|signature| hmac::verify(&self.signing_key, &payload, &signature).is_ok());
+        if !signature_valid {
+            warn!("Received webhook with missing or invalid signature");
+            return build_response(StatusCode::UNAUTHORIZED, "invalid signature");
+        }
+
+        match event_name.as_str() {
+            "ping" => build_response(StatusCode::OK, "pong"),
+            "release" => self.handle_release_event(&payload).await,
+            _ => build_response(StatusCode::ACCEPTED, "event ignored"),
+        }
+    }
+
+    /// Handles a `release` webhook event, starting the deployments of the release if it was published.
+    ///
+    /// # Arguments
+    /// * `payload` - The verified payload of the webhook.
+    async fn handle_release_event(&self, payload: &[u8]) -> Response<Full<Bytes>> {
+        let payload = match serde_json::from_slice::<ReleaseWebhookPayload>(payload) {
+            Ok(payload) => payload,
+            Err(err) => {
+                let error_message = format!("unable to parse release payload: {err}");
+                return build_response(StatusCode::BAD_REQUEST, &error_message);
+            }
+        };
+        if payload.action != "published" {
+            return build_response(StatusCode::ACCEPTED, "release action ignored");
+        }
+
+        // get the profiles that deploy the releases published in the repository
+        let deployment_configurations: Vec<DeploymentConfiguration> = self
+            .configuration_accessor
+            .get_configuration()
+            .await
+            .get_deployment_configurations()
+            .iter()
+            .filter(|config| config.deploy_published_releases && !config.extend_only)
+            .filter(|config| {
+                config
+                    .source_repo_owner
+                    .eq_ignore_ascii_case(&payload.repository.owner.login)
+                    && config
+                        .source_repo_name
+                        .eq_ignore_ascii_case(&payload.repository.name)
+            })
+            .cloned()
+            .collect();
+        if deployment_configurations.is_empty() {
+            return build_response(
+                StatusCode::ACCEPTED,
+                "no profile deploys releases of the repository",
+            );
+        }
+
+        // deploy the release with the profiles one after another, as only one action can be executed at a time
+        info!(
+            "Release {} ({}) of {}/{} was published, deploying it with {} profile(s)",
+            payload.release.id,
+            payload.release.tag_name,
+            payload.repository.owner.login,
+            payload.repository.name,
+            deployment_configurations.len()
+        );
+        let webhook_service = self.clone();
+        let release_id = payload.release.id;
+        tokio::spawn(async move {
+            for deployment_configuration in deployment_configurations {
+                if let Err(err) = webhook_service
+                    .deploy_published_release(&deployment_configuration, &release_id)
+                    .await
+                {
+                    error!(
+                        "Unable to deploy published release {} with profile {}: {err:?}",
+                        release_id, deployment_configuration.id
+                    );
+                }
+            }
+        });
+        build_response(StatusCode::ACCEPTED, "deployment triggered")
+    }
+
+    /// Deploys and publishes the given release with the given profile. The deployment is not retried if another action
+    /// is currently being executed, the release is rejected or the profile reached its deployment rate limit.
+    ///
+    /// # Arguments
+    /// * `deployment_configuration` - The deployment profile configuration to deploy the release with.
+    /// * `release_id` - The id of the published release.
+    async fn deploy_published_release(
+        &self,
+        deployment_configuration: &DeploymentConfiguration,
+        release_id: &u64,
+    ) -> anyhow::Result<()> {
+        if !matches!(
+            self.deployment_status_accessor.get_action().await,
+            CurrentAction::Idle
+        ) {
+            bail!("another action is currently being executed")
+        }
+
+        // check if the release can be deployed with the profile
+        if let Some(tombstone) = self
+            .release_tombstone_accessor
+            .get_tombstone(deployment_configuration, release_id)
+            .await?
+        {
+            bail!("release was marked as bad: {}", tombstone.reason)
+        }
+        let release = self
+            .github_accessor
+            .get_release_by_id(release_id, deployment_configuration)
+            .await?;
+        if !deployment_configuration.is_branch_allowed_to_use_config(&release.target_commitish) {
+            bail!(
+                "branch {} is not allowed to use the deployment configuration",
+                release.target_commitish
+            )
+        }
+        let release_body = release.body.as_deref().unwrap_or_default();
+        DeploymentRequirements::from_release_body(release_body)
+            .check()
+            .context("release cannot be deployed on this server")?;
+        if let Err(retry_after) = self
+            .deployment_rate_limit_accessor
+            .check_deployment(deployment_configuration, false)
+            .await
+        {
+            bail!(
+                "profile reached its deployment rate limit, retry in {} seconds",
+                retry_after.as_secs()
+            )
+        }
+
+        // construct the executor and deploy the release
+        let github_access_token = self
+            .github_accessor
+            .read_github_app_installation_token(deployment_configuration)
+            .await?;
+        let annotation = DeployAnnotation {
+            message: format!(
+                "Automatic deployment of published release {}",
+                release.tag_name
+            ),
+            ticket_reference: None,
+        };
+        let global_configuration = self.configuration_accessor.get_configuration().await;
+        let deployment_executor = Arc::new(DeployExecutor::new(
+            release,
+            github_access_token,
+            global_configuration.as_ref().clone(),
+            deployment_configuration.clone(),
+            Some(annotation),
+            RequestIdentity::internal(GITHUB_WEBHOOK_IDENTITY),
+            None,
+        ));
+        info!(
+            "Deploying published release {} with profile {}",
+            release_id, deployment_configuration.id
+        );
+        prepare_and_publish_deployment(
+            deployment_executor,
+            deployment_configuration,
+            &self.deployment_status_accessor,
+            &self.notification_dispatcher,
+            &self.deployment_rate_limit_accessor,
+            None,
+        )
+        .await
+    }
+}
+
+/// Builds a plain text response with the given status and message.
+///
+/// # Arguments
+/// * `status` - The status of the response.
+/// * `message` - The message to send in the response body.
+fn build_response(status: StatusCode, message: &str) -> Response<Full<Bytes>> {
+    let mut response = Response::new(Full::new(Bytes::from(message.to_string())));
+    *response.status_mut() = status;
+    response
+}
@@ -22,5 +22,10 @@
  * SOFTWARE.
  */
 
+pub(crate) mod auth_interceptor;
 pub(crate) mod deployment_service;
+pub(crate) mod github_webhook_service;
+pub(crate) mod request_identity;
+#[cfg(feature = "concurrency-simulation")]
+pub(crate) mod simulation_service;
 pub(crate) mod status_service;
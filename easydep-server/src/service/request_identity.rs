@@ -0,0 +1,99 @@
+/*
+ * This file is part of easydep, licensed under the MIT License (MIT).
+ *
+ * Copyright (c) 2024 easybill GmbH
+ *
+ * Permission is hereby granted, free of charge, to any person obtaining a copy
+ * of this software and associated documentation files (the "Software"), to deal
+ * in the Software without restriction, including without limitation the rights
+ * to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+ * copies of the Software, and to permit persons to whom the Software is
+ * furnished to do so, subject to the following conditions:
+ *
+ * The above copyright notice and this permission notice shall be included in all
+ * copies or substantial portions of the Software.
+ *
+ * THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+ * IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+ * FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+ * AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+ * LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+ * OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+ * SOFTWARE.
+ */
+
+use std::fmt::{Display, Formatter};
+
+use serde::{Deserialize, Serialize};
+use tonic::Request;
+
+use crate::service::auth_interceptor::AuthenticatedPrincipal;
+
+/// The metadata key in which clients send the name of the operating system user that invoked them.
+const USER_METADATA_KEY: &str = "easydep-user";
+/// The metadata key in which clients send the configured operator name.
+const OPERATOR_METADATA_KEY: &str = "easydep-operator";
+
+/// The identity of the user that triggered an action, as reported by the client.
+#[derive(Serialize, Deserialize, Clone, Debug, Default)]
+pub(crate) struct RequestIdentity {
+    /// The subject that was authenticated for the request, if authentication is enabled.
+    pub subject: Option<String>,
+    /// The name of the operating system user that invoked the client.
+    pub user: Option<String>,
+    /// The operator name that is configured in the client.
+    pub operator: Option<String>,
+}
+
+impl RequestIdentity {
+    /// Reads the identity of the requesting user from the given request. Metadata values that are missing or cannot
+    /// be read as a string are ignored.
+    ///
+    /// # Arguments
+    /// * `request` - The request to read the identity from.
+    pub fn from_request<T>(request: &Request<T>) -> Self {
+        let metadata = request.metadata();
+        let subject = request
+            .extensions()
+            .get::<AuthenticatedPrincipal>()
+            .and_then(|principal| principal.subject.clone());
+        let read_value = |key: &str| {
+            metadata
+                .get(key)
+                .and_then(|value| value.to_str().ok())
+                .map(|value| value.trim().to_string())
+                .filter(|value| !value.is_empty())
+        };
+        Self {
+            subject,
+            user: read_value(USER_METADATA_KEY),
+            operator: read_value(OPERATOR_METADATA_KEY),
+        }
+    }
+
+    /// Constructs the identity for actions that are triggered by the server itself rather than by a client.
+    ///
+    /// # Arguments
+    /// * `name` - The name of the server component that triggers the action.
+    pub fn internal(name: &str) -> Self {
+        Self {
+            operator: Some(name.to_string()),
+            ..Default::default()
+        }
+    }
+}
+
+impl Display for RequestIdentity {
+    fn fmt(&self, formatter: &mut Formatter<'_>) -> std::fmt::Result {
+        // the authenticated subject is the only trustworthy information, the rest is reported by the client
+        if let Some(subject) = &self.subject {
+            write!(formatter, "{} via ", subject)?;
+        }
+        match (&self.operator, &self.user) {
+            (Some(operator), Some(user)) => write!(formatter, "{} ({})", operator, user),
+            (Some(operator), None) => write!(formatter, "{}", operator),
+            (None, Some(user)) => write!(formatter, "{}", user),
+            (None, None) => write!(formatter, "unknown"),
+        }
+    }
+}
@@ -0,0 +1,125 @@
+/*
+ * This file is part of easydep, licensed under the MIT License (MIT).
+ *
+ * Copyright (c) 2024 easybill GmbH
+ *
+ * Permission is hereby granted, free of charge, to any person obtaining a copy
+ * of this software and associated documentation files (the "Software"), to deal
+ * in the Software without restriction, including without limitation the rights
+ * to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+ * copies of the Software, and to permit persons to whom the Software is
+ * furnished to do so, subject to the following conditions:
+ *
+ * The above copyright notice and this permission notice shall be included in all
+ * copies or substantial portions of the Software.
+ *
+ * THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+ * IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+ * FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+ * AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+ * LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+ * OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+ * SOFTWARE.
+ */
+
+use std::sync::Arc;
+use std::time::Duration;
+
+use log::info;
+use tonic::{Request, Response, Status};
+
+use crate::accessor::configuration_accessor::ConfigurationAccessor;
+use crate::config::AccessRole;
+use crate::easydep::simulation_service_server::SimulationService;
+use crate::easydep::{SimulateConcurrencyRequest, SimulateConcurrencyResponse};
+use crate::executor::concurrency_simulation::{ConcurrencySimulation, SimulationOptions};
+use crate::service::auth_interceptor::require_role;
+use crate::service::request_identity::RequestIdentity;
+
+/// The maximum amount of clients that can be simulated at once.
+const MAX_SIMULATED_CLIENTS: u32 = 1_000;
+/// The maximum amount of requests that each simulated client can send.
+const MAX_REQUESTS_PER_CLIENT: u32 = 10_000;
+/// The maximum time (in milliseconds) that each simulated step of an action can take.
+const MAX_STEP_DELAY_MILLIS: u32 = 1_000;
+
+pub struct SimulationServiceImpl {
+    configuration_accessor: ConfigurationAccessor,
+}
+
+impl SimulationServiceImpl {
+    pub fn new(configuration_accessor: ConfigurationAccessor) -> Self {
+        Self {
+            configuration_accessor,
+        }
+    }
+}
+
+#[tonic::async_trait]
+impl SimulationService for SimulationServiceImpl {
+    async fn simulate_concurrency(
+        &self,
+        request: Request<SimulateConcurrencyRequest>,
+    ) -> Result<Response<SimulateConcurrencyResponse>, Status> {
+        require_role(&request, AccessRole::Admin)?;
+        let request_message = request.get_ref();
+        let request_identity = RequestIdentity::from_request(&request);
+        if !(1..=MAX_SIMULATED_CLIENTS).contains(&request_message.clients) {
+            return Err(Status::invalid_argument(format!(
+                "clients must be between 1 and {MAX_SIMULATED_CLIENTS}"
+            )));
+        }
+        if request_message.requests_per_client > MAX_REQUESTS_PER_CLIENT {
+            return Err(Status::invalid_argument(format!(
+                "requests_per_client must not exceed {MAX_REQUESTS_PER_CLIENT}"
+            )));
+        }
+        if request_message.max_step_delay_millis > MAX_STEP_DELAY_MILLIS {
+            return Err(Status::invalid_argument(format!(
+                "max_step_delay_millis must not exceed {MAX_STEP_DELAY_MILLIS}"
+            )));
+        }
+
+        let global_configuration = self.configuration_accessor.get_configuration().await;
+        let Some(deploy_config) =
+            global_configuration.get_deployment_configuration(&request_message.profile)
+        else {
+            return Err(Status::failed_precondition(
+                "requested deployment config is not registered",
+            ));
+        };
+        info!(
+            "Received request from {} to simulate {} concurrent clients with {} requests each in profile {}",
+            request_identity,
+            request_message.clients,
+            request_message.requests_per_client,
+            deploy_config.id
+        );
+
+        let options = SimulationOptions {
+            clients: request_message.clients,
+            requests_per_client: request_message.requests_per_client,
+            seed: request_message.seed,
+            max_step_delay: Duration::from_millis(request_message.max_step_delay_millis.into()),
+            use_action_queue: request_message.use_action_queue,
+        };
+        let simulation = Arc::new(ConcurrencySimulation::new(
+            global_configuration.as_ref().clone(),
+            deploy_config,
+            options,
+        ));
+        let report = simulation
+            .run()
+            .await
+            .map_err(|err| Status::internal(format!("unable to complete the simulation: {err}")))?;
+        info!(
+            "Concurrency simulation completed with {} violation(s)",
+            report.violations.len()
+        );
+        Ok(Response::new(SimulateConcurrencyResponse {
+            accepted_requests: report.accepted_requests,
+            rejected_requests: report.rejected_requests,
+            violations: report.violations,
+        }))
+    }
+}
@@ -22,62 +22,445 @@
  * SOFTWARE.
  */
 
+use std::time::{Instant, UNIX_EPOCH};
+
+use easydep_buildinfo::{BUILD_TIMESTAMP, RUSTC_VERSION};
+use log::info;
 use tonic::{Request, Response, Status};
 
+use crate::accessor::configuration_accessor::ConfigurationAccessor;
 use crate::accessor::deploy_action_accessor::{CurrentAction, DeploymentStatusAccessor};
+use crate::accessor::deploy_status_accessor::{DeployExecutionState, DeployStateTransition};
+use crate::accessor::github_accessor::{describe_github_error, GitHubAccessor};
+use crate::accessor::server_restart_accessor::ServerRestartAccessor;
+use crate::config::{hash_configuration_content, AccessRole, Configuration};
 use crate::easydep::status_service_server::StatusService;
-use crate::easydep::{DeployCurrentAction, StatusRequest, StatusResponse};
+use crate::easydep::{
+    DeployCurrentAction, DeploymentState, DeploymentStateTransition, GitHubCheckRequest,
+    GitHubCheckResponse, GitHubProfileCheck, QueuedDeployment, ServerConfigurationPushRequest,
+    ServerConfigurationPushResponse, ServerConfigurationReloadRequest,
+    ServerConfigurationReloadResponse, ServerConfigurationRequest, ServerConfigurationResponse,
+    ServerUpgradeRequest, ServerUpgradeResponse, StatusRequest, StatusResponse,
+};
+use crate::executor::self_update_executor::install_server_release;
+use crate::service::auth_interceptor::require_role;
+use crate::service::request_identity::RequestIdentity;
+
+/// The optional features that are supported by this server version, reported to the clients so that they can refuse
+/// commands which the server does not support. A feature must never be removed from the list once it was released.
+const SERVER_CAPABILITIES: [&str; 12] = [
+    "adopt",
+    "approve",
+    "cancel",
+    "dry-run",
+    "exec",
+    "history",
+    "list-releases",
+    "mark-bad",
+    "plan",
+    "prune",
+    "queue",
+    "ref-deploy",
+];
 
 pub struct StatusServiceImpl {
     version: String,
-    deploy_configs: Vec<String>,
+    configuration_accessor: ConfigurationAccessor,
+    started_at: Instant,
     deploy_status_accessor: DeploymentStatusAccessor,
+    server_restart_accessor: ServerRestartAccessor,
+    github_accessor: GitHubAccessor,
 }
 
 impl StatusServiceImpl {
     pub fn new(
         version: String,
-        deploy_configs: Vec<String>,
+        configuration_accessor: ConfigurationAccessor,
         deploy_status_accessor: DeploymentStatusAccessor,
+        server_restart_accessor: ServerRestartAccessor,
+        github_accessor: GitHubAccessor,
     ) -> Self {
         Self {
             version,
-            deploy_configs,
+            configuration_accessor,
+            started_at: Instant::now(),
             deploy_status_accessor,
+            server_restart_accessor,
+            github_accessor,
+        }
+    }
+
+    /// Ensures that no action is currently being executed, returning an error status otherwise.
+    async fn ensure_idle(&self) -> Result<(), Status> {
+        match self.deploy_status_accessor.get_action().await {
+            CurrentAction::Idle => Ok(()),
+            _ => Err(Status::failed_precondition(
+                "cannot restart the server while an action is being executed",
+            )),
         }
     }
+
+    /// Get the deployments that wait in the action queues of the profiles, ordered by profile and position.
+    fn get_queued_deployments(&self) -> Vec<QueuedDeployment> {
+        self.deploy_status_accessor
+            .get_queued_actions()
+            .into_iter()
+            .flat_map(|(profile, queued_actions)| {
+                queued_actions
+                    .into_iter()
+                    .enumerate()
+                    .map(move |(index, queued_action)| QueuedDeployment {
+                        profile: profile.clone(),
+                        position: index as u32 + 1,
+                        release_id: queued_action.release_id,
+                        release_tag: queued_action.release_tag,
+                        git_ref: queued_action.git_ref,
+                        annotation: queued_action.annotation,
+                        triggered_by: queued_action.triggered_by.to_string(),
+                        queued_at: queued_action
+                            .queued_at
+                            .duration_since(UNIX_EPOCH)
+                            .unwrap_or_default()
+                            .as_secs(),
+                    })
+            })
+            .collect()
+    }
 }
 
 #[tonic::async_trait]
 impl StatusService for StatusServiceImpl {
     async fn get_status(
         &self,
-        _request: Request<StatusRequest>,
+        request: Request<StatusRequest>,
     ) -> Result<Response<StatusResponse>, Status> {
-        let (current_action, current_release_id, current_release_tag) =
-            match self.deploy_status_accessor.get_action().await {
-                CurrentAction::Idle => (DeployCurrentAction::Idle, None, None),
-                CurrentAction::Executing(executor) => {
-                    let current_release = executor.get_release();
-                    (
-                        DeployCurrentAction::Deploying,
-                        Some(current_release.id.0),
-                        Some(current_release.tag_name.clone()),
-                    )
-                }
-                CurrentAction::RollingBack(current_release) => (
-                    DeployCurrentAction::RollingBack,
+        require_role(&request, AccessRole::Viewer)?;
+        let (
+            current_action,
+            current_release_id,
+            current_release_tag,
+            current_annotation,
+            triggered_by,
+            prepared_expires_in_seconds,
+            git_ref,
+            state_transitions,
+        ) = match self.deploy_status_accessor.get_action().await {
+            CurrentAction::Idle => (
+                DeployCurrentAction::Idle,
+                None,
+                None,
+                None,
+                None,
+                None,
+                None,
+                Vec::new(),
+            ),
+            CurrentAction::Executing(executor) => {
+                let current_release = executor.get_release();
+                (
+                    DeployCurrentAction::Deploying,
                     Some(current_release.id.0),
                     Some(current_release.tag_name.clone()),
-                ),
-            };
+                    executor.get_annotation().cloned(),
+                    Some(executor.get_triggered_by().to_string()),
+                    executor
+                        .get_prepared_time_remaining()
+                        .await
+                        .map(|time_remaining| time_remaining.as_secs()),
+                    executor.get_git_ref().cloned(),
+                    executor
+                        .get_status_accessor()
+                        .get_transitions()
+                        .await
+                        .into_iter()
+                        .map(convert_state_transition)
+                        .collect(),
+                )
+            }
+            CurrentAction::RollingBack(current_release, _, _) => (
+                DeployCurrentAction::RollingBack,
+                Some(current_release.id.0),
+                Some(current_release.tag_name.clone()),
+                None,
+                None,
+                None,
+                None,
+                Vec::new(),
+            ),
+            CurrentAction::RerunningScripts(current_release, _, triggered_by) => (
+                DeployCurrentAction::RerunningScripts,
+                Some(current_release.id.0),
+                Some(current_release.tag_name.clone()),
+                None,
+                Some(triggered_by.to_string()),
+                None,
+                None,
+                Vec::new(),
+            ),
+        };
+        let configuration = self.configuration_accessor.get_configuration().await;
+        let configuration_source = self.configuration_accessor.get_configuration_source().await;
         let response = StatusResponse {
             version: self.version.clone(),
             current_action: i32::from(current_action),
             release_id: current_release_id,
             release_tag: current_release_tag,
-            deployment_configurations: self.deploy_configs.clone(),
+            deployment_configurations: configuration.get_deployment_configuration_ids(),
+            annotation: current_annotation,
+            triggered_by,
+            prepared_expires_in_seconds,
+            git_ref,
+            uptime_seconds: self.started_at.elapsed().as_secs(),
+            config_path: configuration_source.path,
+            config_loaded_at: configuration_source
+                .loaded_at
+                .duration_since(UNIX_EPOCH)
+                .unwrap_or_default()
+                .as_secs(),
+            config_hash: configuration_source.content_hash,
+            build_timestamp: BUILD_TIMESTAMP.to_string(),
+            rustc_version: RUSTC_VERSION.to_string(),
+            state_transitions,
+            capabilities: get_server_capabilities(),
+            queued_deployments: self.get_queued_deployments(),
         };
         Ok(Response::new(response))
     }
+
+    async fn get_server_configuration(
+        &self,
+        request: Request<ServerConfigurationRequest>,
+    ) -> Result<Response<ServerConfigurationResponse>, Status> {
+        require_role(&request, AccessRole::Admin)?;
+
+        // serialize through a toml value which orders all keys, to allow comparing the configuration of servers
+        let redacted_configuration = self
+            .configuration_accessor
+            .get_configuration()
+            .await
+            .to_redacted();
+        let configuration = toml::Value::try_from(&redacted_configuration)
+            .and_then(|configuration_value| toml::to_string_pretty(&configuration_value))
+            .map_err(|err| {
+                let error_message = format!("unable to serialize configuration: {}", err);
+                Status::internal(error_message)
+            })?;
+        Ok(Response::new(ServerConfigurationResponse { configuration }))
+    }
+
+    async fn push_server_configuration(
+        &self,
+        request: Request<ServerConfigurationPushRequest>,
+    ) -> Result<Response<ServerConfigurationPushResponse>, Status> {
+        require_role(&request, AccessRole::Admin)?;
+        let request_identity = RequestIdentity::from_request(&request);
+        info!(
+            "Received request from {} to install a new configuration",
+            request_identity
+        );
+
+        // the server restarts to apply the configuration, which would abort the action that is currently executed
+        self.ensure_idle().await?;
+
+        // validate the configuration before installing it, the current configuration is kept if the server still
+        // fails to start with the installed configuration
+        let configuration_content = &request.get_ref().configuration;
+        let configuration = Configuration::parse(configuration_content).map_err(|err| {
+            let error_message = format!("unable to parse configuration: {}", err);
+            Status::invalid_argument(error_message)
+        })?;
+        if let Err(err) = configuration.validate().await {
+            let error_message = format!("invalid configuration: {}", err);
+            return Err(Status::invalid_argument(error_message));
+        }
+        let configuration_path = self
+            .configuration_accessor
+            .get_configuration_source()
+            .await
+            .path;
+        if let Err(err) =
+            Configuration::install_file(&configuration_path, configuration_content).await
+        {
+            let error_message = format!("unable to install configuration: {}", err);
+            return Err(Status::internal(error_message));
+        }
+
+        info!("Installed new configuration, restarting to apply it");
+        self.server_restart_accessor.request_restart();
+        Ok(Response::new(ServerConfigurationPushResponse {
+            config_hash: hash_configuration_content(configuration_content),
+        }))
+    }
+
+    async fn reload_server_configuration(
+        &self,
+        request: Request<ServerConfigurationReloadRequest>,
+    ) -> Result<Response<ServerConfigurationReloadResponse>, Status> {
+        require_role(&request, AccessRole::Admin)?;
+        let request_identity = RequestIdentity::from_request(&request);
+        info!(
+            "Received request from {} to reload the configuration",
+            request_identity
+        );
+
+        // actions that are running keep the configuration they were started with, no need to wait for them
+        let configuration_source = match self.configuration_accessor.reload().await {
+            Ok(configuration_source) => configuration_source,
+            Err(err) => {
+                let error_message = format!("unable to reload configuration: {err:#}");
+                return Err(Status::failed_precondition(error_message));
+            }
+        };
+        info!(
+            "Reloaded configuration (sha256: {})",
+            configuration_source.content_hash
+        );
+        let deployment_configurations = self
+            .configuration_accessor
+            .get_configuration()
+            .await
+            .get_deployment_configuration_ids();
+        Ok(Response::new(ServerConfigurationReloadResponse {
+            config_hash: configuration_source.content_hash,
+            deployment_configurations,
+        }))
+    }
+
+    async fn upgrade_server(
+        &self,
+        request: Request<ServerUpgradeRequest>,
+    ) -> Result<Response<ServerUpgradeResponse>, Status> {
+        require_role(&request, AccessRole::Admin)?;
+        let request_identity = RequestIdentity::from_request(&request);
+        let requested_release_tag = request.get_ref().release_tag.as_deref();
+        info!(
+            "Received request from {} to upgrade to easydep {}",
+            request_identity,
+            requested_release_tag.unwrap_or("latest")
+        );
+
+        let configuration = self.configuration_accessor.get_configuration().await;
+        let self_update_config = match &configuration.self_update {
+            Some(self_update_config) => self_update_config,
+            None => {
+                return Err(Status::failed_precondition(
+                    "self update is not configured on this server",
+                ))
+            }
+        };
+
+        // the server restarts to apply the upgrade, which would abort the action that is currently executed
+        self.ensure_idle().await?;
+        let release_tag =
+            match install_server_release(self_update_config, requested_release_tag).await {
+                Ok(release_tag) => release_tag,
+                Err(err) => {
+                    let error_message = format!("unable to install easydep release: {err:?}");
+                    return Err(Status::internal(error_message));
+                }
+            };
+
+        info!("Installed easydep {}, restarting to apply it", release_tag);
+        self.server_restart_accessor.request_restart();
+        Ok(Response::new(ServerUpgradeResponse { release_tag }))
+    }
+
+    async fn check_git_hub_access(
+        &self,
+        request: Request<GitHubCheckRequest>,
+    ) -> Result<Response<GitHubCheckResponse>, Status> {
+        require_role(&request, AccessRole::Admin)?;
+        let request_identity = RequestIdentity::from_request(&request);
+        info!(
+            "Received request from {} to check the GitHub access",
+            request_identity
+        );
+
+        // the repository access can only be checked if the app can authenticate
+        let (app_name, app_error) = match self.github_accessor.get_app_name().await {
+            Ok(app_name) => (Some(app_name), None),
+            Err(err) => (None, Some(describe_github_error(&err))),
+        };
+        let mut profile_checks = Vec::new();
+        if app_error.is_none() {
+            for deployment_config in self
+                .configuration_accessor
+                .get_configuration()
+                .await
+                .get_deployment_configurations()
+                .iter()
+                .filter(|config| !config.extend_only)
+            {
+                let access_report = self
+                    .github_accessor
+                    .check_repository_access(deployment_config)
+                    .await;
+                profile_checks.push(GitHubProfileCheck {
+                    profile: deployment_config.id.clone(),
+                    repository: format!(
+                        "{}/{}",
+                        deployment_config.source_repo_owner, deployment_config.source_repo_name
+                    ),
+                    error: access_report.error,
+                    installation_id: access_report.installation_id,
+                    contents_permission: access_report.contents_permission,
+                    rate_limit_remaining: access_report
+                        .rate_limit
+                        .as_ref()
+                        .map(|rate_limit| rate_limit.remaining as u64),
+                    rate_limit_total: access_report
+                        .rate_limit
+                        .as_ref()
+                        .map(|rate_limit| rate_limit.limit as u64),
+                    rate_limit_reset_at: access_report
+                        .rate_limit
+                        .as_ref()
+                        .map(|rate_limit| rate_limit.reset),
+                });
+            }
+        }
+        Ok(Response::new(GitHubCheckResponse {
+            app_name,
+            app_error,
+            profile_checks,
+        }))
+    }
+}
+
+/// Converts the given state transition of a deployment into the transition that is sent to the client.
+///
+/// # Arguments
+/// * `transition` - The state transition to convert.
+fn convert_state_transition(transition: DeployStateTransition) -> DeploymentStateTransition {
+    let state = match transition.state {
+        DeployExecutionState::Preparing => DeploymentState::Preparing,
+        DeployExecutionState::Prepared => DeploymentState::Prepared,
+        DeployExecutionState::Publishing => DeploymentState::Publishing,
+        DeployExecutionState::Published => DeploymentState::Published,
+        DeployExecutionState::Deleting => DeploymentState::Deleting,
+        DeployExecutionState::Deleted => DeploymentState::Deleted,
+        DeployExecutionState::Cancelling => DeploymentState::Cancelling,
+        DeployExecutionState::Cancelled => DeploymentState::Cancelled,
+        DeployExecutionState::Failed => DeploymentState::Failed,
+    };
+    let entered_at_millis = transition
+        .entered_at
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_millis() as u64;
+    DeploymentStateTransition {
+        state: i32::from(state),
+        entered_at_millis,
+    }
+}
+
+/// Get the optional features that are supported by this server, including the features enabled at compile time.
+fn get_server_capabilities() -> Vec<String> {
+    let simulation_capability =
+        cfg!(feature = "concurrency-simulation").then_some("concurrency-simulation");
+    SERVER_CAPABILITIES
+        .into_iter()
+        .chain(simulation_capability)
+        .map(|capability| capability.to_string())
+        .collect()
 }